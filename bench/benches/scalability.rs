@@ -0,0 +1,141 @@
+//! Measures how HIR lowering and each backend scale with the size of the API.
+//!
+//! Run with `cargo bench` from this directory. Besides criterion's timings, the peak
+//! heap usage of every stage is printed once per API size.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diplomat_core::{ast, hir};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of (opaque, struct, enum) type triples in each synthesized API
+const SIZES: &[usize] = &[10, 100, 1000];
+/// Methods generated per opaque type
+const METHODS_PER_TYPE: usize = 8;
+const BACKENDS: &[&str] = &["c2", "cpp2", "js", "dart", "koka"];
+
+/// Tracks the current and peak number of allocated bytes
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Runs `f` and reports the heap it needed on top of what was already allocated
+fn peak_heap<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let result = f();
+    (result, PEAK.load(Ordering::Relaxed) - base)
+}
+
+/// Synthesizes a bridge with `size` opaque types, structs and enums, where every opaque
+/// has methods taking and returning the other kinds of types.
+fn synthesize_bridge(size: usize) -> String {
+    let mut out = String::from("#[diplomat::bridge]\npub mod ffi {\n");
+    for i in 0..size {
+        writeln!(
+            out,
+            "    pub enum Kind{i} {{ A, B, C }}\n\n    \
+             pub struct Options{i} {{ pub kind: Kind{i}, pub count: u32, pub ratio: f64 }}\n\n    \
+             #[diplomat::opaque]\n    pub struct Thing{i}(u32);\n\n    impl Thing{i} {{\n        \
+             pub fn new(options: Options{i}) -> Box<Thing{i}> {{ Box::new(Thing{i}(options.count)) }}"
+        )
+        .unwrap();
+        for m in 0..METHODS_PER_TYPE {
+            let other = (i + m + 1) % size;
+            writeln!(
+                out,
+                "        pub fn method{m}(&self, name: &DiplomatStr, other: &Thing{other}) -> Result<Options{i}, Kind{other}> {{ todo!() }}\n        \
+                 pub fn maybe{m}(&self, values: &[u8]) -> Option<Box<Thing{other}>> {{ todo!() }}"
+            )
+            .unwrap();
+        }
+        out.push_str("    }\n\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn lower(file: &syn::File) -> hir::TypeContext {
+    let file = ast::File::from(file);
+    let env = file.all_types();
+    let attr_validator = hir::BasicAttributeValidator::new("bench");
+    hir::TypeContext::from_ast(&env, attr_validator)
+        .unwrap_or_else(|_| panic!("Synthesized bridge failed to lower"))
+}
+
+/// Writes the synthesized bridge to a scratch directory, next to an output folder per backend
+fn scratch_dir(size: usize, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("diplomat-bench-{size}"));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lib.rs"), source).unwrap();
+    for backend in BACKENDS {
+        std::fs::create_dir_all(dir.join(backend)).unwrap();
+    }
+    dir
+}
+
+fn generate(dir: &Path, backend: &str) {
+    diplomat_tool::gen(
+        &dir.join("lib.rs"),
+        backend,
+        &dir.join(backend),
+        diplomat_tool::GenOptions {
+            silent: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+}
+
+fn scalability(c: &mut Criterion) {
+    let mut lowering = c.benchmark_group("lowering");
+    for &size in SIZES {
+        let file = syn::parse_file(&synthesize_bridge(size)).unwrap();
+        let (_, heap) = peak_heap(|| lower(&file));
+        println!("lowering/{size}: peak heap {} KiB", heap / 1024);
+        lowering.bench_with_input(BenchmarkId::from_parameter(size), &file, |b, file| {
+            b.iter(|| lower(file))
+        });
+    }
+    lowering.finish();
+
+    for backend in BACKENDS {
+        let mut group = c.benchmark_group(format!("gen/{backend}"));
+        // The largest APIs take seconds per run, keep the sample count down
+        group.sample_size(10);
+        for &size in SIZES {
+            let dir = scratch_dir(size, &synthesize_bridge(size));
+            let (_, heap) = peak_heap(|| generate(&dir, backend));
+            println!("gen/{backend}/{size}: peak heap {} KiB", heap / 1024);
+            group.bench_with_input(BenchmarkId::from_parameter(size), &dir, |b, dir| {
+                b.iter(|| generate(dir, backend))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, scalability);
+criterion_main!(benches);
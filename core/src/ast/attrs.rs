@@ -6,7 +6,8 @@ use std::borrow::Cow;
 use std::convert::Infallible;
 use std::str::FromStr;
 use syn::parse::{Error as ParseError, Parse, ParseStream};
-use syn::{Attribute, Expr, Ident, Lit, LitStr, Meta, MetaList, Token};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, ExprLit, Ident, Lit, LitStr, Meta, MetaList, Token};
 
 /// The list of attributes on a type. All attributes except `attrs` (HIR attrs) are
 /// potentially read by the diplomat macro and the AST backends, anything that is not should
@@ -280,11 +281,40 @@ pub(crate) enum AttrInheritContext {
 /// This can be parsed from a string, typically something like `icu4x_{0}`.
 /// It can have up to one {0} for replacement.
 ///
+/// It can also limit the length of the symbols, for toolchains and linkers that cannot handle long
+/// ones, like `#[diplomat::abi_rename("icu4x_{0}", max_length = 31)]` or
+/// `#[diplomat::abi_rename(max_length = 31)]`. Longer symbols are shortened with [`shorten_symbol()`].
+///
 /// In the future this may support transformations like to_camel_case, etc,
 /// probably specified as a list like `#[diplomat::abi_rename("foo{0}", to_camel_case)]`
 #[derive(Default, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize)]
 pub struct RenameAttr {
     pattern: Option<RenamePattern>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_length: Option<usize>,
+}
+
+/// The suffixes appended to the symbol of an async method for the functions polling and
+/// destroying its future
+pub const ASYNC_SYMBOL_SUFFIXES: [&str; 2] = ["_poll", "_destroy_future"];
+
+/// The length of the suffix [`shorten_symbol()`] appends, an underscore and eight hex digits
+pub const SHORTENED_SUFFIX_LEN: usize = 9;
+
+/// Shortens `symbol` to `max_length` bytes by replacing its end with a hash of the whole symbol.
+///
+/// The hash is stable across platforms and releases, so the macro and every backend arrive at the
+/// same symbol. `max_length` has to be at least [`SHORTENED_SUFFIX_LEN`].
+pub fn shorten_symbol(symbol: &str, max_length: usize) -> String {
+    // 32-bit FNV-1a
+    let hash = symbol.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    });
+    let mut prefix = max_length - SHORTENED_SUFFIX_LEN;
+    while !symbol.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    format!("{}_{hash:08x}", &symbol[..prefix])
 }
 
 impl RenameAttr {
@@ -302,15 +332,36 @@ impl RenameAttr {
         }
     }
 
+    /// The maximum length of the symbols, if limited
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    /// Shortens a symbol that is longer than the maximum length, leaving room for `suffix_len` more
+    /// bytes appended to it afterwards (like the [`ASYNC_SYMBOL_SUFFIXES`]).
+    pub fn shorten<'a>(&self, symbol: Cow<'a, str>, suffix_len: usize) -> Cow<'a, str> {
+        match self.max_length {
+            Some(max_length) if symbol.len() + suffix_len > max_length => {
+                // Too short limits are reported when checking the symbols
+                let budget = max_length.saturating_sub(suffix_len);
+                shorten_symbol(&symbol, budget.max(SHORTENED_SUFFIX_LEN)).into()
+            }
+            _ => symbol,
+        }
+    }
+
     /// Whether this rename is empty and will perform no changes
     fn is_empty(&self) -> bool {
-        self.pattern.is_none()
+        self.pattern.is_none() && self.max_length.is_none()
     }
 
     pub(crate) fn extend(&mut self, other: &Self) {
         if other.pattern.is_some() {
             self.pattern = other.pattern.clone()
         }
+        if other.max_length.is_some() {
+            self.max_length = other.max_length;
+        }
 
         // In the future if we support things like to_lower_case they may inherit separately
         // from patterns.
@@ -330,16 +381,63 @@ impl RenameAttr {
             AttrInheritContext::Variant => Default::default(),
             _ => self.pattern.clone(),
         };
+        // The length limit applies to every symbol below
+        let max_length = match context {
+            AttrInheritContext::Variant => None,
+            _ => self.max_length,
+        };
         // In the future if we support things like to_lower_case they may inherit separately
         // from patterns.
-        Self { pattern }
+        Self {
+            pattern,
+            max_length,
+        }
     }
 
     /// From a replacement pattern, like "icu4x_{0}". Can have up to one {0} in it for substitution.
     fn from_pattern(s: &str) -> Self {
         Self {
             pattern: Some(s.parse().unwrap()),
+            max_length: None,
+        }
+    }
+
+    /// From the list form, a pattern and/or `max_length = N`
+    fn from_list(list: &MetaList) -> Result<Self, &'static str> {
+        const MALFORMED: &str =
+            "Failed to parse malformed #[diplomat::abi_rename(...)]: expected a pattern and/or `max_length = N`";
+        let args = list
+            .parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+            .map_err(|_| MALFORMED)?;
+        let mut this = Self::default();
+        for arg in args {
+            match arg {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(ref lit),
+                    ..
+                }) if this.pattern.is_none() => this.pattern = Some(lit.value().parse().unwrap()),
+                Expr::Assign(ref assign) if matches!(*assign.left, Expr::Path(ref path) if path.path.is_ident("max_length")) =>
+                {
+                    let Expr::Lit(ExprLit {
+                        lit: Lit::Int(ref lit),
+                        ..
+                    }) = *assign.right
+                    else {
+                        return Err(MALFORMED);
+                    };
+                    let max_length = lit.base10_parse::<usize>().map_err(|_| MALFORMED)?;
+                    if max_length <= SHORTENED_SUFFIX_LEN {
+                        return Err("#[diplomat::abi_rename(max_length = N)] must leave room for the hash suffix of shortened symbols");
+                    }
+                    this.max_length = Some(max_length);
+                }
+                _ => return Err(MALFORMED),
+            }
+        }
+        if this.is_empty() {
+            return Err(MALFORMED);
         }
+        Ok(this)
     }
 
     pub(crate) fn from_meta(meta: &Meta) -> Result<Self, &'static str> {
@@ -348,9 +446,7 @@ impl RenameAttr {
 
         match attr {
             StandardAttribute::String(s) => Ok(RenameAttr::from_pattern(&s)),
-            StandardAttribute::List(_) => {
-                Err("Failed to parse malformed #[diplomat::abi_rename(...)]: found list")
-            }
+            StandardAttribute::List(list) => RenameAttr::from_list(list),
             StandardAttribute::Empty => {
                 Err("Failed to parse malformed #[diplomat::abi_rename(...)]: found no parameters")
             }
@@ -395,7 +491,7 @@ struct RenamePattern {
 /// and then attributes can choose to handle more complicated lists if they so desire.
 pub(crate) enum StandardAttribute<'a> {
     String(String),
-    List(#[allow(dead_code)] &'a MetaList),
+    List(&'a MetaList),
     Empty,
 }
 
@@ -432,7 +528,7 @@ mod tests {
 
     use syn;
 
-    use super::{DiplomatBackendAttr, DiplomatBackendAttrCfg, RenameAttr};
+    use super::{shorten_symbol, DiplomatBackendAttr, DiplomatBackendAttrCfg, RenameAttr};
 
     #[test]
     fn test_cfgs() {
@@ -465,5 +561,25 @@ mod tests {
         let attr: syn::Attribute = syn::parse_quote!(#[diplomat::abi_rename("foobar_{0}")]);
         let attr = RenameAttr::from_meta(&attr.meta).unwrap();
         insta::assert_yaml_snapshot!(attr);
+        let attr: syn::Attribute =
+            syn::parse_quote!(#[diplomat::abi_rename("foobar_{0}", max_length = 31)]);
+        let attr = RenameAttr::from_meta(&attr.meta).unwrap();
+        assert_eq!(attr.max_length(), Some(31));
+        assert_eq!(attr.apply("baz".into()), "foobar_baz");
+        let attr: syn::Attribute = syn::parse_quote!(#[diplomat::abi_rename(max_length = 8)]);
+        assert!(RenameAttr::from_meta(&attr.meta).is_err());
+    }
+
+    #[test]
+    fn test_shorten_symbol() {
+        // The hash is part of the ABI, it must not change between releases
+        assert_eq!(
+            shorten_symbol("ICU4XFixedDecimalFormatter_format_write", 24),
+            "ICU4XFixedDecim_a5ae4371"
+        );
+        let attr: syn::Attribute = syn::parse_quote!(#[diplomat::abi_rename(max_length = 24)]);
+        let attr = RenameAttr::from_meta(&attr.meta).unwrap();
+        assert_eq!(attr.shorten("Foo_bar".into(), 0), "Foo_bar");
+        assert_eq!(attr.shorten("Foo_bar_baz".into(), 15).len(), 9);
     }
 }
@@ -27,6 +27,11 @@ impl Docs {
         Self(Self::get_doc_lines(attrs), Self::get_rust_link(attrs))
     }
 
+    /// Docs without Rust links, for items Diplomat declares itself
+    pub(crate) fn from_text(text: String) -> Self {
+        Self(text, Vec::new())
+    }
+
     fn get_doc_lines(attrs: &[Attribute]) -> String {
         let mut lines: String = String::new();
 
@@ -115,6 +120,96 @@ impl Docs {
     pub fn rust_links(&self) -> &[RustLink] {
         &self.1
     }
+
+    /// The targets of the intra-doc links in the docs, such as `Foo::bar` for ``[`Foo::bar`]``
+    pub fn references(&self) -> impl Iterator<Item = &str> + '_ {
+        IntraDocLinks(&self.0, 0).map(|link| link.target)
+    }
+
+    /// Rewrites the intra-doc links whose target is matched by `dangling` to plain text,
+    /// keeping the code span of ``[`Foo::bar`]``
+    pub fn unlink_references(&mut self, mut dangling: impl FnMut(&str) -> bool) {
+        self.rewrite_references(|target, text| dangling(target).then(|| text.to_string()))
+    }
+
+    /// Replaces each intra-doc link with what `rewrite` returns for its target and the
+    /// text between its brackets, leaving the links it returns `None` for untouched.
+    ///
+    /// Backends use this to turn ``[`Foo::bar`]`` into their own cross-reference syntax.
+    pub fn rewrite_references(&mut self, mut rewrite: impl FnMut(&str, &str) -> Option<String>) {
+        let mut rewritten = String::with_capacity(self.0.len());
+        let mut end = 0;
+        for link in IntraDocLinks(&self.0, 0) {
+            if let Some(replacement) = rewrite(link.target, link.text) {
+                rewritten.push_str(&self.0[end..link.start]);
+                rewritten.push_str(&replacement);
+                end = link.end;
+            }
+        }
+        if end != 0 {
+            rewritten.push_str(&self.0[end..]);
+            self.0 = rewritten;
+        }
+    }
+}
+
+/// An intra-doc link written with the shorthand syntax, ``[`Foo::bar`]`` or `[Foo::bar]`
+struct IntraDocLink<'a> {
+    start: usize,
+    end: usize,
+    /// The text between the brackets
+    text: &'a str,
+    /// The path the link points at
+    target: &'a str,
+}
+
+/// Iterates over the intra-doc links of a docs string, from a byte offset.
+///
+/// Links with an explicit destination, `[text](target)` or `[text][target]`, and link
+/// definitions are skipped, as well as brackets that do not contain a path.
+struct IntraDocLinks<'a>(&'a str, usize);
+
+impl<'a> Iterator for IntraDocLinks<'a> {
+    type Item = IntraDocLink<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.1 + self.0[self.1..].find('[')?;
+            let len = self.0[start + 1..].find(['[', ']']);
+            let Some(len) = len.filter(|len| self.0.as_bytes()[start + 1 + len] == b']') else {
+                self.1 = start + 1;
+                continue;
+            };
+            let end = start + len + 2;
+            self.1 = end;
+            match self.0[end..].chars().next() {
+                // The label of a reference link is not a link itself
+                Some('[') => {
+                    self.1 = self.0[end..].find(']').map_or(end, |i| end + i + 1);
+                    continue;
+                }
+                Some('(' | ':') => continue,
+                _ => {}
+            }
+            let text = &self.0[start + 1..end - 1];
+            let target = text
+                .strip_prefix('`')
+                .and_then(|t| t.strip_suffix('`'))
+                .unwrap_or(text);
+            let is_path = target.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && target
+                    .split("::")
+                    .all(|s| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_'));
+            if is_path {
+                return Some(IntraDocLink {
+                    start,
+                    end,
+                    text,
+                    target,
+                });
+            }
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
@@ -354,6 +449,35 @@ impl DocsUrlGenerator {
     }
 }
 
+#[test]
+fn test_references() {
+    let mut docs = Docs(
+        "Wraps a [`Foo`], see [Foo::bar] and [`Baz::new`].\n\n\
+         Not [a link](Foo), [`Foo`][Foo], [1] or [`x + y`].\n\n[1]: https://example.com"
+            .into(),
+        Vec::new(),
+    );
+    assert_eq!(
+        docs.references().collect::<Vec<_>>(),
+        ["Foo", "Foo::bar", "Baz::new"]
+    );
+
+    let mut rewritten = docs.clone();
+    rewritten.rewrite_references(|target, _| Some(format!("`{}`", target.to_lowercase())));
+    assert_eq!(
+        rewritten.0,
+        "Wraps a `foo`, see `foo::bar` and `baz::new`.\n\n\
+         Not [a link](Foo), [`Foo`][Foo], [1] or [`x + y`].\n\n[1]: https://example.com"
+    );
+
+    docs.unlink_references(|target| target.starts_with("Foo"));
+    assert_eq!(
+        docs.0,
+        "Wraps a `Foo`, see Foo::bar and [`Baz::new`].\n\n\
+         Not [a link](Foo), [`Foo`][Foo], [1] or [`x + y`].\n\n[1]: https://example.com"
+    );
+}
+
 #[test]
 fn test_docs_url_generator() {
     let test_cases = [
@@ -1,7 +1,7 @@
 use serde::Serialize;
 
 use super::docs::Docs;
-use super::{AttrInheritContext, Attrs, Ident, Method};
+use super::{AttrInheritContext, Attrs, Ident, Method, SourceSpan};
 use quote::ToTokens;
 
 /// A fieldless enum declaration in an FFI module.
@@ -14,6 +14,11 @@ pub struct Enum {
     pub variants: Vec<(Ident, isize, Docs, Attrs)>,
     pub methods: Vec<Method>,
     pub attrs: Attrs,
+    /// Whether the enum is `#[non_exhaustive]`, i.e. may gain variants in the future.
+    pub is_non_exhaustive: bool,
+    /// Where the enum is declared, for diagnostics.
+    #[serde(skip)]
+    pub span: Option<SourceSpan>,
 }
 
 impl Enum {
@@ -67,6 +72,11 @@ impl Enum {
                 .collect(),
             methods: vec![],
             attrs,
+            is_non_exhaustive: enm
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("non_exhaustive")),
+            span: SourceSpan::from_syn(enm.ident.span()),
         }
     }
 }
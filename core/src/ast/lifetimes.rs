@@ -123,6 +123,17 @@ impl LifetimeEnv {
         this
     }
 
+    /// Returns a [`LifetimeEnv`] for an associated constant, accounting for lifetimes and
+    /// bounds defined in the impl block as well as implicit lifetime bounds in its type.
+    pub fn from_const_item(impl_generics: Option<&syn::Generics>, ty: &TypeName) -> Self {
+        let mut this = LifetimeEnv::new();
+        if let Some(generics) = impl_generics {
+            this.extend_generics(generics);
+        }
+        this.extend_implicit_lifetime_bounds(ty, None);
+        this
+    }
+
     /// Returns a [`LifetimeEnv`] for a struct, accounding for lifetimes and bounds
     /// defined in the struct generics, as well as implicit lifetime bounds in
     /// the struct's fields. For example, the field `&'a Foo<'b>` implies `'b: 'a`.
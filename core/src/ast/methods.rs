@@ -2,7 +2,10 @@ use serde::Serialize;
 use std::ops::ControlFlow;
 
 use super::docs::Docs;
-use super::{Attrs, Ident, Lifetime, LifetimeEnv, Mutability, PathType, TypeName};
+use super::{
+    attrs, Attrs, Ident, Lifetime, LifetimeEnv, Mutability, Path, PathType, RequiredEncoding,
+    SourceSpan, TypeName,
+};
 
 /// A method declared in the `impl` associated with an FFI struct.
 /// Includes both static and non-static methods, which can be distinguished
@@ -28,6 +31,20 @@ pub struct Method {
     /// The return type of the method, if any.
     pub return_type: Option<TypeName>,
 
+    /// Whether the method is an `async fn`, in which case `return_type` is the output of the future.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_async: bool,
+
+    /// The parameters grouped into a struct by `#[diplomat::bundle]`, in which case `params`
+    /// takes that struct in their place.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<ParamBundle>,
+
+    /// The value of an associated constant, which is exposed as a method taking no
+    /// parameters and returning it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constant: Option<ConstValue>,
+
     /// The lifetimes introduced in this method and surrounding impl block.
     pub lifetime_env: LifetimeEnv,
 
@@ -36,6 +53,59 @@ pub struct Method {
     /// These are strings instead of `syn::Attribute` or `proc_macro2::TokenStream`
     /// because those types are not `PartialEq`, `Hash`, `Serialize`, etc.
     pub attrs: Attrs,
+
+    /// Where the method is declared, for diagnostics.
+    #[serde(skip)]
+    pub span: Option<SourceSpan>,
+}
+
+/// The value of an associated constant.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Debug)]
+#[non_exhaustive]
+pub enum ConstValue {
+    /// An integer, float or boolean literal, possibly negated, with suffixes and
+    /// underscores removed and integers in base 10
+    Literal(String),
+    /// Any other expression, only known once Rust evaluates it
+    Computed,
+}
+
+impl ConstValue {
+    fn from_syn(expr: &syn::Expr) -> ConstValue {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+                syn::Lit::Int(int) => ConstValue::Literal(int.base10_digits().into()),
+                syn::Lit::Float(float) => ConstValue::Literal(float.base10_digits().into()),
+                syn::Lit::Bool(b) => ConstValue::Literal(b.value.to_string()),
+                _ => ConstValue::Computed,
+            },
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            }) => match ConstValue::from_syn(expr) {
+                ConstValue::Literal(lit) if lit != "true" && lit != "false" => {
+                    ConstValue::Literal(format!("-{lit}"))
+                }
+                _ => ConstValue::Computed,
+            },
+            syn::Expr::Paren(syn::ExprParen { expr, .. }) => ConstValue::from_syn(expr),
+            _ => ConstValue::Computed,
+        }
+    }
+}
+
+/// The room a method's symbol leaves for the suffixes of the other functions generated for it
+pub fn async_suffix_len(is_async: bool) -> usize {
+    if is_async {
+        attrs::ASYNC_SYMBOL_SUFFIXES
+            .iter()
+            .map(|suffix| suffix.len())
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    }
 }
 
 impl Method {
@@ -53,7 +123,10 @@ impl Method {
         let method_ident = &m.sig.ident;
         let concat_method_ident = format!("{self_ident}_{method_ident}");
         let extern_ident = syn::Ident::new(
-            &attrs.abi_rename.apply(concat_method_ident.into()),
+            &attrs.abi_rename.shorten(
+                attrs.abi_rename.apply(concat_method_ident.into()),
+                async_suffix_len(m.sig.asyncness.is_some()),
+            ),
             m.sig.ident.span(),
         );
 
@@ -67,6 +140,12 @@ impl Method {
             })
             .collect::<Vec<_>>();
 
+        let bundle = ParamBundle::from_attrs(&m.attrs, &all_params);
+        let params = match bundle {
+            Some(ref bundle) => bundle.params_at_boundary(),
+            None => all_params.clone(),
+        };
+
         let self_param = m
             .sig
             .receiver()
@@ -97,10 +176,53 @@ impl Method {
             docs: Docs::from_attrs(&m.attrs),
             full_path_name: Ident::from(&extern_ident),
             self_param,
-            params: all_params,
+            params,
             return_type: return_ty,
+            is_async: m.sig.asyncness.is_some(),
+            bundle,
+            constant: None,
             lifetime_env,
             attrs,
+            span: SourceSpan::from_syn(method_ident.span()),
+        }
+    }
+
+    /// Extracts a [`Method`] returning an associated constant from an AST node inside an `impl`.
+    pub fn from_syn_const(
+        c: &syn::ImplItemConst,
+        self_path_type: PathType,
+        impl_generics: Option<&syn::Generics>,
+        impl_attrs: &Attrs,
+    ) -> Method {
+        let mut attrs = impl_attrs.clone();
+        attrs.add_attrs(&c.attrs);
+
+        let self_ident = self_path_type.path.elements.last().unwrap();
+        let const_ident = &c.ident;
+        let concat_const_ident = format!("{self_ident}_{const_ident}");
+        let extern_ident = syn::Ident::new(
+            &attrs
+                .abi_rename
+                .shorten(attrs.abi_rename.apply(concat_const_ident.into()), 0),
+            c.ident.span(),
+        );
+
+        let return_ty = TypeName::from_syn(&c.ty, Some(self_path_type));
+        let lifetime_env = LifetimeEnv::from_const_item(impl_generics, &return_ty);
+
+        Method {
+            name: Ident::from(const_ident),
+            docs: Docs::from_attrs(&c.attrs),
+            full_path_name: Ident::from(&extern_ident),
+            self_param: None,
+            params: vec![],
+            return_type: Some(return_ty),
+            is_async: false,
+            bundle: None,
+            constant: Some(ConstValue::from_syn(&c.expr)),
+            lifetime_env,
+            attrs,
+            span: SourceSpan::from_syn(const_ident.span()),
         }
     }
 
@@ -228,6 +350,10 @@ pub struct SelfParam {
     /// The type of the parameter, which will be a named reference to
     /// the associated struct,
     pub path_type: PathType,
+
+    /// Whether `self` is taken as `self: Box<Self>`, consuming the boxed opaque.
+    #[serde(skip_serializing_if = "core::ops::Not::not")]
+    pub boxed: bool,
 }
 
 impl SelfParam {
@@ -236,16 +362,25 @@ impl SelfParam {
         if let Some((ref lifetime, ref mutability)) = self.reference {
             return TypeName::Reference(lifetime.clone(), *mutability, Box::new(typ));
         }
+        if self.boxed {
+            return TypeName::Box(Box::new(typ));
+        }
         typ
     }
 
     pub fn from_syn(rec: &syn::Receiver, path_type: PathType) -> Self {
+        let boxed = rec.colon_token.is_some()
+            && matches!(
+                &*rec.ty,
+                syn::Type::Path(p) if p.path.segments.last().map_or(false, |s| s.ident == "Box")
+            );
         SelfParam {
             reference: rec
                 .reference
                 .as_ref()
                 .map(|(_, lt)| (lt.into(), Mutability::from_syn(&rec.mutability))),
             path_type,
+            boxed,
         }
     }
 }
@@ -259,6 +394,10 @@ pub struct Param {
 
     /// The type of the parameter.
     pub ty: TypeName,
+
+    /// The encoding required of a string parameter, from `#[diplomat::encoding(...)]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<RequiredEncoding>,
 }
 
 impl Param {
@@ -279,7 +418,95 @@ impl Param {
         Param {
             name: (&ident.ident).into(),
             ty: TypeName::from_syn(&t.ty, Some(self_path_type)),
+            encoding: RequiredEncoding::from_attrs(&t.attrs),
+        }
+    }
+}
+
+/// The parameters of a [`Method`] grouped into a struct by
+/// `#[diplomat::bundle(OpenOptions, mode, create)]`. The struct is declared in the module of the
+/// method, and crosses the FFI boundary as a single parameter in place of the first of them.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Debug)]
+#[non_exhaustive]
+pub struct ParamBundle {
+    /// The name of the struct.
+    pub name: Ident,
+
+    /// The names of the grouped parameters, in the order they are listed in the attribute.
+    ///
+    /// These are not checked until lowering, and may name parameters that do not exist.
+    pub fields: Vec<Ident>,
+
+    /// All parameters of the Rust method, in declaration order, grouped or not.
+    pub rust_params: Vec<Param>,
+}
+
+impl ParamBundle {
+    fn from_attrs(attrs: &[syn::Attribute], params: &[Param]) -> Option<Self> {
+        let path: syn::Path = syn::parse_str("diplomat::bundle").unwrap();
+        let attr = attrs.iter().find(|a| a.path() == &path)?;
+        let mut idents = attr
+            .parse_args_with(
+                syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated,
+            )
+            .expect("Failed to parse malformed diplomat::bundle")
+            .into_iter()
+            .map(|ident| Ident::from(&ident));
+        Some(ParamBundle {
+            name: idents
+                .next()
+                .expect("diplomat::bundle must name the struct grouping the parameters"),
+            fields: idents.collect(),
+            rust_params: params.to_vec(),
+        })
+    }
+
+    /// The name of the parameter taking the struct: the name of the struct in snake case.
+    pub fn param_name(&self) -> Ident {
+        let mut name = String::new();
+        for c in self.name.as_str().chars() {
+            if c.is_uppercase() && !name.is_empty() {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+        }
+        Ident::from(name)
+    }
+
+    /// Whether the parameter named `name` is grouped into the struct.
+    pub fn contains(&self, name: &Ident) -> bool {
+        self.fields.contains(name)
+    }
+
+    /// The grouped parameters that exist, in the order they are listed in the attribute and
+    /// without repetitions.
+    pub fn grouped_params(&self) -> impl Iterator<Item = &Param> + '_ {
+        self.fields.iter().enumerate().filter_map(|(i, name)| {
+            if self.fields[..i].contains(name) {
+                return None;
+            }
+            self.rust_params.iter().find(|p| &p.name == name)
+        })
+    }
+
+    /// The parameters crossing the FFI boundary: the ones that are not grouped, and the struct in
+    /// place of the first grouped one.
+    fn params_at_boundary(&self) -> Vec<Param> {
+        let mut params = Vec::with_capacity(self.rust_params.len());
+        let mut bundled = false;
+        for param in &self.rust_params {
+            if !self.contains(&param.name) {
+                params.push(param.clone());
+            } else if !bundled {
+                bundled = true;
+                params.push(Param {
+                    name: self.param_name(),
+                    ty: TypeName::Named(PathType::new(Path::empty().sub_path(self.name.clone()))),
+                    encoding: None,
+                });
+            }
         }
+        params
     }
 }
 
@@ -7,7 +7,9 @@ pub(crate) use attrs::AttrInheritContext;
 pub use attrs::Attrs;
 
 mod methods;
-pub use methods::{BorrowedParams, Method, Param, SelfParam};
+pub use methods::{
+    async_suffix_len, BorrowedParams, ConstValue, Method, Param, ParamBundle, SelfParam,
+};
 
 mod modules;
 pub use modules::{File, Module};
@@ -18,10 +20,13 @@ pub use structs::{OpaqueStruct, Struct};
 mod enums;
 pub use enums::Enum;
 
+mod traits;
+pub use traits::{Trait, TraitMethod};
+
 mod types;
 pub use types::{
-    CustomType, LifetimeOrigin, ModSymbol, Mutability, PathType, PrimitiveType, StringEncoding,
-    TypeName,
+    CustomType, LifetimeOrigin, ModSymbol, Mutability, PathType, PrimitiveType, RequiredEncoding,
+    StringEncoding, TypeName,
 };
 
 pub(crate) mod lifetimes;
@@ -33,5 +38,8 @@ pub use paths::Path;
 mod idents;
 pub use idents::Ident;
 
+mod span;
+pub use span::SourceSpan;
+
 mod docs;
 pub use docs::{DocType, Docs, DocsUrlGenerator, MarkdownStyle, RustLink, RustLinkDisplay};
@@ -7,7 +7,7 @@ use syn::{ImplItem, Item, ItemMod, UseTree, Visibility};
 
 use super::{
     AttrInheritContext, Attrs, CustomType, Enum, Ident, Method, ModSymbol, Mutability,
-    OpaqueStruct, Path, PathType, RustLink, Struct,
+    OpaqueStruct, Path, PathType, RustLink, Struct, Trait,
 };
 use crate::environment::*;
 
@@ -65,6 +65,8 @@ pub struct Module {
     pub name: Ident,
     pub imports: Vec<(Path, Ident)>,
     pub declared_types: BTreeMap<Ident, CustomType>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub declared_traits: BTreeMap<Ident, Trait>,
     pub sub_modules: Vec<Module>,
     pub attrs: Attrs,
 }
@@ -99,6 +101,15 @@ impl Module {
             }
         });
 
+        self.declared_traits.iter().for_each(|(k, v)| {
+            if mod_symbols
+                .insert(k.clone(), ModSymbol::Trait(v.clone()))
+                .is_some()
+            {
+                panic!("A trait was declared with the same name as another item: {k}");
+            }
+        });
+
         let path_to_self = in_path.sub_path(self.name.clone());
         self.sub_modules.iter().for_each(|m| {
             m.insert_all_types(path_to_self.clone(), out);
@@ -110,6 +121,7 @@ impl Module {
 
     pub fn from_syn(input: &ItemMod, force_analyze: bool) -> Module {
         let mut custom_types_by_name = BTreeMap::new();
+        let mut traits_by_name = BTreeMap::new();
         let mut sub_modules = Vec::new();
         let mut imports = Vec::new();
 
@@ -168,6 +180,12 @@ impl Module {
                     }
                 }
 
+                Item::Trait(trt) if analyze_types => {
+                    let ident = (&trt.ident).into();
+                    let trt = Trait::new(trt, &type_parent_attrs);
+                    traits_by_name.insert(ident, trt);
+                }
+
                 Item::Impl(imp) => {
                     if analyze_types {
                         assert!(imp.trait_.is_none());
@@ -183,11 +201,14 @@ impl Module {
                             .items
                             .iter()
                             .filter_map(|i| match i {
-                                ImplItem::Fn(m) => Some(m),
+                                ImplItem::Fn(m) if matches!(m.vis, Visibility::Public(_)) => {
+                                    Some(Method::from_syn(m, self_path.clone(), Some(&imp.generics), &method_parent_attrs))
+                                }
+                                ImplItem::Const(c) if matches!(c.vis, Visibility::Public(_)) => {
+                                    Some(Method::from_syn_const(c, self_path.clone(), Some(&imp.generics), &method_parent_attrs))
+                                }
                                 _ => None,
                             })
-                            .filter(|m| matches!(m.vis, Visibility::Public(_)))
-                            .map(|m| Method::from_syn(m, self_path.clone(), Some(&imp.generics), &method_parent_attrs))
                             .collect();
 
                         let self_ident = self_path.path.elements.last().unwrap();
@@ -211,10 +232,24 @@ impl Module {
                 _ => {}
             });
 
+        // The structs grouping the parameters of methods marked `#[diplomat::bundle]`. A name
+        // that is already taken is left to lowering to report.
+        let bundles: Vec<_> = custom_types_by_name
+            .values()
+            .flat_map(|ty| ty.methods())
+            .filter_map(|m| Some(Struct::from_bundle(m, m.bundle.as_ref()?)))
+            .collect();
+        for bundle in bundles {
+            custom_types_by_name
+                .entry(bundle.name.clone())
+                .or_insert(CustomType::Struct(bundle));
+        }
+
         Module {
             name: (&input.ident).into(),
             imports,
             declared_types: custom_types_by_name,
+            declared_traits: traits_by_name,
             sub_modules,
             attrs: mod_attrs,
         }
@@ -0,0 +1,48 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Where an item starts in the Rust source, for pointing diagnostics at it.
+///
+/// Spans are not part of an item's identity: all spans compare equal, and they are
+/// never serialized, so that moving a declaration around does not change the AST.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct SourceSpan {
+    /// The line, starting at 1
+    pub line: usize,
+    /// The column in UTF-8 characters, starting at 1
+    pub column: usize,
+}
+
+impl SourceSpan {
+    /// The start of `span`, if it is known.
+    ///
+    /// Locations are not available when running inside a compiler that does not
+    /// expose them to procedural macros, nor for tokens made up by `quote!`, which
+    /// all get the same empty span.
+    pub fn from_syn(span: proc_macro2::Span) -> Option<Self> {
+        let start = span.start();
+        (start.line != 0 && start != span.end()).then(|| SourceSpan {
+            line: start.line,
+            column: start.column + 1,
+        })
+    }
+}
+
+impl PartialEq for SourceSpan {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SourceSpan {}
+
+impl Hash for SourceSpan {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
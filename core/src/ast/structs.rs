@@ -1,7 +1,9 @@
 use serde::Serialize;
 
 use super::docs::Docs;
-use super::{Attrs, Ident, LifetimeEnv, Method, Mutability, PathType, TypeName};
+use super::{
+    Attrs, Ident, LifetimeEnv, Method, Mutability, ParamBundle, PathType, SourceSpan, TypeName,
+};
 
 /// A struct declaration in an FFI module that is not opaque.
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Debug)]
@@ -14,6 +16,13 @@ pub struct Struct {
     pub methods: Vec<Method>,
     pub output_only: bool,
     pub attrs: Attrs,
+    /// The full path name of the method whose parameters this struct groups, if it is declared
+    /// by a `#[diplomat::bundle]` attribute instead of in Rust
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<Ident>,
+    /// Where the struct is declared, for diagnostics.
+    #[serde(skip)]
+    pub span: Option<SourceSpan>,
 }
 
 impl Struct {
@@ -48,6 +57,36 @@ impl Struct {
             methods: vec![],
             output_only,
             attrs,
+            bundle: None,
+            span: SourceSpan::from_syn(strct.ident.span()),
+        }
+    }
+
+    /// Declares the struct grouping the parameters of a method marked `#[diplomat::bundle]`.
+    ///
+    /// Parameters borrowing data are left out, lowering rejects them.
+    pub(crate) fn from_bundle(method: &Method, bundle: &ParamBundle) -> Self {
+        let fields = bundle
+            .grouped_params()
+            .filter(|param| !param.ty.any_lifetime(|_, _| true))
+            .map(|param| (param.name.clone(), param.ty.clone(), Docs::default()))
+            .collect();
+        Struct {
+            name: bundle.name.clone(),
+            docs: Docs::from_text(format!(
+                "The options of `{}`, taken by it as a single parameter",
+                method.name
+            )),
+            lifetimes: LifetimeEnv { nodes: vec![] },
+            fields,
+            methods: vec![],
+            output_only: false,
+            attrs: Attrs {
+                cfg: method.attrs.cfg.clone(),
+                ..Attrs::default()
+            },
+            bundle: Some(method.full_path_name.clone()),
+            span: method.span,
         }
     }
 }
@@ -64,6 +103,9 @@ pub struct OpaqueStruct {
     pub methods: Vec<Method>,
     pub mutability: Mutability,
     pub attrs: Attrs,
+    /// Where the struct is declared, for diagnostics.
+    #[serde(skip)]
+    pub span: Option<SourceSpan>,
 }
 
 impl OpaqueStruct {
@@ -78,6 +120,7 @@ impl OpaqueStruct {
             methods: vec![],
             mutability,
             attrs,
+            span: SourceSpan::from_syn(strct.ident.span()),
         }
     }
 }
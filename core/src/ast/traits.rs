@@ -0,0 +1,130 @@
+use serde::Serialize;
+
+use super::docs::Docs;
+use super::{Attrs, Ident, Param, SourceSpan, TypeName};
+
+/// A trait declaration in an FFI module, which foreign code can implement and pass
+/// into Rust as a `&dyn Trait` parameter.
+#[derive(Clone, Serialize, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Trait {
+    pub name: Ident,
+    pub docs: Docs,
+    pub methods: Vec<TraitMethod>,
+    pub attrs: Attrs,
+    /// Where the trait is declared, for diagnostics.
+    #[serde(skip)]
+    pub span: Option<SourceSpan>,
+}
+
+/// A method of a [`Trait`]. Trait methods always take `&self`.
+#[derive(Clone, Serialize, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TraitMethod {
+    pub name: Ident,
+    pub docs: Docs,
+    /// The parameters of the method, not including `&self`
+    pub params: Vec<Param>,
+    /// The return type of the method, if any
+    pub output: Option<TypeName>,
+}
+
+impl Trait {
+    /// Extract a [`Trait`] metadata value from an AST node.
+    pub fn new(trt: &syn::ItemTrait, parent_attrs: &Attrs) -> Trait {
+        if !trt.generics.params.is_empty() {
+            panic!("Traits cannot have generic parameters");
+        }
+
+        let mut attrs = parent_attrs.clone();
+        attrs.add_attrs(&trt.attrs);
+
+        let methods = trt
+            .items
+            .iter()
+            .map(|item| match item {
+                syn::TraitItem::Fn(m) => TraitMethod::from_syn(m),
+                other => panic!(
+                    "Traits can only contain methods, found {}",
+                    quote::ToTokens::to_token_stream(other)
+                ),
+            })
+            .collect();
+
+        Trait {
+            name: (&trt.ident).into(),
+            docs: Docs::from_attrs(&trt.attrs),
+            methods,
+            attrs,
+            span: SourceSpan::from_syn(trt.ident.span()),
+        }
+    }
+}
+
+impl TraitMethod {
+    fn from_syn(m: &syn::TraitItemFn) -> TraitMethod {
+        let mut inputs = m.sig.inputs.iter();
+        match inputs.next() {
+            Some(syn::FnArg::Receiver(r)) if r.reference.is_some() && r.mutability.is_none() => {}
+            _ => panic!("Trait methods must take `&self`: {}", m.sig.ident),
+        }
+
+        let params = inputs
+            .map(|input| match input {
+                syn::FnArg::Typed(t) => {
+                    let ident = match t.pat.as_ref() {
+                        syn::Pat::Ident(ident) => ident,
+                        _ => panic!("Unexpected param type"),
+                    };
+                    Param {
+                        name: (&ident.ident).into(),
+                        ty: TypeName::from_syn(&t.ty, None),
+                        encoding: None,
+                    }
+                }
+                syn::FnArg::Receiver(_) => unreachable!("`self` can only be the first argument"),
+            })
+            .collect();
+
+        let output = match m.sig.output {
+            syn::ReturnType::Type(_, ref output) => Some(TypeName::from_syn(output, None)),
+            syn::ReturnType::Default => None,
+        };
+
+        TraitMethod {
+            name: (&m.sig.ident).into(),
+            docs: Docs::from_attrs(&m.attrs),
+            params,
+            output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::{self, Settings};
+
+    use syn;
+
+    use super::Trait;
+
+    #[test]
+    fn simple_trait() {
+        let mut settings = Settings::new();
+        settings.set_sort_maps(true);
+
+        settings.bind(|| {
+            insta::assert_yaml_snapshot!(Trait::new(
+                &syn::parse_quote! {
+                    /// Some docs.
+                    trait Provider {
+                        fn get(&self, key: u32) -> f64;
+                        /// Method docs.
+                        fn notify(&self, changed: bool);
+                    }
+                },
+                &Default::default()
+            ));
+        });
+    }
+}
@@ -10,7 +10,7 @@ use std::ops::ControlFlow;
 
 use super::{
     Attrs, Docs, Enum, Ident, Lifetime, LifetimeEnv, LifetimeTransitivity, Method, NamedLifetime,
-    OpaqueStruct, Path, RustLink, Struct,
+    OpaqueStruct, Path, RustLink, Struct, Trait,
 };
 use crate::Env;
 
@@ -55,8 +55,9 @@ impl CustomType {
 
     /// The name of the destructor in C
     pub fn dtor_name(&self) -> String {
-        let name = self.attrs().abi_rename.apply(self.name().as_str().into());
-        format!("{name}_destroy")
+        let rename = &self.attrs().abi_rename;
+        let name = rename.apply(self.name().as_str().into());
+        rename.shorten(format!("{name}_destroy").into(), 0).into()
     }
 
     /// Get the doc lines of the custom type.
@@ -101,6 +102,8 @@ pub enum ModSymbol {
     SubModule(Ident),
     /// A symbol that is a custom type.
     CustomType(CustomType),
+    /// A symbol that is a trait.
+    Trait(Trait),
 }
 
 /// A named type that is just a path, e.g. `std::borrow::Cow<'a, T>`.
@@ -165,11 +168,9 @@ impl PathType {
         }
     }
 
-    /// If this is a [`TypeName::Named`], grab the [`CustomType`] it points to from
-    /// the `env`, which contains all [`CustomType`]s across all FFI modules.
-    ///
-    /// Also returns the path the CustomType is in (useful for resolving fields)
-    pub fn resolve_with_path<'a>(&self, in_path: &Path, env: &'a Env) -> (Path, &'a CustomType) {
+    /// Follows the path through modules and aliases to the symbol it names, returning
+    /// the path of the module containing it
+    fn resolve_symbol<'a>(&self, in_path: &Path, env: &'a Env) -> (Path, &'a ModSymbol) {
         let local_path = &self.path;
         let mut cur_path = in_path.clone();
         for (i, elem) in local_path.elements.iter().enumerate() {
@@ -188,17 +189,17 @@ impl PathType {
                         let mut new_path = p.elements.clone();
                         new_path.append(&mut remaining_elements);
                         return PathType::new(Path { elements: new_path })
-                            .resolve_with_path(&cur_path.clone(), env);
+                            .resolve_symbol(&cur_path.clone(), env);
                     }
                     Some(ModSymbol::SubModule(name)) => {
                         cur_path.elements.push(name.clone());
                     }
-                    Some(ModSymbol::CustomType(t)) => {
+                    Some(symbol) => {
                         if i == local_path.elements.len() - 1 {
-                            return (cur_path, t);
+                            return (cur_path, symbol);
                         } else {
                             panic!(
-                                "Unexpected custom type when resolving symbol {} in {}",
+                                "Unexpected item when resolving symbol {} in {}",
                                 o,
                                 cur_path.elements.join("::")
                             )
@@ -219,6 +220,25 @@ impl PathType {
         )
     }
 
+    /// If this is a [`TypeName::Named`], grab the [`CustomType`] it points to from
+    /// the `env`, which contains all [`CustomType`]s across all FFI modules.
+    ///
+    /// Also returns the path the CustomType is in (useful for resolving fields)
+    pub fn resolve_with_path<'a>(&self, in_path: &Path, env: &'a Env) -> (Path, &'a CustomType) {
+        match self.resolve_symbol(in_path, env) {
+            (path, ModSymbol::CustomType(t)) => (path, t),
+            _ => panic!("Path {self} does not point to a custom type"),
+        }
+    }
+
+    /// If this is a [`TypeName::DynTrait`], grab the [`Trait`] it points to from the `env`.
+    pub fn resolve_trait<'a>(&self, in_path: &Path, env: &'a Env) -> &'a Trait {
+        match self.resolve_symbol(in_path, env) {
+            (_, ModSymbol::Trait(t)) => t,
+            _ => panic!("Path {self} does not point to a trait"),
+        }
+    }
+
     /// If this is a [`TypeName::Named`], grab the [`CustomType`] it points to from
     /// the `env`, which contains all [`CustomType`]s across all FFI modules.
     ///
@@ -343,6 +363,8 @@ pub enum TypeName {
     Box(Box<TypeName>),
     /// A `Option<T>` type.
     Option(Box<TypeName>),
+    /// A `diplomat_runtime::DiplomatOption<T>` type, the FFI-safe `Option<T>` used in struct fields.
+    DiplomatOption(Box<TypeName>),
     /// A `Result<T, E>` or `diplomat_runtime::DiplomatWriteable` type. If the bool is true, it's `Result`
     Result(Box<TypeName>, Box<TypeName>, bool),
     Writeable,
@@ -362,6 +384,13 @@ pub enum TypeName {
     ///
     /// The path must be present! Ordering will be parsed as an AST type!
     Ordering,
+    /// An `impl Fn(A, B) -> R` parameter, passed across the FFI boundary as a
+    /// `diplomat_runtime::DiplomatCallback<R>`.
+    Function(Vec<Box<TypeName>>, Box<TypeName>),
+    /// A `&dyn Trait` parameter, passed across the FFI boundary as the vtable struct
+    /// generated for the trait. The object is only borrowed for the duration of the call,
+    /// so the lifetime of the reference is not kept.
+    DynTrait(PathType),
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Copy)]
@@ -373,6 +402,41 @@ pub enum StringEncoding {
     Utf8,
 }
 
+/// The encoding a string parameter is required to be in, as declared with
+/// `#[diplomat::encoding(...)]`.
+///
+/// This is a promise about the values the Rust function expects, letting backends
+/// pick a cheaper conversion or skip validation for that parameter. It does not
+/// change the parameter's FFI type, which is still given by its [`StringEncoding`].
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Copy)]
+#[non_exhaustive]
+pub enum RequiredEncoding {
+    /// Valid UTF-8, on a `&DiplomatStr` or `&str` parameter
+    Utf8,
+    /// Valid UTF-16, on a `&DiplomatStr16` parameter
+    Utf16,
+    /// ASCII only, on a `&DiplomatStr` or `&str` parameter
+    Ascii,
+}
+
+impl RequiredEncoding {
+    pub(crate) fn from_attrs(attrs: &[syn::Attribute]) -> Option<Self> {
+        let path: syn::Path = syn::parse_str("diplomat::encoding").unwrap();
+        let attr = attrs.iter().find(|a| a.path() == &path)?;
+        let encoding: syn::Ident = attr
+            .parse_args()
+            .expect("Expected #[diplomat::encoding(utf8 | utf16 | ascii)]");
+        Some(match &*encoding.to_string() {
+            "utf8" => Self::Utf8,
+            "utf16" => Self::Utf16,
+            "ascii" => Self::Ascii,
+            _ => {
+                panic!("Unknown encoding `{encoding}`, expected one of `utf8`, `utf16` or `ascii`")
+            }
+        })
+    }
+}
+
 impl TypeName {
     /// Converts the [`TypeName`] back into an AST node that can be spliced into a program.
     pub fn to_syn(&self) -> syn::Type {
@@ -429,6 +493,12 @@ impl TypeName {
                     }]),
                 },
             }),
+            TypeName::DiplomatOption(underlying) => {
+                let underlying = underlying.to_syn();
+                syn::parse_quote! {
+                    diplomat_runtime::DiplomatOption<#underlying>
+                }
+            }
             TypeName::Result(ok, err, true) => syn::Type::Path(TypePath {
                 qself: None,
                 path: syn::Path {
@@ -527,6 +597,22 @@ impl TypeName {
             TypeName::Unit => syn::parse_quote! {
                 ()
             },
+            TypeName::Function(_, output) => {
+                let output = output.to_syn();
+                syn::parse_quote! {
+                    diplomat_runtime::DiplomatCallback<#output>
+                }
+            }
+            TypeName::DynTrait(trt) => {
+                let mut path = trt.path.clone();
+                let name = path.elements.pop().unwrap();
+                path.elements
+                    .push(Ident::from(format!("DiplomatTraitStruct_{name}")));
+                syn::Type::Path(TypePath {
+                    qself: None,
+                    path: path.to_syn(),
+                })
+            }
         }
     }
 
@@ -537,11 +623,14 @@ impl TypeName {
     /// - If the type is a path with a single element [`Option`], returns a [`TypeName::Option`] with the type parameter recursively converted
     /// - If the type is a path with a single element `Self` and `self_path_type` is provided, returns a [`TypeName::Named`]
     /// - If the type is a path with a single element [`Result`], returns a [`TypeName::Result`] with the type parameters recursively converted
+    /// - If the type is a path equal to [`diplomat_runtime::DiplomatOption`], returns a [`TypeName::DiplomatOption`] with the type parameter recursively converted
     /// - If the type is a path equal to [`diplomat_runtime::DiplomatResult`], returns a [`TypeName::DiplomatResult`] with the type parameters recursively converted
     /// - If the type is a path equal to [`diplomat_runtime::DiplomatWriteable`], returns a [`TypeName::Writeable`]
     /// - If the type is a owned or borrowed string type, returns a [`TypeName::StrReference`]
     /// - If the type is a owned or borrowed slice of a Rust primitive, returns a [`TypeName::PrimitiveSlice`]
     /// - If the type is a reference (`&` or `&mut`), returns a [`TypeName::Reference`] with the referenced type recursively converted
+    /// - If the type is an `impl Fn(..)`, returns a [`TypeName::Function`] with the parameter and return types recursively converted
+    /// - If the type is a `&dyn Trait`, returns a [`TypeName::DynTrait`]
     /// - Otherwise, assume that the reference is to a [`CustomType`] in either the current module or another one, returns a [`TypeName::Named`]
     pub fn from_syn(ty: &syn::Type, self_path_type: Option<PathType>) -> TypeName {
         match ty {
@@ -549,6 +638,19 @@ impl TypeName {
                 let lifetime = Lifetime::from(&r.lifetime);
                 let mutability = Mutability::from_syn(&r.mutability);
 
+                if let syn::Type::TraitObject(ref trt) = *r.elem {
+                    if mutability.is_mutable() {
+                        panic!("Trait objects can only be passed by immutable reference");
+                    }
+                    let path = trt.bounds.iter().find_map(|bound| match bound {
+                        syn::TypeParamBound::Trait(t) => Some(&t.path),
+                        _ => None,
+                    });
+                    let path = path
+                        .unwrap_or_else(|| panic!("Expected a trait in {}", trt.to_token_stream()));
+                    return TypeName::DynTrait(PathType::new(Path::from_syn(path)));
+                }
+
                 let name = r.elem.to_token_stream().to_string();
                 if name.starts_with("DiplomatStr") || name == "str" {
                     if mutability.is_mutable() {
@@ -644,6 +746,21 @@ impl TypeName {
                     } else {
                         panic!("Expected angle brackets for Option type")
                     }
+                } else if is_runtime_type(p, "DiplomatOption") {
+                    if let PathArguments::AngleBracketed(type_args) =
+                        &p.path.segments.last().unwrap().arguments
+                    {
+                        if let GenericArgument::Type(tpe) = &type_args.args[0] {
+                            TypeName::DiplomatOption(Box::new(TypeName::from_syn(
+                                tpe,
+                                self_path_type,
+                            )))
+                        } else {
+                            panic!("Expected first type argument for DiplomatOption to be a type")
+                        }
+                    } else {
+                        panic!("Expected angle brackets for DiplomatOption type")
+                    }
                 } else if p_len == 1 && p.path.segments[0].ident == "Self" {
                     if let Some(self_path_type) = self_path_type {
                         TypeName::SelfType(self_path_type)
@@ -685,6 +802,39 @@ impl TypeName {
                     todo!("Tuples are not currently supported")
                 }
             }
+            syn::Type::ImplTrait(imp) => {
+                let fn_args = imp.bounds.iter().find_map(|bound| match bound {
+                    syn::TypeParamBound::Trait(t) => {
+                        let segment = t.path.segments.last()?;
+                        if t.path.segments.len() != 1 || segment.ident != "Fn" {
+                            return None;
+                        }
+                        match segment.arguments {
+                            PathArguments::Parenthesized(ref args) => Some(args),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                });
+                let fn_args = fn_args.unwrap_or_else(|| {
+                    panic!(
+                        "Only `impl Fn(..)` types are supported, found {}",
+                        imp.to_token_stream()
+                    )
+                });
+                let params = fn_args
+                    .inputs
+                    .iter()
+                    .map(|input| Box::new(TypeName::from_syn(input, self_path_type.clone())))
+                    .collect();
+                let output = match fn_args.output {
+                    syn::ReturnType::Type(_, ref output) => {
+                        TypeName::from_syn(output, self_path_type)
+                    }
+                    syn::ReturnType::Default => TypeName::Unit,
+                };
+                TypeName::Function(params, Box::new(output))
+            }
             other => panic!("Unsupported type: {}", other.to_token_stream()),
         }
     }
@@ -712,11 +862,19 @@ impl TypeName {
                 ty.visit_lifetimes(visit)?;
                 visit(lt, LifetimeOrigin::Reference)
             }
-            TypeName::Box(ty) | TypeName::Option(ty) => ty.visit_lifetimes(visit),
+            TypeName::Box(ty) | TypeName::Option(ty) | TypeName::DiplomatOption(ty) => {
+                ty.visit_lifetimes(visit)
+            }
             TypeName::Result(ok, err, _) => {
                 ok.visit_lifetimes(visit)?;
                 err.visit_lifetimes(visit)
             }
+            TypeName::Function(params, output) => {
+                for param in params {
+                    param.visit_lifetimes(visit)?;
+                }
+                output.visit_lifetimes(visit)
+            }
             TypeName::StrReference(Some(lt), ..) => visit(lt, LifetimeOrigin::StrReference),
             TypeName::PrimitiveSlice(Some((lt, _)), ..) => {
                 visit(lt, LifetimeOrigin::PrimitiveSlice)
@@ -829,6 +987,7 @@ impl fmt::Display for TypeName {
             }
             TypeName::Box(typ) => write!(f, "Box<{typ}>"),
             TypeName::Option(typ) => write!(f, "Option<{typ}>"),
+            TypeName::DiplomatOption(typ) => write!(f, "DiplomatOption<{typ}>"),
             TypeName::Result(ok, err, _) => {
                 write!(f, "Result<{ok}, {err}>")
             }
@@ -877,6 +1036,17 @@ impl fmt::Display for TypeName {
             }
             TypeName::PrimitiveSlice(None, typ) => write!(f, "Box<[{typ}]>"),
             TypeName::Unit => "()".fmt(f),
+            TypeName::Function(params, output) => {
+                write!(f, "impl Fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    param.fmt(f)?;
+                }
+                write!(f, ") -> {output}")
+            }
+            TypeName::DynTrait(trt) => write!(f, "&dyn {trt}"),
         }
     }
 }
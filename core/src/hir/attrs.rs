@@ -4,8 +4,8 @@ use crate::ast;
 use crate::ast::attrs::{AttrInheritContext, DiplomatBackendAttrCfg, StandardAttribute};
 use crate::hir::lowering::ErrorStore;
 use crate::hir::{
-    EnumVariant, LoweringError, Method, Mutability, OpaqueId, ReturnType, SelfType, SuccessType,
-    Type, TypeDef, TypeId,
+    EnumVariant, IntType, LoweringError, Method, Mutability, OpaqueId, PrimitiveType, ReturnType,
+    SelfType, SuccessType, Type, TypeDef, TypeId,
 };
 use syn::Meta;
 
@@ -41,6 +41,79 @@ pub struct Attrs {
     /// This attribute does not participate in inheritance and must always
     /// be specified on individual methods
     pub special_method: Option<SpecialMethod>,
+    /// Feature this type in generated usage documentation, such as worked examples.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on types
+    pub featured: bool,
+    /// This opaque is owned by a parent (such as an arena) that frees it along with everything
+    /// else it owns, so bindings must never destroy it on their own.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on opaque types
+    pub parent_managed: bool,
+    /// This opaque is not `Send`: its objects must only be used on the thread that created them,
+    /// which bindings for languages with threads may check.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on opaque types
+    pub non_send: bool,
+    /// This method returns a value along with a list of non-fatal warnings, as an out struct
+    /// with exactly a `value` and a `warnings` field. See [`Method::with_warnings()`].
+    ///
+    /// This attribute does not participate in inheritance and can only be used on methods
+    pub with_warnings: bool,
+    /// This method is on a hot path: bindings calling the C ABI through a generic layer,
+    /// such as JNA on the JVM, should call it through generated glue instead.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on methods
+    pub jni: bool,
+    /// This method is expensive: bindings for languages with an event loop should offer running
+    /// it off the loop, such as on a background isolate in Dart, returning a future.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on methods
+    pub background: bool,
+    /// The platforms this method is available on, among [`PLATFORMS`], or all of them when
+    /// empty. Backends generate it conditionally on the platform, and it is disabled when the
+    /// bindings are generated for a target it isn't available on. The Rust method itself is
+    /// expected to be `#[cfg]`'d out on other platforms.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on methods
+    pub platforms: Vec<String>,
+    /// A numeric ID for this type or method that stays the same across releases, for
+    /// dispatching calls by number, such as over RPC. Items without one are given the
+    /// [`stable_id_of()`] their path after lowering, so this is always set on the HIR of
+    /// types and methods. IDs must be unique across the whole bridge.
+    ///
+    /// This attribute does not participate in inheritance and can only be used on types and methods
+    pub stable_id: Option<u32>,
+}
+
+/// The stable ID given to items without a `stable_id` attribute: the 32-bit FNV-1a hash of
+/// their Rust path, such as `Foo` for types and `Foo::bar` for methods. It only changes when
+/// the item is renamed on the Rust side.
+pub fn stable_id_of(path: &str) -> u32 {
+    path.bytes().fold(0x811c_9dc5, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// The platforms methods can be limited to with `platforms(..)`, named after Rust's
+/// `target_os`, except for `wasm` which covers every WebAssembly target.
+pub const PLATFORMS: &[&str] = &[
+    "android", "freebsd", "ios", "linux", "macos", "wasm", "windows",
+];
+
+/// Whether the target `triple`, such as `x86_64-unknown-linux-gnu`, is on `platform`
+pub fn is_platform_of_target(platform: &str, triple: &str) -> bool {
+    let mut components = triple.split('-');
+    let arch = components.next().unwrap_or_default();
+    let mut os = components.filter(|c| !c.is_empty());
+    match platform {
+        "wasm" => arch.starts_with("wasm"),
+        "macos" => os.any(|c| c == "darwin" || c == "macos"),
+        "ios" | "android" => os.any(|c| c.starts_with(platform)),
+        // Android triples are `aarch64-linux-android`, but Android is not Linux to Rust
+        "linux" => !triple.contains("android") && os.any(|c| c == "linux"),
+        _ => os.any(|c| c == platform),
+    }
 }
 
 /// Attributes that mark methods as "special"
@@ -77,6 +150,13 @@ pub enum SpecialMethod {
     Iterable,
     /// Indexes into the type using an integer
     Indexer,
+    /// A hash function. Must take `self`, have no other parameters and return a `u64`
+    Hash,
+    /// Copies an opaque. Must take `&self`, have no other parameters and return a `Box<Self>`
+    Clone,
+    /// Produces the default value of a struct, which backends can use to fill in the fields
+    /// a caller leaves out. Must be static, have no parameters and return `Self`
+    Default,
 }
 
 /// For special methods that affect type semantics, whether this type has this method.
@@ -86,6 +166,10 @@ pub enum SpecialMethod {
 #[non_exhaustive]
 pub struct SpecialMethodPresence {
     pub comparator: bool,
+    /// Whether it has a hash function
+    pub hash: bool,
+    /// Whether it has a default constructor
+    pub default_constructor: bool,
     /// If it is an iterator, the type it iterates over
     pub iterator: Option<SuccessType>,
     /// If it is an iterable, the iterator type it returns (*not* the type it iterates over,
@@ -147,8 +231,161 @@ impl Attrs {
                                 "`disable` must be a simple path".into(),
                             ))
                         }
+                    } else if path == "with_warnings" {
+                        if let Meta::Path(_) = attr.meta {
+                            if !support.warnings {
+                                errors.push(LoweringError::Other(format!(
+                                    "`with_warnings` not supported in backend {backend}"
+                                )))
+                            } else {
+                                this.with_warnings = true;
+                            }
+                        } else {
+                            errors.push(LoweringError::Other(
+                                "`with_warnings` must be a simple path".into(),
+                            ))
+                        }
+                    } else if path == "jni" {
+                        if let Meta::Path(_) = attr.meta {
+                            if !support.jni {
+                                errors.push(LoweringError::Other(format!(
+                                    "`jni` not supported in backend {backend}"
+                                )))
+                            } else {
+                                this.jni = true;
+                            }
+                        } else {
+                            errors.push(LoweringError::Other("`jni` must be a simple path".into()))
+                        }
+                    } else if path == "background" {
+                        if let Meta::Path(_) = attr.meta {
+                            if !support.background {
+                                errors.push(LoweringError::Other(format!(
+                                    "`background` not supported in backend {backend}"
+                                )))
+                            } else {
+                                this.background = true;
+                            }
+                        } else {
+                            errors.push(LoweringError::Other(
+                                "`background` must be a simple path".into(),
+                            ))
+                        }
+                    } else if path == "platforms" {
+                        let Meta::List(ref list) = attr.meta else {
+                            errors.push(LoweringError::Other(
+                                "`platforms` must list platforms, e.g. `platforms(linux, windows)`"
+                                    .into(),
+                            ));
+                            continue;
+                        };
+                        let platforms = match list.parse_args_with(
+                            syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated,
+                        ) {
+                            Ok(platforms) if !platforms.is_empty() => platforms,
+                            _ => {
+                                errors.push(LoweringError::Other(
+                                    "`platforms` must list platforms, e.g. `platforms(linux, windows)`"
+                                        .into(),
+                                ));
+                                continue;
+                            }
+                        };
+                        for platform in platforms {
+                            let platform = platform.to_string();
+                            if !PLATFORMS.contains(&platform.as_str()) {
+                                errors.push(LoweringError::Other(format!(
+                                    "Unknown platform `{platform}`, expected one of: `{}`",
+                                    PLATFORMS.join(", ")
+                                )));
+                            } else if !this.platforms.contains(&platform) {
+                                this.platforms.push(platform);
+                            }
+                        }
+                        match validator.target() {
+                            // Generating for a single target, which may not have the method
+                            Some(target)
+                                if !this
+                                    .platforms
+                                    .iter()
+                                    .any(|platform| is_platform_of_target(platform, target)) =>
+                            {
+                                this.disable = true;
+                            }
+                            Some(_) => (),
+                            None if !support.platforms => {
+                                errors.push(LoweringError::Other(format!(
+                                    "`platforms` not supported in backend {backend}, unless generating for a single target"
+                                )))
+                            }
+                            None => (),
+                        }
+                    } else if path == "stable_id" {
+                        let id = match attr.meta {
+                            Meta::NameValue(ref nv) => match nv.value {
+                                syn::Expr::Lit(syn::ExprLit {
+                                    lit: syn::Lit::Int(ref lit),
+                                    ..
+                                }) => lit.base10_parse::<u32>().ok(),
+                                _ => None,
+                            },
+                            Meta::List(ref list) => list
+                                .parse_args::<syn::LitInt>()
+                                .ok()
+                                .and_then(|lit| lit.base10_parse::<u32>().ok()),
+                            Meta::Path(_) => None,
+                        };
+                        if let Some(id) = id {
+                            this.stable_id = Some(id);
+                        } else {
+                            errors.push(LoweringError::Other(
+                                "`stable_id` must have a single `u32` parameter, as in `stable_id = 7`"
+                                    .into(),
+                            ))
+                        }
+                    } else if path == "featured" {
+                        if let Meta::Path(_) = attr.meta {
+                            this.featured = true;
+                        } else {
+                            errors.push(LoweringError::Other(
+                                "`featured` must be a simple path".into(),
+                            ))
+                        }
+                    } else if path == "parent_managed" {
+                        if let Meta::Path(_) = attr.meta {
+                            if !support.parent_managed {
+                                errors.push(LoweringError::Other(format!(
+                                    "`parent_managed` not supported in backend {backend}"
+                                )))
+                            } else {
+                                this.parent_managed = true;
+                            }
+                        } else {
+                            errors.push(LoweringError::Other(
+                                "`parent_managed` must be a simple path".into(),
+                            ))
+                        }
+                    } else if path == "non_send" {
+                        if let Meta::Path(_) = attr.meta {
+                            if !support.non_send {
+                                errors.push(LoweringError::Other(format!(
+                                    "`non_send` not supported in backend {backend}"
+                                )))
+                            } else {
+                                this.non_send = true;
+                            }
+                        } else {
+                            errors.push(LoweringError::Other(
+                                "`non_send` must be a simple path".into(),
+                            ))
+                        }
                     } else if path == "rename" {
                         match RenameAttr::from_meta(&attr.meta) {
+                            Ok(rename) if rename.max_length().is_some() => {
+                                errors.push(LoweringError::Other(
+                                    "`rename` cannot limit lengths, only symbols can be limited with `abi_rename(max_length = N)`".into(),
+                                ))
+                            }
                             Ok(rename) => {
                                 // We use the override extend mode: a single ast::Attrs
                                 // will have had these attributes inherited into the list by appending
@@ -184,6 +421,9 @@ impl Attrs {
                         || path == "iterable"
                         || path == "iterator"
                         || path == "indexer"
+                        || path == "hash"
+                        || path == "clone"
+                        || path == "default"
                     {
                         if let Some(ref existing) = this.special_method {
                             errors.push(LoweringError::Other(format!(
@@ -226,6 +466,27 @@ impl Attrs {
                                 )))
                             }
                             SpecialMethod::Indexer
+                        } else if path == "hash" {
+                            if !support.hashing {
+                                errors.push(LoweringError::Other(format!(
+                                    "hashing not supported in backend {backend}"
+                                )))
+                            }
+                            SpecialMethod::Hash
+                        } else if path == "clone" {
+                            if !support.cloning {
+                                errors.push(LoweringError::Other(format!(
+                                    "cloning not supported in backend {backend}"
+                                )))
+                            }
+                            SpecialMethod::Clone
+                        } else if path == "default" {
+                            if !support.default_constructors {
+                                errors.push(LoweringError::Other(format!(
+                                    "default constructors not supported in backend {backend}"
+                                )))
+                            }
+                            SpecialMethod::Default
                         } else {
                             if !support.comparators {
                                 errors.push(LoweringError::Other(format!(
@@ -279,12 +540,12 @@ impl Attrs {
                         }
                     } else {
                         errors.push(LoweringError::Other(format!(
-                        "Unknown diplomat attribute {path}: expected one of: `disable, rename, namespace, constructor, stringifier, comparison, named_constructor, getter, setter, indexer`"
+                        "Unknown diplomat attribute {path}: expected one of: `disable, featured, parent_managed, non_send, jni, background, platforms, stable_id, rename, namespace, constructor, stringifier, comparison, named_constructor, getter, setter, indexer`"
                     )));
                     }
                 } else {
                     errors.push(LoweringError::Other(format!(
-                        "Unknown diplomat attribute {path:?}: expected one of: `disable, rename, namespace, constructor, stringifier, comparison, named_constructor, getter, setter, indexer`"
+                        "Unknown diplomat attribute {path:?}: expected one of: `disable, featured, parent_managed, non_send, jni, background, platforms, stable_id, rename, namespace, constructor, stringifier, comparison, named_constructor, getter, setter, indexer`"
                     )));
                 }
             }
@@ -307,6 +568,14 @@ impl Attrs {
             rename: _,
             abi_rename: _,
             special_method,
+            featured,
+            parent_managed,
+            non_send,
+            with_warnings,
+            jni,
+            background,
+            platforms,
+            stable_id,
         } = &self;
 
         if *disable && matches!(context, AttributeContext::EnumVariant(..)) {
@@ -548,6 +817,78 @@ impl Attrs {
                             errors.push(LoweringError::Other("Indexer must return a value".into()));
                         }
                     }
+                    SpecialMethod::Hash => {
+                        if method.param_self.is_none() || !method.params.is_empty() {
+                            errors.push(LoweringError::Other(
+                                "Hash function must take self and no other parameters".into(),
+                            ));
+                        }
+                        if !matches!(
+                            method.output,
+                            ReturnType::Infallible(SuccessType::OutType(Type::Primitive(
+                                PrimitiveType::Int(IntType::U64)
+                            )))
+                        ) {
+                            errors
+                                .push(LoweringError::Other("Hash function must return u64".into()));
+                        }
+                        if special_method_presence.hash {
+                            errors.push(LoweringError::Other(
+                                "Cannot define two hash functions on the same type".into(),
+                            ));
+                        }
+                        special_method_presence.hash = true;
+                    }
+                    SpecialMethod::Clone => {
+                        let borrows_self = matches!(
+                            method.param_self,
+                            Some(ref s) if matches!(
+                                s.ty,
+                                SelfType::Opaque(ref p) if p.owner.mutability == Mutability::Immutable
+                            )
+                        );
+                        if !borrows_self || !method.params.is_empty() {
+                            errors.push(LoweringError::Other(
+                                "Clone function must take &self and no other parameters".into(),
+                            ));
+                        }
+                        let returns_self = matches!(
+                            method.output,
+                            ReturnType::Infallible(SuccessType::OutType(Type::Opaque(ref p)))
+                                if TypeId::from(p.tcx_id) == self_id
+                                    && !p.is_optional()
+                                    && p.owner.as_borrowed().is_none()
+                        );
+                        if !returns_self {
+                            errors.push(LoweringError::Other(
+                                "Clone function must return Box<Self>".into(),
+                            ));
+                        }
+                    }
+                    SpecialMethod::Default => {
+                        if method.param_self.is_some() || !method.params.is_empty() {
+                            errors.push(LoweringError::Other(
+                                "Default constructor must be static and take no parameters".into(),
+                            ));
+                        }
+                        let returns_self = matches!(
+                            method.output,
+                            ReturnType::Infallible(SuccessType::OutType(ref t @ Type::Struct(..)))
+                                if t.id() == Some(self_id)
+                        );
+                        if !returns_self {
+                            errors.push(LoweringError::Other(
+                                "Default constructor must return Self, which must be a struct"
+                                    .into(),
+                            ));
+                        }
+                        if special_method_presence.default_constructor {
+                            errors.push(LoweringError::Other(
+                                "Cannot define two default constructors on the same type".into(),
+                            ));
+                        }
+                        special_method_presence.default_constructor = true;
+                    }
                 }
             } else {
                 errors.push(LoweringError::Other(format!("Special method (type {special:?}) not allowed on non-method context {context:?}")))
@@ -564,6 +905,59 @@ impl Attrs {
                 "`namespace` can only be used on types".to_string(),
             ));
         }
+
+        if *featured && !matches!(context, AttributeContext::Type(..)) {
+            errors.push(LoweringError::Other(
+                "`featured` can only be used on types".to_string(),
+            ));
+        }
+
+        if *with_warnings && !matches!(context, AttributeContext::Method(..)) {
+            errors.push(LoweringError::Other(
+                "`with_warnings` can only be used on methods".to_string(),
+            ));
+        }
+
+        if *jni && !matches!(context, AttributeContext::Method(..)) {
+            errors.push(LoweringError::Other(
+                "`jni` can only be used on methods".to_string(),
+            ));
+        }
+
+        if *background && !matches!(context, AttributeContext::Method(..)) {
+            errors.push(LoweringError::Other(
+                "`background` can only be used on methods".to_string(),
+            ));
+        }
+
+        if !platforms.is_empty() && !matches!(context, AttributeContext::Method(..)) {
+            errors.push(LoweringError::Other(
+                "`platforms` can only be used on methods".to_string(),
+            ));
+        }
+
+        if stable_id.is_some()
+            && !matches!(
+                context,
+                AttributeContext::Type(..) | AttributeContext::Method(..)
+            )
+        {
+            errors.push(LoweringError::Other(
+                "`stable_id` can only be used on types and methods".to_string(),
+            ));
+        }
+
+        if *parent_managed && !matches!(context, AttributeContext::Type(TypeDef::Opaque(..))) {
+            errors.push(LoweringError::Other(
+                "`parent_managed` can only be used on opaque types".to_string(),
+            ));
+        }
+
+        if *non_send && !matches!(context, AttributeContext::Type(TypeDef::Opaque(..))) {
+            errors.push(LoweringError::Other(
+                "`non_send` can only be used on opaque types".to_string(),
+            ));
+        }
     }
 
     pub(crate) fn for_inheritance(&self, context: AttrInheritContext) -> Attrs {
@@ -592,6 +986,14 @@ impl Attrs {
             abi_rename: Default::default(),
             // Never inherited
             special_method: None,
+            featured: false,
+            parent_managed: false,
+            non_send: false,
+            with_warnings: false,
+            jni: false,
+            background: false,
+            platforms: Vec::new(),
+            stable_id: None,
         }
     }
 }
@@ -608,10 +1010,40 @@ pub struct BackendAttrSupport {
     pub accessors: bool,
     pub stringifiers: bool,
     pub comparators: bool,
+    /// Hash functions, marked `hash`
+    pub hashing: bool,
+    /// Functions copying an opaque, marked `clone`
+    pub cloning: bool,
+    /// Functions producing the default value of a struct, marked `default`
+    pub default_constructors: bool,
     pub memory_sharing: bool,
     pub iterators: bool,
     pub iterables: bool,
     pub indexing: bool,
+    /// Callback (`impl Fn(..)`) parameters
+    pub callbacks: bool,
+    /// `Option<T>` parameters and `DiplomatOption<T>` struct fields of primitives, enums and structs
+    pub option: bool,
+    /// Trait object (`&dyn Trait`) parameters
+    pub traits: bool,
+    /// `async fn` methods
+    pub async_methods: bool,
+    /// Methods consuming an opaque, taking `self: Box<Self>`
+    pub consuming_methods: bool,
+    /// Associated constants (`pub const` items in an `impl`)
+    pub constants: bool,
+    /// Opaques owned by a parent, marked `parent_managed`, which are never destroyed on their own
+    pub parent_managed: bool,
+    /// Opaques that are not `Send`, marked `non_send`, whose objects stay on one thread
+    pub non_send: bool,
+    /// Methods returning a value along with non-fatal warnings, marked `with_warnings`
+    pub warnings: bool,
+    /// Methods called through generated glue rather than a generic FFI layer, marked `jni`
+    pub jni: bool,
+    /// Methods run off the event loop, such as on a background isolate, marked `background`
+    pub background: bool,
+    /// Methods generated conditionally on the platform, marked `platforms(..)`
+    pub platforms: bool,
     // more to be added: namespace, etc
 }
 
@@ -628,10 +1060,25 @@ impl BackendAttrSupport {
             accessors: true,
             stringifiers: true,
             comparators: true,
+            hashing: true,
+            cloning: true,
+            default_constructors: true,
             memory_sharing: true,
             iterators: true,
             iterables: true,
             indexing: true,
+            callbacks: true,
+            option: true,
+            traits: true,
+            async_methods: true,
+            consuming_methods: true,
+            constants: true,
+            parent_managed: true,
+            non_send: true,
+            warnings: true,
+            jni: true,
+            background: true,
+            platforms: true,
         }
     }
 }
@@ -648,6 +1095,11 @@ pub trait AttributeValidator {
     fn is_name_value(&self, name: &str, value: &str) -> Result<bool, LoweringError>;
     /// What backedn attrs does this support?
     fn attrs_supported(&self) -> BackendAttrSupport;
+    /// The target triple the bindings are generated for, if only one. Methods marked
+    /// `platforms(..)` that aren't available on it are disabled.
+    fn target(&self) -> Option<&str> {
+        None
+    }
 
     /// Provided, checks if type satisfies a `DiplomatBackendAttrCfg`
     fn satisfies_cfg(&self, cfg: &DiplomatBackendAttrCfg) -> Result<bool, LoweringError> {
@@ -704,6 +1156,8 @@ pub struct BasicAttributeValidator {
     /// override is_name_value()
     #[allow(clippy::type_complexity)] // dyn fn is not that complex
     pub is_name_value: Option<Box<dyn Fn(&str, &str) -> bool>>,
+    /// The target triple the bindings are generated for, if only one
+    pub target: Option<String>,
 }
 
 impl BasicAttributeValidator {
@@ -736,10 +1190,25 @@ impl AttributeValidator for BasicAttributeValidator {
                 accessors,
                 stringifiers,
                 comparators,
+                hashing,
+                cloning,
+                default_constructors,
                 memory_sharing,
                 iterators,
                 iterables,
                 indexing,
+                callbacks,
+                option,
+                traits,
+                async_methods,
+                consuming_methods,
+                constants,
+                parent_managed,
+                non_send,
+                warnings,
+                jni,
+                background,
+                platforms,
             } = self.support;
             match value {
                 "disabling" => disabling,
@@ -751,10 +1220,25 @@ impl AttributeValidator for BasicAttributeValidator {
                 "accessors" => accessors,
                 "stringifiers" => stringifiers,
                 "comparators" => comparators,
+                "hashing" => hashing,
+                "cloning" => cloning,
+                "default_constructors" => default_constructors,
                 "memory_sharing" => memory_sharing,
                 "iterators" => iterators,
                 "iterables" => iterables,
                 "indexing" => indexing,
+                "callbacks" => callbacks,
+                "option" => option,
+                "traits" => traits,
+                "async_methods" => async_methods,
+                "consuming_methods" => consuming_methods,
+                "constants" => constants,
+                "parent_managed" => parent_managed,
+                "non_send" => non_send,
+                "warnings" => warnings,
+                "jni" => jni,
+                "background" => background,
+                "platforms" => platforms,
                 _ => {
                     return Err(LoweringError::Other(format!(
                         "Unknown supports = value found: {value}"
@@ -770,6 +1254,9 @@ impl AttributeValidator for BasicAttributeValidator {
     fn attrs_supported(&self) -> BackendAttrSupport {
         self.support
     }
+    fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
 }
 
 #[cfg(test)]
@@ -868,6 +1355,311 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_hash() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Opaque {
+                    #[diplomat::attr(*, hash)]
+                    pub fn hash_correct(&self) -> u64 {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, hash)]
+                    pub fn hash_static() -> u64 {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, hash)]
+                    pub fn hash_param(&self, seed: u64) -> u64 {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, hash)]
+                    pub fn hash_badreturn(&self) -> u32 {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_default() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                struct Config {
+                    verbose: bool,
+                }
+
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Config {
+                    #[diplomat::attr(*, default)]
+                    pub fn default_correct() -> Config {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, default)]
+                    pub fn default_self(self) -> Config {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, default)]
+                    pub fn default_param(verbose: bool) -> Config {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, default)]
+                    pub fn default_fallible() -> Result<Config, ()> {
+                        todo!()
+                    }
+                }
+
+                impl Opaque {
+                    #[diplomat::attr(*, default)]
+                    pub fn default_opaque() -> Box<Opaque> {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_warnings() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Warnings;
+
+                #[diplomat::out]
+                struct Parsed {
+                    value: u32,
+                    warnings: Box<Warnings>,
+                }
+
+                #[diplomat::out]
+                struct Unrelated {
+                    value: u32,
+                }
+
+                #[diplomat::opaque]
+                struct Parser;
+
+                impl Parser {
+                    #[diplomat::attr(*, with_warnings)]
+                    pub fn parse(&self) -> Parsed {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, with_warnings)]
+                    pub fn try_parse(&self) -> Result<Parsed, ()> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, with_warnings)]
+                    pub fn parse_unrelated(&self) -> Unrelated {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, with_warnings)]
+                    pub fn parse_int(&self) -> u32 {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_jni() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, jni)]
+                struct Counter(u32);
+
+                impl Counter {
+                    #[diplomat::attr(*, jni)]
+                    pub fn count(&self) -> u32 {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, jni = "fast")]
+                    pub fn reset(&self) {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_background() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, background)]
+                struct Segmenter;
+
+                impl Segmenter {
+                    #[diplomat::attr(*, background)]
+                    pub fn train(&self, corpus: &DiplomatStr) -> u32 {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, background = "pool")]
+                    pub fn reset(&self) {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_platforms() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, platforms(linux))]
+                struct Storage;
+
+                impl Storage {
+                    #[diplomat::attr(*, platforms(linux, macos, windows))]
+                    pub fn open(path: &DiplomatStr) -> Box<Storage> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, platforms(linux, plan9))]
+                    pub fn sync(&self) {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, platforms = "linux")]
+                    pub fn flush(&self) {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_platforms_of_target() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Storage;
+
+                impl Storage {
+                    #[diplomat::attr(*, platforms(linux, macos, windows))]
+                    pub fn open(path: &DiplomatStr) -> Box<Storage> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, platforms(wasm))]
+                    pub fn open_in_memory() -> Box<Storage> {
+                        todo!()
+                    }
+                    pub fn len(&self) -> usize {
+                        todo!()
+                    }
+                }
+            }
+        };
+        let custom_types = crate::ast::File::from(&parsed);
+        let env = custom_types.all_types();
+        let disabled = |target: Option<&str>| {
+            // No support: methods limited to platforms need a single target
+            let mut attr_validator = hir::BasicAttributeValidator::new("tests");
+            attr_validator.support.disabling = true;
+            attr_validator.target = target.map(Into::into);
+            let tcx = hir::TypeContext::from_ast(&env, attr_validator).unwrap();
+            let storage = &tcx.opaques()[0];
+            storage
+                .methods
+                .iter()
+                .filter(|m| m.attrs.disable)
+                .map(|m| m.name.as_str().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(disabled(Some("wasm32-unknown-unknown")), ["open"]);
+        assert_eq!(disabled(Some("x86_64-pc-windows-msvc")), ["open_in_memory"]);
+        assert_eq!(
+            disabled(Some("aarch64-linux-android")),
+            ["open", "open_in_memory"]
+        );
+    }
+
+    #[test]
+    fn test_is_platform_of_target() {
+        use hir::is_platform_of_target;
+        for (platform, triple) in [
+            ("linux", "x86_64-unknown-linux-gnu"),
+            ("linux", "armv7-unknown-linux-gnueabihf"),
+            ("macos", "aarch64-apple-darwin"),
+            ("ios", "aarch64-apple-ios-sim"),
+            ("android", "aarch64-linux-android"),
+            ("windows", "x86_64-pc-windows-msvc"),
+            ("freebsd", "x86_64-unknown-freebsd"),
+            ("wasm", "wasm32-unknown-unknown"),
+            ("wasm", "wasm32-wasip1"),
+        ] {
+            assert!(
+                is_platform_of_target(platform, triple),
+                "{platform} {triple}"
+            );
+        }
+        for (platform, triple) in [
+            ("linux", "aarch64-linux-android"),
+            ("linux", "wasm32-unknown-unknown"),
+            ("macos", "aarch64-apple-ios"),
+            ("wasm", "x86_64-unknown-linux-gnu"),
+            ("windows", "thumbv7em-none-eabihf"),
+        ] {
+            assert!(
+                !is_platform_of_target(platform, triple),
+                "{platform} {triple}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_clone() {
+        uitest_lowering_attr! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                #[diplomat::opaque]
+                struct Other;
+
+                impl Opaque {
+                    #[diplomat::attr(*, clone)]
+                    pub fn clone_correct(&self) -> Box<Opaque> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, clone)]
+                    pub fn clone_mut(&mut self) -> Box<Opaque> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, clone)]
+                    pub fn clone_static() -> Box<Opaque> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, clone)]
+                    pub fn clone_othertype(&self) -> Box<Other> {
+                        todo!()
+                    }
+                    #[diplomat::attr(*, clone)]
+                    pub fn clone_optional(&self) -> Option<Box<Opaque>> {
+                        todo!()
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_iterator() {
         uitest_lowering_attr! {
@@ -2,9 +2,10 @@
 
 use super::lifetimes::LifetimeEnv;
 use super::{
-    Attrs, Everywhere, IdentBuf, Method, OutputOnly, SpecialMethodPresence, TyPosition, Type,
+    Attrs, Callback, Everywhere, IdentBuf, Method, OutputOnly, SpecialMethodPresence, TyPosition,
+    Type,
 };
-use crate::ast::Docs;
+use crate::ast::{Docs, SourceSpan};
 
 #[non_exhaustive]
 pub enum ReturnableStructDef<'tcx> {
@@ -35,6 +36,12 @@ pub struct StructDef<P: TyPosition = Everywhere> {
     pub attrs: Attrs,
     pub lifetimes: LifetimeEnv,
     pub special_method_presence: SpecialMethodPresence,
+    /// Whether this struct groups the parameters of a method marked `#[diplomat::bundle]`.
+    ///
+    /// Its fields are primitives and enums, which backends can give default values.
+    pub is_bundle: bool,
+    /// Where the type is declared, for diagnostics.
+    pub span: Option<SourceSpan>,
 }
 
 /// A struct whose contents are opaque across the FFI boundary, and can only
@@ -54,6 +61,8 @@ pub struct OpaqueDef {
     pub attrs: Attrs,
     pub lifetimes: LifetimeEnv,
     pub special_method_presence: SpecialMethodPresence,
+    /// Where the type is declared, for diagnostics.
+    pub span: Option<SourceSpan>,
 }
 
 /// The enum type.
@@ -66,6 +75,34 @@ pub struct EnumDef {
     pub methods: Vec<Method>,
     pub attrs: Attrs,
     pub special_method_presence: SpecialMethodPresence,
+    /// Whether the Rust enum is `#[non_exhaustive]`, so that values returned
+    /// by a newer version of the library may not correspond to any of `variants`.
+    pub is_non_exhaustive: bool,
+    /// Where the type is declared, for diagnostics.
+    pub span: Option<SourceSpan>,
+}
+
+/// A trait that foreign code can implement, to be passed to methods as `&dyn Trait`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TraitDef {
+    pub docs: Docs,
+    pub name: IdentBuf,
+    pub methods: Vec<TraitMethod>,
+    pub attrs: Attrs,
+    /// Where the trait is declared, for diagnostics.
+    pub span: Option<SourceSpan>,
+}
+
+/// A method of a [`TraitDef`], taking `&self`.
+///
+/// Like callbacks, trait methods can only take and return primitives.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TraitMethod {
+    pub docs: Docs,
+    pub name: IdentBuf,
+    pub signature: Callback,
 }
 
 /// A field on a [`OutStruct`]s.
@@ -91,6 +128,7 @@ pub struct EnumVariant {
 }
 
 impl<P: TyPosition> StructDef<P> {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         docs: Docs,
         name: IdentBuf,
@@ -99,6 +137,8 @@ impl<P: TyPosition> StructDef<P> {
         attrs: Attrs,
         lifetimes: LifetimeEnv,
         special_method_presence: SpecialMethodPresence,
+        is_bundle: bool,
+        span: Option<SourceSpan>,
     ) -> Self {
         Self {
             docs,
@@ -108,6 +148,8 @@ impl<P: TyPosition> StructDef<P> {
             attrs,
             lifetimes,
             special_method_presence,
+            is_bundle,
+            span,
         }
     }
 }
@@ -120,6 +162,7 @@ impl OpaqueDef {
         attrs: Attrs,
         lifetimes: LifetimeEnv,
         special_method_presence: SpecialMethodPresence,
+        span: Option<SourceSpan>,
     ) -> Self {
         Self {
             docs,
@@ -128,11 +171,13 @@ impl OpaqueDef {
             attrs,
             lifetimes,
             special_method_presence,
+            span,
         }
     }
 }
 
 impl EnumDef {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         docs: Docs,
         name: IdentBuf,
@@ -140,6 +185,8 @@ impl EnumDef {
         methods: Vec<Method>,
         attrs: Attrs,
         special_method_presence: SpecialMethodPresence,
+        is_non_exhaustive: bool,
+        span: Option<SourceSpan>,
     ) -> Self {
         Self {
             docs,
@@ -148,6 +195,36 @@ impl EnumDef {
             methods,
             attrs,
             special_method_presence,
+            is_non_exhaustive,
+            span,
+        }
+    }
+}
+
+impl TraitDef {
+    pub(super) fn new(
+        docs: Docs,
+        name: IdentBuf,
+        methods: Vec<TraitMethod>,
+        attrs: Attrs,
+        span: Option<SourceSpan>,
+    ) -> Self {
+        Self {
+            docs,
+            name,
+            methods,
+            attrs,
+            span,
+        }
+    }
+}
+
+impl TraitMethod {
+    pub(super) fn new(docs: Docs, name: IdentBuf, signature: Callback) -> Self {
+        Self {
+            docs,
+            name,
+            signature,
         }
     }
 }
@@ -212,6 +289,15 @@ impl<'tcx> TypeDef<'tcx> {
         }
     }
 
+    pub fn span(&self) -> Option<SourceSpan> {
+        match *self {
+            Self::Struct(ty) => ty.span,
+            Self::OutStruct(ty) => ty.span,
+            Self::Opaque(ty) => ty.span,
+            Self::Enum(ty) => ty.span,
+        }
+    }
+
     pub fn special_method_presence(&self) -> &'tcx SpecialMethodPresence {
         match *self {
             Self::Struct(ty) => &ty.special_method_presence,
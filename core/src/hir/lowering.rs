@@ -1,11 +1,12 @@
 use super::{
-    AttributeContext, AttributeValidator, Attrs, Borrow, BoundedLifetime, EnumDef, EnumPath,
-    EnumVariant, IdentBuf, IntType, Lifetime, LifetimeEnv, LifetimeLowerer, LookupId, MaybeOwn,
-    Method, NonOptional, OpaqueDef, OpaquePath, Optional, OutStructDef, OutStructField,
+    AttributeContext, AttributeValidator, Attrs, Borrow, BoundedLifetime, Callback, EnumDef,
+    EnumPath, EnumVariant, IdentBuf, IntType, Lifetime, LifetimeEnv, LifetimeLowerer, LookupId,
+    MaybeOwn, Method, NonOptional, OpaqueDef, OpaquePath, Optional, OutStructDef, OutStructField,
     OutStructPath, OutType, Param, ParamLifetimeLowerer, ParamSelf, PrimitiveType,
-    ReturnLifetimeLowerer, ReturnType, ReturnableStructPath, SelfParamLifetimeLowerer, SelfType,
-    Slice, SpecialMethod, SpecialMethodPresence, StructDef, StructField, StructPath, SuccessType,
-    Type, TypeDef, TypeId,
+    RequiredEncoding, ReturnLifetimeLowerer, ReturnType, ReturnableStructPath,
+    SelfParamLifetimeLowerer, SelfType, Slice, SpecialMethod, SpecialMethodPresence,
+    StringEncoding, StructDef, StructField, StructPath, SuccessType, TraitDef, TraitMethod,
+    TyPosition, Type, TypeDef, TypeId,
 };
 use crate::ast::attrs::AttrInheritContext;
 use crate::{ast, Env};
@@ -183,6 +184,56 @@ impl<'ast> LoweringContext<'ast> {
         self.lower_all(ast_defs, Self::lower_opaque)
     }
 
+    pub(super) fn lower_all_traits(
+        &mut self,
+        ast_defs: impl Iterator<Item = (&'ast ast::Trait, Attrs)>,
+    ) -> Result<Vec<TraitDef>, ()> {
+        let mut traits = Ok(Vec::new());
+        for (ast_trait, parent_attrs) in ast_defs {
+            match (self.lower_trait(ast_trait, &parent_attrs), &mut traits) {
+                (Ok(trt), Ok(traits)) => traits.push(trt),
+                _ => traits = Err(()),
+            }
+        }
+        traits
+    }
+
+    fn lower_trait(
+        &mut self,
+        ast_trait: &'ast ast::Trait,
+        parent_attrs: &Attrs,
+    ) -> Result<TraitDef, ()> {
+        self.errors.set_item(ast_trait.name.as_str());
+        let name = self.lower_ident(&ast_trait.name, "trait name");
+        let attrs =
+            self.attr_validator
+                .attr_from_ast(&ast_trait.attrs, parent_attrs, &mut self.errors);
+
+        let mut methods = Ok(Vec::with_capacity(ast_trait.methods.len()));
+        for method in &ast_trait.methods {
+            self.errors.set_subitem(method.name.as_str());
+            let name = self.lower_ident(&method.name, "trait method name");
+            let signature = self.lower_signature(
+                method.params.iter().map(|param| &param.ty),
+                method.output.as_ref(),
+            );
+            match (name, signature, &mut methods) {
+                (Ok(name), Ok(signature), Ok(methods)) => {
+                    methods.push(TraitMethod::new(method.docs.clone(), name, signature))
+                }
+                _ => methods = Err(()),
+            }
+        }
+
+        Ok(TraitDef::new(
+            ast_trait.docs.clone(),
+            name?,
+            methods?,
+            attrs,
+            ast_trait.span,
+        ))
+    }
+
     fn lower_enum(&mut self, item: ItemAndInfo<'ast, ast::Enum>) -> Result<EnumDef, ()> {
         let ast_enum = item.item;
         self.errors.set_item(ast_enum.name.as_str());
@@ -239,6 +290,8 @@ impl<'ast> LoweringContext<'ast> {
             methods,
             attrs,
             special_method_presence,
+            ast_enum.is_non_exhaustive,
+            ast_enum.span,
         );
 
         self.attr_validator.validate(
@@ -284,6 +337,7 @@ impl<'ast> LoweringContext<'ast> {
             attrs,
             lifetimes?,
             special_method_presence,
+            ast_opaque.span,
         );
         self.attr_validator.validate(
             &def.attrs,
@@ -328,6 +382,12 @@ impl<'ast> LoweringContext<'ast> {
             &item.ty_parent_attrs,
             &mut self.errors,
         );
+        let has_option_fields = fields.as_ref().map_or(false, |fields| {
+            fields
+                .iter()
+                .any(|field| matches!(field.ty, Type::DiplomatOption(_)))
+        });
+        self.check_option_fields(&attrs, has_option_fields)?;
         let lifetimes = self.lower_type_lifetime_env(&ast_struct.lifetimes);
 
         let mut special_method_presence = SpecialMethodPresence::default();
@@ -350,6 +410,8 @@ impl<'ast> LoweringContext<'ast> {
             attrs,
             lifetimes?,
             special_method_presence,
+            ast_struct.bundle.is_some(),
+            ast_struct.span,
         );
 
         self.attr_validator.validate(
@@ -399,6 +461,12 @@ impl<'ast> LoweringContext<'ast> {
             &item.ty_parent_attrs,
             &mut self.errors,
         );
+        let has_option_fields = fields.as_ref().map_or(false, |fields| {
+            fields
+                .iter()
+                .any(|field| matches!(field.ty, Type::DiplomatOption(_)))
+        });
+        self.check_option_fields(&attrs, has_option_fields)?;
         let mut special_method_presence = SpecialMethodPresence::default();
         let methods = if attrs.disable {
             Vec::new()
@@ -421,6 +489,8 @@ impl<'ast> LoweringContext<'ast> {
             attrs,
             lifetimes?,
             special_method_presence,
+            false,
+            ast_out_struct.span,
         );
 
         self.attr_validator.validate(
@@ -431,6 +501,22 @@ impl<'ast> LoweringContext<'ast> {
         Ok(def)
     }
 
+    /// Checks that the backend supports the `DiplomatOption<T>` fields of a struct, unless the
+    /// struct is disabled.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `Err` is returned.
+    fn check_option_fields(&mut self, attrs: &Attrs, has_option_fields: bool) -> Result<(), ()> {
+        if has_option_fields && !attrs.disable && !self.attr_validator.attrs_supported().option {
+            self.errors.push(LoweringError::Other(format!(
+                "DiplomatOption<T> fields are not supported by the {} backend, \
+                 disable the struct with #[diplomat::attr(not(supports = option), disable)]",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+        Ok(())
+    }
+
     /// Lowers an [`ast::Method`]s an [`hir::Method`].
     ///
     /// If there are any errors, they're pushed to `errors` and `None` is returned.
@@ -450,6 +536,10 @@ impl<'ast> LoweringContext<'ast> {
             _ => (&method.params[..], false),
         };
 
+        if let Some(bundle) = &method.bundle {
+            self.check_bundle(method, bundle, in_path)?;
+        }
+
         let self_param_ltl = SelfParamLifetimeLowerer::new(&method.lifetime_env, self)?;
 
         let (param_self, param_ltl) = if let Some(self_param) = method.self_param.as_ref() {
@@ -469,10 +559,44 @@ impl<'ast> LoweringContext<'ast> {
             in_path,
         )?;
 
+        if method.is_async {
+            self.check_async_method(method)?;
+        }
+        if method.constant.is_some() {
+            self.check_constant(method, &output)?;
+        }
+
         let attrs =
             self.attr_validator
                 .attr_from_ast(&method.attrs, method_parent_attrs, &mut self.errors);
 
+        // A method the backend leaves out may use callbacks it can't bind
+        if !attrs.disable
+            && !self.attr_validator.attrs_supported().callbacks
+            && params.iter().any(|p| matches!(p.ty, Type::Callback(_)))
+        {
+            self.errors.push(LoweringError::Other(format!(
+                "Callback parameters are not supported by the {} backend, \
+                 disable the method with #[diplomat::attr(not(supports = callbacks), disable)]",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+
+        if !attrs.disable
+            && !self.attr_validator.attrs_supported().option
+            && params
+                .iter()
+                .any(|p| matches!(p.ty, Type::DiplomatOption(_)))
+        {
+            self.errors.push(LoweringError::Other(format!(
+                "Option<T> parameters of primitives, enums and structs are not supported by the {} backend, \
+                 disable the method with #[diplomat::attr(not(supports = option), disable)]",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+
         let hir_method = Method {
             docs: method.docs.clone(),
             name: name?,
@@ -480,7 +604,10 @@ impl<'ast> LoweringContext<'ast> {
             param_self,
             params,
             output,
+            is_async: method.is_async,
+            constant: method.constant.clone(),
             attrs,
+            span: method.span,
         };
 
         self.attr_validator.validate(
@@ -503,6 +630,125 @@ impl<'ast> LoweringContext<'ast> {
         Ok(hir_method)
     }
 
+    /// Checks that the parameters grouped by `#[diplomat::bundle]` exist, are listed once, and are
+    /// primitives or enums, so the struct grouping them has fields with default values and
+    /// without lifetimes.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `Err` is returned.
+    fn check_bundle(
+        &mut self,
+        method: &ast::Method,
+        bundle: &ast::ParamBundle,
+        in_path: &ast::Path,
+    ) -> Result<(), ()> {
+        let mut result = Ok(());
+        if bundle.fields.is_empty() {
+            self.errors.push(LoweringError::Other(format!(
+                "Bundle `{}` does not list any parameters",
+                bundle.name
+            )));
+            result = Err(());
+        }
+        for (i, name) in bundle.fields.iter().enumerate() {
+            if bundle.fields[..i].contains(name) {
+                self.errors.push(LoweringError::Other(format!(
+                    "Parameter `{name}` is listed more than once in bundle `{}`",
+                    bundle.name
+                )));
+                result = Err(());
+                continue;
+            }
+            let Some(param) = bundle.rust_params.iter().find(|p| &p.name == name) else {
+                self.errors.push(LoweringError::Other(format!(
+                    "Bundle `{}` lists `{name}`, which is not a parameter of the method",
+                    bundle.name
+                )));
+                result = Err(());
+                continue;
+            };
+            let bundleable = match &param.ty {
+                ast::TypeName::Primitive(_) => true,
+                ast::TypeName::Named(path) => {
+                    matches!(path.resolve(in_path, self.env), ast::CustomType::Enum(_))
+                }
+                _ => false,
+            };
+            if !bundleable {
+                self.errors.push(LoweringError::Other(format!(
+                    "Parameter `{name}` of bundle `{}` is a {}, only primitives and enums can be bundled",
+                    bundle.name, param.ty
+                )));
+                result = Err(());
+            }
+        }
+        let bundle_path = ast::PathType::new(ast::Path::empty().sub_path(bundle.name.clone()));
+        match bundle_path.resolve(in_path, self.env) {
+            ast::CustomType::Struct(strct)
+                if strct.bundle.as_ref() == Some(&method.full_path_name) => {}
+            _ => {
+                self.errors.push(LoweringError::Other(format!(
+                    "Bundle `{}` has the name of another type",
+                    bundle.name
+                )));
+                result = Err(());
+            }
+        }
+        result
+    }
+
+    /// Checks that an `async fn` method is supported by the backend, and only takes and
+    /// returns primitives (besides `self`), which its future can hold on to.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `Err` is returned.
+    fn check_async_method(&mut self, method: &ast::Method) -> Result<(), ()> {
+        if !self.attr_validator.attrs_supported().async_methods {
+            self.errors.push(LoweringError::Other(format!(
+                "Async methods are not supported by the {} backend",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+        if !method.lifetime_env.is_empty() {
+            self.errors.push(LoweringError::Other(
+                "Async methods cannot have named lifetimes".into(),
+            ));
+            return Err(());
+        }
+        let mut result = Ok(());
+        let params = method.params.iter().map(|param| &param.ty);
+        for ty in params.chain(method.return_type.as_ref()) {
+            if !matches!(ty, ast::TypeName::Primitive(_) | ast::TypeName::Unit) {
+                self.errors.push(LoweringError::Other(format!(
+                    "Async methods can only take and return primitives, found {ty}"
+                )));
+                result = Err(());
+            }
+        }
+        result
+    }
+
+    /// Checks that an associated constant is supported by the backend, and has a type its
+    /// method can return as is.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `Err` is returned.
+    fn check_constant(&mut self, method: &ast::Method, output: &ReturnType) -> Result<(), ()> {
+        if !matches!(output, ReturnType::Infallible(SuccessType::OutType(_))) {
+            self.errors.push(LoweringError::Other(format!(
+                "Associated constants cannot be results, options or the unit type, found {}",
+                method.return_type.as_ref().unwrap_or(&ast::TypeName::Unit)
+            )));
+            return Err(());
+        }
+        if !self.attr_validator.attrs_supported().constants {
+            self.errors.push(LoweringError::Other(format!(
+                "Associated constants are not supported by the {} backend",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+        Ok(())
+    }
+
     /// Lowers many [`ast::Method`]s into a vector of [`hir::Method`]s.
     ///
     /// If there are any errors, they're pushed to `errors` and `None` is returned.
@@ -668,11 +914,16 @@ impl<'ast> LoweringContext<'ast> {
                         Err(())
                     }
                     _ => {
-                        self.errors.push(LoweringError::Other(format!("found Option<T> in input, where T isn't a reference but Option<T> in inputs requires that T is a reference to an opaque. T = {opt_ty}")));
+                        self.errors.push(LoweringError::Other(format!("found Option<T> in input, where T isn't a reference but Option<T> is only passed by value as a parameter, use DiplomatOption<T> in struct fields. T = {opt_ty}")));
                         Err(())
                     }
                 }
             }
+            ast::TypeName::DiplomatOption(opt_ty) => {
+                let ty = self.lower_type(opt_ty, ltl, in_path)?;
+                self.check_option_value(&ty, opt_ty)?;
+                Ok(Type::DiplomatOption(Box::new(ty)))
+            }
             ast::TypeName::Result(_, _, _) => {
                 self.errors.push(LoweringError::Other(
                     "Results can only appear as the top-level return type of methods".into(),
@@ -699,6 +950,18 @@ impl<'ast> LoweringContext<'ast> {
                 self.errors.push(LoweringError::Other("Unit types can only appear as the return value of a method, or as the Ok/Err variants of a returned result".into()));
                 Err(())
             }
+            ast::TypeName::Function(..) => {
+                self.errors.push(LoweringError::Other(
+                    "Callbacks can only appear as parameters of a method".into(),
+                ));
+                Err(())
+            }
+            ast::TypeName::DynTrait(..) => {
+                self.errors.push(LoweringError::Other(
+                    "Trait objects can only appear as parameters of a method".into(),
+                ));
+                Err(())
+            }
         }
     }
 
@@ -894,6 +1157,18 @@ impl<'ast> LoweringContext<'ast> {
                     Err(())
                 }
             },
+            ast::TypeName::DiplomatOption(opt_ty) if in_struct => {
+                let ty = self.lower_out_type(opt_ty, ltl, in_path, in_struct)?;
+                self.check_option_value(&ty, opt_ty)?;
+                Ok(OutType::DiplomatOption(Box::new(ty)))
+            }
+            ast::TypeName::DiplomatOption(_) => {
+                self.errors.push(LoweringError::Other(
+                    "DiplomatOption<T> can only appear in struct fields, return Option<T> instead"
+                        .into(),
+                ));
+                Err(())
+            }
             ast::TypeName::Result(_, _, _) => {
                 self.errors.push(LoweringError::Other(
                     "Results can only appear as the top-level return type of methods".into(),
@@ -925,6 +1200,18 @@ impl<'ast> LoweringContext<'ast> {
                 self.errors.push(LoweringError::Other("Unit types can only appear as the return value of a method, or as the Ok/Err variants of a returned result".into()));
                 Err(())
             }
+            ast::TypeName::Function(..) => {
+                self.errors.push(LoweringError::Other(
+                    "Callbacks can only appear as parameters of a method".into(),
+                ));
+                Err(())
+            }
+            ast::TypeName::DynTrait(..) => {
+                self.errors.push(LoweringError::Other(
+                    "Trait objects can only appear as parameters of a method".into(),
+                ));
+                Err(())
+            }
         }
     }
 
@@ -938,7 +1225,16 @@ impl<'ast> LoweringContext<'ast> {
         method_full_path: &ast::Ident, // for better error msg
         in_path: &ast::Path,
     ) -> Result<(ParamSelf, ParamLifetimeLowerer<'ast>), ()> {
-        match self_param.path_type.resolve(in_path, self.env) {
+        let resolved = self_param.path_type.resolve(in_path, self.env);
+        if self_param.boxed {
+            return self.lower_boxed_self_param(
+                resolved,
+                self_param,
+                self_param_ltl,
+                method_full_path,
+            );
+        }
+        match resolved {
             ast::CustomType::Struct(strct) => {
                 if let Some(tcx_id) = self.lookup_id.resolve_struct(strct) {
                     if self_param.reference.is_some() {
@@ -1019,6 +1315,50 @@ impl<'ast> LoweringContext<'ast> {
         }
     }
 
+    /// Lowers a `self: Box<Self>` parameter, which hands a boxed opaque back to Rust.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `Err` is returned.
+    fn lower_boxed_self_param(
+        &mut self,
+        resolved: &'ast ast::CustomType,
+        self_param: &ast::SelfParam,
+        self_param_ltl: SelfParamLifetimeLowerer<'ast>,
+        method_full_path: &ast::Ident,
+    ) -> Result<(ParamSelf, ParamLifetimeLowerer<'ast>), ()> {
+        let ast::CustomType::Opaque(opaque) = resolved else {
+            self.errors.push(LoweringError::Other(format!("Method `{method_full_path}` takes `self: Box<Self>` on a type that isn't opaque, only opaques can be boxed")));
+            return Err(());
+        };
+        if !self.attr_validator.attrs_supported().consuming_methods {
+            self.errors.push(LoweringError::Other(format!(
+                "Consuming methods are not supported by the {} backend",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+        if !opaque.lifetimes.is_empty() {
+            self.errors.push(LoweringError::Other(format!("Method `{method_full_path}` consumes an opaque with lifetimes, which isn't supported")));
+            return Err(());
+        }
+        let tcx_id = self
+            .lookup_id
+            .resolve_opaque(opaque)
+            .expect("opaque is in env");
+        let mut param_ltl = self_param_ltl.no_self_ref();
+        let lifetimes =
+            param_ltl.lower_generics(&self_param.path_type.lifetimes, &opaque.lifetimes, true);
+
+        Ok((
+            ParamSelf::new(SelfType::BoxedOpaque(OpaquePath::new(
+                lifetimes,
+                NonOptional,
+                MaybeOwn::Own,
+                tcx_id,
+            ))),
+            param_ltl,
+        ))
+    }
+
     /// Lowers an [`ast::Param`] into an [`hir::Param`].
     ///
     /// If there are any errors, they're pushed to `errors` and `None` is returned.
@@ -1032,9 +1372,134 @@ impl<'ast> LoweringContext<'ast> {
         in_path: &ast::Path,
     ) -> Result<Param, ()> {
         let name = self.lower_ident(&param.name, "param name");
-        let ty = self.lower_type(&param.ty, ltl, in_path);
+        let ty = match &param.ty {
+            ast::TypeName::Function(params, output) => {
+                self.lower_callback(params, output).map(Type::Callback)
+            }
+            ast::TypeName::DynTrait(path) => self.lower_dyn_trait(path, in_path),
+            // Passed as a `DiplomatOption<T>`, unlike the nullable pointers of `Option<&T>`
+            ast::TypeName::Option(opt_ty) if !opt_ty.is_pointer() => {
+                self.lower_type(opt_ty, ltl, in_path).and_then(|ty| {
+                    self.check_option_value(&ty, opt_ty)?;
+                    Ok(Type::DiplomatOption(Box::new(ty)))
+                })
+            }
+            _ => self.lower_type(&param.ty, ltl, in_path),
+        };
+        let ty = ty?;
+
+        if let Some(encoding) = param.encoding {
+            let compatible = matches!(
+                (&ty, encoding),
+                (
+                    Type::Slice(Slice::Str(_, StringEncoding::UnvalidatedUtf16)),
+                    RequiredEncoding::Utf16,
+                ) | (
+                    Type::Slice(Slice::Str(
+                        _,
+                        StringEncoding::UnvalidatedUtf8 | StringEncoding::Utf8,
+                    )),
+                    RequiredEncoding::Utf8 | RequiredEncoding::Ascii,
+                )
+            );
+            if !compatible {
+                self.errors.push(LoweringError::Other(format!(
+                    "Parameter `{}` cannot be required to be {encoding:?}: \
+                     #[diplomat::encoding] must match the parameter's string type",
+                    param.name
+                )));
+                return Err(());
+            }
+        }
+
+        Ok(Param::new(name?, ty, param.encoding))
+    }
+
+    /// Lowers the parameter and return types of an `impl Fn(..)` parameter into a [`hir::Callback`].
+    ///
+    /// Whether the backend supports callbacks is checked by [`Self::lower_method`], once it is
+    /// known whether the method is disabled.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `None` is returned.
+    fn lower_callback(
+        &mut self,
+        params: &[Box<ast::TypeName>],
+        output: &ast::TypeName,
+    ) -> Result<Callback, ()> {
+        self.lower_signature(params.iter().map(|param| &**param), Some(output))
+    }
+
+    /// Lowers a `&dyn Trait` parameter.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `None` is returned.
+    fn lower_dyn_trait(&mut self, path: &ast::PathType, in_path: &ast::Path) -> Result<Type, ()> {
+        if !self.attr_validator.attrs_supported().traits {
+            self.errors.push(LoweringError::Other(format!(
+                "Trait object parameters are not supported by the {} backend",
+                self.attr_validator.primary_name()
+            )));
+            return Err(());
+        }
+        let trt = path.resolve_trait(in_path, self.env);
+        let id = self
+            .lookup_id
+            .resolve_trait(trt)
+            .expect("can't find trait in lookup map, which contains all traits from env");
+        Ok(Type::DynTrait(id))
+    }
 
-        Ok(Param::new(name?, ty?))
+    /// Checks that the value of an `Option<T>` parameter or `DiplomatOption<T>` struct field is
+    /// a primitive, enum, or struct without lifetimes, which can be passed by value next to the
+    /// flag telling whether it is set.
+    ///
+    /// Whether the backend supports them is checked once it is known whether the method or
+    /// struct is disabled.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `Err` is returned.
+    fn check_option_value<P: TyPosition>(
+        &mut self,
+        ty: &Type<P>,
+        ast_ty: &ast::TypeName,
+    ) -> Result<(), ()> {
+        match ty {
+            Type::Primitive(_) | Type::Enum(_) => Ok(()),
+            Type::Struct(_) if ty.lifetimes().next().is_none() => Ok(()),
+            _ => {
+                self.errors.push(LoweringError::Other(format!(
+                    "found Option<T> passed by value where T isn't a primitive, enum, or struct without lifetimes. T = {ast_ty}"
+                )));
+                Err(())
+            }
+        }
+    }
+
+    /// Lowers the signature of a callback or trait method, which can only use primitives.
+    ///
+    /// If there are any errors, they're pushed to `errors` and `None` is returned.
+    fn lower_signature<'a>(
+        &mut self,
+        params: impl Iterator<Item = &'a ast::TypeName>,
+        output: Option<&ast::TypeName>,
+    ) -> Result<Callback, ()> {
+        let mut lower_primitive = |ty: &ast::TypeName| match ty {
+            ast::TypeName::Primitive(prim) => Ok(PrimitiveType::from_ast(*prim)),
+            _ => {
+                self.errors.push(LoweringError::Other(format!(
+                    "Callbacks and trait methods can only take and return primitives, found {ty}"
+                )));
+                Err(())
+            }
+        };
+        let params = params.map(&mut lower_primitive).collect::<Vec<_>>();
+        let output = match output {
+            None | Some(ast::TypeName::Unit) => Ok(None),
+            Some(output) => lower_primitive(output).map(Some),
+        };
+
+        Ok(Callback {
+            params: params.into_iter().collect::<Result<_, _>>()?,
+            output: output?,
+        })
     }
 
     /// Lowers many [`ast::Param`]s into a vector of [`hir::Param`]s.
@@ -3,7 +3,10 @@
 use std::collections::BTreeSet;
 use std::ops::Deref;
 
-use super::{Attrs, Docs, Ident, IdentBuf, OutType, SelfType, Type, TypeContext};
+use super::{
+    Attrs, ConstValue, Docs, Ident, IdentBuf, OutStructDef, OutStructField, OutType,
+    RequiredEncoding, ReturnableStructPath, SelfType, SourceSpan, Type, TypeContext,
+};
 
 use super::lifetimes::{Lifetime, LifetimeEnv, Lifetimes, MaybeStatic};
 
@@ -24,7 +27,14 @@ pub struct Method {
     pub param_self: Option<ParamSelf>,
     pub params: Vec<Param>,
     pub output: ReturnType,
+    /// Whether this is an `async fn`, whose future is polled from foreign code.
+    /// `output` is the output of the future.
+    pub is_async: bool,
+    /// The value of an associated constant, which this method takes no parameters and returns.
+    pub constant: Option<ConstValue>,
     pub attrs: Attrs,
+    /// Where the method is declared, for diagnostics.
+    pub span: Option<SourceSpan>,
 }
 
 /// Type that the method returns.
@@ -58,6 +68,9 @@ pub struct ParamSelf {
 pub struct Param {
     pub name: IdentBuf,
     pub ty: Type,
+    /// The encoding the string passed for this parameter is required to be in,
+    /// if the method declares one with `#[diplomat::encoding(...)]`.
+    pub encoding: Option<RequiredEncoding>,
 }
 
 impl SuccessType {
@@ -170,18 +183,63 @@ impl ParamSelf {
                 let inner = field.ty.field_leaf_lifetime_counts(tcx);
                 (acc.0 + inner.0, acc.1 + inner.1)
             }),
-            SelfType::Enum(_) => (0, 0),
+            SelfType::Enum(_) | SelfType::BoxedOpaque(_) => (0, 0),
         }
     }
 }
 
 impl Param {
-    pub(super) fn new(name: IdentBuf, ty: Type) -> Self {
-        Self { name, ty }
+    pub(super) fn new(name: IdentBuf, ty: Type, encoding: Option<RequiredEncoding>) -> Self {
+        Self { name, ty, encoding }
     }
 }
 
+/// The output of a method marked `with_warnings`: a value along with a list of
+/// non-fatal warnings, returned as the fields of an out struct.
+///
+/// Backends can surface the two parts idiomatically, e.g. as a tuple, instead of
+/// as the struct Rust returns.
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub struct WithWarnings<'tcx> {
+    /// The out struct returned by the method
+    pub def: &'tcx OutStructDef,
+    /// The `value` field
+    pub value: &'tcx OutStructField,
+    /// The `warnings` field
+    pub warnings: &'tcx OutStructField,
+}
+
 impl Method {
+    /// If this method is marked `with_warnings`, returns the value and warnings it returns.
+    ///
+    /// This is `None` for methods whose output does not have the required shape, which
+    /// is a lowering error.
+    pub fn with_warnings<'tcx>(&self, tcx: &'tcx TypeContext) -> Option<WithWarnings<'tcx>> {
+        if !self.attrs.with_warnings {
+            return None;
+        }
+        let SuccessType::OutType(Type::Struct(ReturnableStructPath::OutStruct(path))) =
+            self.output.success_type()
+        else {
+            return None;
+        };
+        let def = path.resolve(tcx);
+        let [a, b] = &def.fields[..] else {
+            return None;
+        };
+        let (value, warnings) = match (a.name.as_str(), b.name.as_str()) {
+            ("value", "warnings") => (a, b),
+            ("warnings", "value") => (b, a),
+            _ => return None,
+        };
+        Some(WithWarnings {
+            def,
+            value,
+            warnings,
+        })
+    }
+
     /// Returns a fresh [`Lifetimes`] corresponding to `self`.
     pub fn method_lifetimes(&self) -> Lifetimes {
         self.lifetime_env.lifetimes()
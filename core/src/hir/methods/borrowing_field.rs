@@ -147,7 +147,8 @@ impl<'m> BorrowingFieldVisitor<'m> {
                                 &mut leaves,
                             );
                         }
-                        SelfType::Enum(_) => {}
+                        // Consumed by the method, so nothing can borrow from it
+                        SelfType::Enum(_) | SelfType::BoxedOpaque(_) => {}
                     }
                 }
 
@@ -27,5 +27,5 @@ pub use types::*;
 
 pub use lowering::{ErrorAndContext, ErrorContext, LoweringError};
 
-pub use crate::ast::Docs;
+pub use crate::ast::{ConstValue, Docs, SourceSpan};
 pub use strck_ident::rust::{Ident, IdentBuf};
@@ -25,9 +25,9 @@ pub struct StructPath<P: TyPosition = Everywhere> {
 
 /// Path to an opaque.
 ///
-/// There are three kinds of opaques that Diplomat uses, so this type has two
-/// generic arguments to differentiate between the three, while still showing
-/// that the three are all paths to opaques. The monomorphized versions that
+/// There are four kinds of opaques that Diplomat uses, so this type has two
+/// generic arguments to differentiate between the four, while still showing
+/// that the four are all paths to opaques. The monomorphized versions that
 /// Diplomat uses are:
 ///
 /// 1. `OpaquePath<Optional, MaybeOwn>`: Opaques in return types,
@@ -37,6 +37,8 @@ pub struct StructPath<P: TyPosition = Everywhere> {
 /// entirely give up ownership of a value.
 /// 3. `OpaquePath<NonOptional, Borrow>`: Opaques in the `&self` position, which
 /// cannot be optional and must be borrowed for the same reason as above.
+/// 4. `OpaquePath<NonOptional, MaybeOwn>`: Opaques in the `self: Box<Self>`
+///    position, which are always owned and handed back to Rust by the call.
 #[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct OpaquePath<Opt, Owner> {
@@ -43,7 +43,10 @@ pub enum FloatType {
 #[derive(Copy, Clone, Debug)]
 #[allow(clippy::exhaustive_enums)] // there are only these
 pub enum PrimitiveType {
+    /// Crosses the FFI boundary as a `u8` holding 0 or 1, Rust reading any nonzero byte as `true`
     Bool,
+    /// A `DiplomatChar`, crossing the FFI boundary as a `u32` that Rust does not validate as a
+    /// Unicode scalar value
     Char,
     /// a primitive byte that is not meant to be interpreted numerically
     /// in languages that don't have fine-grained integer types
@@ -3,8 +3,8 @@
 use super::lowering::{ErrorAndContext, ErrorStore, ItemAndInfo};
 use super::ty_position::StructPathLike;
 use super::{
-    AttributeValidator, Attrs, EnumDef, LoweringContext, LoweringError, MaybeStatic, OpaqueDef,
-    OutStructDef, StructDef, TypeDef,
+    AttributeValidator, Attrs, Docs, EnumDef, IdentBuf, LoweringContext, LoweringError,
+    MaybeStatic, OpaqueDef, OutStructDef, StructDef, TraitDef, TypeDef,
 };
 use crate::ast::attrs::AttrInheritContext;
 #[allow(unused_imports)] // use in docs links
@@ -12,7 +12,7 @@ use crate::hir;
 use crate::{ast, Env};
 use core::fmt::{self, Display};
 use smallvec::SmallVec;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Index;
 
 /// A context type owning all types exposed to Diplomat.
@@ -22,6 +22,7 @@ pub struct TypeContext {
     structs: Vec<StructDef>,
     opaques: Vec<OpaqueDef>,
     enums: Vec<EnumDef>,
+    traits: Vec<TraitDef>,
 }
 
 /// Key used to index into a [`TypeContext`] representing a struct.
@@ -40,6 +41,10 @@ pub struct OpaqueId(usize);
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct EnumId(usize);
 
+/// Key used to index into a [`TypeContext`] representing a trait.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TraitId(usize);
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[non_exhaustive]
 pub enum TypeId {
@@ -49,6 +54,74 @@ pub enum TypeId {
     Enum(EnumId),
 }
 
+/// An intra-doc link to a bridge item that is not generated, see
+/// [`TypeContext::dangling_doc_references()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DanglingDocReference {
+    /// The item whose docs contain the link, such as `Foo` or `Foo::bar`
+    pub item: String,
+    /// The target of the link, as written in the docs
+    pub target: String,
+}
+
+/// The types and traits of the bridge, by Rust name, with their generated members, or `None`
+/// if they are disabled
+struct DocTargets<'tcx>(HashMap<&'tcx str, Option<HashSet<&'tcx str>>>);
+
+impl<'tcx> DocTargets<'tcx> {
+    fn new(tcx: &'tcx TypeContext) -> Self {
+        let mut targets = HashMap::new();
+        for (_, ty) in tcx.all_types() {
+            let members = (!ty.attrs().disable).then(|| {
+                let methods = ty.methods().iter().filter(|m| !m.attrs.disable);
+                let mut members = methods.map(|m| m.name.as_str()).collect::<HashSet<_>>();
+                match ty {
+                    TypeDef::Struct(st) => {
+                        members.extend(st.fields.iter().map(|f| f.name.as_str()))
+                    }
+                    TypeDef::OutStruct(st) => {
+                        members.extend(st.fields.iter().map(|f| f.name.as_str()))
+                    }
+                    TypeDef::Enum(e) => members.extend(e.variants.iter().map(|v| v.name.as_str())),
+                    _ => {}
+                }
+                members
+            });
+            targets.insert(ty.name().as_str(), members);
+        }
+        for (_, trt) in tcx.all_traits() {
+            let members =
+                (!trt.attrs.disable).then(|| trt.methods.iter().map(|m| m.name.as_str()).collect());
+            targets.insert(trt.name.as_str(), members);
+        }
+        Self(targets)
+    }
+
+    /// Whether `target`, linked from the docs of `owner` or its members, names a bridge item
+    /// that is not generated. Paths are resolved from their first segment naming a bridge
+    /// item, so `crate::ffi::Foo` and `Foo` are the same.
+    fn is_dangling(&self, target: &str, owner: &str) -> bool {
+        let segments = target.split("::").collect::<Vec<_>>();
+        let Some(start) = segments
+            .iter()
+            .position(|s| *s == "Self" || self.0.contains_key(s))
+        else {
+            return false;
+        };
+        let name = if segments[start] == "Self" {
+            owner
+        } else {
+            segments[start]
+        };
+        match (self.0.get(name), &segments[start + 1..]) {
+            (Some(None), _) => true,
+            (Some(Some(members)), [member]) => !members.contains(member),
+            _ => false,
+        }
+    }
+}
+
 enum Param<'a> {
     Input(&'a str),
     Return,
@@ -106,6 +179,18 @@ impl TypeContext {
         &self.enums
     }
 
+    /// All traits, which are not types and thus not part of [`Self::all_types()`]
+    pub fn all_traits(&self) -> impl Iterator<Item = (TraitId, &TraitDef)> {
+        self.traits
+            .iter()
+            .enumerate()
+            .map(|(i, trt)| (TraitId(i), trt))
+    }
+
+    pub fn resolve_trait(&self, id: TraitId) -> &TraitDef {
+        self.traits.index(id.0)
+    }
+
     pub fn resolve_type<'tcx>(&'tcx self, id: TypeId) -> TypeDef<'tcx> {
         match id {
             TypeId::Struct(i) => TypeDef::Struct(self.resolve_struct(i)),
@@ -143,6 +228,125 @@ impl TypeContext {
         self.enums.index(id.0)
     }
 
+    /// The intra-doc links in the docs of generated items that point at bridge items which are
+    /// not generated: disabled types, traits and methods, or members that do not exist.
+    ///
+    /// Links to anything outside of the bridge, such as ``[`Vec`]``, are not checked.
+    pub fn dangling_doc_references(&self) -> Vec<DanglingDocReference> {
+        let targets = DocTargets::new(self);
+        let mut dangling = Vec::new();
+        let mut check = |owner: &str, item: String, docs: &Docs| {
+            for target in docs.references() {
+                if targets.is_dangling(target, owner) {
+                    dangling.push(DanglingDocReference {
+                        item: item.clone(),
+                        target: target.into(),
+                    });
+                }
+            }
+        };
+        for (_, ty) in self.all_types().filter(|(_, ty)| !ty.attrs().disable) {
+            let owner = ty.name().as_str();
+            check(owner, owner.into(), ty.docs());
+            for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+                check(owner, format!("{owner}::{}", method.name), &method.docs);
+            }
+            let members: Vec<(&IdentBuf, &Docs)> = match ty {
+                TypeDef::Struct(st) => st.fields.iter().map(|f| (&f.name, &f.docs)).collect(),
+                TypeDef::OutStruct(st) => st.fields.iter().map(|f| (&f.name, &f.docs)).collect(),
+                TypeDef::Enum(e) => e.variants.iter().map(|v| (&v.name, &v.docs)).collect(),
+                _ => Vec::new(),
+            };
+            for (name, docs) in members {
+                check(owner, format!("{owner}::{name}"), docs);
+            }
+        }
+        for (_, trt) in self.all_traits().filter(|(_, trt)| !trt.attrs.disable) {
+            let owner = trt.name.as_str();
+            check(owner, owner.into(), &trt.docs);
+            for method in &trt.methods {
+                check(owner, format!("{owner}::{}", method.name), &method.docs);
+            }
+        }
+        dangling
+    }
+
+    /// Rewrites the links found by [`Self::dangling_doc_references()`] to plain text, so that
+    /// backends do not generate links to items that do not exist in their bindings.
+    pub fn unlink_dangling_doc_references(&mut self) {
+        let dangling = self
+            .dangling_doc_references()
+            .into_iter()
+            .map(|r| (r.item, r.target))
+            .collect::<HashSet<_>>();
+        if dangling.is_empty() {
+            return;
+        }
+        let unlink = |item: String, docs: &mut Docs| {
+            docs.unlink_references(|target| dangling.contains(&(item.clone(), target.into())));
+        };
+        macro_rules! unlink_type {
+            ($ty:expr, $members:ident) => {{
+                let owner = $ty.name.to_string();
+                unlink(owner.clone(), &mut $ty.docs);
+                for method in &mut $ty.methods {
+                    unlink(format!("{owner}::{}", method.name), &mut method.docs);
+                }
+                for member in &mut $ty.$members {
+                    unlink(format!("{owner}::{}", member.name), &mut member.docs);
+                }
+            }};
+        }
+        for st in &mut self.structs {
+            unlink_type!(st, fields);
+        }
+        for st in &mut self.out_structs {
+            unlink_type!(st, fields);
+        }
+        for e in &mut self.enums {
+            unlink_type!(e, variants);
+        }
+        for op in &mut self.opaques {
+            let owner = op.name.to_string();
+            unlink(owner.clone(), &mut op.docs);
+            for method in &mut op.methods {
+                unlink(format!("{owner}::{}", method.name), &mut method.docs);
+            }
+        }
+        for trt in &mut self.traits {
+            unlink_type!(trt, methods);
+        }
+    }
+
+    /// Gives the types and methods without a `stable_id` attribute the [`hir::stable_id_of()`]
+    /// their path
+    fn assign_stable_ids(&mut self) {
+        macro_rules! assign_type {
+            ($ty:expr) => {{
+                let owner = $ty.name.to_string();
+                let id = &mut $ty.attrs.stable_id;
+                id.get_or_insert_with(|| hir::stable_id_of(&owner));
+                for method in &mut $ty.methods {
+                    let path = format!("{owner}::{}", method.name);
+                    let id = &mut method.attrs.stable_id;
+                    id.get_or_insert_with(|| hir::stable_id_of(&path));
+                }
+            }};
+        }
+        for st in &mut self.structs {
+            assign_type!(st);
+        }
+        for st in &mut self.out_structs {
+            assign_type!(st);
+        }
+        for e in &mut self.enums {
+            assign_type!(e);
+        }
+        for op in &mut self.opaques {
+            assign_type!(op);
+        }
+    }
+
     /// Lower the AST to the HIR while simultaneously performing validation.
     pub fn from_ast<'ast>(
         env: &'ast Env,
@@ -166,6 +370,7 @@ impl TypeContext {
         let mut ast_structs = SmallVec::<[_; 16]>::new();
         let mut ast_opaques = SmallVec::<[_; 16]>::new();
         let mut ast_enums = SmallVec::<[_; 16]>::new();
+        let mut ast_traits = SmallVec::<[_; 16]>::new();
 
         let mut errors = ErrorStore::default();
 
@@ -187,6 +392,9 @@ impl TypeContext {
                 mod_attrs.for_inheritance(AttrInheritContext::MethodOrImplFromModule);
 
             for sym in mod_env.items() {
+                if let ast::ModSymbol::Trait(trt) = sym {
+                    ast_traits.push((trt, ty_attrs.clone()));
+                }
                 if let ast::ModSymbol::CustomType(custom_type) = sym {
                     match custom_type {
                         ast::CustomType::Struct(strct) => {
@@ -238,6 +446,7 @@ impl TypeContext {
             &ast_structs[..],
             &ast_opaques[..],
             &ast_enums[..],
+            ast_traits.iter().map(|(trt, _)| *trt),
         );
         let attr_validator = Box::new(attr_validator);
 
@@ -252,15 +461,18 @@ impl TypeContext {
         let structs = ctx.lower_all_structs(ast_structs.into_iter());
         let opaques = ctx.lower_all_opaques(ast_opaques.into_iter());
         let enums = ctx.lower_all_enums(ast_enums.into_iter());
+        let traits = ctx.lower_all_traits(ast_traits.into_iter());
 
-        match (out_structs, structs, opaques, enums) {
-            (Ok(out_structs), Ok(structs), Ok(opaques), Ok(enums)) => {
-                let res = Self {
+        match (out_structs, structs, opaques, enums, traits) {
+            (Ok(out_structs), Ok(structs), Ok(opaques), Ok(enums), Ok(traits)) => {
+                let mut res = Self {
                     out_structs,
                     structs,
                     opaques,
                     enums,
+                    traits,
                 };
+                res.assign_stable_ids();
 
                 if !ctx.errors.is_empty() {
                     return Err(ctx.errors.take_errors());
@@ -279,15 +491,27 @@ impl TypeContext {
 
     /// Run validation phase
     ///
-    /// Currently validates that methods are not inheriting any transitive bounds from parameters
+    /// Currently validates that methods are not inheriting any transitive bounds from parameters,
+    /// and that no struct contains itself by value
     ///    Todo: Automatically insert these bounds during HIR construction in a second phase
     fn validate<'hir>(&'hir self, errors: &mut ErrorStore<'hir>) {
+        self.validate_struct_cycles(errors);
+        self.validate_stable_ids(errors);
+
         // Lifetime validity check
         for (_id, ty) in self.all_types() {
             errors.set_item(ty.name().as_str());
             for method in ty.methods() {
                 errors.set_subitem(method.name.as_str());
 
+                if method.attrs.with_warnings && method.with_warnings(self).is_none() {
+                    errors.push(LoweringError::Other(
+                        "Methods marked `with_warnings` must return an out struct with exactly \
+                         a `value` and a `warnings` field"
+                            .into(),
+                    ));
+                }
+
                 // This check must occur before validate_ty_in_method is called
                 // since validate_ty_in_method calls link_lifetimes which does not
                 // work for structs with elision
@@ -328,6 +552,104 @@ impl TypeContext {
         }
     }
 
+    /// Ensure that no two generated types or methods have the same stable ID, since they could
+    /// not be told apart when dispatching by ID
+    fn validate_stable_ids<'hir>(&'hir self, errors: &mut ErrorStore<'hir>) {
+        let mut seen = HashMap::new();
+        for (_, ty) in self.all_types().filter(|(_, ty)| !ty.attrs().disable) {
+            errors.set_item(ty.name().as_str());
+            let owner = ty.name().as_str();
+            let methods = ty.methods().iter().filter(|m| !m.attrs.disable);
+            let items = std::iter::once((owner.to_string(), ty.attrs().stable_id))
+                .chain(methods.map(|m| (format!("{owner}::{}", m.name), m.attrs.stable_id)));
+            for (path, id) in items {
+                let Some(id) = id else { continue };
+                // Methods of the same path are the same method under different `cfg`s
+                match seen.insert(id, path.clone()) {
+                    Some(other) if other != path => {
+                        errors.push(LoweringError::Other(format!(
+                            "`{path}` has the stable ID {id} of `{other}`, give one of them another with `stable_id`"
+                        )));
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Ensure that no struct contains itself by value, directly or through the fields of other
+    /// structs, since such a struct cannot be laid out
+    fn validate_struct_cycles<'hir>(&'hir self, errors: &mut ErrorStore<'hir>) {
+        // Structs whose fields have been fully explored
+        let mut done = HashSet::new();
+        let mut path = Vec::new();
+        for (id, ty) in self.all_types() {
+            if let TypeDef::Struct(_) | TypeDef::OutStruct(_) = ty {
+                errors.set_item(ty.name().as_str());
+                self.find_struct_cycles(id, &mut path, &mut done, errors);
+            }
+        }
+    }
+
+    /// Depth-first search for struct cycles reachable from `id`, `path` being the chain
+    /// of structs currently being explored
+    fn find_struct_cycles(
+        &self,
+        id: TypeId,
+        path: &mut Vec<TypeId>,
+        done: &mut HashSet<TypeId>,
+        errors: &mut ErrorStore,
+    ) {
+        if done.contains(&id) {
+            return;
+        }
+        if let Some(start) = path.iter().position(|p| *p == id) {
+            let cycle = path[start..]
+                .iter()
+                .chain([&id])
+                .map(|id| self.resolve_type(*id).name().as_str())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            errors.push(LoweringError::Other(format!(
+                "Struct `{}` contains itself by value ({cycle}), so it cannot be laid out. \
+                 Make one of these types opaque and hold it by reference to break the cycle",
+                self.resolve_type(id).name()
+            )));
+            return;
+        }
+
+        let fields: Vec<TypeId> = match self.resolve_type(id) {
+            TypeDef::Struct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| Self::struct_held_by_value(&f.ty))
+                .collect(),
+            TypeDef::OutStruct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| Self::struct_held_by_value(&f.ty))
+                .collect(),
+            _ => return,
+        };
+
+        path.push(id);
+        for field in fields {
+            self.find_struct_cycles(field, path, done, errors);
+        }
+        path.pop();
+        done.insert(id);
+    }
+
+    /// The struct a field of type `ty` holds by value, directly or as the value of a
+    /// `DiplomatOption`, if any
+    fn struct_held_by_value<P: hir::TyPosition>(ty: &hir::Type<P>) -> Option<TypeId> {
+        match ty {
+            hir::Type::Struct(st) => Some(st.id()),
+            hir::Type::DiplomatOption(inner) => Self::struct_held_by_value(inner),
+            _ => None,
+        }
+    }
+
     /// Ensure that a given method's input our output type does not implicitly introduce bounds that are not
     /// already specified on the method
     fn validate_ty_in_method<P: hir::TyPosition>(
@@ -404,6 +726,7 @@ pub(super) struct LookupId<'ast> {
     struct_map: HashMap<&'ast ast::Struct, StructId>,
     opaque_map: HashMap<&'ast ast::OpaqueStruct, OpaqueId>,
     enum_map: HashMap<&'ast ast::Enum, EnumId>,
+    trait_map: HashMap<&'ast ast::Trait, TraitId>,
 }
 
 impl<'ast> LookupId<'ast> {
@@ -413,6 +736,7 @@ impl<'ast> LookupId<'ast> {
         structs: &[ItemAndInfo<'ast, ast::Struct>],
         opaques: &[ItemAndInfo<'ast, ast::OpaqueStruct>],
         enums: &[ItemAndInfo<'ast, ast::Enum>],
+        traits: impl Iterator<Item = &'ast ast::Trait>,
     ) -> Self {
         Self {
             out_struct_map: out_structs
@@ -435,9 +759,17 @@ impl<'ast> LookupId<'ast> {
                 .enumerate()
                 .map(|(index, item)| (item.item, EnumId(index)))
                 .collect(),
+            trait_map: traits
+                .enumerate()
+                .map(|(index, trt)| (trt, TraitId(index)))
+                .collect(),
         }
     }
 
+    pub(super) fn resolve_trait(&self, trt: &ast::Trait) -> Option<TraitId> {
+        self.trait_map.get(trt).copied()
+    }
+
     pub(super) fn resolve_out_struct(&self, strct: &ast::Struct) -> Option<OutStructId> {
         self.out_struct_map.get(strct).copied()
     }
@@ -534,6 +866,232 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_consuming_methods() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                struct Foo {
+                    field: u8
+                }
+
+                impl Opaque {
+                    pub fn consume(self: Box<Self>) {}
+                }
+
+                impl Foo {
+                    pub fn consume(self: Box<Self>) {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_callbacks() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Opaque {
+                    pub fn each(&self, f: impl Fn(u32)) {}
+                    #[diplomat::attr(not(supports = callbacks), disable)]
+                    pub fn each_disabled(&self, f: impl Fn(u32) -> bool) {}
+                }
+            }
+        };
+        let env = crate::ast::File::from(&parsed).all_types();
+        let mut attr_validator = hir::BasicAttributeValidator::new("tests");
+        attr_validator.support.disabling = true;
+        let errors = hir::TypeContext::from_ast(&env, attr_validator)
+            .expect_err("callbacks should have been rejected")
+            .into_iter()
+            .map(|(ctx, err)| format!("{ctx}: {err}"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "Opaque::each: Callback parameters are not supported by the tests backend, \
+              disable the method with #[diplomat::attr(not(supports = callbacks), disable)]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                struct Point {
+                    x: DiplomatOption<u32>,
+                }
+
+                #[diplomat::attr(not(supports = option), disable)]
+                struct Range {
+                    start: DiplomatOption<u32>,
+                }
+
+                impl Opaque {
+                    pub fn set(&self, value: Option<u32>, other: Option<&Opaque>) {}
+                    #[diplomat::attr(not(supports = option), disable)]
+                    pub fn set_disabled(&self, value: Option<bool>) {}
+                    pub fn set_str(&self, value: Option<&DiplomatStr>) {}
+                    pub fn get(&self) -> DiplomatOption<u32> {}
+                }
+            }
+        };
+        let env = crate::ast::File::from(&parsed).all_types();
+        let lower = |option| {
+            let mut attr_validator = hir::BasicAttributeValidator::new("tests");
+            attr_validator.support.disabling = true;
+            attr_validator.support.option = option;
+            hir::TypeContext::from_ast(&env, attr_validator)
+                .expect_err("invalid options should have been rejected")
+                .into_iter()
+                .map(|(ctx, err)| format!("{ctx}: {err}"))
+                .collect::<Vec<_>>()
+        };
+        let invalid = [
+            "Opaque::set_str: found Option<T> passed by value where T isn't a primitive, enum, or struct without lifetimes. T = &DiplomatStr",
+            "Opaque::get: DiplomatOption<T> can only appear in struct fields, return Option<T> instead",
+        ];
+        assert_eq!(lower(true), invalid);
+        assert_eq!(
+            lower(false),
+            [
+                "Point: DiplomatOption<T> fields are not supported by the tests backend, \
+                 disable the struct with #[diplomat::attr(not(supports = option), disable)]",
+                "Opaque::set: Option<T> parameters of primitives, enums and structs are not supported by the tests backend, \
+                 disable the method with #[diplomat::attr(not(supports = option), disable)]",
+                invalid[0],
+                invalid[1],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Opaque {
+                    pub const LIMIT: u32 = 10;
+                    pub const NAME: &'static str = "opaque";
+                    pub const MAYBE: Option<u32> = None;
+                    pub const RESULT: Result<u8, ()> = Ok(1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stable_ids() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, stable_id = 1)]
+                struct Opaque;
+
+                #[diplomat::attr(*, stable_id = 1)]
+                enum Kind {
+                    #[diplomat::attr(*, stable_id = 2)]
+                    A,
+                }
+
+                impl Opaque {
+                    #[diplomat::attr(*, stable_id = "8")]
+                    pub fn third(&self) {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stable_id_collisions() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, stable_id = 1)]
+                struct Opaque;
+
+                #[diplomat::attr(*, stable_id = 1)]
+                enum Kind {
+                    A,
+                }
+
+                impl Opaque {
+                    #[diplomat::attr(*, stable_id(7))]
+                    pub fn first(&self) {}
+                    #[diplomat::attr(*, stable_id = 7)]
+                    pub fn second(&self) {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_assigned_stable_ids() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Opaque {
+                    #[diplomat::attr(*, stable_id = 7)]
+                    pub fn first(&self) {}
+                    pub fn second(&self) {}
+                }
+            }
+        };
+        let env = crate::ast::File::from(&parsed).all_types();
+        let tcx =
+            hir::TypeContext::from_ast(&env, hir::BasicAttributeValidator::new("tests")).unwrap();
+        let opaque = &tcx.opaques()[0];
+        assert_eq!(opaque.attrs.stable_id, Some(hir::stable_id_of("Opaque")));
+        assert_eq!(opaque.methods[0].attrs.stable_id, Some(7));
+        assert_eq!(
+            opaque.methods[1].attrs.stable_id,
+            Some(hir::stable_id_of("Opaque::second"))
+        );
+        // The FNV-1a test vector, so IDs never change under users
+        assert_eq!(hir::stable_id_of("a"), 0xe40c_292c);
+    }
+
+    #[test]
+    fn test_param_encodings() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Opaque {
+                    pub fn valid(
+                        #[diplomat::encoding(ascii)] a: &DiplomatStr,
+                        #[diplomat::encoding(utf8)] b: &str,
+                        #[diplomat::encoding(utf16)] c: &DiplomatStr16,
+                    ) {}
+                    pub fn utf16_on_utf8(#[diplomat::encoding(utf16)] s: &DiplomatStr) {}
+                    pub fn ascii_on_utf16(#[diplomat::encoding(ascii)] s: &DiplomatStr16) {}
+                    pub fn on_slice(#[diplomat::encoding(ascii)] s: &[u8]) {}
+                }
+            }
+        }
+    }
+
     /// This is a buch of tests put together
     #[test]
     fn test_basic_lowering() {
@@ -720,6 +1278,71 @@ mod tests {
         };
     }
 
+    #[test]
+    fn struct_cycles() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                struct SelfContained {
+                    inner: SelfContained,
+                }
+
+                struct Outer {
+                    middle: Middle,
+                    num: u8,
+                }
+
+                struct Middle {
+                    inner: Inner,
+                }
+
+                struct Inner {
+                    outer: Outer,
+                }
+
+                struct NotACycle {
+                    a: Inner2,
+                    b: Inner2,
+                }
+
+                struct Inner2 {
+                    num: u8,
+                }
+            }
+        };
+    }
+
+    #[test]
+    fn struct_cycles_through_options() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                struct MaybeSelf {
+                    inner: DiplomatOption<MaybeSelf>,
+                    num: u8,
+                }
+
+                struct Outer {
+                    middle: DiplomatOption<Middle>,
+                }
+
+                struct Middle {
+                    outer: Outer,
+                }
+            }
+        };
+        let env = crate::ast::File::from(&parsed).all_types();
+        let mut attr_validator = hir::BasicAttributeValidator::new("tests");
+        attr_validator.support.option = true;
+        let mut output = String::new();
+        for (ctx, err) in hir::TypeContext::from_ast(&env, attr_validator)
+            .expect_err("struct cycles should have been rejected")
+        {
+            writeln!(&mut output, "Lowering error in {ctx}: {err}").unwrap();
+        }
+        insta::assert_snapshot!(output);
+    }
+
     #[test]
     fn non_opaque_move() {
         uitest_lowering! {
@@ -764,4 +1387,91 @@ mod tests {
             }
         };
     }
+
+    #[test]
+    fn test_bundle() {
+        uitest_lowering! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                enum Mode {
+                    Read,
+                    Write,
+                }
+
+                struct Open {
+                    x: u8,
+                }
+
+                impl Opaque {
+                    #[diplomat::bundle(OpenOptions, mode, create)]
+                    pub fn valid(mode: Mode, path: &DiplomatStr, create: bool) -> Box<Opaque> {}
+                    #[diplomat::bundle(MissingOptions, mode, missing)]
+                    pub fn missing(mode: Mode) {}
+                    #[diplomat::bundle(DuplicateOptions, mode, mode)]
+                    pub fn duplicate(mode: Mode) {}
+                    #[diplomat::bundle(SliceOptions, path)]
+                    pub fn slice(path: &DiplomatStr) {}
+                    #[diplomat::bundle(Open, mode)]
+                    pub fn clash(mode: Mode) {}
+                }
+            }
+        };
+    }
+
+    #[test]
+    fn test_dangling_doc_references() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Factory;
+
+                /// Made by [`Factory::make`], see also [`Vec`] and [`crate::ffi::Hidden`].
+                #[diplomat::opaque]
+                struct Widget;
+
+                #[diplomat::opaque]
+                #[diplomat::attr(tests, disable)]
+                struct Hidden;
+
+                impl Factory {
+                    /// Prefer [`Self::make`] over [`Self::make_hidden`] and [`Self::gone`].
+                    pub fn make(&self) -> Box<Widget> {}
+                    #[diplomat::attr(tests, disable)]
+                    pub fn make_hidden(&self) -> Box<Hidden> {}
+                }
+            }
+        };
+        let env = crate::ast::File::from(&parsed).all_types();
+        let mut attr_validator = hir::BasicAttributeValidator::new("tests");
+        attr_validator.support.disabling = true;
+        let mut tcx = hir::TypeContext::from_ast(&env, attr_validator).unwrap();
+
+        let dangling = tcx
+            .dangling_doc_references()
+            .into_iter()
+            .map(|r| format!("{} -> {}", r.item, r.target))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dangling,
+            [
+                "Factory::make -> Self::make_hidden",
+                "Factory::make -> Self::gone",
+                "Widget -> crate::ffi::Hidden",
+            ]
+        );
+
+        tcx.unlink_dangling_doc_references();
+        assert!(tcx.dangling_doc_references().is_empty());
+        let factory = tcx.opaques().iter().find(|o| o.name.as_str() == "Factory");
+        assert_eq!(
+            factory.unwrap().methods[0]
+                .docs
+                .to_markdown(&Default::default(), crate::ast::MarkdownStyle::Normal),
+            "Prefer [`Self::make`] over `Self::make_hidden` and `Self::gone`."
+        );
+    }
 }
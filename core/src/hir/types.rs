@@ -2,12 +2,13 @@
 
 use super::lifetimes::{Lifetime, MaybeStatic};
 use super::{
-    EnumPath, Everywhere, NonOptional, OpaqueOwner, OpaquePath, Optional, OutputOnly,
-    PrimitiveType, StructPath, StructPathLike, TyPosition, TypeContext, TypeId,
+    EnumPath, Everywhere, MaybeOwn, NonOptional, OpaqueOwner, OpaquePath, Optional, OutputOnly,
+    PrimitiveType, ReturnableStructPath, StructPath, StructPathLike, TraitId, TyPosition,
+    TypeContext, TypeId,
 };
 use crate::ast;
 pub use ast::Mutability;
-pub use ast::StringEncoding;
+pub use ast::{RequiredEncoding, StringEncoding};
 use either::Either;
 
 /// Type that can only be used as an output.
@@ -22,6 +23,13 @@ pub enum Type<P: TyPosition = Everywhere> {
     Struct(P::StructPath),
     Enum(EnumPath),
     Slice(Slice),
+    /// A closure passed in from foreign code, only allowed as a method parameter
+    Callback(Callback),
+    /// A `&dyn Trait` implemented by foreign code, only allowed as a method parameter
+    DynTrait(TraitId),
+    /// An `Option<T>` parameter or `DiplomatOption<T>` struct field of a primitive, enum, or
+    /// struct without lifetimes, passed by value with a flag telling whether it is set
+    DiplomatOption(Box<Type<P>>),
 }
 
 /// Type that can appear in the `self` position.
@@ -29,6 +37,8 @@ pub enum Type<P: TyPosition = Everywhere> {
 #[non_exhaustive]
 pub enum SelfType {
     Opaque(OpaquePath<NonOptional, Borrow>),
+    /// An opaque taken as `self: Box<Self>`, whose ownership is transferred back to Rust
+    BoxedOpaque(OpaquePath<NonOptional, MaybeOwn>),
     Struct(StructPath),
     Enum(EnumPath),
 }
@@ -61,6 +71,17 @@ pub enum Slice {
     Strs(StringEncoding),
 }
 
+/// A callback parameter (`impl Fn(A, B) -> R`), implemented by a foreign closure.
+///
+/// Only primitives are currently supported as callback parameters and return values.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Callback {
+    pub params: Vec<PrimitiveType>,
+    /// `None` if the callback returns `()`
+    pub output: Option<PrimitiveType>,
+}
+
 // For now, the lifetime in not optional. This is because when you have references
 // as fields of structs, the lifetime must always be present, and we want to uphold
 // this invariant at the type level within the HIR.
@@ -88,7 +109,11 @@ impl Type {
                 (acc.0 + inner.0, acc.1 + inner.1)
             }),
             Type::Opaque(_) | Type::Slice(_) => (1, 1),
-            Type::Primitive(_) | Type::Enum(_) => (0, 0),
+            Type::Primitive(_)
+            | Type::Enum(_)
+            | Type::Callback(_)
+            | Type::DynTrait(_)
+            | Type::DiplomatOption(_) => (0, 0),
         }
     }
 }
@@ -164,11 +189,22 @@ impl Borrow {
     }
 }
 
-impl From<SelfType> for Type {
-    fn from(s: SelfType) -> Type {
+impl From<SelfType> for OutType {
+    /// Converts the `self` type to an output type, which can hold the opaque owned by a
+    /// consuming method as well as a borrowed one.
+    fn from(s: SelfType) -> OutType {
         match s {
-            SelfType::Opaque(o) => Type::Opaque(o.wrap_optional()),
-            SelfType::Struct(s) => Type::Struct(s),
+            SelfType::Opaque(o) => Type::Opaque(
+                OpaquePath {
+                    lifetimes: o.lifetimes,
+                    optional: NonOptional,
+                    owner: MaybeOwn::Borrow(o.owner),
+                    tcx_id: o.tcx_id,
+                }
+                .wrap_optional(),
+            ),
+            SelfType::BoxedOpaque(o) => Type::Opaque(o.wrap_optional()),
+            SelfType::Struct(s) => Type::Struct(ReturnableStructPath::Struct(s)),
             SelfType::Enum(e) => Type::Enum(e),
         }
     }
@@ -6,6 +6,7 @@
 #include <array>
 #include <optional>
 #include <type_traits>
+#include <utility>
 
 #if __cplusplus >= 202002L
 #include<span>
@@ -13,6 +14,15 @@
 
 #include "diplomat_runtime.h"
 
+namespace capi {
+extern "C" {
+// The batch allocation scope of the runtime, see `BatchScope`
+DiplomatBatch* diplomat_batch_begin(void);
+void* diplomat_batch_alloc(DiplomatBatch* batch, size_t size, size_t align);
+void diplomat_batch_end(DiplomatBatch* batch);
+} // extern "C"
+} // namespace capi
+
 namespace diplomat {
 
 extern "C" inline void Flush(capi::DiplomatWriteable* w) {
@@ -162,6 +172,47 @@ private:
 
 #endif // __cplusplus >= 202002L
 
+// RAII wrapper around a Diplomat batch scope: temporary allocations made through it
+// are shared by a sequence of calls and released all at once when it goes out of scope.
+class BatchScope {
+public:
+  BatchScope() : batch_(::capi::diplomat_batch_begin()) {}
+  ~BatchScope() {
+    if (batch_ != nullptr) {
+      ::capi::diplomat_batch_end(batch_);
+    }
+  }
+  BatchScope(const BatchScope&) = delete;
+  BatchScope& operator=(const BatchScope&) = delete;
+  BatchScope(BatchScope&& other) noexcept : batch_(other.batch_) {
+    other.batch_ = nullptr;
+  }
+  BatchScope& operator=(BatchScope&& other) noexcept {
+    std::swap(batch_, other.batch_);
+    return *this;
+  }
+
+  // Allocate uninitialized storage for `count` values of `T`, valid until this scope ends.
+  // Returns null if the allocation failed.
+  template<typename T>
+  T* alloc(size_t count = 1) {
+    return static_cast<T*>(::capi::diplomat_batch_alloc(batch_, sizeof(T) * count, alignof(T)));
+  }
+
+  ::capi::DiplomatBatch* AsFFI() const {
+    return batch_;
+  }
+private:
+  ::capi::DiplomatBatch* batch_;
+};
+
+// Run `f` with a fresh batch scope, releasing the scope's allocations once it returns.
+template<typename F>
+auto with_batch(F&& f) -> decltype(f(std::declval<BatchScope&>())) {
+  BatchScope scope;
+  return f(scope);
+}
+
 }
 
 #endif
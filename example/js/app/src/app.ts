@@ -1,9 +1,12 @@
 import { format } from "./index.js";
+import { Session } from "./session.js";
+
+const session = new Session();
 
 function submit(input: number): void {
     const my_input = document.getElementById('my-input');
     try {
-        const fmt = format(input);
+        const fmt = session.record(input, format);
         document.getElementById('text-box').innerHTML = fmt;
         my_input.style.background = 'transparent';
     } catch (e) {
@@ -12,5 +15,16 @@ function submit(input: number): void {
     }
 }
 
+// Downloads the inputs and results so far as a trace for `replay.js`
+function exportSession(): void {
+    const blob = new Blob([JSON.stringify(session, null, 4) + "\n"], { type: 'application/json' });
+    const link = document.createElement('a');
+    link.href = URL.createObjectURL(blob);
+    link.download = 'session.json';
+    link.click();
+    URL.revokeObjectURL(link.href);
+}
+
 const my_input = document.getElementById('my-input') as HTMLInputElement | null;
 my_input?.addEventListener('input', () => submit(+my_input.value));
+document.getElementById('export-session')?.addEventListener('click', exportSession);
@@ -1,12 +1,22 @@
 import { format } from "./index.js";
+import { Session } from "./session.js";
 import { question } from "readline-sync";
+import { writeFileSync } from "fs";
+
+// `--record <file>` writes the session to `<file>` after each input, to be replayed by `replay.js`
+const record = process.argv.indexOf("--record");
+const trace = record === -1 ? undefined : process.argv[record + 1];
+const session = new Session();
 
 while (true) {
     const input: string = question("Enter an integer: ");
     try {
-        const fmt = format(+input);
+        const fmt = session.record(+input, format);
         console.log(`Formatted: ${fmt}`);
     } catch (e) {
         console.log(e.message);
     }
+    if (trace !== undefined) {
+        writeFileSync(trace, JSON.stringify(session, null, 4) + "\n");
+    }
 }
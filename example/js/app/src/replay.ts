@@ -0,0 +1,15 @@
+import { format } from "./index.js";
+import { replay, Trace } from "./session.js";
+import { readFileSync } from "fs";
+
+// Replays the sessions given on the command line, failing if any of them no longer behaves
+// as recorded
+let failed = false;
+for (const path of process.argv.slice(2)) {
+    const trace: Trace = JSON.parse(readFileSync(path, "utf8"));
+    const mismatches = replay(trace, format);
+    mismatches.forEach(m => console.log(`${path}: ${m}`));
+    failed ||= mismatches.length > 0;
+    console.log(`${path}: ${trace.steps.length - mismatches.length} of ${trace.steps.length} steps ok`);
+}
+process.exitCode = failed ? 1 : 0;
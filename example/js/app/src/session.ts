@@ -0,0 +1,50 @@
+// Recording of demo sessions, so that a manual repro can be exported as a JSON trace and
+// replayed as a regression test by `replay.ts`.
+
+export interface Step {
+    input: number;
+    output?: string;
+    error?: string;
+}
+
+export interface Trace {
+    steps: Step[];
+}
+
+export class Session {
+    readonly steps: Step[] = [];
+
+    // Runs `f` on `input` and records what it returned or threw, rethrowing errors
+    record(input: number, f: (input: number) => string): string {
+        try {
+            const output = f(input);
+            this.steps.push({ input, output });
+            return output;
+        } catch (e) {
+            this.steps.push({ input, error: e.message });
+            throw e;
+        }
+    }
+
+    toJSON(): Trace {
+        return { steps: this.steps };
+    }
+}
+
+// Runs each step of `trace` through `f`, returning a description of every step that behaves
+// differently from when it was recorded
+export function replay(trace: Trace, f: (input: number) => string): string[] {
+    const mismatches: string[] = [];
+    trace.steps.forEach((step, i) => {
+        let actual: Step;
+        try {
+            actual = { input: step.input, output: f(step.input) };
+        } catch (e) {
+            actual = { input: step.input, error: e.message };
+        }
+        if (actual.output !== step.output || actual.error !== step.error) {
+            mismatches.push(`step ${i}: expected ${JSON.stringify(step)}, got ${JSON.stringify(actual)}`);
+        }
+    });
+    return mismatches;
+}
@@ -1,4 +1,5 @@
 import { FFIError } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 
 /**
 
@@ -8,6 +9,18 @@ import { FFIError } from "./diplomat-runtime"
  */
 export class ICU4XDataProvider {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): ICU4XDataProvider;
+
   /**
 
    * See the {@link https://unicode-org.github.io/icu4x-docs/doc/icu_testdata/fn.get_static_provider.html Rust documentation for `get_static_provider`} for more information.
@@ -7,14 +7,27 @@ const ICU4XDataProvider_box_destroy_registry = new FinalizationRegistry(underlyi
 
 export class ICU4XDataProvider {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      ICU4XDataProvider_box_destroy_registry.register(this, underlying);
+      ICU4XDataProvider_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned ICU4XDataProvider objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, ICU4XDataProvider_box_destroy_registry, "ICU4XDataProvider");
+  }
+
+  static fromTransferable(transferable) {
+    return new ICU4XDataProvider(diplomatRuntime.fromTransferable(transferable, "ICU4XDataProvider"), true, []);
+  }
+
   static new_static() {
     return new ICU4XDataProvider(wasm.ICU4XDataProvider_new_static(), true, []);
   }
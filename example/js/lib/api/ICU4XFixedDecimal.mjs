@@ -7,14 +7,27 @@ const ICU4XFixedDecimal_box_destroy_registry = new FinalizationRegistry(underlyi
 
 export class ICU4XFixedDecimal {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      ICU4XFixedDecimal_box_destroy_registry.register(this, underlying);
+      ICU4XFixedDecimal_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned ICU4XFixedDecimal objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, ICU4XFixedDecimal_box_destroy_registry, "ICU4XFixedDecimal");
+  }
+
+  static fromTransferable(transferable) {
+    return new ICU4XFixedDecimal(diplomatRuntime.fromTransferable(transferable, "ICU4XFixedDecimal"), true, []);
+  }
+
   static new(arg_v) {
     return new ICU4XFixedDecimal(wasm.ICU4XFixedDecimal_new(arg_v), true, []);
   }
@@ -1,4 +1,5 @@
 import { FFIError } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { ICU4XDataProvider } from "./ICU4XDataProvider";
 import { ICU4XFixedDecimal } from "./ICU4XFixedDecimal";
 import { ICU4XFixedDecimalFormatterOptions } from "./ICU4XFixedDecimalFormatterOptions";
@@ -12,6 +13,18 @@ import { ICU4XLocale } from "./ICU4XLocale";
  */
 export class ICU4XFixedDecimalFormatter {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): ICU4XFixedDecimalFormatter;
+
   /**
 
    * Creates a new {@link ICU4XFixedDecimalFormatter `ICU4XFixedDecimalFormatter`} from locale data.
@@ -8,14 +8,27 @@ const ICU4XFixedDecimalFormatter_box_destroy_registry = new FinalizationRegistry
 
 export class ICU4XFixedDecimalFormatter {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      ICU4XFixedDecimalFormatter_box_destroy_registry.register(this, underlying);
+      ICU4XFixedDecimalFormatter_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned ICU4XFixedDecimalFormatter objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, ICU4XFixedDecimalFormatter_box_destroy_registry, "ICU4XFixedDecimalFormatter");
+  }
+
+  static fromTransferable(transferable) {
+    return new ICU4XFixedDecimalFormatter(diplomatRuntime.fromTransferable(transferable, "ICU4XFixedDecimalFormatter"), true, []);
+  }
+
   static try_new(arg_locale, arg_provider, arg_options) {
     const field_grouping_strategy_arg_options = arg_options["grouping_strategy"];
     const field_some_other_config_arg_options = arg_options["some_other_config"];
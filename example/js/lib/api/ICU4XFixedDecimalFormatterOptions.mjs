@@ -5,7 +5,7 @@ import { ICU4XFixedDecimalGroupingStrategy_js_to_rust, ICU4XFixedDecimalGrouping
 export class ICU4XFixedDecimalFormatterOptions {
   constructor(underlying) {
     this.grouping_strategy = ICU4XFixedDecimalGroupingStrategy_rust_to_js[diplomatRuntime.enumDiscriminant(wasm, underlying)];
-    this.some_other_config = (new Uint8Array(wasm.memory.buffer, underlying + 4, 1))[0] == 1;
+    this.some_other_config = diplomatRuntime.readNumber(wasm, underlying + 4, "Uint8") == 1;
   }
 
   static default() {
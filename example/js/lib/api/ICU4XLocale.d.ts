@@ -1,3 +1,4 @@
+import { DiplomatTransferable } from "./diplomat-runtime"
 
 /**
 
@@ -7,6 +8,18 @@
  */
 export class ICU4XLocale {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): ICU4XLocale;
+
   /**
 
    * Construct an {@link ICU4XLocale `ICU4XLocale`} from a locale identifier represented as a string.
@@ -7,14 +7,27 @@ const ICU4XLocale_box_destroy_registry = new FinalizationRegistry(underlying =>
 
 export class ICU4XLocale {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      ICU4XLocale_box_destroy_registry.register(this, underlying);
+      ICU4XLocale_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned ICU4XLocale objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, ICU4XLocale_box_destroy_registry, "ICU4XLocale");
+  }
+
+  static fromTransferable(transferable) {
+    return new ICU4XLocale(diplomatRuntime.fromTransferable(transferable, "ICU4XLocale"), true, []);
+  }
+
   static new(arg_name) {
     const buf_arg_name = diplomatRuntime.DiplomatBuf.str8(wasm, arg_name);
     const diplomat_out = new ICU4XLocale(wasm.ICU4XLocale_new(buf_arg_name.ptr, buf_arg_name.size), true, []);
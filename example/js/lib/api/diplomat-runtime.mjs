@@ -1,15 +1,19 @@
+// Pointers and lengths are numbers from wasm32 modules, and BigInts from wasm64 modules.
+// The helpers below take either.
+
 export function readString8(wasm, ptr, len) {
-  const buf = new Uint8Array(wasm.memory.buffer, ptr, len);
-  return (new TextDecoder("utf-8")).decode(buf)
+  const buf = new Uint8Array(wasm.memory.buffer, Number(ptr), Number(len));
+  // A leading byte order mark is part of the string Rust wrote, not an encoding marker
+  return (new TextDecoder("utf-8", { ignoreBOM: true })).decode(buf)
 }
 
 export function readString16(wasm, ptr, len) {
-  const buf = new Uint16Array(wasm.memory.buffer, ptr, len);
+  const buf = readNumbers(wasm, ptr, len, "Uint16");
   return String.fromCharCode.apply(null, buf)
 }
 
 export function withWriteable(wasm, callback) {
-  const writeable = wasm.diplomat_buffer_writeable_create(0);
+  const writeable = wasm.diplomat_buffer_writeable_create(usize(wasm, 0));
   try {
     callback(writeable);
     const outStringPtr = wasm.diplomat_buffer_writeable_get_bytes(writeable);
@@ -46,17 +50,158 @@ export function extractCodePoint(str, param) {
 // one of those types is another ptr. Call this method to get access to the returned
 // ptr, so the return buffer can be freed.
 export function ptrRead(wasm, ptr) {
-  return (new Uint32Array(wasm.memory.buffer, ptr, 1))[0];
+  return readNumber(wasm, ptr, pointerWidth(wasm) === 64 ? "BigUint64" : "Uint32");
 }
 
 // Get the flag of a result type.
 export function resultFlag(wasm, ptr, offset) {
-  return (new Uint8Array(wasm.memory.buffer, ptr + offset, 1))[0];
+  return (new Uint8Array(wasm.memory.buffer, Number(ptr) + offset, 1))[0];
+}
+
+// The widths of the pointers of each WASM module, cached as they don't change.
+const pointerWidths = new WeakMap();
+
+// The width in bits of pointers and `usize` in `wasm`. Modules built with older
+// runtimes don't export it, and are assumed to be wasm32.
+export function pointerWidth(wasm) {
+  let width = pointerWidths.get(wasm);
+  if (width === undefined) {
+    width = wasm.diplomat_pointer_width?.() ?? 32;
+    pointerWidths.set(wasm, width);
+  }
+  return width;
+}
+
+// `n` as a pointer or `usize` argument to the exports of `wasm`, which wasm64 modules
+// take as a BigInt.
+export function usize(wasm, n) {
+  return pointerWidth(wasm) === 64 ? BigInt(n) : n;
 }
 
 // Get the discriminant of a Rust enum.
 export function enumDiscriminant(wasm, ptr) {
-  return (new Int32Array(wasm.memory.buffer, ptr, 1))[0]
+  return readNumber(wasm, ptr, "Int32");
+}
+
+// WASM memory is little-endian, but typed arrays use the byte order of the host. The
+// helpers below read and write numbers in WASM memory correctly on big-endian hosts
+// too, like s390x. `kind` names a typed array without its `Array` suffix, e.g. "Uint32".
+export const LITTLE_ENDIAN_HOST = new Uint8Array(new Uint16Array([1]).buffer)[0] === 1;
+
+// The DataView over each WASM memory, cached until its buffer changes. Growing the memory
+// detaches its buffer and `memory.buffer` returns a new one, which gets a new view.
+const memoryViews = new WeakMap();
+
+function memoryView(wasm) {
+  const buffer = wasm.memory.buffer;
+  let view = memoryViews.get(wasm.memory);
+  if (view === undefined || view.buffer !== buffer) {
+    view = new DataView(buffer);
+    memoryViews.set(wasm.memory, view);
+  }
+  return view;
+}
+
+// Read the number of type `kind` at `ptr`.
+export function readNumber(wasm, ptr, kind) {
+  return memoryView(wasm)[`get${kind}`](Number(ptr), true);
+}
+
+// Write `value` at `ptr` as a number of type `kind`.
+export function writeNumber(wasm, ptr, kind, value) {
+  memoryView(wasm)[`set${kind}`](Number(ptr), value, true);
+}
+
+// Copy the `len` numbers of type `kind` starting at `ptr` into a new typed array.
+export function readNumbers(wasm, ptr, len, kind) {
+  ptr = Number(ptr);
+  len = Number(len);
+  const TypedArray = globalThis[`${kind}Array`];
+  if (LITTLE_ENDIAN_HOST) {
+    return new TypedArray(wasm.memory.buffer, ptr, len).slice();
+  }
+  const view = memoryView(wasm);
+  const values = new TypedArray(len);
+  for (let i = 0; i < len; i++) {
+    values[i] = view[`get${kind}`](ptr + i * TypedArray.BYTES_PER_ELEMENT, true);
+  }
+  return values;
+}
+
+// Write the values of `list` from `ptr` on as numbers of type `kind`.
+export function writeNumbers(wasm, ptr, kind, list) {
+  ptr = Number(ptr);
+  const TypedArray = globalThis[`${kind}Array`];
+  if (LITTLE_ENDIAN_HOST) {
+    new TypedArray(wasm.memory.buffer, ptr, list.length).set(list);
+    return;
+  }
+  const view = memoryView(wasm);
+  for (let i = 0; i < list.length; i++) {
+    view[`set${kind}`](ptr + i * TypedArray.BYTES_PER_ELEMENT, list[i], true);
+  }
+}
+
+// Check that the WASM module lays out the values it returns, and orders their bytes, the
+// way these bindings read them, using `diplomat_self_test` from diplomat-runtime. Throws
+// naming the first value read wrong. Modules built with older runtimes are not checked.
+export function selfTest(wasm) {
+  if (wasm.diplomat_self_test === undefined) {
+    return;
+  }
+  // The layout of `DiplomatSelfTest`, whose slice takes 16 bytes on wasm64 instead of 8
+  const wasm64 = pointerWidth(wasm) === 64;
+  const size = wasm64 ? 56 : 48;
+  const buf = wasm.diplomat_alloc(usize(wasm, size), usize(wasm, 8));
+  const ptr = Number(buf);
+  try {
+    wasm.diplomat_self_test(buf);
+    const [sliceData, sliceLen] = readNumbers(wasm, ptr + 40, 2, wasm64 ? "BigUint64" : "Uint32");
+    const checks = [
+      ["the byte order flag", readNumber(wasm, ptr, "Uint8"), 0],
+      ["a u16", readNumber(wasm, ptr + 2, "Uint16"), 0x0102],
+      ["a u32", readNumber(wasm, ptr + 4, "Uint32"), 0x01020304],
+      ["a u64", readNumber(wasm, ptr + 8, "BigUint64"), 0x0102030405060708n],
+      ["an f64", readNumber(wasm, ptr + 16, "Float64"), -2.5],
+      ["an ok result", readNumber(wasm, ptr + 24, "Uint32"), 0x01020304],
+      ["the flag of an ok result", resultFlag(wasm, ptr + 24, 4), 1],
+      ["an error result", readNumber(wasm, ptr + 32, "Uint32"), 0x05060708],
+      ["the flag of an error result", resultFlag(wasm, ptr + 32, 4), 0],
+      ["a slice", readNumbers(wasm, sliceData, sliceLen, "Uint16").join(), "258,772,1286"],
+    ];
+    for (const [what, actual, expected] of checks) {
+      if (actual !== expected) {
+        throw new Error(`Diplomat self-test failed: read ${what} as ${actual}, expected ${expected}`);
+      }
+    }
+  } finally {
+    wasm.diplomat_free(buf, usize(wasm, size), usize(wasm, 8));
+  }
+}
+
+// Moves ownership of an opaque object's pointer out of its wrapper so that it
+// can be posted to another worker sharing the same WASM memory.
+//
+// The finalizer is unregistered so the pointer isn't freed when the sender's
+// wrapper is collected, and the wrapper is revoked so it can't be used again.
+export function toTransferable(wrapper, registry, diplomatType) {
+  const underlying = wrapper.underlying;
+  registry.unregister(wrapper);
+  Object.defineProperty(wrapper, "underlying", {
+    get() {
+      throw new Error(`This ${diplomatType} has been transferred and can no longer be used`);
+    }
+  });
+  return { diplomatType, underlying };
+}
+
+// Returns the pointer of a value produced by `toTransferable`, checking that it
+// was produced for the expected type.
+export function fromTransferable(transferable, diplomatType) {
+  if (transferable.diplomatType !== diplomatType) {
+    throw new TypeError(`Expected a transferred ${diplomatType}, found ${transferable.diplomatType}`);
+  }
+  return transferable.underlying;
 }
 
 // A wrapper around a slice of WASM memory that can be freed manually or
@@ -81,50 +226,49 @@ export class DiplomatBuf {
       }
     }
 
-    const ptr = wasm.diplomat_alloc(utf8Length, 1);
+    const ptr = wasm.diplomat_alloc(usize(wasm, utf8Length), usize(wasm, 1));
 
-    const result = (new TextEncoder()).encodeInto(string, new Uint8Array(wasm.memory.buffer, ptr, utf8Length));
+    const result = (new TextEncoder()).encodeInto(string, new Uint8Array(wasm.memory.buffer, Number(ptr), utf8Length));
     console.assert(string.length == result.read && utf8Length == result.written, "UTF-8 write error");
 
-    return new DiplomatBuf(ptr, utf8Length, () => wasm.diplomat_free(ptr, utf8Length, 1));
+    return new DiplomatBuf(ptr, usize(wasm, utf8Length), () => wasm.diplomat_free(ptr, usize(wasm, utf8Length), usize(wasm, 1)));
   }
 
   static str16 = (wasm, string) => {
     const byteLength = string.length * 2;
-    const ptr = wasm.diplomat_alloc(byteLength, 2);
+    const ptr = wasm.diplomat_alloc(usize(wasm, byteLength), usize(wasm, 2));
 
-    const destination = new Uint16Array(wasm.memory.buffer, ptr, byteLength);
-    for (var i; i < string.length; i++) {
-      destination[i] = string.charCodeAt(i);
+    const codeUnits = new Uint16Array(string.length);
+    for (var i = 0; i < string.length; i++) {
+      codeUnits[i] = string.charCodeAt(i);
     }
+    writeNumbers(wasm, ptr, "Uint16", codeUnits);
 
-    return new DiplomatBuf(ptr, string.length, () => wasm.diplomat_free(ptr, byteLength, 2));
+    return new DiplomatBuf(ptr, usize(wasm, string.length), () => wasm.diplomat_free(ptr, usize(wasm, byteLength), usize(wasm, 2)));
   }
 
   static slice = (wasm, list, rustType) => {
-    const elementSize = rustType == "u8" || rustType == "i8" || rustType == "bool" ? 1 :
-      rustType == "u16" || rustType == "i16" ? 2 :
-        rustType == "u64" || rustType == "i64" || rustType == "f64" ? 8 :
-          4;
+    const wasm64 = pointerWidth(wasm) === 64;
+    const kind =
+      rustType == "u8" || rustType == "bool" ? "Uint8" :
+        rustType == "i8" ? "Int8" :
+          rustType == "u16" ? "Uint16" :
+            rustType == "i16" ? "Int16" :
+              rustType == "i32" || rustType == "isize" && !wasm64 ? "Int32" :
+                rustType == "u64" || rustType == "usize" && wasm64 ? "BigUint64" :
+                  rustType == "i64" || rustType == "isize" ? "BigInt64" :
+                    rustType == "f32" ? "Float32" :
+                      rustType == "f64" ? "Float64" :
+                        "Uint32";
+    const elementSize = globalThis[`${kind}Array`].BYTES_PER_ELEMENT;
 
     const byteLength = list.length * elementSize;
-    const ptr = wasm.diplomat_alloc(byteLength, elementSize);
-
-    // Create an array view of the buffer. This gives us the `set` method which correctly handles untyped values
-    const destination =
-      rustType == "u8" || rustType == "bool" ? new Uint8Array(wasm.memory.buffer, ptr, byteLength) :
-        rustType == "i8" ? new Int8Array(wasm.memory.buffer, ptr, byteLength) :
-          rustType == "u16" ? new Uint16Array(wasm.memory.buffer, ptr, byteLength) :
-            rustType == "i16" ? new Int16Array(wasm.memory.buffer, ptr, byteLength) :
-              rustType == "i32" || rustType == "isize" ? new Int32Array(wasm.memory.buffer, ptr, byteLength) :
-                rustType == "u64" ? new BigUint64Array(wasm.memory.buffer, ptr, byteLength) :
-                  rustType == "i64" ? new BigInt64Array(wasm.memory.buffer, ptr, byteLength) :
-                    rustType == "f32" ? new Float32Array(wasm.memory.buffer, ptr, byteLength) :
-                      rustType == "f64" ? new Float64Array(wasm.memory.buffer, ptr, byteLength) :
-                        new Uint32Array(wasm.memory.buffer, ptr, byteLength);
-    destination.set(list);
-
-    return new DiplomatBuf(ptr, list.length, () => wasm.diplomat_free(ptr, byteLength, elementSize));
+    const ptr = wasm.diplomat_alloc(usize(wasm, byteLength), usize(wasm, elementSize));
+
+    // Writing through typed arrays correctly handles untyped values, like booleans
+    writeNumbers(wasm, ptr, kind, list);
+
+    return new DiplomatBuf(ptr, usize(wasm, list.length), () => wasm.diplomat_free(ptr, usize(wasm, byteLength), usize(wasm, elementSize)));
   }
 
   constructor(ptr, size, free) {
@@ -1,4 +1,5 @@
 export { FFIError, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, char } from './diplomat-runtime';
+export { init, InitOptions } from './diplomat-wasm';
 export { ICU4XDataProvider } from './ICU4XDataProvider';
 export { ICU4XFixedDecimal } from './ICU4XFixedDecimal';
 export { ICU4XFixedDecimalFormatter } from './ICU4XFixedDecimalFormatter';
@@ -1,4 +1,5 @@
 export { FFIError } from './diplomat-runtime.mjs';
+export { init } from './diplomat-wasm.mjs';
 export { ICU4XDataProvider } from './ICU4XDataProvider.mjs';
 export { ICU4XFixedDecimal } from './ICU4XFixedDecimal.mjs';
 export { ICU4XFixedDecimalFormatter } from './ICU4XFixedDecimalFormatter.mjs';
@@ -0,0 +1,8 @@
+// Destroys the Rust object behind an opaque once Perceus drops the last Koka reference to it.
+#include "ICU4XFixedDecimal.h"
+
+static void ICU4XFixedDecimal_finalize(void* p, kk_block_t* block, kk_context_t* _ctx) {
+  kk_unused(block);
+  kk_unused(_ctx);
+  ICU4XFixedDecimal_destroy((ICU4XFixedDecimal*)p);
+}
\ No newline at end of file
@@ -14,9 +14,9 @@ extern "C" {
 
 
 typedef struct BorrowedFields {
-  struct { const char16_t* data; size_t len; } a;
-  struct { const char* data; size_t len; } b;
-  struct { const char* data; size_t len; } c;
+  DiplomatU16StringView a;
+  DiplomatStringView b;
+  DiplomatStringView c;
 } BorrowedFields;
 
 
@@ -23,6 +23,8 @@ Opaque* Opaque_new();
 
 void Opaque_assert_struct(const Opaque* self, MyStruct s);
 
+Opaque* Opaque_duplicate(const Opaque* self);
+
 size_t Opaque_returns_usize();
 
 ImportedStruct Opaque_returns_imported();
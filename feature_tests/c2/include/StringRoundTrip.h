@@ -0,0 +1,34 @@
+#ifndef StringRoundTrip_H
+#define StringRoundTrip_H
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stddef.h>
+#include <stdbool.h>
+#include "diplomat_runtime.h"
+
+#include "StringRoundTrip.d.h"
+
+#ifdef __cplusplus
+namespace capi {
+extern "C" {
+#endif // __cplusplus
+
+
+void StringRoundTrip_echo(const char* s_data, size_t s_len, DiplomatWriteable* writeable);
+
+void StringRoundTrip_echo_utf16(const char16_t* s_data, size_t s_len, DiplomatWriteable* writeable);
+
+size_t StringRoundTrip_utf8_len(const char* s_data, size_t s_len);
+
+size_t StringRoundTrip_utf16_len(const char16_t* s_data, size_t s_len);
+
+void StringRoundTrip_destroy(StringRoundTrip* self);
+
+
+#ifdef __cplusplus
+} // extern "C"
+} // namespace capi
+#endif // __cplusplus
+
+#endif // StringRoundTrip_H
@@ -35,6 +35,27 @@ typedef struct DiplomatWriteable {
 
 DiplomatWriteable diplomat_simple_writeable(char* buf, size_t buf_size);
 
+// Writes the opaques never destroyed, only exported with the `leak-report` feature of the runtime
+size_t diplomat_leak_report(DiplomatWriteable* out);
+
+typedef struct DiplomatBatch DiplomatBatch;
+
+DiplomatBatch* diplomat_batch_begin(void);
+void* diplomat_batch_alloc(DiplomatBatch* batch, size_t size, size_t align);
+void diplomat_batch_end(DiplomatBatch* batch);
+
+// Allocations made with the Rust allocator, e.g. for owned slices crossing the boundary
+void* diplomat_alloc(size_t size, size_t align);
+void diplomat_free(void* ptr, size_t size, size_t align);
+
+typedef struct DiplomatWaker {
+    const void* data;
+    void (*wake)(const void*);
+    void (*destructor)(const void*);
+} DiplomatWaker;
+
+typedef struct DiplomatFuture DiplomatFuture;
+
 #define MAKE_SLICES(name, c_ty) \
     typedef struct Diplomat##name##View { \
         const c_ty* data; \
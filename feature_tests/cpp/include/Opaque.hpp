@@ -35,6 +35,7 @@ class Opaque {
    * Additional information: [1](https://docs.rs/Something/latest/struct.Something.html#method.something_small), [2](https://docs.rs/SomethingElse/latest/struct.SomethingElse.html#method.something)
    */
   void assert_struct(MyStruct s) const;
+  Opaque duplicate() const;
   static size_t returns_usize();
   static ImportedStruct returns_imported();
   static int8_t cmp();
@@ -58,6 +59,9 @@ inline void Opaque::assert_struct(MyStruct s) const {
   MyStruct diplomat_wrapped_struct_s = s;
   capi::Opaque_assert_struct(this->inner.get(), capi::MyStruct{ .a = diplomat_wrapped_struct_s.a, .b = diplomat_wrapped_struct_s.b, .c = diplomat_wrapped_struct_s.c, .d = diplomat_wrapped_struct_s.d, .e = diplomat_wrapped_struct_s.e, .f = diplomat_wrapped_struct_s.f, .g = static_cast<capi::MyEnum>(diplomat_wrapped_struct_s.g) });
 }
+inline Opaque Opaque::duplicate() const {
+  return Opaque(capi::Opaque_duplicate(this->inner.get()));
+}
 inline size_t Opaque::returns_usize() {
   return capi::Opaque_returns_usize();
 }
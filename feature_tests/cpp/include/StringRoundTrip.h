@@ -0,0 +1,35 @@
+#ifndef StringRoundTrip_H
+#define StringRoundTrip_H
+#include <stdio.h>
+#include <stdint.h>
+#include <stddef.h>
+#include <stdbool.h>
+#include "diplomat_runtime.h"
+
+#ifdef __cplusplus
+namespace capi {
+#endif
+
+typedef struct StringRoundTrip StringRoundTrip;
+#ifdef __cplusplus
+} // namespace capi
+#endif
+#ifdef __cplusplus
+namespace capi {
+extern "C" {
+#endif
+
+void StringRoundTrip_echo(const char* s_data, size_t s_len, DiplomatWriteable* out);
+
+void StringRoundTrip_echo_utf16(const char16_t* s_data, size_t s_len, DiplomatWriteable* out);
+
+size_t StringRoundTrip_utf8_len(const char* s_data, size_t s_len);
+
+size_t StringRoundTrip_utf16_len(const char16_t* s_data, size_t s_len);
+void StringRoundTrip_destroy(StringRoundTrip* self);
+
+#ifdef __cplusplus
+} // extern "C"
+} // namespace capi
+#endif
+#endif
@@ -0,0 +1,85 @@
+#ifndef StringRoundTrip_HPP
+#define StringRoundTrip_HPP
+#include <stdint.h>
+#include <stddef.h>
+#include <stdbool.h>
+#include <algorithm>
+#include <memory>
+#include <variant>
+#include <optional>
+#include "diplomat_runtime.hpp"
+
+#include "StringRoundTrip.h"
+
+
+/**
+ * A destruction policy for using StringRoundTrip with std::unique_ptr.
+ */
+struct StringRoundTripDeleter {
+  void operator()(capi::StringRoundTrip* l) const noexcept {
+    capi::StringRoundTrip_destroy(l);
+  }
+};
+
+/**
+ * Sends strings back to check how bindings convert them. Lone surrogates become U+FFFD,
+ * everything else, byte order marks included, comes back unchanged.
+ */
+class StringRoundTrip {
+ public:
+
+  /**
+   * Warning: Passing ill-formed UTF-8 is undefined behavior (and may be memory-unsafe).
+   */
+  template<typename W> static void echo_to_writeable(const std::string_view s, W& out);
+
+  /**
+   * Warning: Passing ill-formed UTF-8 is undefined behavior (and may be memory-unsafe).
+   */
+  static std::string echo(const std::string_view s);
+  template<typename W> static void echo_utf16_to_writeable(const std::u16string_view s, W& out);
+  static std::string echo_utf16(const std::u16string_view s);
+
+  /**
+   * Warning: Passing ill-formed UTF-8 is undefined behavior (and may be memory-unsafe).
+   */
+  static size_t utf8_len(const std::string_view s);
+  static size_t utf16_len(const std::u16string_view s);
+  inline const capi::StringRoundTrip* AsFFI() const { return this->inner.get(); }
+  inline capi::StringRoundTrip* AsFFIMut() { return this->inner.get(); }
+  inline explicit StringRoundTrip(capi::StringRoundTrip* i) : inner(i) {}
+  StringRoundTrip() = default;
+  StringRoundTrip(StringRoundTrip&&) noexcept = default;
+  StringRoundTrip& operator=(StringRoundTrip&& other) noexcept = default;
+ private:
+  std::unique_ptr<capi::StringRoundTrip, StringRoundTripDeleter> inner;
+};
+
+
+template<typename W> inline void StringRoundTrip::echo_to_writeable(const std::string_view s, W& out) {
+  capi::DiplomatWriteable out_writer = diplomat::WriteableTrait<W>::Construct(out);
+  capi::StringRoundTrip_echo(s.data(), s.size(), &out_writer);
+}
+inline std::string StringRoundTrip::echo(const std::string_view s) {
+  std::string diplomat_writeable_string;
+  capi::DiplomatWriteable diplomat_writeable_out = diplomat::WriteableFromString(diplomat_writeable_string);
+  capi::StringRoundTrip_echo(s.data(), s.size(), &diplomat_writeable_out);
+  return diplomat_writeable_string;
+}
+template<typename W> inline void StringRoundTrip::echo_utf16_to_writeable(const std::u16string_view s, W& out) {
+  capi::DiplomatWriteable out_writer = diplomat::WriteableTrait<W>::Construct(out);
+  capi::StringRoundTrip_echo_utf16(s.data(), s.size(), &out_writer);
+}
+inline std::string StringRoundTrip::echo_utf16(const std::u16string_view s) {
+  std::string diplomat_writeable_string;
+  capi::DiplomatWriteable diplomat_writeable_out = diplomat::WriteableFromString(diplomat_writeable_string);
+  capi::StringRoundTrip_echo_utf16(s.data(), s.size(), &diplomat_writeable_out);
+  return diplomat_writeable_string;
+}
+inline size_t StringRoundTrip::utf8_len(const std::string_view s) {
+  return capi::StringRoundTrip_utf8_len(s.data(), s.size());
+}
+inline size_t StringRoundTrip::utf16_len(const std::u16string_view s) {
+  return capi::StringRoundTrip_utf16_len(s.data(), s.size());
+}
+#endif
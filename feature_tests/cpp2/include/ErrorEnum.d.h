@@ -18,6 +18,14 @@ typedef enum ErrorEnum {
   ErrorEnum_Bar = 1,
 } ErrorEnum;
 
+static inline const char* ErrorEnum_message(ErrorEnum code) {
+  switch (code) {
+    case ErrorEnum_Foo: return "Foo";
+    case ErrorEnum_Bar: return "Bar";
+    default: return "Unknown ErrorEnum";
+  }
+}
+
 
 #ifdef __cplusplus
 } // extern "C"
@@ -23,6 +23,8 @@ public:
 
   inline void assert_struct(MyStruct s) const;
 
+  inline std::unique_ptr<Opaque> clone() const;
+
   inline static size_t returns_usize();
 
   inline static ImportedStruct returns_imported();
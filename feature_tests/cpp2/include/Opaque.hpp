@@ -25,6 +25,11 @@ inline void Opaque::assert_struct(MyStruct s) const {
     s.AsFFI());
 }
 
+inline std::unique_ptr<Opaque> Opaque::clone() const {
+  auto result = capi::Opaque_duplicate(this->AsFFI());
+  return std::unique_ptr<Opaque>(Opaque::FromFFI(result));
+}
+
 inline size_t Opaque::returns_usize() {
   auto result = capi::Opaque_returns_usize();
   return result;
@@ -0,0 +1,40 @@
+#ifndef StringRoundTrip_D_HPP
+#define StringRoundTrip_D_HPP
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stddef.h>
+#include <stdbool.h>
+#include <memory>
+#include <optional>
+#include "diplomat_runtime.hpp"
+#include "StringRoundTrip.d.h"
+
+
+class StringRoundTrip {
+public:
+
+  inline static std::string echo(std::string_view s);
+
+  inline static std::string echo_utf16(std::u16string_view s);
+
+  inline static size_t utf8_len(std::string_view s);
+
+  inline static size_t utf16_len(std::u16string_view s);
+
+  inline const capi::StringRoundTrip* AsFFI() const;
+  inline capi::StringRoundTrip* AsFFI();
+  inline static const StringRoundTrip* FromFFI(const capi::StringRoundTrip* ptr);
+  inline static StringRoundTrip* FromFFI(capi::StringRoundTrip* ptr);
+  inline static void operator delete(void* ptr);
+private:
+  StringRoundTrip() = delete;
+  StringRoundTrip(const StringRoundTrip&) = delete;
+  StringRoundTrip(StringRoundTrip&&) noexcept = delete;
+  StringRoundTrip operator=(const StringRoundTrip&) = delete;
+  StringRoundTrip operator=(StringRoundTrip&&) noexcept = delete;
+  static void operator delete[](void*, size_t) = delete;
+};
+
+
+#endif // StringRoundTrip_D_HPP
@@ -0,0 +1,67 @@
+#ifndef StringRoundTrip_HPP
+#define StringRoundTrip_HPP
+
+#include "StringRoundTrip.d.hpp"
+
+#include <stdio.h>
+#include <stdint.h>
+#include <stddef.h>
+#include <stdbool.h>
+#include <memory>
+#include <optional>
+#include "diplomat_runtime.hpp"
+#include "StringRoundTrip.h"
+
+
+inline std::string StringRoundTrip::echo(std::string_view s) {
+  std::string output;
+  capi::DiplomatWriteable writeable = diplomat::WriteableFromString(output);
+  capi::StringRoundTrip_echo(s.data(),
+    s.size(),
+    &writeable);
+  return output;
+}
+
+inline std::string StringRoundTrip::echo_utf16(std::u16string_view s) {
+  std::string output;
+  capi::DiplomatWriteable writeable = diplomat::WriteableFromString(output);
+  capi::StringRoundTrip_echo_utf16(s.data(),
+    s.size(),
+    &writeable);
+  return output;
+}
+
+inline size_t StringRoundTrip::utf8_len(std::string_view s) {
+  auto result = capi::StringRoundTrip_utf8_len(s.data(),
+    s.size());
+  return result;
+}
+
+inline size_t StringRoundTrip::utf16_len(std::u16string_view s) {
+  auto result = capi::StringRoundTrip_utf16_len(s.data(),
+    s.size());
+  return result;
+}
+
+inline const capi::StringRoundTrip* StringRoundTrip::AsFFI() const {
+  return reinterpret_cast<const capi::StringRoundTrip*>(this);
+}
+
+inline capi::StringRoundTrip* StringRoundTrip::AsFFI() {
+  return reinterpret_cast<capi::StringRoundTrip*>(this);
+}
+
+inline const StringRoundTrip* StringRoundTrip::FromFFI(const capi::StringRoundTrip* ptr) {
+  return reinterpret_cast<const StringRoundTrip*>(ptr);
+}
+
+inline StringRoundTrip* StringRoundTrip::FromFFI(capi::StringRoundTrip* ptr) {
+  return reinterpret_cast<StringRoundTrip*>(ptr);
+}
+
+inline void StringRoundTrip::operator delete(void* ptr) {
+  capi::StringRoundTrip_destroy(reinterpret_cast<capi::StringRoundTrip*>(ptr));
+}
+
+
+#endif // StringRoundTrip_HPP
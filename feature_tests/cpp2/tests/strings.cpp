@@ -0,0 +1,106 @@
+#include <iostream>
+#include <string>
+#include "../include/StringRoundTrip.hpp"
+#include "assert.hpp"
+
+// Random strings round-tripped through Rust: valid UTF-8 comes back unchanged, UTF-16 comes
+// back as UTF-8 with lone surrogates replaced by U+FFFD. Byte order marks are kept as is.
+
+static uint64_t state = 0x9E3779B97F4A7C15;
+
+// xorshift64*, reproducible across compilers and standard libraries
+static uint32_t next_random(uint32_t bound) {
+    state ^= state >> 12;
+    state ^= state << 25;
+    state ^= state >> 27;
+    return (uint32_t)((state * 0x2545F4914F6CDD1D) >> 32) % bound;
+}
+
+static void push_utf8(std::string& out, char32_t c) {
+    if (c < 0x80) {
+        out += (char)c;
+    } else if (c < 0x800) {
+        out += (char)(0xC0 | (c >> 6));
+        out += (char)(0x80 | (c & 0x3F));
+    } else if (c < 0x10000) {
+        out += (char)(0xE0 | (c >> 12));
+        out += (char)(0x80 | ((c >> 6) & 0x3F));
+        out += (char)(0x80 | (c & 0x3F));
+    } else {
+        out += (char)(0xF0 | (c >> 18));
+        out += (char)(0x80 | ((c >> 12) & 0x3F));
+        out += (char)(0x80 | ((c >> 6) & 0x3F));
+        out += (char)(0x80 | (c & 0x3F));
+    }
+}
+
+static void push_utf16(std::u16string& out, char32_t c) {
+    if (c >= 0x10000) {
+        out += (char16_t)(0xD800 + ((c - 0x10000) >> 10));
+        out += (char16_t)(0xDC00 + ((c - 0x10000) & 0x3FF));
+    } else {
+        out += (char16_t)c;
+    }
+}
+
+// The UTF-8 Rust is expected to write back for `s`
+static std::string expected_utf8(const std::u16string& s) {
+    std::string out;
+    for (size_t i = 0; i < s.size(); i++) {
+        char32_t c = s[i];
+        if (c >= 0xD800 && c < 0xDC00 && i + 1 < s.size() && s[i + 1] >= 0xDC00 && s[i + 1] < 0xE000) {
+            c = 0x10000 + ((c - 0xD800) << 10) + (s[++i] - 0xDC00);
+        } else if (c >= 0xD800 && c < 0xE000) {
+            c = 0xFFFD;
+        }
+        push_utf8(out, c);
+    }
+    return out;
+}
+
+// A scalar value from each UTF-8 length, or a byte order mark
+static char32_t random_scalar() {
+    switch (next_random(5)) {
+        case 0: return next_random(0x80);
+        case 1: return 0x80 + next_random(0x800 - 0x80);
+        case 2: {
+            char32_t c = 0x800 + next_random(0x10000 - 0x800 - 0x800);
+            // Skip over the surrogates
+            return c >= 0xD800 ? c + 0x800 : c;
+        }
+        case 3: return 0x10000 + next_random(0x110000 - 0x10000);
+        default: return 0xFEFF;
+    }
+}
+
+int main(int argc, char *argv[]) {
+    for (int round = 0; round < 2000; round++) {
+        std::string utf8;
+        std::u16string utf16;
+        std::u16string with_surrogates;
+        uint32_t length = next_random(16);
+        for (uint32_t i = 0; i < length; i++) {
+            char32_t c = random_scalar();
+            push_utf8(utf8, c);
+            push_utf16(utf16, c);
+            if (next_random(4) == 0) {
+                // A lone surrogate, unless the next code unit happens to complete it
+                with_surrogates += (char16_t)(0xD800 + next_random(0x800));
+            } else {
+                push_utf16(with_surrogates, c);
+            }
+        }
+
+        simple_assert_eq("UTF-8 comes back unchanged", StringRoundTrip::echo(utf8), utf8);
+        simple_assert_eq("UTF-8 length", StringRoundTrip::utf8_len(utf8), utf8.size());
+        simple_assert_eq("valid UTF-16 comes back as UTF-8", StringRoundTrip::echo_utf16(utf16), utf8);
+        simple_assert_eq("UTF-16 length", StringRoundTrip::utf16_len(with_surrogates), with_surrogates.size());
+        simple_assert_eq("lone surrogates become U+FFFD", StringRoundTrip::echo_utf16(with_surrogates), expected_utf8(with_surrogates));
+    }
+
+    // A leading byte order mark is data like any other character
+    simple_assert_eq("BOM is kept", StringRoundTrip::echo("\xEF\xBB\xBFx"), "\xEF\xBB\xBFx");
+    simple_assert_eq("BOM is kept in UTF-16", StringRoundTrip::echo_utf16(u"\uFEFFx"), "\xEF\xBB\xBFx");
+    simple_assert_eq("lone high surrogate at the end", StringRoundTrip::echo_utf16(std::u16string(1, (char16_t)0xD83D)), "\xEF\xBF\xBD");
+    simple_assert_eq("reversed pair", StringRoundTrip::echo_utf16(std::u16string{(char16_t)0xDE00, (char16_t)0xD83D}), "\xEF\xBF\xBD\xEF\xBF\xBD");
+}
@@ -10,6 +10,11 @@ int main(int argc, char *argv[]) {
 
     o->assert_struct(s);
 
+    // `Opaque` cannot be copied, only cloned on the Rust side into a distinct object
+    std::unique_ptr<Opaque> copy = o->clone();
+    simple_assert("clone", (copy.get() != o.get()));
+    copy->assert_struct(s);
+
     simple_assert_eq("struct values", s.a, 17);
     simple_assert_eq("struct values", s.b, true);
     simple_assert_eq("struct values", s.c, 209);
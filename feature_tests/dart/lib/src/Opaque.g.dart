@@ -37,6 +37,11 @@ final class Opaque implements ffi.Finalizable {
     temp.releaseAll();
   }
 
+  Opaque duplicate() {
+    final result = _Opaque_duplicate(_ffi);
+    return Opaque._fromFfi(result, []);
+  }
+
   static int returnsUsize() {
     final result = _Opaque_returns_usize();
     return result;
@@ -68,6 +73,11 @@ external ffi.Pointer<ffi.Opaque> _Opaque_new();
 // ignore: non_constant_identifier_names
 external void _Opaque_assert_struct(ffi.Pointer<ffi.Opaque> self, _MyStructFfi s);
 
+@meta.ResourceIdentifier('Opaque_duplicate')
+@ffi.Native<ffi.Pointer<ffi.Opaque> Function(ffi.Pointer<ffi.Opaque>)>(isLeaf: true, symbol: 'Opaque_duplicate')
+// ignore: non_constant_identifier_names
+external ffi.Pointer<ffi.Opaque> _Opaque_duplicate(ffi.Pointer<ffi.Opaque> self);
+
 @meta.ResourceIdentifier('Opaque_returns_usize')
 @ffi.Native<ffi.Size Function()>(isLeaf: true, symbol: 'Opaque_returns_usize')
 // ignore: non_constant_identifier_names
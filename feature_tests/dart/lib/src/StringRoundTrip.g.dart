@@ -0,0 +1,84 @@
+// generated by diplomat-tool
+
+part of 'lib.g.dart';
+
+/// Sends strings back to check how bindings convert them. Lone surrogates become U+FFFD,
+/// everything else, byte order marks included, comes back unchanged.
+final class StringRoundTrip implements ffi.Finalizable {
+  final ffi.Pointer<ffi.Opaque> _ffi;
+
+  // These are "used" in the sense that they keep dependencies alive
+  // ignore: unused_field
+  final core.List<Object> _selfEdge;
+
+  // This takes in a list of lifetime edges (including for &self borrows)
+  // corresponding to data this may borrow from. These should be flat arrays containing
+  // references to objects, and this object will hold on to them to keep them alive and
+  // maintain borrow validity.
+  StringRoundTrip._fromFfi(this._ffi, this._selfEdge) {
+    if (_selfEdge.isEmpty) {
+      _finalizer.attach(this, _ffi.cast());
+    }
+  }
+
+  static final _finalizer = ffi.NativeFinalizer(ffi.Native.addressOf(_StringRoundTrip_destroy));
+
+  static String echo(String s) {
+    final temp = ffi2.Arena();
+    final sView = s.utf8View;
+    final writeable = _Writeable();
+    _StringRoundTrip_echo(sView.allocIn(temp), sView.length, writeable._ffi);
+    temp.releaseAll();
+    return writeable.finalize();
+  }
+
+  static String echoUtf16(String s) {
+    final temp = ffi2.Arena();
+    final sView = s.utf16View;
+    final writeable = _Writeable();
+    _StringRoundTrip_echo_utf16(sView.allocIn(temp), sView.length, writeable._ffi);
+    temp.releaseAll();
+    return writeable.finalize();
+  }
+
+  static int utf8Len(String s) {
+    final temp = ffi2.Arena();
+    final sView = s.utf8View;
+    final result = _StringRoundTrip_utf8_len(sView.allocIn(temp), sView.length);
+    temp.releaseAll();
+    return result;
+  }
+
+  static int utf16Len(String s) {
+    final temp = ffi2.Arena();
+    final sView = s.utf16View;
+    final result = _StringRoundTrip_utf16_len(sView.allocIn(temp), sView.length);
+    temp.releaseAll();
+    return result;
+  }
+}
+
+@meta.ResourceIdentifier('StringRoundTrip_destroy')
+@ffi.Native<ffi.Void Function(ffi.Pointer<ffi.Void>)>(isLeaf: true, symbol: 'StringRoundTrip_destroy')
+// ignore: non_constant_identifier_names
+external void _StringRoundTrip_destroy(ffi.Pointer<ffi.Void> self);
+
+@meta.ResourceIdentifier('StringRoundTrip_echo')
+@ffi.Native<ffi.Void Function(ffi.Pointer<ffi.Uint8>, ffi.Size, ffi.Pointer<ffi.Opaque>)>(isLeaf: true, symbol: 'StringRoundTrip_echo')
+// ignore: non_constant_identifier_names
+external void _StringRoundTrip_echo(ffi.Pointer<ffi.Uint8> sData, int sLength, ffi.Pointer<ffi.Opaque> writeable);
+
+@meta.ResourceIdentifier('StringRoundTrip_echo_utf16')
+@ffi.Native<ffi.Void Function(ffi.Pointer<ffi.Uint16>, ffi.Size, ffi.Pointer<ffi.Opaque>)>(isLeaf: true, symbol: 'StringRoundTrip_echo_utf16')
+// ignore: non_constant_identifier_names
+external void _StringRoundTrip_echo_utf16(ffi.Pointer<ffi.Uint16> sData, int sLength, ffi.Pointer<ffi.Opaque> writeable);
+
+@meta.ResourceIdentifier('StringRoundTrip_utf8_len')
+@ffi.Native<ffi.Size Function(ffi.Pointer<ffi.Uint8>, ffi.Size)>(isLeaf: true, symbol: 'StringRoundTrip_utf8_len')
+// ignore: non_constant_identifier_names
+external int _StringRoundTrip_utf8_len(ffi.Pointer<ffi.Uint8> sData, int sLength);
+
+@meta.ResourceIdentifier('StringRoundTrip_utf16_len')
+@ffi.Native<ffi.Size Function(ffi.Pointer<ffi.Uint16>, ffi.Size)>(isLeaf: true, symbol: 'StringRoundTrip_utf16_len')
+// ignore: non_constant_identifier_names
+external int _StringRoundTrip_utf16_len(ffi.Pointer<ffi.Uint16> sData, int sLength);
@@ -26,9 +26,24 @@ final class Unnamespaced implements ffi.Finalizable {
     return Unnamespaced._fromFfi(result, []);
   }
 
+  /// Runs [Unnamespaced.make] on a background isolate, completing with its result.
+  ///
+  /// The objects it is called with are kept alive until then, and must not be mutated meanwhile.
+  static core.Future<Unnamespaced> makeInBackground(AttrEnum e) async {
+    final result = ffi.Pointer<ffi.Opaque>.fromAddress(await _BackgroundPool.run(_namespace_Unnamespaced_make_background, [e.index]) as int);
+    return Unnamespaced._fromFfi(result, []);
+  }
+
   void useNamespaced(AttrOpaque1 n) {
     _namespace_Unnamespaced_use_namespaced(_ffi, n._ffi);
   }
+
+  /// Runs [useNamespaced] on a background isolate, completing with its result.
+  ///
+  /// The objects it is called with are kept alive until then, and must not be mutated meanwhile.
+  core.Future<void> useNamespacedInBackground(AttrOpaque1 n) async {
+    await _BackgroundPool.run(_namespace_Unnamespaced_use_namespaced_background, [_ffi.address, n._ffi.address]);
+  }
 }
 
 @meta.ResourceIdentifier('namespace_Unnamespaced_destroy')
@@ -41,7 +56,13 @@ external void _namespace_Unnamespaced_destroy(ffi.Pointer<ffi.Void> self);
 // ignore: non_constant_identifier_names
 external ffi.Pointer<ffi.Opaque> _namespace_Unnamespaced_make(int e);
 
+// ignore: non_constant_identifier_names
+int _namespace_Unnamespaced_make_background(int e) => _namespace_Unnamespaced_make(e).address;
+
 @meta.ResourceIdentifier('namespace_Unnamespaced_use_namespaced')
 @ffi.Native<ffi.Void Function(ffi.Pointer<ffi.Opaque>, ffi.Pointer<ffi.Opaque>)>(isLeaf: true, symbol: 'namespace_Unnamespaced_use_namespaced')
 // ignore: non_constant_identifier_names
 external void _namespace_Unnamespaced_use_namespaced(ffi.Pointer<ffi.Opaque> self, ffi.Pointer<ffi.Opaque> n);
+
+// ignore: non_constant_identifier_names
+void _namespace_Unnamespaced_use_namespaced_background(int self, int n) => _namespace_Unnamespaced_use_namespaced(ffi.Pointer.fromAddress(self), ffi.Pointer.fromAddress(n));
@@ -0,0 +1,14 @@
+import 'package:feature_tests/lib.dart';
+import 'package:test/test.dart';
+
+void main() {
+  test("Run methods marked background on isolates", () async {
+    final unnamespaced = await Unnamespaced.makeInBackground(AttrEnum.b);
+    final opaque = AttrOpaque1();
+    await Future.wait([
+      unnamespaced.useNamespacedInBackground(opaque),
+      unnamespaced.useNamespacedInBackground(opaque),
+    ]);
+    unnamespaced.useNamespaced(opaque);
+  });
+}
@@ -63,6 +63,22 @@ public partial class Opaque: IDisposable
         }
     }
 
+    /// <returns>
+    /// A <c>Opaque</c> allocated on Rust side.
+    /// </returns>
+    public Opaque Duplicate()
+    {
+        unsafe
+        {
+            if (_inner == null)
+            {
+                throw new ObjectDisposedException("Opaque");
+            }
+            Raw.Opaque* retVal = Raw.Opaque.Duplicate(_inner);
+            return new Opaque(retVal);
+        }
+    }
+
     public static nuint ReturnsUsize()
     {
         unsafe
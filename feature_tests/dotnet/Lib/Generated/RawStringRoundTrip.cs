@@ -0,0 +1,37 @@
+// <auto-generated/> by Diplomat
+
+#pragma warning disable 0105
+using System;
+using System.Runtime.InteropServices;
+
+using DiplomatFeatures.Diplomat;
+#pragma warning restore 0105
+
+namespace DiplomatFeatures.Raw;
+
+#nullable enable
+
+/// <summary>
+/// Sends strings back to check how bindings convert them. Lone surrogates become U+FFFD,
+/// everything else, byte order marks included, comes back unchanged.
+/// </summary>
+[StructLayout(LayoutKind.Sequential)]
+public partial struct StringRoundTrip
+{
+    private const string NativeLib = "diplomat_feature_tests";
+
+    [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "StringRoundTrip_echo", ExactSpelling = true)]
+    public static unsafe extern void Echo(ushort* s, nuint sSz, DiplomatWriteable* out);
+
+    [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "StringRoundTrip_echo_utf16", ExactSpelling = true)]
+    public static unsafe extern void EchoUtf16(ushort* s, nuint sSz, DiplomatWriteable* out);
+
+    [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "StringRoundTrip_utf8_len", ExactSpelling = true)]
+    public static unsafe extern nuint Utf8Len(ushort* s, nuint sSz);
+
+    [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "StringRoundTrip_utf16_len", ExactSpelling = true)]
+    public static unsafe extern nuint Utf16Len(ushort* s, nuint sSz);
+
+    [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "StringRoundTrip_destroy", ExactSpelling = true)]
+    public static unsafe extern void Destroy(StringRoundTrip* self);
+}
@@ -0,0 +1,155 @@
+// <auto-generated/> by Diplomat
+
+#pragma warning disable 0105
+using System;
+using System.Runtime.InteropServices;
+
+using DiplomatFeatures.Diplomat;
+#pragma warning restore 0105
+
+namespace DiplomatFeatures;
+
+#nullable enable
+
+/// <summary>
+/// Sends strings back to check how bindings convert them. Lone surrogates become U+FFFD,
+/// everything else, byte order marks included, comes back unchanged.
+/// </summary>
+public partial class StringRoundTrip: IDisposable
+{
+    private unsafe Raw.StringRoundTrip* _inner;
+
+    /// <summary>
+    /// Creates a managed <c>StringRoundTrip</c> from a raw handle.
+    /// </summary>
+    /// <remarks>
+    /// Safety: you should not build two managed objects using the same raw handle (may causes use-after-free and double-free).
+    /// <br/>
+    /// This constructor assumes the raw struct is allocated on Rust side.
+    /// If implemented, the custom Drop implementation on Rust side WILL run on destruction.
+    /// </remarks>
+    public unsafe StringRoundTrip(Raw.StringRoundTrip* handle)
+    {
+        _inner = handle;
+    }
+
+    public static void Echo(string s, DiplomatWriteable out)
+    {
+        unsafe
+        {
+            byte[] sBuf = DiplomatUtils.StringToUtf8(s);
+            nuint sBufLength = (nuint)sBuf.Length;
+            fixed (byte* sBufPtr = sBuf)
+            {
+                Raw.StringRoundTrip.Echo(sBufPtr, sBufLength, &out);
+            }
+        }
+    }
+
+    public static string Echo(string s)
+    {
+        unsafe
+        {
+            byte[] sBuf = DiplomatUtils.StringToUtf8(s);
+            nuint sBufLength = (nuint)sBuf.Length;
+            fixed (byte* sBufPtr = sBuf)
+            {
+                DiplomatWriteable writeable = new DiplomatWriteable();
+                Raw.StringRoundTrip.Echo(sBufPtr, sBufLength, &writeable);
+                string retVal = writeable.ToUnicode();
+                writeable.Dispose();
+                return retVal;
+            }
+        }
+    }
+
+    public static void EchoUtf16(ushort[] s, DiplomatWriteable out)
+    {
+        unsafe
+        {
+            byte[] sBuf = DiplomatUtils.StringToUtf8(s);
+            nuint sBufLength = (nuint)sBuf.Length;
+            fixed (byte* sBufPtr = sBuf)
+            {
+                Raw.StringRoundTrip.EchoUtf16(sBufPtr, sBufLength, &out);
+            }
+        }
+    }
+
+    public static string EchoUtf16(ushort[] s)
+    {
+        unsafe
+        {
+            byte[] sBuf = DiplomatUtils.StringToUtf8(s);
+            nuint sBufLength = (nuint)sBuf.Length;
+            fixed (byte* sBufPtr = sBuf)
+            {
+                DiplomatWriteable writeable = new DiplomatWriteable();
+                Raw.StringRoundTrip.EchoUtf16(sBufPtr, sBufLength, &writeable);
+                string retVal = writeable.ToUnicode();
+                writeable.Dispose();
+                return retVal;
+            }
+        }
+    }
+
+    public static nuint Utf8Len(string s)
+    {
+        unsafe
+        {
+            byte[] sBuf = DiplomatUtils.StringToUtf8(s);
+            nuint sBufLength = (nuint)sBuf.Length;
+            fixed (byte* sBufPtr = sBuf)
+            {
+                nuint retVal = Raw.StringRoundTrip.Utf8Len(sBufPtr, sBufLength);
+                return retVal;
+            }
+        }
+    }
+
+    public static nuint Utf16Len(ushort[] s)
+    {
+        unsafe
+        {
+            byte[] sBuf = DiplomatUtils.StringToUtf8(s);
+            nuint sBufLength = (nuint)sBuf.Length;
+            fixed (byte* sBufPtr = sBuf)
+            {
+                nuint retVal = Raw.StringRoundTrip.Utf16Len(sBufPtr, sBufLength);
+                return retVal;
+            }
+        }
+    }
+
+    /// <summary>
+    /// Returns the underlying raw handle.
+    /// </summary>
+    public unsafe Raw.StringRoundTrip* AsFFI()
+    {
+        return _inner;
+    }
+
+    /// <summary>
+    /// Destroys the underlying object immediately.
+    /// </summary>
+    public void Dispose()
+    {
+        unsafe
+        {
+            if (_inner == null)
+            {
+                return;
+            }
+
+            Raw.StringRoundTrip.Destroy(_inner);
+            _inner = null;
+
+            GC.SuppressFinalize(this);
+        }
+    }
+
+    ~StringRoundTrip()
+    {
+        Dispose();
+    }
+}
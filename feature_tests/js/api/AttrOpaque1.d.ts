@@ -1,4 +1,5 @@
 import { u8 } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { AttrEnum } from "./AttrEnum";
 import { Unnamespaced } from "./Unnamespaced";
 
@@ -6,6 +7,18 @@ import { Unnamespaced } from "./Unnamespaced";
  */
 export class AttrOpaque1 {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): AttrOpaque1;
+
   /**
    */
   static new(): AttrOpaque1;
@@ -8,14 +8,27 @@ const AttrOpaque1_box_destroy_registry = new FinalizationRegistry(underlying =>
 
 export class AttrOpaque1 {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      AttrOpaque1_box_destroy_registry.register(this, underlying);
+      AttrOpaque1_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned AttrOpaque1 objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, AttrOpaque1_box_destroy_registry, "AttrOpaque1");
+  }
+
+  static fromTransferable(transferable) {
+    return new AttrOpaque1(diplomatRuntime.fromTransferable(transferable, "AttrOpaque1"), true, []);
+  }
+
   static new() {
     return new AttrOpaque1(wasm.namespace_AttrOpaque1_new(), true, []);
   }
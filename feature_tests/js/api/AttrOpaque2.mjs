@@ -7,11 +7,24 @@ const AttrOpaque2_box_destroy_registry = new FinalizationRegistry(underlying =>
 
 export class AttrOpaque2 {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      AttrOpaque2_box_destroy_registry.register(this, underlying);
+      AttrOpaque2_box_destroy_registry.register(this, underlying, this);
     }
   }
+
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned AttrOpaque2 objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, AttrOpaque2_box_destroy_registry, "AttrOpaque2");
+  }
+
+  static fromTransferable(transferable) {
+    return new AttrOpaque2(diplomatRuntime.fromTransferable(transferable, "AttrOpaque2"), true, []);
+  }
 }
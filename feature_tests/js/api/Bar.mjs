@@ -8,11 +8,24 @@ const Bar_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Bar {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Bar_box_destroy_registry.register(this, underlying);
+      Bar_box_destroy_registry.register(this, underlying, this);
     }
   }
+
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Bar objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Bar_box_destroy_registry, "Bar");
+  }
+
+  static fromTransferable(transferable) {
+    return new Bar(diplomatRuntime.fromTransferable(transferable, "Bar"), true, []);
+  }
 }
@@ -4,7 +4,7 @@ import * as diplomatRuntime from "./diplomat-runtime.mjs"
 export class BorrowedFieldsReturning {
   constructor(underlying, edges_a) {
     this.bytes = (() => {
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, underlying, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, underlying, 2, "Uint32");
       return diplomatRuntime.readString8(wasm, ptr, size);
     })();
   }
@@ -1,9 +1,22 @@
 import { u8 } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 
 /**
  */
 export class Comparable {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): Comparable;
+
   /**
    */
   static new(int: u8): Comparable;
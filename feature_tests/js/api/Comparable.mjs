@@ -7,14 +7,27 @@ const Comparable_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Comparable {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Comparable_box_destroy_registry.register(this, underlying);
+      Comparable_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Comparable objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Comparable_box_destroy_registry, "Comparable");
+  }
+
+  static fromTransferable(transferable) {
+    return new Comparable(diplomatRuntime.fromTransferable(transferable, "Comparable"), true, []);
+  }
+
   static new(arg_int) {
     return new Comparable(wasm.namespace_Comparable_new(arg_int), true, []);
   }
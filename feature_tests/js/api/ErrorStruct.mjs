@@ -3,7 +3,7 @@ import * as diplomatRuntime from "./diplomat-runtime.mjs"
 
 export class ErrorStruct {
   constructor(underlying) {
-    this.i = (new Int32Array(wasm.memory.buffer, underlying, 1))[0];
-    this.j = (new Int32Array(wasm.memory.buffer, underlying + 4, 1))[0];
+    this.i = diplomatRuntime.readNumber(wasm, underlying, "Int32");
+    this.j = diplomatRuntime.readNumber(wasm, underlying + 4, "Int32");
   }
 }
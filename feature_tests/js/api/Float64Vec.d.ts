@@ -1,9 +1,22 @@
 import { usize, f64 } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 
 /**
  */
 export class Float64Vec {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): Float64Vec;
+
   /**
    */
   static new(v: Float64Array): Float64Vec;
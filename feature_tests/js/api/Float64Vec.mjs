@@ -7,14 +7,27 @@ const Float64Vec_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Float64Vec {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Float64Vec_box_destroy_registry.register(this, underlying);
+      Float64Vec_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Float64Vec objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Float64Vec_box_destroy_registry, "Float64Vec");
+  }
+
+  static fromTransferable(transferable) {
+    return new Float64Vec(diplomatRuntime.fromTransferable(transferable, "Float64Vec"), true, []);
+  }
+
   static new(arg_v) {
     const buf_arg_v = diplomatRuntime.DiplomatBuf.slice(wasm, arg_v, "f64");
     const diplomat_out = new Float64Vec(wasm.Float64Vec_new(buf_arg_v.ptr, buf_arg_v.size), true, []);
@@ -73,9 +86,9 @@ export class Float64Vec {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(8, 4);
       wasm.Float64Vec_as_boxed_slice(diplomat_receive_buffer, this.underlying);
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, diplomat_receive_buffer, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, diplomat_receive_buffer, 2, "Uint32");
       wasm.diplomat_free(diplomat_receive_buffer, 8, 4);
-      return Float64Array.from(new Float64Array(wasm.memory.buffer, ptr, size));
+      return diplomatRuntime.readNumbers(wasm, ptr, size, "Float64");
     })();
   }
 
@@ -83,9 +96,9 @@ export class Float64Vec {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(8, 4);
       wasm.Float64Vec_as_slice(diplomat_receive_buffer, this.underlying);
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, diplomat_receive_buffer, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, diplomat_receive_buffer, 2, "Uint32");
       wasm.diplomat_free(diplomat_receive_buffer, 8, 4);
-      return Float64Array.from(new Float64Array(wasm.memory.buffer, ptr, size));
+      return diplomatRuntime.readNumbers(wasm, ptr, size, "Float64");
     })();
   }
 
@@ -111,9 +124,9 @@ export class Float64Vec {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(8, 4);
       wasm.Float64Vec_borrow(diplomat_receive_buffer, this.underlying);
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, diplomat_receive_buffer, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, diplomat_receive_buffer, 2, "Uint32");
       wasm.diplomat_free(diplomat_receive_buffer, 8, 4);
-      return Float64Array.from(new Float64Array(wasm.memory.buffer, ptr, size));
+      return diplomatRuntime.readNumbers(wasm, ptr, size, "Float64");
     })();
   }
 
@@ -126,7 +139,7 @@ export class Float64Vec {
         wasm.diplomat_free(diplomat_receive_buffer, 9, 8);
         return;
       }
-      const value = (new Float64Array(wasm.memory.buffer, diplomat_receive_buffer, 1))[0];
+      const value = diplomatRuntime.readNumber(wasm, diplomat_receive_buffer, "Float64");
       wasm.diplomat_free(diplomat_receive_buffer, 9, 8);
       return value;
     })();
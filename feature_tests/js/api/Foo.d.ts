@@ -1,3 +1,4 @@
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { Bar } from "./Bar";
 import { BorrowedFields } from "./BorrowedFields";
 import { BorrowedFieldsReturning } from "./BorrowedFieldsReturning";
@@ -7,6 +8,18 @@ import { BorrowedFieldsWithBounds } from "./BorrowedFieldsWithBounds";
  */
 export class Foo {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): Foo;
+
   /**
    */
   static new(x: string): Foo;
@@ -9,14 +9,27 @@ const Foo_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Foo {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Foo_box_destroy_registry.register(this, underlying);
+      Foo_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Foo objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Foo_box_destroy_registry, "Foo");
+  }
+
+  static fromTransferable(transferable) {
+    return new Foo(diplomatRuntime.fromTransferable(transferable, "Foo"), true, []);
+  }
+
   static new(arg_x) {
     const buf_arg_x = diplomatRuntime.DiplomatBuf.str8(wasm, arg_x);
     const diplomat_out = new Foo(wasm.Foo_new(buf_arg_x.ptr, buf_arg_x.size), true, [buf_arg_x]);
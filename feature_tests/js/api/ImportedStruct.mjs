@@ -5,6 +5,6 @@ import { UnimportedEnum_js_to_rust, UnimportedEnum_rust_to_js } from "./Unimport
 export class ImportedStruct {
   constructor(underlying) {
     this.foo = UnimportedEnum_rust_to_js[diplomatRuntime.enumDiscriminant(wasm, underlying)];
-    this.count = (new Uint8Array(wasm.memory.buffer, underlying + 4, 1))[0];
+    this.count = diplomatRuntime.readNumber(wasm, underlying + 4, "Uint8");
   }
 }
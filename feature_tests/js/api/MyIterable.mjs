@@ -8,14 +8,27 @@ const MyIterable_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class MyIterable {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      MyIterable_box_destroy_registry.register(this, underlying);
+      MyIterable_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned MyIterable objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, MyIterable_box_destroy_registry, "MyIterable");
+  }
+
+  static fromTransferable(transferable) {
+    return new MyIterable(diplomatRuntime.fromTransferable(transferable, "MyIterable"), true, []);
+  }
+
   static new(arg_x) {
     const buf_arg_x = diplomatRuntime.DiplomatBuf.slice(wasm, arg_x, "u8");
     const diplomat_out = new MyIterable(wasm.namespace_MyIterable_new(buf_arg_x.ptr, buf_arg_x.size), true, []);
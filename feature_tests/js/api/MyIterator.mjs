@@ -7,14 +7,27 @@ const MyIterator_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class MyIterator {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      MyIterator_box_destroy_registry.register(this, underlying);
+      MyIterator_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned MyIterator objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, MyIterator_box_destroy_registry, "MyIterator");
+  }
+
+  static fromTransferable(transferable) {
+    return new MyIterator(diplomatRuntime.fromTransferable(transferable, "MyIterator"), true, []);
+  }
+
   next() {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(2, 1);
@@ -24,7 +37,7 @@ export class MyIterator {
         wasm.diplomat_free(diplomat_receive_buffer, 2, 1);
         return;
       }
-      const value = (new Uint8Array(wasm.memory.buffer, diplomat_receive_buffer, 1))[0];
+      const value = diplomatRuntime.readNumber(wasm, diplomat_receive_buffer, "Uint8");
       wasm.diplomat_free(diplomat_receive_buffer, 2, 1);
       return value;
     })();
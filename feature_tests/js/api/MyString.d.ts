@@ -1,8 +1,21 @@
+import { DiplomatTransferable } from "./diplomat-runtime"
 
 /**
  */
 export class MyString {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): MyString;
+
   /**
    */
   static new(v: string): MyString;
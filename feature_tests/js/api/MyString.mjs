@@ -7,14 +7,27 @@ const MyString_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class MyString {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      MyString_box_destroy_registry.register(this, underlying);
+      MyString_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned MyString objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, MyString_box_destroy_registry, "MyString");
+  }
+
+  static fromTransferable(transferable) {
+    return new MyString(diplomatRuntime.fromTransferable(transferable, "MyString"), true, []);
+  }
+
   static new(arg_v) {
     const buf_arg_v = diplomatRuntime.DiplomatBuf.str8(wasm, arg_v);
     const diplomat_out = new MyString(wasm.MyString_new(buf_arg_v.ptr, buf_arg_v.size), true, []);
@@ -4,12 +4,12 @@ import { MyEnum_js_to_rust, MyEnum_rust_to_js } from "./MyEnum.mjs"
 
 export class MyStruct {
   constructor(underlying) {
-    this.a = (new Uint8Array(wasm.memory.buffer, underlying, 1))[0];
-    this.b = (new Uint8Array(wasm.memory.buffer, underlying + 1, 1))[0] == 1;
-    this.c = (new Uint8Array(wasm.memory.buffer, underlying + 2, 1))[0];
-    this.d = (new BigUint64Array(wasm.memory.buffer, underlying + 8, 1))[0];
-    this.e = (new Int32Array(wasm.memory.buffer, underlying + 16, 1))[0];
-    this.f = String.fromCharCode((new Uint32Array(wasm.memory.buffer, underlying + 20, 1))[0]);
+    this.a = diplomatRuntime.readNumber(wasm, underlying, "Uint8");
+    this.b = diplomatRuntime.readNumber(wasm, underlying + 1, "Uint8") == 1;
+    this.c = diplomatRuntime.readNumber(wasm, underlying + 2, "Uint8");
+    this.d = diplomatRuntime.readNumber(wasm, underlying + 8, "BigUint64");
+    this.e = diplomatRuntime.readNumber(wasm, underlying + 16, "Int32");
+    this.f = String.fromCodePoint(diplomatRuntime.readNumber(wasm, underlying + 20, "Uint32"));
     this.g = MyEnum_rust_to_js[diplomatRuntime.enumDiscriminant(wasm, underlying + 24)];
   }
 
@@ -7,14 +7,27 @@ const One_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class One {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      One_box_destroy_registry.register(this, underlying);
+      One_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned One objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, One_box_destroy_registry, "One");
+  }
+
+  static fromTransferable(transferable) {
+    return new One(diplomatRuntime.fromTransferable(transferable, "One"), true, []);
+  }
+
   static transitivity(arg_hold, arg_nohold) {
     return new One(wasm.One_transitivity(arg_hold.underlying, arg_nohold.underlying), true, [arg_hold]);
   }
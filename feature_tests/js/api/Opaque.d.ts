@@ -1,4 +1,5 @@
 import { usize } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { ImportedStruct } from "./ImportedStruct";
 import { MyStruct } from "./MyStruct";
 
@@ -6,6 +7,18 @@ import { MyStruct } from "./MyStruct";
  */
 export class Opaque {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): Opaque;
+
   /**
    */
   static new(): Opaque;
@@ -20,6 +33,10 @@ export class Opaque {
    */
   assert_struct(s: MyStruct): void;
 
+  /**
+   */
+  duplicate(): Opaque;
+
   /**
    */
   static returns_usize(): usize;
@@ -10,14 +10,27 @@ const Opaque_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Opaque {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Opaque_box_destroy_registry.register(this, underlying);
+      Opaque_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Opaque objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Opaque_box_destroy_registry, "Opaque");
+  }
+
+  static fromTransferable(transferable) {
+    return new Opaque(diplomatRuntime.fromTransferable(transferable, "Opaque"), true, []);
+  }
+
   static new() {
     return new Opaque(wasm.Opaque_new(), true, []);
   }
@@ -33,6 +46,10 @@ export class Opaque {
     wasm.Opaque_assert_struct(this.underlying, field_a_arg_s, field_b_arg_s, field_c_arg_s, field_d_arg_s, field_e_arg_s, diplomatRuntime.extractCodePoint(field_f_arg_s, 'field_f_arg_s'), MyEnum_js_to_rust[field_g_arg_s]);
   }
 
+  duplicate() {
+    return new Opaque(wasm.Opaque_duplicate(this.underlying), true, []);
+  }
+
   static returns_usize() {
     return wasm.Opaque_returns_usize();
   }
@@ -1,10 +1,23 @@
 import { usize } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { Utf16Wrap } from "./Utf16Wrap";
 
 /**
  */
 export class OpaqueMutexedString {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): OpaqueMutexedString;
+
   /**
    */
   static from_usize(number: usize): OpaqueMutexedString;
@@ -8,14 +8,27 @@ const OpaqueMutexedString_box_destroy_registry = new FinalizationRegistry(underl
 
 export class OpaqueMutexedString {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      OpaqueMutexedString_box_destroy_registry.register(this, underlying);
+      OpaqueMutexedString_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned OpaqueMutexedString objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, OpaqueMutexedString_box_destroy_registry, "OpaqueMutexedString");
+  }
+
+  static fromTransferable(transferable) {
+    return new OpaqueMutexedString(diplomatRuntime.fromTransferable(transferable, "OpaqueMutexedString"), true, []);
+  }
+
   static from_usize(arg_number) {
     return new OpaqueMutexedString(wasm.OpaqueMutexedString_from_usize(arg_number), true, []);
   }
@@ -32,7 +45,7 @@ export class OpaqueMutexedString {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(8, 4);
       wasm.OpaqueMutexedString_dummy_str(diplomat_receive_buffer, this.underlying);
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, diplomat_receive_buffer, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, diplomat_receive_buffer, 2, "Uint32");
       wasm.diplomat_free(diplomat_receive_buffer, 8, 4);
       return diplomatRuntime.readString8(wasm, ptr, size);
     })();
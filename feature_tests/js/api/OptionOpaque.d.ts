@@ -1,10 +1,23 @@
 import { i32 } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { OptionStruct } from "./OptionStruct";
 
 /**
  */
 export class OptionOpaque {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): OptionOpaque;
+
   /**
    */
   static new(i: i32): OptionOpaque | undefined;
@@ -8,14 +8,27 @@ const OptionOpaque_box_destroy_registry = new FinalizationRegistry(underlying =>
 
 export class OptionOpaque {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      OptionOpaque_box_destroy_registry.register(this, underlying);
+      OptionOpaque_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned OptionOpaque objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, OptionOpaque_box_destroy_registry, "OptionOpaque");
+  }
+
+  static fromTransferable(transferable) {
+    return new OptionOpaque(diplomatRuntime.fromTransferable(transferable, "OptionOpaque"), true, []);
+  }
+
   static new(arg_i) {
     return (() => {
       const option_ptr = wasm.OptionOpaque_new(arg_i);
@@ -70,6 +83,6 @@ export class OptionOpaque {
   }
 
   static option_opaque_argument(arg_arg) {
-    return wasm.OptionOpaque_option_opaque_argument(arg_arg);
+    return wasm.OptionOpaque_option_opaque_argument(arg_arg) == 1;
   }
 }
@@ -7,14 +7,27 @@ const OptionOpaqueChar_box_destroy_registry = new FinalizationRegistry(underlyin
 
 export class OptionOpaqueChar {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      OptionOpaqueChar_box_destroy_registry.register(this, underlying);
+      OptionOpaqueChar_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned OptionOpaqueChar objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, OptionOpaqueChar_box_destroy_registry, "OptionOpaqueChar");
+  }
+
+  static fromTransferable(transferable) {
+    return new OptionOpaqueChar(diplomatRuntime.fromTransferable(transferable, "OptionOpaqueChar"), true, []);
+  }
+
   assert_char(arg_ch) {
     wasm.OptionOpaqueChar_assert_char(this.underlying, diplomatRuntime.extractCodePoint(arg_ch, 'arg_ch'));
   }
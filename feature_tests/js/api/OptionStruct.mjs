@@ -13,7 +13,7 @@ export class OptionStruct {
       const option_ptr = diplomatRuntime.ptrRead(wasm, underlying + 4);
       return (option_ptr == 0) ? undefined : new OptionOpaqueChar(option_ptr, true, []);
     })();
-    this.c = (new Uint32Array(wasm.memory.buffer, underlying + 8, 1))[0];
+    this.c = diplomatRuntime.readNumber(wasm, underlying + 8, "Uint32");
     this.d = (() => {
       const option_ptr = diplomatRuntime.ptrRead(wasm, underlying + 12);
       return (option_ptr == 0) ? undefined : new OptionOpaque(option_ptr, true, []);
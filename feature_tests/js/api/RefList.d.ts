@@ -1,9 +1,22 @@
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { RefListParameter } from "./RefListParameter";
 
 /**
  */
 export class RefList {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): RefList;
+
   /**
    */
   static node(data: RefListParameter): RefList;
@@ -7,14 +7,27 @@ const RefList_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class RefList {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      RefList_box_destroy_registry.register(this, underlying);
+      RefList_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned RefList objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, RefList_box_destroy_registry, "RefList");
+  }
+
+  static fromTransferable(transferable) {
+    return new RefList(diplomatRuntime.fromTransferable(transferable, "RefList"), true, []);
+  }
+
   static node(arg_data) {
     return new RefList(wasm.RefList_node(arg_data.underlying), true, [arg_data]);
   }
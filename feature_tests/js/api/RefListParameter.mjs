@@ -7,11 +7,24 @@ const RefListParameter_box_destroy_registry = new FinalizationRegistry(underlyin
 
 export class RefListParameter {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      RefListParameter_box_destroy_registry.register(this, underlying);
+      RefListParameter_box_destroy_registry.register(this, underlying, this);
     }
   }
+
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned RefListParameter objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, RefListParameter_box_destroy_registry, "RefListParameter");
+  }
+
+  static fromTransferable(transferable) {
+    return new RefListParameter(diplomatRuntime.fromTransferable(transferable, "RefListParameter"), true, []);
+  }
 }
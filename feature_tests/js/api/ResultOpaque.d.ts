@@ -1,5 +1,6 @@
 import { i32 } from "./diplomat-runtime"
 import { FFIError } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { ErrorEnum } from "./ErrorEnum";
 import { ErrorStruct } from "./ErrorStruct";
 
@@ -7,6 +8,18 @@ import { ErrorStruct } from "./ErrorStruct";
  */
 export class ResultOpaque {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): ResultOpaque;
+
   /**
    * @throws {@link FFIError}<{@link ErrorEnum}>
    */
@@ -9,14 +9,27 @@ const ResultOpaque_box_destroy_registry = new FinalizationRegistry(underlying =>
 
 export class ResultOpaque {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      ResultOpaque_box_destroy_registry.register(this, underlying);
+      ResultOpaque_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned ResultOpaque objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, ResultOpaque_box_destroy_registry, "ResultOpaque");
+  }
+
+  static fromTransferable(transferable) {
+    return new ResultOpaque(diplomatRuntime.fromTransferable(transferable, "ResultOpaque"), true, []);
+  }
+
   static new(arg_i) {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(5, 4);
@@ -125,7 +138,7 @@ export class ResultOpaque {
       wasm.ResultOpaque_new_int(diplomat_receive_buffer, arg_i);
       const is_ok = diplomatRuntime.resultFlag(wasm, diplomat_receive_buffer, 4);
       if (is_ok) {
-        const ok_value = (new Int32Array(wasm.memory.buffer, diplomat_receive_buffer, 1))[0];
+        const ok_value = diplomatRuntime.readNumber(wasm, diplomat_receive_buffer, "Int32");
         wasm.diplomat_free(diplomat_receive_buffer, 5, 4);
         return ok_value;
       } else {
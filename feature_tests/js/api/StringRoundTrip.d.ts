@@ -0,0 +1,37 @@
+import { usize } from "./diplomat-runtime"
+import { DiplomatTransferable } from "./diplomat-runtime"
+
+/**
+
+ * Sends strings back to check how bindings convert them. Lone surrogates become U+FFFD, everything else, byte order marks included, comes back unchanged.
+ */
+export class StringRoundTrip {
+
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): StringRoundTrip;
+
+  /**
+   */
+  static echo(s: string): string;
+
+  /**
+   */
+  static echo_utf16(s: string): string;
+
+  /**
+   */
+  static utf8_len(s: string): usize;
+
+  /**
+   */
+  static utf16_len(s: string): usize;
+}
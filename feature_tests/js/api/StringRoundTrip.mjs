@@ -0,0 +1,62 @@
+import wasm from "./diplomat-wasm.mjs"
+import * as diplomatRuntime from "./diplomat-runtime.mjs"
+
+const StringRoundTrip_box_destroy_registry = new FinalizationRegistry(underlying => {
+  wasm.StringRoundTrip_destroy(underlying);
+});
+
+export class StringRoundTrip {
+  #lifetimeEdges = [];
+  #owned;
+  constructor(underlying, owned, edges) {
+    this.underlying = underlying;
+    this.#owned = owned;
+    this.#lifetimeEdges.push(...edges);
+    if (owned) {
+      StringRoundTrip_box_destroy_registry.register(this, underlying, this);
+    }
+  }
+
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned StringRoundTrip objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, StringRoundTrip_box_destroy_registry, "StringRoundTrip");
+  }
+
+  static fromTransferable(transferable) {
+    return new StringRoundTrip(diplomatRuntime.fromTransferable(transferable, "StringRoundTrip"), true, []);
+  }
+
+  static echo(arg_s) {
+    const buf_arg_s = diplomatRuntime.DiplomatBuf.str8(wasm, arg_s);
+    const diplomat_out = diplomatRuntime.withWriteable(wasm, (writeable) => {
+      return wasm.StringRoundTrip_echo(buf_arg_s.ptr, buf_arg_s.size, writeable);
+    });
+    buf_arg_s.free();
+    return diplomat_out;
+  }
+
+  static echo_utf16(arg_s) {
+    const buf_arg_s = diplomatRuntime.DiplomatBuf.str16(wasm, arg_s);
+    const diplomat_out = diplomatRuntime.withWriteable(wasm, (writeable) => {
+      return wasm.StringRoundTrip_echo_utf16(buf_arg_s.ptr, buf_arg_s.size, writeable);
+    });
+    buf_arg_s.free();
+    return diplomat_out;
+  }
+
+  static utf8_len(arg_s) {
+    const buf_arg_s = diplomatRuntime.DiplomatBuf.str8(wasm, arg_s);
+    const diplomat_out = wasm.StringRoundTrip_utf8_len(buf_arg_s.ptr, buf_arg_s.size);
+    buf_arg_s.free();
+    return diplomat_out;
+  }
+
+  static utf16_len(arg_s) {
+    const buf_arg_s = diplomatRuntime.DiplomatBuf.str16(wasm, arg_s);
+    const diplomat_out = wasm.StringRoundTrip_utf16_len(buf_arg_s.ptr, buf_arg_s.size);
+    buf_arg_s.free();
+    return diplomat_out;
+  }
+}
@@ -7,11 +7,24 @@ const Two_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Two {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Two_box_destroy_registry.register(this, underlying);
+      Two_box_destroy_registry.register(this, underlying, this);
     }
   }
+
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Two objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Two_box_destroy_registry, "Two");
+  }
+
+  static fromTransferable(transferable) {
+    return new Two(diplomatRuntime.fromTransferable(transferable, "Two"), true, []);
+  }
 }
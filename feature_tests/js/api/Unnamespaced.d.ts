@@ -1,3 +1,4 @@
+import { DiplomatTransferable } from "./diplomat-runtime"
 import { AttrEnum } from "./AttrEnum";
 import { AttrOpaque1 } from "./AttrOpaque1";
 
@@ -5,6 +6,18 @@ import { AttrOpaque1 } from "./AttrOpaque1";
  */
 export class Unnamespaced {
 
+  /**
+   * Moves ownership of this object into a structured-cloneable value that can be posted to another worker.
+   *
+   * This object may not be used afterwards. Only owned objects without borrows can be transferred.
+   */
+  toTransferable(): DiplomatTransferable;
+
+  /**
+   * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory.
+   */
+  static fromTransferable(transferable: DiplomatTransferable): Unnamespaced;
+
   /**
    */
   static make(_e: AttrEnum): Unnamespaced;
@@ -8,14 +8,27 @@ const Unnamespaced_box_destroy_registry = new FinalizationRegistry(underlying =>
 
 export class Unnamespaced {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Unnamespaced_box_destroy_registry.register(this, underlying);
+      Unnamespaced_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Unnamespaced objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Unnamespaced_box_destroy_registry, "Unnamespaced");
+  }
+
+  static fromTransferable(transferable) {
+    return new Unnamespaced(diplomatRuntime.fromTransferable(transferable, "Unnamespaced"), true, []);
+  }
+
   static make(arg__e) {
     return new Unnamespaced(wasm.namespace_Unnamespaced_make(AttrEnum_js_to_rust[arg__e]), true, []);
   }
@@ -7,19 +7,32 @@ const Utf16Wrap_box_destroy_registry = new FinalizationRegistry(underlying => {
 
 export class Utf16Wrap {
   #lifetimeEdges = [];
+  #owned;
   constructor(underlying, owned, edges) {
     this.underlying = underlying;
+    this.#owned = owned;
     this.#lifetimeEdges.push(...edges);
     if (owned) {
-      Utf16Wrap_box_destroy_registry.register(this, underlying);
+      Utf16Wrap_box_destroy_registry.register(this, underlying, this);
     }
   }
 
+  toTransferable() {
+    if (!this.#owned || this.#lifetimeEdges.length != 0) {
+      throw new Error("Only owned Utf16Wrap objects without borrows can be transferred");
+    }
+    return diplomatRuntime.toTransferable(this, Utf16Wrap_box_destroy_registry, "Utf16Wrap");
+  }
+
+  static fromTransferable(transferable) {
+    return new Utf16Wrap(diplomatRuntime.fromTransferable(transferable, "Utf16Wrap"), true, []);
+  }
+
   borrow_cont() {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(8, 4);
       wasm.Utf16Wrap_borrow_cont(diplomat_receive_buffer, this.underlying);
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, diplomat_receive_buffer, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, diplomat_receive_buffer, 2, "Uint32");
       wasm.diplomat_free(diplomat_receive_buffer, 8, 4);
       return diplomatRuntime.readString16(wasm, ptr, size);
     })();
@@ -29,7 +42,7 @@ export class Utf16Wrap {
     return (() => {
       const diplomat_receive_buffer = wasm.diplomat_alloc(8, 4);
       wasm.Utf16Wrap_owned(diplomat_receive_buffer, this.underlying);
-      const [ptr, size] = new Uint32Array(wasm.memory.buffer, diplomat_receive_buffer, 2);
+      const [ptr, size] = diplomatRuntime.readNumbers(wasm, diplomat_receive_buffer, 2, "Uint32");
       wasm.diplomat_free(diplomat_receive_buffer, 8, 4);
       return diplomatRuntime.readString16(wasm, ptr, size);
     })();
@@ -5,6 +5,15 @@ export class FFIError<E> extends Error {
     error_value: E;
 }
 
+/**
+ * An opaque object whose ownership has been moved out of its wrapper by
+ * `toTransferable()`, so that it can be posted to another worker.
+ */
+export interface DiplomatTransferable {
+    diplomatType: string;
+    underlying: number | bigint;
+}
+
 export type u8 = number;
 export type i8 = number;
 export type u16 = number;
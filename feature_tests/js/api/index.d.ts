@@ -1,4 +1,5 @@
 export { FFIError, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, char } from './diplomat-runtime';
+export { init, InitOptions } from './diplomat-wasm';
 export { AttrEnum } from './AttrEnum';
 export { AttrOpaque1 } from './AttrOpaque1';
 export { AttrOpaque2 } from './AttrOpaque2';
@@ -28,6 +29,7 @@ export { OptionStruct } from './OptionStruct';
 export { RefList } from './RefList';
 export { RefListParameter } from './RefListParameter';
 export { ResultOpaque } from './ResultOpaque';
+export { StringRoundTrip } from './StringRoundTrip';
 export { Two } from './Two';
 export { UnimportedEnum } from './UnimportedEnum';
 export { Unnamespaced } from './Unnamespaced';
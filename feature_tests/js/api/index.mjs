@@ -1,4 +1,5 @@
 export { FFIError } from './diplomat-runtime.mjs';
+export { init } from './diplomat-wasm.mjs';
 export { AttrEnum } from './AttrEnum.mjs';
 export { AttrOpaque1 } from './AttrOpaque1.mjs';
 export { AttrOpaque2 } from './AttrOpaque2.mjs';
@@ -28,6 +29,7 @@ export { OptionStruct } from './OptionStruct.mjs';
 export { RefList } from './RefList.mjs';
 export { RefListParameter } from './RefListParameter.mjs';
 export { ResultOpaque } from './ResultOpaque.mjs';
+export { StringRoundTrip } from './StringRoundTrip.mjs';
 export { Two } from './Two.mjs';
 export { UnimportedEnum } from './UnimportedEnum.mjs';
 export { Unnamespaced } from './Unnamespaced.mjs';
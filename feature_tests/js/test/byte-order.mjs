@@ -0,0 +1,54 @@
+import test from 'ava';
+import { readNumber, readNumbers, selfTest, writeNumber, writeNumbers } from "../api/diplomat-runtime.mjs";
+
+// Stands in for a WASM module, whose memory is little-endian whatever the host
+function fakeWasm(selfTestValues) {
+  const wasm = {
+    memory: { buffer: new ArrayBuffer(256) },
+    diplomat_alloc: () => 64,
+    diplomat_free: () => {},
+  };
+  if (selfTestValues !== undefined) {
+    wasm.diplomat_self_test = (ptr) => selfTestValues(wasm, ptr);
+  }
+  return wasm;
+}
+
+function writeSelfTest(wasm, ptr) {
+  writeNumber(wasm, ptr + 2, "Uint16", 0x0102);
+  writeNumber(wasm, ptr + 4, "Uint32", 0x01020304);
+  writeNumber(wasm, ptr + 8, "BigUint64", 0x0102030405060708n);
+  writeNumber(wasm, ptr + 16, "Float64", -2.5);
+  writeNumber(wasm, ptr + 24, "Uint32", 0x01020304);
+  writeNumber(wasm, ptr + 28, "Uint8", 1);
+  writeNumber(wasm, ptr + 32, "Uint32", 0x05060708);
+  writeNumbers(wasm, 128, "Uint16", [0x0102, 0x0304, 0x0506]);
+  writeNumbers(wasm, ptr + 40, "Uint32", [128, 3]);
+}
+
+test("Write numbers little-endian", t => {
+  const wasm = fakeWasm();
+  writeNumber(wasm, 0, "Uint32", 0x01020304);
+  writeNumbers(wasm, 4, "Uint16", [0x0506, 0x0708]);
+  t.deepEqual([...new Uint8Array(wasm.memory.buffer, 0, 8)], [4, 3, 2, 1, 6, 5, 8, 7]);
+});
+
+test("Read numbers little-endian", t => {
+  const wasm = fakeWasm();
+  new Uint8Array(wasm.memory.buffer).set([4, 3, 2, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 128]);
+  t.is(readNumber(wasm, 0, "Uint32"), 0x01020304);
+  t.deepEqual(readNumbers(wasm, 8, 1, "BigInt64"), new BigInt64Array([-(2n ** 63n) + 1n]));
+});
+
+test("Pass the self-test of a module reading as expected", t => {
+  t.notThrows(() => selfTest(fakeWasm(writeSelfTest)));
+  t.notThrows(() => selfTest(fakeWasm()));
+});
+
+test("Fail the self-test of a module with another byte order", t => {
+  const wasm = fakeWasm((wasm, ptr) => {
+    writeSelfTest(wasm, ptr);
+    new DataView(wasm.memory.buffer).setUint32(ptr + 4, 0x01020304, false);
+  });
+  t.throws(() => selfTest(wasm), { message: /read a u32 as 67305985/ });
+});
@@ -0,0 +1,61 @@
+import test from 'ava';
+import { StringRoundTrip } from "diplomat-wasm-feature-tests";
+
+// Random strings round-tripped through Rust. JavaScript strings are UTF-16 and may contain lone
+// surrogates, which come back as U+FFFD whether the string is passed as UTF-8 or UTF-16. Byte
+// order marks are kept as is.
+
+let state = 0x2545F491;
+
+// xorshift32, so that failures reproduce
+function nextRandom(bound) {
+    state ^= state << 13;
+    state ^= state >>> 17;
+    state ^= state << 5;
+    return (state >>> 0) % bound;
+}
+
+function randomCodeUnits() {
+    switch (nextRandom(6)) {
+        case 0: return String.fromCodePoint(nextRandom(0x80));
+        case 1: return String.fromCodePoint(0x80 + nextRandom(0x800 - 0x80));
+        case 2: return String.fromCodePoint(0xE000 + nextRandom(0x10000 - 0xE000));
+        case 3: return String.fromCodePoint(0x10000 + nextRandom(0x110000 - 0x10000));
+        case 4: return "\uFEFF";
+        // A lone surrogate, unless the next code unit happens to complete it
+        default: return String.fromCharCode(0xD800 + nextRandom(0x800));
+    }
+}
+
+// What Rust is expected to send back for `s`
+function wellFormed(s) {
+    let out = "";
+    for (const c of s) {
+        const code = c.codePointAt(0);
+        out += code >= 0xD800 && code < 0xE000 ? "\uFFFD" : c;
+    }
+    return out;
+}
+
+test("Round-trip random strings", t => {
+    for (let round = 0; round < 2000; round++) {
+        let s = "";
+        const length = nextRandom(16);
+        for (let i = 0; i < length; i++) {
+            s += randomCodeUnits();
+        }
+        const expected = wellFormed(s);
+        t.is(StringRoundTrip.echo(s), expected);
+        t.is(StringRoundTrip.echo_utf16(s), expected);
+        t.is(StringRoundTrip.utf8_len(s), new TextEncoder().encode(s).length);
+        t.is(StringRoundTrip.utf16_len(s), s.length);
+    }
+});
+
+test("Byte order marks and surrogate edge cases", t => {
+    t.is(StringRoundTrip.echo("\uFEFFx"), "\uFEFFx");
+    t.is(StringRoundTrip.echo_utf16("\uFEFFx"), "\uFEFFx");
+    t.is(StringRoundTrip.echo_utf16("\uD83D"), "\uFFFD");
+    t.is(StringRoundTrip.echo_utf16("\uDE00\uD83D"), "\uFFFD\uFFFD");
+    t.is(StringRoundTrip.echo_utf16("😀"), "\u{1F600}");
+});
@@ -0,0 +1,15 @@
+import test from 'ava';
+import { Opaque, OptionOpaque } from "diplomat-wasm-feature-tests";
+
+test("Transfer an opaque between wrappers", t => {
+    const o = Opaque.new();
+    const transferable = structuredClone(o.toTransferable());
+    t.throws(() => o.underlying);
+    const received = Opaque.fromTransferable(transferable);
+    t.is(received.duplicate().constructor, Opaque);
+});
+
+test("Reject transfers to the wrong type", t => {
+    const transferable = Opaque.new().toTransferable();
+    t.throws(() => OptionOpaque.fromTransferable(transferable), { instanceOf: TypeError });
+});
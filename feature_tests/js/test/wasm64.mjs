@@ -0,0 +1,68 @@
+import test from 'ava';
+import { DiplomatBuf, ptrRead, readNumbers, readString8, resultFlag, selfTest, writeNumber, writeNumbers } from "../api/diplomat-runtime.mjs";
+
+// Stands in for a wasm64 module, whose exports take and return pointers and `usize` as BigInts
+function fakeWasm64(selfTestValues) {
+  const calls = [];
+  const wasm = {
+    memory: { buffer: new ArrayBuffer(256) },
+    diplomat_pointer_width: () => 64,
+    diplomat_alloc: (size, align) => {
+      calls.push(["alloc", size, align]);
+      return 64n;
+    },
+    diplomat_free: (ptr, size, align) => calls.push(["free", ptr, size, align]),
+    calls,
+  };
+  if (selfTestValues !== undefined) {
+    wasm.diplomat_self_test = (ptr) => selfTestValues(wasm, ptr);
+  }
+  return wasm;
+}
+
+function writeSelfTest(wasm, ptr) {
+  writeNumber(wasm, ptr + 2n, "Uint16", 0x0102);
+  writeNumber(wasm, ptr + 4n, "Uint32", 0x01020304);
+  writeNumber(wasm, ptr + 8n, "BigUint64", 0x0102030405060708n);
+  writeNumber(wasm, ptr + 16n, "Float64", -2.5);
+  writeNumber(wasm, ptr + 24n, "Uint32", 0x01020304);
+  writeNumber(wasm, ptr + 28n, "Uint8", 1);
+  writeNumber(wasm, ptr + 32n, "Uint32", 0x05060708);
+  writeNumbers(wasm, 192n, "Uint16", [0x0102, 0x0304, 0x0506]);
+  writeNumbers(wasm, ptr + 40n, "BigUint64", [192n, 3n]);
+}
+
+test("Pass the self-test of a wasm64 module", t => {
+  const wasm = fakeWasm64(writeSelfTest);
+  t.notThrows(() => selfTest(wasm));
+  t.deepEqual(wasm.calls, [["alloc", 56n, 8n], ["free", 64n, 56n, 8n]]);
+});
+
+test("Read through BigInt pointers", t => {
+  const wasm = fakeWasm64();
+  writeNumbers(wasm, 8n, "BigUint64", [0x40n, 3n]);
+  new Uint8Array(wasm.memory.buffer).set([0x61, 0x62, 0x63], 0x40);
+  const [ptr, len] = readNumbers(wasm, 8n, 2, "BigUint64");
+  t.is(readString8(wasm, ptr, len), "abc");
+  t.is(ptrRead(wasm, 8n), 0x40n);
+  writeNumber(wasm, 20n, "Uint8", 1);
+  t.is(resultFlag(wasm, 16n, 4), 1);
+});
+
+test("Pass buffers as BigInts", t => {
+  const wasm = fakeWasm64();
+  const buf = DiplomatBuf.str8(wasm, "abc");
+  t.is(buf.ptr, 64n);
+  t.is(buf.size, 3n);
+  t.is(readString8(wasm, buf.ptr, buf.size), "abc");
+  buf.free();
+  t.deepEqual(wasm.calls, [["alloc", 3n, 1n], ["free", 64n, 3n, 1n]]);
+});
+
+test("Write usize slices as 64-bit numbers", t => {
+  const wasm = fakeWasm64();
+  const buf = DiplomatBuf.slice(wasm, new BigUint64Array([1n, 2n]), "usize");
+  t.is(buf.size, 2n);
+  t.deepEqual(readNumbers(wasm, buf.ptr, 2, "BigUint64"), new BigUint64Array([1n, 2n]));
+  t.deepEqual(wasm.calls, [["alloc", 16n, 8n]]);
+});
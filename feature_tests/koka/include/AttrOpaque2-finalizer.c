@@ -0,0 +1,8 @@
+// Destroys the Rust object behind an opaque once Perceus drops the last Koka reference to it.
+#include "AttrOpaque2.h"
+
+static void AttrOpaque2_finalize(void* p, kk_block_t* block, kk_context_t* _ctx) {
+  kk_unused(block);
+  kk_unused(_ctx);
+  namespace_AttrOpaque2_destroy((AttrOpaque2*)p);
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+// Destroys the Rust object behind an opaque once Perceus drops the last Koka reference to it.
+#include "Unnamespaced.h"
+
+static void Unnamespaced_finalize(void* p, kk_block_t* block, kk_context_t* _ctx) {
+  kk_unused(block);
+  kk_unused(_ctx);
+  namespace_Unnamespaced_destroy((Unnamespaced*)p);
+}
\ No newline at end of file
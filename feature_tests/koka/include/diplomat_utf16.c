@@ -0,0 +1,79 @@
+// Transcodes between the UTF-8 of Koka strings and the UTF-16 of `DiplomatStr16`.
+#include "diplomat_runtime.h"
+
+// The number of UTF-16 code units encoding `s`, which Koka keeps valid UTF-8.
+static size_t diplomat_utf16_length(kk_string_t s, kk_context_t* _ctx) {
+  kk_ssize_t len;
+  const uint8_t* bytes = kk_string_buf_borrow(s, &len, _ctx);
+  size_t units = 0;
+  for (kk_ssize_t i = 0; i < len; i++) {
+    // One unit per leading byte, and another for the surrogate pairs of four byte sequences
+    if ((bytes[i] & 0xC0) != 0x80) units++;
+    if (bytes[i] >= 0xF0) units++;
+  }
+  return units;
+}
+
+// Encodes `s` into `out`, which has room for `diplomat_utf16_length(s)` code units.
+static char16_t* diplomat_utf16_encode(kk_string_t s, void* out, kk_context_t* _ctx) {
+  kk_ssize_t len;
+  const uint8_t* bytes = kk_string_buf_borrow(s, &len, _ctx);
+  char16_t* units = (char16_t*)out;
+  size_t n = 0;
+  for (kk_ssize_t i = 0; i < len;) {
+    uint32_t c = bytes[i];
+    int trailing = c >= 0xF0 ? 3 : c >= 0xE0 ? 2 : c >= 0xC0 ? 1 : 0;
+    c &= 0x7F >> trailing;
+    for (i++; trailing > 0; trailing--, i++) {
+      c = (c << 6) | (bytes[i] & 0x3F);
+    }
+    if (c >= 0x10000) {
+      c -= 0x10000;
+      units[n++] = (char16_t)(0xD800 + (c >> 10));
+      units[n++] = (char16_t)(0xDC00 + (c & 0x3FF));
+    } else {
+      units[n++] = (char16_t)c;
+    }
+  }
+  return units;
+}
+
+// Decodes UTF-16 into a Koka string, replacing unpaired surrogates with U+FFFD.
+static kk_string_t diplomat_utf16_decode(DiplomatU16StringView view, kk_context_t* _ctx) {
+  // Each code unit takes at most three bytes, surrogate pairs take four
+  uint8_t* bytes = (uint8_t*)kk_malloc((kk_ssize_t)(view.len * 3 + 1), _ctx);
+  kk_ssize_t len = 0;
+  for (size_t i = 0; i < view.len; i++) {
+    uint32_t c = view.data[i];
+    if (c >= 0xD800 && c < 0xDC00 && i + 1 < view.len && view.data[i + 1] >= 0xDC00 && view.data[i + 1] < 0xE000) {
+      c = 0x10000 + ((c - 0xD800) << 10) + (view.data[++i] - 0xDC00);
+    } else if (c >= 0xD800 && c < 0xE000) {
+      c = 0xFFFD;
+    }
+    if (c < 0x80) {
+      bytes[len++] = (uint8_t)c;
+    } else if (c < 0x800) {
+      bytes[len++] = (uint8_t)(0xC0 | (c >> 6));
+      bytes[len++] = (uint8_t)(0x80 | (c & 0x3F));
+    } else if (c < 0x10000) {
+      bytes[len++] = (uint8_t)(0xE0 | (c >> 12));
+      bytes[len++] = (uint8_t)(0x80 | ((c >> 6) & 0x3F));
+      bytes[len++] = (uint8_t)(0x80 | (c & 0x3F));
+    } else {
+      bytes[len++] = (uint8_t)(0xF0 | (c >> 18));
+      bytes[len++] = (uint8_t)(0x80 | ((c >> 12) & 0x3F));
+      bytes[len++] = (uint8_t)(0x80 | ((c >> 6) & 0x3F));
+      bytes[len++] = (uint8_t)(0x80 | (c & 0x3F));
+    }
+  }
+  kk_string_t s = kk_string_alloc_from_utf8n(len, (const char*)bytes, _ctx);
+  kk_free(bytes, _ctx);
+  return s;
+}
+
+// Decodes UTF-16 owned by the caller, releasing it to Rust afterwards.
+static kk_string_t diplomat_utf16_decode_owned(DiplomatU16StringView view, kk_context_t* _ctx) {
+  kk_string_t s = diplomat_utf16_decode(view, _ctx);
+  diplomat_free((void*)view.data, view.len * 2, 2);
+  return s;
+}
@@ -35,7 +35,7 @@ class Bar internal constructor (
     
         val selfEdges: List<Any> = listOf(this)
         val aEdges: List<Any> = listOf(this)
-        val handle = returnVal 
+        val handle: Pointer = returnVal 
         val returnOpaque = Foo(handle, selfEdges, aEdges)
         
         return returnOpaque
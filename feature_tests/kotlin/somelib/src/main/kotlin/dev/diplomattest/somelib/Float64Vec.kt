@@ -45,7 +45,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -58,7 +58,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_bool(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -71,7 +71,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_i16(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -84,7 +84,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_u16(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -97,7 +97,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_isize(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -110,7 +110,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_usize(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -123,7 +123,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_f64_be_bytes(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             vMem.close()
@@ -136,7 +136,7 @@ class Float64Vec internal constructor (
             val returnVal = lib.Float64Vec_new_from_owned(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Float64Vec(handle, selfEdges)
             CLEANER.register(returnOpaque, Float64Vec.Float64VecCleaner(handle, Float64Vec.lib));
             
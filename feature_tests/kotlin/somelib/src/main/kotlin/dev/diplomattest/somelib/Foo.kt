@@ -39,7 +39,7 @@ class Foo internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(xMem)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Foo(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, Foo.FooCleaner(handle, Foo.lib));
             
@@ -53,7 +53,7 @@ class Foo internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Foo(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, Foo.FooCleaner(handle, Foo.lib));
             xMem.close()
@@ -66,7 +66,7 @@ class Foo internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = fields.aEdges
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Foo(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, Foo.FooCleaner(handle, Foo.lib));
             
@@ -80,7 +80,7 @@ class Foo internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = bounds.bEdges + bounds.cEdges + listOf(anotherStringMem)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Foo(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, Foo.FooCleaner(handle, Foo.lib));
             
@@ -95,7 +95,7 @@ class Foo internal constructor (
         val selfEdges: List<Any> = listOf()
         val bEdges: List<Any> = listOf(this)
         val aEdges: List<Any> = listOf(this)
-        val handle = returnVal 
+        val handle: Pointer = returnVal 
         val returnOpaque = Bar(handle, selfEdges, bEdges, aEdges)
         CLEANER.register(returnOpaque, Bar.BarCleaner(handle, Bar.lib));
         
@@ -8,7 +8,7 @@ import com.sun.jna.Structure
 
 
 // We spawn a cleaner for the library which is responsible for cleaning opaque types.
-val CLEANER = java.lang.ref.Cleaner.create()
+val CLEANER: java.lang.ref.Cleaner = java.lang.ref.Cleaner.create()
 
 
 interface DiplomatWriteableLib: Library {
@@ -37,7 +37,7 @@ class MyString internal constructor (
             val returnVal = lib.MyString_new(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = MyString(handle, selfEdges)
             CLEANER.register(returnOpaque, MyString.MyStringCleaner(handle, MyString.lib));
             vMem.close()
@@ -50,7 +50,7 @@ class MyString internal constructor (
             val returnVal = lib.MyString_new_unsafe(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = MyString(handle, selfEdges)
             CLEANER.register(returnOpaque, MyString.MyStringCleaner(handle, MyString.lib));
             vMem.close()
@@ -63,7 +63,7 @@ class MyString internal constructor (
             val returnVal = lib.MyString_new_owned(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = MyString(handle, selfEdges)
             CLEANER.register(returnOpaque, MyString.MyStringCleaner(handle, MyString.lib));
             
@@ -76,7 +76,7 @@ class MyString internal constructor (
             val returnVal = lib.MyString_new_from_first(vSlice);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = MyString(handle, selfEdges)
             CLEANER.register(returnOpaque, MyString.MyStringCleaner(handle, MyString.lib));
             vMem.forEach {it.close()}
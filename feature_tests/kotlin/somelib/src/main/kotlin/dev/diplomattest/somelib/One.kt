@@ -43,7 +43,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(hold)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -56,7 +56,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(hold)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -69,7 +69,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(a) + listOf(b) + listOf(c) + listOf(d)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -82,7 +82,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val longEdges: List<Any> = listOf(hold)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, longEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -95,7 +95,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val topEdges: List<Any> = listOf(top) + listOf(left) + listOf(right) + listOf(bottom)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, topEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -108,7 +108,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val leftEdges: List<Any> = listOf(left) + listOf(bottom)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, leftEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -121,7 +121,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val rightEdges: List<Any> = listOf(right) + listOf(bottom)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, rightEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -134,7 +134,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val bottomEdges: List<Any> = listOf(bottom)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, bottomEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -147,7 +147,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(a) + listOf(b) + listOf(c) + listOf(d)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -160,7 +160,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(explicitHold) + listOf(implicitHold)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
@@ -173,7 +173,7 @@ class One internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val aEdges: List<Any> = listOf(explicit) + listOf(implicit1) + listOf(implicit2)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = One(handle, selfEdges, aEdges)
             CLEANER.register(returnOpaque, One.OneCleaner(handle, One.lib));
             
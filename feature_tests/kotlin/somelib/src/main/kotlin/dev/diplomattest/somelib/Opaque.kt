@@ -8,6 +8,7 @@ internal interface OpaqueLib: Library {
     fun Opaque_destroy(handle: Pointer)
     fun Opaque_new(): Pointer
     fun Opaque_assert_struct(handle: Pointer, s: MyStructNative): Unit
+    fun Opaque_duplicate(handle: Pointer): Pointer
     fun Opaque_returns_usize(): Long
     fun Opaque_returns_imported(): ImportedStructNative
     fun Opaque_cmp(): Byte
@@ -34,7 +35,7 @@ class Opaque internal constructor (
             val returnVal = lib.Opaque_new();
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = Opaque(handle, selfEdges)
             CLEANER.register(returnOpaque, Opaque.OpaqueCleaner(handle, Opaque.lib));
             
@@ -64,5 +65,17 @@ class Opaque internal constructor (
         
         val returnVal = lib.Opaque_assert_struct(handle, s.nativeStruct);
     }
+    fun duplicate(): Opaque {
+        
+        val returnVal = lib.Opaque_duplicate(handle);
+    
+        val selfEdges: List<Any> = listOf()
+        val handle: Pointer = returnVal 
+        val returnOpaque = Opaque(handle, selfEdges)
+        CLEANER.register(returnOpaque, Opaque.OpaqueCleaner(handle, Opaque.lib));
+        
+        return returnOpaque
+    
+    }
 
 }
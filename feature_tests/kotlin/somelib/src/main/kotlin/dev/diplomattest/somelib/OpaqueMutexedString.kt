@@ -37,7 +37,7 @@ class OpaqueMutexedString internal constructor (
             val returnVal = lib.OpaqueMutexedString_from_usize(number);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = OpaqueMutexedString(handle, selfEdges)
             CLEANER.register(returnOpaque, OpaqueMutexedString.OpaqueMutexedStringCleaner(handle, OpaqueMutexedString.lib));
             
@@ -49,7 +49,7 @@ class OpaqueMutexedString internal constructor (
             val returnVal = lib.OpaqueMutexedString_borrow_other(other.handle);
         
             val selfEdges: List<Any> = listOf(other)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = OpaqueMutexedString(handle, selfEdges)
             
             return returnOpaque
@@ -65,7 +65,7 @@ class OpaqueMutexedString internal constructor (
         val returnVal = lib.OpaqueMutexedString_borrow(handle);
     
         val selfEdges: List<Any> = listOf(this)
-        val handle = returnVal 
+        val handle: Pointer = returnVal 
         val returnOpaque = OpaqueMutexedString(handle, selfEdges)
         
         return returnOpaque
@@ -76,7 +76,7 @@ class OpaqueMutexedString internal constructor (
         val returnVal = lib.OpaqueMutexedString_borrow_self_or_other(handle, other.handle);
     
         val selfEdges: List<Any> = listOf(this) + listOf(other)
-        val handle = returnVal 
+        val handle: Pointer = returnVal 
         val returnOpaque = OpaqueMutexedString(handle, selfEdges)
         
         return returnOpaque
@@ -97,7 +97,7 @@ class OpaqueMutexedString internal constructor (
         val returnVal = lib.OpaqueMutexedString_wrapper(handle);
     
         val selfEdges: List<Any> = listOf()
-        val handle = returnVal 
+        val handle: Pointer = returnVal 
         val returnOpaque = Utf16Wrap(handle, selfEdges)
         CLEANER.register(returnOpaque, Utf16Wrap.Utf16WrapCleaner(handle, Utf16Wrap.lib));
         
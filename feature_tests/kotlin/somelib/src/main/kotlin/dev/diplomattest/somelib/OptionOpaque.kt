@@ -12,7 +12,7 @@ internal interface OptionOpaqueLib: Library {
     fun OptionOpaque_new_struct(): OptionStructNative
     fun OptionOpaque_new_struct_nones(): OptionStructNative
     fun OptionOpaque_assert_integer(handle: Pointer, i: Int): Unit
-    fun OptionOpaque_option_opaque_argument(arg: Pointer?): Boolean
+    fun OptionOpaque_option_opaque_argument(arg: Pointer?): Byte
 }
 
 class OptionOpaque internal constructor (
@@ -36,7 +36,7 @@ class OptionOpaque internal constructor (
             val returnVal = lib.OptionOpaque_new(i);
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal ?: return null
+            val handle: Pointer = returnVal ?: return null
             val returnOpaque = OptionOpaque(handle, selfEdges)
             CLEANER.register(returnOpaque, OptionOpaque.OptionOpaqueCleaner(handle, OptionOpaque.lib));
             
@@ -48,7 +48,7 @@ class OptionOpaque internal constructor (
             val returnVal = lib.OptionOpaque_new_none();
         
             val selfEdges: List<Any> = listOf()
-            val handle = returnVal ?: return null
+            val handle: Pointer = returnVal ?: return null
             val returnOpaque = OptionOpaque(handle, selfEdges)
             CLEANER.register(returnOpaque, OptionOpaque.OptionOpaqueCleaner(handle, OptionOpaque.lib));
             
@@ -87,7 +87,7 @@ class OptionOpaque internal constructor (
         fun optionOpaqueArgument(arg: OptionOpaque?): Boolean {
             
             val returnVal = lib.OptionOpaque_option_opaque_argument(arg?.handle);
-            return returnVal
+            return returnVal > 0
         }
     }
     fun assertInteger(i: Int): Unit {
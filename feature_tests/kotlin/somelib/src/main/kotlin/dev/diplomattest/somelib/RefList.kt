@@ -33,7 +33,7 @@ class RefList internal constructor (
         
             val selfEdges: List<Any> = listOf()
             val bEdges: List<Any> = listOf(data)
-            val handle = returnVal 
+            val handle: Pointer = returnVal 
             val returnOpaque = RefList(handle, selfEdges, bEdges)
             CLEANER.register(returnOpaque, RefList.RefListCleaner(handle, RefList.lib));
             
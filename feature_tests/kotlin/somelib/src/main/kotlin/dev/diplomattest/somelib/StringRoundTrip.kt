@@ -0,0 +1,63 @@
+package dev.diplomattest.somelib;
+import com.sun.jna.Library
+import com.sun.jna.Native
+import com.sun.jna.Pointer
+
+
+internal interface StringRoundTripLib: Library {
+    fun StringRoundTrip_destroy(handle: Pointer)
+    fun StringRoundTrip_echo(s: Slice, writeable: Pointer): Unit
+    fun StringRoundTrip_echo_utf16(s: Slice, writeable: Pointer): Unit
+    fun StringRoundTrip_utf8_len(s: Slice): Long
+    fun StringRoundTrip_utf16_len(s: Slice): Long
+}
+
+class StringRoundTrip internal constructor (
+    internal val handle: Pointer,
+
+    // These ensure that anything that is borrowed is kept alive and not cleaned
+    // up by the garbage collector.
+    internal val selfEdges: List<Any>) {
+
+    internal class StringRoundTripCleaner(val handle: Pointer, val lib: StringRoundTripLib) : Runnable {
+        override fun run() {
+            lib.StringRoundTrip_destroy(handle)
+        }
+    }
+
+    companion object {
+        internal val libClass: Class<StringRoundTripLib> = StringRoundTripLib::class.java
+        internal val lib: StringRoundTripLib = Native.load("somelib", libClass)
+        fun echo(s: String): String {
+            val (sMem, sSlice) = PrimitiveArrayTools.readUtf8(s)
+            val writeable = DW.lib.diplomat_buffer_writeable_create(0)
+            val returnVal = lib.StringRoundTrip_echo(sSlice, writeable);
+        
+            val returnString = DW.writeableToString(writeable)
+            DW.lib.diplomat_buffer_writeable_destroy(writeable)
+            return returnString
+        }
+        fun echoUtf16(s: String): String {
+            val (sMem, sSlice) = PrimitiveArrayTools.readUtf16(s)
+            val writeable = DW.lib.diplomat_buffer_writeable_create(0)
+            val returnVal = lib.StringRoundTrip_echo_utf16(sSlice, writeable);
+        
+            val returnString = DW.writeableToString(writeable)
+            DW.lib.diplomat_buffer_writeable_destroy(writeable)
+            return returnString
+        }
+        fun utf8Len(s: String): Long {
+            val (sMem, sSlice) = PrimitiveArrayTools.readUtf8(s)
+            
+            val returnVal = lib.StringRoundTrip_utf8_len(sSlice);
+            return returnVal
+        }
+        fun utf16Len(s: String): Long {
+            val (sMem, sSlice) = PrimitiveArrayTools.readUtf16(s)
+            
+            val returnVal = lib.StringRoundTrip_utf16_len(sSlice);
+            return returnVal
+        }
+    }
+
+}
@@ -56,10 +56,12 @@ pub mod ffi {
 
     impl Unnamespaced {
         #[diplomat::attr(supports = constructors, named_constructor)]
+        #[diplomat::attr(dart, background)]
         pub fn make(_e: AttrEnum) -> Box<Self> {
             Box::new(Self)
         }
 
+        #[diplomat::attr(dart, background)]
         pub fn use_namespaced(&self, _n: &AttrOpaque1) {}
     }
 
@@ -18,4 +18,5 @@ pub mod option;
 pub mod result;
 pub mod selftype;
 pub mod slices;
+pub mod strings;
 pub mod structs;
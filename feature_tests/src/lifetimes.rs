@@ -36,6 +36,7 @@ pub mod ffi {
         }
 
         #[diplomat::attr(supports = constructors, named_constructor = "static")]
+        #[diplomat::attr(koka, disable)]
         pub fn new_static(x: &'static DiplomatStr) -> Box<Self> {
             Box::new(Foo(x))
         }
@@ -64,6 +65,8 @@ pub mod ffi {
     }
 
     impl<'x> BorrowedFields<'x> {
+        // Koka cannot borrow UTF-16 strings into the return value
+        #[diplomat::attr(koka, disable)]
         pub fn from_bar_and_strings(
             bar: &'x Bar<'x, 'x>,
             dstr16: &'x DiplomatStr16,
@@ -78,6 +81,8 @@ pub mod ffi {
     }
 
     impl<'x, 'y: 'x, 'z: 'y> BorrowedFieldsWithBounds<'x, 'y, 'z> {
+        // Koka cannot borrow UTF-16 strings into the return value
+        #[diplomat::attr(koka, disable)]
         pub fn from_foo_and_strings(
             foo: &'x Foo<'y>,
             dstr16_x: &'x DiplomatStr16,
@@ -98,6 +103,8 @@ pub mod ffi {
     }
 
     impl<'x, 'y: 'x, 'z> NestedBorrowedFields<'x, 'y, 'z> {
+        // Koka cannot borrow UTF-16 strings into the return value
+        #[diplomat::attr(koka, disable)]
         pub fn from_bar_and_foo_and_strings(
             bar: &'x Bar<'x, 'y>,
             foo: &'z Foo<'z>,
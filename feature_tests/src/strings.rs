@@ -0,0 +1,32 @@
+#[diplomat::bridge]
+mod ffi {
+    use diplomat_runtime::{DiplomatStr16, DiplomatWriteable};
+    use std::fmt::Write as _;
+
+    /// Sends strings back to check how bindings convert them. Lone surrogates become U+FFFD,
+    /// everything else, byte order marks included, comes back unchanged.
+    #[diplomat::opaque]
+    struct StringRoundTrip;
+
+    impl StringRoundTrip {
+        pub fn echo(s: &str, out: &mut DiplomatWriteable) {
+            let _ = out.write_str(s);
+            out.flush();
+        }
+
+        pub fn echo_utf16(s: &DiplomatStr16, out: &mut DiplomatWriteable) {
+            for c in char::decode_utf16(s.iter().copied()) {
+                let _ = out.write_char(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            out.flush();
+        }
+
+        pub fn utf8_len(s: &str) -> usize {
+            s.len()
+        }
+
+        pub fn utf16_len(s: &DiplomatStr16) -> usize {
+            s.len()
+        }
+    }
+}
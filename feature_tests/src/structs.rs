@@ -59,6 +59,11 @@ pub mod ffi {
             s.assert_value();
         }
 
+        #[diplomat::attr(supports = cloning, clone)]
+        pub fn duplicate(&self) -> Box<Opaque> {
+            Box::new(Opaque(self.0.clone()))
+        }
+
         pub fn returns_usize() -> usize {
             412
         }
@@ -13,6 +13,76 @@ fn cfgs_to_stream(attrs: &[Attribute]) -> proc_macro2::TokenStream {
         .fold(quote!(), |prev, attr| quote!(#prev #attr))
 }
 
+/// The type a value crosses the FFI boundary as.
+///
+/// A `bool` crosses as a `u8` holding 0 or 1: foreign code is not bound by Rust's validity
+/// invariant for `bool`, and some FFI layers pass booleans in wider integers, so Rust reads any
+/// nonzero byte as `true`. Structs, enums and options, which may hold `bool`s, cross as
+/// [`MaybeUninit`](core::mem::MaybeUninit) and are normalized before Rust reads them. A
+/// `DiplomatChar` is already a `u32`, which may not be a Unicode scalar value.
+fn abi_type(ty: &ast::TypeName) -> syn::Type {
+    match ty {
+        ast::TypeName::Primitive(ast::PrimitiveType::bool) => syn::parse_quote! { u8 },
+        ast::TypeName::Named(_) | ast::TypeName::SelfType(_) => {
+            let ty = ty.to_syn();
+            syn::parse_quote! { core::mem::MaybeUninit<#ty> }
+        }
+        // Unlike the nullable pointers of `Option<&T>`, these are passed with a flag
+        ast::TypeName::Option(inner) if !inner.is_pointer() => {
+            let inner = inner.to_syn();
+            syn::parse_quote! { core::mem::MaybeUninit<diplomat_runtime::DiplomatOption<#inner>> }
+        }
+        ast::TypeName::Function(_, output) => {
+            let output = abi_type(output);
+            syn::parse_quote! { diplomat_runtime::DiplomatCallback<#output> }
+        }
+        _ => ty.to_syn(),
+    }
+}
+
+/// Converts a value received from foreign code as its [`abi_type`]
+fn from_abi(ty: &ast::TypeName, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match ty {
+        ast::TypeName::Primitive(ast::PrimitiveType::bool) => quote! { #value != 0 },
+        ast::TypeName::Named(_) | ast::TypeName::SelfType(_) => {
+            quote! { unsafe { diplomat_runtime::abi::normalized(#value) } }
+        }
+        ast::TypeName::Option(inner) if !inner.is_pointer() => {
+            quote! { unsafe { diplomat_runtime::abi::normalized(#value) }.into_option() }
+        }
+        _ => value,
+    }
+}
+
+/// Converts a value passed to foreign code into its [`abi_type`]
+fn to_abi(ty: &ast::TypeName, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match ty {
+        ast::TypeName::Primitive(ast::PrimitiveType::bool) => quote! { #value as u8 },
+        ast::TypeName::Named(_) | ast::TypeName::SelfType(_) => {
+            quote! { core::mem::MaybeUninit::new(#value) }
+        }
+        ast::TypeName::Option(inner) if !inner.is_pointer() => {
+            quote! { core::mem::MaybeUninit::new(diplomat_runtime::DiplomatOption::from(#value)) }
+        }
+        _ => value,
+    }
+}
+
+/// The type a returned value crosses the FFI boundary as, and the conversion into it, for the
+/// `ok` and `err` of a [`DiplomatResult`](diplomat_runtime::DiplomatResult). Rust's own values
+/// need no normalizing, so only `bool`s are converted.
+fn result_abi(
+    ty: &ast::TypeName,
+    map: proc_macro2::TokenStream,
+) -> (syn::Type, proc_macro2::TokenStream) {
+    match ty {
+        ast::TypeName::Primitive(ast::PrimitiveType::bool) => {
+            (syn::parse_quote! { u8 }, quote! { .#map(u8::from) })
+        }
+        _ => (ty.to_syn(), quote! {}),
+    }
+}
+
 fn gen_params_at_boundary(param: &ast::Param, expanded_params: &mut Vec<FnArg>) {
     match &param.ty {
         ast::TypeName::StrReference(
@@ -24,7 +94,7 @@ fn gen_params_at_boundary(param: &ast::Param, expanded_params: &mut Vec<FnArg>)
         | ast::TypeName::PrimitiveSlice(..)
         | ast::TypeName::StrSlice(..) => {
             let data_type = if let ast::TypeName::PrimitiveSlice(.., prim) = &param.ty {
-                ast::TypeName::Primitive(*prim).to_syn().to_token_stream()
+                abi_type(&ast::TypeName::Primitive(*prim)).to_token_stream()
             } else if let ast::TypeName::StrReference(
                 _,
                 ast::StringEncoding::UnvalidatedUtf8 | ast::StringEncoding::Utf8,
@@ -105,7 +175,7 @@ fn gen_params_at_boundary(param: &ast::Param, expanded_params: &mut Vec<FnArg>)
                     subpat: None,
                 })),
                 colon_token: syn::token::Colon(Span::call_site()),
-                ty: Box::new(o.to_syn()),
+                ty: Box::new(abi_type(o)),
             }));
         }
     }
@@ -120,29 +190,55 @@ fn gen_params_invocation(param: &ast::Param, expanded_params: &mut Vec<Expr>) {
                 Ident::new(&format!("{}_diplomat_data", param.name), Span::call_site());
             let len_ident = Ident::new(&format!("{}_diplomat_len", param.name), Span::call_site());
 
-            let tokens = if let ast::TypeName::PrimitiveSlice(lm, _) = &param.ty {
+            let tokens = if let ast::TypeName::PrimitiveSlice(lm, prim) = &param.ty {
+                // The bytes of a `bool` slice are normalized, in place or into a copy if they can't be written
+                let is_bool = matches!(prim, ast::PrimitiveType::bool);
                 match lm {
-                    Some((_, ast::Mutability::Mutable)) => quote! {
-                        if #len_ident == 0 {
-                            &mut []
+                    Some((_, ast::Mutability::Mutable)) => {
+                        let slice = quote! { unsafe { core::slice::from_raw_parts_mut(#data_ident, #len_ident) } };
+                        let slice = if is_bool {
+                            quote! { diplomat_runtime::abi::bools_mut(#slice) }
                         } else {
-                            unsafe { core::slice::from_raw_parts_mut(#data_ident, #len_ident) }
+                            slice
+                        };
+                        quote! {
+                            if #len_ident == 0 {
+                                &mut []
+                            } else {
+                                #slice
+                            }
                         }
-                    },
-                    Some((_, ast::Mutability::Immutable)) => quote! {
-                        if #len_ident == 0 {
-                            &[]
+                    }
+                    Some((_, ast::Mutability::Immutable)) => {
+                        let slice = quote! {
+                            if #len_ident == 0 {
+                                &[]
+                            } else {
+                                unsafe { core::slice::from_raw_parts(#data_ident, #len_ident) }
+                            }
+                        };
+                        if is_bool {
+                            // Borrows the copy normalizing the slice, if any, for the call
+                            quote! { &*diplomat_runtime::abi::bools(#slice) }
                         } else {
-                            unsafe { core::slice::from_raw_parts(#data_ident, #len_ident) }
+                            slice
                         }
-                    },
-                    None => quote! {
-                        if #len_ident == 0 {
-                            Default::default()
+                    }
+                    None => {
+                        let slice = quote! { unsafe { alloc::boxed::Box::from_raw(core::ptr::slice_from_raw_parts_mut(#data_ident, #len_ident)) } };
+                        let slice = if is_bool {
+                            quote! { diplomat_runtime::abi::boxed_bools(#slice) }
                         } else {
-                            unsafe { alloc::boxed::Box::from_raw(core::ptr::slice_from_raw_parts_mut(#data_ident, #len_ident)) }
+                            slice
+                        };
+                        quote! {
+                            if #len_ident == 0 {
+                                Default::default()
+                            } else {
+                                #slice
+                            }
                         }
-                    },
+                    }
                 }
             } else if let ast::TypeName::StrReference(Some(_), encoding) = &param.ty {
                 let encode = match encoding {
@@ -194,6 +290,49 @@ fn gen_params_invocation(param: &ast::Param, expanded_params: &mut Vec<Expr>) {
             let param = &param.name;
             expanded_params.push(parse2(quote!(#param.into())).unwrap());
         }
+        ast::TypeName::DynTrait(_) => {
+            // The vtable struct implements the trait
+            let param = Ident::new(param.name.as_str(), Span::call_site());
+            expanded_params.push(parse2(quote!(&#param)).unwrap());
+        }
+        ast::TypeName::Function(params, output) => {
+            // Wrap the DiplomatCallback in a closure, which owns it and thus
+            // keeps the foreign closure alive for as long as Rust holds on to it
+            let callback = Ident::new(param.name.as_str(), Span::call_site());
+            let args = (0..params.len())
+                .map(|i| Ident::new(&format!("arg{i}"), Span::call_site()))
+                .collect::<Vec<_>>();
+            let arg_types = params.iter().map(|p| p.to_syn()).collect::<Vec<_>>();
+            let arg_abi_types = params.iter().map(|p| abi_type(p)).collect::<Vec<_>>();
+            let abi_args = params
+                .iter()
+                .zip(&args)
+                .map(|(p, arg)| to_abi(p, quote!(#arg)))
+                .collect::<Vec<_>>();
+            let output_abi_type = abi_type(output);
+            let result = from_abi(output, quote!(run_callback(callback.data #(, #abi_args)*)));
+            let output = output.to_syn();
+            expanded_params.push(
+                parse2(quote! {
+                    move |#(#args: #arg_types),*| -> #output {
+                        let callback = &#callback;
+                        unsafe {
+                            let run_callback: unsafe extern "C" fn(*const core::ffi::c_void #(, #arg_abi_types)*) -> #output_abi_type =
+                                core::mem::transmute(callback.run_callback);
+                            #result
+                        }
+                    }
+                })
+                .unwrap(),
+            );
+        }
+        ty @ (ast::TypeName::Primitive(_)
+        | ast::TypeName::Option(_)
+        | ast::TypeName::Named(_)
+        | ast::TypeName::SelfType(_)) => {
+            let param = Ident::new(param.name.as_str(), Span::call_site());
+            expanded_params.push(parse2(from_abi(ty, quote!(#param))).unwrap());
+        }
         _ => {
             expanded_params.push(Expr::Path(ExprPath {
                 attrs: vec![],
@@ -204,10 +343,120 @@ fn gen_params_invocation(param: &ast::Param, expanded_params: &mut Vec<Expr>) {
     }
 }
 
-fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
+/// Generates the struct a `&dyn Trait` is passed across the FFI boundary as: a pointer to
+/// the foreign object with a function pointer for each method, implementing the trait.
+fn gen_trait_vtable(trt: &ast::Trait) -> Vec<Item> {
+    let trait_ident = trt.name.to_syn();
+    let struct_ident = Ident::new(
+        &format!("DiplomatTraitStruct_{}", trt.name),
+        Span::call_site(),
+    );
+    let cfg = cfgs_to_stream(&trt.attrs.cfg);
+
+    let mut fields = vec![];
+    let mut methods = vec![];
+    for m in &trt.methods {
+        let method_ident = m.name.to_syn();
+        let field_ident = Ident::new(&format!("run_{}_callback", m.name), Span::call_site());
+        let param_idents = m.params.iter().map(|p| p.name.to_syn()).collect::<Vec<_>>();
+        let param_types = m.params.iter().map(|p| p.ty.to_syn()).collect::<Vec<_>>();
+        let param_abi_types = m.params.iter().map(|p| abi_type(&p.ty)).collect::<Vec<_>>();
+        let abi_params = m
+            .params
+            .iter()
+            .zip(&param_idents)
+            .map(|(p, ident)| to_abi(&p.ty, quote!(#ident)))
+            .collect::<Vec<_>>();
+        let output = m.output.as_ref().unwrap_or(&ast::TypeName::Unit);
+        let output_abi_type = abi_type(output);
+        let result = from_abi(
+            output,
+            quote!((self.#field_ident)(self.data #(, #abi_params)*)),
+        );
+        let output = output.to_syn();
+
+        fields.push(quote! {
+            pub #field_ident: unsafe extern "C" fn(*const core::ffi::c_void #(, #param_abi_types)*) -> #output_abi_type
+        });
+        methods.push(quote! {
+            fn #method_ident(&self #(, #param_idents: #param_types)*) -> #output {
+                unsafe { #result }
+            }
+        });
+    }
+
+    vec![
+        syn::parse_quote! {
+            #[repr(C)]
+            #cfg
+            pub struct #struct_ident {
+                pub data: *const core::ffi::c_void,
+                #(#fields,)*
+                pub destructor: Option<unsafe extern "C" fn(*const core::ffi::c_void)>,
+            }
+        },
+        syn::parse_quote! {
+            #cfg
+            impl #trait_ident for #struct_ident {
+                #(#methods)*
+            }
+        },
+        syn::parse_quote! {
+            #cfg
+            impl Drop for #struct_ident {
+                fn drop(&mut self) {
+                    if let Some(destructor) = self.destructor {
+                        unsafe { destructor(self.data) }
+                    }
+                }
+            }
+        },
+    ]
+}
+
+/// Generates the statements recording the opaques in a returned value as created, for the
+/// leak report of the runtime. `value` is a reference to the value.
+fn gen_created_records(
+    ty: &ast::TypeName,
+    value: proc_macro2::TokenStream,
+) -> Option<proc_macro2::TokenStream> {
+    match ty {
+        ast::TypeName::Box(_) => Some(quote! { diplomat_runtime::leaks::created(#value); }),
+        ast::TypeName::Option(inner) => {
+            let record = gen_created_records(inner, quote! { some })?;
+            Some(quote! {
+                if let Some(some) = #value {
+                    #record
+                }
+            })
+        }
+        ast::TypeName::Result(ok, err, _) => {
+            let ok_record = gen_created_records(ok, quote! { ok });
+            let err_record = gen_created_records(err, quote! { err });
+            if ok_record.is_none() && err_record.is_none() {
+                return None;
+            }
+            let ok_pat = ok_record.as_ref().map_or(quote! { _ }, |_| quote! { ok });
+            let err_pat = err_record.as_ref().map_or(quote! { _ }, |_| quote! { err });
+            Some(quote! {
+                match #value {
+                    Ok(#ok_pat) => { #ok_record }
+                    Err(#err_pat) => { #err_record }
+                }
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Generates the parameters of the `extern "C"` function wrapping a method, and the
+/// invocation of the method from within it.
+fn gen_method_params(
+    strct: &ast::CustomType,
+    m: &ast::Method,
+) -> (Vec<FnArg>, proc_macro2::TokenStream) {
     let self_ident = Ident::new(strct.name().as_str(), Span::call_site());
     let method_ident = Ident::new(m.name.as_str(), Span::call_site());
-    let extern_ident = Ident::new(m.full_path_name.as_str(), Span::call_site());
 
     let mut all_params = vec![];
     m.params.iter().for_each(|p| {
@@ -215,9 +464,24 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
     });
 
     let mut all_params_invocation = vec![];
-    m.params.iter().for_each(|p| {
-        gen_params_invocation(p, &mut all_params_invocation);
-    });
+    if let Some(bundle) = &m.bundle {
+        // The grouped parameters are read from the struct taken in their place
+        let bundle_ident = Ident::new(bundle.param_name().as_str(), Span::call_site());
+        bundle.rust_params.iter().for_each(|p| {
+            if bundle.contains(&p.name) {
+                let field = Ident::new(p.name.as_str(), Span::call_site());
+                all_params_invocation.push(parse_quote!(
+                    unsafe { diplomat_runtime::abi::normalized(#bundle_ident) }.#field
+                ));
+            } else {
+                gen_params_invocation(p, &mut all_params_invocation);
+            }
+        });
+    } else {
+        m.params.iter().for_each(|p| {
+            gen_params_invocation(p, &mut all_params_invocation);
+        });
+    }
 
     let this_ident = Pat::Ident(PatIdent {
         attrs: vec![],
@@ -227,6 +491,12 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
         subpat: None,
     });
 
+    // Structs are taken by value, and normalized
+    let struct_self = m
+        .self_param
+        .as_ref()
+        .filter(|s| matches!(strct, ast::CustomType::Struct(_)) && s.reference.is_none())
+        .map(|s| s.to_typename());
     if let Some(self_param) = &m.self_param {
         all_params.insert(
             0,
@@ -234,11 +504,33 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
                 attrs: vec![],
                 pat: Box::new(this_ident.clone()),
                 colon_token: syn::token::Colon(Span::call_site()),
-                ty: Box::new(self_param.to_typename().to_syn()),
+                ty: Box::new(match struct_self {
+                    Some(ref ty) => abi_type(ty),
+                    None => self_param.to_typename().to_syn(),
+                }),
             }),
         );
     }
 
+    let method_invocation = if m.constant.is_some() {
+        quote! { #self_ident::#method_ident }
+    } else if let Some(ref ty) = struct_self {
+        let this = from_abi(ty, quote! { #this_ident });
+        quote! { #this.#method_ident(#(#all_params_invocation),*) }
+    } else if m.self_param.is_some() {
+        quote! { #this_ident.#method_ident(#(#all_params_invocation),*) }
+    } else {
+        quote! { #self_ident::#method_ident(#(#all_params_invocation),*) }
+    };
+
+    (all_params, method_invocation)
+}
+
+fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
+    let extern_ident = Ident::new(m.full_path_name.as_str(), Span::call_site());
+
+    let (all_params, method_invocation) = gen_method_params(strct, m);
+
     let lifetimes = {
         let lifetime_env = &m.lifetime_env;
         if lifetime_env.is_empty() {
@@ -248,23 +540,20 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
         }
     };
 
-    let method_invocation = if m.self_param.is_some() {
-        quote! { #this_ident.#method_ident }
-    } else {
-        quote! { #self_ident::#method_ident }
-    };
-
     let (return_tokens, maybe_into) = if let Some(return_type) = &m.return_type {
         if let ast::TypeName::Result(ok, err, true) = return_type {
-            let ok = ok.to_syn();
-            let err = err.to_syn();
+            let (ok, ok_map) = result_abi(ok, quote! { map });
+            let (err, err_map) = result_abi(err, quote! { map_err });
             (
                 quote! { -> diplomat_runtime::DiplomatResult<#ok, #err> },
-                quote! { .into() },
+                quote! { #ok_map #err_map .into() },
             )
         } else if let ast::TypeName::Ordering = return_type {
             let return_type_syn = return_type.to_syn();
             (quote! { -> #return_type_syn }, quote! { as i8 })
+        } else if let ast::TypeName::Primitive(ast::PrimitiveType::bool) = return_type {
+            let return_type_syn = abi_type(return_type);
+            (quote! { -> #return_type_syn }, quote! { as u8 })
         } else if let ast::TypeName::Option(ty) = return_type {
             match ty.as_ref() {
                 // pass by reference, Option becomes null
@@ -274,10 +563,10 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
                 }
                 // anything else goes through DiplomatResult
                 _ => {
-                    let ty = ty.to_syn();
+                    let (ty, map) = result_abi(ty, quote! { map });
                     (
                         quote! { -> diplomat_runtime::DiplomatResult<#ty, ()> },
-                        quote! { .ok_or(()).into() },
+                        quote! { #map .ok_or(()).into() },
                     )
                 }
             }
@@ -299,14 +588,21 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
         })
         .collect::<Vec<_>>();
 
+    let created_records = m
+        .return_type
+        .as_ref()
+        .and_then(|return_type| gen_created_records(return_type, quote! { &ret }));
+
+    let destroyed_record = gen_destroyed_record(m);
+
     let cfg = cfgs_to_stream(&m.attrs.cfg);
 
-    if writeable_flushes.is_empty() {
+    if writeable_flushes.is_empty() && created_records.is_none() && destroyed_record.is_none() {
         Item::Fn(syn::parse_quote! {
             #[no_mangle]
             #cfg
             extern "C" fn #extern_ident#lifetimes(#(#all_params),*) #return_tokens {
-                #method_invocation(#(#all_params_invocation),*) #maybe_into
+                #method_invocation #maybe_into
             }
         })
     } else {
@@ -314,14 +610,144 @@ fn gen_custom_type_method(strct: &ast::CustomType, m: &ast::Method) -> Item {
             #[no_mangle]
             #cfg
             extern "C" fn #extern_ident#lifetimes(#(#all_params),*) #return_tokens {
-                let ret = #method_invocation(#(#all_params_invocation),*);
+                #destroyed_record
+                let ret = #method_invocation;
                 #(#writeable_flushes)*
+                #created_records
                 ret #maybe_into
             }
         })
     }
 }
 
+/// Records the opaque a method taking `self: Box<Self>` consumes, which foreign code hands back
+/// to Rust instead of destroying it.
+fn gen_destroyed_record(m: &ast::Method) -> Option<proc_macro2::TokenStream> {
+    m.self_param
+        .as_ref()
+        .filter(|self_param| self_param.boxed)
+        .map(|_| quote! { diplomat_runtime::leaks::destroyed(&this); })
+}
+
+/// Generates the `extern "C"` functions exposing an `async fn` method: one returning its
+/// boxed [`DiplomatFuture`](diplomat_runtime::DiplomatFuture), one polling the future with
+/// a foreign waker, and one destroying it.
+fn gen_async_method(strct: &ast::CustomType, m: &ast::Method) -> Vec<Item> {
+    let extern_ident = Ident::new(m.full_path_name.as_str(), Span::call_site());
+    let poll_ident = Ident::new(&format!("{}_poll", m.full_path_name), Span::call_site());
+    let destroy_ident = Ident::new(
+        &format!("{}_destroy_future", m.full_path_name),
+        Span::call_site(),
+    );
+
+    let (all_params, method_invocation) = gen_method_params(strct, m);
+
+    let output = match &m.return_type {
+        Some(return_type) => return_type.to_syn(),
+        None => syn::parse_quote! { () },
+    };
+    // The future borrows `self` if it is a reference, and nothing else
+    let future_lifetime = match &m.self_param {
+        Some(self_param) if self_param.reference.is_some() => quote! { '_ },
+        _ => quote! { 'static },
+    };
+    let future = quote! { diplomat_runtime::DiplomatFuture<#future_lifetime, #output> };
+    let destroyed_record = gen_destroyed_record(m);
+
+    let cfg = cfgs_to_stream(&m.attrs.cfg);
+
+    vec![
+        Item::Fn(syn::parse_quote! {
+            #[no_mangle]
+            #cfg
+            extern "C" fn #extern_ident(#(#all_params),*) -> Box<#future> {
+                #destroyed_record
+                Box::new(diplomat_runtime::DiplomatFuture::new(#method_invocation))
+            }
+        }),
+        Item::Fn(syn::parse_quote! {
+            #[no_mangle]
+            #cfg
+            extern "C" fn #poll_ident(
+                future: &mut #future,
+                waker: diplomat_runtime::DiplomatWaker,
+            ) -> diplomat_runtime::DiplomatResult<#output, ()> {
+                future.poll(waker)
+            }
+        }),
+        Item::Fn(syn::parse_quote! {
+            #[no_mangle]
+            #cfg
+            extern "C" fn #destroy_ident(future: Box<#future>) {}
+        }),
+    ]
+}
+
+/// Declares the struct grouping the parameters of a method marked `#[diplomat::bundle]`.
+fn gen_bundle_struct(strct: &ast::Struct) -> Item {
+    let name = strct.name.to_syn();
+    let fields = strct.fields.iter().map(|(name, ty, _)| {
+        let name = name.to_syn();
+        let ty = ty.to_syn();
+        quote! { pub #name: #ty }
+    });
+    let cfg = cfgs_to_stream(&strct.attrs.cfg);
+    Item::Struct(syn::parse_quote! {
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        #cfg
+        pub struct #name {
+            #(#fields),*
+        }
+    })
+}
+
+/// Implements `Normalize` for a struct or enum, which foreign code may pass to Rust: each field
+/// that may hold a `bool` is normalized in place.
+fn gen_normalize(custom_type: &ast::CustomType) -> Option<Item> {
+    let fields = match custom_type {
+        ast::CustomType::Struct(strct) => strct
+            .fields
+            .iter()
+            .filter(|(_, ty, _)| {
+                matches!(
+                    ty,
+                    ast::TypeName::Primitive(ast::PrimitiveType::bool)
+                        | ast::TypeName::Named(_)
+                        | ast::TypeName::SelfType(_)
+                        | ast::TypeName::DiplomatOption(_)
+                )
+            })
+            .map(|(name, ..)| name.to_syn())
+            .collect::<Vec<_>>(),
+        ast::CustomType::Enum(_) => vec![],
+        _ => return None,
+    };
+    let type_ident = custom_type.name().to_syn();
+    let (lifetime_defs, lifetimes) = if let Some(lifetime_env) = custom_type.lifetimes() {
+        (
+            quote! { <#lifetime_env> },
+            lifetime_env.lifetimes_to_tokens(),
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    let this = if fields.is_empty() {
+        quote! { _this }
+    } else {
+        quote! { this }
+    };
+    let cfg = cfgs_to_stream(&custom_type.attrs().cfg);
+    Some(syn::parse_quote! {
+        #cfg
+        impl #lifetime_defs diplomat_runtime::abi::Normalize for #type_ident #lifetimes {
+            unsafe fn normalize(#this: *mut Self) {
+                #(diplomat_runtime::abi::Normalize::normalize(core::ptr::addr_of_mut!((*this).#fields));)*
+            }
+        }
+    })
+}
+
 struct AttributeInfo {
     repr: bool,
     opaque: bool,
@@ -351,8 +777,10 @@ impl AttributeInfo {
                     } else if seg == "rust_link"
                         || seg == "out"
                         || seg == "attr"
+                        || seg == "bundle"
                         || seg == "skip_if_ast"
                         || seg == "abi_rename"
+                        || seg == "encoding"
                     {
                         // diplomat-tool reads these, not diplomat::bridge.
                         // throw them away so rustc doesn't complain about unknown attributes
@@ -436,20 +864,55 @@ fn gen_bridge(mut input: ItemMod) -> ItemMod {
 
         Item::Impl(i) => {
             for item in &mut i.items {
-                if let syn::ImplItem::Fn(ref mut m) = *item {
-                    let info = AttributeInfo::extract(&mut m.attrs);
-                    if info.opaque {
-                        panic!("#[diplomat::opaque] not allowed on methods")
+                match *item {
+                    syn::ImplItem::Fn(ref mut m) => {
+                        let info = AttributeInfo::extract(&mut m.attrs);
+                        if info.opaque {
+                            panic!("#[diplomat::opaque] not allowed on methods")
+                        }
+                        for input in m.sig.inputs.iter_mut() {
+                            if let syn::FnArg::Typed(ref mut t) = input {
+                                let info = AttributeInfo::extract(&mut t.attrs);
+                                if info.opaque {
+                                    panic!("#[diplomat::opaque] not allowed on parameters")
+                                }
+                            }
+                        }
                     }
+                    syn::ImplItem::Const(ref mut c) => {
+                        let info = AttributeInfo::extract(&mut c.attrs);
+                        if info.opaque {
+                            panic!("#[diplomat::opaque] not allowed on constants")
+                        }
+                    }
+                    _ => (),
                 }
             }
         }
         _ => (),
     });
 
+    for trt in module.declared_traits.values() {
+        new_contents.extend(gen_trait_vtable(trt));
+    }
+
     for custom_type in module.declared_types.values() {
+        if let ast::CustomType::Struct(strct) = custom_type {
+            if strct.bundle.is_some() {
+                new_contents.push(gen_bundle_struct(strct));
+            }
+        }
+
+        if let Some(normalize) = gen_normalize(custom_type) {
+            new_contents.push(normalize);
+        }
+
         custom_type.methods().iter().for_each(|m| {
-            new_contents.push(gen_custom_type_method(custom_type, m));
+            if m.is_async {
+                new_contents.extend(gen_async_method(custom_type, m));
+            } else {
+                new_contents.push(gen_custom_type_method(custom_type, m));
+            }
         });
 
         let destroy_ident = Ident::new(custom_type.dtor_name().as_str(), Span::call_site());
@@ -467,12 +930,14 @@ fn gen_bridge(mut input: ItemMod) -> ItemMod {
 
         let cfg = cfgs_to_stream(&custom_type.attrs().cfg);
 
-        // for now, body is empty since all we need to do is drop the box
+        // for now, all we need to do is drop the box, after recording it for the leak report
         // TODO(#13): change to take a `*mut` and handle DST boxes appropriately
         new_contents.push(Item::Fn(syn::parse_quote! {
             #[no_mangle]
             #cfg
-            extern "C" fn #destroy_ident#lifetime_defs(this: Box<#type_ident#lifetimes>) {}
+            extern "C" fn #destroy_ident#lifetime_defs(this: Box<#type_ident#lifetimes>) {
+                diplomat_runtime::leaks::destroyed(&this);
+            }
         }));
     }
 
@@ -570,6 +1035,8 @@ mod tests {
         drop(file);
 
         Command::new("rustfmt")
+            .arg("--edition")
+            .arg("2021")
             .arg(file_path.to_str().unwrap())
             .spawn()
             .unwrap()
@@ -603,6 +1070,25 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn method_taking_encoded_str() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Foo {}
+
+                    impl Foo {
+                        pub fn from_ascii(#[diplomat::encoding(ascii)] s: &DiplomatStr) {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
     #[test]
     fn method_taking_slice() {
         insta::assert_snapshot!(rustfmt_code(
@@ -679,6 +1165,218 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn method_taking_callback() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Foo {}
+
+                    impl Foo {
+                        pub fn apply(&self, f: impl Fn(i32, bool) -> u8) {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn method_taking_options() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    #[diplomat::opaque]
+                    struct Opaque;
+
+                    struct Foo {
+                        a: DiplomatOption<u32>,
+                    }
+
+                    impl Foo {
+                        pub fn apply(&self, a: Option<u32>, b: Option<bool>, c: Option<&Opaque>) {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn method_with_bundle() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    #[diplomat::opaque]
+                    struct File;
+
+                    enum Mode {
+                        Read,
+                        Write,
+                    }
+
+                    impl File {
+                        #[diplomat::bundle(OpenOptions, mode, create)]
+                        pub fn open(mode: Mode, path: &DiplomatStr, create: bool) -> Box<File> {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn method_taking_bool() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Foo {}
+
+                    impl Foo {
+                        pub fn select(&self, flag: bool, c: DiplomatChar) -> bool {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn struct_with_bools() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Flags {
+                        strict: bool,
+                        fallback: DiplomatOption<bool>,
+                    }
+
+                    struct Config {
+                        flags: Flags,
+                        limit: u32,
+                    }
+
+                    impl Config {
+                        pub fn apply(self, other: Config, flags: Option<Flags>) -> Config {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn method_returning_bool_payloads() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Foo {}
+
+                    impl Foo {
+                        pub fn find(key: u8) -> Option<bool> {
+                            unimplemented!()
+                        }
+
+                        pub fn check(key: u8) -> Result<bool, ()> {
+                            unimplemented!()
+                        }
+
+                        pub fn validate(key: u8) -> Result<u8, bool> {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn method_taking_bool_slices() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Foo {}
+
+                    impl Foo {
+                        pub fn count(flags: &[bool]) -> usize {
+                            unimplemented!()
+                        }
+
+                        pub fn invert(flags: &mut [bool]) {
+                            unimplemented!()
+                        }
+
+                        pub fn consume(flags: Box<[bool]>) {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn async_method() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Foo {}
+
+                    impl Foo {
+                        pub async fn fetch(&self, key: u32) -> f64 {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn method_taking_trait() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    pub trait Provider {
+                        fn get(&self, key: u32) -> f64;
+                        fn notify(&self, changed: bool);
+                    }
+
+                    struct Foo {}
+
+                    impl Foo {
+                        pub fn load(&self, provider: &dyn Provider) {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
     #[test]
     fn mod_with_enum() {
         insta::assert_snapshot!(rustfmt_code(
@@ -739,6 +1437,61 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn mod_with_max_length() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                #[diplomat::abi_rename(max_length = 24)]
+                mod ffi {
+                    #[diplomat::opaque]
+                    struct Foo(u8);
+
+                    impl Foo {
+                        pub fn short(&self) {}
+
+                        pub fn a_rather_long_method_name(&self) {}
+                    }
+
+                    #[diplomat::opaque]
+                    struct ALongOpaqueTypeName;
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn mod_returning_boxes() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    #[diplomat::opaque]
+                    struct Foo(u8);
+
+                    #[diplomat::opaque]
+                    struct Error;
+
+                    impl Foo {
+                        pub fn find(x: u8) -> Option<Box<Foo>> {
+                            unimplemented!()
+                        }
+
+                        pub fn parse(x: u8) -> Result<Box<Foo>, Box<Error>> {
+                            unimplemented!()
+                        }
+
+                        pub fn check(&self) -> Result<(), Box<Error>> {
+                            unimplemented!()
+                        }
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
     #[test]
     fn multilevel_borrows() {
         insta::assert_snapshot!(rustfmt_code(
@@ -774,6 +1527,55 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn consuming_self() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    #[diplomat::opaque]
+                    struct Builder(Vec<u8>);
+
+                    #[diplomat::opaque]
+                    struct Bytes(Vec<u8>);
+
+                    impl Builder {
+                        pub fn build(self: Box<Self>) -> Box<Bytes> {
+                            Box::new(Bytes(self.0))
+                        }
+
+                        pub fn discard(self: Box<Self>) {}
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn associated_constants() {
+        insta::assert_snapshot!(rustfmt_code(
+            &gen_bridge(parse_quote! {
+                mod ffi {
+                    struct Limits {
+                        min: i32,
+                        max: i32,
+                    }
+
+                    impl Limits {
+                        /// The widest limits
+                        #[diplomat::rust_link(foo::Limits::ALL, AssociatedConstantInStruct)]
+                        pub const ALL: Limits = Limits { min: i32::MIN, max: i32::MAX };
+                        pub const STRICT: bool = true;
+                        const HIDDEN: u8 = 0;
+                    }
+                }
+            })
+            .to_token_stream()
+            .to_string()
+        ));
+    }
+
     #[test]
     fn self_params() {
         insta::assert_snapshot!(rustfmt_code(
@@ -0,0 +1,79 @@
+//! Normalizing the values foreign code passes to Rust, used by the bridge macro.
+//!
+//! A `bool` crosses the FFI boundary as a byte holding 0 or 1, but foreign code is not bound by
+//! Rust's validity invariant for `bool`. Values holding one are therefore received as
+//! [`MaybeUninit`] and normalized in place, any nonzero byte becoming `true`, before Rust reads them.
+
+use alloc::borrow::Cow;
+use core::mem::MaybeUninit;
+
+/// A type whose values foreign code may pass to Rust.
+pub trait Normalize {
+    /// Rewrites each `bool` in the value at `this` to 0 or 1.
+    ///
+    /// # Safety
+    /// - `this` must be valid for reads and writes, and point to a value that is initialized
+    ///   except for the validity of its `bool`s.
+    unsafe fn normalize(this: *mut Self);
+}
+
+impl Normalize for bool {
+    unsafe fn normalize(this: *mut Self) {
+        let byte = this as *mut u8;
+        *byte = (*byte != 0) as u8;
+    }
+}
+
+macro_rules! impl_normalize_noop {
+    ($($ty:ty),*) => {
+        $(
+            impl Normalize for $ty {
+                unsafe fn normalize(_this: *mut Self) {}
+            }
+        )*
+    };
+}
+
+impl_normalize_noop!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64);
+
+/// Normalizes a value received from foreign code, see [`Normalize`].
+///
+/// # Safety
+/// - `value` must be initialized except for the validity of its `bool`s.
+pub unsafe fn normalized<T: Normalize>(mut value: MaybeUninit<T>) -> T {
+    T::normalize(value.as_mut_ptr());
+    value.assume_init()
+}
+
+/// Views the bytes of a `&[bool]` received from foreign code as `bool`s.
+///
+/// Unlike other values, Rust cannot rewrite the memory of a shared slice, so when a byte is
+/// other than 0 or 1 the slice is normalized into a copy instead.
+pub fn bools(bytes: &[u8]) -> Cow<'_, [bool]> {
+    if bytes.iter().all(|b| *b <= 1) {
+        // Safety: each byte is a valid `bool`, which has the size and alignment of `u8`.
+        Cow::Borrowed(unsafe {
+            core::slice::from_raw_parts(bytes.as_ptr() as *const bool, bytes.len())
+        })
+    } else {
+        Cow::Owned(bytes.iter().map(|b| *b != 0).collect())
+    }
+}
+
+/// Normalizes the bytes of a `&mut [bool]` received from foreign code in place, and views
+/// them as `bool`s.
+pub fn bools_mut(bytes: &mut [u8]) -> &mut [bool] {
+    for b in bytes.iter_mut() {
+        *b = (*b != 0) as u8;
+    }
+    // Safety: each byte is now a valid `bool`, which has the size and alignment of `u8`.
+    unsafe { core::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut bool, bytes.len()) }
+}
+
+/// Normalizes the bytes of a `Box<[bool]>` received from foreign code in place, and takes
+/// them as `bool`s.
+pub fn boxed_bools(mut bytes: alloc::boxed::Box<[u8]>) -> alloc::boxed::Box<[bool]> {
+    bools_mut(&mut bytes);
+    // Safety: each byte is now a valid `bool`, which has the layout of `u8`.
+    unsafe { alloc::boxed::Box::from_raw(alloc::boxed::Box::into_raw(bytes) as *mut [bool]) }
+}
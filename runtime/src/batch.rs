@@ -0,0 +1,116 @@
+use alloc::alloc::Layout;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+
+/// The size of the first chunk handed out by a [`DiplomatBatch`]. Later chunks
+/// double in size so that long call sequences need few allocations.
+const INITIAL_CHUNK_SIZE: usize = 1024;
+
+/// A temporary allocation scope shared by a sequence of calls.
+///
+/// Backends create a batch with [`diplomat_batch_begin()`], hand out temporary
+/// buffers (converted strings, slices, structs) from it with [`diplomat_batch_alloc()`],
+/// and release everything at once with [`diplomat_batch_end()`]. This replaces one
+/// [`diplomat_alloc()`](crate::diplomat_alloc)/[`diplomat_free()`](crate::diplomat_free)
+/// pair per temporary with a pointer bump, which matters for hot call sequences.
+///
+/// Allocations made from a batch must not outlive it: Rust APIs that retain the
+/// data they are passed need to be given memory from the regular allocator instead.
+pub struct DiplomatBatch {
+    /// All chunks allocated so far, the last one being the one currently bumped into
+    chunks: Vec<(*mut u8, Layout)>,
+    /// Offset of the first free byte in the current chunk
+    offset: usize,
+}
+
+impl DiplomatBatch {
+    fn new() -> Self {
+        DiplomatBatch {
+            chunks: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Allocate `size` bytes aligned to `align`, growing the batch if needed.
+    ///
+    /// Returns null if the allocation failed, or if `size` and `align` don't describe a
+    /// valid layout, instead of panicking across the FFI boundary.
+    fn alloc(&mut self, size: usize, align: usize) -> *mut u8 {
+        if !align.is_power_of_two() {
+            return ptr::null_mut();
+        }
+        if let Some(&(chunk, layout)) = self.chunks.last() {
+            let start = (chunk as usize + self.offset)
+                .checked_add(align - 1)
+                .map(|unaligned| unaligned & !(align - 1));
+            let end = start.and_then(|start| (start - chunk as usize).checked_add(size));
+            if let (Some(start), Some(end)) = (start, end) {
+                if end <= layout.size() {
+                    self.offset = end;
+                    return start as *mut u8;
+                }
+            }
+        }
+
+        let previous = self.chunks.last().map(|(_, l)| l.size()).unwrap_or(0);
+        let Some(chunk_size) = size
+            .checked_add(align)
+            .map(|min| previous.saturating_mul(2).max(INITIAL_CHUNK_SIZE).max(min))
+        else {
+            return ptr::null_mut();
+        };
+        let Ok(layout) = Layout::from_size_align(chunk_size, align.max(8)) else {
+            return ptr::null_mut();
+        };
+        let chunk = unsafe { alloc::alloc::alloc(layout) };
+        if chunk.is_null() {
+            return ptr::null_mut();
+        }
+        self.chunks.push((chunk, layout));
+        self.offset = size;
+        chunk
+    }
+}
+
+impl Drop for DiplomatBatch {
+    fn drop(&mut self) {
+        for (chunk, layout) in self.chunks.drain(..) {
+            unsafe { alloc::alloc::dealloc(chunk, layout) }
+        }
+    }
+}
+
+/// Begins a new batch scope.
+///
+/// The returned batch must be released with [`diplomat_batch_end()`].
+#[no_mangle]
+pub extern "C" fn diplomat_batch_begin() -> *mut DiplomatBatch {
+    Box::into_raw(Box::new(DiplomatBatch::new()))
+}
+
+/// Allocates a temporary buffer that lives until the batch is ended.
+///
+/// Returns null if the allocation failed, if `size` is too large to be allocated, or if
+/// `align` is not a power of two.
+///
+/// # Safety
+/// - `batch` must be a pointer returned by [`diplomat_batch_begin()`] that has not been ended.
+#[no_mangle]
+pub unsafe extern "C" fn diplomat_batch_alloc(
+    batch: *mut DiplomatBatch,
+    size: usize,
+    align: usize,
+) -> *mut u8 {
+    (*batch).alloc(size, align)
+}
+
+/// Ends a batch scope, releasing every buffer allocated from it at once.
+///
+/// # Safety
+/// - `batch` must be a pointer returned by [`diplomat_batch_begin()`] that has not been ended.
+/// - No pointer allocated from `batch` may be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn diplomat_batch_end(batch: *mut DiplomatBatch) {
+    drop(Box::from_raw(batch))
+}
@@ -0,0 +1,26 @@
+use core::ffi::c_void;
+
+/// A foreign closure passed to Rust as an `impl Fn(..)` parameter.
+///
+/// The foreign side boxes its closure into `data` and passes a trampoline that
+/// unpacks and calls it. `run_callback` is declared variadic because its real
+/// signature depends on the parameters of the `Fn`: the code generated by
+/// `#[diplomat::bridge]` casts it back to `extern "C" fn(*const c_void, Args..) -> ReturnType`
+/// before calling it.
+///
+/// The closure is kept alive until this value is dropped, at which point `destructor`
+/// (if any) is called with `data` so that the foreign side can release it.
+#[repr(C)]
+pub struct DiplomatCallback<ReturnType> {
+    pub data: *mut c_void,
+    pub run_callback: unsafe extern "C" fn(*const c_void, ...) -> ReturnType,
+    pub destructor: Option<unsafe extern "C" fn(*const c_void)>,
+}
+
+impl<ReturnType> Drop for DiplomatCallback<ReturnType> {
+    fn drop(&mut self) {
+        if let Some(destructor) = self.destructor {
+            unsafe { destructor(self.data) }
+        }
+    }
+}
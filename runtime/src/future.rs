@@ -0,0 +1,65 @@
+use crate::DiplomatResult;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::ffi::c_void;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+/// A foreign waker, passed to Rust when polling a [`DiplomatFuture`].
+///
+/// `wake` is called with `data` when the future can make progress, at which point
+/// the foreign side should poll it again. Rust may do so from any thread.
+///
+/// The waker is kept alive until Rust is done with it, at which point `destructor`
+/// (if any) is called with `data` so that the foreign side can release it.
+#[repr(C)]
+pub struct DiplomatWaker {
+    pub data: *const c_void,
+    pub wake: unsafe extern "C" fn(*const c_void),
+    pub destructor: Option<unsafe extern "C" fn(*const c_void)>,
+}
+
+// Safety: foreign code is required to make `wake` and `destructor` callable from any thread
+unsafe impl Send for DiplomatWaker {}
+unsafe impl Sync for DiplomatWaker {}
+
+impl Wake for DiplomatWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        unsafe { (self.wake)(self.data) }
+    }
+}
+
+impl Drop for DiplomatWaker {
+    fn drop(&mut self) {
+        if let Some(destructor) = self.destructor {
+            unsafe { destructor(self.data) }
+        }
+    }
+}
+
+/// The future of an `async fn` method, boxed and returned to foreign code, which
+/// polls it with a [`DiplomatWaker`] until it completes.
+pub struct DiplomatFuture<'a, T>(Pin<Box<dyn Future<Output = T> + 'a>>);
+
+impl<'a, T> DiplomatFuture<'a, T> {
+    pub fn new(future: impl Future<Output = T> + 'a) -> Self {
+        DiplomatFuture(Box::pin(future))
+    }
+
+    /// Polls the future once, returning its output if it is ready.
+    ///
+    /// If it is not, `waker` will be woken once it can make progress.
+    pub fn poll(&mut self, waker: DiplomatWaker) -> DiplomatResult<T, ()> {
+        let waker = Waker::from(Arc::new(waker));
+        match self.0.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => Ok(output).into(),
+            Poll::Pending => Err(()).into(),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Statistics of the opaques handed out to foreign code, to report the ones never destroyed.
+//!
+//! The functions generated by `#[diplomat::bridge]` record every boxed opaque they return and
+//! every one they destroy. This is only tracked with the `leak-report` feature, otherwise the
+//! records compile to nothing and `diplomat_leak_report()` is not exported.
+
+use alloc::boxed::Box;
+
+#[cfg(feature = "leak-report")]
+mod tracking {
+    use alloc::vec::Vec;
+    use core::cell::UnsafeCell;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    /// The number of objects of a type created and destroyed so far
+    pub struct Counts {
+        pub type_name: &'static str,
+        pub created: usize,
+        pub destroyed: usize,
+    }
+
+    /// The counts of every type seen, behind a spin lock since the runtime is `no_std`
+    pub struct Registry {
+        locked: AtomicBool,
+        counts: UnsafeCell<Vec<Counts>>,
+    }
+
+    // Safety: `counts` is only accessed with `locked` held
+    unsafe impl Sync for Registry {}
+
+    pub static REGISTRY: Registry = Registry {
+        locked: AtomicBool::new(false),
+        counts: UnsafeCell::new(Vec::new()),
+    };
+
+    impl Registry {
+        pub fn with<R>(&self, f: impl FnOnce(&mut Vec<Counts>) -> R) -> R {
+            while self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                core::hint::spin_loop();
+            }
+            // Safety: the lock is held until the end of the call
+            let result = f(unsafe { &mut *self.counts.get() });
+            self.locked.store(false, Ordering::Release);
+            result
+        }
+
+        pub fn record(&self, type_name: &'static str, f: impl FnOnce(&mut Counts)) {
+            self.with(|counts| {
+                let index = match counts.iter().position(|c| c.type_name == type_name) {
+                    Some(index) => index,
+                    None => {
+                        counts.push(Counts {
+                            type_name,
+                            created: 0,
+                            destroyed: 0,
+                        });
+                        counts.len() - 1
+                    }
+                };
+                f(&mut counts[index]);
+            })
+        }
+    }
+}
+
+/// Records an opaque returned to foreign code, which now owns it.
+#[doc(hidden)]
+#[inline]
+#[allow(clippy::borrowed_box)]
+pub fn created<T: ?Sized>(_object: &Box<T>) {
+    #[cfg(feature = "leak-report")]
+    tracking::REGISTRY.record(core::any::type_name::<T>(), |c| c.created += 1);
+}
+
+/// Records an opaque destroyed by foreign code.
+#[doc(hidden)]
+#[inline]
+#[allow(clippy::borrowed_box)]
+pub fn destroyed<T: ?Sized>(_object: &Box<T>) {
+    #[cfg(feature = "leak-report")]
+    tracking::REGISTRY.record(core::any::type_name::<T>(), |c| c.destroyed += 1);
+}
+
+/// Writes a line for every type with objects that are still alive, with the number alive and
+/// the number created overall, returning the total number alive.
+///
+/// Bindings call this at program exit, when everything still alive has leaked.
+#[cfg(feature = "leak-report")]
+#[no_mangle]
+pub extern "C" fn diplomat_leak_report(out: &mut crate::DiplomatWriteable) -> usize {
+    use core::fmt::Write;
+    let live = tracking::REGISTRY.with(|counts| {
+        let mut live = 0;
+        for c in counts.iter().filter(|c| c.created > c.destroyed) {
+            let alive = c.created - c.destroyed;
+            live += alive;
+            let _ = writeln!(
+                out,
+                "{}: {alive} never freed ({} created)",
+                c.type_name, c.created
+            );
+        }
+        live
+    });
+    out.flush();
+    live
+}
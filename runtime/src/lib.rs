@@ -14,6 +14,27 @@ pub use writeable::DiplomatWriteable;
 mod result;
 pub use result::DiplomatResult;
 
+mod option;
+pub use option::DiplomatOption;
+
+mod batch;
+pub use batch::DiplomatBatch;
+
+mod callback;
+pub use callback::DiplomatCallback;
+
+mod future;
+pub use future::{DiplomatFuture, DiplomatWaker};
+
+mod self_test;
+pub use self_test::{diplomat_self_test, DiplomatSelfTest};
+
+#[doc(hidden)]
+pub mod leaks;
+
+#[doc(hidden)]
+pub mod abi;
+
 /// Like [`char`], but unvalidated.
 pub type DiplomatChar = u32;
 
@@ -44,3 +65,11 @@ pub unsafe extern "C" fn diplomat_alloc(size: usize, align: usize) -> *mut u8 {
 pub unsafe extern "C" fn diplomat_free(ptr: *mut u8, size: usize, align: usize) {
     alloc::alloc::dealloc(ptr, Layout::from_size_align(size, align).unwrap())
 }
+
+/// The width of pointers, `usize` and `isize` in bits.
+///
+/// Bindings generated for a fixed target check that the library they load was built for it.
+#[no_mangle]
+pub extern "C" fn diplomat_pointer_width() -> u32 {
+    usize::BITS
+}
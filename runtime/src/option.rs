@@ -0,0 +1,56 @@
+use crate::abi::Normalize;
+use core::mem::MaybeUninit;
+
+/// An [`Option`]-like type that can be passed across the FFI boundary
+/// as a value, for optional parameters and struct fields.
+///
+/// It has the layout of a [`DiplomatResult<T, ()>`](crate::DiplomatResult): the value,
+/// followed by a flag telling whether it is set.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DiplomatOption<T: Copy> {
+    value: MaybeUninit<T>,
+    pub is_ok: bool,
+}
+
+impl<T: Copy> DiplomatOption<T> {
+    /// Converts to an [`Option`], reading the value only if it is set.
+    pub fn into_option(self) -> Option<T> {
+        if self.is_ok {
+            // Safety: the value is initialized whenever `is_ok` is set.
+            Some(unsafe { self.value.assume_init() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Copy + Normalize> Normalize for DiplomatOption<T> {
+    unsafe fn normalize(this: *mut Self) {
+        bool::normalize(core::ptr::addr_of_mut!((*this).is_ok));
+        if (*this).is_ok {
+            T::normalize((*this).value.as_mut_ptr());
+        }
+    }
+}
+
+impl<T: Copy> From<Option<T>> for DiplomatOption<T> {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(value) => DiplomatOption {
+                value: MaybeUninit::new(value),
+                is_ok: true,
+            },
+            None => DiplomatOption {
+                value: MaybeUninit::uninit(),
+                is_ok: false,
+            },
+        }
+    }
+}
+
+impl<T: Copy> From<DiplomatOption<T>> for Option<T> {
+    fn from(option: DiplomatOption<T>) -> Option<T> {
+        option.into_option()
+    }
+}
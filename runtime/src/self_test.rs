@@ -0,0 +1,59 @@
+//! Values of known bytes and layouts, for bindings to check that they read Rust's memory right.
+//!
+//! Bindings going through C declarations get the byte order and the layouts from the C compiler,
+//! but bindings reading memory themselves, like JS reading WASM memory, have to agree with
+//! Rust on both. They call `diplomat_self_test()` at startup and compare what they read with
+//! the values documented on each field of [`DiplomatSelfTest`], so that reading a layout wrong,
+//! or in the wrong byte order on a big-endian target like s390x, fails right away.
+
+use crate::DiplomatResult;
+
+/// The elements of [`DiplomatSelfTest::slice_data`]
+static SLICE: [u16; 3] = [0x0102, 0x0304, 0x0506];
+
+/// The values written by [`diplomat_self_test()`].
+#[repr(C)]
+pub struct DiplomatSelfTest {
+    /// Whether the target is big-endian
+    pub big_endian: bool,
+    /// `0x0102`
+    pub u16_value: u16,
+    /// `0x0102_0304`
+    pub u32_value: u32,
+    /// `0x0102_0304_0506_0708`
+    pub u64_value: u64,
+    /// `-2.5`
+    pub f64_value: f64,
+    /// `Ok(0x0102_0304)`
+    pub ok: DiplomatResult<u32, u8>,
+    /// `Err(0x0506_0708)`
+    pub err: DiplomatResult<u8, u32>,
+    /// A slice of `[0x0102, 0x0304, 0x0506]`
+    pub slice_data: *const u16,
+    pub slice_len: usize,
+}
+
+impl DiplomatSelfTest {
+    fn new() -> Self {
+        DiplomatSelfTest {
+            big_endian: cfg!(target_endian = "big"),
+            u16_value: 0x0102,
+            u32_value: 0x0102_0304,
+            u64_value: 0x0102_0304_0506_0708,
+            f64_value: -2.5,
+            ok: Ok(0x0102_0304).into(),
+            err: Err(0x0506_0708).into(),
+            slice_data: SLICE.as_ptr(),
+            slice_len: SLICE.len(),
+        }
+    }
+}
+
+/// Writes a [`DiplomatSelfTest`] to `out`.
+///
+/// # Safety
+/// - `out` must be valid for writes and aligned for a [`DiplomatSelfTest`].
+#[no_mangle]
+pub unsafe extern "C" fn diplomat_self_test(out: *mut DiplomatSelfTest) {
+    out.write(DiplomatSelfTest::new())
+}
@@ -1,8 +1,9 @@
 //! This module contains functions for formatting types
 
 use super::ty::ResultType;
+use diplomat_core::ast::{self, MarkdownStyle};
 use diplomat_core::hir::{
-    self, OpaqueOwner, StringEncoding, StructPathLike, Type, TypeContext, TypeId,
+    self, OpaqueOwner, StringEncoding, StructPathLike, TraitId, Type, TypeContext, TypeId,
 };
 use std::borrow::Cow;
 
@@ -19,6 +20,152 @@ pub struct CFormatter<'tcx> {
     tcx: &'tcx TypeContext,
 }
 
+/// C11 keywords, plus the `stdbool.h` macros the generated headers pull in
+const C_RESERVED_WORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "true",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "_Alignas",
+    "_Alignof",
+    "_Atomic",
+    "_Bool",
+    "_Complex",
+    "_Generic",
+    "_Imaginary",
+    "_Noreturn",
+    "_Static_assert",
+    "_Thread_local",
+];
+/// C++20 keywords and alternative operator tokens
+pub const CPP_RESERVED_WORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "and",
+    "and_eq",
+    "asm",
+    "auto",
+    "bitand",
+    "bitor",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "char8_t",
+    "char16_t",
+    "char32_t",
+    "class",
+    "compl",
+    "concept",
+    "const",
+    "consteval",
+    "constexpr",
+    "constinit",
+    "const_cast",
+    "continue",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "decltype",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "dynamic_cast",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "not_eq",
+    "nullptr",
+    "operator",
+    "or",
+    "or_eq",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "reinterpret_cast",
+    "requires",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "static_cast",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "thread_local",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typeid",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "wchar_t",
+    "while",
+    "xor",
+    "xor_eq",
+];
+
 impl<'tcx> CFormatter<'tcx> {
     pub fn new(tcx: &'tcx TypeContext) -> Self {
         Self { tcx }
@@ -40,6 +187,25 @@ impl<'tcx> CFormatter<'tcx> {
         self.tcx.resolve_type(id).name().as_str().into()
     }
 
+    /// Format the name of the function looking up the message of an error enum's variant
+    pub fn fmt_error_message_fn_name(&self, type_name: &str) -> String {
+        format!("{type_name}_message")
+    }
+
+    /// Format the message of an error enum's variant as the contents of a C string literal:
+    /// the first line of its docs, or its name if it has none
+    pub fn fmt_error_message(&self, variant: &'tcx hir::EnumVariant) -> String {
+        let docs = variant
+            .docs
+            .to_markdown(&Default::default(), MarkdownStyle::Normal);
+        let message = docs
+            .lines()
+            .next()
+            .filter(|line| !line.trim().is_empty())
+            .unwrap_or(variant.name.as_str());
+        message.trim().replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
     /// Resolve and format the name of a type for use in header names: decl version
     //
     /// Enums can't be forward-declared in C, but we do want enums to have methods,
@@ -72,7 +238,12 @@ impl<'tcx> CFormatter<'tcx> {
     /// Format a field name or parameter name
     // might need splitting in the future if we decide to support renames here
     pub fn fmt_param_name<'a>(&self, ident: &'a str) -> Cow<'a, str> {
-        ident.into()
+        // The headers are also included from C++, so avoid its keywords too
+        if C_RESERVED_WORDS.contains(&ident) || CPP_RESERVED_WORDS.contains(&ident) {
+            format!("{ident}_").into()
+        } else {
+            ident.into()
+        }
     }
 
     /// Format a method
@@ -80,14 +251,77 @@ impl<'tcx> CFormatter<'tcx> {
         let ty_name = self.fmt_type_name(ty);
         let method_name = method.name.as_str();
         let put_together = format!("{ty_name}_{method_name}");
-        method.attrs.abi_rename.apply(put_together.into()).into()
+        let rename = &method.attrs.abi_rename;
+        rename
+            .shorten(
+                rename.apply(put_together.into()),
+                ast::async_suffix_len(method.is_async),
+            )
+            .into()
+    }
+
+    /// Format the struct a callback parameter of a method is passed as
+    pub fn fmt_callback_name(&self, c_method_name: &str, param_name: &str) -> String {
+        format!("DiplomatCallback_{c_method_name}_{param_name}")
+    }
+
+    /// Format a function pointer field of a callback or trait vtable struct
+    pub fn fmt_callback_fn_ptr(&self, field_name: &str, callback: &hir::Callback) -> String {
+        let output = match callback.output {
+            Some(output) => self.fmt_primitive_as_c(output),
+            None => "void".into(),
+        };
+        let mut params = String::new();
+        for param in &callback.params {
+            params.push_str(", ");
+            params.push_str(&self.fmt_primitive_as_c(*param));
+        }
+        format!("{output} (*{field_name})(const void*{params})")
+    }
+
+    /// Resolve and format the vtable struct a trait object parameter is passed as,
+    /// matching the struct generated by the `diplomat::bridge` macro
+    pub fn fmt_trait_vtable_name(&self, id: TraitId) -> String {
+        let trait_name = self.tcx.resolve_trait(id).name.as_str();
+        format!("DiplomatTraitStruct_{trait_name}")
+    }
+
+    /// Resolve and format the name of the header containing a trait's vtable struct
+    pub fn fmt_trait_header_path(&self, id: TraitId) -> String {
+        let trait_name = self.tcx.resolve_trait(id).name.as_str();
+        format!("{trait_name}.d.h")
+    }
+
+    /// Format the preprocessor condition under which a method marked `platforms(..)` is
+    /// declared, or `None` if it is available everywhere
+    pub fn fmt_platform_condition(&self, platforms: &[String]) -> Option<String> {
+        if platforms.is_empty() {
+            return None;
+        }
+        let conditions = platforms
+            .iter()
+            .map(|platform| match platform.as_str() {
+                "android" => "defined(__ANDROID__)",
+                "freebsd" => "defined(__FreeBSD__)",
+                "ios" => "defined(__ENVIRONMENT_IPHONE_OS_VERSION_MIN_REQUIRED__)",
+                "linux" => "(defined(__linux__) && !defined(__ANDROID__))",
+                "macos" => "defined(__ENVIRONMENT_MAC_OS_X_VERSION_MIN_REQUIRED__)",
+                "wasm" => "defined(__wasm__)",
+                "windows" => "defined(_WIN32)",
+                _ => unreachable!("unknown platforms are rejected by `Attrs::from_ast`"),
+            })
+            .collect::<Vec<_>>();
+        Some(conditions.join(" || "))
     }
 
     /// Resolve and format a type's destructor
     pub fn fmt_dtor_name(&self, ty: TypeId) -> String {
         let ty_name = self.fmt_type_name(ty);
-        let renamed = self.tcx.resolve_type(ty).attrs().abi_rename.apply(ty_name);
-        format!("{renamed}_destroy")
+        let rename = &self.tcx.resolve_type(ty).attrs().abi_rename;
+        let renamed = rename.apply(ty_name);
+        rename
+            .shorten(format!("{renamed}_destroy").into(), 0)
+            .into()
     }
 
     pub fn fmt_ptr<'a>(&self, ident: &'a str, mutability: hir::Mutability) -> Cow<'a, str> {
@@ -105,7 +339,7 @@ impl<'tcx> CFormatter<'tcx> {
     ///
     /// This is primarily used for generating structs for result types,
     /// which require one struct for each distinct instance.
-    pub fn fmt_type_name_uniquely<P: hir::TyPosition>(&self, ty: &'tcx Type<P>) -> Cow<'tcx, str> {
+    pub fn fmt_type_name_uniquely<P: hir::TyPosition>(&self, ty: &Type<P>) -> Cow<'tcx, str> {
         match ty {
             Type::Primitive(p) => self.fmt_primitive_as_c(*p),
             Type::Opaque(o) => {
@@ -139,6 +373,11 @@ impl<'tcx> CFormatter<'tcx> {
         format!("diplomat_result_{ok_ty_name}_{err_ty_name}")
     }
 
+    /// The name of the struct an `Option<T>` parameter or `DiplomatOption<T>` field is passed as
+    pub fn fmt_option_name(&self, ty_name: &str) -> String {
+        format!("diplomat_option_{ty_name}")
+    }
+
     pub fn fmt_result_for_diagnostics(&self, r: ResultType) -> String {
         let ok = if let Some(ok) = r.0 {
             self.fmt_type_name_uniquely(ok)
@@ -176,4 +415,45 @@ impl<'tcx> CFormatter<'tcx> {
         };
         s.into()
     }
+
+    /// Get the view type of a primitive slice, as named in the runtime header
+    pub fn fmt_primitive_slice_name(&self, prim: hir::PrimitiveType) -> &'static str {
+        use diplomat_core::hir::{FloatType, IntSizeType, IntType, PrimitiveType};
+        match prim {
+            PrimitiveType::Bool => "DiplomatBoolView",
+            PrimitiveType::Char => "DiplomatCharView",
+            PrimitiveType::Int(IntType::I8) => "DiplomatI8View",
+            PrimitiveType::Int(IntType::U8) | PrimitiveType::Byte => "DiplomatU8View",
+            PrimitiveType::Int(IntType::I16) => "DiplomatI16View",
+            PrimitiveType::Int(IntType::U16) => "DiplomatU16View",
+            PrimitiveType::Int(IntType::I32) => "DiplomatI32View",
+            PrimitiveType::Int(IntType::U32) => "DiplomatU32View",
+            PrimitiveType::Int(IntType::I64) => "DiplomatI64View",
+            PrimitiveType::Int(IntType::U64) => "DiplomatU64View",
+            PrimitiveType::Int128(_) => panic!("i128 not supported in C"),
+            PrimitiveType::IntSize(IntSizeType::Isize) => "DiplomatIsizeView",
+            PrimitiveType::IntSize(IntSizeType::Usize) => "DiplomatUsizeView",
+            PrimitiveType::Float(FloatType::F32) => "DiplomatF32View",
+            PrimitiveType::Float(FloatType::F64) => "DiplomatF64View",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CFormatter, CPP_RESERVED_WORDS, C_RESERVED_WORDS};
+    use crate::common::keyword_tcx;
+
+    #[test]
+    fn test_reserved_words() {
+        // C names methods after their type and does not apply renames to types, so only
+        // parameter names can end up as bare keywords
+        let tcx = keyword_tcx(&[], &[]);
+        let formatter = CFormatter::new(&tcx);
+        for word in C_RESERVED_WORDS.iter().chain(CPP_RESERVED_WORDS) {
+            let name = formatter.fmt_param_name(word);
+            assert!(!C_RESERVED_WORDS.contains(&&*name), "param name {name:?}");
+            assert!(!CPP_RESERVED_WORDS.contains(&&*name), "param name {name:?}");
+        }
+    }
 }
@@ -0,0 +1,154 @@
+#ifndef DIPLOMAT_FUZZ_H
+#define DIPLOMAT_FUZZ_H
+
+// Decodes the arguments of the generated fuzz targets from libFuzzer's input. The decoding
+// functions return false (or NULL) once the input runs out, which targets treat as nothing to do.
+
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "../diplomat_runtime.h"
+
+#define DIPLOMAT_FUZZ_MAX_ALLOCS 64
+
+typedef struct DiplomatFuzzInput {
+    const uint8_t* data;
+    size_t len;
+    // The buffers slices are copied into, freed by `diplomat_fuzz_end`
+    void* allocs[DIPLOMAT_FUZZ_MAX_ALLOCS];
+    size_t alloc_count;
+} DiplomatFuzzInput;
+
+static inline DiplomatFuzzInput diplomat_fuzz_begin(const uint8_t* data, size_t len) {
+    DiplomatFuzzInput in = {data, len, {0}, 0};
+    return in;
+}
+
+static inline void diplomat_fuzz_end(DiplomatFuzzInput* in) {
+    for (size_t i = 0; i < in->alloc_count; i++) {
+        free(in->allocs[i]);
+    }
+}
+
+// Copies the next `size` bytes of the input into `out`
+static inline bool diplomat_fuzz_read(DiplomatFuzzInput* in, void* out, size_t size) {
+    if (in->len < size) {
+        return false;
+    }
+    memcpy(out, in->data, size);
+    in->data += size;
+    in->len -= size;
+    return true;
+}
+
+// Reads a bool from the low bit of the next byte, Rust only accepting 0 and 1
+static inline bool diplomat_fuzz_bool(DiplomatFuzzInput* in, bool* out) {
+    uint8_t byte;
+    if (!diplomat_fuzz_read(in, &byte, 1)) {
+        return false;
+    }
+    *out = byte & 1;
+    return true;
+}
+
+// Picks one of `count` choices, such as the variants of an enum
+static inline bool diplomat_fuzz_pick(DiplomatFuzzInput* in, size_t count, size_t* out) {
+    uint8_t byte;
+    if (!diplomat_fuzz_read(in, &byte, 1)) {
+        return false;
+    }
+    *out = byte % count;
+    return true;
+}
+
+// Reads a slice of elements of `size` bytes, prefixed by its length as two bytes and cut short
+// if the input ends first. The elements are copied into an aligned buffer living until
+// `diplomat_fuzz_end`, which is never NULL even for empty slices, as Rust requires.
+static inline void* diplomat_fuzz_slice(DiplomatFuzzInput* in, size_t size, size_t* len) {
+    uint16_t count;
+    if (in->alloc_count == DIPLOMAT_FUZZ_MAX_ALLOCS || !diplomat_fuzz_read(in, &count, 2)) {
+        return NULL;
+    }
+    size_t n = count < in->len / size ? count : in->len / size;
+    void* buf = malloc(n * size + 1);
+    if (buf == NULL) {
+        return NULL;
+    }
+    in->allocs[in->alloc_count++] = buf;
+    diplomat_fuzz_read(in, buf, n * size);
+    *len = n;
+    return buf;
+}
+
+// Turns the bytes of a bool slice into 0 or 1
+static inline void diplomat_fuzz_fix_bools(bool* data, size_t len) {
+    for (size_t i = 0; i < len; i++) {
+        ((uint8_t*)data)[i] &= 1;
+    }
+}
+
+// Whether a string slice passed as `&str` is valid UTF-8, without which Rust's behavior is undefined
+static inline bool diplomat_fuzz_is_utf8(const char* s, size_t len) {
+    const uint8_t* p = (const uint8_t*)s;
+    size_t i = 0;
+    while (i < len) {
+        uint8_t b = p[i];
+        size_t n;
+        uint32_t min, c;
+        if (b < 0x80) {
+            i++;
+            continue;
+        } else if ((b & 0xE0) == 0xC0) {
+            n = 1, min = 0x80, c = b & 0x1F;
+        } else if ((b & 0xF0) == 0xE0) {
+            n = 2, min = 0x800, c = b & 0x0F;
+        } else if ((b & 0xF8) == 0xF0) {
+            n = 3, min = 0x10000, c = b & 0x07;
+        } else {
+            return false;
+        }
+        if (len - i <= n) {
+            return false;
+        }
+        for (size_t j = 1; j <= n; j++) {
+            if ((p[i + j] & 0xC0) != 0x80) {
+                return false;
+            }
+            c = (c << 6) | (p[i + j] & 0x3F);
+        }
+        if (c < min || c > 0x10FFFF || (c >= 0xD800 && c <= 0xDFFF)) {
+            return false;
+        }
+        i += n + 1;
+    }
+    return true;
+}
+
+// Whether a string slice required to be ASCII is
+static inline bool diplomat_fuzz_is_ascii(const char* s, size_t len) {
+    for (size_t i = 0; i < len; i++) {
+        if ((uint8_t)s[i] >= 0x80) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Whether a string slice required to be UTF-16 has no unpaired surrogates
+static inline bool diplomat_fuzz_is_utf16(const char16_t* s, size_t len) {
+    for (size_t i = 0; i < len; i++) {
+        if (s[i] >= 0xD800 && s[i] <= 0xDBFF) {
+            if (i + 1 == len || s[i + 1] < 0xDC00 || s[i + 1] > 0xDFFF) {
+                return false;
+            }
+            i++;
+        } else if (s[i] >= 0xDC00 && s[i] <= 0xDFFF) {
+            return false;
+        }
+    }
+    return true;
+}
+
+#endif // DIPLOMAT_FUZZ_H
@@ -0,0 +1,527 @@
+//! libFuzzer targets calling the methods of the C API with arguments decoded from the fuzzer's
+//! input, generated with [`CConfig::fuzz_targets`](super::CConfig::fuzz_targets).
+//!
+//! Each method gets a `fuzz/<method>.c` file defining `LLVMFuzzerTestOneInput`. Opaque
+//! arguments are made with a constructor of their type, a static method returning a new one.
+//! Methods whose arguments can't be decoded from bytes, or whose results can't be released
+//! without knowing more than their type, are left out.
+
+use super::CContext;
+use diplomat_core::ast::RequiredEncoding;
+use diplomat_core::hir::{
+    self, OpaqueId, OpaqueOwner, ReturnType, SelfType, StructPathLike, SuccessType, TyPosition,
+    Type, TypeDef, TypeId,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// The directory the targets are generated into
+const FUZZ_DIR: &str = "fuzz";
+
+/// The header with the functions decoding arguments, shared by the targets
+const FUZZ_H: &str = include_str!("fuzz.h");
+
+impl<'tcx> CContext<'tcx> {
+    /// Generates a fuzz target for each method that can be called with decoded arguments
+    pub fn gen_fuzz_targets(&self) {
+        self.files
+            .add_file(format!("{FUZZ_DIR}/diplomat_fuzz.h"), FUZZ_H.into());
+        for (id, ty) in self.tcx.all_types() {
+            if ty.attrs().disable {
+                continue;
+            }
+            for method in ty.methods() {
+                // Methods limited to some platforms would fail to build the target elsewhere
+                if method.attrs.disable || method.is_async || !method.attrs.platforms.is_empty() {
+                    continue;
+                }
+                let method_name = self.formatter.fmt_method_name(id, method);
+                if let Some(target) = FuzzTarget::new(self).gen(id, method, &method_name) {
+                    self.files
+                        .add_file(format!("{FUZZ_DIR}/{method_name}.c"), target);
+                }
+            }
+        }
+    }
+}
+
+/// The fuzz target of a method, as it is being generated
+struct FuzzTarget<'ccx, 'tcx> {
+    cx: &'ccx CContext<'tcx>,
+    /// The headers of the types the target uses
+    includes: BTreeSet<String>,
+    /// The variants of the enums the target decodes, by enum
+    enums: BTreeMap<String, Vec<String>>,
+    /// The variables of the target, initialized so that the cleanup can run wherever
+    /// decoding stops
+    decls: Vec<String>,
+    /// The statements decoding the arguments and calling the method
+    body: Vec<String>,
+    /// The statements releasing the opaques made for the arguments
+    cleanup: Vec<String>,
+    indent: usize,
+}
+
+impl<'ccx, 'tcx> FuzzTarget<'ccx, 'tcx> {
+    fn new(cx: &'ccx CContext<'tcx>) -> Self {
+        FuzzTarget {
+            cx,
+            includes: BTreeSet::new(),
+            enums: BTreeMap::new(),
+            decls: Vec::new(),
+            body: Vec::new(),
+            cleanup: Vec::new(),
+            indent: 1,
+        }
+    }
+
+    /// Generates the target, or `None` if the method cannot be fuzzed
+    fn gen(mut self, id: TypeId, method: &'tcx hir::Method, method_name: &str) -> Option<String> {
+        self.includes
+            .insert(self.cx.formatter.fmt_impl_header_path(id));
+        let mut args = Vec::new();
+        // The opaques handed over to Rust by the call, which the cleanup must not destroy
+        let mut consumed = Vec::new();
+
+        if let Some(ref param_self) = method.param_self {
+            match param_self.ty {
+                SelfType::Opaque(ref op) => {
+                    self.construct(op.tcx_id, "self")?;
+                }
+                SelfType::BoxedOpaque(ref op) => {
+                    self.construct(op.tcx_id, "self")?;
+                    consumed.push("self".to_string());
+                }
+                SelfType::Struct(ref st) => {
+                    self.declare(&self.cx.formatter.fmt_type_name(st.id()), "self", "{0}");
+                    self.decode_struct(st.id(), "self")?;
+                }
+                SelfType::Enum(ref e) => {
+                    self.declare(
+                        &self.cx.formatter.fmt_type_name(e.tcx_id.into()),
+                        "self",
+                        "0",
+                    );
+                    self.decode_enum(e.tcx_id.into(), "self");
+                }
+                _ => return None,
+            }
+            args.push("self".to_string());
+        }
+
+        for param in &method.params {
+            let name = self.cx.formatter.fmt_param_name(param.name.as_str());
+            match param.ty {
+                Type::Opaque(ref op) => {
+                    if op.is_optional() {
+                        let some = format!("{name}_some");
+                        self.declare("bool", &some, "false");
+                        self.push(format!("if (!diplomat_fuzz_bool(&in, &{some})) goto done;"));
+                        self.push(format!("if ({some}) {{"));
+                        self.indent += 1;
+                        self.construct(op.tcx_id, &name)?;
+                        self.indent -= 1;
+                        self.push("}".into());
+                    } else {
+                        self.construct(op.tcx_id, &name)?;
+                    }
+                    if op.owner.is_owned() {
+                        consumed.push(name.to_string());
+                    }
+                    args.push(name.into_owned());
+                }
+                _ => args.extend(self.decode_param(&param.ty, &name, param.encoding)?),
+            }
+        }
+
+        let call = |args: &[String]| format!("{method_name}({})", args.join(", "));
+        match method.output {
+            ReturnType::Infallible(SuccessType::Unit) => self.push(format!("{};", call(&args))),
+            ReturnType::Infallible(SuccessType::Writeable) => {
+                self.declare_writeable(&mut args);
+                self.push(format!("{};", call(&args)));
+            }
+            ReturnType::Infallible(SuccessType::OutType(ref o)) => {
+                match self.release(o, "result", "")? {
+                    Some((ty, release)) => {
+                        self.push(format!("{ty} result = {};", call(&args)));
+                        self.push(release);
+                    }
+                    None => self.push(format!("(void){};", call(&args))),
+                }
+            }
+            ReturnType::Fallible(ref ok, _) | ReturnType::Nullable(ref ok) => {
+                let err = match method.output {
+                    ReturnType::Fallible(_, Some(ref e)) => Some(e),
+                    _ => None,
+                };
+                let ok = match ok {
+                    SuccessType::Writeable => {
+                        self.declare_writeable(&mut args);
+                        None
+                    }
+                    SuccessType::OutType(o) => Some(o),
+                    _ => None,
+                };
+                let release_ok = match ok {
+                    Some(o) => self.release(o, "result.ok", "result.is_ok && ")?,
+                    None => None,
+                };
+                let release_err = match err {
+                    Some(e) => self.release(e, "result.err", "!result.is_ok && ")?,
+                    None => None,
+                };
+                if release_ok.is_none() && release_err.is_none() {
+                    self.push(format!("(void){};", call(&args)));
+                } else {
+                    let result_name = self.result_name(ok, err);
+                    self.push(format!("{result_name} result = {};", call(&args)));
+                    if let Some((_, release)) = release_ok {
+                        self.push(release);
+                    }
+                    if let Some((_, release)) = release_err {
+                        self.push(release);
+                    }
+                }
+            }
+            _ => return None,
+        }
+        for name in consumed {
+            self.push(format!("{name} = NULL;"));
+        }
+
+        let mut out = format!(
+            "// Fuzzes `{method_name}`, with arguments decoded from the input. Build with\n\
+             // `-fsanitize=fuzzer,address` and link with the Rust library.\n\
+             #include \"diplomat_fuzz.h\"\n"
+        );
+        for include in &self.includes {
+            writeln!(out, "#include \"../{include}\"").unwrap();
+        }
+        for (ty_name, variants) in &self.enums {
+            write!(
+                out,
+                "\nstatic const {ty_name} {ty_name}_variants[] = {{{}}};\n",
+                variants.join(", ")
+            )
+            .unwrap();
+        }
+        out.push_str(
+            "\nint LLVMFuzzerTestOneInput(const uint8_t* data, size_t size) {\n\
+             \tDiplomatFuzzInput in = diplomat_fuzz_begin(data, size);\n",
+        );
+        for line in self.decls.iter().chain(&self.body) {
+            writeln!(out, "{line}").unwrap();
+        }
+        out.push_str("done:\n");
+        for line in self.cleanup.iter().rev() {
+            writeln!(out, "{line}").unwrap();
+        }
+        out.push_str("\tdiplomat_fuzz_end(&in);\n\treturn 0;\n}\n");
+        Some(out)
+    }
+
+    fn push(&mut self, line: String) {
+        self.body
+            .push(format!("{}{line}", "\t".repeat(self.indent)));
+    }
+
+    fn declare(&mut self, ty: &str, name: &str, init: &str) {
+        self.decls.push(format!("\t{ty} {name} = {init};"));
+    }
+
+    fn declare_writeable(&mut self, args: &mut Vec<String>) {
+        self.decls.push("\tchar writeable_buf[64];".into());
+        self.decls.push(
+            "\tDiplomatWriteable writeable = diplomat_simple_writeable(writeable_buf, sizeof(writeable_buf));"
+                .into(),
+        );
+        args.push("&writeable".into());
+    }
+
+    /// Makes a new opaque in `var` with the first constructor of its type taking arguments
+    /// that can be decoded, destroying it in the cleanup
+    fn construct(&mut self, id: OpaqueId, var: &str) -> Option<()> {
+        let ty_id = id.into();
+        let opaque = self.cx.tcx.resolve_opaque(id);
+        let ty_name = self.cx.formatter.fmt_type_name(ty_id);
+        let constructor = opaque.methods.iter().find(|m| {
+            !m.attrs.disable
+                && !m.is_async
+                && m.attrs.platforms.is_empty()
+                && m.param_self.is_none()
+                && m.params.iter().all(|p| Self::can_decode(self.cx, &p.ty))
+                && Self::constructed(&m.output) == Some(id)
+        })?;
+
+        self.includes
+            .insert(self.cx.formatter.fmt_impl_header_path(ty_id));
+        self.declare(&format!("{ty_name}*"), var, "NULL");
+        let mut args = Vec::new();
+        for param in &constructor.params {
+            let name = format!(
+                "{var}_{}",
+                self.cx.formatter.fmt_param_name(param.name.as_str())
+            );
+            args.extend(self.decode_param(&param.ty, &name, param.encoding)?);
+        }
+        let call = format!(
+            "{}({})",
+            self.cx.formatter.fmt_method_name(ty_id, constructor),
+            args.join(", ")
+        );
+        if let ReturnType::Infallible(_) = constructor.output {
+            self.push(format!("{var} = {call};"));
+        } else {
+            let ok = match constructor.output {
+                ReturnType::Fallible(SuccessType::OutType(ref o), _)
+                | ReturnType::Nullable(SuccessType::OutType(ref o)) => Some(o),
+                _ => None,
+            };
+            let err = match constructor.output {
+                ReturnType::Fallible(_, Some(ref e)) => Some(e),
+                _ => None,
+            };
+            let result_name = self.result_name(ok, err);
+            self.push(format!("{result_name} {var}_result = {call};"));
+            self.push(format!("if (!{var}_result.is_ok) goto done;"));
+            self.push(format!("{var} = {var}_result.ok;"));
+        }
+        self.push(format!("if ({var} == NULL) goto done;"));
+        self.cleanup.push(format!(
+            "\tif ({var} != NULL) {}({var});",
+            self.cx.formatter.fmt_dtor_name(ty_id)
+        ));
+        Some(())
+    }
+
+    /// The opaque a method returns a new one of, if it is a constructor whose errors need no
+    /// releasing
+    fn constructed(output: &ReturnType) -> Option<OpaqueId> {
+        let (ok, err) = match output {
+            ReturnType::Infallible(SuccessType::OutType(o)) => (o, None),
+            ReturnType::Fallible(SuccessType::OutType(o), err) => (o, err.as_ref()),
+            ReturnType::Nullable(SuccessType::OutType(o)) => (o, None),
+            _ => return None,
+        };
+        match (ok, err) {
+            (Type::Opaque(op), None | Some(Type::Primitive(_) | Type::Enum(_)))
+                if op.owner.is_owned() =>
+            {
+                Some(op.tcx_id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a value of the type can be decoded from the input, without making opaques
+    fn can_decode(cx: &CContext, ty: &Type) -> bool {
+        match ty {
+            Type::Primitive(hir::PrimitiveType::Int128(_)) => false,
+            Type::Primitive(_) | Type::Enum(_) => true,
+            Type::Struct(st) => match cx.tcx.resolve_type(st.id()) {
+                TypeDef::Struct(def) => def.fields.iter().all(|f| Self::can_decode(cx, &f.ty)),
+                _ => false,
+            },
+            Type::Slice(hir::Slice::Str(lifetime, _)) => lifetime.is_some(),
+            Type::Slice(hir::Slice::Primitive(borrow, p)) => {
+                borrow.is_some() && !matches!(p, hir::PrimitiveType::Int128(_))
+            }
+            _ => false,
+        }
+    }
+
+    /// Declares the variables of a parameter and decodes them, returning the arguments they
+    /// are passed as
+    fn decode_param(
+        &mut self,
+        ty: &Type,
+        name: &str,
+        encoding: Option<RequiredEncoding>,
+    ) -> Option<Vec<String>> {
+        if !Self::can_decode(self.cx, ty) {
+            return None;
+        }
+        let formatter = &self.cx.formatter;
+        match *ty {
+            Type::Primitive(p) => {
+                self.declare(&formatter.fmt_primitive_as_c(p), name, "0");
+                self.decode_primitive(p, name);
+            }
+            Type::Enum(ref e) => {
+                self.declare(&formatter.fmt_type_name(e.tcx_id.into()), name, "0");
+                self.decode_enum(e.tcx_id.into(), name);
+            }
+            Type::Struct(ref st) => {
+                self.declare(&formatter.fmt_type_name(st.id()), name, "{0}");
+                self.decode_struct(st.id(), name)?;
+            }
+            Type::Slice(slice) => {
+                let data = format!("{name}_data");
+                let len = format!("{name}_len");
+                let data_ty = match slice {
+                    hir::Slice::Str(_, hir::StringEncoding::UnvalidatedUtf16) => {
+                        "const char16_t*".into()
+                    }
+                    hir::Slice::Str(..) => "const char*".into(),
+                    hir::Slice::Primitive(borrow, p) => formatter
+                        .fmt_ptr(
+                            &formatter.fmt_primitive_as_c(p),
+                            borrow.map_or(hir::Mutability::Mutable, |b| b.mutability),
+                        )
+                        .into_owned(),
+                    _ => return None,
+                };
+                self.declare(&data_ty, &data, "NULL");
+                self.declare("size_t", &len, "0");
+                self.decode_slice(slice, &data, &len, encoding);
+                return Some(vec![data, len]);
+            }
+            _ => return None,
+        }
+        Some(vec![name.into()])
+    }
+
+    fn decode_primitive(&mut self, p: hir::PrimitiveType, lvalue: &str) {
+        if let hir::PrimitiveType::Bool = p {
+            self.push(format!(
+                "if (!diplomat_fuzz_bool(&in, &{lvalue})) goto done;"
+            ));
+        } else {
+            self.push(format!(
+                "if (!diplomat_fuzz_read(&in, &{lvalue}, sizeof({lvalue}))) goto done;"
+            ));
+        }
+    }
+
+    /// Decodes one of the variants of an enum, as Rust only accepts those
+    fn decode_enum(&mut self, id: TypeId, lvalue: &str) {
+        let TypeDef::Enum(def) = self.cx.tcx.resolve_type(id) else {
+            unreachable!("enum path to a type that is not an enum")
+        };
+        let ty_name = self.cx.formatter.fmt_type_name(id).into_owned();
+        self.includes
+            .insert(self.cx.formatter.fmt_decl_header_path(id));
+        let variants = def
+            .variants
+            .iter()
+            .map(|v| self.cx.formatter.fmt_enum_variant(&ty_name, v).into_owned())
+            .collect::<Vec<_>>();
+        let count = variants.len();
+        self.enums.insert(ty_name.clone(), variants);
+        let pick = format!("{}_pick", lvalue.replace('.', "_"));
+        self.declare("size_t", &pick, "0");
+        self.push(format!(
+            "if (!diplomat_fuzz_pick(&in, {count}, &{pick})) goto done;"
+        ));
+        self.push(format!("{lvalue} = {ty_name}_variants[{pick}];"));
+    }
+
+    fn decode_struct(&mut self, id: TypeId, lvalue: &str) -> Option<()> {
+        let TypeDef::Struct(def) = self.cx.tcx.resolve_type(id) else {
+            return None;
+        };
+        self.includes
+            .insert(self.cx.formatter.fmt_decl_header_path(id));
+        for field in &def.fields {
+            let field_lvalue = format!(
+                "{lvalue}.{}",
+                self.cx.formatter.fmt_param_name(field.name.as_str())
+            );
+            match field.ty {
+                Type::Primitive(p) => self.decode_primitive(p, &field_lvalue),
+                Type::Enum(ref e) => self.decode_enum(e.tcx_id.into(), &field_lvalue),
+                Type::Struct(ref st) => self.decode_struct(st.id(), &field_lvalue)?,
+                Type::Slice(slice) => self.decode_slice(
+                    slice,
+                    &format!("{field_lvalue}.data"),
+                    &format!("{field_lvalue}.len"),
+                    None,
+                ),
+                _ => return None,
+            }
+        }
+        Some(())
+    }
+
+    /// Decodes a slice, rejecting strings that aren't in the encoding Rust requires
+    fn decode_slice(
+        &mut self,
+        slice: hir::Slice,
+        data: &str,
+        len: &str,
+        encoding: Option<RequiredEncoding>,
+    ) {
+        self.push(format!(
+            "{data} = diplomat_fuzz_slice(&in, sizeof(*{data}), &{len});"
+        ));
+        self.push(format!("if ({data} == NULL) goto done;"));
+        let check = match (slice, encoding) {
+            (_, Some(RequiredEncoding::Ascii)) => Some("diplomat_fuzz_is_ascii"),
+            (_, Some(RequiredEncoding::Utf16)) => Some("diplomat_fuzz_is_utf16"),
+            (hir::Slice::Str(_, hir::StringEncoding::Utf8), _)
+            | (_, Some(RequiredEncoding::Utf8)) => Some("diplomat_fuzz_is_utf8"),
+            (hir::Slice::Primitive(_, hir::PrimitiveType::Bool), _) => {
+                self.push(format!("diplomat_fuzz_fix_bools((bool*){data}, {len});"));
+                None
+            }
+            _ => None,
+        };
+        if let Some(check) = check {
+            self.push(format!("if (!{check}({data}, {len})) goto done;"));
+        }
+    }
+
+    /// The C type of a returned value and the statement releasing it as `expr` when `guard`
+    /// holds, or `Some(None)` if nothing needs releasing. `None` if it can't be released.
+    fn release(
+        &mut self,
+        ty: &hir::OutType,
+        expr: &str,
+        guard: &str,
+    ) -> Option<Option<(String, String)>> {
+        match ty {
+            Type::Opaque(op) if op.owner.is_owned() => {
+                let id = op.tcx_id.into();
+                self.includes
+                    .insert(self.cx.formatter.fmt_impl_header_path(id));
+                Some(Some((
+                    format!("{}*", self.cx.formatter.fmt_type_name(id)),
+                    format!(
+                        "if ({guard}{expr} != NULL) {}({expr});",
+                        self.cx.formatter.fmt_dtor_name(id)
+                    ),
+                )))
+            }
+            _ if Self::owns_memory(self.cx, ty) => None,
+            _ => Some(None),
+        }
+    }
+
+    /// Whether a value of the type owns memory that would have to be released
+    fn owns_memory<P: TyPosition>(cx: &CContext, ty: &Type<P>) -> bool {
+        match ty {
+            Type::Opaque(op) => op.owner.is_owned(),
+            Type::Slice(hir::Slice::Str(lifetime, _)) => lifetime.is_none(),
+            Type::Slice(hir::Slice::Primitive(borrow, _)) => borrow.is_none(),
+            Type::Struct(st) => match cx.tcx.resolve_type(st.id()) {
+                TypeDef::Struct(def) => def.fields.iter().any(|f| Self::owns_memory(cx, &f.ty)),
+                TypeDef::OutStruct(def) => def.fields.iter().any(|f| Self::owns_memory(cx, &f.ty)),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The name of the C struct a method returning `Result<ok, err>` or `Option<ok>` uses
+    fn result_name(
+        &self,
+        ok: Option<&'tcx hir::OutType>,
+        err: Option<&'tcx hir::OutType>,
+    ) -> String {
+        let formatter = &self.cx.formatter;
+        let ok = ok.map_or("void".into(), |o| formatter.fmt_type_name_uniquely(o));
+        let err = err.map_or("void".into(), |e| formatter.fmt_type_name_uniquely(e));
+        formatter.fmt_result_name(&ok, &err)
+    }
+}
@@ -1,13 +1,65 @@
 mod formatter;
+mod fuzz;
 mod header;
 mod ty;
 
-pub use self::formatter::CFormatter;
+pub use self::formatter::{CFormatter, CPP_RESERVED_WORDS};
 
-use crate::common::{ErrorStore, FileMap};
-use diplomat_core::hir::TypeContext;
+use crate::common::{ErrorStore, FileMap, NameRegistry};
+use diplomat_core::hir::{ReturnType, Type, TypeContext, TypeId};
+use serde::Deserialize;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Write};
+use std::path::Path;
+
+/// The header aliasing the `capi` namespace, see [`CConfig::cpp_namespace`]
+const NAMESPACE_HEADER: &str = "diplomat_capi.hpp";
+
+/// The header with the stable IDs of the types and functions, see [`CConfig::stable_id_table`]
+const STABLE_IDS_HEADER: &str = "diplomat_stable_ids.h";
+
+/// Identifiers declared by `diplomat_runtime.h`, which every generated header includes
+const RUNTIME_NAMES: &[&str] = &[
+    "DiplomatWriteable",
+    "diplomat_simple_writeable",
+    "diplomat_leak_report",
+    "DiplomatBatch",
+    "diplomat_batch_begin",
+    "diplomat_batch_alloc",
+    "diplomat_batch_end",
+    "diplomat_alloc",
+    "diplomat_free",
+    "DiplomatWaker",
+    "DiplomatFuture",
+];
+
+/// The options of the C backend, read from the library config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CConfig {
+    /// A C++ namespace such as `icu4x::capi`. The headers declare everything in the `capi`
+    /// namespace when included from C++, and with this set a `diplomat_capi.hpp` header
+    /// including all of them aliases it, for C++ code bases using the C API directly.
+    pub cpp_namespace: Option<String>,
+    /// Whether to generate a libFuzzer target in `fuzz/` for each method, calling it with
+    /// arguments decoded from the fuzzer's input. Opaque arguments are made with a static
+    /// method of their type returning a new one, and methods taking arguments that can't be
+    /// made this way are left out.
+    pub fuzz_targets: bool,
+    /// Whether to generate a `diplomat_stable_ids.h` header with the stable ID of each type
+    /// and function, as a `DIPLOMAT_ID_` macro and in a table mapping IDs to names, for
+    /// dispatching calls by number, such as over RPC.
+    pub stable_id_table: bool,
+}
+
+/// Reads the options of the C backend from a library config
+pub fn read_config(conf_path: &Path) -> CConfig {
+    let conf_str = std::fs::read_to_string(conf_path)
+        .unwrap_or_else(|err| panic!("Failed to open config file {conf_path:?}: {err}"));
+    toml::from_str(&conf_str)
+        .unwrap_or_else(|err| panic!("Failed to parse config file {conf_path:?}: {err}"))
+}
 
 /// This is the main object that drives this backend. Most execution steps
 /// for this backend will be found as methods on this context
@@ -17,21 +69,55 @@ pub struct CContext<'tcx> {
     pub files: FileMap,
     // The results needed by various methods
     pub result_store: RefCell<HashMap<String, ty::ResultType<'tcx>>>,
+    // The options taken or held by various methods and structs, with the C type of their
+    // value and the type declaring it
+    pub option_store: RefCell<HashMap<String, (String, Option<TypeId>)>>,
+    /// Enums used as the error of a fallible method, which get a message lookup function
+    pub error_enums: BTreeSet<TypeId>,
+    pub config: CConfig,
+    /// Every identifier declared in the C namespace, which all headers share
+    pub names: NameRegistry,
 
     pub errors: ErrorStore<'tcx, String>,
 }
 
 impl<'tcx> CContext<'tcx> {
     pub fn new(tcx: &'tcx TypeContext, files: FileMap) -> Self {
+        let mut error_enums = BTreeSet::new();
+        for (_, ty) in tcx.all_types() {
+            for method in ty.methods() {
+                if let ReturnType::Fallible(_, Some(Type::Enum(ref e))) = method.output {
+                    error_enums.insert(e.tcx_id.into());
+                }
+            }
+        }
+
+        let names = NameRegistry::default();
+        for name in RUNTIME_NAMES {
+            // The runtime names are distinct, this cannot fail
+            let _ = names.reserve(name, "the Diplomat runtime");
+        }
+
         CContext {
             tcx,
             files,
             formatter: CFormatter::new(tcx),
             result_store: Default::default(),
+            option_store: Default::default(),
+            error_enums,
+            config: CConfig::default(),
+            names,
             errors: ErrorStore::default(),
         }
     }
 
+    /// Reserves `name` in the C namespace for `owner`, reporting a collision as an error
+    pub fn reserve_name(&self, name: &str, owner: impl Display) {
+        if let Err(e) = self.names.reserve(name, owner) {
+            self.errors.push_error(e);
+        }
+    }
+
     /// Run file generation
     ///
     /// Will populate self.files as a result
@@ -39,13 +125,384 @@ impl<'tcx> CContext<'tcx> {
         self.files
             .add_file("diplomat_runtime.h".into(), crate::c::RUNTIME_H.into());
         for (id, ty) in self.tcx.all_types() {
-            self.gen_ty(id, ty)
+            self.files.gen_item(&self.errors, || self.gen_ty(id, ty))
+        }
+        for (id, trt) in self.tcx.all_traits() {
+            self.files
+                .gen_item(&self.errors, || self.gen_trait(id, trt))
         }
 
         for (result_name, result_ty) in self.result_store.borrow().iter() {
             self.gen_result(result_name, *result_ty)
         }
+        for (option_name, (value_ty, value_id)) in self.option_store.borrow().iter() {
+            self.gen_option(option_name, value_ty, *value_id)
+        }
+
+        if let Some(ref namespace) = self.config.cpp_namespace {
+            self.gen_namespace_header(namespace);
+        }
+        if self.config.fuzz_targets {
+            self.gen_fuzz_targets();
+        }
+        if self.config.stable_id_table {
+            self.gen_stable_ids_header();
+        }
+    }
+
+    /// Generates the header with the stable ID of every generated type and function, which
+    /// lowering assigns to all of them
+    fn gen_stable_ids_header(&self) {
+        let mut items = Vec::new();
+        for (id, ty) in self.tcx.all_types() {
+            if ty.attrs().disable {
+                continue;
+            }
+            items.push((self.formatter.fmt_type_name(id).into_owned(), ty.attrs()));
+            for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+                items.push((self.formatter.fmt_method_name(id, method), &method.attrs));
+            }
+        }
+
+        let mut macros = String::new();
+        let mut table = String::new();
+        for (name, attrs) in items {
+            let stable_id = attrs
+                .stable_id
+                .expect("stable IDs are assigned by lowering");
+            writeln!(macros, "#define DIPLOMAT_ID_{name} {stable_id}u").unwrap();
+            writeln!(table, "  {{ {stable_id}u, \"{name}\" }},").unwrap();
+        }
+
+        self.files.add_file(
+            STABLE_IDS_HEADER.into(),
+            format!(
+                r#"#ifndef DIPLOMAT_STABLE_IDS_H
+#define DIPLOMAT_STABLE_IDS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+{macros}
+typedef struct DiplomatStableId {{
+  uint32_t id;
+  const char* name;
+}} DiplomatStableId;
+
+static const DiplomatStableId diplomat_stable_ids[] = {{
+{table}}};
+
+static const size_t diplomat_stable_ids_len =
+  sizeof(diplomat_stable_ids) / sizeof(diplomat_stable_ids[0]);
+
+#endif // DIPLOMAT_STABLE_IDS_H
+"#
+            ),
+        );
+    }
+
+    /// Generates the C++ header including every type's header and aliasing `capi` as
+    /// `namespace`
+    fn gen_namespace_header(&self, namespace: &str) {
+        let segments = namespace.split("::").collect::<Vec<_>>();
+        let is_identifier = |s: &str| {
+            s.chars().next().map_or(false, |c| !c.is_ascii_digit())
+                && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !CPP_RESERVED_WORDS.contains(&s)
+        };
+        if !segments.iter().all(|s| is_identifier(s)) || namespace == "capi" {
+            self.errors.push_error(format!(
+                "`cpp-namespace` must be a C++ namespace other than `capi`, found `{namespace}`"
+            ));
+            return;
+        }
+
+        let mut includes = String::new();
+        for (id, ty) in self.tcx.all_types() {
+            if !ty.attrs().disable {
+                let header = self.formatter.fmt_impl_header_path(id);
+                writeln!(includes, "#include \"{header}\"").unwrap();
+            }
+        }
+        for (id, trt) in self.tcx.all_traits() {
+            if !trt.attrs.disable {
+                let header = self.formatter.fmt_trait_header_path(id);
+                writeln!(includes, "#include \"{header}\"").unwrap();
+            }
+        }
+
+        let (alias, parents) = segments.split_last().unwrap();
+        let mut alias_decl = String::new();
+        for parent in parents {
+            writeln!(alias_decl, "namespace {parent} {{").unwrap();
+        }
+        writeln!(alias_decl, "namespace {alias} = ::capi;").unwrap();
+        for parent in parents.iter().rev() {
+            writeln!(alias_decl, "}} // namespace {parent}").unwrap();
+        }
+
+        self.files.add_file(
+            NAMESPACE_HEADER.into(),
+            format!(
+                r#"#ifndef DIPLOMAT_CAPI_HPP
+#define DIPLOMAT_CAPI_HPP
+
+#ifndef __cplusplus
+#error "{NAMESPACE_HEADER} is only for C++, C code includes the headers it includes directly"
+#endif // __cplusplus
+
+{includes}
+{alias_decl}
+#endif // DIPLOMAT_CAPI_HPP
+"#
+            ),
+        );
     }
 
     // further methods can be found in ty.rs and formatter.rs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CConfig, CContext};
+    use crate::common::FileMap;
+    use diplomat_core::{ast, hir};
+    use quote::quote;
+
+    fn gen_files(cpp_namespace: &str) -> (FileMap, Vec<String>) {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Locale;
+
+                pub struct Options {
+                    strict: bool,
+                }
+
+                impl Locale {
+                    pub fn options(&self) -> Options {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let mut validator = hir::BasicAttributeValidator::new("c");
+        validator.other_backend_names.push("c".into());
+        let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+        let mut context = CContext::new(&tcx, FileMap::default());
+        context.config = CConfig {
+            cpp_namespace: Some(cpp_namespace.into()),
+            ..Default::default()
+        };
+        context.run();
+        let errors = context
+            .errors
+            .take_all()
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect();
+        (context.files, errors)
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Foo;
+
+                impl Foo {
+                    pub fn destroy(&self) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let mut validator = hir::BasicAttributeValidator::new("c");
+        validator.other_backend_names.push("c".into());
+        let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+        let context = CContext::new(&tcx, FileMap::default());
+        context.run();
+        let errors = context
+            .errors
+            .take_all()
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "Identifier `Foo_destroy` generated for the destructor of `Foo` collides with \
+              method `destroy`, please rename one of them"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cpp_namespace() {
+        let (mut files, errors) = gen_files("icu4x::capi");
+        assert!(errors.is_empty());
+        insta::assert_snapshot!(files.take_files().remove("diplomat_capi.hpp").unwrap());
+
+        let (_, errors) = gen_files("icu4x::class");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_fuzz_targets() {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Parser;
+
+                #[diplomat::opaque]
+                struct Document;
+
+                pub enum Mode {
+                    Strict,
+                    Lenient,
+                }
+
+                pub struct Options {
+                    mode: Mode,
+                    depth: u8,
+                }
+
+                impl Parser {
+                    pub fn new(options: Options) -> Box<Parser> {
+                        unimplemented!()
+                    }
+
+                    pub fn parse(&self, text: &str, ids: &[u32]) -> Result<Box<Document>, ()> {
+                        unimplemented!()
+                    }
+
+                    pub fn name(&self, write: &mut DiplomatWriteable) {
+                        unimplemented!()
+                    }
+                }
+
+                impl Document {
+                    pub fn merge(&mut self, other: &Document) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let mut validator = hir::BasicAttributeValidator::new("c");
+        validator.other_backend_names.push("c".into());
+        let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+        let mut context = CContext::new(&tcx, FileMap::default());
+        context.config = CConfig {
+            fuzz_targets: true,
+            ..Default::default()
+        };
+        context.run();
+        assert!(context.errors.take_all().is_empty());
+        let mut files = context.files.take_files();
+        assert!(files.contains_key("fuzz/diplomat_fuzz.h"));
+        assert!(files.contains_key("fuzz/Parser_name.c"));
+        // Documents can only be made by parsing
+        assert!(!files.contains_key("fuzz/Document_merge.c"));
+        insta::assert_snapshot!(files.remove("fuzz/Parser_parse.c").unwrap());
+    }
+
+    #[test]
+    fn test_stable_id_table() {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(c, stable_id = 1)]
+                struct Session;
+
+                impl Session {
+                    #[diplomat::attr(c, stable_id = 2)]
+                    pub fn new() -> Box<Session> {
+                        unimplemented!()
+                    }
+
+                    pub fn send(&self, payload: &[u8]) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let mut validator = hir::BasicAttributeValidator::new("c");
+        validator.other_backend_names.push("c".into());
+        let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+        let mut context = CContext::new(&tcx, FileMap::default());
+        context.config = CConfig {
+            stable_id_table: true,
+            ..Default::default()
+        };
+        context.run();
+        assert!(context.errors.take_all().is_empty());
+        insta::assert_snapshot!(context
+            .files
+            .take_files()
+            .remove("diplomat_stable_ids.h")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_platforms() {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Storage;
+
+                impl Storage {
+                    #[diplomat::attr(c, platforms(linux, macos, windows))]
+                    pub fn open(path: &str) -> Box<Storage> {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(c, platforms(wasm))]
+                    pub fn open_in_memory() -> Box<Storage> {
+                        unimplemented!()
+                    }
+
+                    pub fn len(&self) -> usize {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let gen = |target: Option<&str>| {
+            let mut validator = hir::BasicAttributeValidator::new("c2");
+            validator.other_backend_names.push("c".into());
+            validator.support.disabling = true;
+            validator.support.platforms = true;
+            validator.target = target.map(Into::into);
+            let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+            let mut context = CContext::new(&tcx, FileMap::default());
+            context.run();
+            assert!(context.errors.take_all().is_empty());
+            context.files.take_files().remove("Storage.h").unwrap()
+        };
+
+        let header = gen(None);
+        assert!(header.contains(
+            "#if (defined(__linux__) && !defined(__ANDROID__)) || \
+             defined(__ENVIRONMENT_MAC_OS_X_VERSION_MIN_REQUIRED__) || defined(_WIN32)\n\
+             Storage* Storage_open(const char* path_data, size_t path_len);\n#endif\n\n"
+        ));
+        assert!(header
+            .contains("#if defined(__wasm__)\nStorage* Storage_open_in_memory();\n#endif\n\n"));
+
+        // Generating for a single target leaves out what it doesn't have
+        let header = gen(Some("wasm32-unknown-unknown"));
+        assert!(!header.contains("Storage_open("));
+        assert!(header.contains("Storage* Storage_open_in_memory();"));
+    }
+}
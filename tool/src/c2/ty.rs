@@ -1,6 +1,8 @@
 use super::header::Header;
 use super::CContext;
-use diplomat_core::hir::{self, OpaqueOwner, StructPathLike, TyPosition, Type, TypeDef, TypeId};
+use diplomat_core::hir::{
+    self, OpaqueOwner, StructPathLike, TraitId, TyPosition, Type, TypeDef, TypeId,
+};
 use std::borrow::Cow;
 use std::fmt::Write;
 
@@ -21,7 +23,14 @@ impl<'tcx> super::CContext<'tcx> {
             impl_header: &mut impl_header,
         };
 
-        let _guard = self.errors.set_context_ty(ty.name().as_str().into());
+        let _guard = self
+            .errors
+            .set_context_ty(ty.name().as_str().into())
+            .with_span(ty.span());
+        self.reserve_name(
+            &self.formatter.fmt_type_name(id),
+            format_args!("type `{}`", ty.name()),
+        );
         match ty {
             TypeDef::Enum(e) => context.gen_enum_def(e, id),
             TypeDef::Opaque(o) => context.gen_opaque_def(o, id),
@@ -34,10 +43,13 @@ impl<'tcx> super::CContext<'tcx> {
                 // Skip method if disabled
                 continue;
             }
-            let _guard = self.errors.set_context_method(
-                self.formatter.fmt_type_name_diagnostics(id),
-                method.name.as_str().into(),
-            );
+            let _guard = self
+                .errors
+                .set_context_method(
+                    self.formatter.fmt_type_name_diagnostics(id),
+                    method.name.as_str().into(),
+                )
+                .with_span(method.span);
             context.gen_method(id, method);
         }
 
@@ -61,10 +73,51 @@ impl<'tcx> super::CContext<'tcx> {
             .add_file(impl_header_path, impl_header.to_string());
     }
 
+    pub fn gen_trait(&self, id: TraitId, trt: &'tcx hir::TraitDef) {
+        if trt.attrs.disable {
+            return;
+        }
+        let header_path = self.formatter.fmt_trait_header_path(id);
+        let mut header = Header::new(header_path.clone());
+        let vtable_name = self.formatter.fmt_trait_vtable_name(id);
+        let _guard = self.errors.set_context_ty(trt.name.as_str().into());
+        self.reserve_name(&vtable_name, format_args!("trait `{}`", trt.name));
+        writeln!(header, "typedef struct {vtable_name} {{").unwrap();
+        writeln!(header, "\tconst void* data;").unwrap();
+        for method in &trt.methods {
+            let field_name = format!("run_{}_callback", method.name.as_str());
+            let field = self
+                .formatter
+                .fmt_callback_fn_ptr(&field_name, &method.signature);
+            writeln!(header, "\t{field};").unwrap();
+        }
+        writeln!(header, "\tvoid (*destructor)(const void*);").unwrap();
+        write!(header, "}} {vtable_name};\n\n").unwrap();
+        self.files.add_file(header_path, header.to_string());
+    }
+
+    pub fn gen_option(&self, name: &str, value_ty: &str, value_id: Option<TypeId>) {
+        self.reserve_name(name, "an option type");
+        let header_path = self.formatter.fmt_result_header_path(name);
+        let mut header = Header::new(header_path.clone());
+        if let Some(id) = value_id {
+            header
+                .includes
+                .insert(self.formatter.fmt_decl_header_path(id));
+        }
+        writeln!(
+            header,
+            "typedef struct {name} {{\n\tunion {{\n\t\t{value_ty} ok;\n\t}};\n\tbool is_ok;\n}} {name};"
+        )
+        .unwrap();
+        self.files.add_file(header_path, header.to_string());
+    }
+
     pub fn gen_result(&self, name: &str, ty: ResultType) {
         let _guard = self
             .errors
             .set_context_ty(self.formatter.fmt_result_for_diagnostics(ty).into());
+        self.reserve_name(name, "a result type");
         let header_path = self.formatter.fmt_result_header_path(name);
         let mut header = Header::new(header_path.clone());
         let mut dummy_header = Header::new("".to_string());
@@ -94,10 +147,49 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
         writeln!(self.decl_header, "typedef enum {ty_name} {{").unwrap();
         for variant in def.variants.iter() {
             let enum_variant = self.cx.formatter.fmt_enum_variant(&ty_name, variant);
+            self.cx.reserve_name(
+                &enum_variant,
+                format_args!("variant `{}` of enum `{}`", variant.name, def.name),
+            );
             let discriminant = variant.discriminant;
             writeln!(self.decl_header, "\t{enum_variant} = {discriminant},").unwrap();
         }
         write!(self.decl_header, "}} {ty_name};\n\n").unwrap();
+
+        if self.cx.error_enums.contains(&id) {
+            self.gen_error_messages(def, &ty_name);
+        }
+    }
+
+    /// Error enums get a table of static messages, so that C code can report errors
+    /// without calling into Rust
+    fn gen_error_messages(&mut self, def: &'tcx hir::EnumDef, ty_name: &str) {
+        let fn_name = self.cx.formatter.fmt_error_message_fn_name(ty_name);
+        self.cx.reserve_name(
+            &fn_name,
+            format_args!("the error messages of enum `{}`", def.name),
+        );
+        writeln!(
+            self.decl_header,
+            "static inline const char* {fn_name}({ty_name} code) {{"
+        )
+        .unwrap();
+        writeln!(self.decl_header, "\tswitch (code) {{").unwrap();
+        for variant in def.variants.iter() {
+            let enum_variant = self.cx.formatter.fmt_enum_variant(ty_name, variant);
+            let message = self.cx.formatter.fmt_error_message(variant);
+            writeln!(
+                self.decl_header,
+                "\t\tcase {enum_variant}: return \"{message}\";"
+            )
+            .unwrap();
+        }
+        writeln!(
+            self.decl_header,
+            "\t\tdefault: return \"Unknown {ty_name}\";"
+        )
+        .unwrap();
+        write!(self.decl_header, "\t}}\n}}\n\n").unwrap();
     }
 
     pub fn gen_opaque_def(&mut self, _def: &'tcx hir::OpaqueDef, id: TypeId) {
@@ -121,13 +213,42 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
     pub fn gen_method(&mut self, id: TypeId, method: &'tcx hir::Method) {
         use diplomat_core::hir::{ReturnType, SuccessType};
         let method_name = self.cx.formatter.fmt_method_name(id, method);
+        self.cx
+            .reserve_name(&method_name, format_args!("method `{}`", method.name));
         let mut param_decls = Vec::new();
         if let Some(ref self_ty) = method.param_self {
-            let self_ty = self_ty.ty.clone().into();
+            // A consumed `self` is owned, so passed as a non-const pointer
+            let self_ty: hir::OutType = self_ty.ty.clone().into();
             param_decls = self.gen_ty_decl(&self_ty, "self", false);
         }
 
         for param in &method.params {
+            if let Type::Callback(ref callback) = param.ty {
+                let callback_name = self
+                    .cx
+                    .formatter
+                    .fmt_callback_name(&method_name, param.name.as_str());
+                self.cx.reserve_name(
+                    &callback_name,
+                    format_args!("the callback `{}` of method `{}`", param.name, method.name),
+                );
+                self.gen_callback_def(&callback_name, callback);
+                param_decls.push((
+                    callback_name.into(),
+                    self.cx.formatter.fmt_param_name(param.name.as_str()),
+                ));
+                continue;
+            }
+            if let Type::DynTrait(trt) = param.ty {
+                self.impl_header
+                    .includes
+                    .insert(self.cx.formatter.fmt_trait_header_path(trt));
+                param_decls.push((
+                    self.cx.formatter.fmt_trait_vtable_name(trt).into(),
+                    self.cx.formatter.fmt_param_name(param.name.as_str()),
+                ));
+                continue;
+            }
             let decls = self.gen_ty_decl(&param.ty, param.name.as_str(), false);
             param_decls.extend(decls);
         }
@@ -146,34 +267,16 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
                 } else {
                     None
                 };
-                let (ok_type_name, ok_ty) = match ok {
+                let ok_ty = match ok {
                     SuccessType::Writeable => {
                         param_decls.push(("DiplomatWriteable*".into(), "writeable".into()));
-                        ("void".into(), None)
-                    }
-                    SuccessType::Unit => ("void".into(), None),
-                    SuccessType::OutType(o) => {
-                        (self.cx.formatter.fmt_type_name_uniquely(o), Some(o))
+                        None
                     }
+                    SuccessType::Unit => None,
+                    SuccessType::OutType(o) => Some(o),
                     _ => unreachable!("unknown AST/HIR variant"),
                 };
-                let err_type_name = match err {
-                    Some(o) => self.cx.formatter.fmt_type_name_uniquely(o),
-                    None => "void".into(),
-                };
-                // todo push to results set
-                let result_name = self
-                    .cx
-                    .formatter
-                    .fmt_result_name(&ok_type_name, &err_type_name);
-                self.impl_header
-                    .includes
-                    .insert(self.cx.formatter.fmt_result_header_path(&result_name));
-                self.cx
-                    .result_store
-                    .borrow_mut()
-                    .insert(result_name.clone(), (ok_ty, err));
-                result_name.into()
+                self.gen_result_name(ok_ty, err).into()
             }
             _ => unreachable!("unknown AST/HIR variant"),
         };
@@ -190,12 +293,115 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
             write!(&mut params, "{comma}{decl_ty} {decl_name}").unwrap();
         }
 
-        write!(self.impl_header, "{return_ty} {method_name}({params});\n\n").unwrap();
+        let decls = if method.is_async {
+            // The method returns a future, which is polled until it returns its output
+            // like an `Option`, and destroyed once done with
+            let ok_ty = match method.output {
+                ReturnType::Infallible(SuccessType::OutType(ref o)) => Some(o),
+                _ => None,
+            };
+            let result_name = self.gen_result_name(ok_ty, None);
+            for name in [
+                format!("{method_name}_poll"),
+                format!("{method_name}_destroy_future"),
+            ] {
+                self.cx.reserve_name(
+                    &name,
+                    format_args!("the future of method `{}`", method.name),
+                );
+            }
+            format!(
+                "DiplomatFuture* {method_name}({params});\n\n\
+                 {result_name} {method_name}_poll(DiplomatFuture* future, DiplomatWaker waker);\n\n\
+                 void {method_name}_destroy_future(DiplomatFuture* future);\n"
+            )
+        } else {
+            format!("{return_ty} {method_name}({params});\n")
+        };
+
+        match self
+            .cx
+            .formatter
+            .fmt_platform_condition(&method.attrs.platforms)
+        {
+            Some(condition) => write!(self.impl_header, "#if {condition}\n{decls}#endif\n\n"),
+            None => writeln!(self.impl_header, "{decls}"),
+        }
+        .unwrap();
+    }
+
+    /// Registers the result struct for a method returning `Result<ok, err>` or `Option<ok>`
+    /// (with no `err`), returning its name
+    fn gen_result_name(
+        &mut self,
+        ok_ty: Option<&'tcx hir::OutType>,
+        err: Option<&'tcx hir::OutType>,
+    ) -> String {
+        let ok_type_name = match ok_ty {
+            Some(o) => self.cx.formatter.fmt_type_name_uniquely(o),
+            None => "void".into(),
+        };
+        let err_type_name = match err {
+            Some(o) => self.cx.formatter.fmt_type_name_uniquely(o),
+            None => "void".into(),
+        };
+        let result_name = self
+            .cx
+            .formatter
+            .fmt_result_name(&ok_type_name, &err_type_name);
+        self.impl_header
+            .includes
+            .insert(self.cx.formatter.fmt_result_header_path(&result_name));
+        self.cx
+            .result_store
+            .borrow_mut()
+            .insert(result_name.clone(), (ok_ty, err));
+        result_name
+    }
+
+    /// Registers the struct an `Option<T>` parameter or `DiplomatOption<T>` field is passed as,
+    /// matching `diplomat_runtime::DiplomatOption`, returning its name
+    fn gen_option_name<P: TyPosition>(&mut self, value_ty: &Type<P>, is_decl: bool) -> String {
+        let value_ty_name = self.cx.formatter.fmt_type_name_uniquely(value_ty);
+        let option_name = self.cx.formatter.fmt_option_name(&value_ty_name);
+        let header = if is_decl {
+            &mut self.decl_header
+        } else {
+            &mut self.impl_header
+        };
+        header
+            .includes
+            .insert(self.cx.formatter.fmt_result_header_path(&option_name));
+        self.cx.option_store.borrow_mut().insert(
+            option_name.clone(),
+            (value_ty_name.into_owned(), value_ty.id()),
+        );
+        option_name
+    }
+
+    /// Generates the struct a callback parameter is passed as, matching `diplomat_runtime::DiplomatCallback`
+    pub fn gen_callback_def(&mut self, name: &str, callback: &hir::Callback) {
+        let run_callback = self
+            .cx
+            .formatter
+            .fmt_callback_fn_ptr("run_callback", callback);
+        writeln!(self.impl_header, "typedef struct {name} {{").unwrap();
+        writeln!(self.impl_header, "\tconst void* data;").unwrap();
+        writeln!(self.impl_header, "\t{run_callback};").unwrap();
+        writeln!(self.impl_header, "\tvoid (*destructor)(const void*);").unwrap();
+        write!(self.impl_header, "}} {name};\n\n").unwrap();
     }
 
     pub fn gen_dtor(&mut self, id: TypeId) {
         let ty_name = self.cx.formatter.fmt_type_name(id);
         let dtor_name = self.cx.formatter.fmt_dtor_name(id);
+        self.cx.reserve_name(
+            &dtor_name,
+            format_args!(
+                "the destructor of `{}`",
+                self.cx.tcx.resolve_type(id).name()
+            ),
+        );
         write!(self.impl_header, "void {dtor_name}({ty_name}* self);\n\n").unwrap();
     }
 
@@ -295,6 +501,9 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
     // Generate the C code for referencing a particular type.
     // Handles adding imports and such as necessary
     fn gen_ty_name<P: TyPosition>(&mut self, ty: &Type<P>, is_decl: bool) -> Cow<'ccx, str> {
+        if let Type::DiplomatOption(ref value_ty) = *ty {
+            return self.gen_option_name(value_ty, is_decl).into();
+        }
         let header = if is_decl {
             &mut self.decl_header
         } else {
@@ -343,18 +552,23 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
                 header.includes.insert(header_path);
                 (Some(id), ty_name)
             }
+            // Slices use the views named in the runtime header, so that other code can take them
+            Type::Slice(hir::Slice::Primitive(_, prim)) => (
+                None,
+                self.cx.formatter.fmt_primitive_slice_name(prim).into(),
+            ),
+            Type::Slice(hir::Slice::Str(_, encoding)) => (
+                None,
+                match encoding {
+                    hir::StringEncoding::UnvalidatedUtf16 => "DiplomatU16StringView",
+                    _ => "DiplomatStringView",
+                }
+                .into(),
+            ),
             Type::Slice(ref s) => {
                 let ptr_ty = match s {
-                    hir::Slice::Str(
-                        _,
-                        hir::StringEncoding::UnvalidatedUtf8 | hir::StringEncoding::Utf8,
-                    ) => "char".into(),
-                    hir::Slice::Str(_, hir::StringEncoding::UnvalidatedUtf16) => "char16_t".into(),
-                    hir::Slice::Primitive(_, prim) => self.cx.formatter.fmt_primitive_as_c(*prim),
-                    hir::Slice::Strs(hir::StringEncoding::UnvalidatedUtf16) => {
-                        "DiplomatStrs16View".into()
-                    }
-                    hir::Slice::Strs(_) => "DiplomatStrs8View".into(),
+                    hir::Slice::Strs(hir::StringEncoding::UnvalidatedUtf16) => "DiplomatStrs16View",
+                    hir::Slice::Strs(_) => "DiplomatStrs8View",
                     &_ => unreachable!("unknown AST/HIR variant"),
                 };
                 (
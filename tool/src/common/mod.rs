@@ -10,6 +10,9 @@ use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Path;
+
+use diplomat_core::hir::SourceSpan;
 
 /// This type abstracts over files being written to.
 #[derive(Default, Debug)]
@@ -18,6 +21,8 @@ pub struct FileMap {
     // parameters. However, passing them around as &mut self restricts the amount of
     // borrowing that can be done. We instead use a RefCell to guard the specifically mutable bits.
     files: RefCell<HashMap<String, String>>,
+    /// The files added since [`FileMap::gen_item()`] started generating an item
+    added: RefCell<Option<Vec<String>>>,
 }
 
 impl FileMap {
@@ -25,6 +30,7 @@ impl FileMap {
     pub fn new(files: HashMap<String, String>) -> Self {
         FileMap {
             files: RefCell::new(files),
+            added: RefCell::default(),
         }
     }
 
@@ -36,8 +42,238 @@ impl FileMap {
         if self.files.borrow().get(&name).is_some() {
             panic!("File map already contains {}", name)
         }
+        if let Some(added) = self.added.borrow_mut().as_mut() {
+            added.push(name.clone());
+        }
         self.files.borrow_mut().insert(name, contents);
     }
+
+    /// Generates the files of a single type or trait with `gen`, removing them again if it
+    /// pushed errors to `errors`, so that an item failing to generate leaves no partial
+    /// output behind when the rest is written with `--keep-going`.
+    pub fn gen_item<E>(&self, errors: &ErrorStore<E>, gen: impl FnOnce()) {
+        let error_count = errors.error_count();
+        *self.added.borrow_mut() = Some(Vec::new());
+        gen();
+        let added = self.added.borrow_mut().take().unwrap_or_default();
+        if errors.error_count() > error_count {
+            let mut files = self.files.borrow_mut();
+            for name in added {
+                files.remove(&name);
+            }
+        }
+    }
+}
+
+/// The prefixes removed from the names of types, e.g. `ICU4X` to name `ICU4XLocale` `Locale`.
+#[derive(Debug, Clone, Default)]
+pub struct StripPrefix {
+    /// Removed from every type name starting with one of them, the first one that matches
+    pub prefixes: Vec<String>,
+    /// The prefix to remove from a type instead, by its Rust name. `None` keeps its name whole.
+    pub overrides: HashMap<String, Option<String>>,
+}
+
+impl StripPrefix {
+    /// Strip a single prefix from every type name
+    pub fn new(prefix: impl Into<String>) -> Self {
+        StripPrefix {
+            prefixes: vec![prefix.into()],
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// `name` without its prefix
+    pub fn strip<'a>(&self, name: &'a str) -> &'a str {
+        let stripped = match self.overrides.get(name) {
+            Some(Some(prefix)) => name.strip_prefix(prefix.as_str()),
+            Some(None) => None,
+            None => self
+                .prefixes
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix.as_str())),
+        };
+        // A type named just like the prefix keeps its name
+        stripped.filter(|s| !s.is_empty()).unwrap_or(name)
+    }
+}
+
+/// Checks that `path`, the path of a generated file relative to the output folder, stays
+/// inside of it.
+///
+/// Paths are built from file name templates, namespaces and type names, which are all
+/// configurable, so both `/` and `\` separate segments here whatever the host platform is.
+pub fn check_output_path(path: &str) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("Generated file has an empty path".into());
+    }
+    if path.starts_with(['/', '\\'])
+        || path
+            .split(['/', '\\'])
+            .next()
+            .map_or(false, |s| s.contains(':'))
+    {
+        return Err(format!(
+            "Generated file `{path}` must have a path relative to the output folder"
+        ));
+    }
+    if path.split(['/', '\\']).any(|segment| segment == "..") {
+        return Err(format!(
+            "Generated file `{path}` would be written outside of the output folder"
+        ));
+    }
+    Ok(())
+}
+
+/// Writes `text` to `path` unless the file already has exactly this content, returning whether
+/// it was written.
+///
+/// Leaving unchanged files alone keeps their modification time, so that incremental builds of
+/// the bindings only redo the work for the files a change actually touched.
+pub fn write_if_changed(path: &Path, text: &str) -> std::io::Result<bool> {
+    if is_up_to_date(path, text)? {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, text)?;
+    Ok(true)
+}
+
+/// Whether the file at `path` exists with exactly `text` as its content
+pub fn is_up_to_date(path: &Path, text: &str) -> std::io::Result<bool> {
+    Ok(
+        std::fs::metadata(path).map_or(false, |metadata| metadata.len() == text.len() as u64)
+            && std::fs::read(path)? == text.as_bytes(),
+    )
+}
+
+/// Starts a region of a generated scaffold that is the user's to edit, followed on the same
+/// line by the name of the region. Templates put it in a comment of the language of the file.
+pub const USER_CODE_BEGIN: &str = "diplomat:user-code-begin";
+/// Ends the user region named after it, see [`USER_CODE_BEGIN`]
+pub const USER_CODE_END: &str = "diplomat:user-code-end";
+
+/// The text to write to `path` for the generated `text`, keeping the content of the user
+/// regions of the file already there, see [`preserve_user_regions()`]
+pub fn with_user_regions(path: &Path, text: String) -> std::io::Result<String> {
+    if !text.contains(USER_CODE_BEGIN) {
+        return Ok(text);
+    }
+    let existing = match std::fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(text),
+        Err(e) => return Err(e),
+    };
+    preserve_user_regions(&text, &existing).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// Carries the user regions of `existing`, the previous content of a generated file, over
+/// into `generated`.
+///
+/// Regions are the lines between a [`USER_CODE_BEGIN`] and a [`USER_CODE_END`] marker with the
+/// same name. Each region of `generated` takes the lines of the region of `existing` with its
+/// name, keeping its default content if there is none. Regions no longer generated are dropped.
+pub fn preserve_user_regions(generated: &str, existing: &str) -> Result<String, String> {
+    let kept = user_regions(existing)?;
+    // Checks the markers of `generated` as well
+    user_regions(generated)?;
+
+    let mut merged = String::with_capacity(generated.len());
+    let mut skipping = false;
+    for line in generated.split_inclusive('\n') {
+        if let Some(name) = marker_name(line, USER_CODE_BEGIN) {
+            merged.push_str(line);
+            if let Some(content) = kept.get(name) {
+                merged.push_str(content);
+                skipping = true;
+            }
+        } else if marker_name(line, USER_CODE_END).is_some() {
+            merged.push_str(line);
+            skipping = false;
+        } else if !skipping {
+            merged.push_str(line);
+        }
+    }
+    Ok(merged)
+}
+
+/// The content of each user region of `text` by name, checking that the markers are paired
+fn user_regions(text: &str) -> Result<HashMap<&str, String>, String> {
+    let mut regions = HashMap::new();
+    let mut open: Option<(&str, String)> = None;
+    for line in text.split_inclusive('\n') {
+        if let Some(name) = marker_name(line, USER_CODE_BEGIN) {
+            if let Some((outer, _)) = open {
+                return Err(format!(
+                    "User region `{name}` starts inside of user region `{outer}`"
+                ));
+            }
+            if regions.contains_key(name) {
+                return Err(format!("User region `{name}` appears more than once"));
+            }
+            open = Some((name, String::new()));
+        } else if let Some(name) = marker_name(line, USER_CODE_END) {
+            match open.take() {
+                Some((begun, content)) if begun == name => {
+                    regions.insert(name, content);
+                }
+                Some((begun, _)) => {
+                    return Err(format!(
+                        "User region `{begun}` is ended by the end of user region `{name}`"
+                    ))
+                }
+                None => return Err(format!("User region `{name}` ends before it starts")),
+            }
+        } else if let Some((_, ref mut content)) = open {
+            content.push_str(line);
+        }
+    }
+    if let Some((name, _)) = open {
+        return Err(format!("User region `{name}` is never ended"));
+    }
+    Ok(regions)
+}
+
+/// The name following `marker` on `line`, if it has one
+fn marker_name<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let (_, rest) = line.split_once(marker)?;
+    rest.split_whitespace().next()
+}
+
+/// Tracks the identifiers a backend places in a shared namespace.
+///
+/// Backends generate helper declarations (destructors, slice types, lifetime edge arrays, ...)
+/// alongside the user-facing API, and after renames are applied the two may end up with the
+/// same name. Reserving every emitted identifier here turns such a clash into an error instead
+/// of output that does not compile.
+#[derive(Default)]
+pub struct NameRegistry {
+    /// Maps each reserved identifier to a description of what it was reserved for
+    names: RefCell<HashMap<String, String>>,
+}
+
+impl NameRegistry {
+    /// Reserve `name` for `owner`, a short description like "type Foo" or "destructor of Foo".
+    ///
+    /// Returns a message naming both users if the identifier was already reserved.
+    pub fn reserve(&self, name: &str, owner: impl fmt::Display) -> Result<(), String> {
+        let mut names = self.names.borrow_mut();
+        if let Some(existing) = names.get(name) {
+            return Err(format!(
+                "Identifier `{name}` generated for {owner} collides with {existing}, \
+                 please rename one of them"
+            ));
+        }
+        names.insert(name.into(), owner.to_string());
+        Ok(())
+    }
 }
 
 /// This type acts as a "store" for errors, which can be appended to.
@@ -58,7 +294,11 @@ impl<'tcx, E> ErrorStore<'tcx, E> {
     /// Set the context to a named type. Will return a scope guard that will automatically
     /// clear the context on drop.
     pub fn set_context_ty<'a>(&'a self, ty: Cow<'tcx, str>) -> ErrorContextGuard<'a, 'tcx, E> {
-        let new = ErrorContext { ty, method: None };
+        let new = ErrorContext {
+            ty,
+            method: None,
+            span: None,
+        };
         let old = mem::replace(&mut *self.context.borrow_mut(), new);
         ErrorContextGuard(self, old)
     }
@@ -72,6 +312,7 @@ impl<'tcx, E> ErrorStore<'tcx, E> {
         let new = ErrorContext {
             ty,
             method: Some(method),
+            span: None,
         };
 
         let old = mem::replace(&mut *self.context.borrow_mut(), new);
@@ -84,6 +325,11 @@ impl<'tcx, E> ErrorStore<'tcx, E> {
             .push((self.context.borrow().clone(), error));
     }
 
+    /// The number of errors pushed so far
+    pub fn error_count(&self) -> usize {
+        self.errors.borrow().len()
+    }
+
     pub fn take_all(&self) -> Vec<(impl fmt::Display + 'tcx, E)> {
         mem::take(&mut self.errors.borrow_mut())
     }
@@ -94,16 +340,21 @@ impl<'tcx, E> ErrorStore<'tcx, E> {
 struct ErrorContext<'tcx> {
     ty: Cow<'tcx, str>,
     method: Option<Cow<'tcx, str>>,
+    span: Option<SourceSpan>,
 }
 
 impl<'tcx> fmt::Display for ErrorContext<'tcx> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let ty = &self.ty;
         if let Some(ref method) = self.method {
-            write!(f, "{ty}::{method}")
+            write!(f, "{ty}::{method}")?;
         } else {
-            ty.fmt(f)
+            ty.fmt(f)?;
+        }
+        if let Some(span) = self.span {
+            write!(f, " ({span})")?;
         }
+        Ok(())
     }
 }
 
@@ -111,8 +362,282 @@ impl<'tcx> fmt::Display for ErrorContext<'tcx> {
 #[must_use]
 pub struct ErrorContextGuard<'a, 'tcx, E>(&'a ErrorStore<'tcx, E>, ErrorContext<'tcx>);
 
+impl<'a, 'tcx, E> ErrorContextGuard<'a, 'tcx, E> {
+    /// Point the errors of this context at where the type or method is declared
+    pub fn with_span(self, span: Option<SourceSpan>) -> Self {
+        self.0.context.borrow_mut().span = span;
+        self
+    }
+}
+
 impl<'a, 'tcx, E> Drop for ErrorContextGuard<'a, 'tcx, E> {
     fn drop(&mut self) {
         let _ = mem::replace(&mut *self.0.context.borrow_mut(), mem::take(&mut self.1));
     }
 }
+
+/// Build a [`TypeContext`](diplomat_core::hir::TypeContext) for keyword tests: it contains an opaque
+/// `KeywordHost` with one method renamed to each of `words`, plus one opaque type renamed to each of
+/// `type_words`.
+///
+/// Backends use this to check that reserved words reaching their formatters through renames come
+/// out escaped (or get rejected), so that extending a keyword table cannot silently regress.
+#[cfg(test)]
+pub fn keyword_tcx(words: &[&str], type_words: &[&str]) -> diplomat_core::hir::TypeContext {
+    use diplomat_core::{ast, hir};
+    use quote::{format_ident, quote};
+
+    let methods = words.iter().enumerate().map(|(i, word)| {
+        let ident = format_ident!("method_{i}");
+        quote! {
+            #[diplomat::attr(*, rename = #word)]
+            pub fn #ident(&self) {}
+        }
+    });
+    let types = type_words.iter().enumerate().map(|(i, word)| {
+        let ident = format_ident!("Type{i}");
+        quote! {
+            #[diplomat::opaque]
+            #[diplomat::attr(*, rename = #word)]
+            struct #ident;
+        }
+    });
+    let tokens = quote! {
+        #[diplomat::bridge]
+        mod ffi {
+            #[diplomat::opaque]
+            struct KeywordHost;
+
+            impl KeywordHost {
+                #(#methods)*
+            }
+
+            #(#types)*
+        }
+    };
+
+    let file = syn::parse2::<syn::File>(tokens).expect("failed to parse keyword bridge");
+    let file = ast::File::from(&file);
+    let env = file.all_types();
+    let mut attr_validator = hir::BasicAttributeValidator::new("keyword_test");
+    attr_validator.support.renaming = true;
+    match hir::TypeContext::from_ast(&env, attr_validator) {
+        Ok(tcx) => tcx,
+        Err(errors) => {
+            for (_cx, err) in errors {
+                eprintln!("Lowering error: {err}");
+            }
+            panic!("Failed to lower keyword bridge")
+        }
+    }
+}
+
+/// Whether formatting this type name panics, which is how most formatters reject reserved type names.
+#[cfg(test)]
+pub fn rejects_type_name<R>(format: impl FnOnce() -> R) -> bool {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(format)).is_err()
+}
+
+/// Asserts that generated C or C++ files can be included from any number of translation units
+/// without compiling anything of their own: they are all headers, and every function they define
+/// at the top level is `inline` or a template.
+#[cfg(test)]
+pub fn assert_header_only<'a>(files: impl IntoIterator<Item = (&'a String, &'a String)>) {
+    for (name, contents) in files {
+        assert!(
+            name.ends_with(".h") || name.ends_with(".hpp"),
+            "{name} is not a header"
+        );
+        let mut prev = "";
+        for line in contents.lines() {
+            let is_definition = !line.starts_with(char::is_whitespace)
+                && line.contains('(')
+                && line.trim_end().ends_with('{');
+            let is_template = line.starts_with("template") || prev.starts_with("template");
+            assert!(
+                !is_definition || line.contains("inline") || is_template,
+                "{name} defines a function that is not inline: {line}"
+            );
+            prev = line;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_output_path, preserve_user_regions, with_user_regions, write_if_changed, ErrorStore,
+        FileMap, NameRegistry, StripPrefix,
+    };
+
+    #[test]
+    fn test_strip_prefix() {
+        let strip_prefix = StripPrefix {
+            prefixes: vec!["ICU4X".into(), "Diplomat".into()],
+            overrides: [
+                ("ICU4XDiplomatShim".into(), Some("ICU4XDiplomat".into())),
+                ("DiplomatWriteable".into(), None),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(strip_prefix.strip("ICU4XLocale"), "Locale");
+        assert_eq!(strip_prefix.strip("DiplomatStr"), "Str");
+        assert_eq!(strip_prefix.strip("ICU4XDiplomatShim"), "Shim");
+        assert_eq!(strip_prefix.strip("DiplomatWriteable"), "DiplomatWriteable");
+        assert_eq!(strip_prefix.strip("Locale"), "Locale");
+        assert_eq!(strip_prefix.strip("ICU4X"), "ICU4X");
+    }
+
+    #[test]
+    fn test_name_registry() {
+        let names = NameRegistry::default();
+        assert_eq!(names.reserve("Foo", "type `Foo`"), Ok(()));
+        assert_eq!(
+            names.reserve("Foo_destroy", "the destructor of `Foo`"),
+            Ok(())
+        );
+        assert_eq!(
+            names.reserve("Foo_destroy", "method `destroy`"),
+            Err(
+                "Identifier `Foo_destroy` generated for method `destroy` collides with \
+                 the destructor of `Foo`, please rename one of them"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_gen_item() {
+        let mut files = FileMap::default();
+        let errors = ErrorStore::<String>::default();
+        files.gen_item(&errors, || {
+            files.add_file("Ok.h".into(), String::new());
+        });
+        files.gen_item(&errors, || {
+            files.add_file("Failing.h".into(), String::new());
+            errors.push_error("unsupported".into());
+            files.add_file("Failing.d.h".into(), String::new());
+        });
+        files.add_file("runtime.h".into(), String::new());
+
+        let mut names = files.take_files().into_keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["Ok.h", "runtime.h"]);
+        assert_eq!(errors.error_count(), 1);
+    }
+
+    #[test]
+    fn test_check_output_path() {
+        for path in ["Foo.kk", "ns/Foo.hpp", "./lib/Foo.mjs", "a..b/c.d"] {
+            assert_eq!(check_output_path(path), Ok(()), "{path}");
+        }
+        for path in [
+            "",
+            "../Foo.kk",
+            "ns/../../Foo.kk",
+            "ns\\..\\Foo.kk",
+            "/etc/Foo.kk",
+            "\\Foo.kk",
+            "C:\\Foo.kk",
+        ] {
+            assert!(check_output_path(path).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_write_if_changed() {
+        let dir = std::env::temp_dir().join(format!("diplomat-write-{}", std::process::id()));
+        let path = dir.join("ns").join("Foo.kk");
+        assert!(write_if_changed(&path, "fun foo() : ()").unwrap());
+        assert!(!write_if_changed(&path, "fun foo() : ()").unwrap());
+        assert!(write_if_changed(&path, "fun bar() : ()").unwrap());
+        assert!(write_if_changed(&path, "fun foo() : int").unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fun foo() : int");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_preserve_user_regions() {
+        let generated = "plugins {}\n\
+            // diplomat:user-code-begin deps\n\
+            // Add dependencies here\n\
+            // diplomat:user-code-end deps\n\
+            version = \"2.0\"\n\
+            // diplomat:user-code-begin tasks\n\
+            // diplomat:user-code-end tasks\n";
+        let existing = "plugins {}\n\
+            // diplomat:user-code-begin deps\n\
+            implementation(\"a:b:1\")\n\
+            // diplomat:user-code-end deps\n\
+            version = \"1.0\"\n\
+            // diplomat:user-code-begin removed\n\
+            tasks.clear()\n\
+            // diplomat:user-code-end removed\n";
+        assert_eq!(
+            preserve_user_regions(generated, existing).unwrap(),
+            "plugins {}\n\
+            // diplomat:user-code-begin deps\n\
+            implementation(\"a:b:1\")\n\
+            // diplomat:user-code-end deps\n\
+            version = \"2.0\"\n\
+            // diplomat:user-code-begin tasks\n\
+            // diplomat:user-code-end tasks\n"
+        );
+
+        for (existing, error) in [
+            (
+                "// diplomat:user-code-begin a\n",
+                "User region `a` is never ended",
+            ),
+            (
+                "// diplomat:user-code-end a\n",
+                "User region `a` ends before it starts",
+            ),
+            (
+                "// diplomat:user-code-begin a\n// diplomat:user-code-end b\n",
+                "User region `a` is ended by the end of user region `b`",
+            ),
+            (
+                "// diplomat:user-code-begin a\n// diplomat:user-code-begin b\n",
+                "User region `b` starts inside of user region `a`",
+            ),
+            (
+                "// diplomat:user-code-begin a\n// diplomat:user-code-end a\n\
+                 // diplomat:user-code-begin a\n// diplomat:user-code-end a\n",
+                "User region `a` appears more than once",
+            ),
+        ] {
+            assert_eq!(
+                preserve_user_regions(generated, existing),
+                Err(error.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_user_regions() {
+        let dir = std::env::temp_dir().join(format!("diplomat-regions-{}", std::process::id()));
+        let path = dir.join("build.gradle.kts");
+        let generated = "// diplomat:user-code-begin deps\n// diplomat:user-code-end deps\n";
+        assert_eq!(
+            with_user_regions(&path, generated.into()).unwrap(),
+            generated
+        );
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            &path,
+            "// diplomat:user-code-begin deps\nfoo()\n// diplomat:user-code-end deps\n",
+        )
+        .unwrap();
+        assert_eq!(
+            with_user_regions(&path, generated.into()).unwrap(),
+            "// diplomat:user-code-begin deps\nfoo()\n// diplomat:user-code-end deps\n"
+        );
+        // Files without regions are written over as they are
+        assert_eq!(with_user_regions(&path, "bar()".into()).unwrap(), "bar()");
+        std::fs::write(&path, "// diplomat:user-code-end deps\n").unwrap();
+        assert!(with_user_regions(&path, generated.into()).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -50,6 +50,8 @@ fn render_header(typ_name: &ast::Ident, headers: &[String]) -> String {
     )
 }
 
+/// Generates the C++ bindings, which are header-only: every definition is `inline` or a template,
+/// so the headers can be included from any number of translation units with nothing to compile.
 pub fn gen_bindings(
     env: &Env,
     library_config_path: Option<&Path>,
@@ -356,6 +358,47 @@ fn gen_includes<W: fmt::Write>(
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_header_only() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Foo(u8);
+
+                struct Bar {
+                    x: u8,
+                }
+
+                enum Baz {
+                    A,
+                    B,
+                }
+
+                impl Foo {
+                    pub fn new(x: u8) -> Box<Foo> {
+                        unimplemented!()
+                    }
+
+                    pub fn to_bar(&self, baz: Baz) -> Bar {
+                        unimplemented!()
+                    }
+
+                    pub fn to_string(&self, w: &mut DiplomatWriteable) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let custom_types = diplomat_core::ast::File::from(&parsed);
+        let env = custom_types.all_types();
+
+        let mut out_texts = std::collections::HashMap::new();
+        crate::cpp::gen_bindings(&env, None, &Default::default(), &mut out_texts).unwrap();
+
+        crate::common::assert_header_only(&out_texts);
+    }
+
     #[test]
     fn test_cross_module_struct_fields() {
         test_file! {
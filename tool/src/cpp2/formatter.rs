@@ -1,6 +1,6 @@
 //! This module contains functions for formatting types
 
-use crate::c2::CFormatter;
+use crate::c2::{CFormatter, CPP_RESERVED_WORDS as RESERVED_WORDS};
 use diplomat_core::hir::{self, StringEncoding, TypeContext, TypeId};
 use std::borrow::Cow;
 
@@ -17,6 +17,22 @@ pub struct Cpp2Formatter<'tcx> {
     c: CFormatter<'tcx>,
 }
 
+/// Types the generated headers refer to without a namespace
+const DISALLOWED_CORE_TYPES: &[&str] = &[
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "intptr_t",
+    "size_t",
+    "std",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+    "uintptr_t",
+];
+
 impl<'tcx> Cpp2Formatter<'tcx> {
     pub fn new(tcx: &'tcx TypeContext) -> Self {
         Self {
@@ -28,18 +44,19 @@ impl<'tcx> Cpp2Formatter<'tcx> {
     pub fn fmt_type_name_unnamespaced(&self, id: TypeId) -> Cow<'tcx, str> {
         let resolved = self.c.tcx().resolve_type(id);
 
-        resolved
+        let name = resolved
             .attrs()
             .rename
-            .apply(resolved.name().as_str().into())
+            .apply(resolved.name().as_str().into());
+        if RESERVED_WORDS.contains(&&*name) || DISALLOWED_CORE_TYPES.contains(&&*name) {
+            panic!("{name:?} is not a valid C++ type name. Please rename.");
+        }
+        name
     }
     /// Resolve and format a named type for use in code
     pub fn fmt_type_name(&self, id: TypeId) -> Cow<'tcx, str> {
         let resolved = self.c.tcx().resolve_type(id);
-        let name = resolved
-            .attrs()
-            .rename
-            .apply(resolved.name().as_str().into());
+        let name = self.fmt_type_name_unnamespaced(id);
         if let Some(ref ns) = resolved.attrs().namespace {
             format!("{ns}::{name}").into()
         } else {
@@ -88,7 +105,8 @@ impl<'tcx> Cpp2Formatter<'tcx> {
     /// Format a field name or parameter name
     // might need splitting in the future if we decide to support renames here
     pub fn fmt_param_name<'a>(&self, ident: &'a str) -> Cow<'a, str> {
-        ident.into()
+        // Fields are accessed on the C structs under the same name, so this must match C
+        self.c.fmt_param_name(ident)
     }
 
     pub fn fmt_c_type_name<'a>(&self, id: TypeId) -> Cow<'a, str> {
@@ -157,13 +175,13 @@ impl<'tcx> Cpp2Formatter<'tcx> {
 
     /// Format a method
     pub fn fmt_method_name<'a>(&self, method: &'a hir::Method) -> Cow<'a, str> {
+        // Opaques cannot be copied, only explicitly cloned by the Rust side
+        if let Some(hir::SpecialMethod::Clone) = method.attrs.special_method {
+            return "clone".into();
+        }
         let name = method.attrs.rename.apply(method.name.as_str().into());
-
-        // TODO(#60): handle other keywords
-        if name == "new" {
-            "new_".into()
-        } else if name == "default" {
-            "default_".into()
+        if RESERVED_WORDS.contains(&&*name) {
+            format!("{name}_").into()
         } else {
             name
         }
@@ -180,3 +198,48 @@ impl<'tcx> Cpp2Formatter<'tcx> {
         self.c.fmt_primitive_as_c(prim)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Cpp2Formatter, DISALLOWED_CORE_TYPES, RESERVED_WORDS};
+    use crate::common::{keyword_tcx, rejects_type_name};
+
+    fn is_valid_ident(name: &str) -> bool {
+        !RESERVED_WORDS.contains(&name)
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        let words: Vec<&str> = RESERVED_WORDS
+            .iter()
+            .chain(DISALLOWED_CORE_TYPES)
+            .copied()
+            .collect();
+        let tcx = keyword_tcx(&words, &words);
+        let formatter = Cpp2Formatter::new(&tcx);
+
+        let methods = &tcx
+            .opaques()
+            .iter()
+            .find(|ty| ty.name.as_str() == "KeywordHost")
+            .unwrap()
+            .methods;
+        assert_eq!(methods.len(), words.len());
+        for method in methods {
+            let name = formatter.fmt_method_name(method);
+            assert!(is_valid_ident(&name), "method name {name:?}");
+        }
+        for word in &words {
+            let name = formatter.fmt_param_name(word);
+            assert!(is_valid_ident(&name), "param name {name:?}");
+        }
+
+        for (id, ty) in tcx.all_types() {
+            if ty.name().as_str() != "KeywordHost" {
+                assert!(rejects_type_name(|| formatter.fmt_type_name(id)));
+            }
+        }
+    }
+}
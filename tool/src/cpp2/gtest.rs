@@ -0,0 +1,302 @@
+//! The Google Test suite generated with [`Cpp2Config::gtest`](super::Cpp2Config::gtest)
+
+use super::Cpp2Context;
+use askama::Template;
+use diplomat_core::hir::{
+    self, Method, OpaqueId, OpaqueOwner, ReturnType, SelfType, StructPathLike, SuccessType,
+    TyPosition, Type, TypeDef, TypeId,
+};
+use std::collections::BTreeSet;
+
+/// The file the suite is generated into
+const GTEST_FILE: &str = "diplomat_gtest.cpp";
+/// The file the suite is generated into with [`Cpp2Config::header_only`](super::Cpp2Config::header_only)
+const GTEST_HEADER: &str = "diplomat_gtest.hpp";
+
+#[derive(Template)]
+#[template(path = "cpp2/gtest.cpp.jinja", escape = "none")]
+struct GtestTemplate {
+    /// Whether to define the tests inline, see [`Cpp2Config::header_only`](super::Cpp2Config::header_only)
+    header_only: bool,
+    includes: BTreeSet<String>,
+    tests: Vec<Gtest>,
+    /// The methods not called, as `Type::method` and why
+    skipped: Vec<(String, &'static str)>,
+}
+
+/// One `TEST`, calling a method
+struct Gtest {
+    /// The test suite, named after the type
+    suite: String,
+    /// The test, named after the method
+    name: String,
+    /// The statements making the receiver and the opaque arguments
+    setup: Vec<String>,
+    /// The statement calling the method and checking its result
+    call: String,
+}
+
+impl<'tcx> Cpp2Context<'tcx> {
+    /// Generates a test calling each method with placeholder arguments
+    pub fn gen_gtest(&self) {
+        let mut includes = BTreeSet::new();
+        let mut tests = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (id, ty) in self.tcx.all_types() {
+            if ty.attrs().disable {
+                continue;
+            }
+            for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+                let name = format!("{}::{}", ty.name(), method.name);
+                if self.config.gtest_skip.contains(&name) {
+                    skipped.push((name, "skipped by the config"));
+                } else if method.is_async {
+                    skipped.push((name, "async"));
+                } else if let Some(test) = self.gen_test(id, method, &mut includes) {
+                    tests.push(test);
+                } else {
+                    skipped.push((name, "its arguments can't be built"));
+                }
+            }
+        }
+
+        let header_only = self.config.header_only;
+        let mut suite = GtestTemplate {
+            header_only,
+            includes,
+            tests,
+            skipped,
+        }
+        .render()
+        .unwrap();
+        suite.push('\n');
+        let file = if header_only {
+            GTEST_HEADER
+        } else {
+            GTEST_FILE
+        };
+        self.files.add_file(file.into(), suite);
+    }
+
+    fn gen_test(
+        &self,
+        id: TypeId,
+        method: &'tcx Method,
+        includes: &mut BTreeSet<String>,
+    ) -> Option<Gtest> {
+        let type_name = self.formatter.fmt_type_name(id);
+        let method_name = self.formatter.fmt_method_name(method);
+        let mut setup = Vec::new();
+        let mut used = BTreeSet::from([id]);
+
+        let receiver = match method.param_self {
+            None => format!("{type_name}::"),
+            Some(ref param_self) => {
+                match param_self.ty {
+                    SelfType::Opaque(ref op) => {
+                        self.gen_construction(op.tcx_id, "self", &mut setup, &mut used)?;
+                    }
+                    SelfType::Struct(ref st) => {
+                        let value = self.gen_struct_placeholder(st.id())?;
+                        setup.push(format!("{type_name} self = {value};"));
+                    }
+                    SelfType::Enum(ref e) => {
+                        let value = self.gen_enum_placeholder(e.tcx_id.into())?;
+                        setup.push(format!("{type_name} self = {value};"));
+                    }
+                    // Methods consuming `self` take it out of a `std::unique_ptr`, which the
+                    // C++ API has no way to do
+                    _ => return None,
+                }
+                match param_self.ty {
+                    SelfType::Opaque(..) => "self->".into(),
+                    _ => "self.".into(),
+                }
+            }
+        };
+
+        let mut args = Vec::new();
+        for param in &method.params {
+            let arg = match param.ty {
+                Type::Opaque(ref op) if op.is_optional() => "nullptr".into(),
+                Type::Opaque(ref op) if op.owner.is_owned() => return None,
+                Type::Opaque(ref op) => {
+                    let var = self.formatter.fmt_param_name(param.name.as_str());
+                    self.gen_construction(op.tcx_id, &var, &mut setup, &mut used)?;
+                    format!("*{var}")
+                }
+                ref ty => {
+                    used.extend(self.used_types(ty));
+                    self.gen_placeholder(ty)?
+                }
+            };
+            args.push(arg);
+        }
+
+        let call = format!("{receiver}{method_name}({})", args.join(", "));
+        let call = match method.output {
+            ReturnType::Infallible(SuccessType::Unit) => format!("{call};"),
+            ReturnType::Infallible(SuccessType::OutType(Type::Opaque(ref op)))
+                if op.owner.is_owned() && !op.is_optional() =>
+            {
+                format!("EXPECT_NE({call}, nullptr);")
+            }
+            ReturnType::Fallible(..) => {
+                setup.push(format!("auto result = {call};"));
+                "EXPECT_NE(result.is_ok(), result.is_err());".into()
+            }
+            _ => format!("(void){call};"),
+        };
+
+        includes.extend(
+            used.into_iter()
+                .map(|id| self.formatter.fmt_impl_header_path(id)),
+        );
+        Some(Gtest {
+            suite: type_name.replace("::", "_"),
+            name: method_name.into_owned(),
+            setup,
+            call,
+        })
+    }
+
+    /// Makes an opaque in `var` with the first constructor of its type, a static method
+    /// returning a new one, skipping the test if the constructor rejects its arguments
+    fn gen_construction(
+        &self,
+        id: OpaqueId,
+        var: &str,
+        setup: &mut Vec<String>,
+        used: &mut BTreeSet<TypeId>,
+    ) -> Option<()> {
+        let ty_id = id.into();
+        let opaque = self.tcx.resolve_opaque(id);
+        let (constructor, args) = opaque.methods.iter().find_map(|m| {
+            if m.attrs.disable || m.is_async || m.param_self.is_some() {
+                return None;
+            }
+            let constructed = match m.output {
+                ReturnType::Infallible(SuccessType::OutType(ref o))
+                | ReturnType::Fallible(SuccessType::OutType(ref o), _)
+                | ReturnType::Nullable(SuccessType::OutType(ref o)) => o,
+                _ => return None,
+            };
+            match constructed {
+                Type::Opaque(op) if op.tcx_id == id && op.owner.is_owned() => {}
+                _ => return None,
+            }
+            let args = m
+                .params
+                .iter()
+                .map(|p| self.gen_placeholder(&p.ty))
+                .collect::<Option<Vec<_>>>()?;
+            Some((m, args))
+        })?;
+
+        for param in &constructor.params {
+            used.extend(self.used_types(&param.ty));
+        }
+        used.insert(ty_id);
+        let call = format!(
+            "{}::{}({})",
+            self.formatter.fmt_type_name(ty_id),
+            self.formatter.fmt_method_name(constructor),
+            args.join(", ")
+        );
+        let skip = format!(
+            "GTEST_SKIP() << \"`{}::{}` rejected the placeholder arguments\";",
+            opaque.name, constructor.name
+        );
+        match constructor.output {
+            ReturnType::Infallible(_) => {
+                setup.push(format!("auto {var} = {call};"));
+                setup.push(format!("ASSERT_NE({var}, nullptr);"));
+            }
+            ReturnType::Fallible(..) => {
+                setup.push(format!("auto {var}_result = {call};"));
+                setup.push(format!("if (!{var}_result.is_ok()) {skip}"));
+                setup.push(format!(
+                    "auto {var} = std::move(*std::move({var}_result).ok());"
+                ));
+            }
+            _ => {
+                setup.push(format!("auto {var}_result = {call};"));
+                setup.push(format!("if (!{var}_result) {skip}"));
+                setup.push(format!("auto {var} = std::move(*{var}_result);"));
+            }
+        }
+        Some(())
+    }
+
+    /// A placeholder value of a type, if it can be made without an opaque
+    fn gen_placeholder<P: TyPosition>(&self, ty: &Type<P>) -> Option<String> {
+        Some(match ty {
+            Type::Primitive(hir::PrimitiveType::Bool) => "false".into(),
+            Type::Primitive(_) => "0".into(),
+            Type::Enum(e) => self.gen_enum_placeholder(e.tcx_id.into())?,
+            Type::Struct(st) => self.gen_struct_placeholder(st.id())?,
+            Type::Slice(hir::Slice::Str(_, encoding)) => {
+                format!("{}()", self.formatter.fmt_borrowed_str(*encoding))
+            }
+            Type::Slice(hir::Slice::Primitive(borrow, p)) => format!(
+                "{}(nullptr, 0)",
+                self.formatter.fmt_borrowed_slice(
+                    &self.formatter.fmt_primitive_as_c(*p),
+                    borrow.map_or(hir::Mutability::Mutable, |b| b.mutability)
+                )
+            ),
+            Type::Slice(hir::Slice::Strs(encoding)) => format!(
+                "diplomat::span<const {}>(nullptr, 0)",
+                self.formatter.fmt_borrowed_str(*encoding)
+            ),
+            _ => return None,
+        })
+    }
+
+    /// The first variant of an enum
+    fn gen_enum_placeholder(&self, id: TypeId) -> Option<String> {
+        let TypeDef::Enum(def) = self.tcx.resolve_type(id) else {
+            unreachable!("enum path to a type that is not an enum")
+        };
+        Some(format!(
+            "{}::{}",
+            self.formatter.fmt_type_name(id),
+            self.formatter.fmt_enum_variant(def.variants.first()?)
+        ))
+    }
+
+    /// A struct with placeholder fields, if it is one Rust takes
+    fn gen_struct_placeholder(&self, id: TypeId) -> Option<String> {
+        let TypeDef::Struct(def) = self.tcx.resolve_type(id) else {
+            return None;
+        };
+        let fields = def
+            .fields
+            .iter()
+            .map(|f| self.gen_placeholder(&f.ty))
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!(
+            "{}{{{}}}",
+            self.formatter.fmt_type_name(id),
+            fields.join(", ")
+        ))
+    }
+
+    /// The types whose headers a placeholder of the type needs
+    fn used_types<P: TyPosition>(&self, ty: &Type<P>) -> Vec<TypeId> {
+        match ty {
+            Type::Enum(e) => vec![e.tcx_id.into()],
+            Type::Struct(st) => {
+                let mut used = vec![st.id()];
+                if let TypeDef::Struct(def) = self.tcx.resolve_type(st.id()) {
+                    for field in &def.fields {
+                        used.extend(self.used_types(&field.ty));
+                    }
+                }
+                used
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,10 +1,40 @@
 mod formatter;
+mod gtest;
 mod header;
 mod ty;
 
-use crate::common::{ErrorStore, FileMap};
+use crate::common::{ErrorStore, FileMap, NameRegistry};
 use diplomat_core::hir::TypeContext;
 use formatter::Cpp2Formatter;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Namespaces declared by `diplomat_runtime.hpp`, which every generated header includes
+const RUNTIME_NAMES: &[&str] = &["capi", "diplomat"];
+
+/// The options of the C++ backend, read from the library config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Cpp2Config {
+    /// Whether to generate `diplomat_gtest.cpp`, a Google Test suite calling every method
+    /// with placeholder arguments, to check that the bindings compile, link and run
+    /// (`diplomat_gtest.hpp` with [`header_only`](Self::header_only))
+    pub gtest: bool,
+    /// The methods the suite doesn't call, as `Type::method`, e.g. those panicking on
+    /// placeholder arguments
+    pub gtest_skip: Vec<String>,
+    /// Whether to generate the suite as `diplomat_gtest.hpp`, registering its tests from inline
+    /// variables, instead of `diplomat_gtest.cpp`. The bindings themselves are always header-only.
+    pub header_only: bool,
+}
+
+/// Reads the options of the C++ backend from a library config
+pub fn read_config(conf_path: &Path) -> Cpp2Config {
+    let conf_str = std::fs::read_to_string(conf_path)
+        .unwrap_or_else(|err| panic!("Failed to open config file {conf_path:?}: {err}"));
+    toml::from_str(&conf_str)
+        .unwrap_or_else(|err| panic!("Failed to parse config file {conf_path:?}: {err}"))
+}
 
 /// This is the main object that drives this backend. Most execution steps
 /// for this backend will be found as methods on this context
@@ -12,15 +42,26 @@ pub struct Cpp2Context<'tcx> {
     pub tcx: &'tcx TypeContext,
     pub formatter: Cpp2Formatter<'tcx>,
     pub files: FileMap,
+    pub config: Cpp2Config,
+    /// Every type name declared by the headers, with its namespace
+    pub names: NameRegistry,
     pub errors: ErrorStore<'tcx, String>,
 }
 
 impl<'tcx> Cpp2Context<'tcx> {
     pub fn new(tcx: &'tcx TypeContext, files: FileMap) -> Self {
+        let names = NameRegistry::default();
+        for name in RUNTIME_NAMES {
+            // The runtime names are distinct, this cannot fail
+            let _ = names.reserve(name, "the Diplomat runtime");
+        }
+
         Cpp2Context {
             tcx,
             files,
             formatter: Cpp2Formatter::new(tcx),
+            config: Cpp2Config::default(),
+            names,
             errors: ErrorStore::default(),
         }
     }
@@ -34,9 +75,139 @@ impl<'tcx> Cpp2Context<'tcx> {
             crate::cpp::RUNTIME_HPP.into(),
         );
         for (id, ty) in self.tcx.all_types() {
-            self.gen_ty(id, ty)
+            self.files.gen_item(&self.errors, || self.gen_ty(id, ty))
+        }
+        if self.config.gtest {
+            self.gen_gtest();
         }
     }
 
     // further methods can be found in ty.rs and formatter.rs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Cpp2Config, Cpp2Context};
+    use crate::common::FileMap;
+    use diplomat_core::{ast, hir};
+    use quote::quote;
+
+    /// A counter with methods of each kind the suite calls or skips
+    fn counter_tcx() -> hir::TypeContext {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                pub enum Mode {
+                    Fast,
+                    Exact,
+                }
+
+                pub struct Options {
+                    mode: Mode,
+                    start: u32,
+                }
+
+                impl Counter {
+                    pub fn new(options: Options) -> Result<Box<Counter>, ()> {
+                        unimplemented!()
+                    }
+
+                    pub fn add(&mut self, values: &[u32], label: &str) {
+                        unimplemented!()
+                    }
+
+                    pub fn get(&self) -> u32 {
+                        unimplemented!()
+                    }
+
+                    pub fn merge(&self, other: &Counter) -> Result<u32, Mode> {
+                        unimplemented!()
+                    }
+
+                    pub fn panics(&self) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let mut validator = hir::BasicAttributeValidator::new("cpp2");
+        validator.other_backend_names.push("cpp".into());
+        hir::TypeContext::from_ast(&file.all_types(), validator).unwrap()
+    }
+
+    #[test]
+    fn test_gtest() {
+        let tcx = counter_tcx();
+        let mut context = Cpp2Context::new(&tcx, FileMap::default());
+        context.config = Cpp2Config {
+            gtest: true,
+            gtest_skip: vec!["Counter::panics".into()],
+            ..Default::default()
+        };
+        context.run();
+        assert!(context.errors.take_all().is_empty());
+        insta::assert_snapshot!(context
+            .files
+            .take_files()
+            .remove("diplomat_gtest.cpp")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_header_only() {
+        let tcx = counter_tcx();
+        let mut context = Cpp2Context::new(&tcx, FileMap::default());
+        context.config = Cpp2Config {
+            gtest: true,
+            gtest_skip: vec!["Counter::panics".into()],
+            header_only: true,
+        };
+        context.run();
+        assert!(context.errors.take_all().is_empty());
+        let mut files = context.files.take_files();
+
+        let mut names = files.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        insta::assert_snapshot!(names.join("\n"));
+
+        crate::common::assert_header_only(&files);
+        insta::assert_snapshot!(files.remove("diplomat_gtest.hpp").unwrap());
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub enum Setting {
+                    Value,
+                    Default,
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let mut validator = hir::BasicAttributeValidator::new("cpp2");
+        validator.other_backend_names.push("cpp".into());
+        let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+        let context = Cpp2Context::new(&tcx, FileMap::default());
+        context.run();
+        let errors = context
+            .errors
+            .take_all()
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect::<Vec<_>>();
+        // The variants are declared in the scope of the class
+        assert_eq!(
+            errors,
+            [
+                "Identifier `Value` generated for variant `Value` collides with \
+              the enum of variants, please rename one of them"
+            ]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use super::header::Header;
 use super::Cpp2Context;
 use super::Cpp2Formatter;
+use crate::common::NameRegistry;
 use askama::Template;
 use diplomat_core::hir::{
     self, Mutability, OpaqueOwner, ReturnType, SelfType, StructPathLike, SuccessType, TyPosition,
@@ -25,7 +26,16 @@ impl<'tcx> super::Cpp2Context<'tcx> {
             decl_header: &mut decl_header,
             impl_header: &mut impl_header,
         };
-        let guard = self.errors.set_context_ty(ty.name().as_str().into());
+        let guard = self
+            .errors
+            .set_context_ty(ty.name().as_str().into())
+            .with_span(ty.span());
+        if let Err(e) = self.names.reserve(
+            &self.formatter.fmt_type_name(id),
+            format_args!("type `{}`", ty.name()),
+        ) {
+            self.errors.push_error(e);
+        }
         match ty {
             TypeDef::Enum(o) => context.gen_enum_def(o, id),
             TypeDef::Opaque(o) => context.gen_opaque_def(o, id),
@@ -127,6 +137,18 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
             .flat_map(|method| self.gen_method_info(id, method))
             .collect::<Vec<_>>();
 
+        let mut members = vec![
+            ("value".into(), "the C value".into()),
+            ("Value".into(), "the enum of variants".into()),
+        ];
+        for variant in &ty.variants {
+            members.push((
+                self.cx.formatter.fmt_enum_variant(variant),
+                format!("variant `{}`", variant.name),
+            ));
+        }
+        self.check_member_names(&type_name_unnamespaced, &members, &methods);
+
         #[derive(Template)]
         #[template(path = "cpp2/enum_decl.h.jinja", escape = "none")]
         struct DeclTemplate<'a> {
@@ -190,6 +212,8 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
             .flat_map(|method| self.gen_method_info(id, method))
             .collect::<Vec<_>>();
 
+        self.check_member_names(&type_name_unnamespaced, &[], &methods);
+
         #[derive(Template)]
         #[template(path = "cpp2/opaque_decl.h.jinja", escape = "none")]
         struct DeclTemplate<'a> {
@@ -270,6 +294,14 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
             .flat_map(|method| self.gen_method_info(id, method))
             .collect::<Vec<_>>();
 
+        let members = def
+            .fields
+            .iter()
+            .zip(&field_decls)
+            .map(|(field, decl)| (decl.var_name.clone(), format!("field `{}`", field.name)))
+            .collect::<Vec<_>>();
+        self.check_member_names(&type_name_unnamespaced, &members, &methods);
+
         #[derive(Template)]
         #[template(path = "cpp2/struct_decl.h.jinja", escape = "none")]
         struct DeclTemplate<'a> {
@@ -321,6 +353,34 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
         .unwrap();
     }
 
+    /// Reserves the names declared in the scope of a class, its `members` other than those every
+    /// class has, and its methods, reporting those that collide
+    fn check_member_names(
+        &self,
+        type_name: &str,
+        members: &[(Cow<str>, String)],
+        methods: &[MethodInfo],
+    ) {
+        let names = NameRegistry::default();
+        for (name, owner) in [
+            (type_name, "the constructors"),
+            ("AsFFI", "the conversion to C"),
+            ("FromFFI", "the conversion from C"),
+        ] {
+            // These are reserved first and distinct, they cannot collide
+            let _ = names.reserve(name, owner);
+        }
+        let members = members.iter().map(|(name, owner)| (&**name, owner.clone()));
+        let methods = methods
+            .iter()
+            .map(|m| (&*m.method_name, format!("method `{}`", m.method.name)));
+        for (name, owner) in members.chain(methods) {
+            if let Err(e) = names.reserve(name, owner) {
+                self.cx.errors.push_error(e);
+            }
+        }
+    }
+
     fn gen_method_info(
         &mut self,
         id: TypeId,
@@ -329,10 +389,14 @@ impl<'ccx, 'tcx: 'ccx, 'header> TyGenContext<'ccx, 'tcx, 'header> {
         if method.attrs.disable {
             return None;
         }
-        let _guard = self.cx.errors.set_context_method(
-            self.cx.formatter.fmt_type_name_diagnostics(id),
-            method.name.as_str().into(),
-        );
+        let _guard = self
+            .cx
+            .errors
+            .set_context_method(
+                self.cx.formatter.fmt_type_name_diagnostics(id),
+                method.name.as_str().into(),
+            )
+            .with_span(method.span);
         let method_name = self.cx.formatter.fmt_method_name(method);
         let c_method_name = self.cx.formatter.fmt_c_method_name(id, method);
         let mut param_decls = Vec::new();
@@ -1,6 +1,7 @@
 //! This module contains functions for formatting types
 
 use crate::c2::CFormatter;
+use crate::common::StripPrefix;
 use diplomat_core::ast::{DocsUrlGenerator, MarkdownStyle};
 use diplomat_core::hir::{self, TypeContext, TypeId};
 use heck::ToLowerCamelCase;
@@ -18,18 +19,26 @@ use std::borrow::Cow;
 pub(super) struct DartFormatter<'tcx> {
     c: CFormatter<'tcx>,
     docs_url_generator: &'tcx DocsUrlGenerator,
-    strip_prefix: Option<String>,
+    strip_prefix: StripPrefix,
 }
 
-const INVALID_METHOD_NAMES: &[&str] = &["new", "static", "default"];
-const INVALID_FIELD_NAMES: &[&str] = &["new", "static", "default"];
-const DISALLOWED_CORE_TYPES: &[&str] = &["Object", "String"];
+/// Dart reserved words, plus the built-in identifiers that cannot name class members.
+const RESERVED_WORDS: &[&str] = &[
+    "assert", "break", "case", "catch", "class", "const", "continue", "default", "do", "else",
+    "enum", "extends", "false", "final", "finally", "for", "if", "in", "is", "new", "null",
+    "rethrow", "return", "static", "super", "switch", "this", "throw", "true", "try", "var",
+    "void", "while", "with",
+];
+/// Types the generated code refers to without the `core.` prefix
+const DISALLOWED_CORE_TYPES: &[&str] = &[
+    "bool", "double", "dynamic", "Function", "int", "Never", "Null", "num", "Object", "String",
+];
 
 impl<'tcx> DartFormatter<'tcx> {
     pub fn new(
         tcx: &'tcx TypeContext,
         docs_url_generator: &'tcx DocsUrlGenerator,
-        strip_prefix: Option<String>,
+        strip_prefix: StripPrefix,
     ) -> Self {
         Self {
             c: CFormatter::new(tcx),
@@ -68,14 +77,15 @@ impl<'tcx> DartFormatter<'tcx> {
     }
 
     pub fn fmt_docs(&self, docs: &hir::Docs) -> String {
-        docs.to_markdown(self.docs_url_generator, MarkdownStyle::Normal)
+        let mut docs = docs
+            .to_markdown(self.docs_url_generator, MarkdownStyle::Normal)
             .trim()
             .replace('\n', "\n/// ")
-            .replace(" \n", "\n")
-            .replace(
-                &format!("`{}", self.strip_prefix.as_deref().unwrap_or("")),
-                "`",
-            )
+            .replace(" \n", "\n");
+        for prefix in &self.strip_prefix.prefixes {
+            docs = docs.replace(&format!("`{prefix}"), "`");
+        }
+        docs
     }
 
     pub fn fmt_destructor_name(&self, id: TypeId) -> String {
@@ -86,22 +96,14 @@ impl<'tcx> DartFormatter<'tcx> {
     pub fn fmt_type_name(&self, id: TypeId) -> Cow<'tcx, str> {
         let resolved = self.c.tcx().resolve_type(id);
 
-        let candidate: Cow<str> = if let Some(strip_prefix) = self.strip_prefix.as_ref() {
-            resolved
-                .name()
-                .as_str()
-                .strip_prefix(strip_prefix)
-                .unwrap_or(resolved.name().as_str())
-                .into()
-        } else {
-            resolved.name().as_str().into()
-        };
+        let candidate: Cow<str> = self.strip_prefix.strip(resolved.name().as_str()).into();
 
-        if DISALLOWED_CORE_TYPES.contains(&&*candidate) {
-            panic!("{candidate:?} is not a valid Dart type name. Please rename.");
+        let name = resolved.attrs().rename.apply(candidate);
+        if DISALLOWED_CORE_TYPES.contains(&&*name) || RESERVED_WORDS.contains(&&*name) {
+            panic!("{name:?} is not a valid Dart type name. Please rename.");
         }
 
-        resolved.attrs().rename.apply(candidate)
+        name
     }
 
     /// Resolve and format a named type for use in diagnostics
@@ -113,13 +115,23 @@ impl<'tcx> DartFormatter<'tcx> {
     /// Format an enum variant.
     pub fn fmt_enum_variant(&self, variant: &'tcx hir::EnumVariant) -> Cow<'tcx, str> {
         let name = variant.name.as_str().to_lower_camel_case().into();
-        variant.attrs.rename.apply(name)
+        self.escape_reserved(variant.attrs.rename.apply(name).into_owned())
+            .into()
     }
 
     /// Format a field name or parameter name
     // might need splitting in the future if we decide to support renames here
     pub fn fmt_param_name<'a>(&self, ident: &'a str) -> Cow<'a, str> {
-        ident.to_lower_camel_case().into()
+        self.escape_reserved(ident.to_lower_camel_case()).into()
+    }
+
+    /// Suffix identifiers that Dart reserves with `_`
+    fn escape_reserved(&self, name: String) -> String {
+        if RESERVED_WORDS.contains(&&*name) {
+            format!("{name}_")
+        } else {
+            name
+        }
     }
 
     pub fn fmt_nullable(&self, ident: &str) -> String {
@@ -128,17 +140,12 @@ impl<'tcx> DartFormatter<'tcx> {
 
     /// Format a method
     pub fn fmt_method_name(&self, method: &hir::Method) -> String {
-        // TODO(#60): handle other keywords
         let name = method
             .attrs
             .rename
             .apply(method.name.as_str().into())
             .to_lower_camel_case();
-        if INVALID_METHOD_NAMES.contains(&&*name) {
-            format!("{name}_")
-        } else {
-            name
-        }
+        self.escape_reserved(name)
     }
 
     pub fn fmt_constructor_name(&self, name: &Option<String>, method: &hir::Method) -> String {
@@ -147,12 +154,7 @@ impl<'tcx> DartFormatter<'tcx> {
             .rename
             .apply(name.as_deref().unwrap_or(method.name.as_str()).into())
             .to_lower_camel_case();
-
-        if INVALID_METHOD_NAMES.contains(&name.as_str()) {
-            format!("{name}_")
-        } else {
-            name
-        }
+        self.escape_reserved(name)
     }
 
     pub fn fmt_accessor_name(&self, name: &Option<String>, method: &hir::Method) -> String {
@@ -161,12 +163,7 @@ impl<'tcx> DartFormatter<'tcx> {
             .rename
             .apply(name.as_deref().unwrap_or(method.name.as_str()).into())
             .to_lower_camel_case();
-
-        if INVALID_FIELD_NAMES.contains(&name.as_str()) {
-            format!("{name}_")
-        } else {
-            name
-        }
+        self.escape_reserved(name)
     }
 
     pub fn fmt_c_method_name<'a>(&self, ty: TypeId, method: &'a hir::Method) -> Cow<'a, str> {
@@ -310,3 +307,56 @@ impl<'tcx> DartFormatter<'tcx> {
         "_SliceUtf16"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DartFormatter, DISALLOWED_CORE_TYPES, RESERVED_WORDS};
+    use crate::common::{keyword_tcx, rejects_type_name};
+    use diplomat_core::ast::DocsUrlGenerator;
+
+    fn is_valid_ident(name: &str) -> bool {
+        !RESERVED_WORDS.contains(&name)
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        let words: Vec<&str> = RESERVED_WORDS
+            .iter()
+            .chain(DISALLOWED_CORE_TYPES)
+            .copied()
+            .collect();
+        let tcx = keyword_tcx(&words, &words);
+        let docs_urls = DocsUrlGenerator::default();
+        let formatter = DartFormatter::new(&tcx, &docs_urls, Default::default());
+
+        let methods = &tcx
+            .opaques()
+            .iter()
+            .find(|ty| ty.name.as_str() == "KeywordHost")
+            .unwrap()
+            .methods;
+        assert_eq!(methods.len(), words.len());
+        for method in methods {
+            let name = formatter.fmt_method_name(method);
+            assert!(is_valid_ident(&name), "method name {name:?}");
+            let name = formatter.fmt_constructor_name(&None, method);
+            assert!(is_valid_ident(&name), "constructor name {name:?}");
+            let name = formatter.fmt_accessor_name(&None, method);
+            assert!(is_valid_ident(&name), "accessor name {name:?}");
+        }
+        for word in &words {
+            let name = formatter.fmt_param_name(word);
+            assert!(is_valid_ident(&name), "param name {name:?}");
+        }
+
+        for (id, ty) in tcx.all_types() {
+            if ty.name().as_str() != "KeywordHost" {
+                assert!(rejects_type_name(|| formatter.fmt_type_name(id)));
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::common::{ErrorStore, FileMap};
+use crate::common::{ErrorStore, FileMap, NameRegistry, StripPrefix};
 use askama::Template;
 use diplomat_core::ast::DocsUrlGenerator;
 use diplomat_core::hir::borrowing_param::{
@@ -16,12 +16,15 @@ use std::fmt::{Display, Write};
 
 mod formatter;
 
+/// Identifiers declared by `lib.g.dart` that are not private to it
+const RUNTIME_NAMES: &[&str] = &["Rune"];
+
 /// Run file generation
 pub fn run<'cx>(
     tcx: &'cx TypeContext,
     docs_url_generator: &'cx DocsUrlGenerator,
-    strip_prefix: Option<String>,
-) -> Result<FileMap, Vec<(impl Display + 'cx, String)>> {
+    strip_prefix: StripPrefix,
+) -> (FileMap, Vec<(impl Display + 'cx, String)>) {
     let formatter = DartFormatter::new(tcx, docs_url_generator, strip_prefix);
 
     let files = FileMap::default();
@@ -30,11 +33,18 @@ pub fn run<'cx>(
     let mut directives = BTreeSet::default();
     let mut helper_classes = BTreeMap::default();
 
+    let names = NameRegistry::default();
+    for name in RUNTIME_NAMES {
+        // The runtime names are distinct, this cannot fail
+        let _ = names.reserve(name, "the Diplomat runtime");
+    }
+
     let mut tgcx = TyGenContext {
         tcx,
         errors: &errors,
         helper_classes: &mut helper_classes,
         formatter: &formatter,
+        names: &names,
     };
 
     // Needed for ListStringView
@@ -49,7 +59,11 @@ pub fn run<'cx>(
             continue;
         }
 
+        let error_count = errors.error_count();
         let (file_name, body) = tgcx.gen(id);
+        if errors.error_count() > error_count {
+            continue;
+        }
 
         directives.insert(formatter.fmt_part(&file_name));
 
@@ -75,6 +89,9 @@ pub fn run<'cx>(
         .insert(formatter.fmt_import("package:ffi/ffi.dart", Some("as ffi2 show Arena, calloc")));
     directives.insert(formatter.fmt_import("dart:typed_data", None));
     directives.insert(formatter.fmt_import("package:meta/meta.dart", Some("as meta")));
+    if helper_classes.contains_key("background") {
+        directives.insert(formatter.fmt_import("dart:isolate", Some("as isolate")));
+    }
 
     files.add_file(
         formatter.fmt_file_name("lib"),
@@ -85,12 +102,7 @@ pub fn run<'cx>(
         ),
     );
 
-    let errors = errors.take_all();
-    if !errors.is_empty() {
-        Err(errors)
-    } else {
-        Ok(files)
-    }
+    (files, errors.take_all())
 }
 
 fn render_class(
@@ -120,15 +132,27 @@ struct TyGenContext<'a, 'cx> {
     formatter: &'a DartFormatter<'cx>,
     errors: &'a ErrorStore<'cx, String>,
     helper_classes: &'a mut BTreeMap<String, String>,
+    /// Every identifier declared in the library, which all files are part of
+    names: &'a NameRegistry,
 }
 
 impl<'a, 'cx> TyGenContext<'a, 'cx> {
     fn gen(&mut self, id: TypeId) -> (String, String) {
         let ty = self.tcx.resolve_type(id);
 
-        let _guard = self.errors.set_context_ty(ty.name().as_str().into());
+        let _guard = self
+            .errors
+            .set_context_ty(ty.name().as_str().into())
+            .with_span(ty.span());
 
         let name = self.formatter.fmt_type_name(id);
+        self.reserve_name(&name, format_args!("type `{}`", ty.name()));
+        if let TypeDef::Struct(..) | TypeDef::OutStruct(..) = ty {
+            self.reserve_name(
+                &format!("_{name}Ffi"),
+                format_args!("the FFI struct of `{}`", ty.name()),
+            );
+        }
         (
             self.formatter.fmt_file_name(&name),
             match ty {
@@ -150,6 +174,24 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
         let special = self.gen_special_method_info(&ty.special_method_presence);
 
+        let mut members = vec![
+            ("values".into(), "the list of variants".into()),
+            ("index".into(), "the index of variants".into()),
+        ];
+        if !is_contiguous_enum(ty) {
+            members.push(("_ffi".into(), "the FFI value".into()));
+        }
+        if special.comparator {
+            members.push(("hashCode".into(), "the equality implementation".into()));
+        }
+        for variant in &ty.variants {
+            members.push((
+                self.formatter.fmt_enum_variant(variant).into_owned(),
+                format!("variant `{}`", variant.name),
+            ));
+        }
+        self.check_member_names(members, &methods);
+
         #[derive(Template)]
         #[template(path = "dart/enum.dart.jinja", escape = "none")]
         struct ImplTemplate<'a> {
@@ -183,8 +225,34 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             .collect::<Vec<_>>();
 
         let destructor = self.formatter.fmt_destructor_name(id);
+        self.reserve_name(
+            &format!("_{destructor}"),
+            format_args!("the destructor of `{}`", ty.name),
+        );
         let special = self.gen_special_method_info(&ty.special_method_presence);
 
+        let mut members = vec![
+            ("_ffi".into(), "the FFI pointer".into()),
+            ("_fromFfi".into(), "the conversion from FFI".into()),
+            ("_finalizer".into(), "the finalizer".into()),
+            ("_selfEdge".into(), "the lifetime edges".into()),
+        ];
+        for lifetime in ty.lifetimes.all_lifetimes() {
+            members.push((
+                format!("_{}Edge", ty.lifetimes.fmt_lifetime(lifetime)),
+                "the lifetime edges".into(),
+            ));
+        }
+        if special.iterator.is_some() {
+            for name in ["_current", "current", "moveNext"] {
+                members.push((name.into(), "the iterator implementation".into()));
+            }
+        }
+        if special.comparator {
+            members.push(("hashCode".into(), "the equality implementation".into()));
+        }
+        self.check_member_names(members, &methods);
+
         #[derive(Template)]
         #[template(path = "dart/opaque.dart.jinja", escape = "none")]
         struct ImplTemplate<'a> {
@@ -305,6 +373,25 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             .collect::<Vec<_>>();
         let special = self.gen_special_method_info(&ty.special_method_presence);
 
+        let mut members = vec![
+            ("_fromFfi".into(), "the conversion from FFI".into()),
+            ("_toFfi".into(), "the conversion to FFI".into()),
+            ("hashCode".into(), "the equality implementation".into()),
+        ];
+        for lifetime in ty.lifetimes.all_lifetimes() {
+            members.push((
+                format!(
+                    "_fieldsForLifetime{}",
+                    ty.lifetimes.fmt_lifetime(lifetime).to_uppercase()
+                ),
+                "the lifetime edges".into(),
+            ));
+        }
+        for (field, info) in ty.fields.iter().zip(&fields) {
+            members.push((info.name.to_string(), format!("field `{}`", field.name)));
+        }
+        self.check_member_names(members, &methods);
+
         // Non-out structs need to be constructible in Dart
         let default_constructor = if !is_out {
             if let Some(constructor) = methods
@@ -383,12 +470,19 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
         let mut visitor = method.borrowing_param_visitor(self.tcx);
 
-        let _guard = self.errors.set_context_method(
-            self.formatter.fmt_type_name_diagnostics(id),
-            method.name.as_str().into(),
-        );
+        let _guard = self
+            .errors
+            .set_context_method(
+                self.formatter.fmt_type_name_diagnostics(id),
+                method.name.as_str().into(),
+            )
+            .with_span(method.span);
 
         let c_method_name = self.formatter.fmt_c_method_name(id, method);
+        self.reserve_name(
+            &format!("_{c_method_name}"),
+            format_args!("the binding of method `{}`", method.name),
+        );
 
         let mut param_decls_dart = Vec::new();
         let mut param_types_ffi = Vec::new();
@@ -513,36 +607,74 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
         let params = param_decls_dart.join(", ");
 
-        let declaration = match &method.attrs.special_method {
-            Some(SpecialMethod::Constructor) => format!("factory {type_name}({params})"),
-            Some(SpecialMethod::NamedConstructor(name)) => format!(
-                "factory {type_name}.{}({params})",
-                self.formatter.fmt_constructor_name(name, method)
-            ),
-            Some(SpecialMethod::Getter(name)) => format!(
-                "{return_ty} get {}",
-                self.formatter.fmt_accessor_name(name, method)
-            ),
-            Some(SpecialMethod::Setter(name)) => format!(
-                "set {}({params})",
-                self.formatter.fmt_accessor_name(name, method)
+        let (name, declaration) = match &method.attrs.special_method {
+            Some(SpecialMethod::Constructor) => (None, format!("factory {type_name}({params})")),
+            Some(SpecialMethod::NamedConstructor(name)) => {
+                let name = self.formatter.fmt_constructor_name(name, method);
+                let declaration = format!("factory {type_name}.{name}({params})");
+                (Some(name), declaration)
+            }
+            Some(SpecialMethod::Getter(name)) => {
+                let name = self.formatter.fmt_accessor_name(name, method);
+                let declaration = format!("{return_ty} get {name}");
+                (Some(name), declaration)
+            }
+            Some(SpecialMethod::Setter(name)) => {
+                let name = self.formatter.fmt_accessor_name(name, method);
+                let declaration = format!("set {name}({params})");
+                // Setters are named apart from getters, which they come in pairs with
+                (Some(format!("{name}=")), declaration)
+            }
+            Some(SpecialMethod::Stringifier) => (
+                Some("toString".into()),
+                "@override\n  String toString()".into(),
             ),
-            Some(SpecialMethod::Stringifier) => "@override\n  String toString()".into(),
-            Some(SpecialMethod::Comparison) => format!("int compareTo({type_name} other)"),
-            Some(SpecialMethod::Iterator) => format!("{return_ty} _iteratorNext({params})"),
-            Some(SpecialMethod::Iterable) => format!("{return_ty} get iterator"),
-            Some(SpecialMethod::Indexer) => format!("{return_ty} operator []({params})"),
-            None if method.param_self.is_none() => format!(
-                "static {return_ty} {}({params})",
-                self.formatter.fmt_method_name(method)
+            Some(SpecialMethod::Comparison) => (
+                Some("compareTo".into()),
+                format!("int compareTo({type_name} other)"),
             ),
-            None => format!(
-                "{return_ty} {}({params})",
-                self.formatter.fmt_method_name(method)
+            Some(SpecialMethod::Iterator) => (
+                Some("_iteratorNext".into()),
+                format!("{return_ty} _iteratorNext({params})"),
             ),
+            Some(SpecialMethod::Iterable) => {
+                (Some("iterator".into()), format!("{return_ty} get iterator"))
+            }
+            Some(SpecialMethod::Indexer) => (None, format!("{return_ty} operator []({params})")),
+            None => {
+                let name = self.formatter.fmt_method_name(method);
+                let is_static = if method.param_self.is_none() {
+                    "static "
+                } else {
+                    ""
+                };
+                let declaration = format!("{is_static}{return_ty} {name}({params})");
+                (Some(name), declaration)
+            }
             Some(special) => unimplemented!("Found unknown special method type {special:?}"),
         };
 
+        let background = if method.attrs.background {
+            let background = self.gen_background_info(
+                method,
+                type_name,
+                &return_ty,
+                &c_method_name,
+                &param_conversions,
+            );
+            if background.is_none() {
+                self.errors.push_error(
+                    "`background` methods can only take and return primitives, enums and \
+                     immutably borrowed opaques, returning owned opaques, and cannot be special \
+                     methods other than constructors"
+                        .into(),
+                );
+            }
+            background
+        } else {
+            None
+        };
+
         let mut docs = self.formatter.fmt_docs(&method.docs);
 
         if let hir::ReturnType::Fallible(_, Some(e)) = &method.output {
@@ -557,6 +689,7 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         Some(MethodInfo {
             method,
             docs,
+            name,
             declaration,
             c_method_name,
             param_types_ffi,
@@ -570,9 +703,194 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             return_expression,
             lifetimes: &method.lifetime_env,
             method_lifetimes_map: visitor.borrow_map(),
+            background,
+        })
+    }
+
+    /// Generates the helper running a method marked `background` on a pooled isolate, if the
+    /// method can be called there.
+    ///
+    /// Only handles to opaques that are `Send` cross over, as addresses: the worker never wraps
+    /// or frees them, while the helper keeps their Dart objects alive until the call completes.
+    fn gen_background_info(
+        &mut self,
+        method: &'cx hir::Method,
+        type_name: &str,
+        return_ty: &str,
+        c_method_name: &str,
+        param_conversions: &[Cow<'cx, str>],
+    ) -> Option<BackgroundInfo> {
+        let tcx = self.tcx;
+        let sendable = |mutability, id| {
+            mutability != Some(hir::Mutability::Mutable) && !tcx.resolve_opaque(id).attrs.non_send
+        };
+
+        let mut worker_params = Vec::new();
+        let mut worker_args = Vec::new();
+        let mut args = Vec::new();
+        // `param_conversions` starts with that of the receiver
+        let mut conversions = param_conversions.iter();
+
+        if let Some(param_self) = method.param_self.as_ref() {
+            let conversion = conversions.next()?;
+            match param_self.ty {
+                SelfType::Opaque(ref op) if sendable(Some(op.owner.mutability), op.tcx_id) => {
+                    args.push(format!("{conversion}.address"));
+                    worker_args.push("ffi.Pointer.fromAddress(self)".into());
+                }
+                SelfType::Enum(_) => {
+                    args.push(conversion.to_string());
+                    worker_args.push("self".into());
+                }
+                _ => return None,
+            }
+            worker_params.push("int self".into());
+        }
+
+        for param in &method.params {
+            let name = self.formatter.fmt_param_name(param.name.as_str());
+            let conversion = conversions.next()?;
+            match param.ty {
+                Type::Primitive(prim) => {
+                    args.push(conversion.to_string());
+                    worker_args.push(name.to_string());
+                    worker_params.push(format!(
+                        "{} {name}",
+                        self.formatter.fmt_primitive_as_ffi(prim, true)
+                    ));
+                }
+                Type::Enum(_) => {
+                    args.push(conversion.to_string());
+                    worker_args.push(name.to_string());
+                    worker_params.push(format!("int {name}"));
+                }
+                Type::Opaque(ref op) if sendable(op.owner.mutability(), op.tcx_id) => {
+                    args.push(if op.is_optional() {
+                        format!("{name}?._ffi.address ?? 0")
+                    } else {
+                        format!("{conversion}.address")
+                    });
+                    worker_args.push(format!("ffi.Pointer.fromAddress({name})"));
+                    worker_params.push(format!("int {name}"));
+                }
+                _ => return None,
+            }
+        }
+
+        let run = format!(
+            "await _BackgroundPool.run(_{c_method_name}_background, [{}])",
+            args.join(", ")
+        );
+        let (worker_return, worker_result, call) = match method.output {
+            ReturnType::Infallible(SuccessType::Unit) => ("void".into(), "", format!("{run};")),
+            ReturnType::Infallible(SuccessType::OutType(Type::Primitive(prim))) => {
+                let ty = self.formatter.fmt_primitive_as_ffi(prim, true);
+                (ty.into(), "", format!("final result = {run} as {ty};"))
+            }
+            ReturnType::Infallible(SuccessType::OutType(Type::Enum(_))) => {
+                ("int".into(), "", format!("final result = {run} as int;"))
+            }
+            ReturnType::Infallible(SuccessType::OutType(Type::Opaque(ref op)))
+                if op.owner.is_owned() && sendable(None, op.tcx_id) =>
+            {
+                (
+                    "int".into(),
+                    ".address",
+                    format!("final result = ffi.Pointer<ffi.Opaque>.fromAddress({run} as int);"),
+                )
+            }
+            _ => return None,
+        };
+
+        let (name, link) = match method.attrs.special_method {
+            None => {
+                let name = self.formatter.fmt_method_name(method);
+                let link = name.clone();
+                (name, link)
+            }
+            Some(SpecialMethod::Constructor) => (
+                self.formatter.fmt_method_name(method),
+                format!("{type_name}.new"),
+            ),
+            Some(SpecialMethod::NamedConstructor(ref ctor)) => (
+                self.formatter.fmt_method_name(method),
+                format!(
+                    "{type_name}.{}",
+                    self.formatter.fmt_constructor_name(ctor, method)
+                ),
+            ),
+            _ => return None,
+        };
+        let params = method
+            .params
+            .iter()
+            .map(|param| {
+                format!(
+                    "{} {}",
+                    self.gen_type_name(&param.ty),
+                    self.formatter.fmt_param_name(param.name.as_str())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let is_static = if method.param_self.is_none() {
+            "static "
+        } else {
+            ""
+        };
+
+        self.helper_classes.insert(
+            "background".into(),
+            include_str!("../../templates/dart/background.dart").into(),
+        );
+        self.reserve_name(
+            &format!("_{c_method_name}_background"),
+            format_args!("the binding of method `{}`", method.name),
+        );
+
+        let name = format!("{name}InBackground");
+        Some(BackgroundInfo {
+            declaration: format!("{is_static}core.Future<{return_ty}> {name}({params})"),
+            name,
+            link,
+            call,
+            worker_return,
+            worker_params,
+            worker_args,
+            worker_result,
         })
     }
 
+    /// Reserves `name` in the library for `owner`, reporting a collision as an error
+    fn reserve_name(&self, name: &str, owner: impl Display) {
+        if let Err(e) = self.names.reserve(name, owner) {
+            self.errors.push_error(e);
+        }
+    }
+
+    /// Reserves the names declared in the scope of a class, the `members` generated for it and
+    /// its methods, reporting those that collide
+    fn check_member_names(&self, mut members: Vec<(String, String)>, methods: &[MethodInfo]) {
+        for m in methods {
+            let method = m.method.name.as_str();
+            if let Some(ref name) = m.name {
+                members.push((name.clone(), format!("method `{method}`")));
+            }
+            if let Some(ref background) = m.background {
+                members.push((
+                    background.name.clone(),
+                    format!("the background helper of method `{method}`"),
+                ));
+            }
+        }
+        let names = NameRegistry::default();
+        for (name, owner) in members {
+            if let Err(e) = names.reserve(&name, owner) {
+                self.errors.push_error(e);
+            }
+        }
+    }
+
     fn gen_special_method_info(
         &mut self,
         special_method_presence: &SpecialMethodPresence,
@@ -1177,6 +1495,8 @@ struct MethodInfo<'a> {
     method: &'a hir::Method,
     /// Docs
     docs: String,
+    /// The name the method declares in its class, which constructors and operators don't have
+    name: Option<String>,
     /// The declaration (everything before the parameter list)
     declaration: String,
     /// The C method name
@@ -1210,6 +1530,28 @@ struct MethodInfo<'a> {
     /// an internal slice View that was temporarily constructed, or
     /// a spread of a struct's `_fiellsForLifetimeFoo` getter.
     method_lifetimes_map: BTreeMap<Lifetime, BorrowedLifetimeInfo<'a>>,
+    /// For a method marked `background`, the helper running it on a pooled isolate
+    background: Option<BackgroundInfo>,
+}
+
+/// The helper running a method marked `background` on a pooled isolate, and the top-level
+/// function the isolate calls, which takes handles as addresses.
+struct BackgroundInfo {
+    /// The name of the helper
+    name: String,
+    /// The declaration of the helper, returning a future
+    declaration: String,
+    /// The method the helper runs, for its docs
+    link: String,
+    /// The statement sending the call to the pool, saving its result to `result`
+    call: String,
+    /// The return type of the worker function
+    worker_return: String,
+    worker_params: Vec<String>,
+    /// The arguments of the FFI function, from the worker parameters
+    worker_args: Vec<String>,
+    /// What turns the result of the FFI function into one that can be sent back
+    worker_result: &'static str,
 }
 
 struct SliceParam<'a> {
@@ -1303,3 +1645,37 @@ struct SpecialMethodGenInfo<'a> {
     /// Whether it is an iterable, and the type it iterates over
     iterable: Option<Cow<'a, str>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use diplomat_core::ast::{self, DocsUrlGenerator};
+    use diplomat_core::hir;
+    use quote::quote;
+
+    #[test]
+    fn test_name_collisions() {
+        let tokens = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Entry {
+                    key: u32,
+                    hash_code: u64,
+                }
+            }
+        };
+        let file = ast::File::from(&syn::parse2::<syn::File>(tokens).unwrap());
+        let validator = hir::BasicAttributeValidator::new("dart");
+        let tcx = hir::TypeContext::from_ast(&file.all_types(), validator).unwrap();
+        let docs_urls = DocsUrlGenerator::default();
+        let (_, errors) = super::run(&tcx, &docs_urls, Default::default());
+        let errors = errors.into_iter().map(|(_, e)| e).collect::<Vec<_>>();
+        // Fields are named in camel case
+        assert_eq!(
+            errors,
+            [
+                "Identifier `hashCode` generated for field `hash_code` collides with \
+              the equality implementation, please rename one of them"
+            ]
+        );
+    }
+}
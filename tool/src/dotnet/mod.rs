@@ -8,6 +8,7 @@ use colored::*;
 use diplomat_core::Env;
 
 use self::config::LibraryConfig;
+use crate::symbols::{SymbolRules, C_SYMBOLS};
 use crate::util::CodeWriter;
 use crate::util::SetOfAstTypes;
 
@@ -24,6 +25,12 @@ const INDENTATION: &str = "    ";
 const SCOPE_OPENING: &str = "{";
 const SCOPE_CLOSING: &str = "}";
 
+/// The `DllImport` declarations are named after the symbols, and C# limits identifiers to 511 characters
+pub(crate) const SYMBOLS: SymbolRules = SymbolRules {
+    max_length: Some(511),
+    ..C_SYMBOLS
+};
+
 pub fn gen_bindings(
     env: &Env,
     library_config_path: Option<&Path>,
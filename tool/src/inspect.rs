@@ -0,0 +1,720 @@
+//! `diplomat-tool inspect`, an interactive browser of the HIR the bridge is lowered to for
+//! each backend generating from it: its types and methods, their attributes and lifetimes,
+//! what each backend names them, and why one leaves them out.
+//!
+//! In a terminal it runs full screen, listing the types and methods next to the details of
+//! the selected one. When its output is not a terminal it reads commands from stdin instead,
+//! one per line, so that it also works when piped to.
+
+use crate::{lower, trace, HIR_BACKENDS};
+use console::{Key, Term};
+use diplomat_core::hir::{self, Attrs, ErrorAndContext, Method, TypeContext, TypeDef, TypeId};
+use diplomat_core::Env;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+const HELP: &str = "\
+Commands:
+  list             the types of the bridge
+  <Type>           a type, with its methods and what each backend names it
+  <Type>::<name>   a method, with how it is lowered and what each backend names it
+  /<text>          the types and methods whose name contains <text>, ignoring case
+  errors           the lowering errors of each backend
+  help             this message
+  quit             leave, as does the end of the input
+";
+
+const KEYS: &str = "\
+Keys:
+  up, down, k, j   select a type or method
+  home, end        select the first or last one
+  pgup, pgdn       scroll the details
+  /                search the names, ignoring case, until enter or esc
+  e                the lowering errors of each backend
+  ?                this message
+  esc              back to the selected type or method, or clear the search
+  q                leave
+";
+
+/// The bridge, lowered for each backend generating from the HIR
+pub struct Inspector {
+    lowerings: Vec<(&'static str, Result<TypeContext, Vec<ErrorAndContext>>)>,
+    target: Option<String>,
+}
+
+impl Inspector {
+    /// Lowers `env` for every backend, disabling the methods marked `platforms(..)` that
+    /// aren't available on `target`
+    pub fn new(env: &Env, target: Option<&str>) -> Self {
+        Inspector {
+            lowerings: HIR_BACKENDS
+                .iter()
+                .map(|backend| (*backend, lower(env, backend, target)))
+                .collect(),
+            target: target.map(Into::into),
+        }
+    }
+
+    /// Runs one command, returning what it prints, or `None` to quit
+    pub fn command(&self, line: &str) -> Option<String> {
+        let line = line.trim();
+        Some(match line {
+            "quit" | "q" | "exit" => return None,
+            "help" | "?" => HELP.into(),
+            "" | "list" => self.list(),
+            "errors" => self.errors(),
+            _ => {
+                if let Some(text) = line.strip_prefix('/') {
+                    self.search(text.trim())
+                } else if let Some((ty, method)) = line.split_once("::") {
+                    self.show_method(ty, method)
+                } else {
+                    self.show_type(line)
+                }
+            }
+        })
+    }
+
+    /// The HIR types and methods are the same for every backend that lowered the bridge,
+    /// only their attributes differ, so any of them is browsed
+    fn tcx(&self) -> Option<&TypeContext> {
+        self.lowerings
+            .iter()
+            .find_map(|(_, lowering)| lowering.as_ref().ok())
+    }
+
+    fn list(&self) -> String {
+        let Some(tcx) = self.tcx() else {
+            return "No backend could lower the bridge, see `errors`\n".into();
+        };
+        let mut out = String::new();
+        for (_, ty) in tcx.all_types() {
+            writeln!(
+                out,
+                "{} `{}`, {} methods",
+                describe_kind(ty),
+                ty.name(),
+                ty.methods().len()
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    fn errors(&self) -> String {
+        let mut out = String::new();
+        for (backend, lowering) in &self.lowerings {
+            match lowering {
+                Ok(_) => writeln!(out, "{backend}: lowered").unwrap(),
+                Err(errors) => {
+                    writeln!(out, "{backend}: {} errors", errors.len()).unwrap();
+                    for (ctx, err) in errors {
+                        writeln!(out, "  in {ctx}: {err}").unwrap();
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn search(&self, text: &str) -> String {
+        let Some(tcx) = self.tcx() else {
+            return "No backend could lower the bridge, see `errors`\n".into();
+        };
+        let text = text.to_lowercase();
+        let mut out = String::new();
+        for (_, ty) in tcx.all_types() {
+            if ty.name().as_str().to_lowercase().contains(&text) {
+                writeln!(out, "{}", ty.name()).unwrap();
+            }
+            for method in ty.methods() {
+                if method.name.as_str().to_lowercase().contains(&text) {
+                    writeln!(out, "{}::{}", ty.name(), method.name).unwrap();
+                }
+            }
+        }
+        if out.is_empty() {
+            out = format!("Nothing is named like `{text}`\n");
+        }
+        out
+    }
+
+    fn show_type(&self, name: &str) -> String {
+        let Some(tcx) = self.tcx() else {
+            return self.unlowered(name);
+        };
+        let Some((_, ty)) = tcx.all_types().find(|(_, ty)| ty.name().as_str() == name) else {
+            return format!("No type is named `{name}`, see `list`\n");
+        };
+        let mut out = format!("{} `{name}`\n", describe_kind(ty));
+        describe_attrs(ty.attrs(), &mut out);
+        let members = match ty {
+            TypeDef::Struct(st) => Some(("fields", st.fields.iter().map(|f| &f.name).collect())),
+            TypeDef::OutStruct(st) => Some(("fields", st.fields.iter().map(|f| &f.name).collect())),
+            TypeDef::Enum(e) => Some(("variants", e.variants.iter().map(|v| &v.name).collect())),
+            _ => None::<(&str, Vec<_>)>,
+        };
+        if let Some((kind, names)) = members {
+            let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+            writeln!(out, "  {kind}: {}", names.join(", ")).unwrap();
+        }
+        let methods = ty.methods().iter().map(|m| m.name.as_str());
+        writeln!(out, "  methods: {}", methods.collect::<Vec<_>>().join(", ")).unwrap();
+
+        out.push_str("  generated as:\n");
+        for (backend, lowering) in &self.lowerings {
+            let rendered = match lowering {
+                Err(errors) => describe_failure(errors, name),
+                Ok(tcx) => {
+                    let (id, ty) = find_type(tcx, name);
+                    if ty.attrs().disable {
+                        "not generated, disabled".into()
+                    } else {
+                        format!("`{}`", render_type_name(tcx, backend, id, ty))
+                    }
+                }
+            };
+            writeln!(out, "    {backend}: {rendered}").unwrap();
+        }
+        out
+    }
+
+    fn show_method(&self, ty_name: &str, name: &str) -> String {
+        let path = format!("{ty_name}::{name}");
+        let Some(tcx) = self.tcx() else {
+            return self.unlowered(&path);
+        };
+        let Some((_, ty)) = tcx
+            .all_types()
+            .find(|(_, ty)| ty.name().as_str() == ty_name)
+        else {
+            return format!("No type is named `{ty_name}`, see `list`\n");
+        };
+        let Some(method) = ty.methods().iter().find(|m| m.name.as_str() == name) else {
+            return format!("`{ty_name}` has no method `{name}`, see `{ty_name}`\n");
+        };
+
+        let mut out = format!("method `{path}`\n");
+        describe_attrs(&method.attrs, &mut out);
+        if method.is_async {
+            out.push_str("  async\n");
+        }
+        out.push_str("  lowered as:\n");
+        let mut lowering = String::new();
+        trace::trace_method(tcx, method, &mut lowering);
+        for line in lowering.lines() {
+            writeln!(out, "  {line}").unwrap();
+        }
+
+        out.push_str("  generated as:\n");
+        for (backend, lowering) in &self.lowerings {
+            let rendered = match lowering {
+                Err(errors) => describe_failure(errors, &path),
+                Ok(tcx) => {
+                    let (id, ty) = find_type(tcx, ty_name);
+                    let method = ty.methods().iter().find(|m| m.name.as_str() == name);
+                    let method = method.expect("backends lower the same methods");
+                    if ty.attrs().disable {
+                        "not generated, its type is disabled".into()
+                    } else if method.attrs.disable {
+                        self.describe_disabled(method)
+                    } else {
+                        format!("`{}`", render_method_name(tcx, backend, id, method))
+                    }
+                }
+            };
+            writeln!(out, "    {backend}: {rendered}").unwrap();
+        }
+        out
+    }
+
+    /// Why a disabled method is, as far as its attributes tell
+    fn describe_disabled(&self, method: &Method) -> String {
+        match self.target {
+            Some(ref target)
+                if !method.attrs.platforms.is_empty()
+                    && !method
+                        .attrs
+                        .platforms
+                        .iter()
+                        .any(|platform| hir::is_platform_of_target(platform, target)) =>
+            {
+                format!("not generated, not available on {target}")
+            }
+            _ => "not generated, disabled".into(),
+        }
+    }
+
+    /// The errors of `path` in every backend, when none could lower the bridge
+    fn unlowered(&self, path: &str) -> String {
+        let mut out = format!("No backend could lower the bridge, for `{path}`:\n");
+        for (backend, lowering) in &self.lowerings {
+            if let Err(errors) = lowering {
+                writeln!(out, "  {backend}: {}", describe_failure(errors, path)).unwrap();
+            }
+        }
+        out
+    }
+}
+
+/// The state of the full screen browser: the types and methods matching the search, and
+/// what the inspector shows for the one selected
+struct Browser<'a> {
+    inspector: &'a Inspector,
+    /// Every type and method, as `Type` or `Type::method`, in the order of the bridge
+    paths: Vec<String>,
+    search: String,
+    /// Whether the search is being typed
+    searching: bool,
+    /// The index of the selected path among those matching the search
+    selected: usize,
+    /// What the details show in place of the selected path, like the errors or the keys
+    page: Option<String>,
+    /// The lines of the details scrolled past
+    scroll: usize,
+}
+
+impl<'a> Browser<'a> {
+    fn new(inspector: &'a Inspector) -> Self {
+        let mut paths = Vec::new();
+        if let Some(tcx) = inspector.tcx() {
+            for (_, ty) in tcx.all_types() {
+                paths.push(ty.name().to_string());
+                for method in ty.methods() {
+                    paths.push(format!("{}::{}", ty.name(), method.name));
+                }
+            }
+        }
+        Browser {
+            inspector,
+            page: paths.is_empty().then(|| inspector.errors()),
+            paths,
+            search: String::new(),
+            searching: false,
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    fn matches(&self) -> Vec<&str> {
+        let search = self.search.to_lowercase();
+        self.paths
+            .iter()
+            .filter(|path| path.to_lowercase().contains(&search))
+            .map(|path| &**path)
+            .collect()
+    }
+
+    /// What the right of the screen shows, scrolled to the top
+    fn details(&self) -> String {
+        match (&self.page, self.matches().get(self.selected)) {
+            (Some(page), _) => page.clone(),
+            (None, Some(path)) => self.inspector.command(path).unwrap_or_default(),
+            (None, None) => format!("Nothing is named like `{}`\n", self.search),
+        }
+    }
+
+    fn select(&mut self, selected: usize) {
+        self.selected = selected;
+        self.page = None;
+        self.scroll = 0;
+    }
+
+    /// Handles a key press, returning whether to keep browsing
+    fn handle(&mut self, key: Key) -> bool {
+        let last = self.matches().len().saturating_sub(1);
+        if self.searching {
+            match key {
+                Key::Char(c) => self.search.push(c),
+                Key::Backspace => {
+                    self.search.pop();
+                }
+                Key::Enter => self.searching = false,
+                Key::Escape => {
+                    self.search.clear();
+                    self.searching = false;
+                }
+                Key::CtrlC => return false,
+                _ => return true,
+            }
+            self.select(0);
+            return true;
+        }
+        match key {
+            Key::Char('q') | Key::CtrlC => return false,
+            Key::ArrowUp | Key::Char('k') => self.select(self.selected.saturating_sub(1)),
+            Key::ArrowDown | Key::Char('j') => self.select((self.selected + 1).min(last)),
+            Key::Home => self.select(0),
+            Key::End => self.select(last),
+            Key::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            Key::PageDown => {
+                let lines = self.details().lines().count();
+                self.scroll = (self.scroll + 10).min(lines.saturating_sub(1));
+            }
+            Key::Char('/') => self.searching = true,
+            Key::Char('e') => {
+                self.page = Some(self.inspector.errors());
+                self.scroll = 0;
+            }
+            Key::Char('?') => {
+                self.page = Some(KEYS.into());
+                self.scroll = 0;
+            }
+            Key::Escape if self.page.is_some() => self.select(self.selected),
+            Key::Escape if !self.search.is_empty() => {
+                self.search.clear();
+                self.select(0);
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// The lines of a frame `width` columns wide and `height` rows high: the list, the details
+    /// and a status line
+    fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let matches = self.matches();
+        let rows = height.saturating_sub(1);
+        // Methods are listed under their type, or with it when the search leaves it out
+        let mut ty = "";
+        let labels = matches
+            .iter()
+            .map(|path| match path.split_once("::") {
+                Some((method_ty, method)) if method_ty == ty => format!("  {method}"),
+                _ => {
+                    ty = path.split("::").next().unwrap_or_default();
+                    path.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        let list_width = labels
+            .iter()
+            .map(|label| label.chars().count() + 2)
+            .max()
+            .unwrap_or(0)
+            .min(width / 3);
+
+        let details = self.details();
+        let details = details.lines().skip(self.scroll).collect::<Vec<_>>();
+
+        // Scroll the list just enough to show the selected path
+        let first = (self.selected + 1).saturating_sub(rows);
+        let mut frame = Vec::with_capacity(height);
+        for row in 0..rows {
+            let label = match labels.get(first + row) {
+                Some(label) if first + row == self.selected => format!("> {label}"),
+                Some(label) => format!("  {label}"),
+                None => String::new(),
+            };
+            let mut line = truncate(&label, list_width);
+            line.extend((line.chars().count()..list_width).map(|_| ' '));
+            line.push_str(" | ");
+            line.push_str(details.get(row).copied().unwrap_or_default());
+            frame.push(truncate(line.trim_end(), width));
+        }
+        let status = if self.searching {
+            format!("/{}", self.search)
+        } else {
+            format!(
+                "{} of {} | / search, e errors, ? keys, q quit",
+                matches.len().min(self.selected + 1),
+                matches.len(),
+            )
+        };
+        frame.push(truncate(&status, width));
+        frame
+    }
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+/// Browses the bridge interactively, full screen in a terminal, otherwise reading commands
+/// from stdin until `quit` or its end
+pub fn run(env: &Env, target: Option<&str>) -> std::io::Result<()> {
+    let inspector = Inspector::new(env, target);
+    let term = Term::stdout();
+    if !term.is_term() {
+        return read_commands(&inspector);
+    }
+
+    // The alternate screen leaves the shell as it was once done
+    term.write_str("\x1b[?1049h")?;
+    term.hide_cursor()?;
+    let result = browse(&mut Browser::new(&inspector), &term);
+    term.show_cursor()?;
+    term.write_str("\x1b[?1049l")?;
+    result
+}
+
+fn browse(browser: &mut Browser, term: &Term) -> std::io::Result<()> {
+    loop {
+        let (rows, columns) = term.size();
+        let frame = browser.render(columns.into(), rows.into());
+        term.write_str(&format!("\x1b[H{}\x1b[J", frame.join("\x1b[K\n")))?;
+        term.flush()?;
+        if !browser.handle(term.read_key_raw()?) {
+            return Ok(());
+        }
+    }
+}
+
+fn read_commands(inspector: &Inspector) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "{}\n{HELP}", inspector.list())?;
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            writeln!(stdout)?;
+            return Ok(());
+        }
+        match inspector.command(&line) {
+            Some(output) => write!(stdout, "{output}")?,
+            None => return Ok(()),
+        }
+    }
+}
+
+fn find_type<'tcx>(tcx: &'tcx TypeContext, name: &str) -> (TypeId, TypeDef<'tcx>) {
+    tcx.all_types()
+        .find(|(_, ty)| ty.name().as_str() == name)
+        .expect("backends lower the same types")
+}
+
+fn describe_kind(ty: TypeDef) -> &'static str {
+    match ty {
+        TypeDef::Struct(_) => "struct",
+        TypeDef::OutStruct(_) => "output-only struct",
+        TypeDef::Opaque(_) => "opaque",
+        TypeDef::Enum(_) => "enum",
+        _ => "type",
+    }
+}
+
+/// Lists the attributes that are set, as the first backend lowered them
+fn describe_attrs(attrs: &Attrs, out: &mut String) {
+    if let Some(id) = attrs.stable_id {
+        writeln!(out, "  stable id: {id}").unwrap();
+    }
+    let mut set = Vec::new();
+    if let Some(ref namespace) = attrs.namespace {
+        set.push(format!("namespace = {namespace:?}"));
+    }
+    if let Some(ref special) = attrs.special_method {
+        set.push(format!("{special:?}"));
+    }
+    let flags = [
+        (attrs.featured, "featured"),
+        (attrs.parent_managed, "parent_managed"),
+        (attrs.non_send, "non_send"),
+        (attrs.with_warnings, "with_warnings"),
+        (attrs.jni, "jni"),
+        (attrs.background, "background"),
+    ];
+    set.extend(
+        flags
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, f)| f.to_string()),
+    );
+    if !attrs.platforms.is_empty() {
+        set.push(format!("platforms({})", attrs.platforms.join(", ")));
+    }
+    if !set.is_empty() {
+        writeln!(out, "  attributes: {}", set.join(", ")).unwrap();
+    }
+}
+
+/// The lowering errors of `path` and its members in a backend that failed to lower the
+/// bridge
+fn describe_failure(errors: &[ErrorAndContext], path: &str) -> String {
+    let own = errors
+        .iter()
+        .filter(|(ctx, _)| {
+            let ctx = ctx.to_string();
+            ctx == path || ctx.starts_with(&format!("{path}::"))
+        })
+        .map(|(_, err)| err.to_string())
+        .collect::<Vec<_>>();
+    if own.is_empty() {
+        format!(
+            "not generated, the backend failed to lower {} other items",
+            errors.len()
+        )
+    } else {
+        format!("not generated, {}", own.join("; "))
+    }
+}
+
+/// The name `backend` gives a type: its C name for the C backend, which doesn't rename types
+fn render_type_name(tcx: &TypeContext, backend: &str, id: TypeId, ty: TypeDef) -> String {
+    if backend == "c2" {
+        crate::c2::CFormatter::new(tcx).fmt_type_name(id).into()
+    } else {
+        ty.attrs().rename.apply(ty.name().as_str().into()).into()
+    }
+}
+
+/// The name `backend` gives a method: the symbol it is exported as for the C backend
+fn render_method_name(tcx: &TypeContext, backend: &str, id: TypeId, method: &Method) -> String {
+    if backend == "c2" {
+        crate::c2::CFormatter::new(tcx).fmt_method_name(id, method)
+    } else {
+        let name = method.attrs.rename.apply(method.name.as_str().into());
+        match method.attrs.special_method {
+            Some(ref special) => format!("{name}, as {special:?}"),
+            None => name.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Browser, Inspector};
+    use console::Key;
+    use diplomat_core::ast;
+    use quote::quote;
+
+    #[test]
+    fn test_inspect() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(kotlin, rename = "KLocale")]
+                struct Locale(String);
+
+                pub enum Direction {
+                    Ltr,
+                    Rtl,
+                }
+
+                impl Locale {
+                    #[diplomat::attr(dart, constructor)]
+                    pub fn new(name: &DiplomatStr) -> Option<Box<Locale>> {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(not(c2), disable)]
+                    pub fn direction(&self) -> Direction {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(*, platforms(wasm))]
+                    pub fn from_browser() -> Box<Locale> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item ");
+        let env = ast::File::from(&item).all_types();
+        let inspector = Inspector::new(&env, Some("x86_64-unknown-linux-gnu"));
+
+        let mut out = String::new();
+        for command in [
+            "list",
+            "Locale",
+            "Locale::new",
+            "Locale::direction",
+            "Locale::from_browser",
+            "/DIR",
+            "Locale::missing",
+        ] {
+            out.push_str(&format!("> {command}\n"));
+            out.push_str(&inspector.command(command).unwrap());
+        }
+        assert!(inspector.command("quit").is_none());
+        insta::assert_snapshot!(out);
+    }
+
+    #[test]
+    fn test_browser() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Locale(String);
+
+                pub enum Direction {
+                    Ltr,
+                    Rtl,
+                }
+
+                impl Locale {
+                    pub fn new(name: &DiplomatStr) -> Option<Box<Locale>> {
+                        unimplemented!()
+                    }
+
+                    pub fn direction(&self) -> Direction {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item ");
+        let env = ast::File::from(&item).all_types();
+        let inspector = Inspector::new(&env, None);
+        let mut browser = Browser::new(&inspector);
+
+        let mut out = String::new();
+        for keys in [
+            &[][..],
+            &[Key::ArrowDown, Key::ArrowDown],
+            &[Key::PageDown],
+            &[
+                Key::Char('/'),
+                Key::Char('D'),
+                Key::Char('i'),
+                Key::Char('r'),
+            ],
+            &[Key::Enter, Key::ArrowDown],
+            &[Key::Escape, Key::Char('e')],
+        ] {
+            for key in keys {
+                assert!(browser.handle(key.clone()));
+            }
+            out.push_str(&format!("{keys:?}\n"));
+            for line in browser.render(72, 10) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        assert!(!browser.handle(Key::Char('q')));
+        insta::assert_snapshot!(out);
+    }
+
+    #[test]
+    fn test_inspect_lowering_errors() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    #[diplomat::attr(*, jni)]
+                    pub fn get(&self) -> u32 {
+                        self.0
+                    }
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item ");
+        let env = ast::File::from(&item).all_types();
+        let inspector = Inspector::new(&env, None);
+
+        // Only the Kotlin backend supports `jni`, the others fail to lower the bridge
+        let method = inspector.command("Counter::get").unwrap();
+        assert!(method.contains("    kotlin: `get`\n"));
+        assert!(method.contains("    koka: not generated, `jni` not supported in backend koka\n"));
+        let errors = inspector.command("errors").unwrap();
+        assert!(errors.contains("kotlin: lowered\n"));
+        assert!(errors.contains("dart: 1 errors\n  in Counter::get: `jni` not supported"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Helps keep the bindings within a size budget: [`reachable_types`] prunes the types an
+//! application does not use, and [`size_report`] lists what each remaining type weighs.
+
+use super::Imports;
+use diplomat_core::{ast, Env};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// The file the report is written to
+pub const SIZE_REPORT_FILE: &str = "bundle-size.md";
+
+/// The modules every type's module imports
+const RUNTIME_MODULES: &[&str] = &["diplomat-runtime.mjs", "diplomat-wasm.mjs"];
+
+/// The names of the types used by the entry types, directly or not, including the entry types
+/// themselves. Errors with the entry types that don't exist.
+pub fn reachable_types<'env>(
+    all_types: &[(ast::Path, &'env ast::CustomType)],
+    env: &'env Env,
+    entry_types: &[String],
+) -> Result<BTreeSet<&'env str>, Vec<String>> {
+    let by_name = all_types
+        .iter()
+        .map(|(path, ty)| (ty.name().as_str(), (path, *ty)))
+        .collect::<HashMap<_, _>>();
+    let unknown = entry_types
+        .iter()
+        .filter(|name| !by_name.contains_key(name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(unknown);
+    }
+
+    let mut reachable = BTreeSet::new();
+    let mut queue = entry_types
+        .iter()
+        .map(|name| by_name[name.as_str()])
+        .collect::<Vec<_>>();
+    while let Some((path, ty)) = queue.pop() {
+        if !reachable.insert(ty.name().as_str()) {
+            continue;
+        }
+        let imports = Imports::new(ty, path, env);
+        for used in imports.js_imports.iter().chain(&imports.ts_imports) {
+            queue.push(by_name[used.name().as_str()]);
+        }
+    }
+    Ok(reachable)
+}
+
+/// A Markdown table of the size of each type's modules, with the modules they import, and
+/// whether bundlers can drop the type when the application does not use it
+pub fn size_report(
+    all_types: &[(ast::Path, &ast::CustomType)],
+    env: &Env,
+    outs: &HashMap<String, String>,
+) -> String {
+    let size = |file: &str| outs.get(file).map_or(0, String::len);
+    let runtime_size = RUNTIME_MODULES.iter().map(|file| size(file)).sum::<usize>();
+
+    let mut report = String::new();
+    writeln!(report, "# Bundle size").unwrap();
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "The size in bytes of the modules of each type, before minification. \"With imports\" \
+         adds the modules of the types it imports, but not the {runtime_size} bytes of \
+         `{}` that every type imports.",
+        RUNTIME_MODULES.join("` and `")
+    )
+    .unwrap();
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "Types whose module creates a `FinalizationRegistry` when loaded are kept by bundlers \
+         once imported, even unused; the others are tree-shakeable."
+    )
+    .unwrap();
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "| Type | `.mjs` | `.d.ts` | With imports | Tree-shakeable |"
+    )
+    .unwrap();
+    writeln!(report, "|---|---:|---:|---:|---|").unwrap();
+
+    let by_name = all_types
+        .iter()
+        .map(|(path, ty)| (ty.name().as_str(), (path, *ty)))
+        .collect::<HashMap<_, _>>();
+    for (path, ty) in all_types {
+        let name = ty.name().as_str();
+
+        // The modules loaded along with the type's, following the imports of the `.mjs` files
+        let mut loaded = BTreeSet::new();
+        let mut queue = vec![(path, *ty)];
+        while let Some((path, ty)) = queue.pop() {
+            if !loaded.insert(ty.name().as_str()) {
+                continue;
+            }
+            for used in Imports::new(ty, path, env).js_imports {
+                queue.push(by_name[used.name().as_str()]);
+            }
+        }
+        let with_imports = loaded
+            .iter()
+            .map(|name| size(&format!("{name}.mjs")))
+            .sum::<usize>();
+
+        let tree_shakeable = match ty {
+            ast::CustomType::Opaque(_) => "no, registers destructors",
+            _ => "yes",
+        };
+        writeln!(
+            report,
+            "| `{name}` | {} | {} | {with_imports} | {tree_shakeable} |",
+            size(&format!("{name}.mjs")),
+            size(&format!("{name}.d.ts")),
+        )
+        .unwrap();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reachable_types, size_report};
+    use crate::util;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_reachable_types() {
+        let parsed: syn::File = syn::parse_quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Parser;
+
+                #[diplomat::opaque]
+                struct Unused;
+
+                pub enum Mode {
+                    Strict,
+                }
+
+                pub struct Options {
+                    mode: Mode,
+                }
+
+                impl Parser {
+                    pub fn new(options: Options) -> Box<Parser> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let file = diplomat_core::ast::File::from(&parsed);
+        let env = file.all_types();
+        let all_types = util::get_all_custom_types(&env)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let reachable = reachable_types(&all_types, &env, &["Parser".into()]).unwrap();
+        assert_eq!(
+            reachable.into_iter().collect::<Vec<_>>(),
+            ["Mode", "Options", "Parser"]
+        );
+        assert_eq!(
+            reachable_types(&all_types, &env, &["Parsr".into()]).unwrap_err(),
+            ["Parsr"]
+        );
+
+        let mut outs = HashMap::new();
+        crate::js::gen_bindings(
+            &env,
+            None,
+            &mut outs,
+            None,
+            crate::js::DEFAULT_POINTER_WIDTH,
+        )
+        .unwrap();
+        let report = size_report(&all_types, &env, &outs);
+        let parser_row = report
+            .lines()
+            .find(|line| line.starts_with("| `Parser`"))
+            .unwrap();
+        let parser_size = outs["Parser.mjs"].len();
+        // Parameters are converted inline, but enums through their module
+        let with_imports = parser_size + outs["Mode.mjs"].len();
+        assert!(parser_row.contains(&format!("| {parser_size} |")));
+        assert!(parser_row.contains(&format!("| {with_imports} |")));
+        assert!(parser_row.ends_with("| no, registers destructors |"));
+        assert!(report.contains("| `Mode` |"));
+    }
+}
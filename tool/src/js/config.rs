@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+/// The options of the JS backend, read from the library config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LibraryConfig {
+    /// Whether to write `bundle-size.md`, listing the size of each type's modules and whether
+    /// bundlers can drop the type when it goes unused
+    pub size_report: bool,
+    /// The types to generate, along with the types their fields and methods use. Every type
+    /// is generated when empty.
+    pub entry_types: Vec<String>,
+}
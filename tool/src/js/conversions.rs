@@ -22,11 +22,10 @@ use diplomat_core::{ast, Env};
 use displaydoc::Display;
 use std::collections::BTreeMap;
 use std::fmt::{self, Write as _};
-use std::num::NonZeroUsize;
 
 use super::display;
 use super::types::{return_type_form, ReturnTypeForm};
-use crate::layout;
+use crate::layout::{self, PointerWidth};
 
 /// An [`fmt::Display`] type representing a disambiguated binding to a native JS object.
 ///
@@ -112,6 +111,7 @@ pub fn gen_value_js_to_rust<'env>(
     lifetime_env: &ast::LifetimeEnv,
     borrowed_current_to_root: &BTreeMap<&ast::NamedLifetime, &'env ast::NamedLifetime>,
     entries: &mut BTreeMap<&'env ast::NamedLifetime, Vec<Argument<'env>>>,
+    width: PointerWidth,
 ) {
     match typ {
         ast::TypeName::StrReference(..) | ast::TypeName::PrimitiveSlice(..) => {
@@ -171,6 +171,12 @@ pub fn gen_value_js_to_rust<'env>(
                 "diplomatRuntime.extractCodePoint({param_name}, '{param_name}')"
             ));
         }
+        ast::TypeName::Primitive(ast::PrimitiveType::isize | ast::PrimitiveType::usize)
+            if width == PointerWidth::Bits64 =>
+        {
+            // wasm64 exports take them as BigInts, but they are numbers in JS like on wasm32
+            invocation_params.push(format!("BigInt({param_name})"));
+        }
         ast::TypeName::Box(..) | ast::TypeName::Reference(..) => {
             invocation_params.push(format!("{param_name}.underlying"));
 
@@ -218,6 +224,7 @@ pub fn gen_value_js_to_rust<'env>(
                             &struct_type.lifetimes,
                             &borrowed_current_to_root,
                             entries,
+                            width,
                         );
                     }
                 }
@@ -234,8 +241,22 @@ pub fn gen_value_js_to_rust<'env>(
     }
 }
 
-/// Type alias for readability.
-type Offset = Option<NonZeroUsize>;
+/// An [`fmt::Display`] type representing a `usize` as WASM exports take it, which
+/// is a BigInt on wasm64 like the pointers it is added to.
+#[derive(Copy, Clone)]
+pub struct UsizeLiteral(pub usize, pub PointerWidth);
+
+impl fmt::Display for UsizeLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            PointerWidth::Bits32 => write!(f, "{}", self.0),
+            PointerWidth::Bits64 => write!(f, "{}n", self.0),
+        }
+    }
+}
+
+/// Type alias for readability, a nonzero offset into a buffer.
+type Offset = Option<UsizeLiteral>;
 
 /// An [`fmt::Display`] type representing an invocation of a WASM function.
 pub struct Invocation {
@@ -293,6 +314,9 @@ pub struct Base<'base> {
 
     /// Bindings that the value being created borrow from.
     pub borrows: &'base [Argument<'base>],
+
+    /// Width of pointers in the WASM module.
+    pub width: PointerWidth,
 }
 
 impl<'base> Base<'base> {
@@ -306,10 +330,14 @@ impl<'base> Base<'base> {
         return_type_form(typ, self.in_path, self.env)
     }
 
-    /// Returns the size and align of a given [`ast::TypeName`].
-    fn size_align(&self, typ: &ast::TypeName) -> (usize, usize) {
-        let layout = layout::type_size_alignment(typ, self.in_path, self.env);
-        (layout.size(), layout.align())
+    /// Returns the size and align of a given [`ast::TypeName`], as passed to
+    /// `diplomat_alloc` and `diplomat_free`.
+    fn size_align(&self, typ: &ast::TypeName) -> (UsizeLiteral, UsizeLiteral) {
+        let layout = layout::type_size_alignment(typ, self.in_path, self.env, self.width);
+        (
+            UsizeLiteral(layout.size(), self.width),
+            UsizeLiteral(layout.align(), self.width),
+        )
     }
 
     /// Returns the size, align, and ok-offset of a `DiplomatResult` containing
@@ -318,10 +346,36 @@ impl<'base> Base<'base> {
         &self,
         ok: &ast::TypeName,
         err: &ast::TypeName,
-    ) -> (usize, (usize, usize)) {
+    ) -> (usize, (UsizeLiteral, UsizeLiteral)) {
         let (ok_offset, layout) =
-            layout::result_ok_offset_size_align(ok, err, self.in_path, self.env);
-        (ok_offset, (layout.size(), layout.align()))
+            layout::result_ok_offset_size_align(ok, err, self.in_path, self.env, self.width);
+        (
+            ok_offset,
+            (
+                UsizeLiteral(layout.size(), self.width),
+                UsizeLiteral(layout.align(), self.width),
+            ),
+        )
+    }
+
+    /// Returns the [`JsPrimitive`] a given [`ast::PrimitiveType`] is read as.
+    fn js_primitive(&self, prim: &ast::PrimitiveType) -> JsPrimitive {
+        JsPrimitive::new(prim, self.width)
+    }
+
+    /// Returns the kind of number that pointers and the lengths of slices are read as.
+    fn usize_number(&self) -> JsPrimitiveNumber {
+        match self.width {
+            PointerWidth::Bits32 => JsPrimitiveNumber::Uint32,
+            PointerWidth::Bits64 => JsPrimitiveNumber::BigUint64,
+        }
+    }
+
+    /// Whether the given primitive is read as a BigInt that has to be converted into
+    /// the number it is in JS, as `isize` and `usize` are on wasm64.
+    fn is_big_usize(&self, prim: &ast::PrimitiveType) -> bool {
+        self.width == PointerWidth::Bits64
+            && matches!(prim, ast::PrimitiveType::isize | ast::PrimitiveType::usize)
     }
 }
 
@@ -398,6 +452,16 @@ pub struct InvocationIntoJs<'base> {
 impl fmt::Display for InvocationIntoJs<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.typ {
+            // bools and chars cross the boundary as a `u8` and a `u32` respectively
+            ast::TypeName::Primitive(ast::PrimitiveType::bool) => {
+                write!(f, "{} == 1", self.invocation.scalar())
+            }
+            ast::TypeName::Primitive(ast::PrimitiveType::char) => {
+                write!(f, "String.fromCodePoint({})", self.invocation.scalar())
+            }
+            ast::TypeName::Primitive(prim) if self.base.is_big_usize(prim) => {
+                write!(f, "Number({})", self.invocation.scalar())
+            }
             ast::TypeName::Primitive(..) | ast::TypeName::Ordering => self.invocation.scalar().fmt(f),
             ast::TypeName::Named(path_type) | ast::TypeName::SelfType(path_type) => {
                 match self.base.resolve_type(path_type) {
@@ -559,7 +623,7 @@ impl fmt::Display for InvocationIntoJs<'_> {
             ast::TypeName::StrReference(_, ast::StringEncoding::UnvalidatedUtf8 | ast::StringEncoding::Utf8) => self.display_slice(SliceKind::Str).fmt(f),
             ast::TypeName::StrReference(_, ast::StringEncoding::UnvalidatedUtf16) => self.display_slice(SliceKind::Str16).fmt(f),
             ast::TypeName::PrimitiveSlice(.., prim) => {
-                self.display_slice(SliceKind::Primitive(prim.into())).fmt(f)
+                self.display_slice(SliceKind::Primitive(self.base.js_primitive(prim))).fmt(f)
             }
             ast::TypeName::Writeable => todo!(),
             ast::TypeName::Unit => self.invocation.scalar().fmt(f),
@@ -586,7 +650,7 @@ impl SliceKind {
                     // TODO(#383): can we borrow this?
                     write!(
                         f,
-                        "{num}Array.from(new {num}Array(wasm.memory.buffer, ptr, size))"
+                        "diplomatRuntime.readNumbers(wasm, {ptr}, {size}, \"{num}\")"
                     )
                 }
                 JsPrimitive::Bool => todo!("Handle returning `&[bool]`."),
@@ -608,7 +672,11 @@ impl InvocationIntoJs<'_> {
                 "const {diplomat_receive_buffer} = wasm.diplomat_alloc({size}, {align});"
             )?;
             writeln!(f, "{};", self.invocation.complex(&diplomat_receive_buffer))?;
-            writeln!(f, "const [{ptr_ident}, {size_ident}] = new Uint32Array(wasm.memory.buffer, {diplomat_receive_buffer}, 2);")?;
+            writeln!(
+                f,
+                "const [{ptr_ident}, {size_ident}] = diplomatRuntime.readNumbers(wasm, {diplomat_receive_buffer}, 2, \"{}\");",
+                self.base.usize_number()
+            )?;
             writeln!(
                 f,
                 "wasm.diplomat_free({diplomat_receive_buffer}, {size}, {align});"
@@ -693,8 +761,9 @@ enum JsPrimitive {
     Char,
 }
 
-impl From<&ast::PrimitiveType> for JsPrimitive {
-    fn from(prim: &ast::PrimitiveType) -> Self {
+impl JsPrimitive {
+    /// The JS primitive of a Rust primitive on a target of the given width.
+    fn new(prim: &ast::PrimitiveType, width: PointerWidth) -> Self {
         match prim {
             ast::PrimitiveType::i8 => JsPrimitive::Number(JsPrimitiveNumber::Int8),
             ast::PrimitiveType::u8 | ast::PrimitiveType::byte => {
@@ -708,8 +777,14 @@ impl From<&ast::PrimitiveType> for JsPrimitive {
             ast::PrimitiveType::u64 => JsPrimitive::Number(JsPrimitiveNumber::BigUint64),
             ast::PrimitiveType::i128 => panic!("128-bit integers are unsupported"),
             ast::PrimitiveType::u128 => panic!("128-bit unsigned integers are unsupported"),
-            ast::PrimitiveType::isize => JsPrimitive::Number(JsPrimitiveNumber::Int32),
-            ast::PrimitiveType::usize => JsPrimitive::Number(JsPrimitiveNumber::Uint32),
+            ast::PrimitiveType::isize => JsPrimitive::Number(match width {
+                PointerWidth::Bits32 => JsPrimitiveNumber::Int32,
+                PointerWidth::Bits64 => JsPrimitiveNumber::BigInt64,
+            }),
+            ast::PrimitiveType::usize => JsPrimitive::Number(match width {
+                PointerWidth::Bits32 => JsPrimitiveNumber::Uint32,
+                PointerWidth::Bits64 => JsPrimitiveNumber::BigUint64,
+            }),
             ast::PrimitiveType::f32 => JsPrimitive::Number(JsPrimitiveNumber::Float32),
             ast::PrimitiveType::f64 => JsPrimitive::Number(JsPrimitiveNumber::Float64),
             ast::PrimitiveType::bool => JsPrimitive::Bool,
@@ -738,20 +813,25 @@ pub struct UnderlyingIntoJs<'base> {
 impl fmt::Display for UnderlyingIntoJs<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.inner {
-            ast::TypeName::Primitive(prim) => match prim.into() {
+            ast::TypeName::Primitive(prim) => match self.base.js_primitive(prim) {
+                JsPrimitive::Number(num) if self.base.is_big_usize(prim) => write!(
+                    f,
+                    "Number(diplomatRuntime.readNumber(wasm, {}, \"{num}\"))",
+                    self.underlying
+                ),
                 JsPrimitive::Number(num) => write!(
                     f,
-                    "(new {num}Array(wasm.memory.buffer, {}, 1))[0]",
+                    "diplomatRuntime.readNumber(wasm, {}, \"{num}\")",
                     self.underlying
                 ),
                 JsPrimitive::Bool => write!(
                     f,
-                    "(new Uint8Array(wasm.memory.buffer, {}, 1))[0] == 1",
+                    "diplomatRuntime.readNumber(wasm, {}, \"Uint8\") == 1",
                     self.underlying
                 ),
                 JsPrimitive::Char => write!(
                     f,
-                    "String.fromCharCode((new Uint32Array(wasm.memory.buffer, {}, 1))[0])",
+                    "String.fromCodePoint(diplomatRuntime.readNumber(wasm, {}, \"Uint32\"))",
                     self.underlying
                 ),
             },
@@ -845,9 +925,9 @@ impl fmt::Display for UnderlyingIntoJs<'_> {
             ast::TypeName::StrReference(_, ast::StringEncoding::UnvalidatedUtf16) => {
                 self.display_slice(SliceKind::Str16).fmt(f)
             }
-            ast::TypeName::PrimitiveSlice(.., prim) => {
-                self.display_slice(SliceKind::Primitive(prim.into())).fmt(f)
-            }
+            ast::TypeName::PrimitiveSlice(.., prim) => self
+                .display_slice(SliceKind::Primitive(self.base.js_primitive(prim)))
+                .fmt(f),
             ast::TypeName::Unit => "{}".fmt(f),
             &_ => unreachable!("unknown AST/HIR variant"),
         }
@@ -863,8 +943,9 @@ impl UnderlyingIntoJs<'_> {
             let size_ident: ast::Ident = "size".into();
             writeln!(
                 f,
-                "const [{ptr_ident}, {size_ident}] = new Uint32Array(wasm.memory.buffer, {}, 2);",
-                self.underlying
+                "const [{ptr_ident}, {size_ident}] = diplomatRuntime.readNumbers(wasm, {}, 2, \"{}\");",
+                self.underlying,
+                self.base.usize_number()
             )?;
             writeln!(f, "return {};", kind.display(&ptr_ident, &size_ident))?;
             Ok(())
@@ -960,6 +1041,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wasm64() {
+        test_file! {
+            @width crate::layout::PointerWidth::Bits64,
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                pub struct Buffer(Vec<u8>);
+
+                pub struct Span {
+                    start: usize,
+                    offset: isize,
+                    valid: bool,
+                }
+
+                impl Buffer {
+                    pub fn new(len: usize) -> Box<Buffer> {
+                        unimplemented!()
+                    }
+
+                    pub fn len(&self) -> usize {
+                        unimplemented!()
+                    }
+
+                    pub fn span(&self, start: usize) -> Option<Span> {
+                        unimplemented!()
+                    }
+
+                    pub fn starts(&self) -> &[usize] {
+                        unimplemented!()
+                    }
+
+                    pub fn find(&self, needle: &DiplomatStr) -> Result<Span, ()> {
+                        unimplemented!()
+                    }
+
+                    pub fn first(&self) -> Option<&Buffer> {
+                        unimplemented!()
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_borrowing_opaque_owned_by_struct() {
         test_file! {
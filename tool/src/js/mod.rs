@@ -1,7 +1,12 @@
+use colored::*;
 use diplomat_core::{ast, Env};
 use std::collections::HashMap;
 use std::fmt::{self, Write};
+use std::fs;
+use std::path::Path;
 
+use crate::layout::PointerWidth;
+use crate::symbols::{SymbolRules, C_SYMBOLS};
 use crate::util;
 
 #[cfg(test)]
@@ -10,6 +15,10 @@ mod test_util;
 
 pub mod docs;
 
+mod bundle;
+
+mod config;
+
 pub mod types;
 
 pub mod structs;
@@ -21,11 +30,43 @@ pub mod conversions;
 
 pub mod display;
 
+/// The bindings call into wasm32 modules unless generated for a wasm64 target, whose
+/// modules take and return pointers and `usize` as BigInts
+pub(crate) const DEFAULT_POINTER_WIDTH: PointerWidth = PointerWidth::Bits32;
+
+/// Symbols are wasm exports accessed as properties of the module, which can also contain `$`
+pub(crate) const SYMBOLS: SymbolRules = SymbolRules {
+    allows: |c| c.is_ascii_alphanumeric() || c == '_' || c == '$',
+    charset: "ASCII letters, digits, underscores and dollar signs",
+    ..C_SYMBOLS
+};
+
 pub fn gen_bindings(
     env: &Env,
+    library_config_path: Option<&Path>,
     outs: &mut HashMap<String, String>,
     docs_url_gen: Option<&ast::DocsUrlGenerator>,
+    width: PointerWidth,
 ) -> fmt::Result {
+    let mut library_config = config::LibraryConfig::default();
+    if let Some(path) = library_config_path {
+        // Should be fine, we've already verified the path
+        if let Ok(contents) = fs::read_to_string(path) {
+            match toml::from_str(&contents) {
+                Ok(config) => library_config = config,
+                Err(err) => {
+                    eprintln!(
+                        "{}Unable to parse library configuration file: {:?}\n{}",
+                        "Error: ".red().bold(),
+                        path,
+                        err,
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
     outs.entry("diplomat-runtime.mjs".to_string())
         .or_default()
         .write_str(include_str!("runtime.mjs"))?;
@@ -34,16 +75,41 @@ pub fn gen_bindings(
         .write_str(include_str!("runtime.d.ts"))?;
     outs.entry("diplomat-wasm.mjs".to_string())
         .or_default()
-        .write_str(include_str!("wasm.mjs"))?;
+        .write_str(&include_str!("wasm.mjs").replace(
+            "const POINTER_WIDTH = 32;",
+            &format!("const POINTER_WIDTH = {};", width.bits()),
+        ))?;
+    outs.entry("diplomat-wasm.d.ts".to_string())
+        .or_default()
+        .write_str(include_str!("wasm.d.ts"))?;
 
     let mut all_types = util::get_all_custom_types(env);
     all_types.sort_by_key(|t| t.1.name());
+    let mut all_types = all_types.into_iter().collect::<Vec<_>>();
+    if !library_config.entry_types.is_empty() {
+        match bundle::reachable_types(&all_types, env, &library_config.entry_types) {
+            Ok(reachable) => all_types.retain(|(_, ty)| reachable.contains(ty.name().as_str())),
+            Err(unknown) => {
+                for name in unknown {
+                    eprintln!(
+                        "{}The entry type `{name}` does not exist",
+                        "Error: ".red().bold()
+                    );
+                }
+                std::process::exit(1);
+            }
+        }
+    }
 
     let index_ts = outs.entry("index.d.ts".to_string()).or_default();
     writeln!(
         index_ts,
         "export {{ FFIError, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, char }} from './diplomat-runtime';"
     )?;
+    writeln!(
+        index_ts,
+        "export {{ init, InitOptions }} from './diplomat-wasm';"
+    )?;
     for (_, custom_type) in &all_types {
         let name = custom_type.name();
         writeln!(index_ts, "export {{ {name} }} from './{name}';",)?;
@@ -54,6 +120,7 @@ pub fn gen_bindings(
         index_js,
         "export {{ FFIError }} from './diplomat-runtime.mjs';"
     )?;
+    writeln!(index_js, "export {{ init }} from './diplomat-wasm.mjs';")?;
     for (_, custom_type) in &all_types {
         let name = custom_type.name();
         writeln!(index_js, "export {{ {name} }} from './{name}.mjs';",)?;
@@ -88,7 +155,7 @@ pub fn gen_bindings(
         }
         writeln!(out)?;
 
-        gen_struct(out, custom_type, in_path, env)?;
+        gen_struct(out, custom_type, in_path, env, width)?;
 
         // == Declaration file ==
 
@@ -123,12 +190,23 @@ pub fn gen_bindings(
         if imports.ts_ffierror {
             writeln!(out, "import {{ FFIError }} from \"./diplomat-runtime\"")?;
         }
+        if let ast::CustomType::Opaque(_) = custom_type {
+            writeln!(
+                out,
+                "import {{ DiplomatTransferable }} from \"./diplomat-runtime\""
+            )?;
+        }
         for custom_type in imports.ts_imports.iter() {
             writeln!(out, "import {{ {0} }} from \"./{0}\";", custom_type.name())?;
         }
         writeln!(out)?;
 
-        gen_ts_custom_type_declaration(out, custom_type, in_path, env, docs_url_gen)?;
+        gen_ts_custom_type_declaration(out, custom_type, in_path, env, docs_url_gen, width)?;
+    }
+
+    if library_config.size_report {
+        let report = bundle::size_report(&all_types, env, outs);
+        outs.insert(bundle::SIZE_REPORT_FILE.into(), report);
     }
 
     Ok(())
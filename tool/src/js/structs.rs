@@ -1,15 +1,14 @@
 use diplomat_core::{ast, Env};
 use std::collections::BTreeMap;
 use std::fmt::{self, Display as _, Write as _};
-use std::num::NonZeroUsize;
 
 use super::conversions::{
     gen_value_js_to_rust, Argument, Base, Csv, Invocation, InvocationIntoJs, ReceivedEdges,
-    Underlying, UnderlyingIntoJs, UnpackedBinding,
+    Underlying, UnderlyingIntoJs, UnpackedBinding, UsizeLiteral,
 };
 use super::display;
 use crate::docs_util::{FromMarkdown, TsDoc};
-use crate::layout;
+use crate::layout::{self, PointerWidth};
 
 /// Generates a JS class declaration
 ///
@@ -33,6 +32,7 @@ pub fn gen_struct<W: fmt::Write>(
     custom_type: &ast::CustomType,
     in_path: &ast::Path,
     env: &Env,
+    width: PointerWidth,
 ) -> fmt::Result {
     match custom_type {
         ast::CustomType::Enum(enm) => {
@@ -95,6 +95,7 @@ pub fn gen_struct<W: fmt::Write>(
                                 strct.fields.iter().map(|(_, typ, _)| typ),
                                 in_path,
                                 env,
+                                width,
                             );
 
                             for ((name, inner, _), &offset) in
@@ -114,12 +115,13 @@ pub fn gen_struct<W: fmt::Write>(
                                         inner,
                                         underlying: Underlying::Binding(
                                             &underlying,
-                                            NonZeroUsize::new(offset),
+                                            (offset != 0).then(|| UsizeLiteral(offset, width)),
                                         ),
                                         base: Base {
                                             in_path,
                                             env,
                                             borrows: &borrows[..],
+                                            width,
                                         },
                                     },
                                 )?;
@@ -130,7 +132,7 @@ pub fn gen_struct<W: fmt::Write>(
                     )?;
 
                     for method in strct.methods.iter() {
-                        gen_method(method, in_path, env, &mut f)?;
+                        gen_method(method, in_path, env, width, &mut f)?;
                     }
                     Ok(())
                 })
@@ -152,11 +154,13 @@ pub fn gen_struct<W: fmt::Write>(
                 opaque.name,
                 display::block(|mut f| {
                     writeln!(f, "#lifetimeEdges = [];")?;
+                    writeln!(f, "#owned;")?;
                     writeln!(
                         f,
                         "constructor(underlying, owned, edges) {}",
                         display::block(|mut f| {
                             writeln!(f, "this.underlying = underlying;")?;
+                            writeln!(f, "this.#owned = owned;")?;
                             writeln!(f, "this.#lifetimeEdges.push(...edges);")?;
                             writeln!(
                                 f,
@@ -164,16 +168,17 @@ pub fn gen_struct<W: fmt::Write>(
                                 display::block(|mut f| {
                                     writeln!(
                                         f,
-                                        "{}_box_destroy_registry.register(this, underlying);",
+                                        "{}_box_destroy_registry.register(this, underlying, this);",
                                         opaque.name
                                     )
                                 })
                             )
                         }),
                     )?;
+                    gen_transfer_methods(&opaque.name, &mut f)?;
 
                     for method in opaque.methods.iter() {
-                        gen_method(method, in_path, env, &mut f)?;
+                        gen_method(method, in_path, env, width, &mut f)?;
                     }
 
                     Ok(())
@@ -184,6 +189,47 @@ pub fn gen_struct<W: fmt::Write>(
     }
 }
 
+/// Generates the `toTransferable()`/`fromTransferable()` pair for an opaque
+/// wrapper, which lets the underlying pointer be posted to another worker
+/// sharing the same WASM memory.
+///
+/// Only owned wrappers without lifetime edges can be transferred. The sender's
+/// wrapper is revoked, and the receiver takes over freeing the pointer.
+fn gen_transfer_methods<W: fmt::Write>(name: &ast::Ident, out: &mut W) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "toTransferable() {}",
+        display::block(|mut f| {
+            writeln!(
+                f,
+                "if (!this.#owned || this.#lifetimeEdges.length != 0) {}",
+                display::block(|mut f| {
+                    writeln!(
+                        f,
+                        "throw new Error(\"Only owned {name} objects without borrows can be transferred\");"
+                    )
+                })
+            )?;
+            writeln!(
+                f,
+                "return diplomatRuntime.toTransferable(this, {name}_box_destroy_registry, \"{name}\");"
+            )
+        })
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "static fromTransferable(transferable) {}",
+        display::block(|mut f| {
+            writeln!(
+                f,
+                "return new {name}(diplomatRuntime.fromTransferable(transferable, \"{name}\"), true, []);"
+            )
+        })
+    )
+}
+
 /// Generates the contents of a JS method.
 ///
 /// # Examples
@@ -200,6 +246,7 @@ fn gen_method<W: fmt::Write>(
     method: &ast::Method,
     in_path: &ast::Path,
     env: &Env,
+    width: PointerWidth,
     out: &mut W,
 ) -> fmt::Result {
     if method.attrs.skip_if_ast {
@@ -241,6 +288,7 @@ fn gen_method<W: fmt::Write>(
             &method.lifetime_env,
             &borrowed_current_to_root,
             &mut entries,
+            width,
         );
     }
 
@@ -256,6 +304,7 @@ fn gen_method<W: fmt::Write>(
             &method.lifetime_env,
             &borrowed_current_to_root,
             &mut entries,
+            width,
         );
     }
 
@@ -342,6 +391,7 @@ fn gen_method<W: fmt::Write>(
                                 in_path,
                                 env,
                                 borrows: &borrows[..],
+                                width,
                             },
                         }
                         .fmt(f)
@@ -393,6 +443,7 @@ pub fn gen_ts_custom_type_declaration<W: fmt::Write>(
     in_path: &ast::Path,
     env: &Env,
     docs_url_gen: Option<&ast::DocsUrlGenerator>,
+    width: PointerWidth,
 ) -> fmt::Result {
     if let Some(docs_url_gen) = docs_url_gen {
         write!(
@@ -446,7 +497,7 @@ pub fn gen_ts_custom_type_declaration<W: fmt::Write>(
                     let mut ty = String::new();
                     for field in strct.fields.iter() {
                         ty.clear();
-                        let optional = gen_ts_type(&mut ty, &field.1, in_path, env)?;
+                        let optional = gen_ts_type(&mut ty, &field.1, in_path, env, width)?;
                         if optional {
                             writeln!(f, "{}?: {};", field.0, ty)?;
                         } else {
@@ -455,8 +506,36 @@ pub fn gen_ts_custom_type_declaration<W: fmt::Write>(
                     }
                 }
 
+                if let ast::CustomType::Opaque(opaque) = custom_type {
+                    writeln!(f)?;
+                    writeln!(f, "/**")?;
+                    writeln!(
+                        f,
+                        " * Moves ownership of this object into a structured-cloneable value that can be posted to another worker."
+                    )?;
+                    writeln!(f, " *")?;
+                    writeln!(
+                        f,
+                        " * This object may not be used afterwards. Only owned objects without borrows can be transferred."
+                    )?;
+                    writeln!(f, " */")?;
+                    writeln!(f, "toTransferable(): DiplomatTransferable;")?;
+                    writeln!(f)?;
+                    writeln!(f, "/**")?;
+                    writeln!(
+                        f,
+                        " * Takes ownership of an object produced by `toTransferable()` in a worker sharing this WASM memory."
+                    )?;
+                    writeln!(f, " */")?;
+                    writeln!(
+                        f,
+                        "static fromTransferable(transferable: DiplomatTransferable): {};",
+                        opaque.name
+                    )?;
+                }
+
                 for method in custom_type.methods() {
-                    gen_ts_method_declaration(method, in_path, env, docs_url_gen, &mut f)?;
+                    gen_ts_method_declaration(method, in_path, env, docs_url_gen, width, &mut f)?;
                 }
                 Ok(())
             })
@@ -476,6 +555,7 @@ pub fn gen_ts_type<W: fmt::Write>(
     typ: &ast::TypeName,
     in_path: &ast::Path,
     env: &Env,
+    width: PointerWidth,
 ) -> Result<bool, fmt::Error> {
     match typ {
         ast::TypeName::Primitive(prim) => match prim {
@@ -494,11 +574,13 @@ pub fn gen_ts_type<W: fmt::Write>(
             out.write_str(name.as_str())?;
         }
         ast::TypeName::Reference(.., typ) | ast::TypeName::Box(typ) => {
-            return gen_ts_type(out, typ, in_path, env)
+            return gen_ts_type(out, typ, in_path, env, width)
+        }
+        ast::TypeName::Option(typ) => {
+            return gen_ts_type(out, typ, in_path, env, width).map(|_| true)
         }
-        ast::TypeName::Option(typ) => return gen_ts_type(out, typ, in_path, env).map(|_| true),
         ast::TypeName::Result(ok, _err, _) => {
-            let opt = gen_ts_type(out, ok, in_path, env)?;
+            let opt = gen_ts_type(out, ok, in_path, env, width)?;
             write!(out, " | never")?;
             return Ok(opt);
         }
@@ -520,8 +602,14 @@ pub fn gen_ts_type<W: fmt::Write>(
             ast::PrimitiveType::u64 => write!(out, "BigUint64Array")?,
             ast::PrimitiveType::i128 => panic!("i128 is unsupported"),
             ast::PrimitiveType::u128 => panic!("u128 is unsupported"),
-            ast::PrimitiveType::isize => write!(out, "Int32Array")?,
-            ast::PrimitiveType::usize => write!(out, "Uint32Array")?,
+            ast::PrimitiveType::isize => match width {
+                PointerWidth::Bits32 => write!(out, "Int32Array")?,
+                PointerWidth::Bits64 => write!(out, "BigInt64Array")?,
+            },
+            ast::PrimitiveType::usize => match width {
+                PointerWidth::Bits32 => write!(out, "Uint32Array")?,
+                PointerWidth::Bits64 => write!(out, "BigUint64Array")?,
+            },
             ast::PrimitiveType::f32 => write!(out, "Float32Array")?,
             ast::PrimitiveType::f64 => write!(out, "Float64Array")?,
             ast::PrimitiveType::bool => write!(out, "Uint8Array")?,
@@ -540,6 +628,7 @@ fn gen_ts_method_declaration<W: fmt::Write>(
     in_path: &ast::Path,
     env: &Env,
     docs_url_gen: Option<&ast::DocsUrlGenerator>,
+    width: PointerWidth,
     out: &mut W,
 ) -> fmt::Result {
     if method.attrs.skip_if_ast {
@@ -577,7 +666,7 @@ fn gen_ts_method_declaration<W: fmt::Write>(
                                 }
                                 _ => {
                                     write!(f, "{{@link ")?;
-                                    let opt = gen_ts_type(&mut f, err, in_path, env)?;
+                                    let opt = gen_ts_type(&mut f, err, in_path, env, width)?;
                                     write!(f, "}}")?;
                                     if opt {
                                         write!(f, " | undefined")?;
@@ -608,7 +697,7 @@ fn gen_ts_method_declaration<W: fmt::Write>(
             };
             if let Some((first, rest)) = params.split_first() {
                 let mut ty_name = String::new();
-                let optional = gen_ts_type(&mut ty_name, &first.ty, in_path, env)?;
+                let optional = gen_ts_type(&mut ty_name, &first.ty, in_path, env, width)?;
                 if optional {
                     write!(f, "{}?: {}", first.name, ty_name)?;
                 } else {
@@ -616,7 +705,7 @@ fn gen_ts_method_declaration<W: fmt::Write>(
                 }
                 for item in rest {
                     ty_name.clear();
-                    let optional = gen_ts_type(&mut ty_name, &item.ty, in_path, env)?;
+                    let optional = gen_ts_type(&mut ty_name, &item.ty, in_path, env, width)?;
                     if optional {
                         write!(f, ", {}?: {}", item.name, ty_name)?;
                     } else {
@@ -643,7 +732,7 @@ fn gen_ts_method_declaration<W: fmt::Write>(
                 }
                 Ok(())
             } else if let Some(ref return_type) = method.return_type {
-                let optional = gen_ts_type(f, return_type, in_path, env)?;
+                let optional = gen_ts_type(f, return_type, in_path, env, width)?;
                 if optional {
                     f.write_str(" | undefined")?;
                 }
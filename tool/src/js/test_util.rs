@@ -1,12 +1,12 @@
 macro_rules! test_file {
-    ($($file:tt)*) => {
+    (@width $width:expr, $($file:tt)*) => {
         let parsed: syn::File = syn::parse_quote! { $($file)* };
         let custom_types = diplomat_core::ast::File::from(&parsed);
         let env = custom_types.all_types();
 
         let mut out_texts = std::collections::HashMap::new();
 
-        crate::js::gen_bindings(&env, &mut out_texts, None).unwrap();
+        crate::js::gen_bindings(&env, None, &mut out_texts, None, $width).unwrap();
 
         let mut out_docs = std::collections::HashMap::new();
         crate::js::docs::gen_docs(&env, &mut out_docs, &Default::default()).unwrap();
@@ -14,6 +14,7 @@ macro_rules! test_file {
         out_texts.remove("diplomat-runtime.mjs");
         out_texts.remove("diplomat-runtime.d.ts");
         out_texts.remove("diplomat-wasm.mjs");
+        out_texts.remove("diplomat-wasm.d.ts");
 
         for out in out_texts.keys() {
             insta::with_settings!({ snapshot_suffix => out.clone() }, {
@@ -26,5 +27,8 @@ macro_rules! test_file {
                 insta::assert_snapshot!(out_docs.get(out).unwrap())
             });
         }
-    }
+    };
+    ($($file:tt)*) => {
+        test_file!(@width crate::js::DEFAULT_POINTER_WIDTH, $($file)*)
+    };
 }
@@ -0,0 +1,30 @@
+/**
+ * Options for instantiating the WASM module with `init`.
+ */
+export interface InitOptions {
+    /**
+     * Instantiates the module with the given imports, instead of loading the `wasm_path` of the config.
+     */
+    instantiate?: (imports: WebAssembly.Imports) => Promise<WebAssembly.Instance | WebAssembly.WebAssemblyInstantiatedSource>;
+    /**
+     * Imports added to the ones the bindings need, such as WASI shims.
+     */
+    imports?: WebAssembly.Imports;
+}
+
+/**
+ * Instantiates and initializes the WASM module.
+ *
+ * This happens when the bindings are loaded, unless `manual_init` is set in the config,
+ * in which case the returned promise has to resolve before any binding is used.
+ */
+export function init(options?: InitOptions): Promise<void>;
+
+/**
+ * Logs the Rust objects that are still alive, returning how many there are. Call it once the
+ * bindings are no longer used, e.g. from the devtools console as `diplomatLeakReport()`.
+ *
+ * Only available when the WASM module is built with the `leak-report` feature of
+ * diplomat-runtime, `undefined` otherwise.
+ */
+export const leakReport: (() => number) | undefined;
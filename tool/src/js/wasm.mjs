@@ -1,7 +1,11 @@
 import cfg from '../diplomat.config.mjs';
-import {readString8} from './diplomat-runtime.mjs'
+import {readString8, selfTest, withWriteable} from './diplomat-runtime.mjs'
 
 let wasm;
+let leakReport;
+
+// The width of pointers the bindings were generated for
+const POINTER_WIDTH = 32;
 
 const imports = {
   env: {
@@ -26,19 +30,64 @@ const imports = {
   }
 }
 
-if (typeof fetch === 'undefined') { // Node
-  const fs = await import("fs");
-  const wasmFile = new Uint8Array(fs.readFileSync(cfg['wasm_path']));
-  const loadedWasm = await WebAssembly.instantiate(wasmFile, imports);
-  wasm = loadedWasm.instance.exports;
-} else { // Browser
-  const loadedWasm = await WebAssembly.instantiateStreaming(fetch(cfg['wasm_path']), imports);
-  wasm = loadedWasm.instance.exports;
+// Instantiate and initialize the WASM module. This runs as soon as this module is loaded,
+// unless `manual_init` is set in the config, in which case it has to be awaited before
+// the bindings are used. The options are:
+// - `instantiate`: a function taking the import object and returning a promise of the
+//   instance, or of the result of `WebAssembly.instantiate`. Use this to fetch and
+//   compile the module yourself instead of loading the `wasm_path` of the config.
+// - `imports`: imports added to the ones the bindings need, such as WASI shims.
+export async function init(options = {}) {
+  if (wasm !== undefined) {
+    throw new Error("The WASM module is already initialized");
+  }
+
+  const allImports = {
+    ...options.imports,
+    env: { ...options.imports?.env, ...imports.env },
+  };
+
+  let loadedWasm;
+  if (options.instantiate !== undefined) {
+    loadedWasm = await options.instantiate(allImports);
+  } else if (typeof fetch === 'undefined') { // Node
+    const fs = await import("fs");
+    const wasmFile = new Uint8Array(fs.readFileSync(cfg['wasm_path']));
+    loadedWasm = await WebAssembly.instantiate(wasmFile, allImports);
+  } else { // Browser
+    loadedWasm = await WebAssembly.instantiateStreaming(fetch(cfg['wasm_path']), allImports);
+  }
+  const instance = loadedWasm instanceof WebAssembly.Instance ? loadedWasm : loadedWasm.instance;
+  // The bindings lay memory out for pointers of this width, and pass them as BigInts to wasm64 modules
+  if (instance.exports.diplomat_pointer_width !== undefined && instance.exports.diplomat_pointer_width() !== POINTER_WIDTH) {
+    throw new Error(`The WASM module must be built for wasm${POINTER_WIDTH}`);
+  }
+  wasm = instance.exports;
+
+  wasm.diplomat_init();
+  selfTest(wasm);
+  // Modules built with the `leak-report` feature of diplomat-runtime count the objects never freed
+  if (wasm.diplomat_leak_report !== undefined) {
+    leakReport = () => {
+      let live = 0;
+      const report = withWriteable(wasm, (writeable) => {
+        live = wasm.diplomat_leak_report(writeable);
+      });
+      if (live > 0) {
+        console.warn(`Rust objects never freed:\n${report}`);
+      }
+      return live;
+    };
+    globalThis.diplomatLeakReport = leakReport;
+  }
+  if (cfg['init'] !== undefined) {
+    cfg['init'](wasm);
+  }
 }
 
-wasm.diplomat_init();
-if (cfg['init'] !== undefined) {
-  cfg['init'](wasm);
+if (!cfg['manual_init']) {
+  await init();
 }
 
-export default wasm;
+// Exported as live bindings, so that the bindings see the module once `init` has run
+export { wasm as default, leakReport };
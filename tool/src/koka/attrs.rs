@@ -0,0 +1,110 @@
+//! The `#[diplomat::attr]` support of the Koka backend.
+
+use diplomat_core::ast::attrs::DiplomatBackendAttrCfg;
+use diplomat_core::hir::{
+    AttributeValidator, BackendAttrSupport, BasicAttributeValidator, LoweringError,
+};
+
+/// Names of the backends an attribute can be selected for. The Koka backend rejects any
+/// other name, since a misspelled one would leave the attribute silently unapplied.
+const BACKEND_NAMES: &[&str] = &[
+    "c", "c2", "cpp", "cpp2", "cpp-c2", "dart", "dotnet", "js", "koka", "kotlin",
+];
+
+/// The attributes Koka bindings honor
+pub fn attr_support() -> BackendAttrSupport {
+    let mut support = BackendAttrSupport::default();
+    support.renaming = true;
+    support.disabling = true;
+    support.namespacing = true;
+    support.constructors = true;
+    support.named_constructors = true;
+    support.fallible_constructors = true;
+    support.accessors = true;
+    support.stringifiers = true;
+    support.comparators = true;
+    support.hashing = true;
+    support.default_constructors = true;
+    support.iterators = true;
+    support.iterables = true;
+    support.indexing = true;
+    support.callbacks = true;
+    support.option = true;
+    support.traits = true;
+    support.async_methods = true;
+    support.consuming_methods = true;
+    support.constants = true;
+    support.parent_managed = true;
+    support.non_send = true;
+    support.warnings = true;
+    support
+}
+
+/// Validates attributes for the Koka backend, rejecting those it can't honor.
+///
+/// On top of the features missing from [`attr_support()`], this rejects `cfg`s naming a backend
+/// or a `name = value` pair diplomat doesn't know, which would otherwise never apply.
+pub struct KokaAttributeValidator(BasicAttributeValidator);
+
+impl KokaAttributeValidator {
+    pub fn new() -> Self {
+        let mut validator = BasicAttributeValidator::new("koka");
+        validator.support = attr_support();
+        KokaAttributeValidator(validator)
+    }
+
+    /// Generates for the target `triple` alone, leaving out the methods not available on it
+    pub fn with_target(mut self, triple: Option<&str>) -> Self {
+        self.0.target = triple.map(Into::into);
+        self
+    }
+}
+
+impl Default for KokaAttributeValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AttributeValidator for KokaAttributeValidator {
+    fn primary_name(&self) -> &str {
+        self.0.primary_name()
+    }
+    fn is_backend(&self, backend_name: &str) -> bool {
+        self.0.is_backend(backend_name)
+    }
+    fn is_name_value(&self, name: &str, value: &str) -> Result<bool, LoweringError> {
+        self.0.is_name_value(name, value)
+    }
+    fn attrs_supported(&self) -> BackendAttrSupport {
+        self.0.attrs_supported()
+    }
+    fn target(&self) -> Option<&str> {
+        self.0.target()
+    }
+
+    fn satisfies_cfg(&self, cfg: &DiplomatBackendAttrCfg) -> Result<bool, LoweringError> {
+        check_cfg(cfg)?;
+        self.0.satisfies_cfg(cfg)
+    }
+}
+
+/// Check that every backend and name `cfg` refers to exists
+fn check_cfg(cfg: &DiplomatBackendAttrCfg) -> Result<(), LoweringError> {
+    match cfg {
+        DiplomatBackendAttrCfg::Not(c) => check_cfg(c),
+        DiplomatBackendAttrCfg::Any(cs) | DiplomatBackendAttrCfg::All(cs) => {
+            cs.iter().try_for_each(check_cfg)
+        }
+        DiplomatBackendAttrCfg::BackendName(n) if !BACKEND_NAMES.contains(&n.as_str()) => {
+            Err(LoweringError::Other(format!(
+                "Unknown backend `{n}` in `#[diplomat::attr]`, expected one of {}",
+                BACKEND_NAMES.join(", ")
+            )))
+        }
+        DiplomatBackendAttrCfg::NameValue(n, _) if n != "supports" => Err(LoweringError::Other(
+            format!("Unknown `{n} = ...` in `#[diplomat::attr]`, only `supports = ...` is known"),
+        )),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,307 @@
+//! Rendering of markdown docs as the body of Koka `//` doc comments
+//!
+//! Koka's doc generator reads comments as Madoko markdown, so most of the markdown is kept.
+//! The renderer works a line at a time: it only needs to know which lines are code, which
+//! are kept verbatim, and to normalize the few constructs that read badly in a comment.
+
+use crate::common::StripPrefix;
+
+/// The tokens of a code fence's info string that only tell rustdoc how to test an example.
+/// A fence with nothing else is Rust, like it is for rustdoc.
+const RUSTDOC_ATTRIBUTES: &[&str] = &[
+    "rust",
+    "ignore",
+    "no_run",
+    "should_panic",
+    "compile_fail",
+    "test_harness",
+    "edition2015",
+    "edition2018",
+    "edition2021",
+    "edition2024",
+];
+
+struct Fence {
+    marker: char,
+    len: usize,
+}
+
+/// Render markdown as the lines of a `//` comment, without the `// ` of the first line.
+///
+/// - Fenced and indented code blocks are kept verbatim, and fences of rustdoc examples are
+///   marked as `rust`.
+/// - Headings become bold paragraphs, since a heading would start a new section of the
+///   module's docs instead of belonging to the declaration.
+/// - List items are kept, with `-` bullets and `1.` numbers.
+/// - `strip_prefix` is applied to the type name inline code starts with.
+pub(super) fn render_doc_comment(markdown: &str, strip_prefix: &StripPrefix) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut fence: Option<Fence> = None;
+    let mut indented_code = false;
+    let mut in_list = false;
+    let mut after_blank = true;
+    let mut break_pending = false;
+
+    for line in markdown.lines() {
+        if let Some(open) = &fence {
+            if closes_fence(open, line) {
+                lines.push(line.trim_end().to_string());
+                fence = None;
+            } else {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            lines.push(String::new());
+            after_blank = true;
+            break_pending = false;
+            continue;
+        }
+        let indent = &line[..line.len() - trimmed.len()];
+
+        if indent.len() >= 4 && ((after_blank && !in_list) || indented_code) {
+            lines.push(line.trim_end().to_string());
+            indented_code = true;
+            after_blank = false;
+            continue;
+        }
+        indented_code = false;
+
+        if break_pending {
+            lines.push(String::new());
+            break_pending = false;
+        }
+        if after_blank && indent.is_empty() && list_item(trimmed).is_none() {
+            in_list = false;
+        }
+        after_blank = false;
+
+        if let Some((marker, len, info)) = opening_fence(trimmed) {
+            let lang = if info
+                .split([',', ' '])
+                .filter(|token| !token.is_empty())
+                .all(|token| RUSTDOC_ATTRIBUTES.contains(&token))
+            {
+                "rust"
+            } else {
+                info
+            };
+            lines.push(format!("{indent}{}{lang}", marker.to_string().repeat(len)));
+            fence = Some(Fence { marker, len });
+        } else if let Some(heading) = heading(trimmed) {
+            if lines.last().map_or(false, |last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!(
+                "**{}**",
+                strip_code_prefixes(heading, strip_prefix)
+            ));
+            break_pending = true;
+        } else if is_thematic_break(trimmed) {
+            lines.push("---".into());
+        } else if let Some((bullet, item)) = list_item(trimmed) {
+            lines.push(format!(
+                "{indent}{bullet} {}",
+                strip_code_prefixes(item.trim_end(), strip_prefix)
+            ));
+            in_list = true;
+        } else {
+            lines.push(format!(
+                "{indent}{}",
+                strip_code_prefixes(trimmed.trim_end(), strip_prefix)
+            ));
+        }
+    }
+
+    while lines.last().map_or(false, |line| line.is_empty()) {
+        lines.pop();
+    }
+    let start = lines
+        .iter()
+        .position(|line| !line.is_empty())
+        .unwrap_or(lines.len());
+
+    let mut comment = String::new();
+    for (i, line) in lines[start..].iter().enumerate() {
+        if i != 0 {
+            comment.push_str(if line.is_empty() { "\n//" } else { "\n// " });
+        }
+        comment.push_str(line);
+    }
+    comment
+}
+
+/// The marker, its length and the info string of a line opening a code fence
+fn opening_fence(line: &str) -> Option<(char, usize, &str)> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let rest = line.trim_start_matches(marker);
+    let len = line.len() - rest.len();
+    (len >= 3 && !(marker == '`' && rest.contains('`'))).then(|| (marker, len, rest.trim()))
+}
+
+fn closes_fence(fence: &Fence, line: &str) -> bool {
+    let line = line.trim();
+    let rest = line.trim_start_matches(fence.marker);
+    rest.is_empty() && line.len() >= fence.len
+}
+
+/// The text of an ATX heading, `# Heading`
+fn heading(line: &str) -> Option<&str> {
+    let rest = line.trim_start_matches('#');
+    let level = line.len() - rest.len();
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some(rest.trim().trim_end_matches('#').trim_end())
+}
+
+/// The normalized marker and the text of a list item, `- item` or `1. item`
+fn list_item(line: &str) -> Option<(String, &str)> {
+    if let Some(item) = line
+        .strip_prefix(['-', '*', '+'])
+        .and_then(|rest| rest.strip_prefix(' '))
+    {
+        return Some(("-".into(), item.trim_start()));
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let item = line[digits..]
+        .strip_prefix(['.', ')'])
+        .and_then(|rest| rest.strip_prefix(' '))
+        .filter(|_| (1..=9).contains(&digits))?;
+    Some((format!("{}.", &line[..digits]), item.trim_start()))
+}
+
+/// `---`, `***` or `___`, possibly spaced out
+fn is_thematic_break(line: &str) -> bool {
+    let Some(marker) = line.chars().next().filter(|c| matches!(c, '-' | '*' | '_')) else {
+        return false;
+    };
+    line.chars().all(|c| c == marker || c == ' ') && line.matches(marker).count() >= 3
+}
+
+/// Strip the prefix of the type names the code spans in `text` start with
+fn strip_code_prefixes(text: &str, strip_prefix: &StripPrefix) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        let ticks = backtick_run(&rest[start..]);
+        let after = &rest[start + ticks..];
+        out.push_str(&rest[..start + ticks]);
+        match find_backtick_run(after, ticks) {
+            Some(end) => {
+                let code = &after[..end];
+                let name_len = code
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(code.len());
+                out.push_str(strip_prefix.strip(&code[..name_len]));
+                out.push_str(&code[name_len..]);
+                out.push_str(&after[end..end + ticks]);
+                rest = &after[end + ticks..];
+            }
+            // Unmatched backticks are literal
+            None => rest = after,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn backtick_run(text: &str) -> usize {
+    text.len() - text.trim_start_matches('`').len()
+}
+
+/// The offset of the first run of exactly `len` backticks, which closes a code span
+fn find_backtick_run(text: &str, len: usize) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find('`') {
+        let start = offset + start;
+        let run = backtick_run(&text[start..]);
+        if run == len {
+            return Some(start);
+        }
+        offset = start + run;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_doc_comment;
+    use crate::common::StripPrefix;
+
+    #[test]
+    fn test_render_doc_comment() {
+        let markdown = "\
+Wraps an `ICU4XLocale`, unlike `` `ICU4X` ``.
+
+# Examples
+
+```
+let locale = ICU4XLocale::new(\"en\");
+assert_eq!(locale.to_string(), \"en\");
+```
+
+* first, see `ICU4XLocale`
++ second
+  continued
+    1) nested
+
+Indented code:
+
+    let x = `ICU4XLocale`;
+
+```text,ignore
+plain
+```
+
+~~~~
+```
+~~~~
+***
+";
+        assert_eq!(
+            render_doc_comment(markdown, &StripPrefix::new("ICU4X")),
+            "\
+Wraps an `Locale`, unlike `` `ICU4X` ``.
+//
+// **Examples**
+//
+// ```rust
+// let locale = ICU4XLocale::new(\"en\");
+// assert_eq!(locale.to_string(), \"en\");
+// ```
+//
+// - first, see `Locale`
+// - second
+//   continued
+//     1. nested
+//
+// Indented code:
+//
+//     let x = `ICU4XLocale`;
+//
+// ```text,ignore
+// plain
+// ```
+//
+// ~~~~rust
+// ```
+// ~~~~
+// ---"
+        );
+    }
+
+    #[test]
+    fn test_render_heading_breaks() {
+        assert_eq!(
+            render_doc_comment(
+                "Intro\n## Errors ##\nWhen `x` is empty\n",
+                &StripPrefix::default()
+            ),
+            "Intro\n//\n// **Errors**\n//\n// When `x` is empty"
+        );
+    }
+}
@@ -1,11 +1,75 @@
 //! This module contains functions for formatting types
 
+use super::docs::render_doc_comment;
+use super::Naming;
 use crate::c2::CFormatter;
+use crate::common::StripPrefix;
 use diplomat_core::ast::{DocsUrlGenerator, MarkdownStyle};
-use diplomat_core::hir::{self, TypeContext, TypeId};
-use heck::{ToLowerCamelCase, ToSnekCase, ToUpperCamelCase};
+use diplomat_core::hir::{self, SpecialMethod, TraitId, TypeContext, TypeDef, TypeId};
+use heck::{ToKebabCase, ToLowerCamelCase, ToSnekCase, ToUpperCamelCase};
 use std::borrow::Cow;
 
+/// Constructors of the Koka standard library, which enum variants would make ambiguous
+const STD_CONSTRUCTORS: &[&str] = &[
+    "Cons",
+    "Eq",
+    "Error",
+    "Exception",
+    "False",
+    "Gt",
+    "Just",
+    "Left",
+    "Lt",
+    "Nil",
+    "Nothing",
+    "Ok",
+    "Right",
+    "True",
+    "Unit",
+];
+
+/// The Koka constructor of each variant of `ty`, in declaration order, along with why it was
+/// disambiguated, if it was.
+///
+/// A variant is named after its Rust name in upper camel case, with renames applied. When that
+/// name is a constructor of the standard library, the `Unknown` constructor of a non-exhaustive
+/// enum, or the name of an earlier variant, the position of the variant is appended to it,
+/// counting up until the name is free. Earlier variants thus keep their names, and a name only
+/// depends on the variants declared before it.
+pub fn enum_variant_names(ty: &hir::EnumDef) -> Vec<(String, Option<String>)> {
+    let mut taken: Vec<(String, &str)> = Vec::new();
+    let mut names = Vec::new();
+    for (index, variant) in ty.variants.iter().enumerate() {
+        let name = variant
+            .attrs
+            .rename
+            .apply(variant.name.as_str().to_upper_camel_case().into())
+            .into_owned();
+        let reason = if STD_CONSTRUCTORS.contains(&&*name) {
+            Some(format!(
+                "`{name}` is a constructor of the Koka standard library"
+            ))
+        } else if ty.is_non_exhaustive && name == "Unknown" {
+            Some("`Unknown` stands for the variants added to the Rust enum later".into())
+        } else {
+            taken
+                .iter()
+                .find(|(taken, _)| *taken == name)
+                .map(|(_, owner)| format!("`{name}` is already the name of variant `{owner}`"))
+        };
+        let name = match reason {
+            Some(_) => (index..)
+                .map(|suffix| format!("{name}{suffix}"))
+                .find(|candidate| taken.iter().all(|(taken, _)| taken != candidate))
+                .unwrap(),
+            None => name,
+        };
+        taken.push((name.clone(), variant.name.as_str()));
+        names.push((name, reason));
+    }
+    names
+}
+
 /// This type mediates all formatting
 ///
 /// All identifiers from the HIR should go through here before being formatted
@@ -18,23 +82,116 @@ use std::borrow::Cow;
 pub(super) struct KokaFormatter<'tcx> {
     c: CFormatter<'tcx>,
     docs_url_generator: &'tcx DocsUrlGenerator,
-    strip_prefix: Option<String>,
+    strip_prefix: StripPrefix,
+    naming: Naming,
 }
 
-const INVALID_METHOD_NAMES: &[&str] = &["new", "static", "default"];
-const INVALID_FIELD_NAMES: &[&str] = &["new", "static", "default"];
-const DISALLOWED_CORE_TYPES: &[&str] = &["Object", "String"];
+/// Koka keywords, which cannot be used as plain identifiers.
+///
+/// `new` is not reserved by Koka, but constructors are generated under that name.
+const RESERVED_WORDS: &[&str] = &[
+    "abstract",
+    "alias",
+    "as",
+    "behind",
+    "co",
+    "con",
+    "ctl",
+    "effect",
+    "elif",
+    "else",
+    "exists",
+    "extend",
+    "extern",
+    "final",
+    "fn",
+    "forall",
+    "fun",
+    "handle",
+    "handler",
+    "if",
+    "import",
+    "in",
+    "infix",
+    "infixl",
+    "infixr",
+    "interface",
+    "linear",
+    "mask",
+    "match",
+    "module",
+    "named",
+    "new",
+    "open",
+    "override",
+    "pub",
+    "raw",
+    "rec",
+    "return",
+    "some",
+    "struct",
+    "then",
+    "type",
+    "unsafe",
+    "val",
+    "var",
+    "with",
+];
+/// Types from the Koka standard library (after snek-casing) that generated types must not shadow.
+const DISALLOWED_CORE_TYPES: &[&str] = &[
+    "any",
+    "bool",
+    "char",
+    "either",
+    "exception",
+    "float32",
+    "float64",
+    "int",
+    "int16",
+    "int32",
+    "int64",
+    "int8",
+    "intptr_t",
+    "list",
+    "maybe",
+    "object",
+    "order",
+    "ssize_t",
+    "string",
+    "unit",
+    "vector",
+    "void",
+];
 
 impl<'tcx> KokaFormatter<'tcx> {
     pub fn new(
         tcx: &'tcx TypeContext,
         docs_url_generator: &'tcx DocsUrlGenerator,
-        strip_prefix: Option<String>,
+        strip_prefix: StripPrefix,
+        naming: Naming,
     ) -> Self {
         Self {
             c: CFormatter::new(tcx),
             docs_url_generator,
             strip_prefix,
+            naming,
+        }
+    }
+
+    /// Case a function, parameter or field name according to the configured naming
+    fn fmt_value_case(&self, name: &str) -> String {
+        match self.naming {
+            Naming::Snake => name.to_snek_case(),
+            Naming::Kebab => kebab_case(name),
+            Naming::Camel => name.to_lower_camel_case(),
+        }
+    }
+
+    /// Case a type or trait name according to the configured naming
+    fn fmt_type_case<'a>(&self, name: Cow<'a, str>) -> Cow<'a, str> {
+        match self.naming {
+            Naming::Snake | Naming::Camel => name,
+            Naming::Kebab => self.uppercase_first_letter(&kebab_case(&name)).into(),
         }
     }
 
@@ -43,13 +200,28 @@ impl<'tcx> KokaFormatter<'tcx> {
         lifetime: hir::Lifetime,
         lifetime_env: &hir::LifetimeEnv,
     ) -> Cow<'static, str> {
-        format!("{}Edges", lifetime_env.fmt_lifetime(lifetime)).into()
+        format!("{}-edges", lifetime_env.fmt_lifetime(lifetime)).into()
     }
 
     pub fn fmt_file_name(&self, name: &str) -> String {
         format!("{name}.kk")
     }
 
+    /// Format the name of the C file holding the callback trampolines of a type
+    pub fn fmt_callbacks_file_name(&self, name: &str) -> String {
+        format!("{name}-callbacks.c")
+    }
+
+    /// Format the name of the C file holding the finalizer of an opaque
+    pub fn fmt_finalizer_file_name(&self, name: &str) -> String {
+        format!("{name}-finalizer.c")
+    }
+
+    /// Format the name of the C file holding the trampolines of a trait
+    pub fn fmt_trait_file_name(&self, name: &str) -> String {
+        format!("{name}-trait.c")
+    }
+
     pub fn fmt_import(&self, path: &str, as_show_hide: Option<&str>) -> Cow<'static, str> {
         format!(
             "import {path}{}{};",
@@ -59,15 +231,62 @@ impl<'tcx> KokaFormatter<'tcx> {
         .into()
     }
 
+    /// Format docs as the body of a `//` comment.
+    ///
+    /// Intra-doc links to bridge items become code spans naming their Koka definition, which
+    /// koka's doc generator cross-references; Rust links become markdown hyperlinks.
     pub fn fmt_docs(&self, docs: &hir::Docs) -> String {
-        docs.to_markdown(self.docs_url_generator, MarkdownStyle::Normal)
-            .trim()
-            .replace('\n', "\n// ")
-            .replace(" \n", "\n")
-            .replace(
-                &format!("`{}", self.strip_prefix.as_deref().unwrap_or("")),
-                "`",
+        let mut docs = docs.clone();
+        docs.rewrite_references(|target, text| {
+            Some(
+                self.fmt_doc_reference(target)
+                    .unwrap_or_else(|| text.to_string()),
             )
+        });
+        render_doc_comment(
+            &docs.to_markdown(self.docs_url_generator, MarkdownStyle::Normal),
+            &self.strip_prefix,
+        )
+    }
+
+    /// Format the target of an intra-doc link as a code span naming the Koka definition it
+    /// refers to. Paths are resolved from their first segment naming a bridge type, so
+    /// `crate::ffi::Foo::bar` and `Foo::bar` are the same.
+    fn fmt_doc_reference(&self, target: &str) -> Option<String> {
+        let tcx = self.c.tcx();
+        let segments = target.split("::").collect::<Vec<_>>();
+        let (start, (id, ty)) = segments.iter().enumerate().find_map(|(i, segment)| {
+            tcx.all_types()
+                .find(|(_, ty)| ty.name().as_str() == *segment && !ty.attrs().disable)
+                .map(|found| (i, found))
+        })?;
+        let name = match segments[start + 1..] {
+            [] => self.fmt_type_name(id).into_owned(),
+            [member] => {
+                let method = ty
+                    .methods()
+                    .iter()
+                    .find(|m| m.name.as_str() == member && !m.attrs.disable);
+                match (method, ty) {
+                    (Some(method), _) => self.fmt_qualified_method_name(method, id),
+                    (None, TypeDef::Enum(e)) => {
+                        let variant = e.variants.iter().find(|v| v.name.as_str() == member)?;
+                        self.fmt_enum_variant(e, variant)
+                    }
+                    (None, TypeDef::Struct(st)) => {
+                        st.fields.iter().find(|f| f.name.as_str() == member)?;
+                        self.fmt_param_name(member).into_owned()
+                    }
+                    (None, TypeDef::OutStruct(st)) => {
+                        st.fields.iter().find(|f| f.name.as_str() == member)?;
+                        self.fmt_param_name(member).into_owned()
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(format!("`{name}`"))
     }
 
     pub fn fmt_destructor_name(&self, id: TypeId) -> String {
@@ -78,22 +297,30 @@ impl<'tcx> KokaFormatter<'tcx> {
     pub fn fmt_type_name(&self, id: TypeId) -> Cow<'tcx, str> {
         let resolved = self.c.tcx().resolve_type(id);
 
-        let candidate: Cow<str> = if let Some(strip_prefix) = self.strip_prefix.as_ref() {
-            resolved
-                .name()
-                .as_str()
-                .strip_prefix(strip_prefix)
-                .unwrap_or(resolved.name().as_str())
-                .into()
-        } else {
-            resolved.name().as_str().into()
-        };
+        let candidate: Cow<str> = self.strip_prefix.strip(resolved.name().as_str()).into();
+
+        self.fmt_type_case(resolved.attrs().rename.apply(candidate))
+    }
 
-        if DISALLOWED_CORE_TYPES.contains(&&*candidate) {
-            panic!("{candidate:?} is not a valid Koka type name. Please rename.");
+    /// Check that a name formatted by [`Self::fmt_type_name`] can be used in Koka, where it
+    /// may not clash with a keyword or a type of the standard library
+    pub fn check_type_name(&self, name: &str) -> Result<(), String> {
+        let snek = name.to_snek_case();
+        if DISALLOWED_CORE_TYPES.contains(&&*snek) || RESERVED_WORDS.contains(&&*snek) {
+            return Err(format!(
+                "`{name}` is not a valid Koka type name, please rename it"
+            ));
         }
+        Ok(())
+    }
+
+    /// Resolve and format a trait name for use in code
+    pub fn fmt_trait_name(&self, id: TraitId) -> Cow<'tcx, str> {
+        let resolved = self.c.tcx().resolve_trait(id);
+
+        let candidate: Cow<str> = self.strip_prefix.strip(resolved.name.as_str()).into();
 
-        resolved.attrs().rename.apply(candidate)
+        self.fmt_type_case(resolved.attrs.rename.apply(candidate))
     }
 
     /// Resolve and format a named type for use in diagnostics
@@ -102,35 +329,59 @@ impl<'tcx> KokaFormatter<'tcx> {
         self.c.fmt_type_name_diagnostics(id)
     }
 
-    /// Format an enum variant.
-    pub fn fmt_enum_variant(&self, variant: &'tcx hir::EnumVariant) -> Cow<'tcx, str> {
-        let name = variant.name.as_str().to_upper_camel_case().into();
-        variant.attrs.rename.apply(name)
+    /// Format an enum variant, see [`enum_variant_names`].
+    pub fn fmt_enum_variant(&self, ty: &hir::EnumDef, variant: &hir::EnumVariant) -> String {
+        let index = ty
+            .variants
+            .iter()
+            .position(|v| std::ptr::eq(v, variant))
+            .expect("variant of another enum");
+        enum_variant_names(ty).swap_remove(index).0
+    }
+
+    /// Check that a variant formatted by [`Self::fmt_enum_variant`] can be used as a Koka
+    /// constructor, which has to start with an uppercase letter
+    pub fn check_enum_variant(&self, name: &str) -> Result<(), String> {
+        let mut chars = name.chars();
+        let valid = chars.next().map_or(false, |c| c.is_ascii_uppercase())
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!(
+                "`{name}` is not a valid Koka constructor name, please rename it"
+            ));
+        }
+        Ok(())
     }
 
     /// Format a field name or parameter name
     // might need splitting in the future if we decide to support renames here
     pub fn fmt_param_name<'a>(&self, ident: &'a str) -> Cow<'a, str> {
-        ident.to_lowercase().to_snek_case().into()
+        self.escape_reserved(self.fmt_value_case(&ident.to_lowercase()))
+            .into()
+    }
+
+    /// Suffix identifiers that Koka reserves with `_`
+    fn escape_reserved(&self, name: String) -> String {
+        if RESERVED_WORDS.contains(&&*name) {
+            format!("{name}_")
+        } else {
+            name
+        }
     }
 
     pub fn fmt_nullable(&self, ident: &str) -> String {
-        format!("{ident}?")
+        format!("maybe<{ident}>")
     }
 
     /// Format a method
     pub fn fmt_method_name(&self, method: &hir::Method) -> String {
-        // TODO(#60): handle other keywords
-        let name = method
-            .attrs
-            .rename
-            .apply(method.name.as_str().into())
-            .to_snek_case();
-        if INVALID_METHOD_NAMES.contains(&&*name) {
-            format!("{name}_")
-        } else {
-            name
-        }
+        let name = self.fmt_value_case(&method.attrs.rename.apply(method.name.as_str().into()));
+        self.escape_reserved(name)
+    }
+
+    /// Format a trait method, which becomes a field of the trait's struct
+    pub fn fmt_trait_method_name(&self, method: &hir::TraitMethod) -> String {
+        self.escape_reserved(self.fmt_value_case(method.name.as_str()))
     }
 
     fn uppercase_first_letter(&self, s: &str) -> String {
@@ -140,34 +391,103 @@ impl<'tcx> KokaFormatter<'tcx> {
             Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
         }
     }
+
+    /// Format a named constructor, a function qualified with its type like `new`. Koka reserves
+    /// capitalized names for types and constructors of data types, so it is a lowercase identifier.
     pub fn fmt_constructor_name(&self, name: &Option<String>, method: &hir::Method) -> String {
-        let name = self.uppercase_first_letter(
-            method
+        let name = self.fmt_value_case(
+            &method
                 .attrs
                 .rename
-                .apply(name.as_deref().unwrap_or(method.name.as_str()).into())
-                .to_snek_case()
-                .as_str(),
+                .apply(name.as_deref().unwrap_or(method.name.as_str()).into()),
         );
+        self.escape_reserved(name)
+    }
 
-        if INVALID_METHOD_NAMES.contains(&name.as_str()) {
-            format!("{name}_")
-        } else {
-            name
+    /// Format the name of the field a getter or setter accesses, `name` if the attribute gives
+    /// one. Otherwise it is the method's name, without the `set_` of setters.
+    pub fn fmt_accessor_name(&self, name: &Option<String>, method: &hir::Method) -> String {
+        let method_name = method.name.as_str();
+        let field = match (name, &method.attrs.special_method) {
+            (Some(name), _) => name.as_str(),
+            (None, Some(SpecialMethod::Setter(_))) => {
+                method_name.strip_prefix("set_").unwrap_or(method_name)
+            }
+            (None, _) => method_name,
+        };
+        let name = self.fmt_value_case(&method.attrs.rename.apply(field.into()));
+        self.escape_reserved(name)
+    }
+
+    /// Format the name of a getter or setter, called with dot syntax like a field: `x.name` and
+    /// `x.set-name(value)`.
+    ///
+    /// Koka already defines an accessor for every field of a struct, so a getter named like one
+    /// of them is prefixed with `get-` instead of becoming ambiguous.
+    fn fmt_accessor_method_name(&self, id: TypeId, method: &hir::Method) -> String {
+        match &method.attrs.special_method {
+            Some(SpecialMethod::Getter(name)) => {
+                let name = self.fmt_accessor_name(name, method);
+                let is_field = match self.c.tcx().resolve_type(id) {
+                    TypeDef::Struct(st) => st
+                        .fields
+                        .iter()
+                        .any(|field| self.fmt_param_name(field.name.as_str()) == name),
+                    TypeDef::OutStruct(st) => st
+                        .fields
+                        .iter()
+                        .any(|field| self.fmt_param_name(field.name.as_str()) == name),
+                    // `raw` is reserved, and the edge fields are kebab-case, which accessor
+                    // names never are
+                    _ => false,
+                };
+                if is_field {
+                    format!("get-{name}")
+                } else {
+                    name
+                }
+            }
+            Some(SpecialMethod::Setter(name)) => {
+                format!("set-{}", self.fmt_accessor_name(name, method))
+            }
+            _ => unreachable!("not an accessor"),
         }
     }
 
-    pub fn fmt_accessor_name(&self, name: &Option<String>, method: &hir::Method) -> String {
-        let name = method
-            .attrs
-            .rename
-            .apply(name.as_deref().unwrap_or(method.name.as_str()).into())
-            .to_snek_case();
+    /// Format the name a method is declared and called by, qualified with its type for static methods
+    pub fn fmt_qualified_method_name(&self, method: &hir::Method, id: TypeId) -> String {
+        let type_name = self.fmt_type_name(id);
+        let qualifier = self.fmt_type_qualifier(&type_name);
 
-        if INVALID_FIELD_NAMES.contains(&name.as_str()) {
-            format!("{name}_")
-        } else {
-            name
+        match &method.attrs.special_method {
+            Some(SpecialMethod::Constructor) => format!("{qualifier}/new"),
+            Some(SpecialMethod::NamedConstructor(name)) => {
+                format!("{qualifier}/{}", self.fmt_constructor_name(name, method))
+            }
+            // Without `self` there is nothing to call them on with dot syntax
+            Some(SpecialMethod::Getter(..) | SpecialMethod::Setter(..))
+                if method.param_self.is_none() =>
+            {
+                format!("{qualifier}/{}", self.fmt_accessor_method_name(id, method))
+            }
+            Some(SpecialMethod::Getter(..) | SpecialMethod::Setter(..)) => {
+                self.fmt_accessor_method_name(id, method)
+            }
+            Some(SpecialMethod::Comparison) => "cmp".into(),
+            Some(SpecialMethod::Iterator) => "next".into(),
+            Some(SpecialMethod::Iterable) => "iterator".into(),
+            Some(SpecialMethod::Indexer) => "@index".into(),
+            // Stringifiers, hash functions and default constructors keep their name,
+            // `show`, `hash` and `with-defaults` call them
+            Some(SpecialMethod::Stringifier | SpecialMethod::Hash | SpecialMethod::Default)
+            | None
+                if method.param_self.is_none() =>
+            {
+                format!("{qualifier}/{}", self.fmt_method_name(method))
+            }
+            Some(SpecialMethod::Stringifier | SpecialMethod::Hash | SpecialMethod::Default)
+            | None => self.fmt_method_name(method),
+            Some(special) => unimplemented!("Found unknown special method type {special:?}"),
         }
     }
 
@@ -175,6 +495,172 @@ impl<'tcx> KokaFormatter<'tcx> {
         self.c.fmt_method_name(ty, method).into()
     }
 
+    /// Format the name of the C struct a callback parameter is passed as
+    pub fn fmt_c_callback_name(&self, c_method_name: &str, param_name: &str) -> String {
+        self.c.fmt_callback_name(c_method_name, param_name)
+    }
+
+    /// Format the name of the C vtable struct a trait object is passed as
+    pub fn fmt_c_trait_vtable_name(&self, id: TraitId) -> String {
+        self.c.fmt_trait_vtable_name(id)
+    }
+
+    /// Format the path of the C header declaring a trait's vtable struct
+    pub fn fmt_c_trait_header_path(&self, id: TraitId) -> String {
+        self.c.fmt_trait_header_path(id)
+    }
+
+    /// Format the path of the C header declaring a type's methods
+    pub fn fmt_c_impl_header_path(&self, id: TypeId) -> String {
+        self.c.fmt_impl_header_path(id)
+    }
+
+    pub fn fmt_primitive_as_c(&self, prim: hir::PrimitiveType) -> Cow<'static, str> {
+        self.c.fmt_primitive_as_c(prim)
+    }
+
+    /// Format the C view type of a primitive slice
+    pub fn fmt_primitive_slice_name(&self, prim: hir::PrimitiveType) -> &'static str {
+        self.c.fmt_primitive_slice_name(prim)
+    }
+
+    /// Format the name of the helper converting primitive slices, named after the C element type
+    pub fn fmt_primitive_slice_helper(&self, prim: hir::PrimitiveType) -> String {
+        format!(
+            "slice-{}",
+            self.fmt_primitive_as_c(prim).trim_end_matches("_t")
+        )
+    }
+
+    /// Format the Koka view type primitive slice parameters take
+    pub fn fmt_primitive_view_type(&self, prim: hir::PrimitiveType) -> String {
+        format!("{}-view", self.fmt_primitive_slice_helper(prim))
+    }
+
+    /// Format the C expressions boxing a primitive into a Koka value and unboxing it again,
+    /// as needed when calling Koka closures from C
+    pub fn fmt_primitive_box(&self, prim: hir::PrimitiveType, expr: &str) -> String {
+        match prim {
+            hir::PrimitiveType::Bool => format!("kk_bool_box({expr})"),
+            _ => format!(
+                "kk_{}_box({expr}, _ctx)",
+                self.fmt_primitive_box_suffix(prim)
+            ),
+        }
+    }
+
+    pub fn fmt_primitive_unbox(&self, prim: hir::PrimitiveType, expr: &str) -> String {
+        match prim {
+            hir::PrimitiveType::Bool => format!("kk_bool_unbox({expr})"),
+            _ => format!(
+                "kk_{}_unbox({expr}, KK_OWNED, _ctx)",
+                self.fmt_primitive_box_suffix(prim)
+            ),
+        }
+    }
+
+    fn fmt_primitive_box_suffix(&self, prim: hir::PrimitiveType) -> &'static str {
+        match self.fmt_primitive_as_ffi(prim, false) {
+            "ssize_t" => "ssize",
+            "float32" => "float",
+            "float64" => "double",
+            other => other,
+        }
+    }
+
+    /// Format the name of the `extern` declaration binding a C method
+    pub fn fmt_ffi_method_name(&self, ty: TypeId, method: &hir::Method) -> String {
+        self.c.fmt_method_name(ty, method).to_snek_case()
+    }
+
+    /// Format the name of the `extern` declaration binding a type's destructor
+    pub fn fmt_ffi_destructor_name(&self, id: TypeId) -> String {
+        self.c.fmt_dtor_name(id).to_snek_case()
+    }
+
+    /// Format the name of the `extern` declaration boxing an owned opaque with its finalizer
+    pub fn fmt_ffi_own_name(&self, id: TypeId) -> String {
+        format!("{}_own", self.c.fmt_type_name(id)).to_snek_case()
+    }
+
+    /// Format the name of the `extern` checking that an opaque that is not `Send` stays on the
+    /// thread that created it
+    pub fn fmt_ffi_check_thread_name(&self, id: TypeId) -> String {
+        format!("{}_check_thread", self.c.fmt_type_name(id)).to_snek_case()
+    }
+
+    /// Format the name of a type in C
+    pub fn fmt_c_type_name(&self, id: TypeId) -> Cow<'tcx, str> {
+        self.c.fmt_type_name(id)
+    }
+
+    /// Format the name of the C function destroying an opaque when Koka drops it
+    pub fn fmt_c_finalizer_name(&self, id: TypeId) -> String {
+        format!("{}_finalize", self.c.fmt_type_name(id))
+    }
+
+    /// Format the name of the C function handing an opaque back to Rust for a method consuming it
+    pub fn fmt_c_take_name(&self, id: TypeId) -> String {
+        format!("{}_take", self.c.fmt_type_name(id))
+    }
+
+    /// Format the name of the C cache of the live boxes of an opaque
+    pub fn fmt_c_cache_name(&self, id: TypeId) -> String {
+        format!("{}_cache", self.c.fmt_type_name(id))
+    }
+
+    /// Format the name of the C function boxing an opaque through its cache
+    pub fn fmt_c_wrap_name(&self, id: TypeId) -> String {
+        format!("{}_wrap", self.c.fmt_type_name(id))
+    }
+
+    /// Format the name of the C function unboxing an opaque that methods may have consumed
+    pub fn fmt_c_unbox_name(&self, id: TypeId) -> String {
+        format!("{}_unbox", self.c.fmt_type_name(id))
+    }
+
+    /// Format the name of the C struct a method returning `Result<ok, err>` or `Option<ok>` uses
+    pub fn fmt_c_result_name(
+        &self,
+        ok: Option<&'tcx hir::OutType>,
+        err: Option<&'tcx hir::OutType>,
+    ) -> String {
+        let ok = ok.map_or("void".into(), |o| self.c.fmt_type_name_uniquely(o));
+        let err = err.map_or("void".into(), |e| self.c.fmt_type_name_uniquely(e));
+        self.c.fmt_result_name(&ok, &err)
+    }
+
+    /// Format the name of the C struct an `Option<value>` parameter or `DiplomatOption<value>`
+    /// field is passed as
+    pub fn fmt_c_option_name<P: hir::TyPosition>(&self, value: &hir::Type<P>) -> String {
+        self.c
+            .fmt_option_name(&self.c.fmt_type_name_uniquely(value))
+    }
+
+    /// Format the name of the helper wrapping a C result or option struct
+    pub fn fmt_result_helper_name(&self, c_result_name: &str) -> String {
+        c_result_name.to_snek_case()
+    }
+
+    /// Format the `exception-info` constructor thrown for errors of a type
+    pub fn fmt_exception_name(&self, type_name: &str) -> String {
+        format!("Exn{type_name}")
+    }
+
+    /// Format a namespace as the path of the Koka module hierarchy it is generated into
+    pub fn fmt_namespace_path(&self, namespace: &str) -> String {
+        namespace
+            .split("::")
+            .map(|segment| segment.to_snek_case())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Format the qualifier used to namespace static methods of a type
+    pub fn fmt_type_qualifier(&self, type_name: &str) -> String {
+        self.fmt_value_case(type_name)
+    }
+
     pub fn fmt_string(&self) -> &'static str {
         "string"
     }
@@ -203,24 +689,55 @@ impl<'tcx> KokaFormatter<'tcx> {
         self.fmt_primitive_as_ffi(hir::PrimitiveType::IntSize(hir::IntSizeType::Usize), cast)
     }
 
-    pub fn fmt_type_as_ident(&self, ty: Option<&str>) -> String {
-        ty.unwrap_or("()").replace('-', "")
-    }
-
     pub fn fmt_enum_as_ffi(&self, cast: bool) -> &'static str {
         self.fmt_primitive_as_ffi(hir::PrimitiveType::Int(hir::IntType::I32), cast)
     }
 
+    /// The Koka type of a primitive. Pointer-sized integers are passed as `ssize_t`, which has
+    /// the width of `size_t` on every target, but exposed as `int` and converted with the range
+    /// checks of the `usize` and `isize` helpers in `lib`.
+    pub fn fmt_primitive_as_koka(&self, prim: hir::PrimitiveType) -> &'static str {
+        match prim {
+            hir::PrimitiveType::IntSize(_) => "int",
+            _ => self.fmt_primitive_as_ffi(prim, true),
+        }
+    }
+
+    /// Format a Rust literal of a primitive type as a Koka literal, if Koka has one for the type
+    pub fn fmt_primitive_literal(&self, prim: hir::PrimitiveType, literal: &str) -> Option<String> {
+        use diplomat_core::hir::{FloatType, PrimitiveType};
+        match prim {
+            PrimitiveType::Bool => Some(if literal == "true" { "True" } else { "False" }.into()),
+            PrimitiveType::Int(_) | PrimitiveType::IntSize(_) => Some(literal.into()),
+            PrimitiveType::Float(size) => {
+                // Read the literal at the precision of its type, as Rust does
+                let value = match size {
+                    FloatType::F32 => literal.parse::<f32>().ok()? as f64,
+                    FloatType::F64 => literal.parse::<f64>().ok()?,
+                };
+                let mut literal = format!("{value:?}");
+                // Koka float literals need a fraction before the exponent
+                if !literal.contains('.') {
+                    let exponent = literal.find('e').unwrap_or(literal.len());
+                    literal.insert_str(exponent, ".0");
+                }
+                Some(literal)
+            }
+            PrimitiveType::Char | PrimitiveType::Byte | PrimitiveType::Int128(_) => None,
+        }
+    }
+
     pub fn fmt_primitive_as_ffi(&self, prim: hir::PrimitiveType, cast: bool) -> &'static str {
-        use diplomat_core::hir::{FloatType, IntSizeType, IntType, PrimitiveType};
+        use diplomat_core::hir::{FloatType, IntType, PrimitiveType};
         if cast {
             match prim {
                 PrimitiveType::Bool => "bool",
                 PrimitiveType::Char => "char",
-                PrimitiveType::Int(_) | PrimitiveType::IntSize(_) => "int",
+                PrimitiveType::Int(_) => "int",
+                PrimitiveType::IntSize(_) => "ssize_t",
                 PrimitiveType::Byte => "int8",
                 PrimitiveType::Float(_) => "float64",
-                PrimitiveType::Int128(_) => panic!("i128 not supported in Dart"),
+                PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
             }
         } else {
             match prim {
@@ -234,11 +751,10 @@ impl<'tcx> KokaFormatter<'tcx> {
                 PrimitiveType::Int(IntType::U32) => "int32",
                 PrimitiveType::Int(IntType::I64) => "int64",
                 PrimitiveType::Int(IntType::U64) => "int64",
-                PrimitiveType::IntSize(IntSizeType::Isize) => "intptr_t",
-                PrimitiveType::IntSize(IntSizeType::Usize) => "ssize_t",
+                PrimitiveType::IntSize(_) => "ssize_t",
                 PrimitiveType::Float(FloatType::F32) => "float32",
                 PrimitiveType::Float(FloatType::F64) => "float64",
-                PrimitiveType::Int128(_) => panic!("i128 not supported in Dart"),
+                PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
             }
         }
     }
@@ -251,7 +767,7 @@ impl<'tcx> KokaFormatter<'tcx> {
             PrimitiveType::Byte => "bytes",
             PrimitiveType::Int(_) | PrimitiveType::IntSize(_) => "list<int>",
             PrimitiveType::Float(_) => "list<float64>",
-            PrimitiveType::Int128(_) => panic!("i128 not supported in Dart"),
+            PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
         }
     }
 
@@ -273,7 +789,7 @@ impl<'tcx> KokaFormatter<'tcx> {
             PrimitiveType::IntSize(IntSizeType::Isize) => ".isizeView",
             PrimitiveType::Float(FloatType::F32) => ".float32View",
             PrimitiveType::Float(FloatType::F64) => ".float64View",
-            PrimitiveType::Int128(_) => panic!("i128 not supported in Dart"),
+            PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
         }
     }
 
@@ -294,7 +810,7 @@ impl<'tcx> KokaFormatter<'tcx> {
             PrimitiveType::IntSize(IntSizeType::Isize) => "_SliceIsize",
             PrimitiveType::Float(FloatType::F32) => "_SliceFloat",
             PrimitiveType::Float(FloatType::F64) => "_SliceDouble",
-            PrimitiveType::Int128(_) => panic!("i128 not supported in Dart"),
+            PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
         }
     }
 
@@ -306,3 +822,79 @@ impl<'tcx> KokaFormatter<'tcx> {
         "_SliceUtf16"
     }
 }
+
+/// kebab-case as Koka accepts it: a dash has to be followed by a letter, so numbers are
+/// attached to the word before them, `value2` rather than `value-2`
+fn kebab_case(name: &str) -> String {
+    let kebab = name.to_kebab_case();
+    let mut cased = String::with_capacity(kebab.len());
+    let mut chars = kebab.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '-' || chars.peek().map_or(false, |next| next.is_alphabetic()) {
+            cased.push(c);
+        }
+    }
+    cased
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kebab_case, KokaFormatter, Naming, DISALLOWED_CORE_TYPES, RESERVED_WORDS};
+    use crate::common::keyword_tcx;
+    use diplomat_core::ast::DocsUrlGenerator;
+
+    fn is_valid_ident(name: &str) -> bool {
+        !RESERVED_WORDS.contains(&name)
+            && name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        let words: Vec<&str> = RESERVED_WORDS
+            .iter()
+            .chain(DISALLOWED_CORE_TYPES)
+            .copied()
+            .collect();
+        let tcx = keyword_tcx(&words, &words);
+        let docs_urls = DocsUrlGenerator::default();
+        let methods = &tcx
+            .opaques()
+            .iter()
+            .find(|ty| ty.name.as_str() == "KeywordHost")
+            .unwrap()
+            .methods;
+        assert_eq!(methods.len(), words.len());
+
+        for naming in [Naming::Snake, Naming::Kebab, Naming::Camel] {
+            let formatter = KokaFormatter::new(&tcx, &docs_urls, Default::default(), naming);
+            for method in methods {
+                let name = formatter.fmt_method_name(method);
+                assert!(is_valid_ident(&name), "{naming:?} method name {name:?}");
+                let name = formatter.fmt_accessor_name(&None, method);
+                assert!(is_valid_ident(&name), "{naming:?} accessor name {name:?}");
+            }
+            for word in &words {
+                let name = formatter.fmt_param_name(word);
+                assert!(is_valid_ident(&name), "{naming:?} param name {name:?}");
+            }
+
+            for (id, ty) in tcx.all_types() {
+                if ty.name().as_str() != "KeywordHost" {
+                    let name = formatter.fmt_type_name(id);
+                    assert!(formatter.check_type_name(&name).is_err(), "{name:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_kebab_case() {
+        assert_eq!(kebab_case("to_string"), "to-string");
+        assert_eq!(kebab_case("MyType"), "my-type");
+        assert_eq!(kebab_case("value_2"), "value2");
+        assert_eq!(kebab_case("utf8_to_utf16"), "utf8-to-utf16");
+    }
+}
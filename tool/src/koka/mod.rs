@@ -1,40 +1,402 @@
-use crate::common::{ErrorStore, FileMap};
+use crate::common::{ErrorStore, FileMap, NameRegistry, StripPrefix};
+use crate::layout::PointerWidth;
 use askama::Template;
 use diplomat_core::ast::DocsUrlGenerator;
 use diplomat_core::hir::borrowing_param::{
-    BorrowedLifetimeInfo, LifetimeEdge, LifetimeEdgeKind, ParamBorrowInfo, StructBorrowInfo,
+    BorrowedLifetimeInfo, LifetimeEdge, LifetimeEdgeKind, ParamBorrowInfo,
 };
 use diplomat_core::hir::TypeContext;
 use diplomat_core::hir::{
-    self, Lifetime, LifetimeEnv, MaybeStatic, OpaqueOwner, ReturnType, SelfType, SpecialMethod,
-    SpecialMethodPresence, StructPathLike, SuccessType, TyPosition, Type, TypeDef, TypeId,
+    self, Lifetime, LifetimeEnv, MaybeStatic, OpaqueOwner, ReturnType, SelfType,
+    SpecialMethodPresence, StructPathLike, SuccessType, TraitId, TyPosition, Type, TypeDef, TypeId,
 };
 use formatter::KokaFormatter;
+use modules::{ModuleItem, Modules};
+use overrides::{Overrides, Part};
+use serde::Deserialize;
 use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+pub use attrs::{attr_support, KokaAttributeValidator};
+
+mod attrs;
+mod docs;
 mod formatter;
+mod modules;
+mod overrides;
+mod package;
+mod readme;
+mod smoke;
+mod values;
+
+/// Identifiers declared by the runtime support in `lib.kk` that every generated file imports
+const RUNTIME_NAMES: &[&str] = &[
+    "batch",
+    "batch-t",
+    "with-batch",
+    "writeable-t",
+    "with-writeable",
+    "ExnDiplomat",
+    "null-pointer",
+    "is-null",
+    "rust-object",
+    "diplomat-thread",
+];
+
+/// Options that can be set in the library config file passed to the Koka backend.
+///
+/// Any of them can also be set in a named profile, a `[profiles.<name>]` table whose options
+/// take precedence when the profile is selected with `--profile` or the top-level `profile`
+/// key, e.g. to generate bindings with full checks locally and minimal ones for release.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KokaConfig {
+    /// How fallible methods report their errors
+    pub error_style: ErrorStyle,
+    /// How structs are declared on the Koka side
+    pub struct_layout: StructLayout,
+    /// How the Rust objects behind opaques are destroyed
+    pub destruction: Destruction,
+    /// How much the bindings check values before passing them to Rust
+    pub checks: Checks,
+    /// How the bindings are split into modules
+    pub module_layout: ModuleLayout,
+    /// Added in front of the name of every generated module but `lib`, e.g. to keep a type
+    /// `List` from sharing the qualifier of `std/core/list`
+    pub module_prefix: Option<String>,
+    /// Added to the end of the name of every generated module but `lib`
+    pub module_suffix: Option<String>,
+    /// The name of the entry module re-exporting all generated modules, `diplomat` by default
+    pub package: Option<String>,
+    /// The name of the Rust library to link against
+    pub library: Option<String>,
+    /// The directory the Rust library is built into
+    pub library_dir: Option<String>,
+    /// The directory of the C headers, which the generated C files include. Ignored when the
+    /// headers are emitted along with the bindings.
+    pub c_include_dir: Option<String>,
+    /// Whether to generate `with-leak-report`, printing the Rust objects a program never
+    /// released. Needs the `leak-report` feature of diplomat-runtime.
+    pub leak_report: bool,
+    /// Whether a Rust pointer returned again while its opaque is alive, e.g. by an accessor
+    /// of its parent, is wrapped in the same Koka object rather than a new one, so its
+    /// finalizer is registered once. The cache of each type is not synchronized, so its
+    /// objects must stay on one thread. Only for opaques destroyed by a finalizer.
+    pub wrapper_cache: bool,
+    /// Whether `&str` parameters Rust only reads during the call are copied into temporary
+    /// memory, instead of passed as a pointer into the UTF-8 buffer of the Koka string.
+    /// Strings Rust keeps borrowing after the call are always copied.
+    pub copy_strings: bool,
+    /// How the identifiers of the generated API are cased, overridden by `--naming`
+    pub naming: Naming,
+    /// Whether to generate `smoke.kk`, whose `main` calls every method with placeholder
+    /// arguments, to check that the bindings compile and run
+    pub smoke_tests: bool,
+    /// The methods the smoke tests don't call, as `Type::method`, e.g. those panicking on
+    /// placeholder arguments
+    pub smoke_test_skip: Vec<String>,
+    /// A directory of templates taking the place of parts of the bindings, relative to the
+    /// config file: `prelude.kk`, `method.kk`, `opaque.kk` and `enum.kk`. See [`overrides`].
+    pub templates: Option<PathBuf>,
+    /// The pointer width of the target the bindings are for, checked when they are compiled.
+    /// Set with `--target`, bindings for any target are generated without it.
+    #[serde(skip)]
+    pub pointer_width: Option<PointerWidth>,
+    /// Whether to generate the `c2` headers the generated C files include, from the same
+    /// [`TypeContext`], into an `include` directory next to the bindings. Set with
+    /// `--emit-c-headers`.
+    #[serde(skip)]
+    pub emit_c_headers: bool,
+    /// Whether to generate the runtime support and every type into a single module named
+    /// after the `package`, instead of `lib`, the modules of the namespaces and the entry
+    /// module re-exporting them. The FFI declarations are then private to it. The C files of
+    /// finalizers and callbacks are still generated next to it, as Koka can't inline them.
+    /// Set with `--bundle`.
+    #[serde(skip)]
+    pub bundle: bool,
+}
+
+impl KokaConfig {
+    /// Reads a library config, with the options of `profile` taking precedence, or of the
+    /// `profile` the config selects if `None`. The `templates` directory is resolved relative
+    /// to the config file.
+    pub fn read(conf_path: &Path, profile: Option<&str>) -> Self {
+        let conf_str = std::fs::read_to_string(conf_path)
+            .unwrap_or_else(|err| panic!("Failed to open config file {conf_path:?}: {err}"));
+        let mut conf = toml::from_str::<toml::value::Table>(&conf_str)
+            .unwrap_or_else(|err| panic!("Failed to parse config file {conf_path:?}: {err}"));
+
+        let profiles = conf.remove("profiles");
+        let default_profile = conf.remove("profile");
+        let profile = profile.or_else(|| default_profile.as_ref().and_then(toml::Value::as_str));
+        if let Some(profile) = profile {
+            let options = profiles
+                .as_ref()
+                .and_then(|profiles| profiles.get(profile))
+                .and_then(toml::Value::as_table)
+                .unwrap_or_else(|| {
+                    panic!("Profile `{profile}` is not defined in config file {conf_path:?}")
+                });
+            conf.extend(options.clone());
+        }
+
+        let mut config: KokaConfig = toml::Value::Table(conf)
+            .try_into()
+            .unwrap_or_else(|err| panic!("Failed to parse config file {conf_path:?}: {err}"));
+        if let (Some(templates), Some(dir)) = (&mut config.templates, conf_path.parent()) {
+            *templates = dir.join(&*templates);
+        }
+        config
+    }
+}
+
+/// The directory the C headers are emitted into with `--emit-c-headers`
+const C_HEADERS_DIR: &str = "include";
+
+/// The module of the smoke tests, generated with `smoke-tests`
+const SMOKE_TEST_MODULE: &str = "smoke";
+
+/// How methods returning `Result<T, E>` are exposed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorStyle {
+    /// Return `T` and throw a typed exception carrying `E` using the `exn` effect
+    Exn,
+    /// Return an `either<E, T>`, spelled `result<T, E>` with the alias the bindings declare
+    Either,
+}
+
+impl Default for ErrorStyle {
+    fn default() -> Self {
+        ErrorStyle::Exn
+    }
+}
+
+/// How structs are laid out in Koka
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StructLayout {
+    /// `struct`s with their fields in declaration order, read from C one field at a time
+    Declared,
+    /// `value struct`s with the boxed fields first, mirroring the layout of Koka constructors,
+    /// converted from C by calling the constructor on the fields directly. This lets the reuse
+    /// analysis update them in place instead of going through boxed intermediates.
+    Fbip,
+}
+
+impl Default for StructLayout {
+    fn default() -> Self {
+        StructLayout::Declared
+    }
+}
+
+/// How the functions, parameters, fields and types of the generated API are cased
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Naming {
+    /// `to_string` and `my_field`, with types keeping the `MyType` of their Rust name
+    Snake,
+    /// `to-string`, `my-field` and `My-type`, the casing of the Koka standard library. Types
+    /// stay capitalized since the name of a struct is also its constructor.
+    Kebab,
+    /// `toString`, `myField` and `MyType`
+    Camel,
+}
+
+impl FromStr for Naming {
+    type Err = String;
+
+    fn from_str(naming: &str) -> Result<Self, String> {
+        toml::Value::String(naming.into())
+            .try_into()
+            .map_err(|_| format!("Unknown naming `{naming}`, expected kebab, snake or camel"))
+    }
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Naming::Snake
+    }
+}
+
+/// How opaques release the Rust object they wrap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Destruction {
+    /// Box the pointer with a finalizer calling the destructor, run as soon as Perceus drops
+    /// the last reference to the opaque. Opaques are passed to C as borrowed parameters so
+    /// they stay alive for the whole call.
+    Finalizer,
+    /// Keep the bare pointer and generate a `free` function that has to be called explicitly
+    Manual,
+}
+
+impl Default for Destruction {
+    fn default() -> Self {
+        Destruction::Finalizer
+    }
+}
+
+/// How values are checked before crossing into Rust
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Checks {
+    /// Range check pointer-sized integers for the target, and check that chars are Unicode
+    /// scalar values as Rust expects, throwing `ExnRange` from the methods taking them
+    Full,
+    /// Truncate pointer-sized integers to the target and pass chars on without checking,
+    /// keeping methods `io-noexn`
+    Minimal,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Checks::Full
+    }
+}
+
+/// How the generated bindings are split into Koka modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ModuleLayout {
+    /// Everything in the `lib` module next to the runtime support, or in the module of its namespace
+    Single,
+    /// A module per type and trait, importing the modules of the types it uses. Types that
+    /// depend on each other share a module, since Koka does not allow import cycles.
+    FilePerType,
+}
+
+impl Default for ModuleLayout {
+    fn default() -> Self {
+        ModuleLayout::Single
+    }
+}
 
-/// Run file generation
+/// Describes each enum variant whose Koka constructor is not named after it, because the name
+/// would have been ambiguous.
+pub fn check_enum_variant_names(tcx: &TypeContext) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (_, ty) in tcx.all_types() {
+        let TypeDef::Enum(e) = ty else { continue };
+        if e.attrs.disable {
+            continue;
+        }
+        for (variant, (name, reason)) in e.variants.iter().zip(formatter::enum_variant_names(e)) {
+            if let Some(reason) = reason {
+                warnings.push(format!(
+                    "Variant `{}::{}` is generated as the Koka constructor `{name}`, as {reason}",
+                    e.name, variant.name
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Run file generation, with a config read with [`KokaConfig::read()`] or built in code.
+///
+/// Nothing is written to disk: build scripts and other tools can post-process the returned
+/// files before writing them out. Returns the errors found along with the files, which leave
+/// out the types and traits the errors were found in.
 pub fn run<'cx>(
     tcx: &'cx TypeContext,
+    config: KokaConfig,
     docs_url_generator: &'cx DocsUrlGenerator,
-    strip_prefix: Option<String>,
-) -> Result<FileMap, Vec<(impl Display + 'cx, String)>> {
-    let formatter = KokaFormatter::new(tcx, docs_url_generator, strip_prefix);
+    strip_prefix: StripPrefix,
+) -> (FileMap, Vec<(impl Display + 'cx, String)>) {
+    let KokaConfig {
+        error_style,
+        struct_layout,
+        destruction,
+        checks,
+        module_layout,
+        module_prefix,
+        module_suffix,
+        package,
+        library,
+        library_dir,
+        c_include_dir,
+        leak_report,
+        wrapper_cache,
+        copy_strings,
+        naming,
+        smoke_tests,
+        smoke_test_skip,
+        templates,
+        pointer_width,
+        emit_c_headers,
+        bundle,
+    } = config;
+    let package_name = package.unwrap_or_else(|| "diplomat".into());
+
+    let overrides = templates.map_or_else(Overrides::default, |dir| Overrides::load(&dir));
+
+    let formatter = KokaFormatter::new(tcx, docs_url_generator, strip_prefix, naming);
+    let modules = Modules::new(
+        tcx,
+        &formatter,
+        module_layout,
+        module_prefix.as_deref().unwrap_or_default(),
+        module_suffix.as_deref().unwrap_or_default(),
+        bundle.then(|| package_name.as_str()),
+    );
 
     let files = FileMap::default();
     let errors = ErrorStore::default();
 
+    for (module, std) in modules.std_clashes() {
+        errors.push_error(format!(
+            "Module `{module}` has the same qualifier as `{std}` from the Koka standard library, please set a `module-prefix` or `module-suffix` in the config"
+        ));
+    }
+    let names = NameRegistry::default();
+
+    for name in RUNTIME_NAMES {
+        // The runtime names are distinct, this cannot fail
+        let _ = names.reserve(name, "the Diplomat runtime");
+    }
+
     let mut directives = BTreeSet::default();
     let mut helper_classes = BTreeMap::default();
 
+    // Types used as errors get an `exception-info` constructor in their own module, so we need
+    // to know all of them before generating any type
+    let mut error_types = BTreeSet::new();
+    if error_style == ErrorStyle::Exn {
+        for (_, ty) in tcx.all_types() {
+            for method in ty.methods() {
+                if let ReturnType::Fallible(_, Some(ref e)) = method.output {
+                    match *e {
+                        Type::Opaque(ref o) => error_types.insert(o.tcx_id.into()),
+                        Type::Struct(ref s) => error_types.insert(s.id()),
+                        Type::Enum(ref e) => error_types.insert(e.tcx_id.into()),
+                        _ => false,
+                    };
+                }
+            }
+        }
+    }
+
     let mut tgcx = TyGenContext {
         tcx,
         errors: &errors,
+        names: &names,
         helper_classes: &mut helper_classes,
         formatter: &formatter,
+        modules: &modules,
+        error_style,
+        struct_layout,
+        destruction,
+        wrapper_cache,
+        copy_strings,
+        checks,
+        error_types: &error_types,
+        overrides: &overrides,
+        callbacks: Vec::new(),
+        traits: BTreeSet::new(),
     };
 
     // Needed for ListStringView
@@ -44,41 +406,213 @@ pub fn run<'cx>(
         hir::StringEncoding::UnvalidatedUtf16,
     ));
 
+    // The declarations of each module, in the order their types are declared
+    let mut module_bodies = BTreeMap::<&str, Vec<String>>::new();
+    // The types generated without errors
+    let mut generated = BTreeSet::new();
+
     for (id, ty) in tcx.all_types() {
         if ty.attrs().disable {
             continue;
         }
 
-        let (file_name, body) = tgcx.gen(id);
+        let error_count = errors.error_count();
+        let (body, c_files) = tgcx.gen(id);
+        if errors.error_count() > error_count {
+            continue;
+        }
+        generated.insert(id);
+
+        module_bodies
+            .entry(modules.module(ModuleItem::Type(id)))
+            .or_default()
+            .push(body);
+        for (c_file_name, c_file) in c_files {
+            files.add_file(c_file_name, c_file);
+        }
+    }
+
+    for (id, trt) in tcx.all_traits() {
+        if trt.attrs.disable {
+            continue;
+        }
 
-        files.add_file(
-            file_name,
-            render_class(body, BTreeSet::from_iter([]), Default::default()),
+        let error_count = errors.error_count();
+        let (body, (trampolines_file_name, trampolines)) = tgcx.gen_trait(id);
+        if errors.error_count() > error_count {
+            continue;
+        }
+
+        module_bodies
+            .entry(modules.module(ModuleItem::Trait(id)))
+            .or_default()
+            .push(body);
+        files.add_file(trampolines_file_name, trampolines);
+    }
+
+    if leak_report {
+        helper_classes.insert(
+            "writeable".into(),
+            include_str!("../../templates/koka/writeable.kk").into(),
+        );
+        helper_classes.insert(
+            "leak-report".into(),
+            include_str!("../../templates/koka/leak_report.kk").into(),
         );
     }
 
+    // Helpers are only known once all types have been generated, check them against the type names last
+    for name in helper_classes.keys().filter(|name| *name != "writeable") {
+        if let Err(e) = names.reserve(name, format_args!("generated helper `{name}`")) {
+            errors.push_error(e);
+        }
+    }
+
+    if helper_classes.contains_key("utf16") {
+        files.add_file(
+            "diplomat_utf16.c".into(),
+            include_str!("../../templates/koka/utf16.c").into(),
+        );
+    }
+    if helper_classes.contains_key("bytes") {
+        files.add_file(
+            "diplomat_bytes.c".into(),
+            include_str!("../../templates/koka/bytes.c").into(),
+        );
+    }
+    if helper_classes.contains_key("kept-batch") {
+        files.add_file(
+            "diplomat_kept_batch.c".into(),
+            include_str!("../../templates/koka/kept_batch.c").into(),
+        );
+    }
+    if helper_classes.contains_key("async") {
+        directives.insert(formatter.fmt_import("std/async", None));
+    }
     directives.insert(formatter.fmt_import("std/core/cextern", None));
     directives.insert(formatter.fmt_import("std/core/int64", None));
     directives.insert(formatter.fmt_import("std/core/int32", None));
     directives.insert(formatter.fmt_import("std/core/float64", None));
 
-    files.add_file(
-        formatter.fmt_file_name("lib"),
-        render_class(
-            include_str!("../../templates/koka/init.kk").into(),
-            directives,
-            helper_classes,
-        ),
-    );
+    let mut package = package::PackageGenContext {
+        formatter: &formatter,
+        name: package_name,
+        library,
+        library_dir,
+        c_include_dir,
+        modules: vec![modules.lib().into()],
+        bundle,
+        readme: false,
+        smoke_tests: false,
+    };
+
+    let mut lib_body = String::from(include_str!("../../templates/koka/init.kk"));
+    if let Some(width) = pointer_width {
+        // Generated for a known target, the width is a constant that fails to compile, as an
+        // array of negative size, for any other
+        let bits = width.bits();
+        lib_body = lib_body.replace(
+            "c inline \"(int32_t)(sizeof(size_t) * 8)\"",
+            &format!(
+                "c inline \"(int32_t)(sizeof(char[sizeof(size_t) * 8 == {bits} ? 1 : -1]) * {bits})\""
+            ),
+        );
+    }
+    lib_body = overrides.apply(Part::Prelude, lib_body, &[]);
+    for (module, bodies) in module_bodies {
+        if module == modules.lib() {
+            for body in bodies {
+                lib_body.push_str("\n\n");
+                lib_body.push_str(&body);
+            }
+            continue;
+        }
+
+        let mut module_directives = directives.clone();
+        for import in modules.imports(module) {
+            module_directives.insert(formatter.fmt_import(import, None));
+        }
+        files.add_file(
+            formatter.fmt_file_name(module),
+            render_class(bodies.join("\n\n"), module_directives, Default::default()),
+        );
+        package.modules.push(module.into());
+    }
+
+    let mut lib = render_class(lib_body, directives, helper_classes);
+    if bundle {
+        // The FFI declarations are only public for the other modules to call
+        lib = lib.replace("\npub extern ", "\nextern ");
+    }
+    files.add_file(formatter.fmt_file_name(modules.lib()), lib);
+
+    let values = values::ValueGenContext {
+        tcx,
+        formatter: &formatter,
+        generated: &generated,
+    };
+    let readme = readme::ReadmeGenContext {
+        tcx,
+        formatter: &formatter,
+        modules: &modules,
+        values: &values,
+        error_style,
+        destruction,
+    };
+    if let Some((body, directives)) = readme.gen() {
+        files.add_file(
+            formatter.fmt_file_name("readme"),
+            render_class(body, directives, Default::default()),
+        );
+        package.readme = true;
+    }
+
+    if smoke_tests {
+        let smoke = smoke::SmokeTestGenContext {
+            tcx,
+            formatter: &formatter,
+            modules: &modules,
+            values: &values,
+            error_style,
+            destruction,
+            skip: &smoke_test_skip,
+        };
+        let (body, directives) = smoke.gen();
+        files.add_file(
+            formatter.fmt_file_name(SMOKE_TEST_MODULE),
+            render_class(body, directives, Default::default()),
+        );
+        package.smoke_tests = true;
+    }
+
+    if (!bundle && package.modules.contains(&package.name))
+        || package.name == "readme"
+        || package.name == SMOKE_TEST_MODULE
+    {
+        errors.push_error(format!(
+            "Package name `{}` collides with a generated module, please set another `package` in the config",
+            package.name
+        ));
+    }
 
-    let errors = errors.take_all();
-    if !errors.is_empty() {
-        Err(errors)
-    } else {
-        Ok(files)
+    let mut c_errors = Vec::new();
+    if emit_c_headers {
+        let mut c = crate::c2::CContext::new(tcx, FileMap::default());
+        c.run();
+        for (name, header) in c.files.take_files() {
+            files.add_file(format!("{C_HEADERS_DIR}/{name}"), header);
+        }
+        c_errors = c.errors.take_all();
+        package.c_include_dir = Some(C_HEADERS_DIR.into());
     }
+    package.gen(&files);
+
+    let mut errors = errors.take_all();
+    errors.extend(c_errors);
+    (files, errors)
 }
 
+/// Reads the config file, with the options of the selected profile taking precedence
 fn render_class(
     body: String,
     directives: BTreeSet<Cow<'static, str>>,
@@ -105,25 +639,296 @@ struct TyGenContext<'a, 'cx> {
     tcx: &'cx TypeContext,
     formatter: &'a KokaFormatter<'cx>,
     errors: &'a ErrorStore<'cx, String>,
+    /// Names visible from every generated file, i.e. types and `lib.kk` helpers
+    names: &'a NameRegistry,
     helper_classes: &'a mut BTreeMap<String, String>,
+    modules: &'a Modules,
+    error_style: ErrorStyle,
+    struct_layout: StructLayout,
+    destruction: Destruction,
+    /// Whether opaques destroyed by a finalizer reuse the live wrapper of a pointer
+    wrapper_cache: bool,
+    /// Whether `&str` parameters borrowed for the call only are copied too
+    copy_strings: bool,
+    checks: Checks,
+    /// Types that are thrown as errors and thus need an `exception-info` constructor
+    error_types: &'a BTreeSet<TypeId>,
+    /// The templates overriding parts of the bindings
+    overrides: &'a Overrides,
+    /// Callback parameters of the methods of the current type, needing C trampolines
+    callbacks: Vec<CallbackInfo>,
+    /// Traits taken by the methods of the current type, whose C trampolines need importing
+    traits: BTreeSet<TraitId>,
 }
 
 impl<'a, 'cx> TyGenContext<'a, 'cx> {
-    fn gen(&mut self, id: TypeId) -> (String, String) {
+    /// Generates the Koka file for a type, and the C files with its callback trampolines and
+    /// finalizer if needed
+    fn gen(&mut self, id: TypeId) -> (String, Vec<(String, String)>) {
         let ty = self.tcx.resolve_type(id);
 
-        let _guard = self.errors.set_context_ty(ty.name().as_str().into());
+        let _guard = self
+            .errors
+            .set_context_ty(ty.name().as_str().into())
+            .with_span(ty.span());
 
         let name = self.formatter.fmt_type_name(id);
+        if let Err(e) = self.formatter.check_type_name(&name) {
+            self.errors.push_error(e);
+        }
+        if let Err(e) = self
+            .names
+            .reserve(&name, format_args!("type `{}`", ty.name()))
+        {
+            self.errors.push_error(e);
+        }
+        if !self.check_supported(id, ty) {
+            return (String::new(), Vec::new());
+        }
+        let mut body = match ty {
+            TypeDef::Enum(e) => self.gen_enum(e, id, &name),
+            TypeDef::Opaque(o) => self.gen_opaque_def(o, id, &name),
+            TypeDef::Struct(s) => self.gen_struct_def(s, id, false, &name),
+            TypeDef::OutStruct(s) => self.gen_struct_def(s, id, true, &name),
+            _ => unreachable!("unknown AST/HIR variant"),
+        };
+
+        if self.error_types.contains(&id) {
+            let exception = self.formatter.fmt_exception_name(&name);
+            let owner = format_args!("the exception of type `{}`", ty.name());
+            if let Err(e) = self.names.reserve(&exception, owner) {
+                self.errors.push_error(e);
+            }
+            write!(
+                &mut body,
+                "\n\n// Thrown by fallible methods failing with a `{name}`\n\
+                 pub extend type exception-info\n  pub con {exception}(error : {name})\n"
+            )
+            .unwrap();
+        }
+
+        // The C files are generated next to `lib`, and included relative to this module
+        let c_root = self.modules.root_path(ModuleItem::Type(id));
+        let mut c_files = Vec::new();
+        if !self.callbacks.is_empty() {
+            #[derive(askama::Template)]
+            #[template(path = "koka/callbacks.c.jinja", escape = "none")]
+            struct CallbacksTemplate {
+                header: String,
+                callbacks: Vec<CallbackInfo>,
+            }
+
+            let callbacks_file_name = self.formatter.fmt_callbacks_file_name(&name);
+            body = format!("extern import\n  c file \"{c_root}{callbacks_file_name}\"\n\n{body}");
+            let callbacks = CallbacksTemplate {
+                header: self.formatter.fmt_c_impl_header_path(id),
+                callbacks: std::mem::take(&mut self.callbacks),
+            }
+            .render()
+            .unwrap();
+            c_files.push((callbacks_file_name, callbacks));
+        }
+
+        if matches!(ty, TypeDef::Opaque(o) if !o.attrs.parent_managed)
+            && self.destruction == Destruction::Finalizer
+        {
+            #[derive(askama::Template)]
+            #[template(path = "koka/finalizer.c.jinja", escape = "none")]
+            struct FinalizerTemplate {
+                header: String,
+                finalizer: String,
+                destructor: String,
+                c_type: String,
+                consumable: bool,
+                unbox: String,
+                take: String,
+                /// The wrapper cache, if enabled
+                cache: Option<String>,
+                wrap: String,
+            }
+
+            let finalizer_file_name = self.formatter.fmt_finalizer_file_name(&name);
+            body = format!("extern import\n  c file \"{c_root}{finalizer_file_name}\"\n\n{body}");
+            let finalizer = FinalizerTemplate {
+                header: self.formatter.fmt_c_impl_header_path(id),
+                finalizer: self.formatter.fmt_c_finalizer_name(id),
+                destructor: self.formatter.fmt_destructor_name(id),
+                c_type: self.formatter.fmt_c_type_name(id).into_owned(),
+                consumable: matches!(ty, TypeDef::Opaque(o) if is_consumable(o)),
+                unbox: self.formatter.fmt_c_unbox_name(id),
+                take: self.formatter.fmt_c_take_name(id),
+                cache: self
+                    .wrapper_cache
+                    .then(|| self.formatter.fmt_c_cache_name(id)),
+                wrap: self.formatter.fmt_c_wrap_name(id),
+            }
+            .render()
+            .unwrap();
+            c_files.push((finalizer_file_name, finalizer));
+        }
+
+        for trt in std::mem::take(&mut self.traits).into_iter().rev() {
+            let trait_file_name = self
+                .formatter
+                .fmt_trait_file_name(&self.formatter.fmt_trait_name(trt));
+            body = format!("extern import\n  c file \"{c_root}{trait_file_name}\"\n\n{body}");
+        }
+
+        (body, c_files)
+    }
+
+    /// Report the fields, variants and methods of a type using what Koka bindings can't express,
+    /// returning whether the type can be generated
+    fn check_supported(&self, id: TypeId, ty: TypeDef<'cx>) -> bool {
+        let error_count = self.errors.error_count();
+        match ty {
+            TypeDef::Struct(s) => {
+                for field in &s.fields {
+                    self.check_supported_type(&field.ty, format_args!("Field `{}`", field.name));
+                }
+            }
+            TypeDef::OutStruct(s) => {
+                for field in &s.fields {
+                    self.check_supported_type(&field.ty, format_args!("Field `{}`", field.name));
+                }
+            }
+            TypeDef::Enum(e) => {
+                for variant in &e.variants {
+                    let name = self.formatter.fmt_enum_variant(e, variant);
+                    if let Err(e) = self.formatter.check_enum_variant(&name) {
+                        self.errors
+                            .push_error(format!("Variant `{}`: {e}", variant.name));
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        for method in ty.methods() {
+            if method.attrs.disable {
+                continue;
+            }
+            let _guard = self
+                .errors
+                .set_context_method(
+                    self.formatter.fmt_type_name_diagnostics(id),
+                    method.name.as_str().into(),
+                )
+                .with_span(method.span);
+            for param in &method.params {
+                self.check_supported_type(&param.ty, format_args!("Parameter `{}`", param.name));
+            }
+            method
+                .output
+                .with_contained_types(|ty| self.check_supported_type(ty, "The return type"));
+        }
+
+        self.errors.error_count() == error_count
+    }
+
+    fn check_supported_type<P: TyPosition>(&self, ty: &Type<P>, what: impl Display) {
+        if let Type::DiplomatOption(ref ok) = *ty {
+            return self.check_supported_type(ok, what);
+        }
+        let int128 = match ty {
+            Type::Primitive(hir::PrimitiveType::Int128(int))
+            | Type::Slice(hir::Slice::Primitive(_, hir::PrimitiveType::Int128(int))) => Some(int),
+            _ => None,
+        };
+        if let Some(int) = int128 {
+            self.errors.push_error(format!(
+                "{what} uses `{}`, 128-bit integers are not supported in Koka",
+                int.as_str()
+            ));
+        }
+        if ty.lifetimes().any(|lt| lt == MaybeStatic::Static) {
+            self.errors.push_error(format!(
+                "{what} borrows for `'static`, which is not supported in Koka"
+            ));
+        }
+    }
+
+    /// Generates the Koka file for a trait, a struct of closures, and the C file with
+    /// the trampolines turning it into the trait's vtable
+    fn gen_trait(&mut self, id: TraitId) -> (String, (String, String)) {
+        let trt = self.tcx.resolve_trait(id);
+
+        let _guard = self
+            .errors
+            .set_context_ty(trt.name.as_str().into())
+            .with_span(trt.span);
+
+        let name = self.formatter.fmt_trait_name(id);
+        if let Err(e) = self.formatter.check_type_name(&name) {
+            self.errors.push_error(e);
+        }
+        if let Err(e) = self
+            .names
+            .reserve(&name, format_args!("trait `{}`", trt.name))
+        {
+            self.errors.push_error(e);
+        }
+
+        let vtable = self.formatter.fmt_c_trait_vtable_name(id);
+
+        #[derive(askama::Template)]
+        #[template(path = "koka/trait.kk.jinja", escape = "none")]
+        struct TraitTemplate {
+            name: String,
+            docs: String,
+            methods: Vec<TraitMethodInfo>,
+        }
+
+        struct TraitMethodInfo {
+            name: String,
+            docs: String,
+            ty: String,
+        }
+
+        #[derive(askama::Template)]
+        #[template(path = "koka/trait.c.jinja", escape = "none")]
+        struct TrampolinesTemplate {
+            name: String,
+            header: String,
+            vtable: String,
+            methods: Vec<CallbackInfo>,
+        }
+
+        let body = TraitTemplate {
+            name: name.to_string(),
+            docs: self.formatter.fmt_docs(&trt.docs),
+            methods: trt
+                .methods
+                .iter()
+                .map(|m| TraitMethodInfo {
+                    name: self.formatter.fmt_trait_method_name(m),
+                    docs: self.formatter.fmt_docs(&m.docs),
+                    ty: self.gen_callback_type_name(&m.signature),
+                })
+                .collect(),
+        }
+        .render()
+        .unwrap();
+
+        let trampolines = TrampolinesTemplate {
+            name: name.to_string(),
+            header: self.formatter.fmt_c_trait_header_path(id),
+            methods: trt
+                .methods
+                .iter()
+                .map(|m| {
+                    let c_name = format!("{vtable}_{}", m.name.as_str());
+                    self.gen_callback_info(&c_name, &m.signature)
+                })
+                .collect(),
+            vtable,
+        }
+        .render()
+        .unwrap();
+
         (
-            self.formatter.fmt_file_name(&name),
-            match ty {
-                TypeDef::Enum(e) => self.gen_enum(e, id, &name),
-                TypeDef::Opaque(o) => self.gen_opaque_def(o, id, &name),
-                TypeDef::Struct(s) => self.gen_struct_def(s, id, false, &name, true),
-                TypeDef::OutStruct(s) => self.gen_struct_def(s, id, true, &name, false),
-                _ => unreachable!("unknown AST/HIR variant"),
-            },
+            body,
+            (self.formatter.fmt_trait_file_name(&name), trampolines),
         )
     }
 
@@ -134,7 +939,18 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             .flat_map(|method| self.gen_method_info(id, method, type_name))
             .collect::<Vec<_>>();
 
-        let special = self.gen_special_method_info(&ty.special_method_presence);
+        let special = self.gen_special_method_info(&ty.methods, &ty.special_method_presence);
+        let qualifier = self.formatter.fmt_type_qualifier(type_name);
+        let conversions = ["to-int", "from-int", "from-ffi", "variants"]
+            .map(|name| format!("{qualifier}/{name}"));
+        let mut generated = special.adapter_names();
+        generated.extend([
+            (&*conversions[0], "the `to-int` conversion"),
+            (&*conversions[1], "the `from-int` conversion"),
+            (&*conversions[2], "the `from-ffi` conversion"),
+            (&*conversions[3], "the `variants` list"),
+        ]);
+        self.check_method_names(&methods, &generated);
 
         #[derive(Template)]
         #[template(path = "koka/enum.kk.jinja", escape = "none")]
@@ -142,23 +958,33 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             ty: &'a hir::EnumDef,
             fmt: &'a KokaFormatter<'a>,
             type_name: &'a str,
+            qualifier: &'a str,
             methods: &'a [MethodInfo<'a>],
             docs: String,
-            is_contiguous: bool,
             special: SpecialMethodGenInfo<'a>,
         }
 
-        ImplTemplate {
+        let docs = self.formatter.fmt_docs(&ty.docs);
+        let builtin = ImplTemplate {
             ty,
             fmt: self.formatter,
             type_name,
+            qualifier: &qualifier,
             methods: methods.as_slice(),
-            docs: self.formatter.fmt_docs(&ty.docs),
-            is_contiguous: is_contiguous_enum(ty),
+            docs: docs.clone(),
             special,
         }
         .render()
-        .unwrap()
+        .unwrap();
+        self.overrides.apply(
+            Part::Enum,
+            builtin,
+            &[
+                ("docs", &docs),
+                ("type_name", type_name),
+                ("qualifier", &qualifier),
+            ],
+        )
     }
 
     fn gen_opaque_def(&mut self, ty: &'cx hir::OpaqueDef, id: TypeId, type_name: &str) -> String {
@@ -169,29 +995,102 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             .collect::<Vec<_>>();
 
         let destructor = self.formatter.fmt_destructor_name(id);
-        let special = self.gen_special_method_info(&ty.special_method_presence);
+        let special = self.gen_special_method_info(&ty.methods, &ty.special_method_presence);
+        let qualifier = self.formatter.fmt_type_qualifier(type_name);
+        let from_ffi = format!("{qualifier}/from-ffi");
+        let ffi_destructor = self.formatter.fmt_ffi_destructor_name(id);
+        let parent_managed = ty.attrs.parent_managed;
+        let ffi_own = if self.destruction == Destruction::Finalizer && !parent_managed {
+            Some(self.formatter.fmt_ffi_own_name(id))
+        } else {
+            None
+        };
+        let mut generated = vec![
+            ("raw", "the `raw` field"),
+            ("self-edge", "the `self-edge` field"),
+            (&*from_ffi, "the `from-ffi` constructor"),
+        ];
+        let ffi_check_thread = ty
+            .attrs
+            .non_send
+            .then(|| self.formatter.fmt_ffi_check_thread_name(id));
+        if let Some(ref check) = ffi_check_thread {
+            generated.push(("thread", "the `thread` field"));
+            generated.push((check, "the thread check"));
+        }
+        if let Some(ref own) = ffi_own {
+            generated.push((own, "the finalizer"));
+        } else if !parent_managed {
+            generated.push((&ffi_destructor, "the destructor"));
+            generated.push(("free", "the `free` function"));
+        }
+
+        let mut docs = self.formatter.fmt_docs(&ty.docs);
+        if parent_managed {
+            if !docs.is_empty() {
+                docs.push_str("\n//\n// ");
+            }
+            docs.push_str(
+                "Owned by its parent, which frees it along with everything else it owns: it is never\n\
+                 // destroyed from Koka, and must not be used once its parent is gone.",
+            );
+        }
+        generated.extend_from_slice(&special.adapter_names());
+        self.check_method_names(&methods, &generated);
 
         #[derive(Template)]
         #[template(path = "koka/opaque.kk.jinja", escape = "none")]
         struct ImplTemplate<'a> {
             type_name: &'a str,
+            qualifier: String,
             methods: &'a [MethodInfo<'a>],
             docs: String,
             destructor: String,
+            ffi_destructor: String,
+            /// The extern boxing owned pointers with their finalizer, unless destruction is manual
+            ffi_own: Option<String>,
+            /// The extern aborting when an opaque that is not `Send` is used on another thread
+            ffi_check_thread: Option<String>,
+            /// Whether `ffi_own` boxes every pointer through the wrapper cache
+            cached: bool,
+            wrap: String,
+            /// Whether the opaque belongs to a parent freeing it, and is never destroyed from Koka
+            parent_managed: bool,
+            /// Whether `raw` is a `rust-object` rather than a plain pointer
+            boxed: bool,
+            finalizer: String,
             lifetimes: &'a LifetimeEnv,
             special: SpecialMethodGenInfo<'a>,
         }
 
-        ImplTemplate {
+        let builtin = ImplTemplate {
             type_name,
+            qualifier: qualifier.clone(),
             methods: methods.as_slice(),
             destructor,
-            docs: self.formatter.fmt_docs(&ty.docs),
+            ffi_destructor,
+            ffi_own,
+            ffi_check_thread,
+            cached: self.wrapper_cache,
+            wrap: self.formatter.fmt_c_wrap_name(id),
+            parent_managed,
+            boxed: self.destruction == Destruction::Finalizer,
+            finalizer: self.formatter.fmt_c_finalizer_name(id),
+            docs: docs.clone(),
             lifetimes: &ty.lifetimes,
             special,
         }
         .render()
-        .unwrap()
+        .unwrap();
+        self.overrides.apply(
+            Part::Opaque,
+            builtin,
+            &[
+                ("docs", &docs),
+                ("type_name", type_name),
+                ("qualifier", &qualifier),
+            ],
+        )
     }
 
     fn gen_struct_def<P: TyPosition>(
@@ -200,9 +1099,9 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         id: TypeId,
         is_out: bool,
         type_name: &str,
-        mutable: bool,
     ) -> String {
-        let fields = ty
+        let c_type_name = self.formatter.fmt_c_type_name(id);
+        let mut fields = ty
             .fields
             .iter()
             .map(|field| {
@@ -214,129 +1113,87 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                     _ => None,
                 };
 
-                let ffi_cast_type_name = if let hir::Type::Slice(s) = field.ty {
-                    self.gen_slice(&s).into()
-                } else {
-                    self.gen_type_name_ffi(&field.ty, true)
-                };
-
-                let dart_type_name = self.gen_type_name(&field.ty);
-
-                let c_to_dart = self.gen_c_to_dart_for_type(
-                    &field.ty,
-                    format!("ffi.{name}").into(),
-                    &ty.lifetimes,
-                );
+                let access =
+                    self.gen_field_access(field, &name, type_name, &c_type_name, &ty.lifetimes);
 
-                let (dart_to_c, maybe_struct_borrow_info) = if let hir::Type::Slice(slice) = &field.ty {
-                    let view_expr = self.gen_dart_to_c_for_type(&field.ty, name.clone(), None);
-                    let mut ret = vec![
-                        format!("final {name}View = {view_expr};"),
-                        format!("struct.{name}._length = {name}View.length;"),
-                    ];
-
-                    // We do not need to handle lifetime transitivity here: Methods already resolve
-                    // lifetime transitivity, and we have an HIR validity pass ensuring that struct lifetime bounds
-                    // are explicitly specified on methods.
-                    if let Some(lt) = slice.lifetime() {
-                        let MaybeStatic::NonStatic(lt) = lt else {
-                            panic!("'static not supported in Dart");
-                        };
-                        ret.push(format!(
-                            "struct.{name}._data = {name}View.allocIn({lt_name}AppendArray.isNotEmpty ? _FinalizedArena.withLifetime({lt_name}AppendArray).arena : temp);",
-                            lt_name = ty.lifetimes.fmt_lifetime(lt),
-                        ));
-                    } else {
-                        ret.push(format!(
-                            "struct.{name}._data = {name}View.allocIn(_RustAlloc());"
-                        ));
+                let koka_type_name = match field.ty {
+                    // Copied by the slice helpers, which work with vectors
+                    hir::Type::Slice(hir::Slice::Primitive(_, p)) => {
+                        format!("vector<{}>", self.formatter.fmt_primitive_as_koka(p)).into()
                     }
-                    (ret, None)
-                } else {
-                    let struct_borrow_info = if let hir::Type::Struct(path) = &field.ty {
-                        StructBorrowInfo::compute_for_struct_field(ty, path, self.tcx).map(
-                            |param_info| StructBorrowContext {
-                                use_env: &ty.lifetimes,
-                                param_info,
-                                is_method: false,
-                            },
-                        )
-                    } else {
-                        None
-                    };
-                    let dart_to_c = self.gen_dart_to_c_for_type(&field.ty, name.clone(), struct_borrow_info.as_ref());
-                    (vec![format!(
-                        "struct.{name} = {};",
-                        dart_to_c
-                    )], struct_borrow_info.map(|s| s.param_info))
+                    _ => self.gen_type_name(&field.ty),
                 };
 
                 FieldInfo {
                     name,
+                    c_name: field.name.as_str(),
                     ty: &field.ty,
                     annotation,
-                    ffi_cast_type_name,
-                    dart_type_name,
-                    c_to_dart,
-                    dart_to_c,
-                    maybe_struct_borrow_info
+                    koka_type_name,
+                    access,
                 }
             })
             .collect::<Vec<_>>();
 
-        let mut methods = ty
+        // Bundles of parameters can be constructed with the fields left out taking default values
+        let bundle_fields = if ty.is_bundle {
+            ty.fields
+                .iter()
+                .map(|field| BundleField {
+                    name: self.formatter.fmt_param_name(field.name.as_str()),
+                    ty: self.gen_type_name(&field.ty),
+                    default: self.gen_default_value(&field.ty),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let fbip = self.struct_layout == StructLayout::Fbip;
+        if fbip {
+            // Koka stores the boxed fields of a constructor before the unboxed ones
+            fields.sort_by_key(|field| matches!(field.ty, Type::Primitive(..) | Type::Enum(..)));
+        }
+
+        let methods = ty
             .methods
             .iter()
             .flat_map(|method| self.gen_method_info(id, method, type_name))
             .collect::<Vec<_>>();
-        let special = self.gen_special_method_info(&ty.special_method_presence);
+        let special = self.gen_special_method_info(&ty.methods, &ty.special_method_presence);
+        let qualifier = self.formatter.fmt_type_qualifier(type_name);
 
-        // Non-out structs need to be constructible in Dart
-        let default_constructor = if !is_out {
-            if let Some(constructor) = methods
-                .iter_mut()
-                .find(|m| m.declaration.contains(&format!("{type_name}()")))
-            {
-                // If there's an existing zero-arg constructor, we repurpose it with optional arguments for all fields
-                let args = fields
-                    .iter()
-                    .map(|field| format!("{}? {}", field.dart_type_name, field.name))
-                    .collect::<Vec<_>>();
-                constructor.declaration =
-                    format!("factory {type_name}({{{args}}})", args = args.join(", "));
-
-                let mut r = String::new();
-                writeln!(&mut r, "final dart = {type_name}._fromFfi(result);").unwrap();
-                for field in &fields {
-                    let name = &field.name;
-                    writeln!(&mut r, "if ({name} != null) {{").unwrap();
-                    writeln!(&mut r, "  dart.{name} = {name};").unwrap();
-                    writeln!(&mut r, "}}").unwrap();
-                }
-                write!(&mut r, "return dart;").unwrap();
-                constructor.return_expression = Some(r.into());
-
-                None
-            } else {
-                // Otherwise we create a constructor with required values for all fields.
-                let args = fields
-                    .iter()
-                    .map(|field| format!("required this.{}", field.name))
-                    .collect::<Vec<_>>();
-
-                Some(format!("{type_name}({{{args}}});", args = args.join(", ")))
-            }
-        } else {
-            None
-        };
+        // The default constructor fills in the fields left out of `with-defaults`
+        let with_defaults = format!("{qualifier}/with-defaults");
+        let default_constructor_call = ty
+            .methods
+            .iter()
+            .find(|method| {
+                matches!(
+                    method.attrs.special_method,
+                    Some(hir::SpecialMethod::Default)
+                ) && !method.attrs.disable
+            })
+            .filter(|_| !is_out)
+            .map(|method| format!("{}()", self.formatter.fmt_qualified_method_name(method, id)));
+
+        let mut generated = special.adapter_names();
+        if default_constructor_call.is_some() {
+            generated.push((&*with_defaults, "the `with-defaults` constructor"));
+        }
+        self.check_method_names(&methods, &generated);
 
         #[derive(Template)]
         #[template(path = "koka/struct.kk.jinja", escape = "none")]
         struct ImplTemplate<'a, P: TyPosition> {
             type_name: &'a str,
-            default_constructor: Option<String>,
-            mutable: bool,
+            c_type_name: &'a str,
+            is_out: bool,
+            fbip: bool,
+            default_constructor_call: Option<String>,
+            qualifier: String,
             fields: Vec<FieldInfo<'a, P>>,
+            bundle_fields: Vec<BundleField<'a>>,
             methods: Vec<MethodInfo<'a>>,
             docs: String,
             lifetimes: &'a LifetimeEnv,
@@ -345,9 +1202,13 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
         ImplTemplate {
             type_name,
-            default_constructor,
-            mutable,
+            c_type_name: &c_type_name,
+            is_out,
+            fbip,
+            default_constructor_call,
+            qualifier,
             fields,
+            bundle_fields,
             methods,
             docs: self.formatter.fmt_docs(&ty.docs),
             lifetimes: &ty.lifetimes,
@@ -357,6 +1218,218 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         .unwrap()
     }
 
+    /// Generates the accessors of a field of a C struct.
+    ///
+    /// Slices are stored as the pointer and length of a view. They are copied when reading the
+    /// field, so the Koka value borrows nothing. When writing it, borrowed slices are copied into
+    /// the batch the struct is allocated from, and owned ones into memory allocated by Rust.
+    fn gen_field_access<P: TyPosition>(
+        &mut self,
+        field: &hir::StructField<P>,
+        name: &str,
+        type_name: &str,
+        c_type_name: &str,
+        lifetimes: &LifetimeEnv,
+    ) -> FieldAccess {
+        let value = format!("s.{name}");
+        let target = format!("(({c_type_name}*)#1)->{}", field.name);
+        match field.ty {
+            Type::Slice(slice) => {
+                let Some(view) = self.gen_slice_view(&slice, &value) else {
+                    self.errors.push_error(format!(
+                        "Field `{name}` of `{type_name}`: string slice fields are not supported"
+                    ));
+                    return FieldAccess {
+                        ffi_type: "()".into(),
+                        by_address: true,
+                        c_to_koka: "()".into(),
+                        setter_params: String::new(),
+                        setter_c: String::new(),
+                        koka_to_c: String::new(),
+                    };
+                };
+                FieldAccess {
+                    ffi_type: self.formatter.fmt_pointer(&view.view_ty),
+                    by_address: true,
+                    c_to_koka: format!("{}(external/{name}(c))", view.read),
+                    setter_params: "data : c-pointer<()>, len : ssize_t".into(),
+                    setter_c: format!("{target}.data = (void*)#2, {target}.len = (size_t)#3"),
+                    koka_to_c: format!("external/set-{name}(c, {}, {})", view.alloc, view.length),
+                }
+            }
+            Type::Struct(ref st) => {
+                let field_type = self.formatter.fmt_type_name(st.id());
+                let qualifier = self.formatter.fmt_type_qualifier(&field_type);
+                let ptr = self.formatter.fmt_pointer(&field_type);
+                FieldAccess {
+                    ffi_type: ptr.clone(),
+                    by_address: true,
+                    c_to_koka: format!(
+                        "{qualifier}/from-c(external/{name}(c){})",
+                        self.gen_struct_edges(st, lifetimes)
+                    ),
+                    setter_params: format!("v : {ptr}"),
+                    setter_c: format!(
+                        "{target} = *({}*)#2",
+                        self.formatter.fmt_c_type_name(st.id())
+                    ),
+                    // Nested structs are written in place, as they are read
+                    koka_to_c: format!("{qualifier}/write-c({value}, external/{name}(c), b)"),
+                }
+            }
+            // Read and written in place, the value along with the flag telling whether it is set
+            Type::DiplomatOption(ref ok) => {
+                let (helper, c_option_name) = self.gen_option(ok);
+                let ptr = self.formatter.fmt_pointer(&format!("{helper}-t"));
+                FieldAccess {
+                    ffi_type: ptr.clone(),
+                    by_address: true,
+                    c_to_koka: format!(
+                        "{helper}/read(external/{name}(c), {})",
+                        self.gen_option_read(ok, &helper, lifetimes)
+                    ),
+                    setter_params: format!("v : {ptr}"),
+                    setter_c: format!("{target} = *({c_option_name}*)#2"),
+                    koka_to_c: format!(
+                        "{helper}/write({value}, external/{name}(c), {})",
+                        self.gen_option_write(ok, &helper, "b")
+                    ),
+                }
+            }
+            ref ty => {
+                let ffi_type = self.gen_type_name_ffi(ty, true).into_owned();
+                let c_to_koka = self
+                    .gen_c_to_dart_for_type(ty, format!("external/{name}(c)").into(), lifetimes)
+                    .into_owned();
+                let conversion = self.gen_dart_to_c_for_type(ty, value.into());
+                FieldAccess {
+                    setter_params: format!("v : {ffi_type}"),
+                    ffi_type,
+                    by_address: false,
+                    c_to_koka,
+                    setter_c: format!("{target} = #2"),
+                    koka_to_c: format!("external/set-{name}(c, {conversion})"),
+                }
+            }
+        }
+    }
+
+    /// Whether a struct has slice fields borrowing for a lifetime of its definition, directly or
+    /// through the structs in its fields.
+    fn borrows_slices(&self, def: &hir::StructDef, lifetime: Lifetime) -> bool {
+        def.fields.iter().any(|field| match field.ty {
+            Type::Slice(slice) => slice.lifetime() == Some(&MaybeStatic::NonStatic(lifetime)),
+            Type::Struct(ref st) => {
+                let inner = st.resolve(self.tcx);
+                inner
+                    .lifetimes
+                    .all_lifetimes()
+                    .zip(st.lifetimes().lifetimes())
+                    .any(|(def_lt, use_lt)| {
+                        use_lt == MaybeStatic::NonStatic(lifetime)
+                            && self.borrows_slices(inner, def_lt)
+                    })
+            }
+            _ => false,
+        })
+    }
+
+    /// Generates the helpers copying a slice in and out of its C view, or `None` for the slices
+    /// of strings, which have no view. `value` is the Koka value written into the view.
+    fn gen_slice_view(&mut self, slice: &hir::Slice, value: &str) -> Option<SliceView> {
+        let owned = slice.lifetime().is_none();
+        let (read, alloc) = if owned {
+            ("take", "alloc-owned")
+        } else {
+            ("copy", "alloc-in")
+        };
+        Some(match slice {
+            hir::Slice::Primitive(_, p) => {
+                let helper = self.gen_prim_slice(*p);
+                SliceView {
+                    view_ty: format!("{helper}-t"),
+                    read: format!("{helper}/{read}"),
+                    alloc: if owned {
+                        format!("{helper}/alloc-owned({value})")
+                    } else {
+                        format!("{helper}/alloc-in({value}, b)")
+                    },
+                    length: format!("{value}.length.ssize_t"),
+                }
+            }
+            hir::Slice::Str(_, encoding) => {
+                let helper = match encoding {
+                    hir::StringEncoding::UnvalidatedUtf16 => {
+                        self.helper_classes.insert(
+                            "utf16".into(),
+                            include_str!("../../templates/koka/utf16.kk").into(),
+                        );
+                        "utf16"
+                    }
+                    _ => {
+                        self.helper_classes.insert(
+                            "utf8".into(),
+                            include_str!("../../templates/koka/utf8.kk").into(),
+                        );
+                        "utf8"
+                    }
+                };
+                SliceView {
+                    view_ty: format!("{helper}-view-t"),
+                    read: format!("{helper}-{read}"),
+                    alloc: if owned {
+                        format!("{value}.{helper}-{alloc}")
+                    } else {
+                        format!("{value}.{helper}-{alloc}(b.raw)")
+                    },
+                    length: format!("{value}.{helper}-length"),
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Checks that the functions and `extern`s generated for a type's methods do not collide with
+    /// each other or with the other functions generated for the type (such as its destructor),
+    /// which all end up in the same module.
+    fn check_method_names(&self, methods: &[MethodInfo], generated: &[(&str, &str)]) {
+        let names = NameRegistry::default();
+        for (name, owner) in generated {
+            // These are reserved first and distinct, they cannot collide
+            let _ = names.reserve(name, owner);
+        }
+        for m in methods {
+            let method = m.method.name.as_str();
+            for result in [
+                names.reserve(&m.name, format_args!("method `{method}`")),
+                names.reserve(
+                    &m.ffi_name,
+                    format_args!("the binding of method `{method}`"),
+                ),
+            ] {
+                if let Err(e) = result {
+                    self.errors.push_error(e);
+                }
+            }
+            if let Some(ref future) = m.future {
+                for result in [
+                    names.reserve(
+                        &future.poll_ffi_name,
+                        format_args!("the future binding of method `{method}`"),
+                    ),
+                    names.reserve(
+                        &future.destroy_ffi_name,
+                        format_args!("the future binding of method `{method}`"),
+                    ),
+                ] {
+                    if let Err(e) = result {
+                        self.errors.push_error(e);
+                    }
+                }
+            }
+        }
+    }
+
     fn gen_method_info(
         &mut self,
         id: TypeId,
@@ -369,55 +1442,325 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
         let mut visitor = method.borrowing_param_visitor(self.tcx);
 
-        let _guard = self.errors.set_context_method(
-            self.formatter.fmt_type_name_diagnostics(id),
-            method.name.as_str().into(),
-        );
+        let _guard = self
+            .errors
+            .set_context_method(
+                self.formatter.fmt_type_name_diagnostics(id),
+                method.name.as_str().into(),
+            )
+            .with_span(method.span);
 
         let c_method_name = self.formatter.fmt_c_method_name(id, method);
+        let ffi_name = self.formatter.fmt_ffi_method_name(id, method);
 
         let mut param_decls_dart = Vec::new();
-        let mut param_types_ffi = Vec::new();
         let mut param_types_ffi_cast = Vec::new();
         let mut param_names_ffi = Vec::new();
         let mut param_conversions = Vec::new();
 
         let mut needs_temp_arena = false;
 
+        // The (1-based) FFI arguments that are boxed opaques, with the C function unboxing them
+        let mut boxed_args: Vec<(usize, String)> = Vec::new();
+        // The (1-based) FFI arguments that are pointers to structs passed by value, with their C type
+        let mut struct_args = Vec::new();
+        // The (1-based) FFI arguments that are Koka strings, passed as a pointer to their buffer
+        let mut borrowed_strs = Vec::new();
+
+        if let Some(hir::ParamSelf {
+            ty: SelfType::BoxedOpaque(ref op),
+            ..
+        }) = method.param_self
+        {
+            if self.tcx.resolve_opaque(op.tcx_id).attrs.parent_managed {
+                self.errors.push_error(
+                    "Methods cannot consume `self` of a `parent_managed` opaque, its parent owns it"
+                        .into(),
+                );
+                return None;
+            }
+        }
+
         if let Some(param_self) = method.param_self.as_ref() {
-            visitor.visit_param(&param_self.ty.clone().into(), "this");
+            // A consumed `self` is handed back to Rust, so nothing borrows from it
+            if !matches!(param_self.ty, SelfType::BoxedOpaque(..)) {
+                visitor.visit_param(&param_self.ty.clone().into(), "self");
+            }
 
-            param_types_ffi.push(self.gen_self_type_name_ffi(&param_self.ty, false));
-            param_types_ffi_cast.push(self.gen_self_type_name_ffi(&param_self.ty, true));
-            param_conversions.push(self.gen_dart_to_c_self(&param_self.ty));
-            param_names_ffi.push("self".into());
+            if let (SelfType::BoxedOpaque(ref op), Destruction::Finalizer) =
+                (&param_self.ty, self.destruction)
+            {
+                // Disarms the finalizer, so that Rust's ownership is never released twice
+                boxed_args.push((1, self.formatter.fmt_c_take_name(op.tcx_id.into())));
+                param_types_ffi_cast.push("rust-object".into());
+                param_conversions.push("self.raw".into());
+                param_names_ffi.push("^self".into());
+            } else if let (SelfType::Opaque(ref op), Destruction::Finalizer) =
+                (&param_self.ty, self.destruction)
+            {
+                boxed_args.push((1, self.gen_unbox_name(op.tcx_id)));
+                param_types_ffi_cast.push("rust-object".into());
+                param_conversions.push("self.raw".into());
+                param_names_ffi.push("^self".into());
+            } else if let hir::SelfType::Struct(ref st) = param_self.ty {
+                let struct_ty = self.formatter.fmt_type_name(st.id());
+                struct_args.push((1, self.formatter.fmt_c_type_name(st.id())));
+                param_types_ffi_cast.push(self.formatter.fmt_pointer(&struct_ty).into());
+                param_conversions.push(
+                    format!(
+                        "{}/to-c(self, temp)",
+                        self.formatter.fmt_type_qualifier(&struct_ty)
+                    )
+                    .into(),
+                );
+                param_names_ffi.push("self".into());
+            } else {
+                param_types_ffi_cast.push(self.gen_self_type_name_ffi(&param_self.ty, true));
+                param_conversions.push(self.gen_dart_to_c_self(&param_self.ty));
+                param_names_ffi.push("self".into());
+            }
+            param_decls_dart.push(format!("self : {type_name}"));
             if matches!(param_self.ty, hir::SelfType::Struct(..)) {
                 needs_temp_arena = true;
             }
         }
 
+        // Opaques that are not `Send` abort before Rust sees them on another thread
+        let mut thread_checks = Vec::new();
+        let self_opaque = match method.param_self {
+            Some(hir::ParamSelf {
+                ty: SelfType::Opaque(ref op),
+                ..
+            }) => Some(op.tcx_id),
+            Some(hir::ParamSelf {
+                ty: SelfType::BoxedOpaque(ref op),
+                ..
+            }) => Some(op.tcx_id),
+            _ => None,
+        };
+        if let Some(id) = self_opaque {
+            if self.tcx.resolve_opaque(id).attrs.non_send {
+                let check = self.formatter.fmt_ffi_check_thread_name(id.into());
+                thread_checks.push(format!("{check}(self.thread)"));
+            }
+        }
+        for param in &method.params {
+            if let hir::Type::Opaque(ref op) = param.ty {
+                if self.tcx.resolve_opaque(op.tcx_id).attrs.non_send {
+                    let check = self.formatter.fmt_ffi_check_thread_name(op.tcx_id.into());
+                    let param_name = self.formatter.fmt_param_name(param.name.as_str());
+                    thread_checks.push(if op.is_optional() {
+                        format!("{param_name}.map(fn(o) {check}(o.thread)).default(())")
+                    } else {
+                        format!("{check}({param_name}.thread)")
+                    });
+                }
+            }
+        }
+
         let mut slice_params = Vec::new();
+        let mut kept_batches = Vec::new();
+        // The C callback or vtable struct closures are wrapped into, with the (1-based) FFI
+        // argument of the first closure and the number of closures
+        let mut callback_args = Vec::new();
+        // Bindings set up before the call and assignments after it, for mutable slices
+        let mut prelude = Vec::new();
+        let mut writeback = Vec::new();
 
         for param in method.params.iter() {
             let param_name = self.formatter.fmt_param_name(param.name.as_str());
-            let param_borrow_kind = visitor.visit_param(&param.ty, &param_name);
+            // Strings the result borrows are copied into a view, which its edges keep alive
+            let is_utf8_str = matches!(
+                param.ty,
+                hir::Type::Slice(hir::Slice::Str(_, encoding))
+                    if encoding != hir::StringEncoding::UnvalidatedUtf16
+            );
+            let edge_name = if is_utf8_str {
+                format!("{param_name}-view").into()
+            } else {
+                param_name.clone()
+            };
+            let param_borrow_kind = visitor.visit_param(&param.ty, &edge_name);
+
+            if let hir::Type::Callback(ref callback) = param.ty {
+                let c_name = self
+                    .formatter
+                    .fmt_c_callback_name(&c_method_name, param.name.as_str());
+                self.callbacks
+                    .push(self.gen_callback_info(&c_name, callback));
+                callback_args.push((param_names_ffi.len() + 1, c_name, 1));
+            }
+
+            if let hir::Type::DynTrait(trt) = param.ty {
+                // Trait objects are passed as one closure per method
+                let trait_def = self.tcx.resolve_trait(trt);
+                param_decls_dart.push(format!("{param_name} : {}", self.gen_type_name(&param.ty)));
+                callback_args.push((
+                    param_names_ffi.len() + 1,
+                    self.formatter.fmt_c_trait_vtable_name(trt),
+                    trait_def.methods.len(),
+                ));
+                self.traits.insert(trt);
+                for trait_method in &trait_def.methods {
+                    let method_name = self.formatter.fmt_trait_method_name(trait_method);
+                    let closure_ty = self.gen_callback_type_name(&trait_method.signature);
+                    param_types_ffi_cast.push(closure_ty.into());
+                    param_conversions.push(format!("{param_name}.{method_name}").into());
+                    param_names_ffi.push(format!("{param_name}-{method_name}").into());
+                }
+                continue;
+            }
+
+            if let hir::Type::Slice(hir::Slice::Primitive(borrow, prim)) = param.ty {
+                if !borrow.map_or(false, |b| b.mutability.is_mutable()) {
+                    // Views already hold a buffer Rust can read, which is borrowed for the call,
+                    // or kept alive as a lifetime edge by the values borrowing from it
+                    self.gen_prim_slice(prim);
+                    let view_ty = match self.gen_bytes(prim) {
+                        Some(bytes) => bytes.into(),
+                        None => self.formatter.fmt_primitive_view_type(prim),
+                    };
+                    param_decls_dart.push(format!("{param_name} : {view_ty}"));
+                    if borrow.is_none() {
+                        // Rust frees owned slices, so they are copied into its allocator
+                        param_types_ffi_cast.push(self.formatter.fmt_pointer("()").into());
+                        param_conversions.push(format!("{param_name}.alloc-owned").into());
+                        param_names_ffi.push(format!("{param_name}-data").into());
+                    } else {
+                        boxed_args.push((
+                            param_names_ffi.len() + 1,
+                            "kk_cptr_raw_unbox_borrowed".into(),
+                        ));
+                        param_types_ffi_cast.push("rust-object".into());
+                        param_conversions.push(format!("{param_name}.raw").into());
+                        param_names_ffi.push(format!("^{param_name}-data").into());
+                    }
+                    param_types_ffi_cast.push(self.formatter.fmt_usize(false).into());
+                    param_conversions.push(format!("{param_name}.length.ssize_t").into());
+                    param_names_ffi.push(format!("{param_name}-len").into());
+                    continue;
+                }
+            }
 
-            param_decls_dart.push(format!("{} {param_name}", self.gen_type_name(&param.ty)));
+            param_decls_dart.push(format!("{param_name} : {}", self.gen_type_name(&param.ty)));
 
             let param_type_ffi = self.gen_type_name_ffi(&param.ty, false);
             let param_type_ffi_cast = self.gen_type_name_ffi(&param.ty, true);
 
-            if let hir::Type::Slice(slice) = param.ty {
+            if let hir::Type::Slice(hir::Slice::Primitive(Some(borrow), prim)) = param.ty {
+                if borrow.mutability.is_mutable() {
+                    // Koka values are immutable, so the buffer is copied back into the reference
+                    // after the call
+                    let helper = self.gen_prim_slice(prim);
+                    prelude.push(format!("val {param_name}-len = (!{param_name}).length"));
+                    prelude.push(format!(
+                        "val {param_name}-data = {helper}/alloc-in(!{param_name}, temp)"
+                    ));
+                    writeback.push(format!(
+                        "{param_name} := {helper}/read({param_name}-data, {param_name}-len)"
+                    ));
+                    needs_temp_arena = true;
+                    if let ParamBorrowInfo::BorrowedSlice = param_borrow_kind {
+                        self.errors.push_error(format!(
+                            "Mutable slice parameter `{param_name}` borrowed by the return value is not supported"
+                        ));
+                    }
+
+                    param_types_ffi_cast.push(self.formatter.fmt_pointer("()").into());
+                    param_conversions.push(format!("{param_name}-data").into());
+                    param_names_ffi.push(format!("{param_name}-data").into());
+
+                    param_types_ffi_cast.push(self.formatter.fmt_usize(false).into());
+                    param_conversions.push(format!("{param_name}-len.ssize_t").into());
+                    param_names_ffi.push(format!("{param_name}-len").into());
+                    continue;
+                }
+            }
+
+            if let hir::Type::Slice(hir::Slice::Str(
+                lifetime,
+                hir::StringEncoding::UnvalidatedUtf16,
+            )) = param.ty
+            {
+                // Koka strings are UTF-8, they are transcoded by the `utf16` helpers
+                param_types_ffi_cast.push(self.formatter.fmt_pointer(&param_type_ffi).into());
+                param_names_ffi.push(format!("{param_name}-data").into());
+
+                param_types_ffi_cast.push(self.formatter.fmt_usize(false).into());
+                param_names_ffi.push(format!("{param_name}-length").into());
+
+                if let ParamBorrowInfo::BorrowedSlice = param_borrow_kind {
+                    self.errors.push_error(format!(
+                        "UTF-16 string parameter `{param_name}` borrowed by the return value is not supported"
+                    ));
+                }
+                if lifetime.is_none() {
+                    // Owned strings use the Rust allocator
+                    param_conversions.push(format!("{param_name}.utf16-alloc-owned").into());
+                } else {
+                    param_conversions.push(format!("{param_name}.utf16-alloc-in(temp.raw)").into());
+                    needs_temp_arena = true;
+                }
+                param_conversions.push(format!("{param_name}.utf16-length").into());
+                self.helper_classes.insert(
+                    "utf16".into(),
+                    include_str!("../../templates/koka/utf16.kk").into(),
+                );
+            } else if let hir::Type::Slice(hir::Slice::Str(lifetime, _)) = param.ty {
+                self.helper_classes.insert(
+                    "utf8".into(),
+                    include_str!("../../templates/koka/utf8.kk").into(),
+                );
+                let is_kept = matches!(param_borrow_kind, ParamBorrowInfo::BorrowedSlice);
+                if is_kept {
+                    // Rust reads the copy after the call, as long as the result lives
+                    let bytes = self.gen_bytes(hir::PrimitiveType::Byte).unwrap();
+                    slice_params.push(SliceParam {
+                        param_name: param_name.clone(),
+                        view_expr: format!("{param_name}.{bytes}").into(),
+                        is_borrowed: false,
+                    });
+                    boxed_args.push((
+                        param_names_ffi.len() + 1,
+                        "kk_cptr_raw_unbox_borrowed".into(),
+                    ));
+                    param_types_ffi_cast.push("rust-object".into());
+                    param_conversions.push(format!("{param_name}-view.raw").into());
+                    param_names_ffi.push(format!("^{param_name}-data").into());
+                } else if lifetime.is_none() {
+                    // Owned strings use the Rust allocator
+                    param_types_ffi_cast.push(self.formatter.fmt_pointer(&param_type_ffi).into());
+                    param_conversions.push(format!("{param_name}.utf8-alloc-owned").into());
+                    param_names_ffi.push(format!("{param_name}-data").into());
+                } else if self.copy_strings {
+                    param_types_ffi_cast.push(self.formatter.fmt_pointer(&param_type_ffi).into());
+                    param_conversions.push(format!("{param_name}.utf8-alloc-in(temp.raw)").into());
+                    param_names_ffi.push(format!("{param_name}-data").into());
+                    needs_temp_arena = true;
+                } else {
+                    // Koka strings are UTF-8 already, Rust reads their buffer, which the extern
+                    // borrows so that Perceus cannot drop the string during the call
+                    borrowed_strs.push(param_names_ffi.len() + 1);
+                    param_types_ffi_cast.push(self.formatter.fmt_string().into());
+                    param_conversions.push(param_name.clone());
+                    param_names_ffi.push(format!("^{param_name}-data").into());
+                }
+                param_types_ffi_cast.push(self.formatter.fmt_usize(false).into());
+                param_conversions.push(if is_kept {
+                    format!("{param_name}-view.length.ssize_t").into()
+                } else {
+                    format!("{param_name}.utf8-length").into()
+                });
+                param_names_ffi.push(format!("{param_name}-len").into());
+            } else if let hir::Type::Slice(slice) = param.ty {
                 // Two args on the ABI: pointer and size
-                param_types_ffi.push(self.formatter.fmt_pointer(&param_type_ffi).into());
                 param_types_ffi_cast.push(self.formatter.fmt_pointer(&param_type_ffi_cast).into());
                 param_names_ffi.push(format!("{param_name}Data").into());
 
-                param_types_ffi.push(self.formatter.fmt_usize(false).into());
                 param_types_ffi_cast.push(self.formatter.fmt_usize(true).into());
                 param_names_ffi.push(format!("{param_name}Length").into());
 
-                let view_expr = self.gen_dart_to_c_for_type(&param.ty, param_name.clone(), None);
+                let view_expr = self.gen_dart_to_c_for_type(&param.ty, param_name.clone());
 
                 let is_borrowed = match param_borrow_kind {
                     ParamBorrowInfo::TemporarySlice => false,
@@ -446,35 +1789,88 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                     view_expr,
                     is_borrowed,
                 });
+            } else if let (hir::Type::Opaque(ref op), Destruction::Finalizer) =
+                (&param.ty, self.destruction)
+            {
+                // Borrowed by the extern so that Perceus cannot drop the object during the call
+                let unbox = if op.is_optional() {
+                    // Null for `Nothing`, which the unchecked unboxing lets through
+                    "kk_cptr_raw_unbox_borrowed".into()
+                } else {
+                    self.gen_unbox_name(op.tcx_id)
+                };
+                boxed_args.push((param_names_ffi.len() + 1, unbox));
+                param_types_ffi_cast.push("rust-object".into());
+                param_conversions.push(if op.is_optional() {
+                    format!("{param_name}.map(fn(o) o.raw).default(rust-object/null())").into()
+                } else {
+                    format!("{param_name}.raw").into()
+                });
+                param_names_ffi.push(format!("^{param_name}").into());
+            } else if let hir::Type::Struct(ref st) = param.ty {
+                // Koka cannot pass C structs by value either, they are written into the
+                // temporary batch and dereferenced in C
+                // unless the return value borrows its slice fields, which are then written into a
+                // batch it keeps alive
+                let batch = match param_borrow_kind {
+                    ParamBorrowInfo::Struct(ref param_info)
+                        if param_info
+                            .borrowed_struct_lifetime_map
+                            .keys()
+                            .any(|lt| self.borrows_slices(st.resolve(self.tcx), *lt)) =>
+                    {
+                        self.helper_classes.insert(
+                            "kept-batch".into(),
+                            include_str!("../../templates/koka/kept_batch.kk").into(),
+                        );
+                        kept_batches.push(param_name.to_string());
+                        format!("{param_name}-batch.scope")
+                    }
+                    _ => "temp".into(),
+                };
+                let struct_ty = self.formatter.fmt_type_name(st.id());
+                struct_args.push((
+                    param_names_ffi.len() + 1,
+                    self.formatter.fmt_c_type_name(st.id()),
+                ));
+                param_types_ffi_cast.push(self.formatter.fmt_pointer(&struct_ty).into());
+                param_conversions.push(
+                    format!(
+                        "{}/to-c({param_name}, {batch})",
+                        self.formatter.fmt_type_qualifier(&struct_ty)
+                    )
+                    .into(),
+                );
+                param_names_ffi.push(param_name);
+                needs_temp_arena |= batch == "temp";
+            } else if let hir::Type::DiplomatOption(ref ok) = param.ty {
+                // Passed by value like structs, written into the temporary batch
+                let (helper, c_option_name) = self.gen_option(ok);
+                struct_args.push((param_names_ffi.len() + 1, c_option_name.into()));
+                param_types_ffi_cast.push(param_type_ffi_cast);
+                let write_ok = self.gen_option_write(ok, &helper, "temp");
+                param_conversions
+                    .push(format!("{helper}/to-c({param_name}, temp, {write_ok})").into());
+                param_names_ffi.push(param_name);
+                needs_temp_arena = true;
             } else {
-                if let hir::Type::Struct(..) = param.ty {
-                    needs_temp_arena = true;
-                }
-                let struct_borrow_info =
-                    if let ParamBorrowInfo::Struct(param_info) = param_borrow_kind {
-                        Some(StructBorrowContext {
-                            use_env: &method.lifetime_env,
-                            param_info,
-                            is_method: true,
-                        })
-                    } else {
-                        None
-                    };
-                param_types_ffi.push(param_type_ffi);
                 param_types_ffi_cast.push(param_type_ffi_cast);
-                param_conversions.push(self.gen_dart_to_c_for_type(
-                    &param.ty,
-                    param_name.clone(),
-                    struct_borrow_info.as_ref(),
-                ));
+                let conversion = self.gen_dart_to_c_for_type(&param.ty, param_name.clone());
+                param_conversions.push(match param.ty {
+                    // A Koka `char` can be any `int32`, Rust expects a Unicode scalar value
+                    Type::Primitive(hir::PrimitiveType::Char) if self.checks == Checks::Full => {
+                        format!("char/to-ffi({conversion})").into()
+                    }
+                    _ => conversion,
+                });
                 param_names_ffi.push(param_name);
             }
         }
 
         if method.output.is_writeable() {
-            param_conversions.push("writeable._ffi".into());
-            param_types_ffi.push(self.formatter.fmt_pointer("()").into());
-            param_types_ffi_cast.push(self.formatter.fmt_pointer("()").into());
+            // The `writeable` variable is bound by `with-writeable` in the template
+            param_conversions.push("writeable".into());
+            param_types_ffi_cast.push(self.formatter.fmt_pointer("writeable-t").into());
             param_names_ffi.push("writeable".into());
             self.helper_classes.insert(
                 "writeable".into(),
@@ -482,77 +1878,323 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             );
         }
 
-        let return_ty = self.gen_return_type_name(&method.output);
-        let return_type_ffi = self.gen_return_type_name_ffi(&method.output, false);
-        let return_type_ffi_cast = self.gen_return_type_name_ffi(&method.output, true);
+        // Koka cannot receive C structs by value, so results are written into
+        // memory allocated from the temporary batch
+        let result = match method.output {
+            ReturnType::Fallible(ref ok, ref err) => {
+                Some(self.gen_result(ok.as_type(), err.as_ref()))
+            }
+            ReturnType::Nullable(ref ok) => Some(self.gen_result(ok.as_type(), None)),
+            // Returned slices are views, and copied into a vector by the slice helper
+            ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Primitive(
+                _,
+                prim,
+            )))) if !method.is_async => Some((
+                self.gen_prim_slice(prim),
+                self.formatter.fmt_primitive_slice_name(prim).into(),
+            )),
+            _ => None,
+        };
+        // Structs returned by value are read with `from-c` from memory they are written into
+        let struct_result = match method.output {
+            ReturnType::Infallible(SuccessType::OutType(Type::Struct(ref st)))
+                if !method.is_async =>
+            {
+                Some((
+                    self.formatter.fmt_type_name(st.id()),
+                    self.formatter.fmt_c_type_name(st.id()),
+                ))
+            }
+            _ => None,
+        };
+        let future = if method.is_async {
+            let ok = match method.output {
+                ReturnType::Infallible(ref ok) => ok.as_type(),
+                _ => None,
+            };
+            let (result_helper, result_c_name) = self.gen_result(ok, None);
+            // Rust wakes the Koka strand awaiting the future through a callback
+            if !self.callbacks.iter().any(|cb| cb.c_name == "DiplomatWaker") {
+                self.callbacks.push(CallbackInfo {
+                    c_name: "DiplomatWaker".into(),
+                    output: "void".into(),
+                    params: Vec::new(),
+                    result: None,
+                });
+            }
+            self.helper_classes.insert(
+                "async".into(),
+                include_str!("../../templates/koka/async.kk").into(),
+            );
+            Some(FutureInfo {
+                poll_ffi_name: format!("{ffi_name}_poll"),
+                poll_c_name: format!("{c_method_name}_poll"),
+                destroy_ffi_name: format!("{ffi_name}_destroy_future"),
+                destroy_c_name: format!("{c_method_name}_destroy_future"),
+                result_helper,
+                result_c_name,
+            })
+        } else {
+            None
+        };
+        // Koka closures have to be wrapped into the callback structs in C
+        let arg = |i: usize| {
+            if let Some((_, unbox)) = boxed_args.iter().find(|(j, _)| *j == i) {
+                format!("{unbox}(#{i}, kk_context())")
+            } else if borrowed_strs.contains(&i) {
+                format!("(const char*)kk_string_buf_borrow(#{i}, NULL, kk_context())")
+            } else if let Some((_, struct_ty)) = struct_args.iter().find(|(j, _)| *j == i) {
+                format!("*({struct_ty}*)#{i}")
+            } else {
+                format!("#{i}")
+            }
+        };
+        let mut args = Vec::new();
+        let mut next_arg = 1;
+        for (first, c_name, count) in &callback_args {
+            args.extend((next_arg..*first).map(arg));
+            let closures = (*first..first + count)
+                .map(|i| format!("#{i}, "))
+                .collect::<String>();
+            args.push(format!("{c_name}_new({closures}kk_context())"));
+            next_arg = first + count;
+        }
+        args.extend((next_arg..=param_names_ffi.len()).map(arg));
+        let mut ffi_inline = None;
+        if !callback_args.is_empty()
+            || !boxed_args.is_empty()
+            || !struct_args.is_empty()
+            || !borrowed_strs.is_empty()
+        {
+            ffi_inline = Some(format!("{c_method_name}({})", args.join(", ")));
+        }
+        let mut result_helper = None;
+        let mut result_alloc = None;
+        if let Some((helper, c_name)) = result {
+            ffi_inline = Some(format!(
+                "*({c_name}*)#{} = {c_method_name}({})",
+                param_names_ffi.len() + 1,
+                args.join(", ")
+            ));
+            param_conversions.push("result".into());
+            param_types_ffi_cast.push(self.formatter.fmt_pointer(&format!("{helper}-t")).into());
+            param_names_ffi.push("result".into());
+            needs_temp_arena = true;
+            result_alloc = Some(format!("{helper}/alloc(temp.raw)"));
+            result_helper = Some(helper);
+        } else if let Some((struct_ty, c_struct_ty)) = struct_result {
+            ffi_inline = Some(format!(
+                "*({c_struct_ty}*)#{} = {c_method_name}({})",
+                param_names_ffi.len() + 1,
+                args.join(", ")
+            ));
+            param_conversions.push("result".into());
+            param_types_ffi_cast.push(self.formatter.fmt_pointer(&struct_ty).into());
+            param_names_ffi.push("result".into());
+            needs_temp_arena = true;
+            result_alloc = Some(format!(
+                "{}/alloc-c(temp)",
+                self.formatter.fmt_type_qualifier(&struct_ty)
+            ));
+        }
+
+        // Returned UTF-16 strings are views that Koka cannot take by value, so they are
+        // transcoded in C right away, and the copy borrows from nothing
+        let mut method_lifetimes_map = visitor.borrow_map();
+        if let ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Primitive(
+            ..,
+        ))))
+        | ReturnType::Nullable(SuccessType::OutType(Type::Slice(hir::Slice::Primitive(..))))
+        | ReturnType::Fallible(
+            SuccessType::OutType(Type::Slice(hir::Slice::Primitive(..))),
+            None | Some(Type::Primitive(..) | Type::Enum(..)),
+        ) = method.output
+        {
+            // Primitive slices are copied as well
+            method_lifetimes_map.clear();
+        }
+        if let ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Str(
+            lifetime,
+            hir::StringEncoding::UnvalidatedUtf16,
+        )))) = method.output
+        {
+            if !method.is_async {
+                let decode = if lifetime.is_some() {
+                    "diplomat_utf16_decode"
+                } else {
+                    "diplomat_utf16_decode_owned"
+                };
+                ffi_inline = Some(format!(
+                    "{decode}({c_method_name}({}), kk_context())",
+                    args.join(", ")
+                ));
+                method_lifetimes_map.clear();
+                self.helper_classes.insert(
+                    "utf16".into(),
+                    include_str!("../../templates/koka/utf16.kk").into(),
+                );
+            }
+        }
+
+        // The value and warnings of `with_warnings` methods are returned as a tuple
+        let with_warnings = method.with_warnings(self.tcx);
+        if with_warnings.is_some() && method.is_async {
+            self.errors
+                .push_error("`with_warnings` is not supported on async methods".into());
+        }
+        let mut return_ty = self.gen_return_type_name(&method.output, with_warnings);
+        let return_type_ffi_cast = if future.is_some() {
+            // Async methods return the future
+            self.formatter.fmt_pointer("()").into()
+        } else {
+            self.gen_return_type_name_ffi(&method.output, true)
+        };
 
-        let return_expression =
-            self.gen_c_to_dart_for_return_type(&method.output, &method.lifetime_env);
+        let mut return_expression = self.gen_c_to_dart_for_return_type(
+            &method.output,
+            with_warnings.is_some(),
+            result_helper.as_deref(),
+            &c_method_name,
+            &method.lifetime_env,
+        );
+        if let Some(hir::SpecialMethod::Comparison) = method.attrs.special_method {
+            // Rust returns the `cmp::Ordering` as -1, 0 or 1
+            return_ty = "order".into();
+            return_expression =
+                Some("if result < 0 then Lt elif result > 0 then Gt else Eq".into());
+        }
 
         let params = param_decls_dart.join(", ");
-
-        let declaration = match &method.attrs.special_method {
-            Some(SpecialMethod::Constructor) => format!("factory {type_name}({params})"),
-            Some(SpecialMethod::NamedConstructor(name)) => format!(
-                "factory {type_name}.{}({params})",
-                self.formatter.fmt_constructor_name(name, method)
-            ),
-            Some(SpecialMethod::Getter(name)) => format!(
-                "{return_ty} get {}",
-                self.formatter.fmt_accessor_name(name, method)
-            ),
-            Some(SpecialMethod::Setter(name)) => format!(
-                "set {}({params})",
-                self.formatter.fmt_accessor_name(name, method)
-            ),
-            Some(SpecialMethod::Stringifier) => "@override\n  String toString()".into(),
-            Some(SpecialMethod::Comparison) => format!("int compareTo({type_name} other)"),
-            Some(SpecialMethod::Iterator) => format!("{return_ty} _iteratorNext({params})"),
-            Some(SpecialMethod::Iterable) => format!("{return_ty} get iterator"),
-            Some(SpecialMethod::Indexer) => format!("{return_ty} operator []({params})"),
-            None if method.param_self.is_none() => format!(
-                "static {return_ty} {}({params})",
-                self.formatter.fmt_method_name(method)
-            ),
-            None => format!(
-                "{return_ty} {}({params})",
-                self.formatter.fmt_method_name(method)
-            ),
-            Some(special) => unimplemented!("Found unknown special method type {special:?}"),
+        let name = self.formatter.fmt_qualified_method_name(method, id);
+        let throws = self.error_style == ErrorStyle::Exn
+            && matches!(method.output, ReturnType::Fallible(..));
+        // Pointer-sized integers are range checked for the target before the call
+        let checks_size = self.checks == Checks::Full
+            && method
+                .params
+                .iter()
+                .any(|param| matches!(param.ty, Type::Primitive(hir::PrimitiveType::IntSize(_))));
+        // So are chars, see `char/to-ffi`
+        let checks_char = self.checks == Checks::Full
+            && method
+                .params
+                .iter()
+                .any(|param| matches!(param.ty, Type::Primitive(hir::PrimitiveType::Char)));
+        let effect = if method.is_async {
+            "asyncx"
+        } else if throws || checks_size || checks_char {
+            "io"
+        } else {
+            "io-noexn"
+        };
+        // Literals of Koka's own primitive types become values, other constants are read from Rust
+        let constant = match (&method.constant, &method.output) {
+            (
+                Some(hir::ConstValue::Literal(literal)),
+                ReturnType::Infallible(SuccessType::OutType(Type::Primitive(prim))),
+            ) => self.formatter.fmt_primitive_literal(*prim, literal),
+            _ => None,
+        };
+        let declaration = if constant.is_some() {
+            format!("{name} : {return_ty}")
+        } else {
+            format!("{name}({params}) : {effect} {return_ty}")
         };
 
         let mut docs = self.formatter.fmt_docs(&method.docs);
 
-        if let hir::ReturnType::Fallible(_, Some(e)) = &method.output {
-            write!(
-                &mut docs,
-                "\n///\n/// Throws [{}] on failure.",
-                self.gen_type_name(e)
-            )
-            .unwrap();
+        if let Some(hir::ParamSelf {
+            ty: SelfType::BoxedOpaque(..),
+            ..
+        }) = method.param_self
+        {
+            if !docs.is_empty() {
+                docs.push_str("\n//\n// ");
+            }
+            docs.push_str(match self.destruction {
+                Destruction::Finalizer => {
+                    "Consumes `self`: using it afterwards aborts, as does consuming an object borrowed from another one."
+                }
+                Destruction::Manual => {
+                    "Consumes `self`: it must neither be used nor freed afterwards, and must not be borrowed from another object."
+                }
+            });
+        }
+
+        if throws {
+            let exception = match method.output {
+                ReturnType::Fallible(_, Some(Type::Opaque(ref o))) => Some(o.tcx_id.into()),
+                ReturnType::Fallible(_, Some(Type::Struct(ref s))) => Some(s.id()),
+                ReturnType::Fallible(_, Some(Type::Enum(ref e))) => Some(e.tcx_id.into()),
+                _ => None,
+            }
+            .map_or("ExnDiplomat".into(), |id| {
+                self.formatter
+                    .fmt_exception_name(&self.formatter.fmt_type_name(id))
+            });
+            if !docs.is_empty() {
+                docs.push_str("\n//\n// ");
+            }
+            write!(&mut docs, "Throws `{exception}` on failure.").unwrap();
+        }
+        if checks_size {
+            if !docs.is_empty() {
+                docs.push_str("\n//\n// ");
+            }
+            docs.push_str("Throws `ExnRange` if a pointer-sized integer does not fit the target.");
+        }
+        if checks_char {
+            if !docs.is_empty() {
+                docs.push_str("\n//\n// ");
+            }
+            docs.push_str("Throws `ExnRange` if a `char` is not a Unicode scalar value.");
         }
 
-        Some(MethodInfo {
+        let mut info = MethodInfo {
             method,
             docs,
+            name,
             declaration,
+            constant,
             c_method_name,
-            param_types_ffi,
+            ffi_name,
+            ffi_inline,
+            future,
             param_types_ffi_cast,
             param_names_ffi,
-            return_type_ffi,
             return_type_ffi_cast,
             slice_params,
+            kept_batches,
+            prelude,
+            thread_checks,
+            writeback,
             needs_temp_arena,
+            result_alloc,
             param_conversions,
             return_expression,
             lifetimes: &method.lifetime_env,
-            method_lifetimes_map: visitor.borrow_map(),
-        })
+            method_lifetimes_map,
+            overridden: None,
+        };
+        if self.overrides.overrides(Part::Method) {
+            let builtin = MethodTemplate { m: &info }.render().unwrap();
+            info.overridden = Some(self.overrides.apply(
+                Part::Method,
+                builtin,
+                &[
+                    ("docs", &info.docs),
+                    ("name", &info.name),
+                    ("declaration", &info.declaration),
+                    ("ffi_name", &info.ffi_name),
+                ],
+            ));
+        }
+        Some(info)
     }
 
     fn gen_special_method_info(
         &mut self,
+        methods: &[hir::Method],
         special_method_presence: &SpecialMethodPresence,
     ) -> SpecialMethodGenInfo<'cx> {
         let mut info = SpecialMethodGenInfo {
@@ -560,6 +2202,26 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
             ..Default::default()
         };
 
+        // `show` has to be total, so fallible stringifiers only get their method
+        info.stringifier = methods
+            .iter()
+            .find(|method| {
+                matches!(
+                    method.attrs.special_method,
+                    Some(hir::SpecialMethod::Stringifier)
+                ) && !method.attrs.disable
+                    && method.param_self.is_some()
+                    && matches!(method.output, ReturnType::Infallible(_))
+            })
+            .map(|method| self.formatter.fmt_method_name(method));
+        info.hash = methods
+            .iter()
+            .find(|method| {
+                matches!(method.attrs.special_method, Some(hir::SpecialMethod::Hash))
+                    && !method.attrs.disable
+            })
+            .map(|method| self.formatter.fmt_method_name(method));
+
         if let Some(ref val) = special_method_presence.iterator {
             info.iterator = Some(self.gen_success_ty(val))
         }
@@ -579,16 +2241,42 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
     fn gen_success_ty(&mut self, out_ty: &SuccessType) -> Cow<'cx, str> {
         match out_ty {
             SuccessType::Writeable => self.formatter.fmt_string().into(),
+            // Copied out of Rust by the slice helper
+            SuccessType::OutType(Type::Slice(hir::Slice::Primitive(_, p))) => {
+                match self.gen_bytes(*p) {
+                    Some(bytes) => bytes.into(),
+                    None => format!("vector<{}>", self.formatter.fmt_primitive_as_koka(*p)).into(),
+                }
+            }
             SuccessType::OutType(o) => self.gen_type_name(o),
             SuccessType::Unit => self.formatter.fmt_void().into(),
             _ => unreachable!(),
         }
     }
 
+    /// The value a field of a struct grouping parameters with `#[diplomat::bundle]` defaults to:
+    /// zero, false, or the first variant of an enum
+    fn gen_default_value<P: TyPosition>(&self, ty: &Type<P>) -> Cow<'cx, str> {
+        match *ty {
+            Type::Primitive(hir::PrimitiveType::Bool) => "False".into(),
+            Type::Primitive(hir::PrimitiveType::Char) => "' '".into(),
+            Type::Primitive(hir::PrimitiveType::Byte) => "0.int8".into(),
+            Type::Primitive(hir::PrimitiveType::Float(_)) => "0.0".into(),
+            Type::Primitive(_) => "0".into(),
+            Type::Enum(ref e) => {
+                let def = e.resolve(self.tcx);
+                self.formatter
+                    .fmt_enum_variant(def, &def.variants[0])
+                    .into()
+            }
+            _ => unreachable!("only primitives and enums can be bundled"),
+        }
+    }
+
     /// Generates a type's Dart type.
     fn gen_type_name<P: TyPosition>(&mut self, ty: &Type<P>) -> Cow<'cx, str> {
         match *ty {
-            Type::Primitive(prim) => self.formatter.fmt_primitive_as_ffi(prim, true).into(),
+            Type::Primitive(prim) => self.formatter.fmt_primitive_as_koka(prim).into(),
             Type::Opaque(ref op) => {
                 let op_id = op.tcx_id.into();
                 let type_name = self.formatter.fmt_type_name(op_id);
@@ -624,40 +2312,89 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                 type_name
             }
             Type::Slice(hir::Slice::Str(..)) => self.formatter.fmt_string().into(),
+            // Rust writes into mutable slices, which Koka can only observe through a reference
+            Type::Slice(hir::Slice::Primitive(Some(borrow), p))
+                if borrow.mutability.is_mutable() =>
+            {
+                self.helper_classes.insert(
+                    "mut-slice".into(),
+                    "// A vector Rust writes into, which Koka observes through the reference\npub alias mut-slice<a> = ref<global, vector<a>>".into(),
+                );
+                format!("mut-slice<{}>", self.formatter.fmt_primitive_as_koka(p)).into()
+            }
             Type::Slice(hir::Slice::Primitive(_, p)) => {
                 self.formatter.fmt_primitive_list_type(p).into()
             }
             Type::Slice(hir::Slice::Strs(..)) => "core.List<core.String>".into(),
+            Type::Callback(ref callback) => self.gen_callback_type_name(callback).into(),
+            Type::DiplomatOption(ref ok) => {
+                let ok = self.gen_type_name(ok);
+                self.formatter.fmt_nullable(&ok).into()
+            }
+            Type::DynTrait(trt) => {
+                let type_name = self.formatter.fmt_trait_name(trt);
+                if self.tcx.resolve_trait(trt).attrs.disable {
+                    self.errors
+                        .push_error(format!("Found usage of disabled trait {type_name}"))
+                }
+                type_name
+            }
             _ => unreachable!("unknown AST/HIR variant"),
         }
     }
 
-    /// Generates a return type's Dart type.
-    fn gen_return_type_name(&mut self, result_ty: &ReturnType) -> Cow<'cx, str> {
-        match *result_ty {
-            ReturnType::Infallible(SuccessType::Unit)
-            | ReturnType::Fallible(SuccessType::Unit, Some(_)) => self.formatter.fmt_void().into(),
-            ReturnType::Infallible(SuccessType::Writeable)
-            | ReturnType::Fallible(SuccessType::Writeable, Some(_)) => {
-                self.formatter.fmt_string().into()
-            }
-            ReturnType::Infallible(SuccessType::OutType(ref o))
-            | ReturnType::Fallible(SuccessType::OutType(ref o), Some(_)) => self.gen_type_name(o),
-            ReturnType::Fallible(SuccessType::Writeable, None)
-            | ReturnType::Nullable(SuccessType::Writeable) => self
-                .formatter
-                .fmt_nullable(self.formatter.fmt_string())
+    /// Generates a return type's Koka type.
+    fn gen_return_type_name(
+        &mut self,
+        result_ty: &ReturnType,
+        with_warnings: Option<hir::WithWarnings<'cx>>,
+    ) -> Cow<'cx, str> {
+        let gen_ok = |this: &mut Self, ok: &SuccessType| -> Cow<'cx, str> {
+            match with_warnings {
+                Some(w) => format!(
+                    "({}, {})",
+                    this.gen_type_name(&w.value.ty),
+                    this.gen_type_name(&w.warnings.ty)
+                )
                 .into(),
-            ReturnType::Fallible(SuccessType::Unit, None)
-            | ReturnType::Nullable(SuccessType::Unit) => self
+                None => this.gen_success_ty(ok),
+            }
+        };
+        match *result_ty {
+            // Returned slices are copied out of Rust, see `gen_method_info`
+            ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Primitive(
+                _,
+                p,
+            )))) => match self.gen_bytes(p) {
+                Some(bytes) => bytes.into(),
+                None => format!("vector<{}>", self.formatter.fmt_primitive_as_koka(p)).into(),
+            },
+            ReturnType::Infallible(ref ok) => gen_ok(self, ok),
+            ReturnType::Fallible(ref ok, ref err) => {
+                let ok = gen_ok(self, ok);
+                match self.error_style {
+                    ErrorStyle::Exn => ok,
+                    ErrorStyle::Either => {
+                        let err = err
+                            .as_ref()
+                            .map_or(self.formatter.fmt_void().into(), |e| self.gen_type_name(e));
+                        self.helper_classes.insert(
+                            "result".into(),
+                            "// The result of a fallible method, `Right` on success and `Left` with the error otherwise\npub alias result<a, e> = either<e, a>".into(),
+                        );
+                        format!("result<{ok}, {err}>").into()
+                    }
+                }
+            }
+            // Option<()> is exposed as a bool
+            ReturnType::Nullable(SuccessType::Unit) => self
                 .formatter
                 .fmt_primitive_as_ffi(hir::PrimitiveType::Bool, true)
                 .into(),
-            ReturnType::Fallible(SuccessType::OutType(ref o), None)
-            | ReturnType::Nullable(SuccessType::OutType(ref o)) => {
-                self.formatter.fmt_nullable(&self.gen_type_name(o)).into()
+            ReturnType::Nullable(ref ok) => {
+                let ok = gen_ok(self, ok);
+                self.formatter.fmt_nullable(&ok).into()
             }
-            _ => unreachable!("unknown AST/HIR variant"),
         }
     }
 
@@ -681,7 +2418,7 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                     self.errors
                         .push_error(format!("Found usage of disabled type {type_name}"))
                 }
-                format!("_{type_name}Ffi").into()
+                type_name
             }
             Type::Enum(ref e) => {
                 let id = e.tcx_id.into();
@@ -707,25 +2444,101 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                 _ => "_SliceUtf16",
             }
             .into(),
+            Type::Callback(ref callback) => self.gen_callback_type_name(callback).into(),
+            Type::DiplomatOption(ref ok) => {
+                let (helper, _) = self.gen_option(ok);
+                self.formatter.fmt_pointer(&format!("{helper}-t")).into()
+            }
             _ => unreachable!("unknown AST/HIR variant"),
         }
     }
 
-    /// Generates the Dart FFI type name of a return type.
-    fn gen_return_type_name_ffi(&mut self, result_ty: &ReturnType, cast: bool) -> Cow<'cx, str> {
-        match *result_ty {
-            ReturnType::Infallible(SuccessType::Unit) => if cast {
-                self.formatter.fmt_void()
-            } else {
-                self.formatter.fmt_ffi_void()
-            }
-            .into(),
+    /// Callbacks take and return the sized FFI types, since they are called from C directly.
+    ///
+    /// They are spelled with the `callback<n>` alias of their arity, e.g. `callback1<float64, ()>`
+    /// for `(float64) -> io-noexn ()`.
+    fn gen_callback_type_name(&mut self, callback: &hir::Callback) -> String {
+        let mut args = callback
+            .params
+            .iter()
+            .map(|p| self.formatter.fmt_primitive_as_ffi(*p, false))
+            .collect::<Vec<_>>();
+        args.push(
+            callback
+                .output
+                .map_or("()", |p| self.formatter.fmt_primitive_as_ffi(p, false)),
+        );
+
+        let arity = callback.params.len();
+        let alias = format!("callback{arity}");
+        if !self.helper_classes.contains_key(&alias) {
+            let params = (0..arity).map(|i| format!("a{i}")).collect::<Vec<_>>();
+            let helper = format!(
+                "// A Koka function Rust calls back with {arity} argument{}\npub alias {alias}<{}> = ({}) -> io-noexn r",
+                if arity == 1 { "" } else { "s" },
+                params.iter().map(String::as_str).chain(["r"]).collect::<Vec<_>>().join(", "),
+                params.join(", "),
+            );
+            self.helper_classes.insert(alias.clone(), helper);
+        }
+        format!("{alias}<{}>", args.join(", "))
+    }
+
+    fn gen_callback_info(&self, c_name: &str, callback: &hir::Callback) -> CallbackInfo {
+        CallbackInfo {
+            c_name: c_name.into(),
+            output: callback
+                .output
+                .map_or("void".into(), |p| self.formatter.fmt_primitive_as_c(p)),
+            params: callback
+                .params
+                .iter()
+                .enumerate()
+                .map(|(i, p)| CallbackParam {
+                    ty: self.formatter.fmt_primitive_as_c(*p),
+                    boxed: self.formatter.fmt_primitive_box(*p, &format!("arg{i}")),
+                })
+                .collect(),
+            result: callback
+                .output
+                .map(|p| self.formatter.fmt_primitive_unbox(p, "result")),
+        }
+    }
+
+    /// Generates the Dart FFI type name of a return type.
+    fn gen_return_type_name_ffi(&mut self, result_ty: &ReturnType, cast: bool) -> Cow<'cx, str> {
+        match *result_ty {
+            ReturnType::Infallible(SuccessType::Unit) => if cast {
+                self.formatter.fmt_void()
+            } else {
+                self.formatter.fmt_ffi_void()
+            }
+            .into(),
             ReturnType::Infallible(SuccessType::Writeable) => if cast {
                 self.formatter.fmt_void()
             } else {
                 self.formatter.fmt_ffi_void()
             }
             .into(),
+            ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Str(
+                _,
+                hir::StringEncoding::UnvalidatedUtf16,
+            )))) => self.formatter.fmt_string().into(),
+            // Written to an out parameter, see `gen_method_info`
+            ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Primitive(
+                ..,
+            )))) => if cast {
+                self.formatter.fmt_void()
+            } else {
+                self.formatter.fmt_ffi_void()
+            }
+            .into(),
+            ReturnType::Infallible(SuccessType::OutType(Type::Struct(_))) => if cast {
+                self.formatter.fmt_void()
+            } else {
+                self.formatter.fmt_ffi_void()
+            }
+            .into(),
             ReturnType::Infallible(SuccessType::OutType(ref o)) => {
                 if let hir::OutType::Slice(s) = o {
                     self.gen_slice(s).into()
@@ -733,18 +2546,33 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                     self.gen_type_name_ffi(o, cast)
                 }
             }
-            ReturnType::Fallible(ref ok, ref err) => {
-                self.gen_result(ok.as_type(), err.as_ref()).into()
+            // Results are written to an out parameter, see `gen_result`
+            ReturnType::Fallible(..) | ReturnType::Nullable(..) => if cast {
+                self.formatter.fmt_void()
+            } else {
+                self.formatter.fmt_ffi_void()
             }
-            ReturnType::Nullable(ref ok) => self.gen_result(ok.as_type(), None).into(),
+            .into(),
             _ => unreachable!("unknown AST/HIR variant"),
         }
     }
 
+    /// Generates the C function unboxing an opaque argument, checking that no method consumed
+    /// it if any can.
+    fn gen_unbox_name(&self, id: hir::OpaqueId) -> String {
+        if is_consumable(self.tcx.resolve_opaque(id)) {
+            self.formatter.fmt_c_unbox_name(id.into())
+        } else {
+            "kk_cptr_raw_unbox_borrowed".into()
+        }
+    }
+
     /// Generates a self type's Dart FFI type.
     fn gen_self_type_name_ffi(&self, ty: &SelfType, cast: bool) -> Cow<'cx, str> {
         match ty {
-            SelfType::Opaque(_) => self.formatter.fmt_pointer("()").into(),
+            SelfType::Opaque(_) | SelfType::BoxedOpaque(_) => {
+                self.formatter.fmt_pointer("()").into()
+            }
             SelfType::Struct(s) => format!("_{}Ffi", s.resolve(self.tcx).name.as_str()).into(),
             SelfType::Enum(_) => self.formatter.fmt_enum_as_ffi(cast).into(),
             _ => unreachable!("unknown AST/HIR variant"),
@@ -754,34 +2582,45 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
     /// Generates an FFI expression for a self type.
     fn gen_dart_to_c_self(&self, ty: &SelfType) -> Cow<'static, str> {
         match *ty {
-            SelfType::Enum(ref e) if is_contiguous_enum(e.resolve(self.tcx)) => "index".into(),
-            SelfType::Struct(..) => "_toFfi(temp)".into(),
-            SelfType::Opaque(..) | SelfType::Enum(..) => "_ffi".into(),
+            SelfType::Struct(..) => "self._toFfi(temp)".into(),
+            SelfType::Opaque(..) | SelfType::BoxedOpaque(..) => "self.raw".into(),
+            SelfType::Enum(..) => "self.to-int".into(),
             _ => unreachable!("unknown AST/HIR variant"),
         }
     }
 
-    /// Generates an FFI expression for a type.
-    ///
-    /// For struct parameters borrowed by the output, `struct_borrow_info` is a map of
+    /// Generates the pointer to the Rust object behind an opaque. In finalizer mode it is only
+    /// valid as long as the opaque is alive.
+    fn gen_opaque_ptr(&self, opaque: &str) -> String {
+        match self.destruction {
+            Destruction::Finalizer => format!("{opaque}.raw.ptr"),
+            Destruction::Manual => format!("{opaque}.raw"),
+        }
+    }
+
+    /// Generates an FFI expression for a type. Structs are written into C memory with `to-c`
+    /// instead.
     fn gen_dart_to_c_for_type<P: TyPosition>(
         &mut self,
         ty: &Type<P>,
         dart_name: Cow<'cx, str>,
-        struct_borrow_info: Option<&StructBorrowContext<'cx>>,
     ) -> Cow<'cx, str> {
         match *ty {
-            Type::Primitive(..) => dart_name.clone(),
+            Type::Primitive(hir::PrimitiveType::IntSize(size)) => {
+                let conversion = match self.checks {
+                    Checks::Full => "from-int",
+                    Checks::Minimal => "truncate",
+                };
+                format!("{}/{conversion}({dart_name})", size_helper(size)).into()
+            }
+            Type::Primitive(..) | Type::Callback(..) => dart_name.clone(),
             Type::Opaque(ref op) if op.is_optional() => format!(
-                // Use coalescing to only evaluate `{dart_name}` once
-                "{dart_name}?._ffi ?? ffi.Pointer.fromAddress(0)"
+                "{dart_name}.map(fn(o) {}).default(null-pointer())",
+                self.gen_opaque_ptr("o")
             )
             .into(),
-            Type::Enum(ref e) if is_contiguous_enum(e.resolve(self.tcx)) => {
-                format!("{dart_name}.index").into()
-            }
-            Type::Struct(..) => self.gen_dart_to_c_for_struct_type(dart_name, struct_borrow_info),
-            Type::Opaque(..) | Type::Enum(..) => format!("{dart_name}._ffi").into(),
+            Type::Opaque(..) => self.gen_opaque_ptr(&dart_name).into(),
+            Type::Enum(..) => format!("{dart_name}.to-int").into(),
             Type::Slice(hir::Slice::Str(_, encoding) | hir::Slice::Strs(encoding)) => {
                 match encoding {
                     hir::StringEncoding::UnvalidatedUtf8 | hir::StringEncoding::Utf8 => {
@@ -800,38 +2639,6 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         }
     }
 
-    /// Generates an FFI expression for a struct
-    fn gen_dart_to_c_for_struct_type(
-        &mut self,
-        dart_name: Cow<'cx, str>,
-        struct_borrow_info: Option<&StructBorrowContext<'cx>>,
-    ) -> Cow<'cx, str> {
-        let mut params = String::new();
-        if let Some(info) = struct_borrow_info {
-            for (def_lt, use_lts) in &info.param_info.borrowed_struct_lifetime_map {
-                write!(
-                    &mut params,
-                    ", {}AppendArray: [",
-                    info.param_info.env.fmt_lifetime(def_lt)
-                )
-                .unwrap();
-                let mut maybe_comma = "";
-                for use_lt in use_lts {
-                    // Generate stuff like `, aEdges` or for struct fields, `, ...aAppendArray`
-                    let lt = info.use_env.fmt_lifetime(use_lt);
-                    if info.is_method {
-                        write!(&mut params, "{maybe_comma}{lt}Edges",).unwrap();
-                    } else {
-                        write!(&mut params, "{maybe_comma}...{lt}AppendArray",).unwrap();
-                    }
-                    maybe_comma = ", ";
-                }
-                write!(&mut params, "]").unwrap();
-            }
-        }
-        format!("{dart_name}._toFfi(temp{params})").into()
-    }
-
     /// Generates a Dart expression for a type.
     fn gen_c_to_dart_for_type<P: TyPosition>(
         &mut self,
@@ -840,6 +2647,9 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         lifetime_env: &LifetimeEnv,
     ) -> Cow<'cx, str> {
         match *ty {
+            Type::Primitive(hir::PrimitiveType::IntSize(size)) => {
+                format!("{}/to-int({var_name})", size_helper(size)).into()
+            }
             Type::Primitive(..) => var_name,
             Type::Opaque(ref op) => {
                 let id = op.tcx_id.into();
@@ -847,7 +2657,7 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
                 let mut edges = if let Some(lt) = op.owner.lifetime() {
                     let MaybeStatic::NonStatic(lt) = lt else {
-                        panic!("'static not supported in Dart")
+                        unreachable!("'static is rejected by `check_supported`")
                     };
                     self.formatter
                         .fmt_lifetime_edge_array(lt, lifetime_env)
@@ -858,7 +2668,7 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
                 for lt in op.lifetimes.lifetimes() {
                     let MaybeStatic::NonStatic(lt) = lt else {
-                        panic!("'static not supported in Dart");
+                        unreachable!("'static is rejected by `check_supported`");
                     };
                     // We only generate a single edge in the list per lifetime, despite transitivity
                     //
@@ -874,38 +2684,39 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                     .unwrap();
                 }
 
+                let qualifier = self.formatter.fmt_type_qualifier(&type_name);
                 if op.is_optional() {
-                    format!("{var_name}.address == 0 ? null : {type_name}._fromFfi({var_name}, {edges})").into()
+                    format!(
+                        "if is-null({var_name}) then Nothing else Just({qualifier}/from-ffi({var_name}, {edges}))"
+                    )
+                    .into()
                 } else {
-                    format!("{type_name}._fromFfi({var_name}, {edges})").into()
+                    format!("{qualifier}/from-ffi({var_name}, {edges})").into()
                 }
             }
             Type::Struct(ref st) => {
-                let id = st.id();
-                let type_name = self.formatter.fmt_type_name(id);
-                let mut edges = String::new();
-                for lt in st.lifetimes().lifetimes() {
-                    let MaybeStatic::NonStatic(lt) = lt else {
-                        panic!("'static not supported in Dart")
-                    };
-                    write!(&mut edges, ", {}Edges", lifetime_env.fmt_lifetime(lt)).unwrap();
-                }
-
-                format!("{type_name}._fromFfi({var_name}{edges})").into()
-            }
-            Type::Enum(ref e) if is_contiguous_enum(e.resolve(self.tcx)) => {
-                let id = e.tcx_id.into();
-                let type_name = self.formatter.fmt_type_name(id);
-                format!("{type_name}.values[{var_name}]").into()
+                let type_name = self.formatter.fmt_type_name(st.id());
+                format!(
+                    "{}/from-c({var_name}{})",
+                    self.formatter.fmt_type_qualifier(&type_name),
+                    self.gen_struct_edges(st, lifetime_env)
+                )
+                .into()
             }
             Type::Enum(ref e) => {
-                let id = e.tcx_id.into();
-                let type_name = self.formatter.fmt_type_name(id);
-                format!("{type_name}.values.firstWhere((v) => v._ffi == {var_name})").into()
+                let type_name = self.formatter.fmt_type_name(e.tcx_id.into());
+                let qualifier = self.formatter.fmt_type_qualifier(&type_name);
+                format!("{qualifier}/from-ffi({var_name})").into()
+            }
+            // Results hold the view itself, which the slice helper copies into a vector
+            Type::Slice(hir::Slice::Primitive(lifetime, p)) => {
+                let helper = self.gen_prim_slice(p);
+                let read = if lifetime.is_some() { "copy" } else { "take" };
+                format!("{helper}/{read}({var_name})").into()
             }
             Type::Slice(slice) => if let Some(lt) = slice.lifetime() {
                 let MaybeStatic::NonStatic(lifetime) = lt else {
-                    panic!("'static not supported in Dart");
+                    unreachable!("'static is rejected by `check_supported`");
                 };
                 format!(
                     "{var_name}._toDart({}Edges)",
@@ -919,64 +2730,152 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         }
     }
 
-    /// Generates a Dart expressions for a return type.
+    /// Generates the edge lists passed to `from-c` for the lifetimes of a struct, from the
+    /// edges of the lifetimes it is used with. `'static` borrows need no edges.
+    fn gen_struct_edges(&self, st: &impl StructPathLike, lifetime_env: &LifetimeEnv) -> String {
+        st.lifetimes()
+            .lifetimes()
+            .map(|lt| match lt {
+                MaybeStatic::NonStatic(lt) => format!(
+                    ", {}",
+                    self.formatter.fmt_lifetime_edge_array(lt, lifetime_env)
+                ),
+                MaybeStatic::Static => ", []".into(),
+            })
+            .collect()
+    }
+
+    /// Generates a Koka expression for a return type.
+    ///
+    /// `result_helper` is the helper generated by [`Self::gen_result()`] for fallible and
+    /// nullable return types.
+    ///
+    /// With `split_warnings`, the out struct returned by a `with_warnings` method is turned
+    /// into a tuple of its value and warnings.
     fn gen_c_to_dart_for_return_type(
         &mut self,
-        result_ty: &ReturnType,
+        result_ty: &'cx ReturnType,
+        split_warnings: bool,
+        result_helper: Option<&str>,
+        c_method_name: &str,
         lifetime_env: &LifetimeEnv,
     ) -> Option<Cow<'cx, str>> {
-        match *result_ty {
-            ReturnType::Infallible(SuccessType::Unit) => None,
+        let (ok, err) = match *result_ty {
+            ReturnType::Infallible(SuccessType::Unit) => return None,
             ReturnType::Infallible(SuccessType::Writeable) => {
-                // Note: the `writeable` variable is initialized in the template
-                Some("return writeable.finalize();".into())
+                // Note: the `written` variable is bound by `with-writeable` in the template
+                return Some("written".into());
+            }
+            // Already transcoded by the extern, see `gen_method_info`
+            ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Str(
+                _,
+                hir::StringEncoding::UnvalidatedUtf16,
+            )))) => return Some("result".into()),
+            // Owned slices are released once copied
+            ReturnType::Infallible(SuccessType::OutType(Type::Slice(hir::Slice::Primitive(
+                lifetime,
+                p,
+            )))) if result_helper.is_some() => {
+                let helper = self.gen_bytes(p).unwrap_or(result_helper.unwrap());
+                return Some(if lifetime.is_some() {
+                    format!("{helper}/copy(result)").into()
+                } else {
+                    format!("{helper}/take(result)").into()
+                });
+            }
+            ReturnType::Infallible(SuccessType::OutType(ref out_ty)) => {
+                let value = self.gen_c_to_dart_for_type(out_ty, "result".into(), lifetime_env);
+                return Some(if split_warnings {
+                    Self::gen_split_warnings(&value).into()
+                } else {
+                    value
+                });
+            }
+            ReturnType::Fallible(ref ok, ref err) => (ok, err.as_ref()),
+            ReturnType::Nullable(ref ok) => (ok, None),
+            _ => unreachable!("unknown AST/HIR variant"),
+        };
+
+        // Note: the `result` variable is allocated in the template and filled in by the C method
+        let helper = result_helper.expect("Fallible methods must have a result helper");
+        let is_ok = format!("{helper}/is-ok(result)");
+
+        let ok_expr: Cow<str> = match ok {
+            // Option<()> is exposed as a bool
+            SuccessType::Unit if matches!(result_ty, ReturnType::Nullable(_)) => {
+                return Some(is_ok.into())
+            }
+            SuccessType::Unit => self.formatter.fmt_void().into(),
+            SuccessType::Writeable => "written".into(),
+            // Struct fields stay vectors, only returned bytes are bridged
+            SuccessType::OutType(Type::Slice(hir::Slice::Primitive(lifetime, p)))
+                if self.gen_bytes(*p).is_some() =>
+            {
+                let read = if lifetime.is_some() { "copy" } else { "take" };
+                format!("bytes/{read}({helper}/ok(result))").into()
+            }
+            SuccessType::OutType(o) => {
+                let value = self.gen_c_to_dart_for_type(
+                    o,
+                    format!("{helper}/ok(result)").into(),
+                    lifetime_env,
+                );
+                if split_warnings {
+                    Self::gen_split_warnings(&value).into()
+                } else {
+                    value
+                }
             }
-            ReturnType::Infallible(SuccessType::OutType(ref out_ty)) => Some(
+            _ => unreachable!("unknown AST/HIR variant"),
+        };
+        let err_expr = err.map(|e| {
+            self.gen_c_to_dart_for_type(e, format!("{helper}/err(result)").into(), lifetime_env)
+        });
+
+        let (ok_expr, err_expr) = match (result_ty, self.error_style) {
+            (ReturnType::Nullable(_), _) => (format!("Just({ok_expr})"), "Nothing".into()),
+            (_, ErrorStyle::Either) => (
+                format!("Right({ok_expr})"),
                 format!(
-                    "return {};",
-                    self.gen_c_to_dart_for_type(out_ty, "result".into(), lifetime_env)
-                )
-                .into(),
+                    "Left({})",
+                    err_expr.as_deref().unwrap_or(self.formatter.fmt_void())
+                ),
             ),
-            // Special case Result<(), ()> and Option<()> to bool
-            ReturnType::Fallible(SuccessType::Unit, None)
-            | ReturnType::Nullable(SuccessType::Unit) => Some("return result.isOk;".into()),
-            ReturnType::Fallible(ref ok, _) | ReturnType::Nullable(ref ok) => {
-                let err_check = format!(
-                    "if (!result.isOk) {{\n  {}\n}}\n",
-                    match result_ty {
-                        ReturnType::Fallible(_, Some(e)) => format!(
-                            "throw {};",
-                            self.gen_c_to_dart_for_type(e, "result.union.err".into(), lifetime_env)
-                        ),
-                        _ => "return null;".into(),
+            (_, ErrorStyle::Exn) => {
+                let message = format!("{c_method_name} failed");
+                let throw = match (err, err_expr) {
+                    (Some(Type::Opaque(o)), Some(e)) => {
+                        self.gen_throw(&message, o.tcx_id.into(), &e)
                     }
-                );
-
-                Some(
-                    match ok {
-                        // Note: the `writeable` variable is initialized in the template
-                        SuccessType::Writeable => {
-                            format!("{err_check}return writeable.finalize();")
-                        }
-                        SuccessType::OutType(o) => {
-                            format!(
-                                "{err_check}return {};",
-                                self.gen_c_to_dart_for_type(
-                                    o,
-                                    "result.union.ok".into(),
-                                    lifetime_env
-                                )
-                            )
-                        }
-                        SuccessType::Unit => err_check,
-                        _ => unreachable!("unknown AST/HIR variant"),
+                    (Some(Type::Struct(s)), Some(e)) => self.gen_throw(&message, s.id(), &e),
+                    (Some(Type::Enum(en)), Some(e)) => {
+                        self.gen_throw(&message, en.tcx_id.into(), &e)
                     }
-                    .into(),
-                )
+                    // Other errors carry no type of their own, describe them in the message
+                    (Some(Type::Primitive(_)), Some(e)) => {
+                        format!("throw(\"{message} with \" ++ {e}.show, ExnDiplomat)")
+                    }
+                    _ => format!("throw(\"{message}\", ExnDiplomat)"),
+                };
+                (ok_expr.into_owned(), throw)
             }
-            _ => unreachable!("unknown AST/HIR variant"),
-        }
+        };
+
+        Some(format!("if {is_ok} then\n  {ok_expr}\nelse\n  {err_expr}").into())
+    }
+
+    /// Generates a tuple of the value and warnings of the out struct returned by a
+    /// `with_warnings` method.
+    fn gen_split_warnings(out_struct: &str) -> String {
+        format!("{{ val out = {out_struct}; (out.value, out.warnings) }}")
+    }
+
+    /// Generates a `throw` of the `exception-info` constructor of an error type.
+    fn gen_throw(&self, message: &str, error_ty: TypeId, error: &str) -> String {
+        let exception = self
+            .formatter
+            .fmt_exception_name(&self.formatter.fmt_type_name(error_ty));
+        format!("throw(\"{message}\", {exception}({error}))")
     }
 
     /// Generates a Dart helper class for a slice type.
@@ -1046,7 +2945,7 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
                     hir::PrimitiveType::Int(hir::IntType::U32 | hir::IntType::I32) | hir::PrimitiveType::Float(hir::FloatType::F32) => "  _rustFree.attach(r, (pointer: _data.cast(), bytes: _length * 4, align: 4));",
                     hir::PrimitiveType::Int(hir::IntType::U64 | hir::IntType::I64) | hir::PrimitiveType::Float(hir::FloatType::F64) => "  _rustFree.attach(r, (pointer: _data.cast(), bytes: _length * 8, align: 8));",
                     hir::PrimitiveType::IntSize(..) => "  _rustFree.attach(r, (pointer: _data.cast(), bytes: _length * ffi.sizeOf<ffi.Size>(), align: ffi.sizeOf<ffi.Size>()));",
-                    hir::PrimitiveType::Int128(_) => panic!("i128 not supported in Dart"),
+                    hir::PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
                 },
                 "} else {",
                 "  // Keep lifetimeEdges alive",
@@ -1081,67 +2980,294 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
         slice_ty
     }
 
-    /// Generates a Dart helper class for a result type.
-    fn gen_result(&mut self, ok: Option<&hir::OutType>, err: Option<&hir::OutType>) -> String {
-        let name = format!(
-            "_Result{}{}",
-            &self
-                .formatter
-                .fmt_type_as_ident(ok.map(|o| self.gen_type_name_ffi(o, false)).as_deref()),
-            &self
-                .formatter
-                .fmt_type_as_ident(err.map(|o| self.gen_type_name_ffi(o, false)).as_deref())
+    /// Generates a Koka helper copying vectors into and out of the buffers of slices of a
+    /// primitive, returning its name.
+    /// Generates the `bytes` helpers that `&[u8]` parameters and returns are bridged to, for
+    /// building them from lists and strings, and returns their name. Other slices are `None`.
+    fn gen_bytes(&mut self, prim: hir::PrimitiveType) -> Option<&'static str> {
+        use hir::{IntType, PrimitiveType};
+        if !matches!(prim, PrimitiveType::Int(IntType::U8) | PrimitiveType::Byte) {
+            return None;
+        }
+        // Returned views are read with the slice helpers
+        self.gen_prim_slice(prim);
+        self.helper_classes.insert(
+            "bytes".into(),
+            include_str!("../../templates/koka/bytes.kk").into(),
         );
+        Some("bytes")
+    }
 
+    fn gen_prim_slice(&mut self, prim: hir::PrimitiveType) -> String {
+        use hir::{IntSizeType, IntType, PrimitiveType};
+        // Rust reads each `bool` as a byte holding 0 or 1, which is spelled out rather than
+        // left to how Koka represents its `bool`s
+        let c_ty = match prim {
+            PrimitiveType::Bool => "uint8_t".into(),
+            _ => self.formatter.fmt_primitive_as_c(prim),
+        };
+        let name = self.formatter.fmt_primitive_slice_helper(prim);
         if self.helper_classes.contains_key(&name) {
             return name;
         }
 
-        let decls = [ok.map(|o| (o, "ok")), err.map(|o| (o, "err"))]
+        // Koka `int`s are passed through the smallest sized type that holds every value
+        let (ffi_ty, to_ffi, from_ffi) = match prim {
+            PrimitiveType::Int(IntType::U64) => (
+                "int64",
+                "(if x >= pow(2, 63) then x - pow(2, 64) else x).int64",
+                "if x.int < 0 then x.int + pow(2, 64) else x.int",
+            ),
+            PrimitiveType::Int(IntType::U32 | IntType::I64) => ("int64", "x.int64", "x.int"),
+            PrimitiveType::Int(_) => ("int32", "x.int32", "x.int"),
+            PrimitiveType::IntSize(IntSizeType::Usize) => {
+                ("ssize_t", "usize/truncate(x)", "usize/to-int(x)")
+            }
+            PrimitiveType::IntSize(IntSizeType::Isize) => {
+                ("ssize_t", "isize/truncate(x)", "isize/to-int(x)")
+            }
+            PrimitiveType::Bool => ("int8", "if x then 1.int8 else 0.int8", "x.int != 0"),
+            _ => (self.formatter.fmt_primitive_as_koka(prim), "x", "x"),
+        };
+        let size = match prim {
+            PrimitiveType::Bool | PrimitiveType::Byte => "1",
+            PrimitiveType::Int(IntType::I8 | IntType::U8) => "1",
+            PrimitiveType::Int(IntType::I16 | IntType::U16) => "2",
+            PrimitiveType::Int(IntType::I32 | IntType::U32) | PrimitiveType::Char => "4",
+            PrimitiveType::Float(hir::FloatType::F32) => "4",
+            PrimitiveType::Int(IntType::I64 | IntType::U64) => "8",
+            PrimitiveType::Float(hir::FloatType::F64) => "8",
+            PrimitiveType::IntSize(_) => "(size-bits / 8)",
+            PrimitiveType::Int128(_) => unreachable!("i128 is rejected by `check_supported`"),
+        };
+
+        #[derive(askama::Template)]
+        #[template(path = "koka/prim_slice.kk.jinja", escape = "none")]
+        struct PrimSliceTemplate<'a> {
+            name: &'a str,
+            c_ty: &'a str,
+            view_ty: &'a str,
+            ffi_ty: &'a str,
+            elem_ty: &'a str,
+            size: &'a str,
+            to_ffi: &'a str,
+            from_ffi: &'a str,
+        }
+
+        self.helper_classes.insert(
+            name.clone(),
+            PrimSliceTemplate {
+                name: &name,
+                c_ty: &c_ty,
+                view_ty: self.formatter.fmt_primitive_slice_name(prim),
+                ffi_ty,
+                elem_ty: self.formatter.fmt_primitive_as_koka(prim),
+                size,
+                to_ffi,
+                from_ffi,
+            }
+            .render()
+            .unwrap(),
+        );
+
+        name
+    }
+
+    /// Generates a Koka helper for a C result type, returning the names of the helper and the C struct.
+    fn gen_result(
+        &mut self,
+        ok: Option<&'cx hir::OutType>,
+        err: Option<&'cx hir::OutType>,
+    ) -> (String, String) {
+        let c_name = self.formatter.fmt_c_result_name(ok, err);
+        let name = self.formatter.fmt_result_helper_name(&c_name);
+
+        if self.helper_classes.contains_key(&name) {
+            return (name, c_name);
+        }
+
+        let fields = [ok.map(|o| (o, "ok")), err.map(|e| (e, "err"))]
             .into_iter()
             .flatten()
-            .map(|(o, field_name)| {
-                format!(
-                    "{}external {} {field_name};",
-                    match o {
-                        hir::OutType::Primitive(p) => {
-                            format!("@{}()\n", self.formatter.fmt_primitive_as_ffi(*p, false))
-                        }
-                        hir::OutType::Enum(_) =>
-                            format!("@{}()\n", self.formatter.fmt_enum_as_ffi(false)),
-                        _ => String::new(),
-                    },
-                    { self.gen_type_name_ffi(o, true) }
-                )
+            .map(|(o, field)| {
+                let (ty, expr): (Cow<str>, _) = match o {
+                    hir::OutType::Primitive(p) => (
+                        self.formatter.fmt_primitive_as_ffi(*p, false).into(),
+                        format!("(({c_name}*)#1)->{field}"),
+                    ),
+                    hir::OutType::Enum(_) => (
+                        self.formatter.fmt_enum_as_ffi(false).into(),
+                        format!("(({c_name}*)#1)->{field}"),
+                    ),
+                    hir::OutType::Opaque(_) => (
+                        self.formatter.fmt_pointer("()").into(),
+                        format!("(intptr_t)(({c_name}*)#1)->{field}"),
+                    ),
+                    // Structs and slices are returned by reference into the result
+                    hir::OutType::Slice(hir::Slice::Primitive(_, p)) => (
+                        self.formatter
+                            .fmt_pointer(&format!("{}-t", self.gen_prim_slice(*p)))
+                            .into(),
+                        format!("(intptr_t)&(({c_name}*)#1)->{field}"),
+                    ),
+                    hir::OutType::Slice(s) => (
+                        self.formatter.fmt_pointer(self.gen_slice(s)).into(),
+                        format!("(intptr_t)&(({c_name}*)#1)->{field}"),
+                    ),
+                    _ => (
+                        self.formatter
+                            .fmt_pointer(&self.gen_type_name_ffi(o, true))
+                            .into(),
+                        format!("(intptr_t)&(({c_name}*)#1)->{field}"),
+                    ),
+                };
+                ResultField {
+                    name: field,
+                    ty: ty.into_owned(),
+                    expr,
+                }
             })
             .collect();
 
+        struct ResultField {
+            name: &'static str,
+            ty: String,
+            expr: String,
+        }
+
         #[derive(askama::Template)]
         #[template(path = "koka/result.kk.jinja", escape = "none")]
-        struct ResultTemplate {
-            name: String,
-            decls: Vec<String>,
+        struct ResultTemplate<'a> {
+            name: &'a str,
+            c_name: &'a str,
+            fields: Vec<ResultField>,
         }
 
         self.helper_classes.insert(
             name.clone(),
             ResultTemplate {
-                name: name.clone(),
-                decls,
+                name: &name,
+                c_name: &c_name,
+                fields,
             }
             .render()
             .unwrap(),
         );
 
-        name
+        (name, c_name)
+    }
+
+    /// Generates the helper reading and writing the C struct an `Option<value>` parameter or
+    /// `DiplomatOption<value>` field is passed as, returning its name and the name of the struct
+    fn gen_option<P: TyPosition>(&mut self, value: &Type<P>) -> (String, String) {
+        let c_name = self.formatter.fmt_c_option_name(value);
+        let name = self.formatter.fmt_result_helper_name(&c_name);
+
+        if self.helper_classes.contains_key(&name) {
+            return (name, c_name);
+        }
+
+        // Structs are read and written in place, like nested struct fields
+        let (ok_ty, ok_expr, set_ok_expr) = match *value {
+            Type::Struct(ref st) => (
+                self.formatter
+                    .fmt_pointer(&self.formatter.fmt_type_name(st.id())),
+                format!("(intptr_t)&(({c_name}*)#1)->ok"),
+                None,
+            ),
+            _ => (
+                self.gen_type_name_ffi(value, true).into_owned(),
+                format!("(({c_name}*)#1)->ok"),
+                Some(format!("(({c_name}*)#1)->ok = #2")),
+            ),
+        };
+
+        #[derive(askama::Template)]
+        #[template(path = "koka/option.kk.jinja", escape = "none")]
+        struct OptionTemplate<'a> {
+            name: &'a str,
+            c_name: &'a str,
+            ok_ty: String,
+            ok_expr: String,
+            set_ok_expr: Option<String>,
+        }
+
+        self.helper_classes.insert(
+            name.clone(),
+            OptionTemplate {
+                name: &name,
+                c_name: &c_name,
+                ok_ty,
+                ok_expr,
+                set_ok_expr,
+            }
+            .render()
+            .unwrap(),
+        );
+
+        (name, c_name)
+    }
+
+    /// Generates the function passed to `read` reading the value of an option
+    fn gen_option_read<P: TyPosition>(
+        &mut self,
+        value: &Type<P>,
+        helper: &str,
+        lifetimes: &LifetimeEnv,
+    ) -> String {
+        let ok = self.gen_c_to_dart_for_type(value, format!("{helper}/ok(o)").into(), lifetimes);
+        format!("fn(o) {ok}")
+    }
+
+    /// Generates the function passed to `write` and `to-c` writing the value of an option, with
+    /// the slices of a struct value copied into the batch `batch`
+    fn gen_option_write<P: TyPosition>(
+        &mut self,
+        value: &Type<P>,
+        helper: &str,
+        batch: &str,
+    ) -> String {
+        match *value {
+            Type::Struct(ref st) => {
+                let type_name = self.formatter.fmt_type_name(st.id());
+                format!(
+                    "fn(x, o) {}/write-c(x, {helper}/ok(o), {batch})",
+                    self.formatter.fmt_type_qualifier(&type_name)
+                )
+            }
+            _ => {
+                let mut conversion = self.gen_dart_to_c_for_type(value, "x".into());
+                if let (Type::Primitive(hir::PrimitiveType::Char), Checks::Full) =
+                    (value, self.checks)
+                {
+                    conversion = format!("char/to-ffi({conversion})").into();
+                }
+                format!("fn(x, o) {helper}/set-ok(o, {conversion})")
+            }
+        }
+    }
+}
+
+/// The `lib` helper converting between Koka `int`s and a pointer-sized integer
+fn size_helper(size: hir::IntSizeType) -> &'static str {
+    match size {
+        hir::IntSizeType::Usize => "usize",
+        hir::IntSizeType::Isize => "isize",
     }
 }
 
-fn is_contiguous_enum(ty: &hir::EnumDef) -> bool {
-    ty.variants
-        .iter()
-        .enumerate()
-        .all(|(i, v)| i as isize == v.discriminant)
+/// Whether methods may consume the opaque, taking `self: Box<Self>`
+fn is_consumable(ty: &hir::OpaqueDef) -> bool {
+    !ty.attrs.parent_managed
+        && ty.methods.iter().any(|m| {
+            matches!(
+                m.param_self,
+                Some(hir::ParamSelf {
+                    ty: SelfType::BoxedOpaque(..),
+                    ..
+                })
+            )
+        })
 }
 
 /// Everything needed for rendering a method.
@@ -1150,31 +3276,50 @@ struct MethodInfo<'a> {
     method: &'a hir::Method,
     /// Docs
     docs: String,
+    /// The name of the generated function
+    name: String,
     /// The declaration (everything before the parameter list)
     declaration: String,
+    /// The Koka literal a constant is declared as a value with, instead of a function
+    constant: Option<String>,
     /// The C method name
     c_method_name: Cow<'a, str>,
+    /// The name of the Koka `extern` binding the C method
+    ffi_name: String,
+    /// Inline C used by the `extern` instead of calling the C method directly
+    ffi_inline: Option<String>,
+    /// The `extern`s for the future of an `async fn`, returned by the C method
+    future: Option<FutureInfo>,
 
-    // The types for the FFI declaration. The uncast types are the types
-    // from the `dart:ffi` package, the cast types are native Dart types.
-    param_types_ffi: Vec<Cow<'a, str>>,
+    // The types and names of the FFI declaration
     param_types_ffi_cast: Vec<Cow<'a, str>>,
     param_names_ffi: Vec<Cow<'a, str>>,
-    return_type_ffi: Cow<'a, str>,
     return_type_ffi_cast: Cow<'a, str>,
 
     /// All slice parameters, and their conversion code
     slice_params: Vec<SliceParam<'a>>,
+    /// The struct parameters whose slice fields the return value borrows, written into a
+    /// `kept-batch` named after them
+    kept_batches: Vec<String>,
+    /// Bindings made before the call, once the temporary batch is available
+    prelude: Vec<String>,
+    /// Calls aborting when opaques that are not `Send` are passed on another thread
+    thread_checks: Vec<String>,
+    /// Assignments made after the call, copying mutable slices back
+    writeback: Vec<String>,
     /// The invocation of the Rust method might need temporary allocations,
     /// for which we use a Dart Arena type.
     needs_temp_arena: bool,
+    /// Allocates the `result` the C method writes into from the temporary batch, if the
+    /// method returns a result, a slice, or a struct by value.
+    result_alloc: Option<String>,
 
     /// Conversion code for each parameter
     param_conversions: Vec<Cow<'a, str>>,
 
-    /// If the function has a return value, the Dart code for the conversion. Assumes that
+    /// If the function has a return value, the Koka code for the conversion. Assumes that
     /// the C function return value is saved to a variable named `result` or that the
-    /// writeable, if present, is saved to a variable named `writeable`.
+    /// writeable, if present, is saved to a variable named `written`.
     return_expression: Option<Cow<'a, str>>,
 
     lifetimes: &'a LifetimeEnv,
@@ -1183,6 +3328,26 @@ struct MethodInfo<'a> {
     /// an internal slice View that was temporarily constructed, or
     /// a spread of a struct's `_fiellsForLifetimeFoo` getter.
     method_lifetimes_map: BTreeMap<Lifetime, BorrowedLifetimeInfo<'a>>,
+    /// The rendering of the `method.kk` template overriding the built-in one
+    overridden: Option<String>,
+}
+
+/// The built-in rendering of a method, which the templates of types include directly
+#[derive(Template)]
+#[template(path = "koka/method.kk.jinja", escape = "none")]
+struct MethodTemplate<'a, 'b> {
+    m: &'b MethodInfo<'a>,
+}
+
+/// The `extern`s polling and destroying the future of an `async fn` method
+struct FutureInfo {
+    poll_ffi_name: String,
+    poll_c_name: String,
+    destroy_ffi_name: String,
+    destroy_c_name: String,
+    /// The helper wrapping the C result struct the output of the future is polled into
+    result_helper: String,
+    result_c_name: String,
 }
 
 struct SliceParam<'a> {
@@ -1194,85 +3359,2615 @@ struct SliceParam<'a> {
     is_borrowed: bool,
 }
 
+/// A C trampoline calling a Koka closure, see `callbacks.c.jinja` and `trait.c.jinja`
+struct CallbackInfo {
+    /// The C struct the callback is passed to Rust as, or the prefix of a trait method's trampoline
+    c_name: String,
+    /// The C return type
+    output: Cow<'static, str>,
+    params: Vec<CallbackParam>,
+    /// How to unbox the `result` of the closure, if it returns a value
+    result: Option<String>,
+}
+
+struct CallbackParam {
+    /// The C type
+    ty: Cow<'static, str>,
+    /// How to box the argument for the Koka closure
+    boxed: String,
+}
+
 struct FieldInfo<'a, P: TyPosition> {
     name: Cow<'a, str>,
+    /// The name of the field in the C struct
+    c_name: &'a str,
     ty: &'a Type<P>,
     annotation: Option<&'static str>,
-    ffi_cast_type_name: Cow<'a, str>,
-    dart_type_name: Cow<'a, str>,
-    c_to_dart: Cow<'a, str>,
-    dart_to_c: Vec<String>,
-    /// If this is a struct field that borrows, the borrowing information for that field.
-    maybe_struct_borrow_info: Option<StructBorrowInfo<'a>>,
+    koka_type_name: Cow<'a, str>,
+    /// The accessors of the field in the C struct
+    access: FieldAccess,
 }
 
-// Helpers used in templates (Askama has restrictions on Rust syntax)
-
-fn display_lifetime_edge<'a>(edge: &'a LifetimeEdge) -> Cow<'a, str> {
-    let param_name = &edge.param_name;
-    match edge.kind {
-        // Opaque parameters are just retained as edges
-        LifetimeEdgeKind::OpaqueParam => param_name.into(),
-        // Slice parameters make an arena which is retained as an edge
-        LifetimeEdgeKind::SliceParam => format!("{param_name}Arena").into(),
-        // We extract the edge-relevant fields for a borrowed struct lifetime
-        LifetimeEdgeKind::StructLifetime(def_env, def_lt) => format!(
-            "...{param_name}._fieldsForLifetime{}",
-            def_env.fmt_lifetime(def_lt).to_uppercase(),
-        )
-        .into(),
-        _ => unreachable!("Unknown lifetime edge kind {:?}", edge.kind),
-    }
+/// A field of a struct grouping parameters with `#[diplomat::bundle]`, with its default value
+struct BundleField<'a> {
+    name: Cow<'a, str>,
+    ty: Cow<'a, str>,
+    default: Cow<'a, str>,
 }
 
-/// Iterate over fields, filtering by fields that actually use lifetimes from `lifetimes`
-fn iter_fields_with_lifetimes_from_set<'a, P: TyPosition>(
-    fields: &'a [FieldInfo<'a, P>],
-    lifetime: &'a Lifetime,
-) -> impl Iterator<Item = &'a FieldInfo<'a, P>> + 'a {
-    /// Does `ty` use any lifetime from `lifetimes`?
-    fn does_type_use_lifetime_from_set<P: TyPosition>(ty: &Type<P>, lifetime: &Lifetime) -> bool {
-        ty.lifetimes().any(|lt| {
-            let MaybeStatic::NonStatic(lt) = lt else {
-                panic!("'static not supported in Dart");
-            };
-            lt == *lifetime
-        })
-    }
-
-    fields
-        .iter()
-        .filter(move |f| does_type_use_lifetime_from_set(f.ty, lifetime))
+/// The helpers reading and writing the C view of a slice, see [`TyGenContext::gen_slice_view()`]
+struct SliceView {
+    /// The type the view points to
+    view_ty: String,
+    /// The function copying the view into a Koka value
+    read: String,
+    /// Expression copying the Koka value into C memory
+    alloc: String,
+    /// Expression for the number of elements of the Koka value in C memory
+    length: String,
 }
 
-fn iter_def_lifetimes_matching_use_lt<'a>(
-    use_lt: &'a Lifetime,
-    info: &'a StructBorrowInfo,
-) -> impl Iterator<Item = Lifetime> + 'a {
-    info.borrowed_struct_lifetime_map
-        .iter()
-        .filter(|(_def_lt, use_lts)| use_lts.contains(use_lt))
-        .map(|(def_lt, _use_lts)| def_lt)
-        .copied()
+/// How a struct field is read from and written to the C struct, see [`TyGenContext::gen_field_access()`]
+struct FieldAccess {
+    /// The type the getter returns
+    ffi_type: String,
+    /// Whether the getter returns a pointer to the field rather than its value, for the
+    /// fields that are C structs themselves
+    by_address: bool,
+    /// Conversion reading the field from a C struct pointer `c`
+    c_to_koka: String,
+    /// The parameters of the setter after the C struct pointer
+    setter_params: String,
+    /// The C statement of the setter, writing its parameters into the field
+    setter_c: String,
+    /// Call of the setter writing the field of the Koka value `s` into the C struct `c`,
+    /// allocating the data it refers to in the batch `b`
+    koka_to_c: String,
 }
 
-/// Context about a struct being borrowed when doing dart-to-c conversions
-struct StructBorrowContext<'tcx> {
-    /// Is this in a method or struct?
-    ///
-    /// Methods generate things like `[aEdges, bEdges]`
-    /// whereas structs do `[...aAppendArray, ...bAppendArray]`
-    is_method: bool,
-    use_env: &'tcx LifetimeEnv,
-    param_info: StructBorrowInfo<'tcx>,
+// Helpers used in templates (Askama has restrictions on Rust syntax)
+
+fn display_lifetime_edge<'a>(edge: &'a LifetimeEdge, kept_batches: &[String]) -> Cow<'a, str> {
+    let param_name = &edge.param_name;
+    match edge.kind {
+        // Structs whose slice fields are borrowed also keep the batch they were written into
+        LifetimeEdgeKind::StructLifetime(..) if kept_batches.contains(param_name) => {
+            format!("edge({param_name}), edge({param_name}-batch)").into()
+        }
+        // Opaque parameters keep their Rust object alive, and slice parameters the view Rust
+        // reads. Structs are retained whole, along with the opaques in their fields.
+        LifetimeEdgeKind::OpaqueParam
+        | LifetimeEdgeKind::SliceParam
+        | LifetimeEdgeKind::StructLifetime(..) => format!("edge({param_name})").into(),
+        _ => unreachable!("Unknown lifetime edge kind {:?}", edge.kind),
+    }
 }
 
 #[derive(Default)]
 struct SpecialMethodGenInfo<'a> {
     /// Whether it is a comparator
     comparator: bool,
+    /// The stringifier `show` calls, if it has one
+    stringifier: Option<String>,
+    /// The hash function `hash` calls, if it has one
+    hash: Option<String>,
     /// Whether it is an iterator, and the type it iterates over
     iterator: Option<Cow<'a, str>>,
     /// Whether it is an iterable, and the type it iterates over
     iterable: Option<Cow<'a, str>>,
 }
+
+impl SpecialMethodGenInfo<'_> {
+    /// The functions generated for the special methods, see `iterators.kk.jinja`,
+    /// `show.kk.jinja` and `hash.kk.jinja`
+    fn adapter_names(&self) -> Vec<(&'static str, &'static str)> {
+        let mut names = Vec::new();
+        if self.iterator.is_some() || self.iterable.is_some() {
+            names.push(("foreach", "the `foreach` adapter"));
+            names.push(("list", "the `list` adapter"));
+        }
+        if self.stringifier.is_some() {
+            names.push(("show", "the `show` function"));
+        }
+        if self.hash.is_some() {
+            names.push(("hash", "the `hash` function"));
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use diplomat_core::{ast, hir};
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{run, KokaAttributeValidator, KokaConfig, PointerWidth, StripPrefix};
+
+    /// Generates Koka bindings for a bridge, with the given library config
+    fn gen_files(tk_stream: TokenStream, conf: Option<&str>) -> HashMap<String, String> {
+        gen_files_with(tk_stream, conf, None, None, false)
+    }
+
+    fn gen_files_with(
+        tk_stream: TokenStream,
+        conf: Option<&str>,
+        profile: Option<&str>,
+        pointer_width: Option<PointerWidth>,
+        emit_c_headers: bool,
+    ) -> HashMap<String, String> {
+        let (files, errors) = gen_output(
+            tk_stream,
+            conf,
+            profile,
+            pointer_width,
+            emit_c_headers,
+            Default::default(),
+        );
+        if !errors.is_empty() {
+            panic!("Failed to generate bindings: {errors:?}");
+        }
+        files
+    }
+
+    /// The files generated along with the errors found, formatted as `context: error`
+    fn gen_output(
+        tk_stream: TokenStream,
+        conf: Option<&str>,
+        profile: Option<&str>,
+        pointer_width: Option<PointerWidth>,
+        emit_c_headers: bool,
+        strip_prefix: StripPrefix,
+    ) -> (HashMap<String, String>, Vec<String>) {
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item");
+        let diplomat_file = ast::File::from(&item);
+        let env = diplomat_file.all_types();
+        let tcx = hir::TypeContext::from_ast(&env, KokaAttributeValidator::new())
+            .unwrap_or_else(|_| panic!("Failed to create context"));
+
+        let conf_path = conf.map(|conf| {
+            // Tests run in parallel, every config needs a file of its own
+            static CONFIGS: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "koka-test-{}-{}.toml",
+                std::process::id(),
+                CONFIGS.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::write(&path, conf).unwrap();
+            path
+        });
+        let docs_url_generator = Default::default();
+        let mut config = match conf_path {
+            Some(ref path) => KokaConfig::read(path, profile),
+            None => KokaConfig::default(),
+        };
+        config.pointer_width = pointer_width;
+        config.emit_c_headers = emit_c_headers;
+        let (mut files, errors) = run(&tcx, config, &docs_url_generator, strip_prefix);
+        if let Some(path) = conf_path {
+            std::fs::remove_file(path).unwrap();
+        }
+        let errors = errors
+            .into_iter()
+            .map(|(context, error)| format!("{context}: {error}"))
+            .collect();
+        (files.take_files(), errors)
+    }
+
+    #[test]
+    fn test_callback_param() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    pub fn apply(&self, f: impl Fn(u32, bool) -> i64) -> i64 {
+                        f(self.0, true)
+                    }
+
+                    pub fn visit(f: impl Fn(f64)) {
+                        f(1.0)
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        // The signatures are spelled with the alias of their arity
+        let lib = &files["lib.kk"];
+        assert!(lib.contains("pub alias callback1<a0, r> = (a0) -> io-noexn r"));
+        assert!(lib.contains("pub alias callback2<a0, a1, r> = (a0, a1) -> io-noexn r"));
+        insta::assert_snapshot!(files.remove("Counter.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Counter-callbacks.c").unwrap());
+    }
+
+    #[test]
+    fn test_trait_param() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                /// Provides values to a `Counter`.
+                pub trait Provider {
+                    fn get(&self, key: u32) -> f64;
+                    /// Called after every change.
+                    fn notify(&self, changed: bool);
+                }
+
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    pub fn sum(&self, provider: &dyn Provider, count: u32) -> f64 {
+                        (0..count).map(|i| provider.get(i)).sum()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Provider.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Provider-trait.c").unwrap());
+        insta::assert_snapshot!(files.remove("Counter.kk").unwrap());
+    }
+
+    #[test]
+    fn test_utf16() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                use diplomat_runtime::DiplomatStr16;
+
+                #[diplomat::opaque]
+                struct Text(Vec<u16>);
+
+                impl Text {
+                    pub fn new(units: &DiplomatStr16) -> Box<Text> {
+                        Box::new(Text(units.to_vec()))
+                    }
+
+                    pub fn from_owned(units: Box<DiplomatStr16>) -> Box<Text> {
+                        Box::new(Text(units.into()))
+                    }
+
+                    pub fn units<'a>(&'a self) -> &'a DiplomatStr16 {
+                        &self.0
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        assert!(files["lib.kk"].contains("c file \"diplomat_utf16.c\""));
+        assert!(files.contains_key("diplomat_utf16.c"));
+        insta::assert_snapshot!(files.remove("Text.kk").unwrap());
+    }
+
+    #[test]
+    fn test_iterator() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Bytes(Vec<u8>);
+
+                #[diplomat::opaque]
+                struct BytesIterator<'a>(std::slice::Iter<'a, u8>);
+
+                impl Bytes {
+                    #[diplomat::attr(*, iterable)]
+                    pub fn iter<'a>(&'a self) -> Box<BytesIterator<'a>> {
+                        Box::new(BytesIterator(self.0.iter()))
+                    }
+                }
+
+                impl<'a> BytesIterator<'a> {
+                    #[diplomat::attr(*, iterator)]
+                    pub fn next(&mut self) -> Option<u8> {
+                        self.0.next().copied()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Bytes.kk").unwrap());
+        insta::assert_snapshot!(files.remove("BytesIterator.kk").unwrap());
+    }
+
+    #[test]
+    fn test_async_method() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Client(u32);
+
+                impl Client {
+                    pub async fn fetch(&self, key: u32) -> f64 {
+                        key as f64
+                    }
+
+                    pub async fn flush(&self) {}
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Client.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Client-callbacks.c").unwrap());
+    }
+
+    #[test]
+    fn test_fbip_struct_layout() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                pub struct Sample<'a> {
+                    count: u32,
+                    counter: &'a Counter,
+                    ratio: f64,
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(files.remove("Sample.kk").unwrap());
+    }
+
+    #[test]
+    fn test_bundled_params() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct File;
+
+                pub enum Mode {
+                    Read,
+                    Write,
+                }
+
+                impl File {
+                    #[diplomat::bundle(OpenOptions, mode, create)]
+                    pub fn open(mode: Mode, path: &DiplomatStr, create: bool) -> Box<File> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(files.remove("OpenOptions.kk").unwrap());
+        insta::assert_snapshot!(files.remove("File.kk").unwrap());
+    }
+
+    #[test]
+    fn test_struct_returns() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Point {
+                    x: f64,
+                    y: f64,
+                }
+
+                #[diplomat::out]
+                pub struct Bounds<'a> {
+                    min: Point,
+                    max: Point,
+                    shape: &'a Shape,
+                }
+
+                #[diplomat::opaque]
+                struct Shape(Vec<Point>);
+
+                impl Shape {
+                    pub fn center(&self) -> Point {
+                        todo!()
+                    }
+
+                    pub fn bounds<'a>(&'a self) -> Bounds<'a> {
+                        todo!()
+                    }
+                }
+
+                impl Point {
+                    pub fn scaled(self, factor: f64) -> Point {
+                        todo!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        // `Shape` is generated along with `Bounds`, the type borrowing from it
+        let shape = files.remove("Bounds.kk").unwrap();
+        assert!(shape.contains("val result = point/alloc-c(temp)"));
+        assert!(shape.contains("\"*(Point*)#2 = Shape_center("));
+        assert!(shape.contains("result : c-pointer<Point>) : io-noexn ()"));
+        assert!(shape.contains("point/from-c(result)"));
+        assert!(shape.contains("val result = bounds/alloc-c(temp)"));
+        insta::assert_snapshot!(files.remove("Point.kk").unwrap());
+    }
+
+    #[test]
+    fn test_nested_structs() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                pub struct Inner<'a> {
+                    a: u8,
+                    counter: &'a Counter,
+                    data: &'a [u8],
+                }
+
+                pub struct Middle<'a> {
+                    inner: Inner<'a>,
+                    c: i32,
+                }
+
+                pub struct Outer<'a, 'b> {
+                    middle: Middle<'a>,
+                    inner: Inner<'b>,
+                    d: bool,
+                }
+
+                impl Counter {
+                    pub fn make<'a>(&'a self) -> Outer<'a, 'a> {
+                        todo!()
+                    }
+
+                    pub fn take<'a, 'b>(&self, o: Outer<'a, 'b>) {
+                        todo!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        // The structs borrowing from `Counter` are generated along with it
+        let structs = files.remove("Inner.kk").unwrap();
+        assert!(structs.contains(
+            "Outer(middle/from-c(external/middle(c), a-edges), inner/from-c(external/inner(c), b-edges), external/d(c))"
+        ));
+        assert!(structs.contains("Middle(inner/from-c(external/inner(c), a-edges), external/c(c))"));
+        assert!(structs.contains("middle/write-c(s.middle, external/middle(c), b)"));
+        assert!(structs.contains("inner/write-c(s.inner, external/inner(c), b)"));
+        insta::assert_snapshot!(structs);
+    }
+
+    #[test]
+    fn test_pointer_sized_ints() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Span {
+                    start: usize,
+                    offset: isize,
+                }
+
+                #[diplomat::opaque]
+                struct Buffer(Vec<u8>);
+
+                impl Buffer {
+                    pub fn len(&self) -> usize {
+                        self.0.len()
+                    }
+
+                    pub fn get(&self, index: usize, offset: isize) -> u8 {
+                        self.0[(index as isize + offset) as usize]
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(files.remove("Span.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Buffer.kk").unwrap());
+    }
+
+    #[test]
+    fn test_options() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                use diplomat_runtime::DiplomatOption;
+
+                pub enum LengthUnit {
+                    Meter,
+                    Foot,
+                }
+
+                pub struct Inner {
+                    x: u32,
+                }
+
+                pub struct Measure {
+                    value: DiplomatOption<u32>,
+                    unit: DiplomatOption<LengthUnit>,
+                    inner: DiplomatOption<Inner>,
+                }
+
+                #[diplomat::opaque]
+                struct Ruler(u32);
+
+                impl Ruler {
+                    pub fn set(&mut self, len: Option<u32>, exact: Option<bool>, unit: Option<LengthUnit>, inner: Option<Inner>) {
+                        todo!()
+                    }
+
+                    pub fn len(&self) -> Option<u32> {
+                        todo!()
+                    }
+
+                    pub fn unit(&self) -> Option<LengthUnit> {
+                        todo!()
+                    }
+
+                    pub fn inner(&self) -> Option<Inner> {
+                        todo!()
+                    }
+
+                    pub fn measure(&self, m: Measure) -> Measure {
+                        todo!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files_with(
+            tk_stream,
+            Some("module-layout = \"file-per-type\""),
+            None,
+            None,
+            true,
+        );
+        let lib = files.remove("lib.kk").unwrap();
+        let option_helper = |name: &str| {
+            let start = lib
+                .find(&format!("// View of the C `{name}` struct"))
+                .unwrap();
+            let end = lib[start..].find("\n  o\n").unwrap() + 5;
+            lib[start..start + end].to_owned()
+        };
+        insta::assert_snapshot!(option_helper("diplomat_option_uint32_t"));
+        insta::assert_snapshot!(option_helper("diplomat_option_Inner"));
+        assert!(lib.contains("pub extern diplomat_option_bool/set-ok"));
+        assert!(lib.contains("pub extern diplomat_option_length_unit/set-ok"));
+        insta::assert_snapshot!(files.remove("Measure.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Ruler.kk").unwrap());
+        insta::assert_snapshot!(files.remove("include/diplomat_option_Inner.d.h").unwrap());
+    }
+
+    #[test]
+    fn test_char_params() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Glyphs(Vec<DiplomatChar>);
+
+                impl Glyphs {
+                    pub fn push(&mut self, ch: DiplomatChar) {
+                        self.0.push(ch)
+                    }
+
+                    pub fn first(&self) -> DiplomatChar {
+                        self.0[0]
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream.clone(), None);
+        let lib = &files["lib.kk"];
+        assert!(lib.contains(
+            "// Throws `ExnRange` if a `char` is not a Unicode scalar value.\n\
+             pub fun push(self : Glyphs, ch : char) : io ()\n  \
+             glyphs_push(self.raw, char/to-ffi(ch))"
+        ));
+        assert!(lib.contains("pub fun first(self : Glyphs) : io-noexn char\n"));
+        assert!(lib.contains("pub fun char/to-ffi(c : char) : exn char"));
+
+        let files = gen_files(tk_stream, Some("checks = \"minimal\""));
+        assert!(files["lib.kk"].contains(
+            "pub fun push(self : Glyphs, ch : char) : io-noexn ()\n  glyphs_push(self.raw, ch)"
+        ));
+    }
+
+    #[test]
+    fn test_mut_slice_param() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Shuffler(u64);
+
+                impl Shuffler {
+                    pub fn shuffle(&self, bytes: &mut [u8], weights: &mut [f64]) -> usize {
+                        bytes.reverse();
+                        weights.reverse();
+                        bytes.len()
+                    }
+
+                    pub fn fill(&self, indices: &mut [usize]) {
+                        indices.fill(0);
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        let lib = files.remove("lib.kk").unwrap();
+        assert!(lib.contains("pub fun slice-uint8/alloc-in(v : vector<int>, b : batch)"));
+        assert!(lib.contains(
+            "pub fun slice-double/read(data : c-pointer<()>, n : int) : io-noexn vector<float64>"
+        ));
+        assert!(lib.contains("slice-size/set(data, i.ssize_t, usize/truncate(x))"));
+        assert!(lib.contains("pub alias mut-slice<a> = ref<global, vector<a>>"));
+        insta::assert_snapshot!(files.remove("Shuffler.kk").unwrap());
+    }
+
+    #[test]
+    fn test_bool_slices() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Flags(Vec<bool>);
+
+                impl Flags {
+                    pub fn new(bits: &[bool]) -> Box<Flags> {
+                        Box::new(Flags(bits.to_vec()))
+                    }
+
+                    pub fn bits<'a>(&'a self) -> &'a [bool] {
+                        &self.0
+                    }
+
+                    pub fn toggle(&self, bits: &mut [bool]) {
+                        bits.iter_mut().for_each(|b| *b = !*b);
+                    }
+
+                    pub fn checked(&self) -> Result<Box<[bool]>, ()> {
+                        Ok(self.0.clone().into())
+                    }
+
+                    pub fn first_set<'a>(&'a self) -> Option<&'a [bool]> {
+                        self.0.iter().any(|b| *b).then(|| &self.0[..])
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream, None);
+        let lib = &files["lib.kk"];
+        // One byte per `bool`, holding 0 or 1
+        assert!(lib.contains(
+            "extern slice-bool/set(data : c-pointer<()>, i : ssize_t, x : int8) : io-noexn ()\n  \
+             c inline \"((uint8_t*)#1)[#2] = (uint8_t)#3\""
+        ));
+        assert!(lib.contains("slice-bool/set(data, i.ssize_t, if x then 1.int8 else 0.int8)"));
+        assert!(lib.contains("    val x = slice-bool/get(data, i.ssize_t)\n    x.int != 0"));
+        assert!(lib.contains("pub fun flags/new_(bits : slice-bool-view) : io-noexn Flags"));
+        assert!(lib.contains("pub fun bits(self : Flags) : io-noexn vector<bool>"));
+        assert!(lib.contains("bits := slice-bool/read(bits-data, bits-len)"));
+        assert!(lib.contains(
+            "pub fun checked(self : Flags) : io vector<bool>\n  \
+             with temp <- with-batch\n  \
+             val result = diplomat_result_ref_prim_slice_bool_void/alloc(temp.raw)"
+        ));
+        assert!(
+            lib.contains("slice-bool/take(diplomat_result_ref_prim_slice_bool_void/ok(result))")
+        );
+        assert!(lib.contains(
+            "pub fun first_set(self : Flags) : io-noexn maybe<vector<bool>>\n  \
+             with temp <- with-batch"
+        ));
+        assert!(lib.contains(
+            "Just(slice-bool/copy(diplomat_result_ref_const_prim_slice_bool_void/ok(result)))"
+        ));
+        assert!(!lib.contains("_SliceBool"));
+    }
+
+    #[test]
+    fn test_string_params() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Interner;
+
+                #[diplomat::opaque]
+                struct Symbol<'a>(&'a str);
+
+                impl Interner {
+                    pub fn contains(&self, name: &str) -> bool {
+                        unimplemented!()
+                    }
+
+                    pub fn insert(&mut self, name: Box<str>) {
+                        unimplemented!()
+                    }
+
+                    pub fn symbol<'a>(&self, name: &'a str) -> Box<Symbol<'a>> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream.clone(), None);
+        let lib = &files["lib.kk"];
+        // Read in place, without a copy
+        assert!(lib.contains(
+            "extern interner_contains(^self : rust-object, ^name-data : string, name-len : ssize_t) : io-noexn bool\n  \
+             c inline \"Interner_contains(kk_cptr_raw_unbox_borrowed(#1, kk_context()), \
+             (const char*)kk_string_buf_borrow(#2, NULL, kk_context()), #3)\""
+        ));
+        assert!(lib.contains("interner_contains(self.raw, name, name.utf8-length)"));
+        assert!(lib.contains("interner_insert(self.raw, name.utf8-alloc-owned, name.utf8-length)"));
+        // Copied into a view the symbol keeps alive
+        assert!(lib.contains("  val name-view = name.bytes\n"));
+        assert!(lib.contains("val a-edges : list<any> = [edge(name-view)]"));
+        assert!(lib.contains("interner_symbol(self.raw, name-view.raw, name-view.length.ssize_t)"));
+
+        let files = gen_files(tk_stream, Some("copy-strings = true"));
+        let lib = &files["lib.kk"];
+        assert!(lib.contains(
+            "interner_contains(self.raw, name.utf8-alloc-in(temp.raw), name.utf8-length)"
+        ));
+    }
+
+    #[test]
+    fn test_byte_slices() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Hasher(Vec<u8>);
+
+                impl Hasher {
+                    pub fn new(key: &[u8]) -> Box<Hasher> {
+                        Box::new(Hasher(key.to_vec()))
+                    }
+
+                    pub fn update(&mut self, data: Box<[u8]>) {
+                        self.0.extend(data.iter());
+                    }
+
+                    pub fn key<'a>(&'a self) -> &'a [u8] {
+                        &self.0
+                    }
+
+                    pub fn digest(&self) -> Result<Box<[u8]>, ()> {
+                        Ok(self.0.clone().into())
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream, None);
+        let lib = &files["lib.kk"];
+        assert!(lib.contains("pub fun hasher/new_(key : bytes) : io-noexn Hasher"));
+        assert!(lib.contains("pub fun update(self : Hasher, data : bytes) : io-noexn ()"));
+        assert!(lib.contains("data.alloc-owned"));
+        assert!(lib.contains("pub fun key(self : Hasher) : io-noexn bytes"));
+        assert!(lib.contains("bytes/copy(result)"));
+        assert!(lib.contains("pub fun digest(self : Hasher) : io bytes"));
+        assert!(lib.contains("bytes/take(diplomat_result_ref_prim_slice_uint8_t_void/ok(result))"));
+        assert!(lib.contains("pub fun list/bytes(xs : list<int8>) : bytes"));
+        assert!(lib.contains("c file \"diplomat_bytes.c\""));
+        assert!(files.contains_key("diplomat_bytes.c"));
+    }
+
+    #[test]
+    fn test_slice_views() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Histogram(Vec<u32>);
+
+                impl Histogram {
+                    pub fn new(bins: Box<[u32]>) -> Box<Histogram> {
+                        Box::new(Histogram(bins.into()))
+                    }
+
+                    pub fn add(&mut self, samples: &[f64], weights: &[u32]) {
+                        let _ = (samples, weights);
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        let lib = files.remove("lib.kk").unwrap();
+        assert!(lib.contains("pub value struct slice-double-view"));
+        assert!(
+            lib.contains("pub fun slice-uint32/view(v : vector<int>) : io-noexn slice-uint32-view")
+        );
+        assert!(lib.contains("kk_cptr_raw_box(&kk_free_fun, kk_malloc("));
+        insta::assert_snapshot!(files.remove("Histogram.kk").unwrap());
+    }
+
+    #[test]
+    fn test_lifetime_edges() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Dictionary(Vec<String>);
+
+                #[diplomat::opaque]
+                struct Entry<'a>(&'a str);
+
+                pub struct Lookup<'a> {
+                    entry: &'a Entry<'a>,
+                    score: f64,
+                }
+
+                impl Dictionary {
+                    pub fn first<'a>(&'a self) -> Box<Entry<'a>> {
+                        Box::new(Entry(&self.0[0]))
+                    }
+
+                    pub fn find<'a>(&'a self, weights: &'a [f64]) -> Option<Lookup<'a>> {
+                        let _ = weights;
+                        None
+                    }
+                }
+
+                impl<'a> Entry<'a> {
+                    pub fn either<'b: 'a>(&'a self, other: &'b Entry<'b>) -> &'a Entry<'a> {
+                        let _ = other;
+                        self
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        let lookup = files.remove("Lookup.kk").unwrap();
+        assert!(lookup.contains(
+            "pub fun lookup/from-c(c : c-pointer<Lookup>, a-edges : list<any>) : io-noexn Lookup"
+        ));
+        assert!(lookup.contains("entry/from-ffi(external/entry(c), a-edges, a-edges)"));
+        insta::assert_snapshot!(files.remove("Dictionary.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Entry.kk").unwrap());
+    }
+
+    #[test]
+    fn test_borrowed_struct_slices() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Token<'a>(&'a str);
+
+                pub struct Source<'a> {
+                    text: &'a DiplomatStr,
+                    line: u32,
+                }
+
+                impl<'a> Token<'a> {
+                    pub fn first(source: Source<'a>) -> Box<Token<'a>> {
+                        unimplemented!()
+                    }
+
+                    pub fn count(source: Source) -> u32 {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream, None);
+        let lib = &files["lib.kk"];
+        // The slices the result borrows are written into a batch it keeps alive
+        assert!(lib.contains(
+            "  val source-batch = kept-batch/begin()\n  \
+             // This lifetime edge depends on lifetimes: 'a\n  \
+             val a-edges : list<any> = [edge(source), edge(source-batch)]\n  \
+             val result = token_first(source/to-c(source, source-batch.scope))"
+        ));
+        assert!(lib.contains(
+            "  with temp <- with-batch\n  \
+             val result = token_count(source/to-c(source, temp))"
+        ));
+        assert!(lib.contains("c file \"diplomat_kept_batch.c\""));
+        assert!(files.contains_key("diplomat_kept_batch.c"));
+    }
+
+    #[test]
+    fn test_consuming_method() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Builder(Vec<u8>);
+
+                #[diplomat::opaque]
+                struct Bytes(Vec<u8>);
+
+                impl Builder {
+                    pub fn push(&mut self, byte: u8) {
+                        self.0.push(byte);
+                    }
+
+                    pub fn build(self: Box<Self>) -> Box<Bytes> {
+                        Box::new(Bytes(self.0))
+                    }
+                }
+            }
+        };
+        let mut files = gen_files_with(
+            tk_stream.clone(),
+            Some("module-layout = \"file-per-type\""),
+            None,
+            None,
+            true,
+        );
+        let header = files.remove("include/Builder.h").unwrap();
+        assert!(header.contains("Bytes* Builder_build(Builder* self);"));
+        insta::assert_snapshot!(files.remove("Builder.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Builder-finalizer.c").unwrap());
+        // Types no method consumes are unboxed without checks
+        let bytes = files.remove("Bytes-finalizer.c").unwrap();
+        assert!(!bytes.contains("Bytes_take"));
+
+        let mut files = gen_files(tk_stream, Some("destruction = \"manual\""));
+        assert!(files
+            .remove("lib.kk")
+            .unwrap()
+            .contains("// Consumes `self`: it must neither be used nor freed afterwards"));
+    }
+
+    #[test]
+    fn test_comparators() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Version(u32);
+
+                impl Version {
+                    #[diplomat::attr(*, comparison)]
+                    pub fn compare(&self, other: &Version) -> core::cmp::Ordering {
+                        self.0.cmp(&other.0)
+                    }
+                }
+
+                pub struct Point {
+                    x: i32,
+                    y: i32,
+                }
+
+                impl Point {
+                    #[diplomat::attr(*, comparison)]
+                    pub fn compare(self, other: Point) -> core::cmp::Ordering {
+                        (self.x, self.y).cmp(&(other.x, other.y))
+                    }
+                }
+
+                pub enum Level {
+                    Low,
+                    High,
+                }
+
+                impl Level {
+                    #[diplomat::attr(*, comparison)]
+                    pub fn compare(self, other: Level) -> core::cmp::Ordering {
+                        (self as u8).cmp(&(other as u8))
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("module-layout = \"file-per-type\"\nstruct-layout = \"fbip\""),
+        );
+        insta::assert_snapshot!(files.remove("Version.kk").unwrap());
+        for file in ["Point.kk", "Level.kk"] {
+            let bindings = files.remove(file).unwrap();
+            assert!(bindings.contains("this.cmp(other) == Lt"));
+            assert!(bindings.contains("if result < 0 then Lt elif result > 0 then Gt else Eq"));
+        }
+    }
+
+    #[test]
+    fn test_stringifiers() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                use diplomat_runtime::DiplomatWriteable;
+
+                #[diplomat::opaque]
+                struct Locale(u32);
+
+                impl Locale {
+                    #[diplomat::attr(*, stringifier)]
+                    pub fn to_string(&self, out: &mut DiplomatWriteable) {
+                        unimplemented!()
+                    }
+                }
+
+                pub struct Date {
+                    day: u8,
+                }
+
+                impl Date {
+                    #[diplomat::attr(*, stringifier)]
+                    pub fn format(self, out: &mut DiplomatWriteable) -> Result<(), ()> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Locale.kk").unwrap());
+        // `show` has to be total, a fallible stringifier is only a method
+        let date = files.remove("Date.kk").unwrap();
+        assert!(date.contains("pub fun format("));
+        assert!(!date.contains("pub fun show("));
+    }
+
+    #[test]
+    fn test_hash() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Locale(u32);
+
+                impl Locale {
+                    #[diplomat::attr(*, hash)]
+                    pub fn hash_value(&self) -> u64 {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        let locale = files.remove("Locale.kk").unwrap();
+        assert!(locale
+            .contains("pub fun hash(this : Locale) : int\n  unsafe-total { this.hash_value() }"));
+        assert!(locale.contains("pub fun hash_value(self : Locale) : io-noexn int"));
+    }
+
+    #[test]
+    fn test_enum_discriminants() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub enum Level {
+                    Low = -1,
+                    Mid = 5,
+                    High = 10,
+                }
+
+                impl Level {
+                    pub fn next(self) -> Level {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Level.kk").unwrap());
+    }
+
+    #[test]
+    fn test_non_exhaustive_enum() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[non_exhaustive]
+                pub enum Level {
+                    Low = -1,
+                    Mid = 5,
+                    High = 10,
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Level.kk").unwrap());
+    }
+
+    #[test]
+    fn test_non_exhaustive_enum_unknown_clash() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[non_exhaustive]
+                pub enum Level {
+                    Unknown,
+                    Known,
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Level.kk").unwrap());
+    }
+
+    #[test]
+    fn test_enum_variant_collisions() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub enum Answer {
+                    Yes,
+                    #[diplomat::attr(koka, rename = "Yes")]
+                    Sure,
+                    Yes2,
+                    #[diplomat::attr(koka, rename = "Just")]
+                    Perhaps,
+                    No,
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream.clone()).expect("failed to parse item");
+        let diplomat_file = ast::File::from(&item);
+        let env = diplomat_file.all_types();
+        let tcx = hir::TypeContext::from_ast(&env, KokaAttributeValidator::new())
+            .unwrap_or_else(|_| panic!("Failed to create context"));
+        assert_eq!(
+            super::check_enum_variant_names(&tcx),
+            [
+                "Variant `Answer::Sure` is generated as the Koka constructor `Yes1`, as `Yes` is \
+                 already the name of variant `Yes`",
+                "Variant `Answer::Perhaps` is generated as the Koka constructor `Just3`, as `Just` \
+                 is a constructor of the Koka standard library",
+            ]
+        );
+
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Answer.kk").unwrap());
+    }
+
+    #[test]
+    fn test_naming() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct LineMetrics {
+                    line_height: f64,
+                    glyph_count_2: u32,
+                }
+
+                #[diplomat::opaque]
+                struct TextShaper;
+
+                impl TextShaper {
+                    #[diplomat::attr(koka, constructor)]
+                    pub fn new() -> Box<TextShaper> {
+                        unimplemented!()
+                    }
+
+                    pub fn measure_line(&self, font_size: f64) -> LineMetrics {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream.clone(),
+            Some("naming = \"kebab\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(files.remove("Text-shaper.kk").unwrap());
+
+        let files = gen_files(
+            tk_stream,
+            Some("naming = \"camel\"\nmodule-layout = \"file-per-type\""),
+        );
+        let shaper = &files["TextShaper.kk"];
+        assert!(shaper.contains("pub fun measureLine(self : TextShaper, fontSize : float64)"));
+        assert!(files["LineMetrics.kk"].contains("lineHeight"));
+    }
+
+    #[test]
+    fn test_doc_references() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                /// A bucket, see [`Level`], [`Level::High`] and [`Bucket::level`].
+                ///
+                /// Built by [`crate::ffi::Bucket::with_capacity`]; [`Vec`] is not ours.
+                #[diplomat::rust_link(std::vec::Vec, Struct)]
+                pub struct Bucket {
+                    /// Mirrors [`Bucket::level`]
+                    level: Level,
+                }
+
+                pub enum Level {
+                    Low,
+                    High,
+                }
+
+                impl Bucket {
+                    #[diplomat::attr(koka, named_constructor)]
+                    pub fn with_capacity(capacity: u32) -> Bucket {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Bucket.kk").unwrap());
+    }
+
+    #[test]
+    fn test_named_constructors() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Decimal(i64);
+
+                impl Decimal {
+                    #[diplomat::attr(koka, named_constructor)]
+                    pub fn from_int(value: i64) -> Box<Decimal> {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(koka, named_constructor = "FromParts")]
+                    pub fn new_from_parts(int: i64, frac: u32) -> Box<Decimal> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream.clone(), Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Decimal.kk").unwrap());
+
+        // Koka reserves capitalized names for types and data constructors
+        let files = gen_files(tk_stream, Some("naming = \"kebab\""));
+        assert!(
+            files["lib.kk"].contains("pub fun decimal/from-int(value : int) : io-noexn Decimal")
+        );
+        assert!(files["lib.kk"].contains("pub fun decimal/from-parts("));
+    }
+
+    /// The doc comments of a generated file, where the links are
+    fn doc_comments(file: &str) -> String {
+        file.lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("//") && line != &"// generated by diplomat-tool")
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_doc_references_strip_prefix() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                /// Formats a [`ICU4XDecimal`] as configured by [`ICU4XGrouping::Min2`], see
+                /// [`ICU4XFormatter::format`] and [`ICU4XFormatter::try_new`].
+                #[diplomat::opaque]
+                struct ICU4XFormatter;
+
+                /// Read by [`ICU4XFormatter::format`], see also [`ICU4XDecimal::digits`].
+                pub struct ICU4XDecimal {
+                    /// Every digit, rendered by [`ICU4XFormatter`]
+                    digits: u32,
+                }
+
+                pub enum ICU4XGrouping {
+                    /// Unlike [`ICU4XGrouping::Min2`]
+                    Always,
+                    Min2,
+                }
+
+                impl ICU4XFormatter {
+                    /// Takes a [`ICU4XGrouping`]
+                    #[diplomat::attr(koka, named_constructor)]
+                    pub fn try_new(grouping: ICU4XGrouping) -> Box<ICU4XFormatter> {
+                        unimplemented!()
+                    }
+
+                    /// Writes [`ICU4XDecimal::digits`] of a [`crate::ffi::ICU4XDecimal`]
+                    pub fn format(&self, decimal: ICU4XDecimal) -> u32 {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let (files, errors) = gen_output(
+            tk_stream,
+            Some("module-layout = \"file-per-type\""),
+            None,
+            None,
+            false,
+            StripPrefix::new("ICU4X"),
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        insta::assert_snapshot!(["Formatter.kk", "Decimal.kk", "Grouping.kk"]
+            .map(|file| format!("{file}:\n{}", doc_comments(&files[file])))
+            .join("\n\n"));
+    }
+
+    #[test]
+    fn test_doc_references_renamed() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                /// Filled by [`Bucket::fill`] up to [`Level::High`], the [`Bucket::level`] of
+                /// every [`Bucket`] made by [`Bucket::with_level`]
+                #[diplomat::attr(koka, rename = "Pail")]
+                pub struct Bucket {
+                    level: Level,
+                }
+
+                #[diplomat::attr(koka, rename = "Mark")]
+                pub enum Level {
+                    Low,
+                    #[diplomat::attr(koka, rename = "Brim")]
+                    High,
+                }
+
+                impl Bucket {
+                    /// Starts at [`Level::Low`], unlike [`Bucket::fill`]
+                    #[diplomat::attr(koka, rename = "top_up")]
+                    pub fn fill(self) -> Bucket {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(koka, named_constructor = "at")]
+                    pub fn with_level(level: Level) -> Bucket {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let files = gen_files(
+            tk_stream,
+            Some("naming = \"kebab\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(["Pail.kk", "Mark.kk"]
+            .map(|file| format!("{file}:\n{}", doc_comments(&files[file])))
+            .join("\n\n"));
+    }
+
+    #[test]
+    fn test_doc_references_namespaced() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                /// Created from a [`Locale`] by [`DecimalFormatter::create`], which
+                /// [`Locale::und`] gives a default for
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "icu4x::decimal")]
+                struct DecimalFormatter(u32);
+
+                /// Used by [`DecimalFormatter`], see [`icu4x::decimal::DecimalFormatter::create`]
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "icu4x::locale")]
+                struct Locale(u32);
+
+                impl Locale {
+                    pub fn und() -> Box<Locale> {
+                        unimplemented!()
+                    }
+                }
+
+                impl DecimalFormatter {
+                    /// Formats for the [`Locale`]
+                    pub fn create(locale: &Locale) -> Box<DecimalFormatter> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream, Some("module-prefix = \"my-\""));
+        insta::assert_snapshot!(["icu4x/my-decimal.kk", "icu4x/my-locale.kk"]
+            .map(|file| format!("{file}:\n{}", doc_comments(&files[file])))
+            .join("\n\n"));
+    }
+
+    #[test]
+    fn test_reproducible_output() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "shapes")]
+                struct Zebra;
+
+                pub struct Point {
+                    x: i32,
+                    y: i32,
+                }
+
+                pub enum Axis {
+                    X,
+                    Y,
+                }
+
+                impl Zebra {
+                    pub fn stripes(&self, axis: Axis) -> Point {
+                        unimplemented!()
+                    }
+
+                    pub fn new() -> Box<Zebra> {
+                        unimplemented!()
+                    }
+
+                    pub fn apply(&self, f: impl Fn(i32) -> i32) {
+                        unimplemented!()
+                    }
+                }
+
+                impl Point {
+                    pub fn along(self, axis: Axis) -> Result<i32, Axis> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let conf = "module-layout = \"file-per-type\"\nsmoke-tests = true";
+        // Every hash map gets new keys, so any output following their order would differ
+        let files = gen_files(tk_stream.clone(), Some(conf));
+        for _ in 0..4 {
+            assert!(gen_files(tk_stream.clone(), Some(conf)) == files);
+        }
+        // Methods come in declaration order
+        let zebra = &files["shapes/Zebra.kk"];
+        assert!(zebra.find("pub fun stripes") < zebra.find("pub fun zebra/new"));
+        // Modules are listed by name
+        assert!(files["diplomat.files"].starts_with("lib.kk\nAxis.kk\nPoint.kk\nshapes/Zebra.kk\n"));
+    }
+
+    #[test]
+    fn test_failing_type_left_out() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub enum Level {
+                    #[diplomat::attr(koka, rename = "unknown")]
+                    Unknown,
+                    Known,
+                }
+
+                pub enum Color {
+                    Red,
+                    Green,
+                }
+            }
+        };
+        let (files, errors) = gen_output(
+            tk_stream,
+            Some("module-layout = \"file-per-type\""),
+            None,
+            None,
+            false,
+            Default::default(),
+        );
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].starts_with("Level: "), "{errors:?}");
+        assert!(!files.contains_key("Level.kk"));
+        assert!(files.contains_key("Color.kk"));
+    }
+
+    #[test]
+    fn test_unsupported_constructs() {
+        // Parsed from text rather than `quote!`, which gives tokens no location
+        let tk_stream: TokenStream = r#"
+#[diplomat::bridge]
+mod ffi {
+    pub struct Wide {
+        pub value: u128,
+    }
+
+    #[diplomat::opaque]
+    struct Counter;
+
+    impl Counter {
+        pub fn add(&self, amount: i128) {}
+        pub fn label(&self) -> &'static str {
+            "counter"
+        }
+    }
+
+    #[diplomat::opaque]
+    struct Locale;
+}
+"#
+        .parse()
+        .unwrap();
+        let (files, errors) = gen_output(tk_stream, None, None, None, false, Default::default());
+        assert_eq!(
+            errors,
+            [
+                "Wide (line 4, column 16): Field `value` uses `u128`, 128-bit integers are not supported in Koka",
+                "Counter::add (line 12, column 16): Parameter `amount` uses `i128`, 128-bit integers are not supported in Koka",
+                "Counter::label (line 13, column 16): The return type borrows for `'static`, which is not supported in Koka",
+            ]
+        );
+        let lib = &files["lib.kk"];
+        assert!(lib.contains("Locale"));
+        assert!(!lib.contains("Counter") && !lib.contains("Wide"));
+    }
+
+    #[test]
+    fn test_rejected_attrs() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter;
+
+                impl Counter {
+                    #[diplomat::attr(any(kotlin, kokaa), disable)]
+                    pub fn reset(&mut self) {}
+                    #[diplomat::attr(not(platform = "wasm"), rename = "total")]
+                    pub fn sum(&self) -> u32 {
+                        0
+                    }
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item");
+        let env = ast::File::from(&item).all_types();
+        let errors = hir::TypeContext::from_ast(&env, KokaAttributeValidator::new())
+            .expect_err("attributes should have been rejected");
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|(ctx, err)| format!("{ctx}: {err}"))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "Counter::reset: Unknown backend `kokaa` in `#[diplomat::attr]`, expected one of c, c2, cpp, cpp2, cpp-c2, dart, dotnet, js, koka, kotlin",
+                "Counter::sum: Unknown `platform = ...` in `#[diplomat::attr]`, only `supports = ...` is known",
+            ]
+        );
+
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub enum Level {
+                    #[diplomat::attr(koka, rename = "low")]
+                    Low,
+                    High,
+                }
+            }
+        };
+        let (files, errors) = gen_output(tk_stream, None, None, None, false, Default::default());
+        assert_eq!(
+            errors,
+            ["Level: Variant `Low`: `low` is not a valid Koka constructor name, please rename it"]
+        );
+        assert!(!files["lib.kk"].contains("Level"));
+    }
+
+    #[test]
+    fn test_strip_prefixes() {
+        let strip_prefix = StripPrefix {
+            prefixes: vec!["ICU4X".into(), "Diplomat".into()],
+            overrides: [("ICU4XDiplomatShim".into(), None)].into_iter().collect(),
+        };
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                /// Compares with a `DiplomatCounter`
+                #[diplomat::opaque]
+                struct ICU4XLocale;
+
+                #[diplomat::opaque]
+                struct DiplomatCounter;
+
+                #[diplomat::opaque]
+                struct ICU4XDiplomatShim;
+            }
+        };
+        let (files, errors) = gen_output(
+            tk_stream,
+            Some("module-layout = \"file-per-type\""),
+            None,
+            None,
+            false,
+            strip_prefix.clone(),
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(files["Locale.kk"].contains("// Compares with a `Counter`"));
+        assert!(files.contains_key("Counter.kk"));
+        assert!(files.contains_key("ICU4XDiplomatShim.kk"));
+
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct ICU4XLocale;
+
+                #[diplomat::opaque]
+                struct DiplomatLocale;
+            }
+        };
+        let (_, errors) = gen_output(tk_stream, None, None, None, false, strip_prefix);
+        assert!(errors.iter().any(|e| e.contains("`Locale`")), "{errors:?}");
+    }
+
+    #[test]
+    fn test_parent_managed() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Arena(Vec<Node>);
+
+                /// A node allocated from an `Arena`
+                #[diplomat::opaque]
+                #[diplomat::attr(koka, parent_managed)]
+                struct Node(u32);
+
+                impl Arena {
+                    #[diplomat::attr(*, constructor)]
+                    pub fn new() -> Box<Arena> {
+                        Box::new(Arena(Vec::new()))
+                    }
+
+                    pub fn alloc<'a>(&'a mut self, value: u32) -> &'a Node {
+                        self.0.push(Node(value));
+                        self.0.last().unwrap()
+                    }
+                }
+
+                impl Node {
+                    pub fn value(&self) -> u32 {
+                        self.0
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream.clone(), Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Node.kk").unwrap());
+        assert!(files.contains_key("Arena-finalizer.c"));
+        assert!(!files.contains_key("Node-finalizer.c"));
+
+        let mut files = gen_files(
+            tk_stream,
+            Some("destruction = \"manual\"\nmodule-layout = \"file-per-type\""),
+        );
+        let node = files.remove("Node.kk").unwrap();
+        assert!(node.contains("pub fun node/from-ffi(ptr : c-pointer<()>, self-edge : list<any>) : Node\n  Node(ptr, self-edge)"));
+        assert!(!node.contains("pub fun free"));
+        assert!(files.remove("Arena.kk").unwrap().contains("pub fun free"));
+    }
+
+    #[test]
+    fn test_default_constructor() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Config {
+                    verbose: bool,
+                    retries: u32,
+                    timeout: f64,
+                }
+
+                impl Config {
+                    #[diplomat::attr(*, default)]
+                    pub fn new_default() -> Config {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(files.remove("Config.kk").unwrap());
+    }
+
+    #[test]
+    fn test_with_warnings() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Warnings(Vec<String>);
+
+                #[diplomat::out]
+                pub struct Parsed {
+                    value: u32,
+                    warnings: Box<Warnings>,
+                }
+
+                #[diplomat::opaque]
+                struct Parser;
+
+                impl Parser {
+                    #[diplomat::attr(*, with_warnings)]
+                    pub fn parse(&self) -> Parsed {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(*, with_warnings)]
+                    pub fn try_parse(&self) -> Result<Parsed, ()> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        insta::assert_snapshot!(files.remove("Parser.kk").unwrap());
+    }
+
+    #[test]
+    fn test_accessors() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Point {
+                    x: f64,
+                    y: f64,
+                }
+
+                impl Point {
+                    #[diplomat::attr(*, getter)]
+                    pub fn x(self) -> f64 {
+                        self.x
+                    }
+
+                    #[diplomat::attr(*, getter = "norm")]
+                    pub fn length(self) -> f64 {
+                        self.x.hypot(self.y)
+                    }
+                }
+
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    #[diplomat::attr(*, getter)]
+                    pub fn count(&self) -> u32 {
+                        self.0
+                    }
+
+                    #[diplomat::attr(*, setter)]
+                    pub fn set_count(&mut self, count: u32) {
+                        self.0 = count;
+                    }
+
+                    #[diplomat::attr(*, getter)]
+                    pub fn limit() -> u32 {
+                        u32::MAX
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        let point = &files["Point.kk"];
+        // Koka already defines `point/x` for the field
+        assert!(point.contains("pub fun get-x(self : Point) : io-noexn float64"));
+        assert!(point.contains("pub fun norm(self : Point) : io-noexn float64"));
+        insta::assert_snapshot!(files["Counter.kk"]);
+    }
+
+    #[test]
+    fn test_constants() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Limits {
+                    min: i32,
+                    max: i32,
+                }
+
+                impl Limits {
+                    /// The widest limits
+                    pub const ALL: Limits = Limits { min: i32::MIN, max: i32::MAX };
+                    pub const MAX_COUNT: u64 = 1_000_000u64;
+                    pub const OFFSET: i8 = -(3);
+                    pub const RATIO: f32 = 0.1;
+                    pub const HUGE: f64 = 1e300;
+                    pub const STRICT: bool = true;
+                    pub const SEPARATOR: DiplomatChar = ',' as DiplomatChar;
+                    pub const SCALED: u32 = 3 * 4;
+                }
+            }
+        };
+        let files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files["Limits.kk"]);
+    }
+
+    #[test]
+    fn test_slice_returns() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Samples(Vec<f64>);
+
+                impl Samples {
+                    pub fn borrow<'a>(&'a self) -> &'a [f64] {
+                        &self.0
+                    }
+
+                    pub fn to_boxed(&self) -> Box<[f64]> {
+                        self.0.clone().into()
+                    }
+
+                    pub fn indices(&self) -> Box<[u32]> {
+                        (0..self.0.len() as u32).collect()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        let lib = files.remove("lib.kk").unwrap();
+        assert!(lib.contains("sizeof(DiplomatF64View), _Alignof(DiplomatF64View)"));
+        assert!(lib.contains("pub fun slice-uint32/take(r : c-pointer<slice-uint32-t>)"));
+        insta::assert_snapshot!(files.remove("Samples.kk").unwrap());
+    }
+
+    #[test]
+    fn test_slice_fields() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Range {
+                    start: u32,
+                    end: u32,
+                }
+
+                pub struct Record<'a> {
+                    name: &'a DiplomatStr,
+                    title: &'a DiplomatStr16,
+                    weights: &'a [f64],
+                    range: Range,
+                }
+
+                #[diplomat::opaque]
+                struct Index(Vec<u32>);
+
+                impl Index {
+                    pub fn add<'a>(&mut self, record: Record<'a>) -> usize {
+                        self.0.push(record.range.start);
+                        record.weights.len()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("struct-layout = \"fbip\"\nmodule-layout = \"file-per-type\""),
+        );
+        let lib = files.remove("lib.kk").unwrap();
+        assert!(lib.contains("pub extern utf8-copy(r : c-pointer<utf8-view-t>) : io-noexn string"));
+        assert!(
+            lib.contains("pub extern utf16-copy(r : c-pointer<utf16-view-t>) : io-noexn string")
+        );
+        assert!(files["Index.kk"].contains("*(Record*)#2"));
+        insta::assert_snapshot!(files.remove("Record.kk").unwrap());
+    }
+
+    #[test]
+    fn test_target_pointer_width() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Buffer(Vec<u8>);
+
+                impl Buffer {
+                    pub fn len(&self) -> usize {
+                        self.0.len()
+                    }
+                }
+            }
+        };
+        let files = gen_files_with(tk_stream.clone(), None, None, None, false);
+        assert!(files["lib.kk"].contains("c inline \"(int32_t)(sizeof(size_t) * 8)\""));
+
+        let files = gen_files_with(tk_stream, None, None, Some(PointerWidth::Bits32), false);
+        assert!(files["lib.kk"].contains(
+            "c inline \"(int32_t)(sizeof(char[sizeof(size_t) * 8 == 32 ? 1 : -1]) * 32)\""
+        ));
+    }
+
+    #[test]
+    fn test_leak_report() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u8);
+
+                impl Counter {
+                    pub fn new() -> Box<Counter> {
+                        Box::new(Counter(0))
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream.clone(), None);
+        assert!(!files["lib.kk"].contains("with-leak-report"));
+
+        let files = gen_files(tk_stream, Some("leak-report = true"));
+        let lib = &files["lib.kk"];
+        assert!(lib.contains("pub fun with-leak-report(main : () -> <io|e> a) : <io|e> a"));
+        assert!(lib.contains("pub fun with-writeable("));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Buffer(Vec<u8>);
+
+                impl Buffer {
+                    pub fn get(&self, index: usize) -> u8 {
+                        self.0[index]
+                    }
+                }
+            }
+        };
+        let conf = r#"
+            profile = "debug"
+
+            [profiles.debug]
+            checks = "full"
+
+            [profiles.release]
+            checks = "minimal"
+            destruction = "manual"
+        "#;
+
+        let files = gen_files_with(tk_stream.clone(), Some(conf), None, None, false);
+        assert!(files["lib.kk"].contains(
+            "pub fun get(self : Buffer, index : int) : io int\n  val result = buffer_get(self.raw, usize/from-int(index))"
+        ));
+        assert!(files["lib.kk"].contains("extern buffer_own("));
+
+        let files = gen_files_with(tk_stream, Some(conf), Some("release"), None, false);
+        assert!(files["lib.kk"].contains(
+            "pub fun get(self : Buffer, index : int) : io-noexn int\n  val result = buffer_get(self.raw, usize/truncate(index))"
+        ));
+        assert!(!files["lib.kk"].contains("extern buffer_own("));
+    }
+
+    #[test]
+    fn test_opaque_finalizer() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                #[diplomat::opaque]
+                struct Registry(Vec<Counter>);
+
+                impl Registry {
+                    pub fn first<'a>(&'a self) -> &'a Counter {
+                        &self.0[0]
+                    }
+
+                    pub fn count(&self, counter: &Counter, fallback: Option<&Counter>) -> u32 {
+                        counter.0
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Registry.kk").unwrap());
+        insta::assert_snapshot!(files.remove("Registry-finalizer.c").unwrap());
+    }
+
+    #[test]
+    fn test_wrapper_cache() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                #[diplomat::opaque]
+                struct Registry(Box<Counter>);
+
+                impl Registry {
+                    pub fn first<'a>(&'a self) -> &'a Counter {
+                        &self.0
+                    }
+                }
+
+                impl Counter {
+                    pub fn new() -> Box<Counter> {
+                        Box::new(Counter(0))
+                    }
+
+                    pub fn into_registry(self: Box<Self>) -> Box<Registry> {
+                        Box::new(Registry(self))
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("module-layout = \"file-per-type\"\nwrapper-cache = true"),
+        );
+        let counter = files.remove("Counter.kk").unwrap();
+        assert!(counter.contains("val raw = counter_own(ptr, self-edge.is-empty)"));
+        assert!(counter.contains("c inline \"Counter_wrap((void*)#1, #2, kk_context())\""));
+        insta::assert_snapshot!(files.remove("Counter-finalizer.c").unwrap());
+    }
+
+    #[test]
+    fn test_non_send() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(koka, non_send)]
+                struct Parser(u32);
+
+                impl Parser {
+                    pub fn new() -> Box<Parser> {
+                        Box::new(Parser(0))
+                    }
+
+                    pub fn merge(&self, other: &Parser, fallback: Option<&Parser>) -> u32 {
+                        self.0 + other.0
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream.clone(), Some("module-layout = \"file-per-type\""));
+        insta::assert_snapshot!(files.remove("Parser.kk").unwrap());
+
+        let files = gen_files(tk_stream, Some("destruction = \"manual\""));
+        assert!(files["lib.kk"].contains(
+            "pub fun free(self : Parser) : io-noexn ()\n  parser_check_thread(self.thread)\n"
+        ));
+    }
+
+    #[test]
+    fn test_opaque_manual_destruction() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    pub fn count(&self) -> u32 {
+                        self.0
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some("destruction = \"manual\"\nmodule-layout = \"file-per-type\""),
+        );
+        assert!(!files.contains_key("Counter-finalizer.c"));
+        insta::assert_snapshot!(files.remove("Counter.kk").unwrap());
+    }
+
+    #[test]
+    fn test_file_per_type() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Document(u32);
+
+                #[diplomat::opaque]
+                struct Node(u32);
+
+                #[diplomat::opaque]
+                struct Parser;
+
+                impl Document {
+                    pub fn root(&self) -> Box<Node> {
+                        unimplemented!()
+                    }
+                }
+
+                impl Node {
+                    pub fn document(&self) -> Box<Document> {
+                        unimplemented!()
+                    }
+                }
+
+                impl Parser {
+                    pub fn parse(&self, node: &Node) -> Box<Document> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let files = gen_files(tk_stream, Some("module-layout = \"file-per-type\""));
+
+        // `Document` and `Node` import each other, so they have to share a module
+        assert!(!files.contains_key("Node.kk"));
+        let document = &files["Document.kk"];
+        assert!(document.contains("pub value struct Node"));
+        assert!(document.contains("import lib;"));
+        assert!(!document.contains("import Document;"));
+        insta::assert_snapshot!(files["Parser.kk"]);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "icu4x::locale")]
+                struct Locale(u32);
+
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "icu4x::decimal")]
+                struct DecimalFormatter(u32);
+
+                impl Locale {
+                    pub fn und() -> Box<Locale> {
+                        unimplemented!()
+                    }
+                }
+
+                impl DecimalFormatter {
+                    pub fn create(locale: &Locale) -> Box<DecimalFormatter> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, None);
+        assert!(files.contains_key("icu4x/locale.kk"));
+        insta::assert_snapshot!(files.remove("icu4x/decimal.kk").unwrap());
+    }
+
+    /// A type and a namespace named like modules of `std/core`, which theirs would share a qualifier with
+    fn console_bridge() -> TokenStream {
+        quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Console(Vec<u32>);
+
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "icu4x::char")]
+                struct CodePointSet(u32);
+
+                impl CodePointSet {
+                    pub fn console(&self) -> Box<Console> {
+                        unimplemented!()
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to generate bindings")]
+    fn test_std_module_clash() {
+        gen_files(console_bridge(), Some("module-layout = \"file-per-type\""));
+    }
+
+    #[test]
+    fn test_module_prefix() {
+        let files = gen_files(
+            console_bridge(),
+            Some("module-layout = \"file-per-type\"\nmodule-prefix = \"ffi-\""),
+        );
+        assert!(files.contains_key("lib.kk"));
+        assert!(files.contains_key("ffi-Console.kk"));
+        assert!(files["icu4x/char/ffi-CodePointSet.kk"].contains("import ffi-Console;"));
+
+        // Namespaces name the modules of the single layout, and get the affixes as well
+        let files = gen_files(console_bridge(), Some("module-suffix = \"-ffi\""));
+        assert!(files.contains_key("icu4x/char-ffi.kk"));
+    }
+
+    /// A bridge using the features whose bindings depend on the options
+    fn feature_bridge() -> TokenStream {
+        quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                use diplomat_runtime::DiplomatWriteable;
+
+                #[diplomat::attr(*, namespace = "features")]
+                pub enum ParseError {
+                    Empty,
+                    Invalid,
+                }
+
+                #[diplomat::attr(*, namespace = "features")]
+                pub struct Point {
+                    x: f64,
+                    y: f64,
+                    id: u32,
+                }
+
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "features")]
+                struct Shape(Vec<f64>);
+
+                impl Shape {
+                    #[diplomat::attr(*, constructor)]
+                    pub fn parse(input: &str) -> Result<Box<Shape>, ParseError> {
+                        unimplemented!()
+                    }
+
+                    pub fn center(&self) -> Point {
+                        unimplemented!()
+                    }
+
+                    pub fn closest(&self, p: Point) -> Option<Point> {
+                        unimplemented!()
+                    }
+
+                    pub fn scale(&mut self, factors: &[f64], index: usize) -> Result<(), ()> {
+                        unimplemented!()
+                    }
+
+                    pub fn describe(&self, out: &mut DiplomatWriteable) {
+                        unimplemented!()
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generates the feature bridge with every combination of the options changing the shape
+    /// of the bindings, so that a change to one combination cannot go unnoticed
+    #[test]
+    fn test_option_matrix() {
+        for error_style in ["exn", "either"] {
+            for struct_layout in ["declared", "fbip"] {
+                for module_layout in ["single", "file-per-type"] {
+                    let conf = format!(
+                        "error-style = \"{error_style}\"\n\
+                         struct-layout = \"{struct_layout}\"\n\
+                         module-layout = \"{module_layout}\""
+                    );
+                    let files = gen_files(feature_bridge(), Some(&conf));
+                    // The modules of the bridge, without the runtime support
+                    let mut names = files
+                        .keys()
+                        .filter(|name| name.starts_with("features") || *name == "readme.kk")
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    let bindings = names
+                        .into_iter()
+                        .map(|name| format!("// {name}\n{}", files[name]))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let suffix = format!("{error_style}-{struct_layout}-{module_layout}");
+                    insta::with_settings!({ snapshot_suffix => suffix }, {
+                        insta::assert_snapshot!(bindings)
+                    });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_package() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                #[diplomat::opaque]
+                struct Registry;
+
+                impl Registry {
+                    pub fn get(&self) -> Box<Counter> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(
+            tk_stream,
+            Some(
+                "module-layout = \"file-per-type\"\n\
+                 package = \"counters\"\n\
+                 library = \"counters_ffi\"\n\
+                 library-dir = \"target/release\"",
+            ),
+        );
+        insta::assert_snapshot!(files.remove("counters.kk").unwrap());
+        assert_eq!(
+            files["counters.files"],
+            "lib.kk\nCounter.kk\nRegistry.kk\ncounters.kk\n"
+        );
+        assert_eq!(
+            files["counters.flags"],
+            "--cclib=counters_ffi\n--cclibdir=target/release\n"
+        );
+    }
+
+    #[test]
+    fn test_emit_c_headers() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    pub fn new() -> Box<Counter> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let files = gen_files_with(tk_stream.clone(), None, None, None, false);
+        assert!(!files.keys().any(|name| name.starts_with("include/")));
+
+        let files = gen_files_with(
+            tk_stream,
+            Some("c-include-dir = \"../c2/include\""),
+            None,
+            None,
+            true,
+        );
+        for header in [
+            "include/diplomat_runtime.h",
+            "include/Counter.d.h",
+            "include/Counter.h",
+        ] {
+            assert!(files.contains_key(header), "missing {header}");
+        }
+        // The emitted headers are the ones the generated C files include
+        assert!(files["Counter-finalizer.c"].contains("#include \"Counter.h\""));
+        assert_eq!(files["diplomat.flags"], "--ccincdir=include\n");
+    }
+
+    #[test]
+    fn test_bundle() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::attr(*, namespace = "icu4x::locale")]
+                struct Locale(u32);
+
+                #[diplomat::attr(*, namespace = "icu4x::decimal")]
+                struct Options {
+                    grouping: bool,
+                }
+
+                impl Locale {
+                    pub fn und() -> Box<Locale> {
+                        unimplemented!()
+                    }
+
+                    pub fn options(&self) -> Options {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item");
+        let diplomat_file = ast::File::from(&item);
+        let env = diplomat_file.all_types();
+        let tcx = hir::TypeContext::from_ast(&env, KokaAttributeValidator::new())
+            .unwrap_or_else(|_| panic!("Failed to create context"));
+        let config = KokaConfig {
+            package: Some("locales".into()),
+            bundle: true,
+            ..Default::default()
+        };
+        let docs_url_generator = Default::default();
+        let (mut files, errors) = run(&tcx, config, &docs_url_generator, Default::default());
+        assert!(errors.is_empty());
+        let files = files.take_files();
+
+        // The entry module is the bundle, with the types of every namespace
+        let modules = files
+            .keys()
+            .filter(|name| name.ends_with(".kk") && *name != "readme.kk")
+            .collect::<Vec<_>>();
+        assert_eq!(modules, ["locales.kk"]);
+        let bundle = &files["locales.kk"];
+        assert!(bundle.contains("pub fun with-batch("));
+        assert!(bundle.contains("pub value struct Locale"));
+        assert!(bundle.contains("pub struct Options"));
+        assert!(!bundle.contains("import lib") && !bundle.contains("import icu4x"));
+        assert!(!bundle.contains("pub extern "));
+        assert!(bundle.contains("\nextern null-pointer()"));
+
+        assert!(files.contains_key("Locale-finalizer.c"));
+        assert_eq!(files["locales.files"], "locales.kk\nreadme.kk\n");
+        assert!(files["readme.kk"].contains("import locales"));
+    }
+
+    #[test]
+    fn test_readme() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Config(u32);
+
+                impl Config {
+                    #[diplomat::attr(*, constructor)]
+                    pub fn new(verbose: bool) -> Box<Config> {
+                        Box::new(Config(verbose as u32))
+                    }
+                }
+
+                #[diplomat::opaque]
+                #[diplomat::attr(*, featured)]
+                struct Session(u32);
+
+                impl Session {
+                    pub fn connect(config: &Config, host: &DiplomatStr) -> Result<Box<Session>, ()> {
+                        Ok(Box::new(Session(config.0)))
+                    }
+                }
+
+                #[diplomat::opaque]
+                struct Unrelated(u32);
+
+                impl Unrelated {
+                    pub fn new() -> Box<Unrelated> {
+                        Box::new(Unrelated(0))
+                    }
+                }
+            }
+        };
+        let mut files = gen_files(tk_stream, Some("error-style = \"either\""));
+        insta::assert_snapshot!(files.remove("readme.kk").unwrap());
+    }
+
+    #[test]
+    fn test_smoke_tests() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub enum Mode {
+                    Fast,
+                    Safe,
+                }
+
+                impl Mode {
+                    pub fn is_fast(self) -> bool {
+                        matches!(self, Mode::Fast)
+                    }
+                }
+
+                pub trait Listener {
+                    fn on_event(&self, code: u32);
+                }
+
+                #[diplomat::opaque]
+                struct Counter(Vec<u32>);
+
+                impl Counter {
+                    #[diplomat::attr(*, constructor)]
+                    pub fn new(mode: Mode) -> Box<Counter> {
+                        unimplemented!()
+                    }
+                    pub fn add(&mut self, amounts: &[u32], each: impl Fn(u32) -> bool) -> Result<u32, ()> {
+                        unimplemented!()
+                    }
+                    #[diplomat::attr(*, indexer)]
+                    pub fn get(&self, index: usize) -> Option<u32> {
+                        unimplemented!()
+                    }
+                    pub fn merge(&mut self, other: &Counter) {
+                        unimplemented!()
+                    }
+                    pub fn finish(self: Box<Self>) -> u32 {
+                        unimplemented!()
+                    }
+                    pub fn panics(&self) {
+                        unimplemented!()
+                    }
+                    pub fn listen(&self, listener: &dyn Listener) {
+                        unimplemented!()
+                    }
+                    pub async fn wait(&self) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let conf = "smoke-tests = true\nsmoke-test-skip = [\"Counter::panics\"]";
+        let files = gen_files(tk_stream.clone(), Some(conf));
+        assert!(files["diplomat.files"].contains("smoke.kk"));
+        insta::assert_snapshot!(files["smoke.kk"]);
+
+        let files = gen_files(
+            tk_stream.clone(),
+            Some(&format!(
+                "{conf}\ndestruction = \"manual\"\nerror-style = \"either\""
+            )),
+        );
+        let smoke = &files["smoke.kk"];
+        assert!(smoke.contains("  other.free\n  counter.free\n"));
+        assert!(smoke.contains("  val result = counter/new(Fast)\n  result.free\n"));
+        assert!(smoke.contains("  val _ = counter.add(slice-uint32/view(vector()), fn(_) False)\n"));
+
+        let files = gen_files(tk_stream, None);
+        assert!(!files.contains_key("smoke.kk"));
+    }
+
+    #[test]
+    fn test_template_overrides() {
+        let tk_stream = || {
+            quote! {
+                #[diplomat::bridge]
+                mod ffi {
+                    #[diplomat::opaque]
+                    struct Counter(u32);
+
+                    impl Counter {
+                        pub fn new() -> Box<Counter> {
+                            Box::new(Counter(0))
+                        }
+                    }
+
+                    enum Speed {
+                        Slow,
+                        Fast,
+                    }
+                }
+            }
+        };
+        let dir = std::env::temp_dir().join(format!("koka-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conf = format!("templates = {:?}", dir.display().to_string());
+
+        std::fs::write(
+            dir.join("prelude.kk"),
+            "{{ builtin }}\n\n// Project prelude\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("method.kk"),
+            "// Calls `{{ ffi_name }}`\n{{builtin}}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("opaque.kk"),
+            "{{ builtin }}\n\npub fun {{ qualifier }}/describe() : string\n  \"{{ type_name }}\"\n",
+        )
+        .unwrap();
+        let files = gen_files(tk_stream(), Some(&conf));
+        let lib = &files["lib.kk"];
+        assert!(lib.contains("// Project prelude\n\n"), "{lib}");
+        assert!(
+            lib.contains("// Calls `counter_new`\npub fun counter/new_() : io-noexn Counter\n"),
+            "{lib}"
+        );
+        assert!(
+            lib.contains("pub fun counter/describe() : string\n  \"Counter\""),
+            "{lib}"
+        );
+        // Parts without a template keep the built-in rendering
+        assert!(lib.contains("pub type Speed\n"), "{lib}");
+
+        std::fs::write(dir.join("enum.kk"), "{{ variants }}").unwrap();
+        let panic = std::panic::catch_unwind(|| gen_files(tk_stream(), Some(&conf)))
+            .expect_err("unknown placeholder accepted");
+        assert!(panic
+            .downcast_ref::<String>()
+            .unwrap()
+            .starts_with("Unknown placeholder `{{ variants }}`"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
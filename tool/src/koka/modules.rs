@@ -0,0 +1,331 @@
+use super::formatter::KokaFormatter;
+use super::ModuleLayout;
+use diplomat_core::hir::{
+    ReturnType, SuccessType, TraitId, TyPosition, Type, TypeContext, TypeDef, TypeId,
+};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The name of the module with the runtime support, which every other module imports
+pub(super) const LIB_MODULE: &str = "lib";
+
+/// The modules of the Koka standard library that generated modules import, implicitly through
+/// `std/core` or explicitly. A generated module whose last segment is named like one of these
+/// gets the same qualifier, which makes uses of either ambiguous.
+const STD_MODULES: &[&str] = &[
+    "std/core",
+    "std/core/types",
+    "std/core/hnd",
+    "std/core/exn",
+    "std/core/bool",
+    "std/core/order",
+    "std/core/char",
+    "std/core/int",
+    "std/core/vector",
+    "std/core/string",
+    "std/core/sslice",
+    "std/core/list",
+    "std/core/maybe",
+    "std/core/either",
+    "std/core/tuple",
+    "std/core/show",
+    "std/core/debug",
+    "std/core/delayed",
+    "std/core/console",
+    "std/core/unsafe",
+    "std/core/undiv",
+    "std/core/cextern",
+    "std/core/int32",
+    "std/core/int64",
+    "std/core/float64",
+    "std/async",
+];
+
+/// Something generated into a Koka module
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum ModuleItem {
+    Type(TypeId),
+    Trait(TraitId),
+}
+
+/// Decides which module each type and trait is generated into, and what each module imports.
+///
+/// Types in a `namespace` are generated into the module hierarchy it names, e.g. `icu4x::locale`
+/// becomes `icu4x/locale`, with a module per type below it with [`ModuleLayout::FilePerType`].
+/// Koka does not allow import cycles though, so modules that depend on each other, directly or
+/// not, are merged into the module of the first item declared in them.
+///
+/// The last segment of every module but `lib` gets the configured `module-prefix` and
+/// `module-suffix`, to tell apart modules that would otherwise be named like a standard one.
+///
+/// When bundling, everything is generated into a single module taking the place of `lib`,
+/// whatever the namespaces and layout.
+pub(super) struct Modules {
+    modules: BTreeMap<ModuleItem, String>,
+    imports: BTreeMap<String, BTreeSet<String>>,
+    /// The module with the runtime support, `lib` unless bundling
+    lib: String,
+}
+
+impl Modules {
+    pub fn new(
+        tcx: &TypeContext,
+        formatter: &KokaFormatter,
+        layout: ModuleLayout,
+        prefix: &str,
+        suffix: &str,
+        bundle: Option<&str>,
+    ) -> Self {
+        let lib = bundle.unwrap_or(LIB_MODULE);
+        let items = tcx
+            .all_types()
+            .filter(|(_, ty)| !ty.attrs().disable)
+            .map(|(id, ty)| (ModuleItem::Type(id), ty.attrs().namespace.as_deref()))
+            .chain(
+                tcx.all_traits()
+                    .filter(|(_, trt)| !trt.attrs.disable)
+                    .map(|(id, trt)| (ModuleItem::Trait(id), trt.attrs.namespace.as_deref())),
+            )
+            .map(|(item, namespace)| {
+                if bundle.is_some() {
+                    return (item, lib.into());
+                }
+                let namespace = namespace.map(|ns| formatter.fmt_namespace_path(ns));
+                let (parent, name) = match layout {
+                    ModuleLayout::Single => match namespace {
+                        Some(namespace) => match namespace.rsplit_once('/') {
+                            Some((parent, name)) => (Some(parent.to_owned()), name.to_owned()),
+                            None => (None, namespace),
+                        },
+                        None => return (item, lib.into()),
+                    },
+                    ModuleLayout::FilePerType => {
+                        let name: Cow<str> = match item {
+                            ModuleItem::Type(id) => formatter.fmt_type_name(id),
+                            ModuleItem::Trait(id) => formatter.fmt_trait_name(id),
+                        };
+                        (namespace, name.into_owned())
+                    }
+                };
+                let module = match parent {
+                    Some(parent) => format!("{parent}/{prefix}{name}{suffix}"),
+                    None => format!("{prefix}{name}{suffix}"),
+                };
+                (item, module)
+            })
+            .collect::<Vec<_>>();
+
+        // Every module imports the runtime support in `lib`, which imports nothing in turn
+        let mut module_deps = BTreeMap::<String, BTreeSet<String>>::new();
+        module_deps.insert(lib.into(), BTreeSet::new());
+        let module_of = items.iter().cloned().collect::<BTreeMap<_, _>>();
+        for (item, module) in items.iter() {
+            let deps = module_deps.entry(module.clone()).or_default();
+            if module != lib {
+                deps.insert(lib.into());
+            }
+            deps.extend(
+                dependencies(tcx, *item)
+                    .iter()
+                    .filter_map(|dep| module_of.get(dep))
+                    .filter(|dep| *dep != module)
+                    .cloned(),
+            );
+        }
+
+        // `lib` keeps its name, other merged modules are named after the one declared first
+        let order = std::iter::once(lib.to_string())
+            .chain(items.iter().map(|(_, module)| module.clone()))
+            .fold(Vec::new(), |mut order, module| {
+                if !order.contains(&module) {
+                    order.push(module);
+                }
+                order
+            });
+        let mut merged = BTreeMap::new();
+        for component in strongly_connected_components(&order, &module_deps) {
+            let first = order
+                .iter()
+                .find(|module| component.contains(module))
+                .unwrap();
+            for module in component.iter() {
+                merged.insert(module.clone(), first.clone());
+            }
+        }
+
+        let mut imports = BTreeMap::<String, BTreeSet<String>>::new();
+        for (module, deps) in module_deps.iter() {
+            let module = &merged[module];
+            let module_imports = imports.entry(module.clone()).or_default();
+            module_imports.extend(
+                deps.iter()
+                    .map(|dep| &merged[dep])
+                    .filter(|dep| *dep != module)
+                    .cloned(),
+            );
+        }
+
+        Modules {
+            modules: items
+                .into_iter()
+                .map(|(item, module)| (item, merged[&module].clone()))
+                .collect(),
+            imports,
+            lib: lib.into(),
+        }
+    }
+
+    /// The module with the runtime support, which every other module imports
+    pub fn lib(&self) -> &str {
+        &self.lib
+    }
+
+    /// The module an item is generated into
+    pub fn module(&self, item: ModuleItem) -> &str {
+        &self.modules[&item]
+    }
+
+    /// The relative path from the directory of an item's module to the root of the generated files
+    pub fn root_path(&self, item: ModuleItem) -> String {
+        "../".repeat(self.module(item).matches('/').count())
+    }
+
+    /// The standard modules generated modules share their qualifier with, as pairs of the
+    /// generated module and the standard one
+    pub fn std_clashes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.imports.keys().filter_map(|module| {
+            let name = module.rsplit('/').next().unwrap_or(module).to_lowercase();
+            STD_MODULES
+                .iter()
+                .find(|std| std.rsplit('/').next() == Some(name.as_str()))
+                .map(|std| (module.as_str(), *std))
+        })
+    }
+
+    /// The generated modules a module imports
+    pub fn imports(&self, module: &str) -> impl Iterator<Item = &str> {
+        self.imports
+            .get(module)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+}
+
+/// The types and traits mentioned in the Koka declarations of an item
+fn dependencies(tcx: &TypeContext, item: ModuleItem) -> BTreeSet<ModuleItem> {
+    let mut deps = BTreeSet::new();
+    // Traits are structs of callbacks, which only take primitives
+    let ModuleItem::Type(id) = item else {
+        return deps;
+    };
+
+    fn add<P: TyPosition>(deps: &mut BTreeSet<ModuleItem>, ty: &Type<P>) {
+        match *ty {
+            Type::DynTrait(trt) => {
+                deps.insert(ModuleItem::Trait(trt));
+            }
+            Type::DiplomatOption(ref ok) => add(deps, ok),
+            _ => {
+                if let Some(id) = ty.id() {
+                    deps.insert(ModuleItem::Type(id));
+                }
+            }
+        }
+    }
+
+    let ty = tcx.resolve_type(id);
+    match ty {
+        TypeDef::Struct(s) => s.fields.iter().for_each(|f| add(&mut deps, &f.ty)),
+        TypeDef::OutStruct(s) => s.fields.iter().for_each(|f| add(&mut deps, &f.ty)),
+        _ => {}
+    }
+    for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+        for param in method.params.iter() {
+            add(&mut deps, &param.ty);
+        }
+        let (ok, err) = match method.output {
+            ReturnType::Infallible(ref ok) | ReturnType::Nullable(ref ok) => (ok, None),
+            ReturnType::Fallible(ref ok, ref err) => (ok, err.as_ref()),
+        };
+        if let SuccessType::OutType(ref o) = *ok {
+            add(&mut deps, o);
+        }
+        if let Some(e) = err {
+            add(&mut deps, e);
+        }
+        // The value and warnings are returned as a tuple, without the struct around them
+        if let Some(with_warnings) = method.with_warnings(tcx) {
+            add(&mut deps, &with_warnings.value.ty);
+            add(&mut deps, &with_warnings.warnings.ty);
+        }
+    }
+
+    deps.remove(&item);
+    deps
+}
+
+/// Groups modules that depend on each other, directly or not, using Tarjan's algorithm
+fn strongly_connected_components(
+    modules: &[String],
+    deps: &BTreeMap<String, BTreeSet<String>>,
+) -> Vec<Vec<String>> {
+    struct State<'a> {
+        deps: &'a BTreeMap<String, BTreeSet<String>>,
+        index: BTreeMap<&'a str, usize>,
+        low_link: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        components: Vec<Vec<String>>,
+    }
+
+    fn visit<'a>(state: &mut State<'a>, module: &'a str) {
+        let index = state.index.len();
+        state.index.insert(module, index);
+        state.low_link.insert(module, index);
+        state.stack.push(module);
+        state.on_stack.insert(module);
+
+        let deps = state.deps;
+        for dep in deps[module].iter() {
+            let dep = dep.as_str();
+            if !state.index.contains_key(dep) {
+                visit(state, dep);
+                let low_link = state.low_link[module].min(state.low_link[dep]);
+                state.low_link.insert(module, low_link);
+            } else if state.on_stack.contains(dep) {
+                let low_link = state.low_link[module].min(state.index[dep]);
+                state.low_link.insert(module, low_link);
+            }
+        }
+
+        if state.low_link[module] == state.index[module] {
+            let mut component = Vec::new();
+            loop {
+                let member = state.stack.pop().unwrap();
+                state.on_stack.remove(member);
+                component.push(member.to_string());
+                if member == module {
+                    break;
+                }
+            }
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        deps,
+        index: BTreeMap::new(),
+        low_link: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for module in modules {
+        if !state.index.contains_key(module.as_str()) {
+            visit(&mut state, module);
+        }
+    }
+
+    state.components
+}
@@ -0,0 +1,138 @@
+//! Templates taking the place of parts of the generated bindings, read from the `templates`
+//! directory of the config.
+//!
+//! A template is Koka code in which `{{ name }}` placeholders are replaced by what the backend
+//! generated for the part. `{{ builtin }}` is the whole built-in rendering, so a template can
+//! wrap it with project-specific helpers as well as replace it.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A part of the bindings a template can override
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Part {
+    /// The runtime support opening `lib.kk`
+    Prelude,
+    /// The function generated for each method
+    Method,
+    /// The declarations of an opaque, including its methods
+    Opaque,
+    /// The declarations of an enum, including its methods
+    Enum,
+}
+
+impl Part {
+    const ALL: [Part; 4] = [Part::Prelude, Part::Method, Part::Opaque, Part::Enum];
+
+    /// The name of the file overriding the part
+    fn file_name(self) -> &'static str {
+        match self {
+            Part::Prelude => "prelude.kk",
+            Part::Method => "method.kk",
+            Part::Opaque => "opaque.kk",
+            Part::Enum => "enum.kk",
+        }
+    }
+
+    /// The placeholders a template of the part can use
+    fn placeholders(self) -> &'static [&'static str] {
+        match self {
+            Part::Prelude => &["builtin"],
+            Part::Method => &["builtin", "docs", "name", "declaration", "ffi_name"],
+            Part::Opaque | Part::Enum => &["builtin", "docs", "type_name", "qualifier"],
+        }
+    }
+}
+
+/// The override templates of a config, by part
+#[derive(Debug, Default)]
+pub(super) struct Overrides {
+    templates: BTreeMap<Part, String>,
+}
+
+impl Overrides {
+    /// Reads the templates of a directory, which may only contain templates of known parts
+    /// using their placeholders
+    pub fn load(dir: &Path) -> Self {
+        let entries = std::fs::read_dir(dir)
+            .unwrap_or_else(|err| panic!("Failed to open templates directory {dir:?}: {err}"));
+        let mut templates = BTreeMap::new();
+        for entry in entries {
+            let path = entry
+                .unwrap_or_else(|err| panic!("Failed to read templates directory {dir:?}: {err}"))
+                .path();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let part = Part::ALL
+                .into_iter()
+                .find(|part| part.file_name() == file_name)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Unknown template {path:?}, expected one of {}",
+                        Part::ALL.map(Part::file_name).join(", ")
+                    )
+                });
+            let template = std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("Failed to read template {path:?}: {err}"));
+            for name in placeholders(&template) {
+                if !part.placeholders().contains(&name) {
+                    panic!(
+                        "Unknown placeholder `{{{{ {name} }}}}` in template {path:?}, expected one of {}",
+                        part.placeholders().join(", ")
+                    );
+                }
+            }
+            templates.insert(part, template);
+        }
+        Overrides { templates }
+    }
+
+    /// Whether a template overrides `part`
+    pub fn overrides(&self, part: Part) -> bool {
+        self.templates.contains_key(&part)
+    }
+
+    /// Renders the template of `part` if there is one, or returns `builtin`
+    pub fn apply(&self, part: Part, builtin: String, values: &[(&str, &str)]) -> String {
+        let Some(template) = self.templates.get(&part) else {
+            return builtin;
+        };
+        let mut out = String::with_capacity(template.len() + builtin.len());
+        let mut rest = template.as_str();
+        while let Some((before, name, after)) = next_placeholder(rest) {
+            out.push_str(before);
+            if name == "builtin" {
+                out.push_str(&builtin);
+            } else if let Some((_, value)) = values.iter().find(|(n, _)| *n == name) {
+                out.push_str(value);
+            }
+            rest = after;
+        }
+        out.push_str(rest);
+        // The generated parts end without a newline, so a template ending in one doesn't add
+        // a blank line
+        out.truncate(out.trim_end_matches('\n').len());
+        out
+    }
+}
+
+/// The names of the placeholders of a template
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some((_, name, after)) = next_placeholder(rest) {
+        names.push(name);
+        rest = after;
+    }
+    names
+}
+
+/// Splits `text` around its first `{{ name }}` placeholder
+fn next_placeholder(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find("{{")?;
+    let len = text[start..].find("}}")?;
+    Some((
+        &text[..start],
+        text[start + 2..start + len].trim(),
+        &text[start + len + 2..],
+    ))
+}
@@ -0,0 +1,73 @@
+use super::formatter::KokaFormatter;
+use crate::common::FileMap;
+use askama::Template;
+
+/// Generates the scaffolding to use the bindings as a package: an entry module re-exporting
+/// every generated module, a listing of the generated modules for build systems, and the
+/// flags `koka` needs to compile and link a program against them.
+pub(super) struct PackageGenContext<'a, 'tcx> {
+    pub formatter: &'a KokaFormatter<'tcx>,
+    /// The name of the entry module
+    pub name: String,
+    pub library: Option<String>,
+    pub library_dir: Option<String>,
+    pub c_include_dir: Option<String>,
+    /// The generated modules, `lib` first
+    pub modules: Vec<String>,
+    /// Whether the bindings are bundled into the module named after the package, which then
+    /// takes the place of the entry module
+    pub bundle: bool,
+    /// Whether the `readme` module with the worked examples was generated
+    pub readme: bool,
+    /// Whether the module with the smoke tests was generated
+    pub smoke_tests: bool,
+}
+
+#[derive(Template)]
+#[template(path = "koka/package.kk.jinja", escape = "none")]
+struct PackageTemplate<'a> {
+    flags_file: &'a str,
+    modules: &'a [String],
+}
+
+impl<'a, 'tcx> PackageGenContext<'a, 'tcx> {
+    pub fn gen(&self, files: &FileMap) {
+        let flags_file = format!("{}.flags", self.name);
+
+        if !self.bundle {
+            let entry = PackageTemplate {
+                flags_file: &flags_file,
+                modules: &self.modules,
+            }
+            .render()
+            .unwrap();
+            files.add_file(self.formatter.fmt_file_name(&self.name), entry);
+        }
+
+        let mut listing = String::new();
+        for module in self
+            .modules
+            .iter()
+            .map(String::as_str)
+            .chain(self.readme.then(|| "readme"))
+            .chain(self.smoke_tests.then(|| super::SMOKE_TEST_MODULE))
+            .chain((!self.bundle).then(|| self.name.as_str()))
+        {
+            listing.push_str(&self.formatter.fmt_file_name(module));
+            listing.push('\n');
+        }
+        files.add_file(format!("{}.files", self.name), listing);
+
+        let mut flags = String::new();
+        if let Some(ref library) = self.library {
+            flags.push_str(&format!("--cclib={library}\n"));
+        }
+        if let Some(ref library_dir) = self.library_dir {
+            flags.push_str(&format!("--cclibdir={library_dir}\n"));
+        }
+        if let Some(ref c_include_dir) = self.c_include_dir {
+            flags.push_str(&format!("--ccincdir={c_include_dir}\n"));
+        }
+        files.add_file(flags_file, flags);
+    }
+}
@@ -0,0 +1,200 @@
+use super::formatter::KokaFormatter;
+use super::modules::{ModuleItem, Modules};
+use super::values::{Construction, ValueGenContext};
+use super::{Destruction, ErrorStyle};
+use askama::Template;
+use diplomat_core::hir::{ReturnType, SuccessType, TypeContext, TypeDef, TypeId};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How many types get an example when none are marked `featured`
+const DEFAULT_EXAMPLE_COUNT: usize = 3;
+
+/// Generates `readme.kk`, a module of worked examples showing how to construct, use and
+/// release the most central types of the library.
+pub(super) struct ReadmeGenContext<'a, 'tcx> {
+    pub tcx: &'tcx TypeContext,
+    pub formatter: &'a KokaFormatter<'tcx>,
+    pub values: &'a ValueGenContext<'a, 'tcx>,
+    pub modules: &'a Modules,
+    pub error_style: ErrorStyle,
+    pub destruction: Destruction,
+}
+
+#[derive(Template)]
+#[template(path = "koka/readme.kk.jinja", escape = "none")]
+struct ReadmeTemplate {
+    examples: Vec<Example>,
+    /// Whether fallible methods throw, rather than returning an `either`
+    throws: bool,
+    /// Whether opaques have to be released with `free`, rather than by their finalizer
+    manual_free: bool,
+}
+
+/// One `example-` function, constructing a value of a type and releasing it again
+struct Example {
+    fn_name: String,
+    type_name: String,
+    value: String,
+    is_opaque: bool,
+    /// Whether the value is an opaque released from Koka, rather than by its parent
+    freed: bool,
+    /// The `val` bindings for the values the constructor takes
+    setup: Vec<String>,
+    ctor_name: String,
+    ctor_call: String,
+    ctor_fallible: bool,
+    /// A call to a fallible method on the value, shown when the constructor cannot fail
+    check: Option<(String, String)>,
+    /// The opaques constructed in `setup`, in construction order
+    cleanup: Vec<String>,
+}
+
+impl<'a, 'tcx> ReadmeGenContext<'a, 'tcx> {
+    /// Generates the body of the readme module and the imports it needs, or `None` if no type
+    /// can be constructed from Koka
+    pub fn gen(&self) -> Option<(String, BTreeSet<Cow<'static, str>>)> {
+        let mut imports = BTreeSet::new();
+        let examples = self
+            .featured_types()
+            .into_iter()
+            .filter_map(|id| self.gen_example(id, &mut imports))
+            .collect::<Vec<_>>();
+
+        if examples.is_empty() {
+            return None;
+        }
+
+        let mut directives = BTreeSet::new();
+        directives.insert(self.formatter.fmt_import(self.modules.lib(), None));
+        for id in imports {
+            let module = self.modules.module(ModuleItem::Type(id));
+            directives.insert(self.formatter.fmt_import(module, None));
+        }
+
+        let body = ReadmeTemplate {
+            examples,
+            throws: self.error_style == ErrorStyle::Exn,
+            manual_free: self.destruction == Destruction::Manual,
+        }
+        .render()
+        .unwrap();
+
+        Some((body, directives))
+    }
+
+    /// The types marked `featured`, or else the constructible types most linked to the rest of the API
+    fn featured_types(&self) -> Vec<TypeId> {
+        let marked = self
+            .tcx
+            .all_types()
+            .filter(|(_, ty)| !ty.attrs().disable && ty.attrs().featured)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        if !marked.is_empty() {
+            return marked;
+        }
+
+        let mut links = BTreeMap::<TypeId, usize>::new();
+        for (id, ty) in self.tcx.all_types() {
+            if ty.attrs().disable {
+                continue;
+            }
+            for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+                let used = method
+                    .params
+                    .iter()
+                    .filter_map(|param| param.ty.id())
+                    .chain(match method.output {
+                        ReturnType::Infallible(SuccessType::OutType(ref o))
+                        | ReturnType::Nullable(SuccessType::OutType(ref o))
+                        | ReturnType::Fallible(SuccessType::OutType(ref o), _) => o.id(),
+                        _ => None,
+                    });
+                // Count the links between types both ways, the type tying others together is just
+                // as central as the types used everywhere
+                for used in used.filter(|used| *used != id) {
+                    *links.entry(used).or_default() += 1;
+                    *links.entry(id).or_default() += 1;
+                }
+            }
+        }
+
+        let mut candidates = self
+            .tcx
+            .all_types()
+            .filter(|(_, ty)| {
+                !ty.attrs().disable && matches!(ty, TypeDef::Opaque(..) | TypeDef::Struct(..))
+            })
+            .enumerate()
+            .map(|(i, (id, ty))| {
+                let score = (links.get(&id).copied().unwrap_or(0), ty.methods().len());
+                (std::cmp::Reverse(score), i, id)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        candidates
+            .into_iter()
+            .map(|(_, _, id)| id)
+            .filter(|id| {
+                self.values
+                    .gen_constructor_call(*id, true, 0, &mut Construction::default())
+                    .is_some()
+            })
+            .take(DEFAULT_EXAMPLE_COUNT)
+            .collect()
+    }
+
+    fn gen_example(&self, id: TypeId, imports: &mut BTreeSet<TypeId>) -> Option<Example> {
+        let type_name = self.formatter.fmt_type_name(id);
+        let value = self.formatter.fmt_type_qualifier(&type_name);
+
+        let mut construction = Construction::default();
+        construction.names.insert(value.clone());
+        let (ctor, ctor_call) = self
+            .values
+            .gen_constructor_call(id, true, 0, &mut construction)?;
+        let ctor_name = self.formatter.fmt_qualified_method_name(ctor, id);
+        let ctor_fallible = matches!(ctor.output, ReturnType::Fallible(..));
+
+        let check = if ctor_fallible {
+            None
+        } else {
+            // Show error handling on the first fallible method we can call instead
+            self.tcx
+                .resolve_type(id)
+                .methods()
+                .iter()
+                .filter(|m| {
+                    !m.attrs.disable
+                        && !m.is_async
+                        && m.param_self.is_some()
+                        && m.attrs.special_method.is_none()
+                        && matches!(m.output, ReturnType::Fallible(..))
+                })
+                .find_map(|m| {
+                    let args = self.values.gen_args(m, 0, &mut construction)?;
+                    let name = self.formatter.fmt_qualified_method_name(m, id);
+                    Some((name.clone(), format!("{value}.{name}({args})")))
+                })
+        };
+
+        imports.insert(id);
+        imports.extend(construction.imports);
+
+        Some(Example {
+            fn_name: format!("example-{}", self.formatter.fmt_type_qualifier(&type_name)),
+            type_name: type_name.into_owned(),
+            value,
+            is_opaque: matches!(id, TypeId::Opaque(..)),
+            freed: self.values.is_freed(id),
+            setup: construction.setup,
+            ctor_name,
+            ctor_call,
+            ctor_fallible,
+            check,
+            cleanup: construction.cleanup,
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use super::formatter::KokaFormatter;
+use super::modules::{ModuleItem, Modules};
+use super::values::{Construction, ValueGenContext};
+use super::{Destruction, ErrorStyle};
+use askama::Template;
+use diplomat_core::hir::{
+    Method, ReturnType, SelfType, SpecialMethod, SuccessType, Type, TypeContext, TypeId,
+};
+use heck::ToSnakeCase;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+/// Generates `smoke.kk`, whose `main` calls every method of the bindings with placeholder
+/// arguments, so that running it checks that the bindings compile, link and cross into Rust
+/// and back for every signature.
+pub(super) struct SmokeTestGenContext<'a, 'tcx> {
+    pub tcx: &'tcx TypeContext,
+    pub formatter: &'a KokaFormatter<'tcx>,
+    pub modules: &'a Modules,
+    pub values: &'a ValueGenContext<'a, 'tcx>,
+    pub error_style: ErrorStyle,
+    pub destruction: Destruction,
+    /// The methods not to call, as `Type::method`
+    pub skip: &'a [String],
+}
+
+#[derive(Template)]
+#[template(path = "koka/smoke.kk.jinja", escape = "none")]
+struct SmokeTemplate {
+    tests: Vec<SmokeTest>,
+    /// The methods not called, as `Type::method` and why
+    skipped: Vec<(String, &'static str)>,
+    /// Whether opaques have to be released with `free`, rather than by their finalizer
+    manual_free: bool,
+}
+
+/// One `test-` function, calling a method
+struct SmokeTest {
+    fn_name: String,
+    /// The Rust method, as `Type::method`
+    method: String,
+    /// The `val` bindings for the receiver and the arguments
+    setup: Vec<String>,
+    /// The call, unless its result is bound in `setup` to be released
+    call: Option<String>,
+    /// The opaques constructed in `setup`, in construction order
+    cleanup: Vec<String>,
+}
+
+impl<'a, 'tcx> SmokeTestGenContext<'a, 'tcx> {
+    /// Generates the body of the smoke test module and the imports it needs
+    pub fn gen(&self) -> (String, BTreeSet<Cow<'static, str>>) {
+        let mut tests = Vec::new();
+        let mut skipped = Vec::new();
+        let mut imports = BTreeSet::new();
+
+        for (id, ty) in self.tcx.all_types() {
+            if !self.values.generated.contains(&id) {
+                continue;
+            }
+            for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+                let type_name = ty.name().as_str();
+                let name = format!("{type_name}::{}", method.name);
+                if self.skip.contains(&name) {
+                    skipped.push((name, "skipped by the config"));
+                } else if method.is_async {
+                    skipped.push((name, "async"));
+                } else if let Some(test) = self.gen_test(id, type_name, method, &name, &mut imports)
+                {
+                    tests.push(test);
+                } else {
+                    skipped.push((name, "its arguments can't be built"));
+                }
+            }
+        }
+
+        let mut directives = BTreeSet::new();
+        directives.insert(self.formatter.fmt_import(self.modules.lib(), None));
+        for id in imports {
+            let module = self.modules.module(ModuleItem::Type(id));
+            directives.insert(self.formatter.fmt_import(module, None));
+        }
+
+        let body = SmokeTemplate {
+            tests,
+            skipped,
+            manual_free: self.destruction == Destruction::Manual,
+        }
+        .render()
+        .unwrap();
+
+        (body, directives)
+    }
+
+    fn gen_test(
+        &self,
+        id: TypeId,
+        type_name: &str,
+        method: &'tcx Method,
+        name: &str,
+        imports: &mut BTreeSet<TypeId>,
+    ) -> Option<SmokeTest> {
+        let mut construction = Construction::default();
+        let receiver = match method.param_self {
+            None => None,
+            Some(ref param_self) => {
+                let var = self
+                    .formatter
+                    .fmt_type_qualifier(&self.formatter.fmt_type_name(id));
+                let value = match param_self.ty {
+                    SelfType::Enum(ref e) => {
+                        let def = e.resolve(self.tcx);
+                        self.formatter.fmt_enum_variant(def, def.variants.first()?)
+                    }
+                    _ => {
+                        let (_, call) =
+                            self.values
+                                .gen_constructor_call(id, false, 0, &mut construction)?;
+                        call
+                    }
+                };
+                let var = construction.bind(&var, value);
+                // A consumed opaque is released by Rust
+                if self.values.is_freed(id) && !matches!(param_self.ty, SelfType::BoxedOpaque(..)) {
+                    construction.cleanup.push(var.clone());
+                }
+                Some(var)
+            }
+        };
+        let args = self.values.gen_args(method, 0, &mut construction)?;
+
+        let method_name = self.formatter.fmt_qualified_method_name(method, id);
+        let mut call = match (receiver, &method.attrs.special_method) {
+            (None, _) => format!("{method_name}({args})"),
+            (Some(receiver), Some(SpecialMethod::Indexer)) => format!("{receiver}[{args}]"),
+            (Some(receiver), _) => format!("{receiver}.{method_name}({args})"),
+        };
+        if self.error_style == ErrorStyle::Exn && matches!(method.output, ReturnType::Fallible(..))
+        {
+            // Placeholder arguments may well be rejected, the error is as good as a success
+            call = format!("try {{ {call} }}");
+        }
+
+        // An opaque the method returns has to be released along with the others
+        let call = match method.output {
+            ReturnType::Infallible(SuccessType::OutType(Type::Opaque(ref o)))
+                if !o.is_optional()
+                    && o.as_borrowed().is_none()
+                    && self.values.is_freed(o.tcx_id.into()) =>
+            {
+                let var = construction.bind("result", call);
+                construction.cleanup.push(var);
+                None
+            }
+            _ => Some(call),
+        };
+
+        imports.insert(id);
+        imports.extend(construction.imports);
+
+        Some(SmokeTest {
+            fn_name: self
+                .formatter
+                .fmt_param_name(&format!(
+                    "test_{}_{}",
+                    type_name.to_snake_case(),
+                    method.name
+                ))
+                .into_owned(),
+            method: name.into(),
+            setup: construction.setup,
+            call,
+            cleanup: construction.cleanup,
+        })
+    }
+}
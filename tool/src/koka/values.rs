@@ -0,0 +1,204 @@
+use super::formatter::KokaFormatter;
+use diplomat_core::hir::{
+    self, PrimitiveType, ReturnType, SpecialMethod, SuccessType, TyPosition, Type, TypeContext,
+    TypeId,
+};
+use std::collections::BTreeSet;
+
+/// How many constructors deep the values passed to a constructor may be built
+const MAX_CONSTRUCTION_DEPTH: usize = 3;
+
+/// Builds placeholder values to call the generated bindings with, for the worked examples of
+/// the readme and the smoke tests.
+pub(super) struct ValueGenContext<'a, 'tcx> {
+    pub tcx: &'tcx TypeContext,
+    pub formatter: &'a KokaFormatter<'tcx>,
+    /// The types the bindings were generated for, the only ones values can be built of
+    pub generated: &'a BTreeSet<TypeId>,
+}
+
+/// The values built so far for a call
+#[derive(Default)]
+pub(super) struct Construction {
+    /// The `val` bindings of the values, in construction order
+    pub setup: Vec<String>,
+    /// The opaques constructed in `setup` that have to be released from Koka
+    pub cleanup: Vec<String>,
+    /// The types whose modules the values need imported
+    pub imports: BTreeSet<TypeId>,
+    /// The variables bound so far
+    pub names: BTreeSet<String>,
+}
+
+impl Construction {
+    /// Bind `expr` to a fresh variable named after `name`
+    pub fn bind(&mut self, name: &str, expr: String) -> String {
+        let mut var = name.to_string();
+        let mut i = 2;
+        while !self.names.insert(var.clone()) {
+            var = format!("{name}{i}");
+            i += 1;
+        }
+        self.setup.push(format!("val {var} = {expr}"));
+        var
+    }
+}
+
+impl<'a, 'tcx> ValueGenContext<'a, 'tcx> {
+    /// Whether values of a type are opaques released from Koka, rather than by a parent owning them
+    pub fn is_freed(&self, id: TypeId) -> bool {
+        match id {
+            TypeId::Opaque(o) => !self.tcx.resolve_opaque(o).attrs.parent_managed,
+            _ => false,
+        }
+    }
+
+    /// Picks a constructor of a type whose arguments can all be built, and generates a call to it.
+    ///
+    /// Constructors of values passed to other constructors must not fail.
+    pub fn gen_constructor_call(
+        &self,
+        id: TypeId,
+        allow_fallible: bool,
+        depth: usize,
+        construction: &mut Construction,
+    ) -> Option<(&'tcx hir::Method, String)> {
+        if depth > MAX_CONSTRUCTION_DEPTH || !self.generated.contains(&id) {
+            return None;
+        }
+
+        let mut ctors = self
+            .tcx
+            .resolve_type(id)
+            .methods()
+            .iter()
+            .filter(|m| {
+                !m.attrs.disable
+                    && !m.is_async
+                    && m.param_self.is_none()
+                    && match m.output {
+                        // `Option<Box<Self>>` is returned as a `maybe`
+                        ReturnType::Infallible(SuccessType::OutType(Type::Opaque(ref o)))
+                            if o.is_optional() =>
+                        {
+                            false
+                        }
+                        ReturnType::Infallible(SuccessType::OutType(ref o)) => o.id() == Some(id),
+                        ReturnType::Fallible(SuccessType::OutType(ref o), _) => {
+                            allow_fallible && o.id() == Some(id)
+                        }
+                        _ => false,
+                    }
+            })
+            .collect::<Vec<_>>();
+        // Prefer what the bindings present as constructors, then the simplest signatures
+        ctors.sort_by_key(|m| {
+            let rank = match m.attrs.special_method {
+                Some(SpecialMethod::Constructor) => 0,
+                Some(SpecialMethod::NamedConstructor(..)) => 1,
+                _ => 2,
+            };
+            (rank, m.params.len())
+        });
+
+        ctors.into_iter().find_map(|m| {
+            let args = self.gen_args(m, depth, construction)?;
+            let name = self.formatter.fmt_qualified_method_name(m, id);
+            Some((m, format!("{name}({args})")))
+        })
+    }
+
+    /// Generates the arguments of a call, binding the values they need only if all of them can be built
+    pub fn gen_args(
+        &self,
+        method: &hir::Method,
+        depth: usize,
+        construction: &mut Construction,
+    ) -> Option<String> {
+        let mut attempt = Construction {
+            names: construction.names.clone(),
+            ..Default::default()
+        };
+        let args = method
+            .params
+            .iter()
+            .map(|param| {
+                let name = self.formatter.fmt_param_name(param.name.as_str());
+                self.gen_arg(&param.ty, &name, depth, &mut attempt)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        construction.setup.extend(attempt.setup);
+        construction.cleanup.extend(attempt.cleanup);
+        construction.imports.extend(attempt.imports);
+        construction.names = attempt.names;
+        Some(args.join(", "))
+    }
+
+    /// Generates a placeholder value for a parameter, binding the values it needs constructed
+    pub fn gen_arg<P: TyPosition>(
+        &self,
+        ty: &Type<P>,
+        name: &str,
+        depth: usize,
+        construction: &mut Construction,
+    ) -> Option<String> {
+        Some(match *ty {
+            Type::Primitive(prim) => self.gen_primitive_value(prim).into(),
+            Type::Opaque(ref o) if o.is_optional() => "Nothing".into(),
+            Type::DiplomatOption(..) => "Nothing".into(),
+            Type::Opaque(..) | Type::Struct(..) => {
+                let id = ty.id()?;
+                let (_, call) = self.gen_constructor_call(id, false, depth + 1, construction)?;
+                construction.imports.insert(id);
+                let var = construction.bind(name, call);
+                if self.is_freed(id) {
+                    construction.cleanup.push(var.clone());
+                }
+                var
+            }
+            Type::Enum(ref e) => {
+                let id = e.tcx_id.into();
+                if !self.generated.contains(&id) {
+                    return None;
+                }
+                let def = e.resolve(self.tcx);
+                construction.imports.insert(id);
+                self.formatter.fmt_enum_variant(def, def.variants.first()?)
+            }
+            Type::Slice(hir::Slice::Primitive(Some(borrow), _))
+                if borrow.mutability.is_mutable() =>
+            {
+                "ref(vector())".into()
+            }
+            Type::Slice(hir::Slice::Str(..)) => "\"\"".into(),
+            Type::Slice(hir::Slice::Primitive(
+                _,
+                hir::PrimitiveType::Int(hir::IntType::U8) | hir::PrimitiveType::Byte,
+            )) => "vector/bytes(vector())".into(),
+            Type::Slice(hir::Slice::Primitive(_, p)) => format!(
+                "{}/view(vector())",
+                self.formatter.fmt_primitive_slice_helper(p)
+            ),
+            Type::Slice(hir::Slice::Strs(..)) => "[]".into(),
+            Type::Callback(ref callback) => {
+                let params = vec!["_"; callback.params.len()].join(", ");
+                let output = callback
+                    .output
+                    .map_or("()", |prim| self.gen_primitive_value(prim));
+                format!("fn({params}) {output}")
+            }
+            _ => return None,
+        })
+    }
+
+    fn gen_primitive_value(&self, prim: PrimitiveType) -> &'static str {
+        match prim {
+            PrimitiveType::Bool => "False",
+            PrimitiveType::Char => "' '",
+            PrimitiveType::Byte => "0.int8",
+            PrimitiveType::Float(_) => "0.0",
+            _ => "0",
+        }
+    }
+}
@@ -17,10 +17,49 @@ pub(super) struct KotlinFormatter<'tcx> {
     strip_prefix: Option<String>,
 }
 
-const INVALID_METHOD_NAMES: &[&str] = &[
-    "new", "static", "default", "private", "internal", "toString",
+/// Kotlin hard keywords, plus names that would clash with members every class already has or
+/// with the Java interop layer.
+const RESERVED_WORDS: &[&str] = &[
+    "as",
+    "break",
+    "class",
+    "continue",
+    "default",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "internal",
+    "is",
+    "new",
+    "null",
+    "object",
+    "package",
+    "private",
+    "return",
+    "static",
+    "super",
+    "this",
+    "throw",
+    "toString",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+/// Types the generated code refers to unqualified
+const DISALLOWED_CORE_TYPES: &[&str] = &[
+    "Any", "Array", "Boolean", "Byte", "Char", "Double", "Float", "Int", "List", "Long", "Nothing",
+    "Object", "Short", "String", "UByte", "UInt", "ULong", "UShort", "Unit",
 ];
-const DISALLOWED_CORE_TYPES: &[&str] = &["Object", "String"];
 
 impl<'tcx> KotlinFormatter<'tcx> {
     pub fn new(tcx: &'tcx TypeContext, strip_prefix: Option<String>) -> Self {
@@ -71,12 +110,23 @@ impl<'tcx> KotlinFormatter<'tcx> {
         }
     }
 
-    pub fn fmt_method_name<'a>(&self, method: &'a hir::Method) -> Cow<'a, str> {
-        // TODO(#60): handle other keywords
+    pub fn fmt_primitive_as_c(&self, prim: PrimitiveType) -> Cow<'static, str> {
+        self.c.fmt_primitive_as_c(prim)
+    }
+
+    /// The type of a primitive in the JNA library interfaces. Booleans cross the FFI boundary
+    /// as a byte, which JNA would otherwise pass as a 32-bit int.
+    pub fn fmt_primitive_as_native(&self, prim: PrimitiveType) -> &'static str {
+        match prim {
+            PrimitiveType::Bool => "Byte",
+            _ => self.fmt_primitive_as_ffi(prim),
+        }
+    }
 
+    pub fn fmt_method_name<'a>(&self, method: &'a hir::Method) -> Cow<'a, str> {
         let name = method.name.as_str().to_lower_camel_case();
         let name = method.attrs.rename.apply(name.into());
-        if INVALID_METHOD_NAMES.contains(&&*name) {
+        if RESERVED_WORDS.contains(&&*name) {
             format!("{name}_").into()
         } else {
             name
@@ -84,7 +134,12 @@ impl<'tcx> KotlinFormatter<'tcx> {
     }
 
     pub fn fmt_param_name<'a>(&self, ident: &'a str) -> Cow<'tcx, str> {
-        ident.to_lower_camel_case().into()
+        let name = ident.to_lower_camel_case();
+        if RESERVED_WORDS.contains(&&*name) {
+            format!("{name}_").into()
+        } else {
+            name.into()
+        }
     }
 
     pub fn fmt_borrow<'a>(&self, edge: &LifetimeEdge<'a>) -> Cow<'a, str> {
@@ -93,7 +148,12 @@ impl<'tcx> KotlinFormatter<'tcx> {
             kind: ty,
             ..
         } = edge;
-        let param_name = self.fmt_param_name(param_name).to_string();
+        // Borrows of `self` are recorded under the receiver, which must not be escaped
+        let param_name = if param_name == "this" {
+            param_name.to_string()
+        } else {
+            self.fmt_param_name(param_name).to_string()
+        };
         match ty {
             LifetimeEdgeKind::OpaqueParam => format!("listOf({param_name})").into(),
             LifetimeEdgeKind::SliceParam => format!("listOf({param_name}Mem)").into(),
@@ -249,7 +309,6 @@ impl<'tcx> KotlinFormatter<'tcx> {
         match ty {
             Type::Primitive(prim) => self.fmt_primitive_as_ffi(*prim).into(),
             Type::Opaque(op) => {
-                // todo: optional
                 let optional = if op.is_optional() { "?" } else { "" };
                 format!(
                     "{}{optional}",
@@ -309,11 +368,12 @@ impl<'tcx> KotlinFormatter<'tcx> {
             resolved.name().as_str().into()
         };
 
-        if DISALLOWED_CORE_TYPES.contains(&&*candidate) {
-            panic!("{candidate:?} is not a valid Kotlin type name. Please rename.");
+        let name = resolved.attrs().rename.apply(candidate);
+        if DISALLOWED_CORE_TYPES.contains(&&*name) || RESERVED_WORDS.contains(&&*name) {
+            panic!("{name:?} is not a valid Kotlin type name. Please rename.");
         }
 
-        resolved.attrs().rename.apply(candidate)
+        name
     }
 
     pub fn fmt_nullable(&self, ident: &str) -> String {
@@ -325,7 +385,8 @@ impl<'tcx> KotlinFormatter<'tcx> {
 pub mod test {
     use std::borrow::Cow;
 
-    use super::KotlinFormatter;
+    use super::{KotlinFormatter, DISALLOWED_CORE_TYPES, RESERVED_WORDS};
+    use crate::common::{keyword_tcx, rejects_type_name};
     use diplomat_core::{
         ast::{self},
         hir::{self, TypeContext},
@@ -343,6 +404,7 @@ pub mod test {
         let mut attr_validator = hir::BasicAttributeValidator::new("kotlin_test");
         attr_validator.support.renaming = true;
         attr_validator.support.disabling = true;
+        attr_validator.support.jni = true;
 
         match hir::TypeContext::from_ast(&env, attr_validator) {
             Ok(context) => context,
@@ -398,4 +460,45 @@ pub mod test {
 
         assert_eq!(Cow::from("StringWrapper"), formatter.fmt_type_name(ty_id));
     }
+
+    fn is_valid_ident(name: &str) -> bool {
+        !RESERVED_WORDS.contains(&name)
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        let words: Vec<&str> = RESERVED_WORDS
+            .iter()
+            .chain(DISALLOWED_CORE_TYPES)
+            .copied()
+            .collect();
+        let tcx = keyword_tcx(&words, &words);
+        let formatter = KotlinFormatter::new(&tcx, None);
+
+        let methods = &tcx
+            .opaques()
+            .iter()
+            .find(|ty| ty.name.as_str() == "KeywordHost")
+            .unwrap()
+            .methods;
+        assert_eq!(methods.len(), words.len());
+        for method in methods {
+            let name = formatter.fmt_method_name(method);
+            assert!(is_valid_ident(&name), "method name {name:?}");
+        }
+        for word in &words {
+            let name = formatter.fmt_param_name(word);
+            assert!(is_valid_ident(&name), "param name {name:?}");
+            let name = formatter.fmt_field_name(word);
+            assert!(is_valid_ident(&name), "field name {name:?}");
+        }
+
+        for (id, ty) in tcx.all_types() {
+            if ty.name().as_str() != "KeywordHost" {
+                assert!(rejects_type_name(|| formatter.fmt_type_name(id)));
+            }
+        }
+    }
 }
@@ -0,0 +1,325 @@
+//! The JNI glue of methods marked `jni`, which are called through a generated C function
+//! and a Kotlin `external fun` instead of JNA, skipping its reflective dispatch.
+//!
+//! Only methods whose receiver, parameters and result cross the boundary as JVM primitives,
+//! i.e. primitives, enums and opaques, get glue. Other ones are still called through JNA.
+
+use super::TyGenContext;
+use askama::Template;
+use diplomat_core::hir::{
+    self, IntType, Method, OpaqueOwner, PrimitiveType, ReturnType, SelfType, SuccessType,
+    TyPosition, Type, TypeId,
+};
+use std::borrow::Cow;
+
+/// The C file with the glue of every `jni` method, relative to the project root
+pub(super) const GLUE_FILE: &str = "src/main/c/jni.c";
+
+/// A method called through JNI
+pub(super) struct JniMethod {
+    /// The name of the `external fun`, which is that of the C function it calls
+    c_name: String,
+    /// The Kotlin object declaring the `external fun`
+    pub(super) object_name: String,
+    /// The receiver, if any, followed by the parameters
+    params: Vec<JniType>,
+    /// The result, `None` for `Unit`
+    output: Option<JniType>,
+}
+
+/// How a value crosses the boundary
+#[derive(Clone, Copy)]
+pub(super) enum JniType {
+    /// A primitive, passed as the signed JVM type of the same width
+    Primitive(PrimitiveType),
+    /// An opaque, passed as the address of the Rust object, `0` for `None`
+    Opaque { optional: bool },
+    /// An enum, passed as its discriminant
+    Enum,
+}
+
+impl JniType {
+    fn of<P: TyPosition>(ty: &Type<P>) -> Option<Self> {
+        Some(match ty {
+            Type::Primitive(prim) => JniType::Primitive(*prim),
+            // Not supported by the JNA bindings either
+            Type::Opaque(op) if op.owner.mutability() == Some(hir::Mutability::Mutable) => {
+                return None
+            }
+            Type::Opaque(op) => JniType::Opaque {
+                optional: op.is_optional(),
+            },
+            Type::Enum(_) => JniType::Enum,
+            _ => return None,
+        })
+    }
+
+    /// The type of the `external fun` parameter or result
+    fn kotlin(self) -> &'static str {
+        match self {
+            JniType::Primitive(PrimitiveType::Bool) => "Byte",
+            JniType::Primitive(PrimitiveType::Int(IntType::U8)) => "Byte",
+            JniType::Primitive(PrimitiveType::Int(IntType::U16)) => "Short",
+            JniType::Primitive(PrimitiveType::Int(IntType::U32)) => "Int",
+            JniType::Primitive(PrimitiveType::Int(IntType::U64)) => "Long",
+            JniType::Primitive(PrimitiveType::Char) => "Int",
+            JniType::Primitive(PrimitiveType::Int(IntType::I8) | PrimitiveType::Byte) => "Byte",
+            JniType::Primitive(PrimitiveType::Int(IntType::I16)) => "Short",
+            JniType::Primitive(PrimitiveType::Int(IntType::I32)) => "Int",
+            JniType::Primitive(PrimitiveType::Int(IntType::I64) | PrimitiveType::IntSize(_)) => {
+                "Long"
+            }
+            JniType::Primitive(PrimitiveType::Float(hir::FloatType::F32)) => "Float",
+            JniType::Primitive(PrimitiveType::Float(hir::FloatType::F64)) => "Double",
+            JniType::Primitive(PrimitiveType::Int128(_)) => panic!("i128 not supported in Kotlin"),
+            JniType::Opaque { .. } => "Long",
+            JniType::Enum => "Int",
+        }
+    }
+
+    /// The type of the glue function parameter or result
+    fn jni(self) -> &'static str {
+        match self.kotlin() {
+            "Byte" => "jbyte",
+            "Short" => "jshort",
+            "Int" => "jint",
+            "Long" => "jlong",
+            "Float" => "jfloat",
+            _ => "jdouble",
+        }
+    }
+
+    /// Turns the value JNA would be passed into the one the `external fun` takes
+    fn wrap_arg(self, kotlin: &str) -> String {
+        match self {
+            JniType::Opaque { .. } => format!("com.sun.jna.Pointer.nativeValue({kotlin})"),
+            JniType::Primitive(PrimitiveType::Int(
+                IntType::U8 | IntType::U16 | IntType::U32 | IntType::U64,
+            )) => format!("{kotlin}.to{}()", self.kotlin()),
+            _ => kotlin.into(),
+        }
+    }
+
+    /// Turns the result of the `external fun` into the one JNA would return
+    fn wrap_result(self) -> &'static str {
+        match self {
+            JniType::Opaque { optional: false } => ".let { com.sun.jna.Pointer(it) }",
+            JniType::Opaque { optional: true } => {
+                ".let { if (it == 0L) null else com.sun.jna.Pointer(it) }"
+            }
+            JniType::Primitive(PrimitiveType::Int(IntType::U8)) => ".toUByte()",
+            JniType::Primitive(PrimitiveType::Int(IntType::U16)) => ".toUShort()",
+            JniType::Primitive(PrimitiveType::Int(IntType::U32)) => ".toUInt()",
+            JniType::Primitive(PrimitiveType::Int(IntType::U64)) => ".toULong()",
+            _ => "",
+        }
+    }
+}
+
+/// A glue function, as rendered into [`GLUE_FILE`]
+struct GlueFunction<'a> {
+    /// The exported name of the function, as JNI looks it up
+    symbol: String,
+    c_name: &'a str,
+    /// The C type the function declared by Diplomat returns
+    c_output: Cow<'a, str>,
+    /// The C types of its parameters
+    c_params: Vec<Cow<'a, str>>,
+    jni_output: &'static str,
+    /// The glue parameters, after the environment and class
+    jni_params: Vec<String>,
+    /// The C result turned into the glue one
+    result: String,
+}
+
+#[derive(Template)]
+#[template(path = "kotlin/jni.c.jinja", escape = "none")]
+struct GlueTemplate<'a> {
+    functions: Vec<GlueFunction<'a>>,
+}
+
+#[derive(Template)]
+#[template(path = "kotlin/Jni.kt.jinja", escape = "none")]
+struct ExternsTemplate<'a> {
+    domain: &'a str,
+    lib_name: &'a str,
+    object_name: &'a str,
+    /// The `external fun` declarations
+    externs: Vec<String>,
+}
+
+impl<'a, 'cx> TyGenContext<'a, 'cx> {
+    /// The JNI signature of a method marked `jni`, if it can be called through JNI
+    pub(super) fn gen_jni_method(&self, id: TypeId, method: &Method) -> Option<JniMethod> {
+        let mut params = Vec::new();
+        match method.param_self.as_ref().map(|s| &s.ty) {
+            None => {}
+            Some(SelfType::Opaque(op)) if op.owner.mutability == hir::Mutability::Immutable => {
+                params.push(JniType::Opaque { optional: false })
+            }
+            Some(SelfType::Enum(_)) => params.push(JniType::Enum),
+            Some(_) => return None,
+        }
+        for param in &method.params {
+            params.push(JniType::of(&param.ty)?);
+        }
+        let output = match method.output {
+            ReturnType::Infallible(SuccessType::Unit) => None,
+            ReturnType::Infallible(SuccessType::OutType(ref o)) => Some(JniType::of(o)?),
+            ReturnType::Nullable(SuccessType::OutType(Type::Opaque(ref o)))
+                if o.owner.mutability() != Some(hir::Mutability::Mutable) =>
+            {
+                Some(JniType::Opaque { optional: true })
+            }
+            _ => return None,
+        };
+
+        Some(JniMethod {
+            c_name: self.formatter.fmt_c_method_name(id, method).into_owned(),
+            object_name: format!("{}Jni", self.formatter.fmt_type_name(id)),
+            params,
+            output,
+        })
+    }
+}
+
+impl JniMethod {
+    /// The call of the `external fun` replacing that of the JNA one, from the arguments
+    /// JNA would be passed
+    pub(super) fn gen_call(&self, jna_args: &[Cow<str>]) -> String {
+        let args = self
+            .params
+            .iter()
+            .zip(jna_args)
+            .map(|(ty, arg)| ty.wrap_arg(arg))
+            .collect::<Vec<_>>();
+        format!(
+            "{}.{}({}){}",
+            self.object_name,
+            self.c_name,
+            args.join(", "),
+            self.output.map_or("", JniType::wrap_result)
+        )
+    }
+
+    fn gen_extern(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("p{i}: {}", ty.kotlin()))
+            .collect::<Vec<_>>();
+        format!(
+            "@JvmStatic external fun {}({}): {}",
+            self.c_name,
+            params.join(", "),
+            self.output.map_or("Unit", JniType::kotlin)
+        )
+    }
+
+    fn gen_glue<'a>(&'a self, tcx: &TyGenContext, package: &str) -> GlueFunction<'a> {
+        let c_type = |ty: JniType| -> Cow<'a, str> {
+            match ty {
+                JniType::Primitive(prim) => tcx.formatter.fmt_primitive_as_c(prim),
+                JniType::Opaque { .. } => "void*".into(),
+                JniType::Enum => "int32_t".into(),
+            }
+        };
+        let args = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| match ty {
+                JniType::Opaque { .. } => format!("(void*)(intptr_t)p{i}"),
+                _ => format!("({})p{i}", c_type(*ty)),
+            })
+            .collect::<Vec<_>>();
+        let call = format!("{}({})", self.c_name, args.join(", "));
+        GlueFunction {
+            symbol: format!(
+                "Java_{}_{}_{}",
+                jni_mangle(package),
+                jni_mangle(&self.object_name),
+                jni_mangle(&self.c_name)
+            ),
+            c_name: &self.c_name,
+            c_output: self.output.map_or("void".into(), c_type),
+            c_params: self.params.iter().map(|ty| c_type(*ty)).collect(),
+            jni_output: self.output.map_or("void", JniType::jni),
+            jni_params: self
+                .params
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| format!("{} p{i}", ty.jni()))
+                .collect(),
+            result: match self.output {
+                None => call,
+                Some(JniType::Opaque { .. }) => format!("return (jlong)(intptr_t){call}"),
+                Some(ty) => format!("return ({}){call}", ty.jni()),
+            },
+        }
+    }
+}
+
+/// Renders the Kotlin object with the `external fun`s of the `jni` methods of a type
+pub(super) fn gen_externs(methods: &[JniMethod], domain: &str, lib_name: &str) -> (String, String) {
+    let object_name = &methods[0].object_name;
+    let body = ExternsTemplate {
+        domain,
+        lib_name,
+        object_name,
+        externs: methods.iter().map(JniMethod::gen_extern).collect(),
+    }
+    .render()
+    .expect("Failed to render JNI externs");
+    (
+        format!("{}/{lib_name}/{object_name}.kt", domain.replace('.', "/")),
+        body,
+    )
+}
+
+/// Renders the C glue of every `jni` method
+pub(super) fn gen_glue(
+    methods: &[JniMethod],
+    tcx: &TyGenContext,
+    domain: &str,
+    lib_name: &str,
+) -> String {
+    let package = format!("{domain}.{lib_name}");
+    GlueTemplate {
+        functions: methods.iter().map(|m| m.gen_glue(tcx, &package)).collect(),
+    }
+    .render()
+    .expect("Failed to render JNI glue")
+}
+
+/// Escapes a qualified name for a JNI symbol: `_` becomes `_1`, the `.` between packages `_`,
+/// and characters that aren't ASCII alphanumerics their UTF-16 code units as `_0xxxx`
+fn jni_mangle(name: &str) -> String {
+    let mut mangled = String::new();
+    for c in name.chars() {
+        match c {
+            '.' => mangled.push('_'),
+            '_' => mangled.push_str("_1"),
+            c if c.is_ascii_alphanumeric() => mangled.push(c),
+            c => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    mangled.push_str(&format!("_0{unit:04x}"));
+                }
+            }
+        }
+    }
+    mangled
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_jni_mangle() {
+        assert_eq!(
+            super::jni_mangle("dev.gigapixel.some_lib"),
+            "dev_gigapixel_some_1lib"
+        );
+        assert_eq!(super::jni_mangle("Größe"), "Gr_000f6_000dfe");
+    }
+}
@@ -9,22 +9,36 @@ use diplomat_core::hir::{ReturnType, SuccessType};
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::iter::once;
 use std::path::Path;
 
 mod formatter;
 use formatter::KotlinFormatter;
+mod jni;
 
-use crate::common::{ErrorStore, FileMap};
+use crate::common::{ErrorStore, FileMap, NameRegistry};
 use serde::{Deserialize, Serialize};
 
+/// Identifiers declared by `Lib.kt` in the package of the library
+const RUNTIME_NAMES: &[&str] = &[
+    "CLEANER",
+    "DiplomatWriteableLib",
+    "DW",
+    "PrimitiveArrayTools",
+    "Slice",
+];
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 struct KotlinConfig {
     domain: String,
     lib_name: String,
 }
 
-pub fn run(tcx: &TypeContext, conf_path: Option<&Path>) -> FileMap {
+pub fn run<'cx>(
+    tcx: &'cx TypeContext,
+    conf_path: Option<&Path>,
+) -> (FileMap, Vec<(impl Display + 'cx, String)>) {
     let conf_path = conf_path.expect("Kotlin library needs to be called with config");
 
     let conf_str = std::fs::read_to_string(conf_path)
@@ -37,17 +51,45 @@ pub fn run(tcx: &TypeContext, conf_path: Option<&Path>) -> FileMap {
     let files = FileMap::default();
     let errors = ErrorStore::default();
 
+    let names = NameRegistry::default();
+    for name in RUNTIME_NAMES {
+        // The runtime names are distinct, this cannot fail
+        let _ = names.reserve(name, "the Diplomat runtime");
+    }
+    let reserve_name = |name: String, owner: String| {
+        if let Err(e) = names.reserve(&name, owner) {
+            errors.push_error(e);
+        }
+    };
+
     let mut ty_gen_cx = TyGenContext {
         tcx,
         errors: &errors,
         formatter: &formatter,
+        jni_methods: Vec::new(),
     };
+    let mut jni_methods = Vec::new();
 
     for (id, ty) in tcx.all_types() {
-        let _guard = ty_gen_cx.errors.set_context_ty(ty.name().as_str().into());
+        let _guard = ty_gen_cx
+            .errors
+            .set_context_ty(ty.name().as_str().into())
+            .with_span(ty.span());
         if ty.attrs().disable {
             continue;
         }
+        let type_name = ty.name().as_str();
+        reserve_name(type_name.into(), format!("type `{type_name}`"));
+        reserve_name(
+            format!("{type_name}Lib"),
+            format!("the native library of `{type_name}`"),
+        );
+        if let TypeDef::Struct(..) | TypeDef::OutStruct(..) = ty {
+            reserve_name(
+                format!("{type_name}Native"),
+                format!("the native struct of `{type_name}`"),
+            );
+        }
         if let TypeDef::Opaque(o) = ty {
             let type_name = o.name.to_string();
 
@@ -81,6 +123,26 @@ pub fn run(tcx: &TypeContext, conf_path: Option<&Path>) -> FileMap {
 
             files.add_file(format!("src/main/kotlin/{file_name}"), body);
         }
+
+        let type_jni_methods = std::mem::take(&mut ty_gen_cx.jni_methods);
+        if let Some(jni) = type_jni_methods.first() {
+            reserve_name(
+                jni.object_name.clone(),
+                format!("the JNI bindings of `{type_name}`"),
+            );
+        }
+        if !type_jni_methods.is_empty() {
+            let (file_name, body) = jni::gen_externs(&type_jni_methods, &domain, &lib_name);
+            files.add_file(format!("src/main/kotlin/{file_name}"), body);
+            jni_methods.extend(type_jni_methods);
+        }
+    }
+
+    if !jni_methods.is_empty() {
+        files.add_file(
+            jni::GLUE_FILE.into(),
+            jni::gen_glue(&jni_methods, &ty_gen_cx, &domain, &lib_name),
+        );
     }
 
     #[derive(Template)]
@@ -134,13 +196,15 @@ pub fn run(tcx: &TypeContext, conf_path: Option<&Path>) -> FileMap {
         init,
     );
 
-    files
+    (files, errors.take_all())
 }
 
 struct TyGenContext<'a, 'cx> {
     tcx: &'cx TypeContext,
     formatter: &'a KotlinFormatter<'cx>,
     errors: &'a ErrorStore<'cx, String>,
+    /// The methods of the current type called through JNI
+    jni_methods: Vec<jni::JniMethod>,
 }
 
 impl<'a, 'cx> TyGenContext<'a, 'cx> {
@@ -174,6 +238,9 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
     fn gen_kt_to_c_for_type(&self, ty: &Type, name: Cow<'cx, str>) -> Cow<'cx, str> {
         match *ty {
+            Type::Primitive(hir::PrimitiveType::Bool) => {
+                format!("if ({name}) 1.toByte() else 0.toByte()").into()
+            }
             Type::Primitive(_) => name,
             Type::Opaque(ref op @ OpaquePath { owner, .. }) => {
                 let optional = if op.is_optional() { "?" } else { "" };
@@ -213,7 +280,7 @@ impl<'a, 'cx> TyGenContext<'a, 'cx> {
 
     fn gen_type_name_ffi<P: TyPosition>(&self, ty: &Type<P>) -> Cow<'cx, str> {
         match *ty {
-            Type::Primitive(prim) => self.formatter.fmt_primitive_as_ffi(prim).into(),
+            Type::Primitive(prim) => self.formatter.fmt_primitive_as_native(prim).into(),
             Type::Opaque(ref op) => {
                 let optional = if op.is_optional() { "?" } else { "" };
                 format!("Pointer{optional}").into()
@@ -420,6 +487,9 @@ return string"#
         match res {
             SuccessType::Writeable => Some(Self::WRITEABLE_RETURN.into()),
             SuccessType::OutType(o) => match o {
+                Type::Primitive(hir::PrimitiveType::Bool) => {
+                    Some("    return returnVal > 0".into())
+                }
                 // todo: unsigned need to be handled
                 Type::Primitive(_) => Some("    return returnVal".into()),
                 Type::Opaque(opaque_path) => Some(self.gen_opaque_return(
@@ -613,6 +683,17 @@ if (returnVal == null) {{
             self.formatter.fmt_method_name(method),
             params
         );
+        let jni_call = method
+            .attrs
+            .jni
+            .then(|| self.gen_jni_method(id, method))
+            .flatten()
+            .map(|jni| {
+                let call = jni.gen_call(&param_conversions);
+                self.jni_methods.push(jni);
+                call
+            });
+
         let method_lifetimes_map = visitor.borrow_map();
         let return_expression = self
             .gen_return(method, method_lifetimes_map, cleanups.as_ref())
@@ -623,6 +704,7 @@ if (returnVal == null) {{
             declaration,
             native_method_name,
             param_conversions,
+            jni_call,
             return_expression,
             writeable_return,
             slice_conversions,
@@ -669,6 +751,39 @@ if (returnVal == null) {{
         }
     }
 
+    /// Reserves the names declared in the scope of a class and in its `Lib` interface,
+    /// the `properties`, `functions` and `bindings` generated for it and its methods, reporting
+    /// those that collide. Kotlin keeps properties apart from functions.
+    fn check_member_names(
+        &self,
+        id: TypeId,
+        methods: &[Method],
+        mut properties: Vec<(String, String)>,
+        mut functions: Vec<(String, String)>,
+        mut bindings: Vec<(String, String)>,
+    ) {
+        properties.push(("libClass".into(), "the native library".into()));
+        properties.push(("lib".into(), "the native library".into()));
+        for method in methods {
+            functions.push((
+                self.formatter.fmt_method_name(method).into_owned(),
+                format!("method `{}`", method.name),
+            ));
+            bindings.push((
+                self.formatter.fmt_c_method_name(id, method).into_owned(),
+                format!("the binding of method `{}`", method.name),
+            ));
+        }
+        for members in [properties, functions, bindings] {
+            let names = NameRegistry::default();
+            for (name, owner) in members {
+                if let Err(e) = names.reserve(&name, owner) {
+                    self.errors.push_error(e);
+                }
+            }
+        }
+    }
+
     fn gen_opaque_def(
         &mut self,
         ty: &'cx hir::OpaqueDef,
@@ -711,7 +826,17 @@ if (returnVal == null) {{
                 MaybeStatic::NonStatic(lt) => Some(lt),
             })
             .map(|lt| ty.lifetimes.fmt_lifetime(lt))
-            .collect();
+            .collect::<Vec<_>>();
+
+        let mut properties = vec![
+            ("handle".into(), "the native handle".into()),
+            ("selfEdges".into(), "the lifetime edges".into()),
+        ];
+        for lt in &lifetimes {
+            properties.push((format!("{lt}Edges"), "the lifetime edges".into()));
+        }
+        let bindings = vec![(format!("{type_name}_destroy"), "the destructor".into())];
+        self.check_member_names(id, &ty.methods, properties, Vec::new(), bindings);
 
         #[derive(Template)]
         #[template(path = "kotlin/Opaque.kt.jinja", escape = "none")]
@@ -783,7 +908,24 @@ if (returnVal == null) {{
                 MaybeStatic::NonStatic(lt) => Some(lt),
             })
             .map(|lt| ty.lifetimes.fmt_lifetime(lt))
-            .collect();
+            .collect::<Vec<_>>();
+
+        let mut properties = vec![
+            ("nativeStruct".into(), "the native struct".into()),
+            ("NATIVESIZE".into(), "the size of the native struct".into()),
+        ];
+        for lt in &lifetimes {
+            properties.push((format!("{lt}Edges"), "the lifetime edges".into()));
+        }
+        for field in &ty.fields {
+            properties.push((
+                self.formatter
+                    .fmt_field_name(field.name.as_str())
+                    .into_owned(),
+                format!("field `{}`", field.name),
+            ));
+        }
+        self.check_member_names(id, &ty.methods, properties, Vec::new(), Vec::new());
 
         struct StructFieldDef<'d> {
             name: Cow<'d, str>,
@@ -941,6 +1083,22 @@ if (returnVal == null) {{
 
         let variants = EnumVariants::new(ty);
 
+        let mut properties = Vec::new();
+        if let EnumVariants::NonContiguous(..) = variants {
+            properties.push(("inner".into(), "the native value".into()));
+        }
+        for variant in &ty.variants {
+            properties.push((
+                variant.name.to_string(),
+                format!("variant `{}`", variant.name),
+            ));
+        }
+        let functions = ["toNative", "fromNative", "default"]
+            .into_iter()
+            .map(|name| (name.into(), "the native conversions".into()))
+            .collect();
+        self.check_member_names(id, &ty.methods, properties, functions, Vec::new());
+
         let enum_def = EnumDef {
             lib_name: lib_name.into(),
             domain: domain.into(),
@@ -961,7 +1119,7 @@ if (returnVal == null) {{
 
     fn gen_native_type_name<P: TyPosition>(&self, ty: &Type<P>) -> Cow<'cx, str> {
         match *ty {
-            Type::Primitive(prim) => self.formatter.fmt_primitive_as_ffi(prim).into(),
+            Type::Primitive(prim) => self.formatter.fmt_primitive_as_native(prim).into(),
             Type::Opaque(ref op) => {
                 let optional = if op.is_optional() { "?" } else { "" };
                 format!("Pointer{optional}").into()
@@ -1024,6 +1182,8 @@ struct MethodTpl<'a> {
 
     /// Conversion code for each parameter
     param_conversions: Vec<Cow<'a, str>>,
+    /// The call through JNI replacing the JNA one, for methods marked `jni`
+    jni_call: Option<String>,
     return_expression: Option<Cow<'a, str>>,
     writeable_return: bool,
     slice_conversions: Vec<Cow<'a, str>>,
@@ -1042,7 +1202,7 @@ mod test {
     use crate::common::ErrorStore;
 
     use super::formatter::test::new_tcx;
-    use super::{formatter::KotlinFormatter, TyGenContext};
+    use super::{formatter::KotlinFormatter, jni, TyGenContext};
 
     #[test]
     fn test_enum() {
@@ -1090,6 +1250,7 @@ mod test {
                 tcx: &tcx,
                 formatter: &formatter,
                 errors: &error_store,
+                jni_methods: Vec::new(),
             };
             let type_name = enum_def.name.to_string();
             // test that we can render and that it doesn't panic
@@ -1152,6 +1313,7 @@ mod test {
                 tcx: &tcx,
                 formatter: &formatter,
                 errors: &error_store,
+                jni_methods: Vec::new(),
             };
             let type_name = strct.name.to_string();
             // test that we can render and that it doesn't panic
@@ -1255,6 +1417,7 @@ mod test {
                 tcx: &tcx,
                 formatter: &formatter,
                 errors: &eror_store,
+                jni_methods: Vec::new(),
             };
             let type_name = opaque_def.name.to_string();
             // test that we can render and that it doesn't panic
@@ -1268,4 +1431,173 @@ mod test {
             insta::assert_snapshot!(result)
         }
     }
+
+    #[test]
+    fn test_jni() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Counter(u32);
+
+                impl Counter {
+                    #[diplomat::attr(*, jni)]
+                    pub fn new(start: u32, strict: bool) -> Box<Counter> {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(*, jni)]
+                    pub fn count(&self) -> u32 {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(*, jni)]
+                    pub fn find(&self, other: Option<&Counter>) -> Option<Box<Counter>> {
+                        unimplemented!()
+                    }
+
+                    #[diplomat::attr(*, jni)]
+                    pub fn describe(&self, out: &mut DiplomatWriteable) {
+                        unimplemented!()
+                    }
+
+                    pub fn reset(&self) {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let tcx = new_tcx(tk_stream);
+        let mut all_types = tcx.all_types();
+        if let (type_id, TypeDef::Opaque(opaque_def)) = all_types
+            .next()
+            .expect("Failed to generate first opaque def")
+        {
+            let error_store = ErrorStore::default();
+            let formatter = KotlinFormatter::new(&tcx, None);
+            let mut ty_gen_cx = TyGenContext {
+                tcx: &tcx,
+                formatter: &formatter,
+                errors: &error_store,
+                jni_methods: Vec::new(),
+            };
+            let type_name = opaque_def.name.to_string();
+            let (_, result) = ty_gen_cx.gen_opaque_def(
+                opaque_def,
+                type_id,
+                &type_name,
+                "dev.gigapixel",
+                "somelib",
+            );
+            // Writeables can't cross JNI as a primitive, so `describe` stays on JNA
+            assert_eq!(ty_gen_cx.jni_methods.len(), 3);
+            assert!(result.contains("val returnVal = CounterJni.Counter_count(com.sun.jna.Pointer.nativeValue(handle)).toUInt();"));
+            assert!(result.contains("val returnVal = lib.Counter_describe(handle, writeable);"));
+            assert!(result.contains("val returnVal = lib.Counter_reset(handle);"));
+
+            let jni_methods = std::mem::take(&mut ty_gen_cx.jni_methods);
+            let (file_name, externs) = jni::gen_externs(&jni_methods, "dev.gigapixel", "somelib");
+            assert_eq!(file_name, "dev/gigapixel/somelib/CounterJni.kt");
+            insta::assert_snapshot!(externs);
+            insta::assert_snapshot!(jni::gen_glue(
+                &jni_methods,
+                &ty_gen_cx,
+                "dev.gigapixel",
+                "somelib"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_nullability() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Opaque;
+
+                impl Opaque {
+                    pub fn find(&self, other: Option<&Opaque>) -> Option<Box<Opaque>> {
+                        unimplemented!()
+                    }
+
+                    pub fn get(&self) -> Box<Opaque> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let tcx = new_tcx(tk_stream);
+        let mut all_types = tcx.all_types();
+        if let (type_id, TypeDef::Opaque(opaque_def)) = all_types
+            .next()
+            .expect("Failed to generate first opaque def")
+        {
+            let error_store = ErrorStore::default();
+            let formatter = KotlinFormatter::new(&tcx, None);
+            let mut ty_gen_cx = TyGenContext {
+                tcx: &tcx,
+                formatter: &formatter,
+                errors: &error_store,
+                jni_methods: Vec::new(),
+            };
+            let type_name = opaque_def.name.to_string();
+            let (_, result) = ty_gen_cx.gen_opaque_def(
+                opaque_def,
+                type_id,
+                &type_name,
+                "dev.gigapixel",
+                "somelib",
+            );
+            // Optionality comes from the HIR on both sides of JNA, and handles read from native
+            // code are never left to be inferred as platform types
+            assert!(result.contains("fun Opaque_find(handle: Pointer, other: Pointer?): Pointer?"));
+            assert!(result.contains("fun find(other: Opaque?): Opaque?"));
+            assert!(result.contains("fun get(): Opaque"));
+            assert!(!result.contains("val handle ="));
+        }
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Handle {
+                    native_struct: u32,
+                }
+            }
+        };
+
+        let tcx = new_tcx(tk_stream);
+        let mut all_types = tcx.all_types();
+        if let (type_id, TypeDef::Struct(strct)) = all_types
+            .next()
+            .expect("Failed to generate first struct def")
+        {
+            let error_store = ErrorStore::default();
+            let formatter = KotlinFormatter::new(&tcx, None);
+            let mut ty_gen_cx = TyGenContext {
+                tcx: &tcx,
+                formatter: &formatter,
+                errors: &error_store,
+                jni_methods: Vec::new(),
+            };
+            let type_name = strct.name.to_string();
+            ty_gen_cx.gen_struct_def(strct, type_id, &type_name, "dev.gigapixel", "somelib");
+            let errors = error_store
+                .take_all()
+                .into_iter()
+                .map(|(_, e)| e)
+                .collect::<Vec<_>>();
+            // Fields are named in camel case
+            assert_eq!(
+                errors,
+                [
+                    "Identifier `nativeStruct` generated for field `native_struct` collides with \
+                  the native struct, please rename one of them"
+                ]
+            );
+        }
+    }
 }
@@ -1,24 +1,76 @@
 use core::panic;
 use std::alloc::Layout;
 use std::cmp::max;
+use std::collections::HashSet;
 
 use diplomat_core::ast::{self, PrimitiveType, TypeName};
 use diplomat_core::Env;
 
-// TODO(#58): support non-32-bit platforms
-use u32 as usize_target;
+/// The width of pointers, and so of `usize` and `isize`, on the target the bindings are for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerWidth {
+    /// wasm32 and 32-bit platforms such as armv7 or i686
+    Bits32,
+    /// wasm64 and 64-bit platforms
+    Bits64,
+}
+
+impl PointerWidth {
+    /// The pointer width of a target triple, or `None` for an architecture we do not know.
+    pub fn of_target(triple: &str) -> Option<Self> {
+        const ARCHS_32: &[&str] = &[
+            "wasm32", "i386", "i586", "i686", "arm", "thumb", "riscv32", "mips", "powerpc",
+            "sparc", "hexagon", "csky", "xtensa", "m68k",
+        ];
+        let arch = triple.split('-').next().unwrap_or_default();
+        // Note: checked before the 32-bit prefixes, e.g. `mips64` starts with `mips`, except
+        // for the ILP32 ABI of `arm64_32`
+        if arch.contains("64") && !arch.ends_with("_32") || arch == "s390x" {
+            Some(PointerWidth::Bits64)
+        } else if ARCHS_32.iter().any(|prefix| arch.starts_with(prefix)) {
+            Some(PointerWidth::Bits32)
+        } else {
+            None
+        }
+    }
+
+    /// The number of bits in a pointer.
+    pub fn bits(self) -> u32 {
+        match self {
+            PointerWidth::Bits32 => 32,
+            PointerWidth::Bits64 => 64,
+        }
+    }
+
+    /// The layout of pointers and of `usize` and `isize`.
+    pub fn layout(self) -> Layout {
+        match self {
+            PointerWidth::Bits32 => Layout::new::<u32>(),
+            PointerWidth::Bits64 => Layout::new::<u64>(),
+        }
+    }
+
+    /// The layout of the pointer and length of a slice.
+    fn slice_layout(self) -> Layout {
+        match self {
+            PointerWidth::Bits32 => Layout::new::<(u32, u32)>(),
+            PointerWidth::Bits64 => Layout::new::<(u64, u64)>(),
+        }
+    }
+}
 
 pub fn struct_offsets_size_max_align<'a>(
     type_names: impl Iterator<Item = &'a ast::TypeName>,
     in_path: &ast::Path,
     env: &Env,
+    width: PointerWidth,
 ) -> (Vec<usize>, Layout) {
     let mut max_align = 0;
     let mut next_offset = 0;
     let mut offsets = vec![];
 
     for typ in type_names {
-        let size_align = type_size_alignment(typ, in_path, env);
+        let size_align = type_size_alignment(typ, in_path, env, width);
         let size = size_align.size();
         let align = size_align.align();
         max_align = max(max_align, align);
@@ -39,9 +91,10 @@ pub fn result_ok_offset_size_align(
     err: &TypeName,
     in_path: &ast::Path,
     env: &Env,
+    width: PointerWidth,
 ) -> (usize, Layout) {
-    let ok_size_align = type_size_alignment(ok, in_path, env);
-    let err_size_align = type_size_alignment(err, in_path, env);
+    let ok_size_align = type_size_alignment(ok, in_path, env, width);
+    let err_size_align = type_size_alignment(err, in_path, env, width);
     let (offsets, size_max_align) = struct_offsets_size_max_align(
         [
             if ok_size_align.size() > err_size_align.size() {
@@ -54,27 +107,33 @@ pub fn result_ok_offset_size_align(
         .iter(),
         in_path,
         env,
+        width,
     );
     (offsets[1], size_max_align)
 }
 
-pub fn type_size_alignment(typ: &ast::TypeName, in_path: &ast::Path, env: &Env) -> Layout {
+pub fn type_size_alignment(
+    typ: &ast::TypeName,
+    in_path: &ast::Path,
+    env: &Env,
+    width: PointerWidth,
+) -> Layout {
     match typ {
-        // TODO(#58): support non-32-bit platforms
-        // Actual:
-        // ast::TypeName::Box(_) => Layout::new::<Box<()>>(),
-        // ast::TypeName::Reference(_, _) => Layout::new::<&()>(),
-        // Temporary:
-        ast::TypeName::Box(_) => Layout::new::<usize_target>(),
-        ast::TypeName::Reference(_, _mut, _lt) => Layout::new::<usize_target>(),
+        ast::TypeName::Box(_) => width.layout(),
+        ast::TypeName::Reference(_, _mut, _lt) => width.layout(),
         ast::TypeName::Option(underlying) => match underlying.as_ref() {
             ast::TypeName::Box(_) | ast::TypeName::Reference(..) => {
-                type_size_alignment(underlying, in_path, env)
+                type_size_alignment(underlying, in_path, env, width)
             }
             _ => unreachable!("Cannot have non-pointer types inside Option"),
         },
         ast::TypeName::Result(ok, err, _) => {
-            let (_, size_align) = result_ok_offset_size_align(ok, err, in_path, env);
+            let (_, size_align) = result_ok_offset_size_align(ok, err, in_path, env, width);
+            size_align
+        }
+        ast::TypeName::DiplomatOption(underlying) => {
+            let (_, size_align) =
+                result_ok_offset_size_align(underlying, &ast::TypeName::Unit, in_path, env, width);
             size_align
         }
         ast::TypeName::Named(path_type) | ast::TypeName::SelfType(path_type) => {
@@ -84,13 +143,14 @@ pub fn type_size_alignment(typ: &ast::TypeName, in_path: &ast::Path, env: &Env)
                         strct.fields.iter().map(|(_, typ, _)| typ),
                         &struct_path,
                         env,
+                        width,
                     );
                     size_max_align
                 }
 
                 (_, ast::CustomType::Enum(_)) => {
                     // repr(C) fieldless enums use the default platform representation: isize
-                    Layout::new::<usize_target>()
+                    width.layout()
                 }
 
                 (_, ast::CustomType::Opaque(_)) => {
@@ -99,21 +159,16 @@ pub fn type_size_alignment(typ: &ast::TypeName, in_path: &ast::Path, env: &Env)
                 (_, &_) => unreachable!("unknown AST/HIR variant"),
             }
         }
-        ast::TypeName::Primitive(p) => primitive_size_alignment(*p),
-        // TODO(#58): support non-32-bit platforms
-        // Actual:
-        // ast::TypeName::StrReference => Layout::new::<&str>(),
-        // ast::TypeName::PrimitiveSlice(_) => Layout::new::<&[u8]>(),
-        // Temporary:
-        ast::TypeName::StrReference(..) => Layout::new::<(usize_target, usize_target)>(),
-        ast::TypeName::PrimitiveSlice(..) => Layout::new::<(usize_target, usize_target)>(),
+        ast::TypeName::Primitive(p) => primitive_size_alignment(*p, width),
+        ast::TypeName::StrReference(..) => width.slice_layout(),
+        ast::TypeName::PrimitiveSlice(..) => width.slice_layout(),
         ast::TypeName::Writeable => panic!(),
         ast::TypeName::Unit => Layout::new::<()>(),
         &_ => unreachable!("unknown AST/HIR variant"),
     }
 }
 
-pub fn primitive_size_alignment(prim: PrimitiveType) -> Layout {
+pub fn primitive_size_alignment(prim: PrimitiveType, width: PointerWidth) -> Layout {
     match prim {
         ast::PrimitiveType::bool => Layout::new::<bool>(),
         ast::PrimitiveType::char => Layout::new::<char>(),
@@ -124,8 +179,412 @@ pub fn primitive_size_alignment(prim: PrimitiveType) -> Layout {
         ast::PrimitiveType::i32 | ast::PrimitiveType::u32 => Layout::new::<u32>(),
         ast::PrimitiveType::i64 | ast::PrimitiveType::u64 => Layout::new::<u64>(),
         ast::PrimitiveType::i128 | ast::PrimitiveType::u128 => Layout::new::<u128>(),
-        ast::PrimitiveType::isize | ast::PrimitiveType::usize => Layout::new::<usize_target>(),
+        ast::PrimitiveType::isize | ast::PrimitiveType::usize => width.layout(),
         ast::PrimitiveType::f32 => Layout::new::<f32>(),
         ast::PrimitiveType::f64 => Layout::new::<f64>(),
     }
 }
+
+/// Finds bridge structs containing themselves by value, directly or through the fields of other
+/// structs, which cannot be laid out.
+///
+/// Lowering to the HIR rejects them too, but the backends working on the AST would recurse
+/// forever laying them out, so every backend checks them first.
+pub fn check_struct_cycles(env: &Env) -> Vec<String> {
+    let mut errors = Vec::new();
+    // Structs whose fields have been fully explored
+    let mut done = HashSet::new();
+    let mut path = Vec::new();
+    for (in_path, _, item) in env.iter_items() {
+        if let ast::ModSymbol::CustomType(ast::CustomType::Struct(strct)) = item {
+            find_struct_cycles(strct, in_path, env, &mut path, &mut done, &mut errors);
+        }
+    }
+    errors
+}
+
+/// Depth-first search for struct cycles reachable from `strct`, `path` being the chain of
+/// structs currently being explored
+fn find_struct_cycles<'a>(
+    strct: &'a ast::Struct,
+    in_path: &ast::Path,
+    env: &'a Env,
+    path: &mut Vec<(ast::Path, &'a ast::Struct)>,
+    done: &mut HashSet<ast::Path>,
+    errors: &mut Vec<String>,
+) {
+    let full_path = in_path.sub_path(strct.name.clone());
+    if done.contains(&full_path) {
+        return;
+    }
+    if let Some(start) = path.iter().position(|(p, _)| *p == full_path) {
+        let cycle = path[start..]
+            .iter()
+            .map(|(_, s)| s.name.as_str())
+            .chain([strct.name.as_str()])
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        errors.push(format!(
+            "Struct `{}` contains itself by value ({cycle}), so it cannot be laid out. \
+             Make one of these types opaque and hold it by reference to break the cycle",
+            strct.name
+        ));
+        return;
+    }
+
+    path.push((full_path.clone(), strct));
+    for (_, typ, _) in strct.fields.iter() {
+        if let Some((field_path, field)) = struct_held_by_value(typ, in_path, env) {
+            find_struct_cycles(field, &field_path, env, path, done, errors);
+        }
+    }
+    path.pop();
+    done.insert(full_path);
+}
+
+/// The struct a field of type `typ` holds by value, directly or as the value of a
+/// `DiplomatOption`, with the path of its module, if any
+fn struct_held_by_value<'a>(
+    typ: &ast::TypeName,
+    in_path: &ast::Path,
+    env: &'a Env,
+) -> Option<(ast::Path, &'a ast::Struct)> {
+    match typ {
+        ast::TypeName::DiplomatOption(underlying) => struct_held_by_value(underlying, in_path, env),
+        ast::TypeName::Named(path_type) | ast::TypeName::SelfType(path_type) => {
+            match path_type.resolve_with_path(in_path, env) {
+                (struct_path, ast::CustomType::Struct(strct)) => Some((struct_path, strct)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Warns about bridge structs whose layout is likely to cause trouble across languages:
+///
+/// - fields padded to more bytes in declaration order than ordered by decreasing alignment,
+///   for a target with pointers of the given width, suggesting that order;
+/// - with `check_offsets`, for the backends reading struct memory at the offsets
+///   [`struct_offsets_size_max_align()`] computes, fields it places elsewhere than C does.
+///
+/// Structs with fields of a type those backends can't lay out are skipped.
+pub fn check_struct_layouts(
+    env: &Env,
+    backend: &str,
+    width: PointerWidth,
+    check_offsets: bool,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (in_path, _, item) in env.iter_items() {
+        let ast::ModSymbol::CustomType(ast::CustomType::Struct(strct)) = item else {
+            continue;
+        };
+        if !strct
+            .fields
+            .iter()
+            .all(|(_, typ, _)| has_known_layout(typ, in_path, env))
+        {
+            continue;
+        }
+
+        let types = || strct.fields.iter().map(|(_, typ, _)| typ);
+        let (c_offsets, declared) = c_struct_layout(types(), in_path, env, width);
+        let mut fields = strct.fields.iter().collect::<Vec<_>>();
+        // Stable, so fields of the same alignment keep their order
+        fields.sort_by_key(|(_, typ, _)| {
+            std::cmp::Reverse(c_layout(typ, in_path, env, width).align())
+        });
+        let (_, reordered) =
+            c_struct_layout(fields.iter().map(|(_, typ, _)| typ), in_path, env, width);
+
+        if reordered.size() < declared.size() {
+            let fields_size: usize = types()
+                .map(|typ| c_layout(typ, in_path, env, width).size())
+                .sum();
+            let order = fields
+                .iter()
+                .map(|(name, _, _)| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            warnings.push(format!(
+                "Struct `{}` takes {} bytes, {} of them padding, on {}-bit targets; \
+                 declaring its fields as {order} would make it {} bytes",
+                strct.name,
+                declared.size(),
+                declared.size() - fields_size,
+                width.bits(),
+                reordered.size()
+            ));
+        }
+
+        if check_offsets {
+            let (offsets, _) = struct_offsets_size_max_align(types(), in_path, env, width);
+            for ((name, _, _), (offset, c_offset)) in
+                strct.fields.iter().zip(offsets.into_iter().zip(c_offsets))
+            {
+                if offset != c_offset {
+                    warnings.push(format!(
+                        "Field `{}::{name}` is at offset {c_offset} in C, but the {backend} backend reads it at \
+                         offset {offset}; move the fields after a nested struct before it, or pad that struct \
+                         to a multiple of its alignment with explicit fields",
+                        strct.name
+                    ));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+/// The offsets of fields of the given types in a `#[repr(C)]` struct, and its layout padded to
+/// its alignment, as C compilers lay it out.
+fn c_struct_layout<'a>(
+    type_names: impl Iterator<Item = &'a ast::TypeName>,
+    in_path: &ast::Path,
+    env: &Env,
+    width: PointerWidth,
+) -> (Vec<usize>, Layout) {
+    let mut layout = Layout::from_size_align(0, 1).unwrap();
+    let offsets = type_names
+        .map(|typ| {
+            let (extended, offset) = layout.extend(c_layout(typ, in_path, env, width)).unwrap();
+            layout = extended;
+            offset
+        })
+        .collect();
+    (offsets, layout.pad_to_align())
+}
+
+/// The layout of a struct field in C, where nested structs are padded to their alignment and
+/// `#[repr(C)]` enums are `int`s
+fn c_layout(typ: &ast::TypeName, in_path: &ast::Path, env: &Env, width: PointerWidth) -> Layout {
+    match typ {
+        // The value followed by the flag telling whether it is set
+        ast::TypeName::DiplomatOption(underlying) => {
+            let fields = [
+                &**underlying,
+                &ast::TypeName::Primitive(PrimitiveType::bool),
+            ];
+            c_struct_layout(fields.into_iter(), in_path, env, width).1
+        }
+        ast::TypeName::Named(path_type) | ast::TypeName::SelfType(path_type) => {
+            match path_type.resolve_with_path(in_path, env) {
+                (struct_path, ast::CustomType::Struct(strct)) => {
+                    let fields = strct.fields.iter().map(|(_, typ, _)| typ);
+                    c_struct_layout(fields, &struct_path, env, width).1
+                }
+                (_, ast::CustomType::Enum(_)) => Layout::new::<i32>(),
+                _ => type_size_alignment(typ, in_path, env, width),
+            }
+        }
+        _ => type_size_alignment(typ, in_path, env, width),
+    }
+}
+
+/// Whether [`type_size_alignment()`] can lay out a struct field of this type.
+///
+/// A struct holding itself by value, directly or through other structs, has no layout: this
+/// returns false for it rather than recursing forever, so that [`c_layout()`] and
+/// [`c_struct_layout()`] are never asked to lay it out.
+fn has_known_layout(typ: &ast::TypeName, in_path: &ast::Path, env: &Env) -> bool {
+    has_known_layout_visiting(typ, in_path, env, &mut Vec::new())
+}
+
+/// [`has_known_layout()`], with the structs whose fields are being checked
+fn has_known_layout_visiting(
+    typ: &ast::TypeName,
+    in_path: &ast::Path,
+    env: &Env,
+    visiting: &mut Vec<ast::Path>,
+) -> bool {
+    match typ {
+        ast::TypeName::Primitive(_)
+        | ast::TypeName::Box(_)
+        | ast::TypeName::Reference(..)
+        | ast::TypeName::StrReference(..)
+        | ast::TypeName::PrimitiveSlice(..) => true,
+        ast::TypeName::Option(underlying) => matches!(
+            underlying.as_ref(),
+            ast::TypeName::Box(_) | ast::TypeName::Reference(..)
+        ),
+        ast::TypeName::DiplomatOption(underlying) => {
+            has_known_layout_visiting(underlying, in_path, env, visiting)
+        }
+        ast::TypeName::Named(path_type) | ast::TypeName::SelfType(path_type) => {
+            match path_type.resolve_with_path(in_path, env) {
+                (struct_path, ast::CustomType::Struct(strct)) => {
+                    let full_path = struct_path.sub_path(strct.name.clone());
+                    if visiting.contains(&full_path) {
+                        return false;
+                    }
+                    visiting.push(full_path);
+                    let known = strct.fields.iter().all(|(_, typ, _)| {
+                        has_known_layout_visiting(typ, &struct_path, env, visiting)
+                    });
+                    visiting.pop();
+                    known
+                }
+                (_, ast::CustomType::Enum(_)) => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_struct_cycles, check_struct_layouts, PointerWidth};
+
+    #[test]
+    fn test_pointer_width_of_target() {
+        for triple in [
+            "wasm32-unknown-unknown",
+            "wasm32-wasip1",
+            "i686-pc-windows-msvc",
+            "armv7-unknown-linux-gnueabihf",
+            "thumbv7em-none-eabihf",
+            "riscv32imac-unknown-none-elf",
+            "mipsel-unknown-linux-gnu",
+            "arm64_32-apple-watchos",
+        ] {
+            assert_eq!(
+                PointerWidth::of_target(triple),
+                Some(PointerWidth::Bits32),
+                "{triple}"
+            );
+        }
+        for triple in [
+            "wasm64-unknown-unknown",
+            "x86_64-unknown-linux-gnu",
+            "aarch64-apple-darwin",
+            "mips64el-unknown-linux-gnuabi64",
+            "powerpc64le-unknown-linux-gnu",
+            "s390x-unknown-linux-gnu",
+        ] {
+            assert_eq!(
+                PointerWidth::of_target(triple),
+                Some(PointerWidth::Bits64),
+                "{triple}"
+            );
+        }
+        assert_eq!(PointerWidth::of_target("avr-unknown-gnu-atmega328"), None);
+    }
+
+    #[test]
+    fn test_check_struct_layouts() {
+        let tk_stream = quote::quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Padded {
+                    flag: bool,
+                    count: u64,
+                    other: bool,
+                    len: usize,
+                }
+
+                pub struct Packed {
+                    count: u64,
+                    len: u32,
+                    flag: bool,
+                }
+
+                pub struct Nested {
+                    inner: Packed,
+                    first: u8,
+                    last: u8,
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).unwrap();
+        let env = diplomat_core::ast::File::from(&item).all_types();
+
+        assert_eq!(
+            check_struct_layouts(&env, "c2", PointerWidth::Bits64, false),
+            [
+                "Struct `Padded` takes 32 bytes, 14 of them padding, on 64-bit targets; \
+              declaring its fields as `count`, `len`, `flag`, `other` would make it 24 bytes"
+            ]
+        );
+        assert_eq!(
+            check_struct_layouts(&env, "js", PointerWidth::Bits32, true),
+            [
+                "Field `Nested::first` is at offset 16 in C, but the js backend reads it at \
+                 offset 13; move the fields after a nested struct before it, or pad that struct \
+                 to a multiple of its alignment with explicit fields",
+                "Field `Nested::last` is at offset 17 in C, but the js backend reads it at \
+                 offset 14; move the fields after a nested struct before it, or pad that struct \
+                 to a multiple of its alignment with explicit fields",
+                "Struct `Padded` takes 24 bytes, 10 of them padding, on 32-bit targets; \
+                 declaring its fields as `count`, `len`, `flag`, `other` would make it 16 bytes",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_struct_layouts_skips_cycles() {
+        let tk_stream = quote::quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Foo {
+                    a: Foo,
+                    b: u8,
+                }
+
+                pub struct Outer {
+                    flag: bool,
+                    inner: DiplomatOption<Foo>,
+                    count: u64,
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).unwrap();
+        let env = diplomat_core::ast::File::from(&item).all_types();
+
+        // Structs that can't be laid out are skipped, not overflowing the stack
+        assert!(check_struct_layouts(&env, "c2", PointerWidth::Bits64, false).is_empty());
+        assert!(check_struct_layouts(&env, "js", PointerWidth::Bits32, true).is_empty());
+    }
+
+    #[test]
+    fn test_check_struct_cycles() {
+        let tk_stream = quote::quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                pub struct Foo {
+                    a: Foo,
+                    b: u8,
+                }
+
+                pub struct Outer {
+                    middle: DiplomatOption<Middle>,
+                }
+
+                pub struct Middle {
+                    outer: Outer,
+                    foo: Foo,
+                }
+
+                pub struct NotACycle {
+                    first: Middle,
+                    second: Middle,
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).unwrap();
+        let env = diplomat_core::ast::File::from(&item).all_types();
+
+        let mut errors = check_struct_cycles(&env);
+        errors.sort();
+        assert_eq!(
+            errors,
+            [
+                "Struct `Foo` contains itself by value (Foo -> Foo), so it cannot be laid out. \
+                 Make one of these types opaque and hold it by reference to break the cycle",
+                "Struct `Middle` contains itself by value (Middle -> Outer -> Middle), so it cannot \
+                 be laid out. Make one of these types opaque and hold it by reference to break the cycle",
+            ]
+        );
+    }
+}
@@ -23,30 +23,87 @@ pub mod koka;
 pub mod kotlin;
 
 mod docs_util;
-mod layout;
+mod inspect;
+#[doc(hidden)]
+pub mod layout;
+mod sbom;
+mod symbols;
+mod trace;
 mod util;
 
 use colored::*;
 use core::panic;
 use diplomat_core::{ast, hir};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 pub use ast::DocsUrlGenerator;
 
-#[allow(clippy::too_many_arguments)]
+/// Options of [`gen`] besides what to generate and where. Each defaults to what the CLI does
+/// without the flag of the same name.
+#[derive(Default)]
+pub struct GenOptions<'a> {
+    /// The folder docs are generated into, for the backends generating docs
+    pub docs_out_folder: Option<&'a Path>,
+    /// The base URLs of the docs of other crates, which docs link to their items with
+    pub docs_url_gen: ast::DocsUrlGenerator,
+    /// The config file overriding the code generation defaults of the backend
+    pub library_config: Option<&'a Path>,
+    /// Whether to leave out the notes printed while generating
+    pub silent: bool,
+    /// The prefixes removed from type names, for the backends supporting it
+    pub strip_prefix: common::StripPrefix,
+    /// The profile of the library config to generate with, for the backends supporting it
+    pub profile: Option<&'a str>,
+    /// How the identifiers of the generated API are cased, overriding the library config
+    pub naming: Option<&'a str>,
+    /// The target triple the library is built for
+    pub target: Option<&'a str>,
+    /// Whether to also generate the C headers the bindings are built on
+    pub emit_c_headers: bool,
+    /// Whether to generate the bindings into a single module
+    pub bundle: bool,
+    /// Whether to also write an SPDX document listing the generated files
+    pub emit_sbom: bool,
+    /// Whether links in docs to items that are not generated become plain text
+    pub unlink_dangling_docs: bool,
+    /// Whether to write what generated successfully when some types or traits fail to
+    pub keep_going: bool,
+    /// Whether to list the files that would be written instead of writing them
+    pub dry_run: bool,
+    /// Whether to log how the signature of each method was lowered
+    pub trace: bool,
+}
+
+/// Generates the bindings of the bridge crate at `entry` in `target_language`.
+///
+/// Several targets can be given separated by commas, e.g. `koka,c2,js`. They are generated
+/// from one parse of the crate, each into the folder of its name in `out_folder` (and
+/// `docs_out_folder`), and none of them is written if one fails to generate.
 pub fn gen(
     entry: &Path,
     target_language: &str,
     out_folder: &Path,
-    docs_out_folder: Option<&Path>,
-    docs_url_gen: &ast::DocsUrlGenerator,
-    library_config: Option<&Path>,
-    silent: bool,
-    strip_prefix: Option<String>,
+    options: GenOptions,
 ) -> std::io::Result<()> {
+    let GenOptions {
+        docs_out_folder,
+        ref docs_url_gen,
+        library_config,
+        silent,
+        strip_prefix,
+        profile,
+        naming,
+        target,
+        emit_c_headers,
+        bundle,
+        emit_sbom,
+        unlink_dangling_docs,
+        keep_going,
+        dry_run,
+        trace,
+    } = options;
+
     // Check that user-provided paths exist. Exit early with a nice error message
     // if anything doesn't exist.
     exit_if_path_missing(
@@ -68,103 +125,316 @@ pub fn gen(
         );
     }
 
+    let targets = target_language.split(',').collect::<Vec<_>>();
+    for (i, target_language) in targets.iter().enumerate() {
+        if targets[..i].contains(target_language) {
+            eprintln!(
+                "{}The target {target_language} is given twice",
+                "Error: ".red().bold()
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if profile.is_some() && !targets.contains(&"koka") {
+        eprintln!(
+            "{}--profile is only supported by the koka backend",
+            "Error: ".red().bold()
+        );
+        std::process::exit(1);
+    }
+
+    if naming.is_some() && !targets.contains(&"koka") {
+        eprintln!(
+            "{}--naming is only supported by the koka backend",
+            "Error: ".red().bold()
+        );
+        std::process::exit(1);
+    }
+
+    let pointer_width = target.map(|triple| {
+        layout::PointerWidth::of_target(triple).unwrap_or_else(|| {
+            eprintln!(
+                "{}Unknown pointer width of the target {triple}",
+                "Error: ".red().bold()
+            );
+            std::process::exit(1);
+        })
+    });
+    if targets.contains(&"js") && target.map_or(false, |triple| !triple.starts_with("wasm")) {
+        eprintln!(
+            "{}The js backend only supports wasm targets such as wasm32-unknown-unknown",
+            "Error: ".red().bold()
+        );
+        std::process::exit(1);
+    }
+
+    if emit_c_headers && !targets.contains(&"koka") {
+        eprintln!(
+            "{}--emit-c-headers is only supported by the koka backend",
+            "Error: ".red().bold()
+        );
+        std::process::exit(1);
+    }
+
+    if trace {
+        for target_language in targets
+            .iter()
+            .filter(|t| ["c", "cpp", "dotnet", "js"].contains(t))
+        {
+            eprintln!(
+                "{}--trace logs nothing for the {target_language} backend, which generates from the AST",
+                "Warning: ".yellow().bold()
+            );
+        }
+    }
+
+    if bundle && !targets.contains(&"koka") {
+        eprintln!(
+            "{}--bundle is only supported by the koka backend",
+            "Error: ".red().bold()
+        );
+        std::process::exit(1);
+    }
+
     let lib_file = syn_inline_mod::parse_and_inline_modules(entry);
     let diplomat_file = ast::File::from(&lib_file);
     let env = diplomat_file.all_types();
 
-    let mut out_texts: HashMap<String, String> = HashMap::new();
-
+    // Every target is generated before any is written, so that their outputs are consistent
     let mut errors_found = false;
+    let mut generated = Vec::new();
+    for &target_language in &targets {
+        let mut out_texts = gen_target(
+            &env,
+            target_language,
+            docs_url_gen,
+            library_config,
+            strip_prefix.clone(),
+            profile,
+            naming,
+            target,
+            pointer_width,
+            emit_c_headers,
+            bundle,
+            unlink_dangling_docs,
+            trace,
+            &mut errors_found,
+        );
+        if emit_sbom {
+            let bridge = sbom::BridgeCrate::of_entry(entry);
+            let document = sbom::gen(target_language, &bridge, &out_texts);
+            out_texts.insert(sbom::SBOM_FILE.into(), document);
+        }
+        exit_if_paths_escape(out_texts.keys());
+        generated.push((target_language, out_texts));
+    }
+
+    if errors_found && !keep_going {
+        eprintln!("Not generating files due to errors");
+        // Eventually this should use eyre or something
+        std::process::exit(1);
+    }
+
+    let mut written = 0;
+    for (target_language, out_texts) in generated {
+        let (out_folder, docs_out_folder) = if targets.len() == 1 {
+            (out_folder.to_owned(), docs_out_folder.map(Path::to_owned))
+        } else {
+            (
+                out_folder.join(target_language),
+                docs_out_folder.map(|folder| folder.join(target_language)),
+            )
+        };
+
+        written += out_texts.len();
+        // Written in the order of their paths, so that runs list them the same way
+        let out_texts = out_texts.into_iter().collect::<BTreeMap<_, _>>();
+
+        if !silent {
+            println!(
+                "{}",
+                format!("Generating {} bindings:", target_language)
+                    .green()
+                    .bold()
+            );
+        }
+
+        write_files(&out_folder, out_texts, silent, dry_run)?;
+
+        if let Some(docs_out_folder) = docs_out_folder {
+            if !silent {
+                println!(
+                    "{}",
+                    format!("Generating {} docs:", target_language)
+                        .green()
+                        .bold()
+                );
+            }
+
+            let mut docs_out_texts: HashMap<String, String> = HashMap::new();
+
+            match target_language {
+                "js" => js::docs::gen_docs(&env, &mut docs_out_texts, docs_url_gen).unwrap(),
+                "cpp" | "cpp-c2" => {
+                    cpp::docs::gen_docs(&env, library_config, &mut docs_out_texts, docs_url_gen)
+                        .unwrap()
+                }
+                "c" => todo!("Docs generation for C"),
+                "dotnet" => todo!("Docs generation for .NET?"),
+                o => panic!("Unknown target: {}", o),
+            }
+
+            exit_if_paths_escape(docs_out_texts.keys());
+            let docs_out_texts = docs_out_texts.into_iter().collect::<BTreeMap<_, _>>();
+
+            write_files(&docs_out_folder, docs_out_texts, silent, dry_run)?;
+        }
+    }
+
+    if errors_found {
+        // Only reached with `keep_going`
+        eprintln!(
+            "{}Generated {written} files, leaving out the types and traits with the errors listed above",
+            "Error: ".red().bold()
+        );
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Browses the HIR the bridge crate at `entry` is lowered to interactively, full screen in a
+/// terminal, otherwise reading commands from stdin. Methods marked `platforms(..)` that aren't
+/// available on `target` are shown as left out.
+pub fn inspect(entry: &Path, target: Option<&str>) -> std::io::Result<()> {
+    exit_if_path_missing(entry, "The entry file does not exist.");
+    let lib_file = syn_inline_mod::parse_and_inline_modules(entry);
+    let env = ast::File::from(&lib_file).all_types();
+    inspect::run(&env, target)
+}
+
+/// Generates the files of the bindings in one target language, setting `errors_found` if
+/// some types or traits fail to generate
+#[allow(clippy::too_many_arguments)]
+fn gen_target(
+    env: &diplomat_core::Env,
+    target_language: &str,
+    docs_url_gen: &ast::DocsUrlGenerator,
+    library_config: Option<&Path>,
+    strip_prefix: common::StripPrefix,
+    profile: Option<&str>,
+    naming: Option<&str>,
+    target: Option<&str>,
+    pointer_width: Option<layout::PointerWidth>,
+    emit_c_headers: bool,
+    bundle: bool,
+    unlink_dangling_docs: bool,
+    trace: bool,
+    errors_found: &mut bool,
+) -> HashMap<String, String> {
+    let symbol_rules = match target_language {
+        "js" => &js::SYMBOLS,
+        "dotnet" => &dotnet::SYMBOLS,
+        _ => &symbols::C_SYMBOLS,
+    };
+    let symbol_errors = symbols::check(env, target_language, symbol_rules);
+    if !symbol_errors.is_empty() {
+        for error in symbol_errors {
+            eprintln!("{}{error}", "Error: ".red().bold());
+        }
+        std::process::exit(1);
+    }
+
+    // Checked before anything lays structs out, which would not terminate on a cycle
+    let cycle_errors = layout::check_struct_cycles(env);
+    if !cycle_errors.is_empty() {
+        for error in cycle_errors {
+            eprintln!("{}{error}", "Error: ".red().bold());
+        }
+        std::process::exit(1);
+    }
+
+    // Without a target, padding is reported for the common 64-bit ones
+    let layout_width = pointer_width.unwrap_or(if target_language == "js" {
+        js::DEFAULT_POINTER_WIDTH
+    } else {
+        layout::PointerWidth::Bits64
+    });
+    for warning in
+        layout::check_struct_layouts(env, target_language, layout_width, target_language == "js")
+    {
+        eprintln!("{}{warning}", "Warning: ".yellow().bold());
+    }
+
+    if matches!(target_language, "js" | "c" | "cpp" | "dotnet") {
+        exit_if_callbacks(env, target_language);
+    }
+
+    let mut out_texts: HashMap<String, String> = HashMap::new();
 
     match target_language {
-        "js" => js::gen_bindings(&env, &mut out_texts, Some(docs_url_gen)).unwrap(),
+        "js" => js::gen_bindings(
+            env,
+            library_config,
+            &mut out_texts,
+            Some(docs_url_gen),
+            layout_width,
+        )
+        .unwrap(),
         "kotlin" => {
-            let mut attr_validator = hir::BasicAttributeValidator::new("kotlin");
-            attr_validator.support.renaming = true;
-            attr_validator.support.disabling = true;
-            let tcx = match hir::TypeContext::from_ast(&env, attr_validator) {
-                Ok(context) => context,
-
-                Err(e) => {
-                    for (ctx, err) in e {
-                        eprintln!("Lowering error in {ctx}: {err}");
-                    }
-                    std::process::exit(1);
+            let mut tcx = lower_or_exit(env, target_language, target);
+            check_doc_references(&mut tcx, target_language, unlink_dangling_docs);
+            if trace {
+                print_trace(&tcx, target_language);
+            }
+            let (mut files, errors) = kotlin::run(&tcx, library_config);
+            if !errors.is_empty() {
+                eprintln!("Found errors whilst generating {target_language}:");
+                for error in errors {
+                    eprintln!("\t{}: {}", error.0, error.1);
                 }
-            };
-            out_texts = kotlin::run(&tcx, library_config).take_files();
+                *errors_found = true;
+            }
+            out_texts = files.take_files();
         }
         "dart" => {
-            let mut attr_validator = hir::BasicAttributeValidator::new("dart");
-            attr_validator.support.renaming = true;
-            attr_validator.support.disabling = true;
-            attr_validator.support.constructors = true;
-            attr_validator.support.named_constructors = true;
-            attr_validator.support.fallible_constructors = true;
-            attr_validator.support.accessors = true;
-            attr_validator.support.stringifiers = true;
-            attr_validator.support.comparators = true;
-            attr_validator.support.iterators = true;
-            attr_validator.support.iterables = true;
-            attr_validator.support.indexing = true;
-            let tcx = match hir::TypeContext::from_ast(&env, attr_validator) {
-                Ok(context) => context,
-                Err(e) => {
-                    for (ctx, err) in e {
-                        eprintln!("Lowering error in {ctx}: {err}");
-                    }
-                    std::process::exit(1);
-                }
-            };
-            match dart::run(&tcx, docs_url_gen, strip_prefix) {
-                Ok(mut files) => out_texts = files.take_files(),
-                Err(errors) => {
-                    eprintln!("Found errors whilst generating {target_language}:");
-                    for error in errors {
-                        eprintln!("\t{}: {}", error.0, error.1);
-                    }
-                    errors_found = true;
+            let mut tcx = lower_or_exit(env, target_language, target);
+            check_doc_references(&mut tcx, target_language, unlink_dangling_docs);
+            if trace {
+                print_trace(&tcx, target_language);
+            }
+            let (mut files, errors) = dart::run(&tcx, docs_url_gen, strip_prefix);
+            if !errors.is_empty() {
+                eprintln!("Found errors whilst generating {target_language}:");
+                for error in errors {
+                    eprintln!("\t{}: {}", error.0, error.1);
                 }
-            };
+                *errors_found = true;
+            }
+            out_texts = files.take_files();
         }
-        "c" => c::gen_bindings(&env, &mut out_texts).unwrap(),
+        "c" => c::gen_bindings(env, &mut out_texts).unwrap(),
         "cpp" => {
-            c::gen_bindings(&env, &mut out_texts).unwrap();
-            cpp::gen_bindings(&env, library_config, docs_url_gen, &mut out_texts).unwrap()
+            c::gen_bindings(env, &mut out_texts).unwrap();
+            cpp::gen_bindings(env, library_config, docs_url_gen, &mut out_texts).unwrap()
         }
         "dotnet" => {
-            dotnet::gen_bindings(&env, library_config, docs_url_gen, &mut out_texts).unwrap()
+            dotnet::gen_bindings(env, library_config, docs_url_gen, &mut out_texts).unwrap()
         }
         "c2" | "cpp-c2" | "cpp2" => {
-            let mut attr_validator = hir::BasicAttributeValidator::new(target_language);
-
-            if target_language == "c2" {
-                attr_validator.other_backend_names.push("c".into());
-            } else {
-                attr_validator.other_backend_names.push("cpp".into());
-                // C backends cannot rename types using backend attributes
-                // In the future we may add a c_rename attribute
-                attr_validator.support.renaming = true;
-
-                attr_validator.support.namespacing = true;
+            let mut tcx = lower_or_exit(env, target_language, target);
+            check_doc_references(&mut tcx, target_language, unlink_dangling_docs);
+            if trace {
+                print_trace(&tcx, target_language);
             }
-
-            attr_validator.support.memory_sharing = true;
-            attr_validator.support.disabling = true;
-            // cpp-c2 is a testing backend, we're not going to treat it as a real c/cpp backend
-            // since the ast-cpp backend doesn't know about attributes.
-
-            let tcx = match hir::TypeContext::from_ast(&env, attr_validator) {
-                Ok(context) => context,
-                Err(e) => {
-                    for (ctx, err) in e {
-                        eprintln!("Lowering error in {ctx}: {err}");
-                    }
-                    std::process::exit(1);
-                }
-            };
             let files = common::FileMap::default();
             let mut context = c2::CContext::new(&tcx, files);
+            if target_language == "c2" {
+                if let Some(library_config) = library_config {
+                    context.config = c2::read_config(library_config);
+                }
+            }
             context.run();
 
             let errors = context.errors.take_all();
@@ -174,17 +444,20 @@ pub fn gen(
                 for error in errors {
                     eprintln!("\t{}: {}", error.0, error.1);
                 }
-                errors_found = true;
+                *errors_found = true;
             }
 
             out_texts = context.files.take_files();
 
             if target_language == "cpp-c2" {
-                cpp::gen_bindings(&env, library_config, docs_url_gen, &mut out_texts).unwrap()
+                cpp::gen_bindings(env, library_config, docs_url_gen, &mut out_texts).unwrap()
             }
             if target_language == "cpp2" {
                 let files = common::FileMap::default();
                 let mut context = cpp2::Cpp2Context::new(&tcx, files);
+                if let Some(library_config) = library_config {
+                    context.config = cpp2::read_config(library_config);
+                }
                 context.run();
                 out_texts.extend(context.files.take_files());
 
@@ -195,12 +468,73 @@ pub fn gen(
                     for error in errors {
                         eprintln!("\t{}: {}", error.0, error.1);
                     }
-                    errors_found = true;
+                    *errors_found = true;
                 }
             }
         }
         "koka" => {
-            let mut attr_validator = hir::BasicAttributeValidator::new(target_language);
+            let mut tcx = lower_or_exit(env, target_language, target);
+            check_doc_references(&mut tcx, target_language, unlink_dangling_docs);
+            if trace {
+                print_trace(&tcx, target_language);
+            }
+            let mut config = match library_config {
+                Some(path) => koka::KokaConfig::read(path, profile),
+                None => {
+                    if let Some(profile) = profile {
+                        panic!("Profile `{profile}` selected without a config file");
+                    }
+                    koka::KokaConfig::default()
+                }
+            };
+            if let Some(naming) = naming {
+                config.naming = naming.parse().unwrap_or_else(|err| panic!("{err}"));
+            }
+            config.pointer_width = pointer_width;
+            config.emit_c_headers = emit_c_headers;
+            config.bundle = bundle;
+            for warning in koka::check_enum_variant_names(&tcx) {
+                eprintln!("{}{warning}", "Warning: ".yellow().bold());
+            }
+            let (mut files, errors) = koka::run(&tcx, config, docs_url_gen, strip_prefix);
+            if !errors.is_empty() {
+                eprintln!("Found errors whilst generating {target_language}:");
+                for error in errors {
+                    eprintln!("\t{}: {}", error.0, error.1);
+                }
+                *errors_found = true;
+            }
+            out_texts = files.take_files();
+        }
+        o => panic!("Unknown target: {}", o),
+    }
+
+    out_texts
+}
+
+/// The backends generating from the HIR, which [`lower()`] lowers the bridge for
+const HIR_BACKENDS: &[&str] = &["c2", "cpp2", "dart", "koka", "kotlin"];
+
+/// Lowers the bridge to the HIR for `target_language`, one of the backends generating from
+/// it, validating the attributes it supports. Methods marked `platforms(..)` that aren't
+/// available on `target` are disabled.
+fn lower(
+    env: &diplomat_core::Env,
+    target_language: &str,
+    target: Option<&str>,
+) -> Result<hir::TypeContext, Vec<hir::ErrorAndContext>> {
+    match target_language {
+        "kotlin" => {
+            let mut attr_validator = hir::BasicAttributeValidator::new("kotlin");
+            attr_validator.target = target.map(Into::into);
+            attr_validator.support.renaming = true;
+            attr_validator.support.disabling = true;
+            attr_validator.support.jni = true;
+            hir::TypeContext::from_ast(env, attr_validator)
+        }
+        "dart" => {
+            let mut attr_validator = hir::BasicAttributeValidator::new("dart");
+            attr_validator.target = target.map(Into::into);
             attr_validator.support.renaming = true;
             attr_validator.support.disabling = true;
             attr_validator.support.constructors = true;
@@ -212,91 +546,174 @@ pub fn gen(
             attr_validator.support.iterators = true;
             attr_validator.support.iterables = true;
             attr_validator.support.indexing = true;
-            let tcx = match hir::TypeContext::from_ast(&env, attr_validator) {
-                Ok(context) => context,
-                Err(e) => {
-                    for (ctx, err) in e {
-                        eprintln!("Lowering error in {ctx}: {err}");
-                    }
-                    std::process::exit(1);
-                }
-            };
-            match koka::run(&tcx, docs_url_gen, strip_prefix) {
-                Ok(mut files) => out_texts = files.take_files(),
-                Err(errors) => {
-                    eprintln!("Found errors whilst generating {target_language}:");
-                    for error in errors {
-                        eprintln!("\t{}: {}", error.0, error.1);
-                    }
-                    errors_found = true;
-                }
-            };
+            attr_validator.support.background = true;
+            hir::TypeContext::from_ast(env, attr_validator)
         }
-        o => panic!("Unknown target: {}", o),
-    }
+        "c2" | "cpp-c2" | "cpp2" => {
+            let mut attr_validator = hir::BasicAttributeValidator::new(target_language);
+            attr_validator.target = target.map(Into::into);
 
-    if errors_found {
-        eprintln!("Not generating files due to errors");
-        // Eventually this should use eyre or something
-        std::process::exit(1);
-    }
+            if target_language == "c2" {
+                attr_validator.other_backend_names.push("c".into());
+                attr_validator.support.callbacks = true;
+                attr_validator.support.option = true;
+                attr_validator.support.traits = true;
+                attr_validator.support.async_methods = true;
+                attr_validator.support.platforms = true;
+            } else {
+                attr_validator.other_backend_names.push("cpp".into());
+                // C backends cannot rename types using backend attributes
+                // In the future we may add a c_rename attribute
+                attr_validator.support.renaming = true;
 
-    if !silent {
-        println!(
-            "{}",
-            format!("Generating {} bindings:", target_language)
-                .green()
-                .bold()
-        );
-    }
+                attr_validator.support.namespacing = true;
+            }
 
-    for (subpath, text) in out_texts {
-        let out_path = out_folder.join(subpath);
-        let parent = out_path.parent().unwrap();
-        std::fs::create_dir_all(parent).unwrap();
-        let mut out_file = File::create(&out_path)?;
-        out_file.write_all(text.as_bytes())?;
-        if !silent {
-            println!("{}", format!("  {}", out_path.display()).dimmed());
+            attr_validator.support.memory_sharing = true;
+            attr_validator.support.disabling = true;
+            attr_validator.support.cloning = true;
+            // cpp-c2 is a testing backend, we're not going to treat it as a real c/cpp backend
+            // since the ast-cpp backend doesn't know about attributes.
+
+            hir::TypeContext::from_ast(env, attr_validator)
         }
+        "koka" => {
+            let attr_validator = koka::KokaAttributeValidator::new().with_target(target);
+            hir::TypeContext::from_ast(env, attr_validator)
+        }
+        o => panic!("The {o} backend does not generate from the HIR"),
     }
+}
 
-    if let Some(docs_out_folder) = docs_out_folder {
-        if !silent {
-            println!(
-                "{}",
-                format!("Generating {} docs:", target_language)
-                    .green()
-                    .bold()
-            );
+/// [`lower()`], exiting with the lowering errors if there are any
+fn lower_or_exit(
+    env: &diplomat_core::Env,
+    target_language: &str,
+    target: Option<&str>,
+) -> hir::TypeContext {
+    lower(env, target_language, target).unwrap_or_else(|errors| {
+        for (ctx, err) in errors {
+            eprintln!("Lowering error in {ctx}: {err}");
         }
+        std::process::exit(1);
+    })
+}
 
-        let mut docs_out_texts: HashMap<String, String> = HashMap::new();
+/// Warns about the links in docs to items the backend does not generate, such as disabled
+/// ones, or rewrites them to plain text
+fn check_doc_references(tcx: &mut hir::TypeContext, backend: &str, unlink: bool) {
+    if unlink {
+        tcx.unlink_dangling_doc_references();
+        return;
+    }
+    for reference in tcx.dangling_doc_references() {
+        eprintln!(
+            "{}The docs of `{}` link to `{}`, which the {backend} backend does not generate",
+            "Warning: ".yellow().bold(),
+            reference.item,
+            reference.target
+        );
+    }
+}
 
-        match target_language {
-            "js" => js::docs::gen_docs(&env, &mut docs_out_texts, docs_url_gen).unwrap(),
-            "cpp" | "cpp-c2" => {
-                cpp::docs::gen_docs(&env, library_config, &mut docs_out_texts, docs_url_gen)
-                    .unwrap()
-            }
-            "c" => todo!("Docs generation for C"),
-            "dotnet" => todo!("Docs generation for .NET?"),
-            o => panic!("Unknown target: {}", o),
-        }
+/// Prints how the HIR lowered the signature of each method, for `--trace`
+fn print_trace(tcx: &hir::TypeContext, backend: &str) {
+    eprintln!("Lowering of the methods for the {backend} backend:");
+    eprint!("{}", trace::trace_lowering(tcx));
+}
 
-        for (subpath, text) in docs_out_texts {
-            let out_path = docs_out_folder.join(subpath);
-            let mut out_file = File::create(&out_path)?;
-            out_file.write_all(text.as_bytes())?;
-            if !silent {
-                println!("{}", format!("  {}", out_path.display()).dimmed());
+/// Exits with an error if a method takes a callback, for the backends generating bindings from
+/// the AST, which can neither bind callbacks nor honor `#[diplomat::attr(.., disable)]`
+fn exit_if_callbacks(env: &diplomat_core::Env, backend: &str) {
+    let mut found = false;
+    for (_, _, item) in env.iter_items() {
+        let ast::ModSymbol::CustomType(ty) = item else {
+            continue;
+        };
+        for method in ty.methods() {
+            if method
+                .params
+                .iter()
+                .any(|p| matches!(p.ty, ast::TypeName::Function(..)))
+            {
+                eprintln!(
+                    "{}Method `{}::{}` takes a callback, which the {backend} backend does not support \
+                     and cannot leave out; generate bindings with a backend supporting callbacks, such as c2",
+                    "Error: ".red().bold(),
+                    ty.name(),
+                    method.name
+                );
+                found = true;
             }
         }
     }
+    if found {
+        std::process::exit(1);
+    }
+}
 
+/// Writes the generated files into `folder`, listing those whose content changed. Unchanged
+/// files are left alone, so that incremental builds don't redo work for them.
+///
+/// The user regions of scaffolds keep the content they have in the files already there, see
+/// [`common::preserve_user_regions()`].
+///
+/// A dry run writes nothing, listing every file as `new`, `changed` or `unchanged` instead,
+/// even when `silent`.
+fn write_files(
+    folder: &Path,
+    texts: BTreeMap<String, String>,
+    silent: bool,
+    dry_run: bool,
+) -> std::io::Result<()> {
+    if dry_run {
+        for (subpath, text) in texts {
+            let out_path = folder.join(subpath);
+            let text = common::with_user_regions(&out_path, text)?;
+            let status = if !out_path.exists() {
+                "new"
+            } else if common::is_up_to_date(&out_path, &text)? {
+                "unchanged"
+            } else {
+                "changed"
+            };
+            println!("{status} {}", out_path.display());
+        }
+        return Ok(());
+    }
+    let mut unchanged = 0;
+    for (subpath, text) in texts {
+        let out_path = folder.join(subpath);
+        let text = common::with_user_regions(&out_path, text)?;
+        if !common::write_if_changed(&out_path, &text)? {
+            unchanged += 1;
+        } else if !silent {
+            println!("{}", format!("  {}", out_path.display()).dimmed());
+        }
+    }
+    if unchanged > 0 && !silent {
+        println!(
+            "{}",
+            format!("  {unchanged} unchanged files left alone").dimmed()
+        );
+    }
     Ok(())
 }
 
+/// Exits with an error if a generated file would be written outside of its output folder,
+/// before any file is written
+fn exit_if_paths_escape<'a>(paths: impl Iterator<Item = &'a String>) {
+    let errors = paths
+        .filter_map(|path| common::check_output_path(path).err())
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        for error in errors {
+            eprintln!("{}{error}", "Error: ".red().bold());
+        }
+        std::process::exit(1);
+    }
+}
+
 /// Provide nice error messages if a folder doesn't exist.
 fn exit_if_path_missing(path: &Path, message: &str) {
     if !path.exists() {
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use std::path::PathBuf;
 
 /// diplomat-tool CLI options, as parsed by [clap-derive].
@@ -8,13 +8,18 @@ use std::path::PathBuf;
     about = "Generate bindings to a target language"
 )]
 struct Opt {
-    /// The target language, "js", "c", "cpp", "dotnet" (C#), or "kotlin" (JVM)
+    /// The target language, "js", "c", "cpp", "dotnet" (C#), or "kotlin" (JVM). Several can be
+    /// given separated by commas, e.g. "koka,c2,js", to generate them from one parse of the
+    /// crate, each into the folder of its name in the out folders.
+    ///
+    /// "inspect" browses the types and methods the crate is lowered to for each backend
+    /// interactively instead, generating nothing, which needs no out folder.
     #[clap()]
     target_language: String,
 
     /// The folder that stores the bindings.
     #[clap(value_parser)]
-    out_folder: PathBuf,
+    out_folder: Option<PathBuf>,
 
     #[clap(short, long, value_parser)]
     docs: Option<PathBuf>,
@@ -34,38 +39,143 @@ struct Opt {
 
     #[clap(short = 's', long)]
     silent: bool,
+
+    /// A prefix to remove from type names, e.g. "ICU4X" to name `ICU4XLocale` `Locale`, for
+    /// backends supporting it ("dart", "koka"). When given several times, the first one a
+    /// type name starts with is removed.
+    #[clap(long)]
+    strip_prefix: Vec<String>,
+
+    /// The prefix to remove from a single type instead, as "<Type>=<Prefix>". An empty
+    /// prefix keeps the name of the type whole.
+    #[clap(long)]
+    strip_prefix_override: Vec<String>,
+
+    /// The profile of the library config to generate with, for backends supporting
+    /// profiles ("koka").
+    #[clap(long)]
+    profile: Option<String>,
+
+    /// How the identifiers of the generated API are cased, "kebab", "snake" or "camel", for
+    /// backends supporting it ("koka"). Overrides the `naming` of the library config.
+    #[clap(long, value_parser = ["kebab", "snake", "camel"])]
+    naming: Option<String>,
+
+    /// The target triple the library is built for, e.g. "wasm32-unknown-unknown", for
+    /// backends that depend on the width of pointers ("js", "koka"). Defaults to the width
+    /// the C compiler uses ("koka") or to wasm32 ("js"). Methods marked `platforms(..)` that
+    /// aren't available on it are left out, for the backends generating from the HIR.
+    #[clap(long)]
+    target: Option<String>,
+
+    /// Also generate the C headers the bindings are built on, for backends calling into the
+    /// C ABI ("koka").
+    #[clap(long)]
+    emit_c_headers: bool,
+
+    /// Generate the runtime support and every type into a single module named after the
+    /// package, instead of a module tree with an entry module, for backends supporting it
+    /// ("koka"). The C files the bindings need are still written next to it.
+    #[clap(long)]
+    bundle: bool,
+
+    /// Also write an SPDX document listing the generated files with their checksums, the
+    /// crate they are generated from and the version of diplomat-tool.
+    #[clap(long)]
+    emit_sbom: bool,
+
+    /// Rewrite the links in docs to items the backend does not generate, such as disabled
+    /// ones, to plain text instead of warning about them.
+    #[clap(long)]
+    unlink_dangling_docs: bool,
+
+    /// Write the bindings of everything that generated successfully when some types or
+    /// traits fail to, instead of writing nothing. The errors are still reported, and the
+    /// exit status is still a failure. Supported by "c2", "cpp2", "dart" and "koka".
+    #[clap(long)]
+    keep_going: bool,
+
+    /// Lower and validate the API and generate the bindings without writing them, listing
+    /// each file that would be written as "new", "changed" or "unchanged" instead.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Log to stderr how the signature of each method was lowered: how its receiver,
+    /// parameters and result are represented, and which borrows the result keeps alive. For
+    /// the backends generating from the HIR, i.e. all but "c", "cpp", "dotnet" and "js".
+    #[clap(short = 'v', long)]
+    trace: bool,
 }
 
 fn main() -> std::io::Result<()> {
     let opt = Opt::parse();
 
+    if opt.target_language == "inspect" {
+        return diplomat_tool::inspect(&opt.entry, opt.target.as_deref());
+    }
+    let Some(out_folder) = opt.out_folder else {
+        Opt::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "The folder that stores the bindings is required",
+            )
+            .exit();
+    };
+
+    let strip_prefix = diplomat_tool::common::StripPrefix {
+        prefixes: opt.strip_prefix.clone(),
+        overrides: opt
+            .strip_prefix_override
+            .iter()
+            .map(|entry| {
+                let (ty, prefix) = entry
+                    .split_once('=')
+                    .expect("Expected syntax <Type>=<Prefix>");
+                let prefix = (!prefix.is_empty()).then(|| prefix.to_string());
+                (ty.to_string(), prefix)
+            })
+            .collect(),
+    };
+
     diplomat_tool::gen(
         &opt.entry,
         &opt.target_language,
-        &opt.out_folder,
-        opt.docs.as_deref(),
-        &diplomat_core::ast::DocsUrlGenerator::with_base_urls(
-            opt.docs_base_urls
-                .iter()
-                .filter_map(|entry| entry.strip_prefix("*:").map(ToString::to_string))
-                .next(),
-            opt.docs_base_urls
-                .iter()
-                .filter(|entry| !entry.starts_with('*'))
-                .map(|entry| {
-                    let mut parts = entry.splitn(2, ':');
-                    (
-                        parts.next().unwrap().to_string(),
-                        parts
-                            .next()
-                            .expect("Expected syntax <crate>|*:<url>")
-                            .to_string(),
-                    )
-                })
-                .collect(),
-        ),
-        opt.library_config.as_deref(),
-        opt.silent,
-        None,
+        &out_folder,
+        diplomat_tool::GenOptions {
+            docs_out_folder: opt.docs.as_deref(),
+            docs_url_gen: diplomat_core::ast::DocsUrlGenerator::with_base_urls(
+                opt.docs_base_urls
+                    .iter()
+                    .filter_map(|entry| entry.strip_prefix("*:").map(ToString::to_string))
+                    .next(),
+                opt.docs_base_urls
+                    .iter()
+                    .filter(|entry| !entry.starts_with('*'))
+                    .map(|entry| {
+                        let mut parts = entry.splitn(2, ':');
+                        (
+                            parts.next().unwrap().to_string(),
+                            parts
+                                .next()
+                                .expect("Expected syntax <crate>|*:<url>")
+                                .to_string(),
+                        )
+                    })
+                    .collect(),
+            ),
+            library_config: opt.library_config.as_deref(),
+            silent: opt.silent,
+            strip_prefix,
+            profile: opt.profile.as_deref(),
+            naming: opt.naming.as_deref(),
+            target: opt.target.as_deref(),
+            emit_c_headers: opt.emit_c_headers,
+            bundle: opt.bundle,
+            emit_sbom: opt.emit_sbom,
+            unlink_dangling_docs: opt.unlink_dangling_docs,
+            keep_going: opt.keep_going,
+            dry_run: opt.dry_run,
+            trace: opt.trace,
+        },
     )
 }
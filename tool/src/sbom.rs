@@ -0,0 +1,331 @@
+//! Generates an SPDX document describing the generated bindings, for supply-chain attestation
+//! of generated code.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The file the SPDX document is written to, next to the bindings
+pub const SBOM_FILE: &str = "diplomat.spdx";
+
+/// The crate the bindings are generated from
+pub struct BridgeCrate {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl BridgeCrate {
+    /// Finds the crate of an entry file from the closest `Cargo.toml` above it, falling back to
+    /// the name of the entry's directory.
+    pub fn of_entry(entry: &Path) -> Self {
+        #[derive(serde::Deserialize)]
+        struct Manifest {
+            package: Option<Package>,
+        }
+        #[derive(serde::Deserialize)]
+        struct Package {
+            name: String,
+            version: Option<toml::Value>,
+        }
+
+        let entry = entry.canonicalize().unwrap_or_else(|_| entry.to_path_buf());
+        for dir in entry.ancestors().skip(1) {
+            let Ok(manifest) = std::fs::read_to_string(dir.join("Cargo.toml")) else {
+                continue;
+            };
+            if let Ok(Manifest {
+                package: Some(package),
+            }) = toml::from_str(&manifest)
+            {
+                return BridgeCrate {
+                    name: package.name,
+                    // Workspace-inherited versions are tables, which we do not resolve
+                    version: package
+                        .version
+                        .and_then(|v| v.as_str().map(ToString::to_string)),
+                };
+            }
+        }
+
+        BridgeCrate {
+            name: entry
+                .parent()
+                .and_then(Path::file_name)
+                .map_or("bindings".into(), |name| {
+                    name.to_string_lossy().into_owned()
+                }),
+            version: None,
+        }
+    }
+}
+
+/// Generates an SPDX 2.3 document in the tag-value format, listing the generated files with
+/// their checksums as generated from the bridge crate by this version of diplomat-tool.
+///
+/// The creation time is taken from `SOURCE_DATE_EPOCH` if set, so that the document can be
+/// reproduced.
+pub fn gen(target_language: &str, bridge: &BridgeCrate, files: &HashMap<String, String>) -> String {
+    let mut files = files.iter().collect::<Vec<_>>();
+    files.sort();
+
+    let checksums = files
+        .iter()
+        .map(|(_, text)| (sha1(text.as_bytes()), sha256(text.as_bytes())))
+        .collect::<Vec<_>>();
+    let all_checksums = checksums
+        .iter()
+        .map(|(_, sha256)| sha256.as_str())
+        .collect::<String>();
+
+    let package_id = format!("SPDXRef-Package-{}", spdx_id(&bridge.name));
+    let mut out = String::new();
+    writeln!(out, "SPDXVersion: SPDX-2.3").unwrap();
+    writeln!(out, "DataLicense: CC0-1.0").unwrap();
+    writeln!(out, "SPDXID: SPDXRef-DOCUMENT").unwrap();
+    writeln!(
+        out,
+        "DocumentName: {}-{target_language}-bindings",
+        bridge.name
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "DocumentNamespace: https://spdx.org/spdxdocs/diplomat/{}-{target_language}-{}",
+        bridge.name,
+        &sha256(all_checksums.as_bytes())[..16]
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "Creator: Tool: diplomat-tool-{}",
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap();
+    writeln!(out, "Created: {}", fmt_timestamp(created())).unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(out, "PackageName: {}", bridge.name).unwrap();
+    writeln!(out, "SPDXID: {package_id}").unwrap();
+    if let Some(ref version) = bridge.version {
+        writeln!(out, "PackageVersion: {version}").unwrap();
+    }
+    writeln!(out, "PackageDownloadLocation: NOASSERTION").unwrap();
+    writeln!(out, "FilesAnalyzed: false").unwrap();
+
+    for (i, ((path, _), (sha1, sha256))) in files.iter().zip(checksums.iter()).enumerate() {
+        let file_id = format!("SPDXRef-File-{}", i + 1);
+        writeln!(out).unwrap();
+        writeln!(out, "FileName: ./{}", path.replace('\\', "/")).unwrap();
+        writeln!(out, "SPDXID: {file_id}").unwrap();
+        writeln!(out, "FileChecksum: SHA1: {sha1}").unwrap();
+        writeln!(out, "FileChecksum: SHA256: {sha256}").unwrap();
+        writeln!(out, "LicenseConcluded: NOASSERTION").unwrap();
+        writeln!(out, "CopyrightText: NOASSERTION").unwrap();
+        writeln!(out, "Relationship: SPDXRef-DOCUMENT DESCRIBES {file_id}").unwrap();
+        writeln!(out, "Relationship: {file_id} GENERATED_FROM {package_id}").unwrap();
+    }
+
+    out
+}
+
+/// SPDX identifiers may only contain letters, digits, `.` and `-`
+fn spdx_id(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Seconds since the Unix epoch the document is created at
+fn created() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        })
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DDThh:mm:ssZ`
+fn fmt_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs = secs % 86400;
+
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Pads a message into the 64 byte blocks of SHA-1 and SHA-256
+fn md_blocks(data: &[u8]) -> Vec<[u8; 64]> {
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    padded
+        .chunks(64)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect()
+}
+
+fn hex(words: &[u32]) -> String {
+    words.iter().map(|w| format!("{w:08x}")).collect()
+}
+
+fn sha1(data: &[u8]) -> String {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    for block in md_blocks(data) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    hex(&h)
+}
+
+fn sha256(data: &[u8]) -> String {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    for block in md_blocks(data) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for (ki, wi) in K.iter().zip(w.iter()) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*ki)
+                .wrapping_add(*wi);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    hex(&h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(sha1(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(sha1(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(fmt_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(fmt_timestamp(1709251199), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn test_document() {
+        std::env::set_var("SOURCE_DATE_EPOCH", "0");
+        let files = HashMap::from([
+            ("lib.kk".to_string(), "abc".to_string()),
+            ("include/Foo.h".to_string(), String::new()),
+        ]);
+        let bridge = BridgeCrate {
+            name: "my_crate".into(),
+            version: Some("1.0.0".into()),
+        };
+        insta::assert_snapshot!(gen("koka", &bridge, &files));
+    }
+}
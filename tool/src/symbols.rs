@@ -0,0 +1,160 @@
+//! Checks the C symbols of a bridge against the identifiers the backend's toolchain accepts,
+//! before any binding is generated.
+
+use diplomat_core::ast::{self, ModSymbol};
+use diplomat_core::Env;
+
+/// The symbols a backend's bindings can refer to
+pub struct SymbolRules {
+    /// The longest symbol the backend's toolchain handles, if limited
+    pub max_length: Option<usize>,
+    /// Whether a character may appear in symbols
+    pub allows: fn(char) -> bool,
+    /// What `allows` accepts, for error messages
+    pub charset: &'static str,
+}
+
+/// The identifiers of C and the languages binding to its symbols.
+pub const C_SYMBOLS: SymbolRules = SymbolRules {
+    max_length: None,
+    allows: |c| c.is_ascii_alphanumeric() || c == '_',
+    charset: "ASCII letters, digits and underscores",
+};
+
+/// Checks every symbol exported by the bridge: method and destructor symbols, including the
+/// functions generated for async methods.
+///
+/// Symbols have to fit both the backend's limit and the one configured with
+/// `#[diplomat::abi_rename(max_length = N)]`, which shortens them automatically.
+pub fn check(env: &Env, backend: &str, rules: &SymbolRules) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut check = |symbol: &str, owner: &str, configured: Option<usize>| {
+        if let Some(c) = symbol
+            .chars()
+            .find(|c| !(rules.allows)(*c))
+            .or_else(|| symbol.chars().next().filter(char::is_ascii_digit))
+        {
+            errors.push(format!(
+                "Symbol `{symbol}` of {owner} contains `{c}`, the {backend} backend only supports {} in symbols not starting with a digit",
+                rules.charset
+            ));
+        }
+        if let Some(max_length) = rules.max_length.filter(|max| symbol.len() > *max) {
+            errors.push(format!(
+                "Symbol `{symbol}` of {owner} is {} bytes long, more than the {max_length} the {backend} backend supports; \
+                 use #[diplomat::abi_rename(max_length = {max_length})] to shorten it",
+                symbol.len()
+            ));
+        } else if let Some(max_length) = configured.filter(|max| symbol.len() > *max) {
+            errors.push(format!(
+                "Symbol `{symbol}` of {owner} is {} bytes long, more than the configured {max_length}",
+                symbol.len()
+            ));
+        }
+    };
+
+    for (_, _, item) in env.iter_items() {
+        let ModSymbol::CustomType(ty) = item else {
+            continue;
+        };
+        let configured = ty.attrs().abi_rename.max_length();
+        check(
+            &ty.dtor_name(),
+            &format!("the destructor of `{}`", ty.name()),
+            configured,
+        );
+        for method in ty.methods() {
+            let owner = format!("method `{}::{}`", ty.name(), method.name);
+            let configured = method.attrs.abi_rename.max_length();
+            check(method.full_path_name.as_str(), &owner, configured);
+            if method.is_async {
+                for suffix in ast::attrs::ASYNC_SYMBOL_SUFFIXES {
+                    check(
+                        &format!("{}{suffix}", method.full_path_name),
+                        &owner,
+                        configured,
+                    );
+                }
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, SymbolRules, C_SYMBOLS};
+    use diplomat_core::ast;
+    use quote::quote;
+
+    fn env(tokens: proc_macro2::TokenStream) -> diplomat_core::Env {
+        let file = syn::parse2::<syn::File>(tokens).unwrap();
+        ast::File::from(&file).all_types()
+    }
+
+    #[test]
+    fn test_symbol_limits() {
+        let env = env(quote! {
+            #[diplomat::bridge]
+            #[diplomat::abi_rename(max_length = 28)]
+            mod ffi {
+                #[diplomat::opaque]
+                struct SomewhatLongerTypeName(u8);
+
+                impl SomewhatLongerTypeName {
+                    pub fn short(&self) {}
+                    pub fn a_method_with_a_long_name(&self) {}
+                    pub async fn fetch_everything_there_is(&self) {}
+                }
+            }
+        });
+        let rules = SymbolRules {
+            max_length: Some(28),
+            ..C_SYMBOLS
+        };
+        assert_eq!(check(&env, "c2", &rules), Vec::<String>::new());
+
+        let ty = env
+            .iter_items()
+            .find_map(|(_, _, item)| match item {
+                ast::ModSymbol::CustomType(ty) => Some(ty),
+                _ => None,
+            })
+            .unwrap();
+        let symbols = ty
+            .methods()
+            .iter()
+            .map(|m| m.full_path_name.to_string())
+            .collect::<Vec<_>>();
+        // Short enough symbols are kept, the others end with a hash of the full symbol
+        assert_eq!(symbols[0], "SomewhatLongerTypeName_short");
+        assert_eq!(symbols[1].len(), 28);
+        assert!(symbols[1].starts_with("SomewhatLongerTypeN_"));
+        // Leaving room for the suffixes of the functions generated for async methods
+        assert_eq!(symbols[2].len(), 28 - "_destroy_future".len());
+        assert_eq!(ty.dtor_name().len(), 28);
+
+        let strict = SymbolRules {
+            max_length: Some(16),
+            ..C_SYMBOLS
+        };
+        let errors = check(&env, "c2", &strict);
+        assert_eq!(errors.len(), 5, "{errors:#?}");
+        assert!(errors[0].contains("more than the 16 the c2 backend supports"));
+    }
+
+    #[test]
+    fn test_symbol_charset() {
+        let env = env(quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                #[diplomat::abi_rename = "lib.{0}"]
+                struct Thing(u8);
+            }
+        });
+        let errors = check(&env, "c2", &C_SYMBOLS);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("`lib.Thing_destroy` of the destructor of `Thing` contains `.`"));
+    }
+}
@@ -0,0 +1,283 @@
+//! The `--trace` log of how the HIR lowered the signature of each method: how its receiver,
+//! parameters and result are represented, and which borrows the result keeps alive.
+
+use diplomat_core::hir::borrowing_param::{LifetimeEdgeKind, ParamBorrowInfo};
+use diplomat_core::hir::{
+    self, Lifetime, LifetimeEnv, MaybeStatic, Method, OpaqueOwner, OutType, ReturnType, SelfType,
+    StringEncoding, StructPathLike, SuccessType, TyPosition, Type, TypeContext,
+};
+use std::fmt::Write;
+
+/// Describes the lowering of every method that isn't disabled, one block per method
+pub fn trace_lowering(tcx: &TypeContext) -> String {
+    let mut out = String::new();
+    for (_, ty) in tcx.all_types() {
+        if ty.attrs().disable {
+            continue;
+        }
+        for method in ty.methods().iter().filter(|m| !m.attrs.disable) {
+            writeln!(out, "{}::{}", ty.name(), method.name).unwrap();
+            trace_method(tcx, method, &mut out);
+        }
+    }
+    out
+}
+
+pub(crate) fn trace_method(tcx: &TypeContext, method: &Method, out: &mut String) {
+    let env = &method.lifetime_env;
+    let mut visitor = method.borrowing_param_visitor(tcx);
+
+    if let Some(ref param_self) = method.param_self {
+        let (description, borrow) = match param_self.ty {
+            SelfType::BoxedOpaque(ref op) => (
+                format!("opaque `{}` taken by value, consumed", op.resolve(tcx).name),
+                None,
+            ),
+            ref st => {
+                let ty: OutType = st.clone().into();
+                (
+                    describe_type(tcx, env, &ty),
+                    Some(visitor.visit_param(&ty, "self")),
+                )
+            }
+        };
+        writeln!(out, "  self: {description}{}", describe_borrow(borrow)).unwrap();
+    }
+
+    for param in &method.params {
+        let borrow = visitor.visit_param(&param.ty, param.name.as_str());
+        writeln!(
+            out,
+            "  `{}`: {}{}",
+            param.name,
+            describe_type(tcx, env, &param.ty),
+            describe_borrow(Some(borrow))
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        out,
+        "  returns: {}",
+        describe_return(tcx, env, &method.output)
+    )
+    .unwrap();
+
+    for (lt, info) in visitor.borrow_map() {
+        let edges = info
+            .incoming_edges
+            .iter()
+            .map(|edge| match edge.kind {
+                LifetimeEdgeKind::OpaqueParam => format!("`{}` (opaque)", edge.param_name),
+                LifetimeEdgeKind::SliceParam => format!("`{}` (slice)", edge.param_name),
+                LifetimeEdgeKind::StructLifetime(struct_env, struct_lt) => format!(
+                    "the '{} fields of `{}`",
+                    struct_env.fmt_lifetime(struct_lt),
+                    edge.param_name
+                ),
+                _ => format!("`{}`", edge.param_name),
+            })
+            .collect::<Vec<_>>();
+        let edges = if edges.is_empty() {
+            "nothing to keep alive".into()
+        } else {
+            edges.join(", ")
+        };
+        write!(
+            out,
+            "  edge: '{} of the result keeps {edges}",
+            env.fmt_lifetime(lt)
+        )
+        .unwrap();
+        let longer = info
+            .all_longer_lifetimes
+            .iter()
+            .filter(|longer| **longer != lt)
+            .collect::<Vec<_>>();
+        if !longer.is_empty() {
+            let longer = longer
+                .iter()
+                .map(|lt| format!("'{}", env.fmt_lifetime(*lt)))
+                .collect::<Vec<_>>();
+            write!(out, ", as it may borrow from {}", longer.join(", ")).unwrap();
+        }
+        out.push('\n');
+    }
+}
+
+fn describe_type<P: TyPosition>(tcx: &TypeContext, env: &LifetimeEnv, ty: &Type<P>) -> String {
+    match ty {
+        Type::Primitive(prim) => format!("primitive {}", prim.as_str()),
+        Type::Opaque(op) => {
+            let ownership = if op.owner.is_owned() {
+                String::new()
+            } else {
+                let mutability = match op.owner.mutability() {
+                    Some(hir::Mutability::Mutable) => "mutably ",
+                    _ => "",
+                };
+                match op.owner.lifetime() {
+                    Some(lt) => format!("{mutability}borrowed for {}", fmt_lifetime(env, lt)),
+                    None => format!("{mutability}borrowed"),
+                }
+            };
+            let pointer = if op.is_optional() {
+                "nullable pointer"
+            } else {
+                "non-null pointer"
+            };
+            if op.owner.is_owned() {
+                format!("owned opaque `{}`, as a {pointer}", op.resolve(tcx).name)
+            } else {
+                format!(
+                    "opaque `{}` {ownership}, as a {pointer}",
+                    op.resolve(tcx).name
+                )
+            }
+        }
+        Type::Struct(st) => {
+            let name = tcx.resolve_type(st.id()).name();
+            let lifetimes = st
+                .lifetimes()
+                .lifetimes()
+                .map(|lt| fmt_lifetime(env, lt))
+                .collect::<Vec<_>>();
+            if lifetimes.is_empty() {
+                format!("struct `{name}` by value")
+            } else {
+                format!(
+                    "struct `{name}` by value, with fields borrowed for {}",
+                    lifetimes.join(", ")
+                )
+            }
+        }
+        Type::Enum(e) => format!("enum `{}`, as its discriminant", e.resolve(tcx).name),
+        Type::Slice(hir::Slice::Str(lt, encoding)) => match lt {
+            Some(lt) => format!(
+                "{} string slice borrowed for {}",
+                describe_encoding(*encoding),
+                fmt_lifetime(env, *lt)
+            ),
+            None => format!("owned {} string", describe_encoding(*encoding)),
+        },
+        Type::Slice(hir::Slice::Primitive(borrow, prim)) => match borrow {
+            Some(borrow) => format!(
+                "{}slice of {} borrowed for {}",
+                match borrow.mutability {
+                    hir::Mutability::Mutable => "mutable ",
+                    hir::Mutability::Immutable => "",
+                },
+                prim.as_str(),
+                fmt_lifetime(env, borrow.lifetime)
+            ),
+            None => format!("owned slice of {}", prim.as_str()),
+        },
+        Type::Slice(hir::Slice::Strs(encoding)) => {
+            format!("slice of {} string slices", describe_encoding(*encoding))
+        }
+        Type::Callback(_) => "callback, called through a trampoline".into(),
+        Type::DiplomatOption(value) => format!(
+            "optional {}, with a flag telling whether it is set",
+            describe_type(tcx, env, value)
+        ),
+        Type::DynTrait(id) => format!(
+            "trait object `{}`, as a vtable of foreign functions",
+            tcx.resolve_trait(*id).name
+        ),
+        _ => "unknown type".into(),
+    }
+}
+
+fn describe_borrow(borrow: Option<ParamBorrowInfo>) -> &'static str {
+    match borrow {
+        Some(ParamBorrowInfo::TemporarySlice) => ", copied for the call only",
+        Some(ParamBorrowInfo::BorrowedSlice) => ", kept alive by the result",
+        Some(ParamBorrowInfo::Struct(_)) => ", its borrowed fields kept alive by the result",
+        Some(ParamBorrowInfo::BorrowedOpaque) => ", kept alive by the result",
+        _ => "",
+    }
+}
+
+fn describe_return(tcx: &TypeContext, env: &LifetimeEnv, output: &ReturnType) -> String {
+    let success = |success: &SuccessType| match success {
+        SuccessType::Unit => "nothing".into(),
+        SuccessType::Writeable => "a string, written into a `DiplomatWrite`".into(),
+        SuccessType::OutType(ty) => describe_type(tcx, env, ty),
+        _ => "unknown type".into(),
+    };
+    match output {
+        ReturnType::Infallible(ok) => success(ok),
+        ReturnType::Fallible(ok, err) => format!(
+            "result, as a tagged union of {} and error {}",
+            success(ok),
+            err.as_ref()
+                .map_or_else(|| "nothing".into(), |err| describe_type(tcx, env, err))
+        ),
+        ReturnType::Nullable(ok) => {
+            format!("option, as a tagged union of {} and nothing", success(ok))
+        }
+    }
+}
+
+fn describe_encoding(encoding: StringEncoding) -> &'static str {
+    match encoding {
+        StringEncoding::UnvalidatedUtf8 => "UTF-8 (unvalidated)",
+        StringEncoding::UnvalidatedUtf16 => "UTF-16 (unvalidated)",
+        StringEncoding::Utf8 => "UTF-8",
+        _ => "unknown encoding",
+    }
+}
+
+fn fmt_lifetime(env: &LifetimeEnv, lt: MaybeStatic<Lifetime>) -> String {
+    match lt {
+        MaybeStatic::Static => "'static".into(),
+        MaybeStatic::NonStatic(lt) => format!("'{}", env.fmt_lifetime(lt)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use diplomat_core::{ast, hir};
+    use quote::quote;
+
+    #[test]
+    fn test_trace_lowering() {
+        let tk_stream = quote! {
+            #[diplomat::bridge]
+            mod ffi {
+                #[diplomat::opaque]
+                struct Locale(String);
+
+                #[diplomat::opaque]
+                struct Segmenter;
+
+                #[diplomat::opaque]
+                struct Segments<'a>(&'a str);
+
+                impl Locale {
+                    pub fn new(name: &DiplomatStr) -> Option<Box<Locale>> {
+                        unimplemented!()
+                    }
+                }
+
+                impl Segmenter {
+                    pub fn segment<'a>(&self, text: &'a DiplomatStr, locale: &Locale) -> Box<Segments<'a>> {
+                        unimplemented!()
+                    }
+
+                    pub fn lengths(&self, counts: &[u32]) -> Result<u32, ()> {
+                        unimplemented!()
+                    }
+                }
+            }
+        };
+        let item = syn::parse2::<syn::File>(tk_stream).expect("failed to parse item ");
+        let diplomat_file = ast::File::from(&item);
+        let env = diplomat_file.all_types();
+        let attr_validator = hir::BasicAttributeValidator::new("trace_test");
+        let tcx = hir::TypeContext::from_ast(&env, attr_validator)
+            .unwrap_or_else(|_| panic!("Failed to create context"));
+
+        insta::assert_snapshot!(super::trace_lowering(&tcx));
+    }
+}
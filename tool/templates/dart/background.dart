@@ -0,0 +1,42 @@
+/// The isolates running the methods marked `background`, spawned as calls come in.
+///
+/// Workers call top-level functions taking opaque handles as addresses, so no Dart object
+/// wrapping a handle, nor its finalizer, is ever copied to them.
+final class _BackgroundPool {
+  static const _size = 4;
+  static final _workers = <core.Future<isolate.SendPort>>[];
+  static var _next = 0;
+
+  static core.Future<core.Object?> run(core.Function function, core.List<core.Object?> args) async {
+    if (_workers.length < _size) {
+      _workers.add(_spawn());
+    }
+    final worker = await _workers[_next++ % _workers.length];
+    final reply = isolate.ReceivePort();
+    worker.send((function, args, reply.sendPort));
+    final (result, error) = await reply.first as (core.Object?, isolate.RemoteError?);
+    if (error != null) {
+      throw error;
+    }
+    return result;
+  }
+
+  static core.Future<isolate.SendPort> _spawn() async {
+    final ready = isolate.ReceivePort();
+    await isolate.Isolate.spawn(_work, ready.sendPort);
+    return await ready.first as isolate.SendPort;
+  }
+
+  static void _work(isolate.SendPort ready) {
+    final requests = isolate.ReceivePort();
+    ready.send(requests.sendPort);
+    requests.listen((request) {
+      final (function, args, reply) = request as (core.Function, core.List<core.Object?>, isolate.SendPort);
+      try {
+        reply.send((core.Function.apply(function, args), null));
+      } catch (e, s) {
+        reply.send((null, isolate.RemoteError(e.toString(), s.toString())));
+      }
+    });
+  }
+}
@@ -0,0 +1,23 @@
+// Copies binary data into the buffers of Koka `bytes`.
+#include "diplomat_runtime.h"
+
+// A new buffer holding the `len` bytes at `data`, freed along with the Koka value boxing it.
+// It is never empty, so that Rust reads the empty slice from a valid pointer.
+static kk_box_t diplomat_bytes_of(const void* data, size_t len, kk_context_t* _ctx) {
+  void* buf = kk_malloc(len > 0 ? (kk_ssize_t)len : 1, _ctx);
+  if (len > 0) {
+    memcpy(buf, data, len);
+  }
+  return kk_cptr_raw_box(&kk_free_fun, buf, _ctx);
+}
+
+// A new buffer holding the elements of `v`, a `vector<int8>`.
+static kk_box_t diplomat_bytes_of_vector(kk_vector_t v, kk_context_t* _ctx) {
+  kk_ssize_t len;
+  kk_box_t* items = kk_vector_buf_borrow(v, &len, _ctx);
+  uint8_t* buf = (uint8_t*)kk_malloc(len > 0 ? len : 1, _ctx);
+  for (kk_ssize_t i = 0; i < len; i++) {
+    buf[i] = (uint8_t)kk_int8_unbox(items[i], KK_BORROWED, _ctx);
+  }
+  return kk_cptr_raw_box(&kk_free_fun, buf, _ctx);
+}
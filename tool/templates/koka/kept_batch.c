@@ -0,0 +1,8 @@
+// Ends the batches of `kept-batch` once Perceus drops the last reference to them.
+#include "diplomat_runtime.h"
+
+static void diplomat_kept_batch_finalize(void* p, kk_block_t* block, kk_context_t* _ctx) {
+  kk_unused(block);
+  kk_unused(_ctx);
+  diplomat_batch_end((DiplomatBatch*)p);
+}
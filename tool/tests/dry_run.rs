@@ -0,0 +1,52 @@
+//! Generates bindings with `--dry-run`, checking that nothing is written.
+
+use std::path::Path;
+
+const BRIDGE: &str = r#"
+#[diplomat::bridge]
+mod ffi {
+    #[diplomat::opaque]
+    struct Counter(u32);
+
+    impl Counter {
+        pub fn new() -> Box<Counter> {
+            Box::new(Counter(0))
+        }
+    }
+}
+"#;
+
+fn gen(dir: &Path, dry_run: bool) {
+    diplomat_tool::gen(
+        &dir.join("lib.rs"),
+        "koka",
+        &dir.join("out"),
+        diplomat_tool::GenOptions {
+            silent: true,
+            dry_run,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn writes_nothing() {
+    let dir = std::env::temp_dir().join(format!("diplomat-dry-run-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    std::fs::write(dir.join("lib.rs"), BRIDGE).unwrap();
+
+    gen(&dir, true);
+    assert_eq!(std::fs::read_dir(dir.join("out")).unwrap().count(), 0);
+
+    gen(&dir, false);
+    let lib = dir.join("out").join("lib.kk");
+    let text = std::fs::read_to_string(&lib).unwrap();
+    std::fs::write(&lib, "").unwrap();
+    gen(&dir, true);
+    assert_eq!(std::fs::read_to_string(&lib).unwrap(), "");
+    assert!(!text.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
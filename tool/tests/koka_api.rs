@@ -0,0 +1,37 @@
+//! Generates Koka bindings from code, as a build script would, with a config built in place
+//! rather than read from a file.
+
+use diplomat_tool::koka::{run, ErrorStyle, KokaAttributeValidator, KokaConfig};
+
+#[test]
+fn generates_in_memory() {
+    let parsed: syn::File = syn::parse_quote! {
+        #[diplomat::bridge]
+        mod ffi {
+            #[diplomat::opaque]
+            struct Counter(u32);
+
+            impl Counter {
+                pub fn new() -> Result<Box<Counter>, ()> {
+                    Ok(Box::new(Counter(0)))
+                }
+            }
+        }
+    };
+    let env = diplomat_core::ast::File::from(&parsed).all_types();
+    let tcx = diplomat_core::hir::TypeContext::from_ast(&env, KokaAttributeValidator::new())
+        .unwrap_or_else(|_| panic!("Failed to create context"));
+
+    let config = KokaConfig {
+        error_style: ErrorStyle::Either,
+        package: Some("counter".into()),
+        ..Default::default()
+    };
+    let docs_url_generator = Default::default();
+    let (mut files, errors) = run(&tcx, config, &docs_url_generator, Default::default());
+    assert!(errors.is_empty());
+
+    let files = files.take_files();
+    assert!(files["counter.files"].contains("lib.kk"));
+    assert!(files["lib.kk"].contains("pub fun counter/new_() : io-noexn result<Counter, ()>"));
+}
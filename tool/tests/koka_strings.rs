@@ -0,0 +1,202 @@
+//! Round-trips random strings through the UTF-16 transcoding the Koka bindings compile into
+//! C, checking it against Rust's own conversions: valid strings come back unchanged, byte
+//! order marks included, and lone surrogates become U+FFFD.
+//!
+//! The C file is compiled with the C compiler in `CC` (`cc` by default) against the handful
+//! of kklib functions it uses, and the test is skipped without one.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Stands in for the parts of kklib `diplomat_utf16.c` uses, and reads the cases from stdin:
+/// `d` followed by UTF-16 code units to decode, `e` followed by UTF-8 bytes to encode, all in
+/// hex. Prints the result of every case on a line, in hex as well.
+const DRIVER: &str = r#"
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "diplomat_runtime.h"
+
+typedef ptrdiff_t kk_ssize_t;
+typedef struct kk_context_s { int unused; } kk_context_t;
+typedef struct kk_string_s { const uint8_t* buf; kk_ssize_t len; } kk_string_t;
+
+static const uint8_t* kk_string_buf_borrow(kk_string_t s, kk_ssize_t* len, kk_context_t* ctx) {
+  (void)ctx;
+  *len = s.len;
+  return s.buf;
+}
+
+static void* kk_malloc(kk_ssize_t size, kk_context_t* ctx) {
+  (void)ctx;
+  return malloc((size_t)size);
+}
+
+static void kk_free(void* p, kk_context_t* ctx) {
+  (void)ctx;
+  free(p);
+}
+
+static kk_string_t kk_string_alloc_from_utf8n(kk_ssize_t len, const char* bytes, kk_context_t* ctx) {
+  uint8_t* buf = (uint8_t*)kk_malloc(len, ctx);
+  memcpy(buf, bytes, (size_t)len);
+  kk_string_t s = { buf, len };
+  return s;
+}
+
+void diplomat_free(void* ptr, size_t size, size_t align) {
+  (void)size;
+  (void)align;
+  free(ptr);
+}
+
+#include "diplomat_utf16.c"
+
+int main(void) {
+  kk_context_t ctx;
+  char kind;
+  size_t n;
+  while (scanf(" %c %zu", &kind, &n) == 2) {
+    if (kind == 'd') {
+      char16_t* units = (char16_t*)malloc((n + 1) * sizeof(char16_t));
+      for (size_t i = 0; i < n; i++) {
+        unsigned int unit;
+        if (scanf("%x", &unit) != 1) return 1;
+        units[i] = (char16_t)unit;
+      }
+      DiplomatU16StringView view = { units, n };
+      // Hands `units` to `diplomat_free` like a string returned from Rust
+      kk_string_t s = diplomat_utf16_decode_owned(view, &ctx);
+      for (kk_ssize_t i = 0; i < s.len; i++) printf("%x ", s.buf[i]);
+      kk_free((void*)s.buf, &ctx);
+    } else {
+      uint8_t* bytes = (uint8_t*)malloc(n + 1);
+      for (size_t i = 0; i < n; i++) {
+        unsigned int byte;
+        if (scanf("%x", &byte) != 1) return 1;
+        bytes[i] = (uint8_t)byte;
+      }
+      kk_string_t s = { bytes, (kk_ssize_t)n };
+      size_t len = diplomat_utf16_length(s, &ctx);
+      char16_t* units = diplomat_utf16_encode(s, malloc(len * sizeof(char16_t) + 1), &ctx);
+      for (size_t i = 0; i < len; i++) printf("%x ", units[i]);
+      free(units);
+      free(bytes);
+    }
+    printf("\n");
+  }
+  return 0;
+}
+"#;
+
+/// xorshift64*, so that failures reproduce
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self, bound: u32) -> u32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        ((self.0.wrapping_mul(0x2545F4914F6CDD1D) >> 32) as u32) % bound
+    }
+
+    /// A scalar value of each UTF-8 length, or a byte order mark
+    fn scalar(&mut self) -> char {
+        let c = match self.next(5) {
+            0 => self.next(0x80),
+            1 => 0x80 + self.next(0x800 - 0x80),
+            2 => 0xE000 + self.next(0x10000 - 0xE000),
+            3 => 0x10000 + self.next(0x110000 - 0x10000),
+            _ => 0xFEFF,
+        };
+        char::from_u32(c).unwrap()
+    }
+}
+
+/// Compiles the driver, or returns `None` without a C compiler
+fn compile_driver(dir: &Path) -> Option<PathBuf> {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    std::fs::write(dir.join("driver.c"), DRIVER).unwrap();
+    std::fs::copy(
+        manifest.join("templates/koka/utf16.c"),
+        dir.join("diplomat_utf16.c"),
+    )
+    .unwrap();
+    std::fs::copy(
+        manifest.join("src/c/runtime.h"),
+        dir.join("diplomat_runtime.h"),
+    )
+    .unwrap();
+    let driver = dir.join("driver");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = Command::new(cc)
+        .current_dir(dir)
+        .args(["-std=c11", "-Wall", "-Werror", "driver.c", "-o"])
+        .arg(&driver)
+        .status()
+        .ok()?;
+    assert!(status.success(), "the transcoding C file does not compile");
+    Some(driver)
+}
+
+fn hex(values: impl IntoIterator<Item = impl Into<u32>>) -> String {
+    values.into_iter().fold(String::new(), |mut out, v| {
+        write!(out, "{:x} ", v.into()).unwrap();
+        out
+    })
+}
+
+#[test]
+fn koka_utf16_round_trip() {
+    let dir = std::env::temp_dir().join(format!("koka-strings-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let Some(driver) = compile_driver(&dir) else {
+        eprintln!("Skipping: no C compiler found");
+        return;
+    };
+
+    let mut random = Random(0x9E3779B97F4A7C15);
+    let mut input = String::new();
+    let mut expected = Vec::new();
+    for _ in 0..2000 {
+        let mut valid = String::new();
+        let mut units = Vec::new();
+        for _ in 0..random.next(16) {
+            let c = random.scalar();
+            valid.push(c);
+            if random.next(4) == 0 {
+                // A lone surrogate, unless the next code unit happens to complete it
+                units.push(0xD800 + random.next(0x800) as u16);
+            } else {
+                units.extend(c.encode_utf16(&mut [0; 2]).iter());
+            }
+        }
+
+        writeln!(input, "d {} {}", units.len(), hex(units.iter().copied())).unwrap();
+        expected.push(hex(String::from_utf16_lossy(&units).bytes()));
+        writeln!(input, "e {} {}", valid.len(), hex(valid.bytes())).unwrap();
+        expected.push(hex(valid.encode_utf16()));
+    }
+    // Koka strings are valid UTF-8, so only decoding sees lone surrogates
+    for units in [&[0xD83D][..], &[0xDE00, 0xD83D], &[0xFEFF, 0x78]] {
+        writeln!(input, "d {} {}", units.len(), hex(units.iter().copied())).unwrap();
+        expected.push(hex(String::from_utf16_lossy(units).bytes()));
+    }
+
+    let input_path = dir.join("input");
+    std::fs::write(&input_path, input).unwrap();
+    let output = Command::new(driver)
+        .stdin(std::fs::File::open(&input_path).unwrap())
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), expected.len());
+    for (i, (line, expected)) in lines.iter().zip(&expected).enumerate() {
+        assert_eq!(line, expected, "case {i}");
+    }
+}
@@ -0,0 +1,70 @@
+//! Generates several targets in one invocation, checking that each gets the files generating
+//! it alone would.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const BRIDGE: &str = r#"
+#[diplomat::bridge]
+mod ffi {
+    #[diplomat::opaque]
+    struct Counter(u32);
+
+    impl Counter {
+        pub fn new() -> Box<Counter> {
+            Box::new(Counter(0))
+        }
+
+        pub fn count(&self) -> u32 {
+            self.0
+        }
+    }
+}
+"#;
+
+fn gen(dir: &Path, targets: &str, out_folder: &Path) {
+    diplomat_tool::gen(
+        &dir.join("lib.rs"),
+        targets,
+        out_folder,
+        diplomat_tool::GenOptions {
+            silent: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+}
+
+fn read_dir(dir: &Path) -> BTreeMap<PathBuf, String> {
+    let mut files = BTreeMap::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(read_dir(&path));
+        } else {
+            let text = std::fs::read_to_string(&path).unwrap();
+            files.insert(path.strip_prefix(dir).unwrap().to_owned(), text);
+        }
+    }
+    files
+}
+
+#[test]
+fn generates_each_target_into_its_folder() {
+    let dir =
+        std::env::temp_dir().join(format!("diplomat-multiple-targets-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("all")).unwrap();
+    std::fs::write(dir.join("lib.rs"), BRIDGE).unwrap();
+
+    gen(&dir, "koka,c2", &dir.join("all"));
+    for target in ["koka", "c2"] {
+        std::fs::create_dir_all(dir.join(target)).unwrap();
+        gen(&dir, target, &dir.join(target));
+        let alone = read_dir(&dir.join(target));
+        assert!(!alone.is_empty());
+        assert_eq!(read_dir(&dir.join("all").join(target)), alone);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}